@@ -48,3 +48,45 @@ impl PartialOrd for Outcome {
         self.ordering().partial_cmp(&other.ordering())
     }
 }
+
+/// The lifecycle state of an OID4VP session, as reported to a frontend polling for it while the
+/// request is displayed (e.g. as a QR code).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SessionState {
+    /// The request was created, and is waiting for the wallet to retrieve it by reference.
+    Created,
+    /// The wallet has retrieved the request, or the request was passed by value.
+    RequestRetrieved,
+    /// The wallet has submitted a response, which is being verified.
+    ResponseReceived,
+    /// The response was verified.
+    Verified { info: Json },
+    /// The response did not pass verification, or could not be processed.
+    Failed { reason: String },
+    /// The session expired before a response was verified.
+    Expired,
+}
+
+impl SessionState {
+    /// Whether the session has reached a final state, and polling can stop.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            Self::Verified { .. } | Self::Failed { .. } | Self::Expired
+        )
+    }
+}
+
+impl From<Status> for SessionState {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::SentRequestByReference => Self::Created,
+            Status::SentRequest => Self::RequestRetrieved,
+            Status::ReceivedResponse => Self::ResponseReceived,
+            Status::Complete(Outcome::Success { info }) => Self::Verified { info },
+            Status::Complete(Outcome::Failure { reason }) => Self::Failed { reason },
+            Status::Complete(Outcome::Error { cause }) => Self::Failed { reason: cause },
+        }
+    }
+}
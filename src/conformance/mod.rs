@@ -0,0 +1,165 @@
+//! Self-checks of a configured [Verifier](crate::verifier::Verifier) or
+//! [Wallet](crate::wallet::Wallet) against the expectations of the specification, in the spirit of
+//! the OpenID Foundation conformance suite: parameter handling, error codes, format and
+//! `client_id_scheme` negotiation and nonce binding.
+//!
+//! The checks run in process, against the instances as they are configured, and produce a
+//! [ConformanceReport] instead of failing on the first problem, e.g. to run in CI or at startup:
+//!
+//! ```ignore
+//! let report = conformance::check_verifier(&verifier).await;
+//! if !report.passed() {
+//!     for check in report.failures() {
+//!         eprintln!("{}: {:?}", check.id, check.status);
+//!     }
+//! }
+//! ```
+//!
+//! Checking a verifier creates sessions and submits responses to them; checking a wallet only
+//! validates requests. Neither should be run against production stores.
+
+use std::fmt;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    object::UntypedObject,
+    util::{self, Base64Policy},
+};
+
+mod verifier;
+#[cfg(feature = "wallet")]
+mod wallet;
+
+pub use verifier::check_verifier;
+#[cfg(feature = "wallet")]
+pub use wallet::check_wallet;
+
+/// The results of a battery of checks.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConformanceReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl ConformanceReport {
+    /// Whether no check failed. Skipped checks do not fail the report.
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// The checks that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &CheckResult> {
+        self.checks
+            .iter()
+            .filter(|check| matches!(check.status, CheckStatus::Failed(_)))
+    }
+
+    /// The result of a check, by its id.
+    pub fn get(&self, id: &str) -> Option<&CheckResult> {
+        self.checks.iter().find(|check| check.id == id)
+    }
+
+    fn push(&mut self, id: &'static str, description: &'static str, status: CheckStatus) {
+        self.checks.push(CheckResult {
+            id: id.to_owned(),
+            description: description.to_owned(),
+            status,
+        })
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            match &check.status {
+                CheckStatus::Passed => writeln!(f, "PASS {}", check.id)?,
+                CheckStatus::Failed(reason) => writeln!(f, "FAIL {}: {reason}", check.id)?,
+                CheckStatus::Skipped(reason) => writeln!(f, "SKIP {}: {reason}", check.id)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The result of a single check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckResult {
+    /// A stable identifier of the check, e.g. `verifier.nonce-binding`.
+    pub id: String,
+    /// What the check expects.
+    pub description: String,
+    pub status: CheckStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "reason", rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    /// The check failed, and why.
+    Failed(String),
+    /// The check does not apply to the configuration, and why.
+    Skipped(String),
+}
+
+impl CheckStatus {
+    /// The status of a check that returns why it does not apply, if it does not.
+    fn from_result(result: Result<Option<String>>) -> Self {
+        match result {
+            Ok(None) => Self::Passed,
+            Ok(Some(reason)) => Self::Skipped(reason),
+            Err(e) => Self::Failed(format!("{e:#}")),
+        }
+    }
+}
+
+/// Decode the payload of a JWT, without verifying it.
+fn jwt_payload(jwt: &str) -> Result<UntypedObject> {
+    util::jwt_payload(jwt, Base64Policy::Strict).context("the request object is not a compact JWS")
+}
+
+#[cfg(test)]
+mod test {
+    use base64::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn report_status() {
+        let mut report = ConformanceReport::default();
+        report.push("a", "passes", CheckStatus::from_result(Ok(None)));
+        report.push(
+            "b",
+            "is skipped",
+            CheckStatus::from_result(Ok(Some("not applicable".into()))),
+        );
+        assert!(report.passed());
+
+        report.push(
+            "c",
+            "fails",
+            CheckStatus::from_result(Err(anyhow::anyhow!("no"))),
+        );
+        assert!(!report.passed());
+        assert_eq!(
+            report.failures().map(|c| c.id.as_str()).collect::<Vec<_>>(),
+            ["c"]
+        );
+        assert_eq!(
+            report.get("c").unwrap().status,
+            CheckStatus::Failed("no".into())
+        );
+        assert_eq!(
+            report.to_string(),
+            "PASS a\nSKIP b: not applicable\nFAIL c: no\n"
+        );
+    }
+
+    #[test]
+    fn decode_jwt_payload() {
+        let payload = BASE64_URL_SAFE_NO_PAD.encode(r#"{"nonce":"n"}"#);
+        let object = jwt_payload(&format!("e30.{payload}.")).unwrap();
+        assert_eq!(object.0["nonce"], "n");
+        assert!(jwt_payload("e30.e30").is_err());
+    }
+}
@@ -0,0 +1,332 @@
+use anyhow::{bail, ensure, Context, Result};
+use base64::prelude::*;
+use serde_json::json;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    core::{
+        authorization_request::{
+            parameters::{
+                PresentationDefinition, PresentationDefinitionUri, ResponseMode, Scope, State,
+            },
+            AuthorizationRequest, AuthorizationRequestObject, RequestIndirection,
+        },
+        credential_format::ClaimFormatDesignation,
+        dcql_query::{DcqlQuery, DcqlVpToken},
+        metadata::WalletMetadata,
+        object::{TypedParameter, UntypedObject},
+        presentation_submission::PresentationSubmission,
+        response::{
+            AuthorizationResponse, DcqlAuthorizationResponse, UnencodedAuthorizationResponse,
+        },
+    },
+    verifier::{
+        session::{DuplicateResponse, Outcome, Status},
+        Verifier,
+    },
+};
+
+use super::{jwt_payload, CheckStatus, ConformanceReport};
+
+/// The minimum length of a nonce, so that a base64url nonce carries at least 128 bits of entropy.
+const MIN_NONCE_LENGTH: usize = 22;
+
+/// A request made by a verifier, as a wallet receives it.
+pub(super) struct ObservedRequest {
+    /// The `client_id` of the request URL.
+    pub(super) client_id: String,
+    /// The Request Object, unless the request is unsigned.
+    #[cfg_attr(not(feature = "wallet"), allow(dead_code))]
+    pub(super) jwt: Option<String>,
+    pub(super) parameters: UntypedObject,
+}
+
+impl ObservedRequest {
+    /// Parse the request URL of a session, retrieving the Request Object from the verifier if it
+    /// is passed by reference.
+    pub(super) async fn from_url(verifier: &Verifier, url: &Url) -> Result<Self> {
+        let request = AuthorizationRequest::from_query_params(url.query().unwrap_or_default())?;
+        let jwt = match request.request_indirection {
+            RequestIndirection::Unsigned(parameters) => {
+                return Ok(Self {
+                    client_id: request.client_id,
+                    jwt: None,
+                    parameters,
+                })
+            }
+            RequestIndirection::ByValue(jwt) => jwt,
+            RequestIndirection::ByReference(request_uri) => {
+                let token = request_uri
+                    .path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .context("the request_uri has no token")?;
+                verifier
+                    .retrieve_authorization_request(token)
+                    .await
+                    .context("the request_uri could not be retrieved")?
+            }
+        };
+        Ok(Self {
+            client_id: request.client_id,
+            parameters: jwt_payload(&jwt)?,
+            jwt: Some(jwt),
+        })
+    }
+
+    pub(super) fn request_object(&self) -> Result<AuthorizationRequestObject> {
+        self.parameters
+            .clone()
+            .try_into()
+            .context("the request is missing required parameters")
+    }
+}
+
+/// Run the verifier checks against `verifier`, which must be configured to begin sessions with
+/// [Verifier::begin_session], and to store them.
+///
+/// | Check | Expects |
+/// |---|---|
+/// | `verifier.request-parameters` | requests carry the required parameters, the `client_id` of the URL matches the Request Object, and exactly one of `presentation_definition`, `presentation_definition_uri`, `dcql_query` or `scope` |
+/// | `verifier.nonce-entropy` | nonces of at least 22 characters |
+/// | `verifier.nonce-unique` | a new nonce for each session |
+/// | `verifier.client-id-scheme-negotiation` | no request is built for a wallet that does not support the `client_id_scheme` of the verifier |
+/// | `verifier.nonce-binding` | a presentation bound to another nonce is rejected, and fails the session |
+/// | `verifier.duplicate-response` | a second response to a session is rejected |
+pub async fn check_verifier(verifier: &Verifier) -> ConformanceReport {
+    let mut report = ConformanceReport::default();
+
+    let first = begin_session(verifier).await;
+    let second = begin_session(verifier).await;
+
+    report.push(
+        "verifier.request-parameters",
+        "requests carry the required parameters, and what is requested once",
+        CheckStatus::from_result(
+            first
+                .as_ref()
+                .map_err(clone_error)
+                .and_then(|(_, request)| check_request_parameters(request)),
+        ),
+    );
+
+    let first_nonce = first
+        .as_ref()
+        .map_err(clone_error)
+        .and_then(|(_, request)| Ok(request.request_object()?.nonce().clone()));
+    report.push(
+        "verifier.nonce-entropy",
+        "nonces are long enough to be unguessable",
+        CheckStatus::from_result(first_nonce.as_ref().map_err(clone_error).and_then(|nonce| {
+            let length = nonce.len();
+            ensure!(
+                length >= MIN_NONCE_LENGTH,
+                "the nonce has {length} characters, expected at least {MIN_NONCE_LENGTH}"
+            );
+            Ok(None)
+        })),
+    );
+    report.push(
+        "verifier.nonce-unique",
+        "each session has a new nonce",
+        CheckStatus::from_result(match (&first_nonce, &second) {
+            (Ok(first), Ok((_, second))) => second.request_object().and_then(|second| {
+                ensure!(
+                    first.as_str() != second.nonce().as_str(),
+                    "two sessions have the same nonce"
+                );
+                Ok(None)
+            }),
+            (Err(e), _) | (_, Err(e)) => Err(clone_error(e)),
+        }),
+    );
+
+    report.push(
+        "verifier.client-id-scheme-negotiation",
+        "no request is built for a wallet that does not support the client_id_scheme",
+        CheckStatus::from_result(check_client_id_scheme_negotiation(verifier).await),
+    );
+
+    let binding = match &first {
+        Ok((uuid, request)) => check_nonce_binding(verifier, *uuid, request).await,
+        Err(e) => Err(clone_error(e)),
+    };
+    let duplicate = match (&first, &binding) {
+        (Ok((uuid, request)), Ok(None)) => check_duplicate_response(verifier, *uuid, request).await,
+        (_, Ok(Some(reason))) => Ok(Some(reason.clone())),
+        _ => Ok(Some("the nonce binding check did not pass".to_owned())),
+    };
+    report.push(
+        "verifier.nonce-binding",
+        "a presentation bound to another nonce is rejected",
+        CheckStatus::from_result(binding),
+    );
+    report.push(
+        "verifier.duplicate-response",
+        "a second response to a session is rejected",
+        CheckStatus::from_result(duplicate),
+    );
+
+    report
+}
+
+async fn begin_session(verifier: &Verifier) -> Result<(Uuid, ObservedRequest)> {
+    let (url, uuid) = verifier
+        .begin_session()
+        .await
+        .context("the session could not be started")?;
+    Ok((uuid, ObservedRequest::from_url(verifier, &url).await?))
+}
+
+fn check_request_parameters(request: &ObservedRequest) -> Result<Option<String>> {
+    let request_object = request.request_object()?;
+    ensure!(
+        request.client_id == request_object.client_id().0,
+        "the client_id of the request URL ({}) does not match the request ({})",
+        request.client_id,
+        request_object.client_id().0
+    );
+    let queries: Vec<_> = [
+        PresentationDefinition::KEY,
+        PresentationDefinitionUri::KEY,
+        DcqlQuery::KEY,
+        Scope::KEY,
+    ]
+    .into_iter()
+    .filter(|key| request.parameters.0.contains_key(*key))
+    .collect();
+    ensure!(
+        queries.len() == 1,
+        "expected exactly one of presentation_definition, presentation_definition_uri, dcql_query or scope, found {queries:?}"
+    );
+    Ok(None)
+}
+
+async fn check_client_id_scheme_negotiation(verifier: &Verifier) -> Result<Option<String>> {
+    let wallet_metadata: WalletMetadata = serde_json::from_value(json!({
+        "authorization_endpoint": "openid4vp:",
+        "client_id_schemes_supported": ["conformance_unsupported"],
+        "response_types_supported": ["vp_token"],
+        "vp_formats_supported": { "jwt_vp_json": {} }
+    }))?;
+    wallet_metadata.validate()?;
+    let builder = verifier.with_default_query(verifier.build_authorization_request(), None)?;
+    if builder.build(wallet_metadata).await.is_ok() {
+        bail!("a request was built for a wallet that does not support the client_id_scheme")
+    }
+    Ok(None)
+}
+
+async fn check_nonce_binding(
+    verifier: &Verifier,
+    uuid: Uuid,
+    request: &ObservedRequest,
+) -> Result<Option<String>> {
+    let request_object = request.request_object()?;
+    if request_object.response_mode() == &ResponseMode::DirectPostJwt {
+        return Ok(Some(
+            "the nonce of encrypted responses is checked by the validator".to_owned(),
+        ));
+    }
+
+    // A JWT presentation whose nonce can be read without verifying it.
+    let claims = json!({ "nonce": "conformance-wrong-nonce", "vp": {} });
+    let vp = format!(
+        "{}.{}.c2lnbmF0dXJl",
+        BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256"}"#),
+        BASE64_URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    let response = wrong_nonce_response(request, vp)?;
+
+    let result = verifier
+        .verify_response(uuid, response, |_, _| {
+            Box::pin(async {
+                Outcome::Success {
+                    info: json!("the validator was called"),
+                }
+            })
+        })
+        .await;
+    ensure!(
+        result.is_err(),
+        "the response was accepted, with the validator called"
+    );
+    match verifier.poll_status(uuid).await? {
+        Status::Complete(Outcome::Failure { .. }) => Ok(None),
+        status => bail!("expected the session to fail, found {status:?}"),
+    }
+}
+
+/// A response to `request`, presenting `vp`.
+fn wrong_nonce_response(request: &ObservedRequest, vp: String) -> Result<AuthorizationResponse> {
+    let mut parameters = UntypedObject::default();
+    if let Some(state) = request.parameters.get::<State>() {
+        parameters.insert(state?);
+    }
+    if let Some(dcql_query) = request.parameters.get::<DcqlQuery>() {
+        let dcql_query = dcql_query?;
+        let id = dcql_query
+            .credentials()
+            .first()
+            .context("the DCQL query has no credential query")?
+            .id
+            .clone();
+        return Ok(AuthorizationResponse::Dcql(DcqlAuthorizationResponse(
+            parameters,
+            DcqlVpToken([(id, vec![vp.into()])].into()),
+        )));
+    }
+    let (definition_id, descriptor_id) = match request.parameters.get::<PresentationDefinition>() {
+        Some(presentation_definition) => {
+            let presentation_definition = presentation_definition?.into_parsed();
+            let descriptor_id = presentation_definition
+                .input_descriptors()
+                .first()
+                .map(|input_descriptor| input_descriptor.id().to_owned())
+                .unwrap_or_default();
+            (presentation_definition.id().to_owned(), descriptor_id)
+        }
+        None => ("conformance".to_owned(), "conformance".to_owned()),
+    };
+    Ok(AuthorizationResponse::Unencoded(
+        UnencodedAuthorizationResponse(
+            parameters,
+            vp.into(),
+            PresentationSubmission::for_vp_token(
+                definition_id,
+                [(descriptor_id, ClaimFormatDesignation::JwtVpJson)],
+            ),
+        ),
+    ))
+}
+
+async fn check_duplicate_response(
+    verifier: &Verifier,
+    uuid: Uuid,
+    request: &ObservedRequest,
+) -> Result<Option<String>> {
+    let response = wrong_nonce_response(request, "conformance-duplicate".to_owned())?;
+    let Err(e) = verifier
+        .verify_response(uuid, response, |_, _| {
+            Box::pin(async {
+                Outcome::Success {
+                    info: json!("the validator was called"),
+                }
+            })
+        })
+        .await
+    else {
+        bail!("a second response to the session was accepted")
+    };
+    ensure!(
+        e.downcast_ref::<DuplicateResponse>().is_some(),
+        "a second response was rejected, but not as a duplicate: {e:#}"
+    );
+    Ok(None)
+}
+
+/// Errors are not `Clone`, so the error of a shared step is reported by each check depending on
+/// it.
+fn clone_error(e: &anyhow::Error) -> anyhow::Error {
+    anyhow::anyhow!("{e:#}")
+}
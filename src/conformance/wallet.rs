@@ -0,0 +1,204 @@
+use anyhow::{bail, ensure, Context, Result};
+use base64::prelude::*;
+
+use crate::{
+    core::{
+        authorization_request::{
+            parameters::{ClientIdScheme, Nonce, ResponseType},
+            verification::unsigned::UnsignedRequestPolicy,
+            AuthorizationRequest, RequestIndirection,
+        },
+        response::error::AuthorizationErrorCode,
+    },
+    verifier::Verifier,
+    wallet::Wallet,
+};
+
+use super::{verifier::ObservedRequest, CheckStatus, ConformanceReport};
+
+/// Run the wallet checks against `wallet`, with requests made by `verifier`, which must be
+/// configured to make requests that the wallet accepts.
+///
+/// | Check | Expects |
+/// |---|---|
+/// | `wallet.metadata` | metadata supporting the `vp_token` response type, at least one VP format, and well-formed `client_id_schemes_supported` |
+/// | `wallet.accepts-valid-request` | a request of the verifier is accepted |
+/// | `wallet.rejects-tampered-request` | a Request Object modified after it was signed is rejected with `invalid_request_object` |
+/// | `wallet.rejects-unsigned-request` | a Request Object with `alg` `none` is rejected with `invalid_request`, unless unsigned requests are accepted |
+/// | `wallet.rejects-client-id-mismatch` | a request whose URL `client_id` differs from the Request Object is rejected with `invalid_request` |
+pub async fn check_wallet<W: Wallet + Send + Sync>(
+    wallet: &W,
+    verifier: &Verifier,
+) -> ConformanceReport {
+    let mut report = ConformanceReport::default();
+
+    report.push(
+        "wallet.metadata",
+        "the metadata supports vp_token responses and at least one VP format",
+        CheckStatus::from_result(check_metadata(wallet)),
+    );
+
+    report.push(
+        "wallet.accepts-valid-request",
+        "a request of the verifier is accepted",
+        CheckStatus::from_result(check_valid_request(wallet, verifier).await),
+    );
+
+    let signed = signed_request(verifier).await;
+    report.push(
+        "wallet.rejects-tampered-request",
+        "a Request Object modified after it was signed is rejected",
+        CheckStatus::from_result(match &signed {
+            Ok(Some(request)) => check_tampered_request(wallet, request).await,
+            Ok(None) => Ok(Some("the verifier makes unsigned requests".to_owned())),
+            Err(e) => Err(anyhow::anyhow!("{e:#}")),
+        }),
+    );
+    report.push(
+        "wallet.rejects-unsigned-request",
+        "a Request Object that is not signed is rejected",
+        CheckStatus::from_result(match &signed {
+            _ if wallet.unsigned_request_policy() != UnsignedRequestPolicy::Reject => {
+                Ok(Some("the wallet accepts unsigned requests".to_owned()))
+            }
+            Ok(Some(request)) => check_unsigned_request(wallet, request).await,
+            Ok(None) => Ok(Some("the verifier makes unsigned requests".to_owned())),
+            Err(e) => Err(anyhow::anyhow!("{e:#}")),
+        }),
+    );
+    report.push(
+        "wallet.rejects-client-id-mismatch",
+        "a request whose client_id differs from the Request Object is rejected",
+        CheckStatus::from_result(match &signed {
+            Ok(Some(request)) => check_client_id_mismatch(wallet, request).await,
+            Ok(None) => Ok(Some("the verifier makes unsigned requests".to_owned())),
+            Err(e) => Err(anyhow::anyhow!("{e:#}")),
+        }),
+    );
+
+    report
+}
+
+fn check_metadata<W: Wallet>(wallet: &W) -> Result<Option<String>> {
+    let metadata = wallet.metadata();
+    let response_types = metadata
+        .response_types_supported()
+        .context("response_types_supported is missing")?;
+    ensure!(
+        response_types.0.contains(&ResponseType::VpToken),
+        "response_types_supported does not include vp_token"
+    );
+    ensure!(
+        !metadata.vp_formats_supported().0.is_empty(),
+        "vp_formats_supported is empty"
+    );
+    Ok(None)
+}
+
+async fn check_valid_request<W: Wallet + Send + Sync>(
+    wallet: &W,
+    verifier: &Verifier,
+) -> Result<Option<String>> {
+    let (url, _) = verifier
+        .begin_session()
+        .await
+        .context("the session could not be started")?;
+    wallet
+        .validate_request(url)
+        .await
+        .context("the request was rejected")?;
+    Ok(None)
+}
+
+/// A signed request of the verifier, or `None` if its requests are unsigned.
+async fn signed_request(verifier: &Verifier) -> Result<Option<(String, String)>> {
+    let (url, _) = verifier
+        .begin_session()
+        .await
+        .context("the session could not be started")?;
+    let request = ObservedRequest::from_url(verifier, &url).await?;
+    if request.request_object()?.client_id_scheme() == &ClientIdScheme::RedirectUri {
+        return Ok(None);
+    }
+    Ok(request.jwt.map(|jwt| (request.client_id, jwt)))
+}
+
+async fn check_tampered_request<W: Wallet + Send + Sync>(
+    wallet: &W,
+    (client_id, jwt): &(String, String),
+) -> Result<Option<String>> {
+    let (header, _, signature) = split_jwt(jwt)?;
+    let mut payload = super::jwt_payload(jwt)?;
+    payload.insert(Nonce::from("conformance-tampered-nonce"));
+    let payload = BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&payload)?);
+    expect_rejection(
+        wallet,
+        client_id,
+        format!("{header}.{payload}.{signature}"),
+        "the tampered request",
+        AuthorizationErrorCode::InvalidRequestObject,
+    )
+    .await
+}
+
+async fn check_unsigned_request<W: Wallet + Send + Sync>(
+    wallet: &W,
+    (client_id, jwt): &(String, String),
+) -> Result<Option<String>> {
+    let (_, payload, _) = split_jwt(jwt)?;
+    let header = BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
+    expect_rejection(
+        wallet,
+        client_id,
+        format!("{header}.{payload}."),
+        "the unsigned request",
+        AuthorizationErrorCode::InvalidRequest,
+    )
+    .await
+}
+
+async fn check_client_id_mismatch<W: Wallet + Send + Sync>(
+    wallet: &W,
+    (_, jwt): &(String, String),
+) -> Result<Option<String>> {
+    expect_rejection(
+        wallet,
+        "conformance-other-client",
+        jwt.clone(),
+        "the request with another client_id",
+        AuthorizationErrorCode::InvalidRequest,
+    )
+    .await
+}
+
+/// Check that the wallet rejects a request by value, with the `expected` error code.
+async fn expect_rejection<W: Wallet + Send + Sync>(
+    wallet: &W,
+    client_id: &str,
+    jwt: String,
+    what: &str,
+    expected: AuthorizationErrorCode,
+) -> Result<Option<String>> {
+    let url = AuthorizationRequest {
+        client_id: client_id.to_owned(),
+        request_indirection: RequestIndirection::ByValue(jwt),
+    }
+    .to_url(wallet.metadata().authorization_endpoint().0.clone())?;
+    let Err(e) = wallet.validate_request(url).await else {
+        bail!("{what} was accepted")
+    };
+    let code = AuthorizationErrorCode::for_error(&e);
+    ensure!(
+        code == expected,
+        "{what} was rejected with '{code}', expected '{expected}'"
+    );
+    Ok(None)
+}
+
+fn split_jwt(jwt: &str) -> Result<(&str, &str, &str)> {
+    let mut parts = jwt.splitn(3, '.');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(header), Some(payload), Some(signature)) => Ok((header, payload, signature)),
+        _ => bail!("the request object is not a compact JWS"),
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+
+use crate::{
+    core::object::{TypedParameter, UntypedObject},
+    wallet::Wallet,
+};
+
+use super::{
+    decrypt_request_object,
+    parameters::ExpectedOrigins,
+    verification::{unsigned::normalize_origin, validate_request_against_metadata, verify_request},
+    AuthorizationRequestObject,
+};
+
+/// An Authorization Request as delivered to the wallet by the
+/// [W3C Digital Credentials API](https://wicg.github.io/digital-credentials/).
+///
+/// The platform passes the `data` of the request made by the verifier's web page, together with
+/// the origin of that page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DcApiRequest {
+    /// The origin of the invoking web page, as asserted by the platform.
+    pub origin: String,
+    /// Either an object with a signed Request Object in the `request` parameter, or the
+    /// parameters of an unsigned request.
+    pub data: Json,
+}
+
+impl DcApiRequest {
+    /// Validate the request, and return the parsed [AuthorizationRequestObject].
+    ///
+    /// Request Objects are decrypted and verified as for URL-based invocation, and must list the
+    /// invoking origin in `expected_origins`. Unsigned requests, and unsigned (`"alg": "none"`)
+    /// Request Objects, are subject to the wallet's
+    /// [unsigned_request_policy](Wallet::unsigned_request_policy), applied to the invoking origin.
+    pub async fn validate<W: Wallet + ?Sized>(
+        self,
+        wallet: &W,
+    ) -> Result<AuthorizationRequestObject> {
+        let origin = normalize_origin(&self.origin)
+            .with_context(|| format!("invalid origin '{}'", self.origin))?;
+
+        let Json::Object(mut data) = self.data else {
+            bail!("Digital Credentials API request data was not a JSON object")
+        };
+
+        if let Some(request) = data.remove("request") {
+            let Json::String(jwt) = request else {
+                bail!("'request' was not a string")
+            };
+            let jwt = decrypt_request_object(wallet, jwt)?;
+            let request = verify_request(wallet, jwt, Some(&origin))
+                .await
+                .context("unable to validate Authorization Request")?;
+
+            let Some(expected_origins) = request.get::<ExpectedOrigins>() else {
+                bail!("signed requests must contain '{}'", ExpectedOrigins::KEY)
+            };
+            let ExpectedOrigins(expected_origins) = expected_origins?;
+            if !expected_origins
+                .iter()
+                .any(|expected| normalize_origin(expected).as_deref() == Some(origin.as_str()))
+            {
+                bail!("request was invoked from an unexpected origin '{origin}'")
+            }
+
+            return Ok(request);
+        }
+
+        wallet.unsigned_request_policy().check_origin(&origin)?;
+        let request: AuthorizationRequestObject = UntypedObject(data)
+            .try_into()
+            .context("unable to parse Authorization Request")?;
+        validate_request_against_metadata(wallet, &request).await?;
+        Ok(request)
+    }
+}
@@ -1,26 +1,39 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    collections::BTreeMap,
+    ops::{Deref, DerefMut},
+};
 
 use anyhow::{anyhow, bail, Context, Error, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as Json;
 use url::Url;
 
+#[cfg(feature = "wallet")]
 use crate::wallet::Wallet;
 
-use self::{
-    parameters::{
-        ClientId, ClientIdScheme, Nonce, PresentationDefinition, PresentationDefinitionUri,
-        RedirectUri, ResponseMode, ResponseType, ResponseUri,
-    },
-    verification::verify_request,
+use self::parameters::{
+    Audience, ClientId, ClientIdScheme, ClientMetadata, ClientMetadataUri, ExpectedOrigins, Nonce,
+    PresentationDefinition, PresentationDefinitionUri, RedirectUri, RequestUriMethod, ResponseMode,
+    ResponseType, ResponseUri, Scope, State, TransactionData,
 };
+#[cfg(feature = "wallet")]
+use self::verification::{verify_request, verify_unsigned_request};
 
 use super::{
-    object::{ParsingErrorContext, UntypedObject},
-    util::{base_request, AsyncHttpClient},
+    dcql_query::DcqlQuery,
+    object::{ParsingErrorContext, TypedParameter, UntypedObject},
+    util::AsyncHttpClient,
+};
+#[cfg(feature = "wallet")]
+use super::{
+    jwe::compact,
+    util::{base_request, retry::HttpOperation},
 };
 
+#[cfg(feature = "wallet")]
+pub mod dc_api;
 pub mod parameters;
+#[cfg(feature = "wallet")]
 pub mod verification;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,13 +64,49 @@ pub enum RequestIndirection {
     ByValue(String),
     #[serde(rename = "request_uri")]
     ByReference(Url),
+    /// The parameters of an unsigned request, passed directly in the URL instead of in a Request
+    /// Object, as with the `redirect_uri` client_id_scheme.
+    #[serde(skip)]
+    Unsigned(UntypedObject),
 }
 
-/// A PresentationDefinition, passed by value or by reference
+/// A PresentationDefinition, passed by value or by reference, or identified by a scope.
+///
+/// Requests may instead carry a [DcqlQuery].
 #[derive(Debug, Clone)]
 pub enum PresentationDefinitionIndirection {
     ByValue(PresentationDefinition),
     ByReference(Url),
+    ByScope(Scope),
+    Dcql(DcqlQuery),
+}
+
+/// Decrypt a Request Object that was encrypted to the wallet, with its
+/// [decryption key](Wallet::request_object_decryption_key). Signed Request Objects are returned
+/// as they are.
+#[cfg(feature = "wallet")]
+fn decrypt_request_object<W: Wallet + ?Sized>(
+    wallet: &W,
+    request_object: String,
+) -> Result<String> {
+    if request_object.split('.').count() != 5 {
+        return Ok(request_object);
+    }
+    let Some(key) = wallet.request_object_decryption_key() else {
+        bail!("the Request Object is encrypted, but the wallet has no decryption key")
+    };
+    let (header, plaintext) =
+        compact::decrypt(&request_object, key).context("failed to decrypt the Request Object")?;
+    if let Some(encs) = wallet
+        .metadata()
+        .request_object_encryption_enc_values_supported()
+    {
+        let enc = header.get("enc").and_then(Json::as_str).unwrap_or_default();
+        if !encs.0.iter().any(|supported| supported == enc) {
+            bail!("the Request Object is encrypted with the unsupported 'enc' '{enc}'")
+        }
+    }
+    String::from_utf8(plaintext).context("the decrypted Request Object is not UTF-8")
 }
 
 impl AuthorizationRequest {
@@ -65,12 +114,19 @@ impl AuthorizationRequest {
     /// [RequestObject].
     ///
     /// Custom wallet metadata can be provided, otherwise the default metadata for this profile is used.
+    #[cfg(feature = "wallet")]
     pub async fn validate<W: Wallet + ?Sized>(
         self,
         wallet: &W,
     ) -> Result<AuthorizationRequestObject> {
         let jwt = match self.request_indirection {
             RequestIndirection::ByValue(jwt) => jwt,
+            RequestIndirection::Unsigned(parameters) => {
+                let aro = verify_unsigned_request(wallet, parameters)
+                    .await
+                    .context("unable to validate Authorization Request")?;
+                return Ok(aro);
+            }
             RequestIndirection::ByReference(url) => {
                 let request = base_request()
                     .method("GET")
@@ -79,8 +135,7 @@ impl AuthorizationRequest {
                     .context("failed to build authorization request request")?;
 
                 let response = wallet
-                    .http_client()
-                    .execute(request)
+                    .execute_http(HttpOperation::RequestObject, request)
                     .await
                     .context(format!(
                         "failed to make authorization request request at {url}"
@@ -100,7 +155,8 @@ impl AuthorizationRequest {
                 body
             }
         };
-        let aro = verify_request(wallet, jwt)
+        let jwt = decrypt_request_object(wallet, jwt)?;
+        let aro = verify_request(wallet, jwt, None)
             .await
             .context("unable to validate Authorization Request")?;
         if self.client_id.as_str() != aro.client_id().0.as_str() {
@@ -129,7 +185,16 @@ impl AuthorizationRequest {
     /// assert_eq!(authorization_request_url.as_str(), "example://?client_id=xyz&request=test");
     /// ```
     pub fn to_url(self, mut authorization_endpoint: Url) -> Result<Url> {
-        let query = serde_urlencoded::to_string(self)?;
+        let query = match self.request_indirection {
+            RequestIndirection::Unsigned(mut parameters) => {
+                parameters.insert(ClientId(self.client_id));
+                serde_urlencoded::to_string(parameters.flatten_for_form()?)?
+            }
+            request_indirection => serde_urlencoded::to_string(Self {
+                client_id: self.client_id,
+                request_indirection,
+            })?,
+        };
         authorization_endpoint.set_query(Some(&query));
         Ok(authorization_endpoint)
     }
@@ -158,21 +223,37 @@ impl AuthorizationRequest {
     /// assert_eq!(request_object, "test");
     /// ```
     pub fn from_url(url: Url, authorization_endpoint: &Url) -> Result<Self> {
+        Self::from_url_with_endpoints(url, [authorization_endpoint])
+    }
+
+    /// Parse from [Url], validating that it is one of the `authorization_endpoints` of the wallet,
+    /// e.g. its
+    /// [invocation endpoints](crate::core::metadata::WalletMetadata::invocation_endpoints).
+    pub fn from_url_with_endpoints<'a>(
+        url: Url,
+        authorization_endpoints: impl IntoIterator<Item = &'a Url>,
+    ) -> Result<Self> {
         let query = url
             .query()
             .ok_or(anyhow!("missing query params in Authorization Request uri"))?
             .to_string();
-        let fnd = url.authority();
-        let exp = authorization_endpoint.authority();
-        if fnd != exp {
-            bail!("unexpected authorization_endpoint authority, expected '{exp}', received '{fnd}'")
+        let mut mismatches = Vec::new();
+        for authorization_endpoint in authorization_endpoints {
+            match check_endpoint(&url, authorization_endpoint) {
+                Ok(()) => return Self::from_query_params(&query),
+                Err(e) => mismatches.push(e.to_string()),
+            }
         }
-        let fnd = url.path();
-        let exp = authorization_endpoint.path();
-        if fnd != exp {
-            bail!("unexpected authorization_endpoint path, expected '{exp}', received '{fnd}'")
+        match mismatches.as_slice() {
+            [] => {
+                bail!("no authorization_endpoint to validate the Authorization Request uri against")
+            }
+            [mismatch] => bail!("{mismatch}"),
+            _ => bail!(
+                "the Authorization Request uri does not match any authorization_endpoint: {}",
+                mismatches.join("; ")
+            ),
         }
-        Self::from_query_params(&query)
     }
 
     /// Parse from urlencoded query parameters.
@@ -189,13 +270,77 @@ impl AuthorizationRequest {
     /// else { panic!("expected request-by-value") };
     /// assert_eq!(request_object, "test");
     /// ```
+    ///
+    /// Without a `request` or `request_uri`, the query parameters are the parameters of an
+    /// unsigned request, where objects and arrays are JSON encoded.
     pub fn from_query_params(query_params: &str) -> Result<Self> {
-        serde_urlencoded::from_str(query_params)
-            .context("unable to parse Authorization Request from query params")
+        let parameters = serde_urlencoded::from_str::<BTreeMap<String, String>>(query_params)
+            .context("unable to parse Authorization Request from query params")?;
+        if parameters.contains_key("request") || parameters.contains_key("request_uri") {
+            return serde_urlencoded::from_str(query_params)
+                .context("unable to parse Authorization Request from query params");
+        }
+
+        let client_id = parameters
+            .get(ClientId::KEY)
+            .context("missing client_id in Authorization Request")?
+            .clone();
+        Ok(Self {
+            client_id,
+            request_indirection: RequestIndirection::Unsigned(UntypedObject::from_form(parameters)),
+        })
     }
 }
 
+/// Check that `url` is at the `authorization_endpoint`.
+fn check_endpoint(url: &Url, authorization_endpoint: &Url) -> Result<()> {
+    let fnd = url.scheme();
+    let exp = authorization_endpoint.scheme();
+    if fnd != exp {
+        bail!("unexpected authorization_endpoint scheme, expected '{exp}', received '{fnd}'")
+    }
+    let fnd = url.authority();
+    let exp = authorization_endpoint.authority();
+    if fnd != exp {
+        bail!("unexpected authorization_endpoint authority, expected '{exp}', received '{fnd}'")
+    }
+    let fnd = url.path();
+    let exp = authorization_endpoint.path();
+    if fnd != exp {
+        bail!("unexpected authorization_endpoint path, expected '{exp}', received '{fnd}'")
+    }
+    Ok(())
+}
+
 impl AuthorizationRequestObject {
+    /// The request parameters that this library knows, and the claims of Request Objects, see
+    /// [UnknownParameters](super::object::UnknownParameters).
+    pub const KNOWN_PARAMETERS: &'static [&'static str] = &[
+        ClientId::KEY,
+        ClientIdScheme::KEY,
+        ResponseType::KEY,
+        ResponseMode::KEY,
+        ResponseUri::KEY,
+        RedirectUri::KEY,
+        Nonce::KEY,
+        State::KEY,
+        PresentationDefinition::KEY,
+        PresentationDefinitionUri::KEY,
+        DcqlQuery::KEY,
+        Scope::KEY,
+        ClientMetadata::KEY,
+        ClientMetadataUri::KEY,
+        RequestUriMethod::KEY,
+        TransactionData::KEY,
+        ExpectedOrigins::KEY,
+        "iss",
+        Audience::KEY,
+        "iat",
+        "exp",
+        "nbf",
+        "jti",
+    ];
+
     pub fn client_id(&self) -> &ClientId {
         &self.1
     }
@@ -204,20 +349,14 @@ impl AuthorizationRequestObject {
         &self.2
     }
 
-    pub async fn resolve_presentation_definition<H: AsyncHttpClient>(
+    pub async fn resolve_presentation_definition<H: AsyncHttpClient + Sync>(
         &self,
         http_client: &H,
     ) -> Result<PresentationDefinition> {
         match &self.5 {
             PresentationDefinitionIndirection::ByValue(by_value) => Ok(by_value.clone()),
             PresentationDefinitionIndirection::ByReference(by_reference) => {
-                let request = base_request()
-                    .method("GET")
-                    .uri(by_reference.to_string())
-                    .body(vec![])
-                    .context("failed to build presentation definition request")?;
-
-                let response = http_client.execute(request).await.context(format!(
+                let response = http_client.get(by_reference).await.context(format!(
                     "failed to make presentation definition request at {by_reference}"
                 ))?;
 
@@ -234,6 +373,30 @@ impl AuthorizationRequestObject {
                 .try_into()
                 .context("failed to parse presentation definition from JSON")
             }
+            PresentationDefinitionIndirection::Dcql(_) => {
+                bail!("the request carries a DCQL query instead of a presentation definition")
+            }
+            PresentationDefinitionIndirection::ByScope(scope) => bail!(
+                "the presentation definition is identified by the scope '{}', which must be resolved by the wallet",
+                Json::from(scope.clone())
+            ),
+        }
+    }
+
+    /// The DCQL query of the request, if it carries one instead of a presentation definition.
+    pub fn dcql_query(&self) -> Option<&DcqlQuery> {
+        match &self.5 {
+            PresentationDefinitionIndirection::Dcql(query) => Some(query),
+            _ => None,
+        }
+    }
+
+    /// The scope identifying the presentation definition, if it was neither passed by value nor
+    /// by reference.
+    pub fn presentation_definition_scope(&self) -> Option<&Scope> {
+        match &self.5 {
+            PresentationDefinitionIndirection::ByScope(scope) => Some(scope),
+            _ => None,
         }
     }
 
@@ -314,8 +477,22 @@ impl TryFrom<UntypedObject> for AuthorizationRequestObject {
             value.get::<PresentationDefinition>(),
             value.get::<PresentationDefinitionUri>(),
         ) {
-            (None, None) => bail!(
-                "one of 'presentation_definition' and 'presentation_definition_uri' are required"
+            (None, None) => match (value.get::<DcqlQuery>(), value.get::<Scope>()) {
+                (Some(_), Some(_)) => bail!("'dcql_query' and 'scope' are mutually exclusive"),
+                (Some(query), None) => {
+                    let query = query.parsing_error()?;
+                    query.validate().context("invalid 'dcql_query'")?;
+                    PresentationDefinitionIndirection::Dcql(query)
+                }
+                (None, Some(scope)) => {
+                    PresentationDefinitionIndirection::ByScope(scope.parsing_error()?)
+                }
+                (None, None) => bail!(
+                    "one of 'presentation_definition', 'presentation_definition_uri', 'dcql_query' and 'scope' are required"
+                ),
+            },
+            _ if value.contains::<DcqlQuery>() => bail!(
+                "'dcql_query' and 'presentation_definition' or 'presentation_definition_uri' are mutually exclusive"
             ),
             (Some(_), Some(_)) => {
                 bail!("'presentation_definition' and 'presentation_definition_uri' are mutually exclusive")
@@ -1,9 +1,14 @@
 use std::{fmt, ops::Deref};
 
 use crate::core::{
-    object::{ParsingErrorContext, TypedParameter, UntypedObject},
+    consts::{client_id_scheme::*, response_mode::*, response_type::*},
+    metadata::parameters::verifier::{
+        AuthorizationEncryptedResponseAlg, AuthorizationEncryptedResponseEnc, JWKs,
+        RequireSignedRequestObject, VpFormats,
+    },
+    object::{typed_parameter, ParsingErrorContext, TypedParameter, UntypedObject},
     presentation_definition::PresentationDefinition as PresentationDefinitionParsed,
-    util::{base_request, AsyncHttpClient},
+    util::AsyncHttpClient,
 };
 use anyhow::{bail, Context, Error, Ok};
 use serde::{Deserialize, Serialize};
@@ -12,14 +17,6 @@ use url::Url;
 
 use super::AuthorizationRequestObject;
 
-const DID: &str = "did";
-const ENTITY_ID: &str = "entity_id";
-const PREREGISTERED: &str = "pre-registered";
-const REDIRECT_URI: &str = "redirect_uri";
-const VERIFIER_ATTESTATION: &str = "verifier_attestation";
-const X509_SAN_DNS: &str = "x509_san_dns";
-const X509_SAN_URI: &str = "x509_san_uri";
-
 #[derive(Debug, Clone)]
 pub struct ClientId(pub String);
 
@@ -57,12 +54,34 @@ impl TypedParameter for ClientIdScheme {
     const KEY: &'static str = "client_id_scheme";
 }
 
+impl ClientIdScheme {
+    /// Parse a Client Identifier Prefix, the name later drafts give to client ID schemes, e.g.
+    /// `decentralized_identifier` for [Did](Self::Did). Scheme names are accepted as prefixes.
+    pub fn from_prefix(prefix: String) -> Self {
+        match prefix.as_str() {
+            DECENTRALIZED_IDENTIFIER => ClientIdScheme::Did,
+            OPENID_FEDERATION => ClientIdScheme::EntityId,
+            _ => prefix.into(),
+        }
+    }
+
+    /// The Client Identifier Prefix of the scheme, e.g. `decentralized_identifier` for
+    /// [Did](Self::Did).
+    pub fn prefix(&self) -> String {
+        match self {
+            ClientIdScheme::Did => DECENTRALIZED_IDENTIFIER.to_owned(),
+            ClientIdScheme::EntityId => OPENID_FEDERATION.to_owned(),
+            other => other.to_string(),
+        }
+    }
+}
+
 impl From<String> for ClientIdScheme {
     fn from(s: String) -> Self {
         match s.as_str() {
             DID => ClientIdScheme::Did,
             ENTITY_ID => ClientIdScheme::EntityId,
-            PREREGISTERED => ClientIdScheme::PreRegistered,
+            PRE_REGISTERED => ClientIdScheme::PreRegistered,
             REDIRECT_URI => ClientIdScheme::RedirectUri,
             VERIFIER_ATTESTATION => ClientIdScheme::VerifierAttestation,
             X509_SAN_DNS => ClientIdScheme::X509SanDns,
@@ -93,7 +112,7 @@ impl fmt::Display for ClientIdScheme {
         match self {
             ClientIdScheme::Did => DID,
             ClientIdScheme::EntityId => ENTITY_ID,
-            ClientIdScheme::PreRegistered => PREREGISTERED,
+            ClientIdScheme::PreRegistered => PRE_REGISTERED,
             ClientIdScheme::RedirectUri => REDIRECT_URI,
             ClientIdScheme::VerifierAttestation => VERIFIER_ATTESTATION,
             ClientIdScheme::X509SanDns => X509_SAN_DNS,
@@ -104,6 +123,14 @@ impl fmt::Display for ClientIdScheme {
     }
 }
 
+/// The kind of Subject Alternative Name that the `client_id` of the `x509_san_dns` and
+/// `x509_san_uri` client_id_schemes is matched against.
+#[derive(Debug, Clone, Copy)]
+pub enum X509SanVariant {
+    Uri,
+    Dns,
+}
+
 /// `client_metadata` field in the Authorization Request.
 #[derive(Debug, Clone)]
 pub struct ClientMetadata(pub UntypedObject);
@@ -127,11 +154,36 @@ impl TryFrom<Json> for ClientMetadata {
 }
 
 impl ClientMetadata {
+    /// The client metadata members that this library knows, and the ones registered for OAuth
+    /// 2.0 clients that verifiers commonly publish, see
+    /// [UnknownParameters](crate::core::object::UnknownParameters).
+    pub const KNOWN_PARAMETERS: &'static [&'static str] = &[
+        JWKs::KEY,
+        VpFormats::KEY,
+        AuthorizationEncryptedResponseAlg::KEY,
+        AuthorizationEncryptedResponseEnc::KEY,
+        RequireSignedRequestObject::KEY,
+        "vp_formats_supported",
+        "encrypted_response_enc_values_supported",
+        "jwks_uri",
+        "client_name",
+        "client_uri",
+        "logo_uri",
+        "policy_uri",
+        "tos_uri",
+        "contacts",
+        "redirect_uris",
+        "subject_syntax_types_supported",
+        "id_token_signed_response_alg",
+        "id_token_encrypted_response_alg",
+        "id_token_encrypted_response_enc",
+    ];
+
     /// Resolves the client metadata from the Authorization Request Object.
     ///
     /// If the client metadata is not passed by reference or value if the Authorization Request Object,
     /// then this function will return an error.
-    pub async fn resolve<H: AsyncHttpClient>(
+    pub async fn resolve<H: AsyncHttpClient + Sync>(
         request: &AuthorizationRequestObject,
         http_client: &H,
     ) -> Result<Self, Error> {
@@ -141,14 +193,8 @@ impl ClientMetadata {
 
         if let Some(metadata_uri) = request.get::<ClientMetadataUri>() {
             let uri = metadata_uri.parsing_error()?.0;
-            let request = base_request()
-                .method("GET")
-                .uri(uri.to_string())
-                .body(vec![])
-                .context("failed to build client metadata request")?;
-
             let response = http_client
-                .execute(request)
+                .get(&uri)
                 .await
                 .context(format!("failed to make client metadata request at {uri}"))?;
 
@@ -249,25 +295,9 @@ impl From<Nonce> for Json {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Audience(pub String);
-
-impl TypedParameter for Audience {
-    const KEY: &'static str = "aud";
-}
-
-impl TryFrom<Json> for Audience {
-    type Error = Error;
-
-    fn try_from(value: Json) -> Result<Self, Self::Error> {
-        Ok(Self(serde_json::from_value(value)?))
-    }
-}
-
-impl From<Audience> for Json {
-    fn from(value: Audience) -> Json {
-        Json::String(value.0)
-    }
+typed_parameter! {
+    #[derive(Debug, Clone)]
+    pub struct Audience(pub String) = "aud";
 }
 
 /// `redirect_uri` field in the Authorization Request.
@@ -314,9 +344,6 @@ impl TryFrom<Json> for ResponseUri {
     }
 }
 
-const DIRECT_POST: &str = "direct_post";
-const DIRECT_POST_JWT: &str = "direct_post.jwt";
-
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(into = "String", from = "String")]
 pub enum ResponseMode {
@@ -380,7 +407,7 @@ impl fmt::Display for ResponseMode {
 
 impl Default for ResponseMode {
     fn default() -> Self {
-        Self::Unsupported("fragment".into())
+        Self::Unsupported(FRAGMENT.into())
     }
 }
 
@@ -394,9 +421,6 @@ impl ResponseMode {
     }
 }
 
-const VP_TOKEN: &str = "vp_token";
-const VP_TOKEN_ID_TOKEN: &str = "vp_token id_token";
-
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 #[serde(into = "String", from = "String")]
 pub enum ResponseType {
@@ -425,6 +449,17 @@ impl From<String> for ResponseType {
     }
 }
 
+impl fmt::Display for ResponseType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseType::VpToken => VP_TOKEN,
+            ResponseType::VpTokenIdToken => VP_TOKEN_ID_TOKEN,
+            ResponseType::Unsupported(u) => u,
+        }
+        .fmt(f)
+    }
+}
+
 impl TypedParameter for ResponseType {
     const KEY: &'static str = "response_type";
 }
@@ -444,25 +479,14 @@ impl From<ResponseType> for Json {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct State(pub String);
+typed_parameter! {
+    #[derive(Debug, Clone)]
+    pub struct State(pub String) = "state";
 
-impl TypedParameter for State {
-    const KEY: &'static str = "state";
-}
-
-impl TryFrom<Json> for State {
-    type Error = Error;
-
-    fn try_from(value: Json) -> Result<Self, Self::Error> {
-        Ok(Self(serde_json::from_value(value)?))
-    }
-}
-
-impl From<State> for Json {
-    fn from(value: State) -> Self {
-        Json::String(value.0)
-    }
+    /// The HTTP method with which the wallet retrieves a Request Object passed by reference, `get` or
+    /// `post`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RequestUriMethod(pub String) = "request_uri_method";
 }
 
 #[derive(Debug, Clone)]
@@ -492,6 +516,14 @@ impl TryFrom<PresentationDefinitionParsed> for PresentationDefinition {
 
 impl TypedParameter for PresentationDefinition {
     const KEY: &'static str = "presentation_definition";
+
+    fn try_from_ref(value: &Json) -> Result<Self, Error> {
+        let parsed = PresentationDefinitionParsed::deserialize(value)?;
+        Ok(Self {
+            raw: value.clone(),
+            parsed,
+        })
+    }
 }
 
 impl TryFrom<Json> for PresentationDefinition {
@@ -529,3 +561,54 @@ impl From<PresentationDefinitionUri> for Json {
         value.0.to_string().into()
     }
 }
+
+/// `scope` field in the Authorization Request.
+///
+/// A request may identify the presentation definition by a scope value agreed out of band between
+/// the verifier and the wallet, instead of passing it by value or by reference. Multiple scope
+/// values are space-delimited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scope(pub Vec<String>);
+
+impl Scope {
+    pub fn new(scope: impl Into<String>) -> Self {
+        Self(vec![scope.into()])
+    }
+}
+
+impl TypedParameter for Scope {
+    const KEY: &'static str = "scope";
+}
+
+impl TryFrom<Json> for Scope {
+    type Error = Error;
+
+    fn try_from(value: Json) -> Result<Self, Self::Error> {
+        let scope: String = serde_json::from_value(value)?;
+        let values: Vec<String> = scope.split_whitespace().map(ToOwned::to_owned).collect();
+        if values.is_empty() {
+            bail!("'scope' was empty")
+        }
+        Ok(Self(values))
+    }
+}
+
+impl From<Scope> for Json {
+    fn from(value: Scope) -> Self {
+        Json::String(value.0.join(" "))
+    }
+}
+
+typed_parameter! {
+    /// `transaction_data` field in the Authorization Request.
+    ///
+    /// Each entry is a base64url-encoded JSON object, which is kept in its encoded form as the
+    /// `transaction_data_hashes` are computed over the exact strings from the request.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct TransactionData(pub Vec<String>) = "transaction_data";
+
+    /// `expected_origins` field in the Authorization Request, listing the origins from which a
+    /// request delivered over the Digital Credentials API may be invoked.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ExpectedOrigins(pub Vec<String>) = "expected_origins";
+}
@@ -1,15 +1,116 @@
 use crate::core::{
     authorization_request::AuthorizationRequestObject,
     metadata::{parameters::wallet::RequestObjectSigningAlgValuesSupported, WalletMetadata},
-    object::ParsingErrorContext,
+    object::TypedParameter,
+    response::error::InvalidRequestObject,
 };
 use anyhow::{bail, Context, Result};
 use base64::prelude::*;
 use serde_json::{Map, Value as Json};
 
-use ssi::jwk::JWKResolver;
+use ssi::{
+    claims::ProofValidationError,
+    jwk::{JWKResolver, JWK},
+};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+use tracing::{debug, debug_span, instrument, Instrument};
+
+/// A [JWKResolver] that caches resolved keys by key id.
+///
+/// Share a single instance across requests, such as a batch of requests scanned in quick
+/// succession, to avoid resolving the same DID repeatedly.
+///
+/// Keys are cached for [DEFAULT_TTL](Self::DEFAULT_TTL), so that rotated keys are eventually
+/// resolved again, and at most [DEFAULT_MAX_KEYS](Self::DEFAULT_MAX_KEYS) keys are kept, the
+/// oldest being evicted first.
+#[derive(Debug)]
+pub struct CachingJwkResolver<R> {
+    inner: R,
+    cache: Mutex<HashMap<String, (JWK, Instant)>>,
+    max_keys: usize,
+    ttl: Duration,
+}
+
+impl<R> CachingJwkResolver<R> {
+    /// How long a key is cached for unless otherwise configured.
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(3600);
+    /// The number of keys cached unless otherwise configured.
+    pub const DEFAULT_MAX_KEYS: usize = 256;
+
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            cache: Mutex::default(),
+            max_keys: Self::DEFAULT_MAX_KEYS,
+            ttl: Self::DEFAULT_TTL,
+        }
+    }
+
+    /// Set the maximum number of cached keys.
+    pub fn with_max_keys(mut self, max_keys: usize) -> Self {
+        self.max_keys = max_keys;
+        self
+    }
+
+    /// Set how long a key is cached for after it was resolved.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Remove all cached keys.
+    pub async fn clear(&self) {
+        self.cache.lock().await.clear()
+    }
+}
+
+impl<R: JWKResolver> JWKResolver for CachingJwkResolver<R> {
+    async fn fetch_public_jwk(
+        &self,
+        key_id: Option<&str>,
+    ) -> Result<Cow<'_, JWK>, ProofValidationError> {
+        let Some(key_id) = key_id else {
+            return self.inner.fetch_public_jwk(None).await;
+        };
+
+        if let Some((jwk, resolved_at)) = self.cache.lock().await.get(key_id) {
+            if resolved_at.elapsed() < self.ttl {
+                debug!(key_id, "resolved key from cache");
+                return Ok(Cow::Owned(jwk.clone()));
+            }
+        }
+
+        let jwk = self
+            .inner
+            .fetch_public_jwk(Some(key_id))
+            .await?
+            .into_owned();
+
+        let mut cache = self.cache.lock().await;
+        cache.retain(|_, (_, resolved_at)| resolved_at.elapsed() < self.ttl);
+        while !cache.is_empty() && cache.len() >= self.max_keys {
+            let oldest = cache
+                .iter()
+                .min_by_key(|(_, (_, resolved_at))| *resolved_at)
+                .map(|(key_id, _)| key_id.clone());
+            if let Some(oldest) = oldest {
+                cache.remove(&oldest);
+            }
+        }
+        if self.max_keys > 0 {
+            cache.insert(key_id.to_owned(), (jwk.clone(), Instant::now()));
+        }
+        Ok(Cow::Owned(jwk))
+    }
+}
 
 /// Default implementation of request validation for `client_id_scheme` `did`.
+#[instrument(level = "debug", skip_all, fields(client_id = %request_object.client_id().0))]
 pub async fn verify_with_resolver(
     wallet_metadata: &WalletMetadata,
     request_object: &AuthorizationRequestObject,
@@ -33,11 +134,19 @@ pub async fn verify_with_resolver(
         bail!("'alg' header was not a string")
     };
 
-    let supported_algs: RequestObjectSigningAlgValuesSupported =
-        wallet_metadata.get().parsing_error()?;
+    let supported_algs = wallet_metadata
+        .request_object_signing_alg_values_supported()
+        .with_context(|| {
+            format!(
+                "'{}' is missing",
+                RequestObjectSigningAlgValuesSupported::KEY
+            )
+        })?;
 
     if !supported_algs.0.contains(&alg) {
-        bail!("request was signed with unsupported algorithm: {alg}")
+        bail!(InvalidRequestObject(format!(
+            "request was signed with unsupported algorithm: {alg}"
+        )))
     }
 
     let Json::String(kid) = headers
@@ -67,11 +176,75 @@ pub async fn verify_with_resolver(
 
     let jwk = resolver
         .fetch_public_jwk(Some(&kid))
+        .instrument(debug_span!("resolve_did", did, kid))
         .await
         .context("unable to resolve key from verification method")?;
 
-    let _: Json = ssi::claims::jwt::decode_verify(&request_jwt, &jwk)
-        .context("request signature could not be verified")?;
+    let _: Json = ssi::claims::jwt::decode_verify(&request_jwt, &jwk).context(
+        InvalidRequestObject("request signature could not be verified".into()),
+    )?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Resolves a fixed key for any key id, counting the resolutions.
+    struct CountingResolver {
+        jwk: JWK,
+        resolutions: AtomicUsize,
+    }
+
+    impl JWKResolver for CountingResolver {
+        async fn fetch_public_jwk(
+            &self,
+            _: Option<&str>,
+        ) -> Result<Cow<'_, JWK>, ProofValidationError> {
+            self.resolutions.fetch_add(1, Ordering::SeqCst);
+            Ok(Cow::Borrowed(&self.jwk))
+        }
+    }
+
+    fn counting_resolver() -> CountingResolver {
+        CountingResolver {
+            jwk: JWK::generate_p256(),
+            resolutions: AtomicUsize::new(0),
+        }
+    }
+
+    async fn resolve<R: JWKResolver>(resolver: &CachingJwkResolver<R>, key_ids: &[&str]) {
+        for key_id in key_ids {
+            resolver.fetch_public_jwk(Some(key_id)).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn evicts_oldest_key() {
+        let resolver = CachingJwkResolver::new(counting_resolver()).with_max_keys(2);
+
+        resolve(&resolver, &["a", "b", "a", "b"]).await;
+        assert_eq!(resolver.inner.resolutions.load(Ordering::SeqCst), 2);
+
+        // Resolving `c` evicts `a`, the oldest key.
+        resolve(&resolver, &["c", "b", "c"]).await;
+        assert_eq!(resolver.inner.resolutions.load(Ordering::SeqCst), 3);
+        resolve(&resolver, &["a"]).await;
+        assert_eq!(resolver.inner.resolutions.load(Ordering::SeqCst), 4);
+        assert_eq!(resolver.cache.lock().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn expires_keys() {
+        let resolver = CachingJwkResolver::new(counting_resolver()).with_ttl(Duration::ZERO);
+        resolve(&resolver, &["a", "a"]).await;
+        assert_eq!(resolver.inner.resolutions.load(Ordering::SeqCst), 2);
+
+        let resolver = CachingJwkResolver::new(counting_resolver());
+        resolve(&resolver, &["a", "a"]).await;
+        assert_eq!(resolver.inner.resolutions.load(Ordering::SeqCst), 1);
+    }
+}
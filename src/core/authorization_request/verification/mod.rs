@@ -1,25 +1,30 @@
 use crate::{
     core::{
-        metadata::parameters::{
-            verifier::{AuthorizationEncryptedResponseAlg, AuthorizationEncryptedResponseEnc},
-            wallet::{
-                AuthorizationEncryptionAlgValuesSupported,
-                AuthorizationEncryptionEncValuesSupported, ClientIdSchemesSupported,
-            },
+        jwe,
+        metadata::parameters::verifier::AuthorizationEncryptedResponseAlg,
+        object::{
+            registry::{ParameterLocation, ParameterRegistry},
+            TypedParameter, UntypedObject,
         },
-        object::{ParsingErrorContext, TypedParameter, UntypedObject},
+        response::error::{
+            ClientIdSchemeNotSupported, PresentationDefinitionUriNotSupported,
+            ResponseModeNotSupported, ResponseTypeNotSupported,
+        },
+        spans,
     },
     wallet::Wallet,
 };
 use anyhow::{bail, Context, Error, Result};
 use async_trait::async_trait;
+use tracing::{field::Empty, instrument, Span};
 
 use super::{
-    parameters::{ClientIdScheme, ClientMetadata, ResponseMode},
+    parameters::{ClientIdScheme, ClientMetadata, PresentationDefinitionUri},
     AuthorizationRequestObject,
 };
 
 pub mod did;
+pub mod unsigned;
 pub mod verifier;
 pub mod x509_san;
 
@@ -101,17 +106,40 @@ pub trait RequestVerifier {
     }
 }
 
+/// Verify a Request Object JWT.
+///
+/// Unsigned Request Objects are subject to the wallet's
+/// [UnsignedRequestPolicy](unsigned::UnsignedRequestPolicy), applied to `origin` when the request
+/// was invoked from a known origin (e.g. through the Digital Credentials API), or to the
+/// `return_uri` of the request otherwise.
+#[instrument(
+    level = "debug",
+    skip_all,
+    fields(client_id = Empty, client_id_scheme = Empty, response_mode = Empty)
+)]
 pub(crate) async fn verify_request<W: Wallet + ?Sized>(
     wallet: &W,
     jwt: String,
+    origin: Option<&str>,
 ) -> Result<AuthorizationRequestObject> {
     let request: AuthorizationRequestObject =
         ssi::claims::jwt::decode_unverified::<UntypedObject>(&jwt)
             .context("unable to decode Authorization Request Object JWT")?
             .try_into()?;
+    spans::record_request(&Span::current(), &request);
 
     validate_request_against_metadata(wallet, &request).await?;
 
+    // Unsigned requests are subject to the wallet's policy, and have no signature to verify.
+    if unsigned::is_unsigned(&jwt)? {
+        match origin {
+            Some(origin) => wallet.unsigned_request_policy().check_origin(origin)?,
+            None => wallet.unsigned_request_policy().check(&request)?,
+        }
+        unsigned::verify(&request, &jwt)?;
+        return Ok(request);
+    }
+
     let client_id_scheme = request.client_id_scheme();
 
     match client_id_scheme {
@@ -128,59 +156,79 @@ pub(crate) async fn verify_request<W: Wallet + ?Sized>(
     Ok(request)
 }
 
+/// Verify an unsigned request whose parameters were passed directly in the URL, which is subject to
+/// the wallet's [UnsignedRequestPolicy](unsigned::UnsignedRequestPolicy).
+pub(crate) async fn verify_unsigned_request<W: Wallet + ?Sized>(
+    wallet: &W,
+    parameters: UntypedObject,
+) -> Result<AuthorizationRequestObject> {
+    let request: AuthorizationRequestObject = parameters.try_into()?;
+
+    validate_request_against_metadata(wallet, &request).await?;
+
+    wallet.unsigned_request_policy().check(&request)?;
+    unsigned::verify_parameters(&request)?;
+
+    Ok(request)
+}
+
 pub(crate) async fn validate_request_against_metadata<W: Wallet + ?Sized>(
     wallet: &W,
     request: &AuthorizationRequestObject,
 ) -> Result<(), Error> {
     let wallet_metadata = wallet.metadata();
+    wallet_metadata
+        .validate()
+        .context("the wallet metadata is invalid")?;
 
     let client_id_scheme = request.client_id_scheme();
-    if !wallet_metadata
-        .get_or_default::<ClientIdSchemesSupported>()?
-        .0
-        .contains(client_id_scheme)
+    if !wallet_metadata.supports_client_id_scheme(client_id_scheme) {
+        bail!(ClientIdSchemeNotSupported(client_id_scheme.clone()))
+    }
+
+    let response_type = request.response_type();
+    if !wallet_metadata.supports_response_type(response_type) {
+        bail!(ResponseTypeNotSupported(response_type.clone()))
+    }
+
+    let response_mode = request.response_mode();
+    if !wallet_metadata.supports_response_mode(response_mode) {
+        bail!(ResponseModeNotSupported(response_mode.clone()))
+    }
+
+    if request.contains::<PresentationDefinitionUri>()
+        && !wallet_metadata.supports_presentation_definition_uri()
     {
-        bail!(
-            "wallet does not support client_id_scheme '{}'",
-            client_id_scheme
-        )
+        bail!(PresentationDefinitionUriNotSupported)
     }
 
     let client_metadata = ClientMetadata::resolve(request, wallet.http_client())
         .await?
         .0;
 
-    let response_mode = request.get::<ResponseMode>().parsing_error()?;
+    let unknown_parameters = wallet.unknown_parameters();
+    let no_registry = ParameterRegistry::default();
+    let registry = wallet.parameter_registry().unwrap_or(&no_registry);
+    registry.check(
+        ParameterLocation::Request,
+        request,
+        unknown_parameters,
+        "the request",
+    )?;
+    registry.check(
+        ParameterLocation::ClientMetadata,
+        &client_metadata,
+        unknown_parameters,
+        "the client metadata",
+    )?;
 
     if response_mode.is_jarm()? {
-        let alg = client_metadata
-            .get::<AuthorizationEncryptedResponseAlg>()
-            .parsing_error()?;
-        let enc = client_metadata
-            .get::<AuthorizationEncryptedResponseEnc>()
-            .parsing_error()?;
-
-        if let Some(supported_algs) =
-            wallet_metadata.get::<AuthorizationEncryptionAlgValuesSupported>()
-        {
-            if !supported_algs?.0.contains(&alg.0) {
-                bail!(
-                    "unsupported {} '{}'",
-                    AuthorizationEncryptedResponseAlg::KEY,
-                    alg.0
-                )
-            }
-        }
-        if let Some(supported_encs) =
-            wallet_metadata.get::<AuthorizationEncryptionEncValuesSupported>()
-        {
-            if !supported_encs?.0.contains(&enc.0) {
-                bail!(
-                    "unsupported {} '{}'",
-                    AuthorizationEncryptedResponseEnc::KEY,
-                    enc.0
-                )
-            }
+        // Fail early if the wallet cannot produce a response the verifier can decrypt.
+        if jwe::negotiate(&client_metadata, wallet_metadata)?.is_none() {
+            bail!(
+                "'{}' is required for response_mode '{response_mode}'",
+                AuthorizationEncryptedResponseAlg::KEY
+            )
         }
     }
 
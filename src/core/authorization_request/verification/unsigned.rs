@@ -0,0 +1,164 @@
+use anyhow::{bail, Context, Result};
+use base64::prelude::*;
+use serde_json::{Map, Value as Json};
+use url::Url;
+
+use crate::core::authorization_request::{
+    parameters::{ClientIdScheme, RequestUriMethod},
+    AuthorizationRequestObject,
+};
+
+/// Whether the wallet accepts unsigned Authorization Requests, i.e. Request Objects with
+/// `"alg": "none"` as used with the `redirect_uri` client_id_scheme.
+///
+/// Unsigned requests offer no assurance of the verifier's identity beyond the origin the response
+/// is sent to, so they are rejected unless explicitly allowed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum UnsignedRequestPolicy {
+    /// Reject all unsigned requests.
+    #[default]
+    Reject,
+    /// Accept unsigned requests whose response is sent to one of these origins, e.g.
+    /// `https://verifier.example.com`.
+    AllowFrom(Vec<String>),
+    /// Accept all unsigned requests.
+    Allow,
+}
+
+impl UnsignedRequestPolicy {
+    /// Check an unsigned request against the policy.
+    pub fn check(&self, request: &AuthorizationRequestObject) -> Result<()> {
+        self.check_origin(&request.return_uri().origin().ascii_serialization())
+    }
+
+    /// Check an unsigned request from the given origin against the policy.
+    pub fn check_origin(&self, origin: &str) -> Result<()> {
+        match self {
+            Self::Reject => bail!("unsigned authorization requests are not accepted"),
+            Self::Allow => Ok(()),
+            Self::AllowFrom(origins) => {
+                let normalized = normalize_origin(origin);
+                if normalized.is_none()
+                    || !origins
+                        .iter()
+                        .any(|allowed| normalize_origin(allowed) == normalized)
+                {
+                    bail!("unsigned authorization requests are not accepted from '{origin}'")
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub(crate) fn normalize_origin(origin: &str) -> Option<String> {
+    Url::parse(origin)
+        .ok()
+        .map(|url| url.origin().ascii_serialization())
+}
+
+/// Return whether a Request Object JWT is unsigned (`"alg": "none"`).
+pub fn is_unsigned(request_jwt: &str) -> Result<bool> {
+    let (header, _) = request_jwt
+        .split_once('.')
+        .context("request was not a compact serialized JWT")?;
+    let header: Map<String, Json> = serde_json::from_slice(
+        &BASE64_URL_SAFE_NO_PAD
+            .decode(header)
+            .context("jwt headers were not valid base64url")?,
+    )
+    .context("jwt headers were not valid json")?;
+    Ok(header.get("alg").and_then(Json::as_str) == Some("none"))
+}
+
+/// Validation of unsigned requests, which are only defined for the `redirect_uri`
+/// client_id_scheme: the `client_id` must be the URI the response is sent to.
+pub fn verify(request: &AuthorizationRequestObject, request_jwt: &str) -> Result<()> {
+    if !request_jwt.ends_with('.') {
+        bail!("unsigned request must not have a signature")
+    }
+
+    verify_parameters(request)
+}
+
+/// Validation of the parameters of an unsigned request, whether passed in an unsigned Request
+/// Object or directly in the URL.
+pub fn verify_parameters(request: &AuthorizationRequestObject) -> Result<()> {
+    if request.contains::<RequestUriMethod>() {
+        bail!("unsigned requests must not have a 'request_uri_method'")
+    }
+
+    if request.client_id_scheme() != &ClientIdScheme::RedirectUri {
+        bail!(
+            "unsigned requests are not accepted with client_id_scheme '{}'",
+            request.client_id_scheme()
+        )
+    }
+
+    if request.client_id().0 != request.return_uri().as_str() {
+        bail!(
+            "client_id '{}' does not match the response URI '{}'",
+            request.client_id().0,
+            request.return_uri()
+        )
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::core::object::UntypedObject;
+
+    use super::*;
+
+    fn unsigned_request(client_id: &str) -> (AuthorizationRequestObject, String) {
+        let claims = json!({
+            "client_id": client_id,
+            "client_id_scheme": "redirect_uri",
+            "response_type": "vp_token",
+            "response_mode": "direct_post",
+            "response_uri": "https://verifier.example.com/response",
+            "nonce": "n-0S6_WzA2Mj",
+            "presentation_definition_uri": "https://verifier.example.com/pd"
+        });
+        let jwt = format!(
+            "{}.{}.",
+            BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#),
+            BASE64_URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let request = serde_json::from_value::<UntypedObject>(claims)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        (request, jwt)
+    }
+
+    #[test]
+    fn unsigned_redirect_uri() {
+        let (request, jwt) = unsigned_request("https://verifier.example.com/response");
+        assert!(is_unsigned(&jwt).unwrap());
+        verify(&request, &jwt).unwrap();
+
+        let (request, jwt) = unsigned_request("https://attacker.example.com/response");
+        assert!(verify(&request, &jwt).is_err());
+    }
+
+    #[test]
+    fn policy() {
+        let (request, _) = unsigned_request("https://verifier.example.com/response");
+
+        assert!(UnsignedRequestPolicy::default().check(&request).is_err());
+        UnsignedRequestPolicy::Allow.check(&request).unwrap();
+        UnsignedRequestPolicy::AllowFrom(vec!["https://verifier.example.com".into()])
+            .check(&request)
+            .unwrap();
+        assert!(
+            UnsignedRequestPolicy::AllowFrom(vec!["https://other.example.com".into()])
+                .check(&request)
+                .is_err()
+        );
+    }
+}
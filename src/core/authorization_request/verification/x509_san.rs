@@ -1,25 +1,28 @@
 use anyhow::{bail, Context, Result};
 use base64::prelude::*;
 use serde_json::{Map, Value as Json};
-use tracing::debug;
+use tracing::{debug, instrument};
 use x509_cert::{
     der::{referenced::OwnedToRef, Decode},
     ext::pkix::{name::GeneralName, SubjectAltName},
     Certificate,
 };
 
-use crate::{
-    core::{
-        authorization_request::AuthorizationRequestObject,
-        metadata::{parameters::wallet::RequestObjectSigningAlgValuesSupported, WalletMetadata},
-        object::ParsingErrorContext,
-    },
-    verifier::client::X509SanVariant,
+use crate::core::{
+    authorization_request::{parameters::X509SanVariant, AuthorizationRequestObject},
+    metadata::{parameters::wallet::RequestObjectSigningAlgValuesSupported, WalletMetadata},
+    object::TypedParameter,
+    response::error::InvalidRequestObject,
 };
 
 use super::verifier::Verifier;
 
 /// Default implementation of request validation for `client_id_scheme` `x509_san_dns`.
+#[instrument(
+    level = "debug",
+    skip_all,
+    fields(client_id = %request_object.client_id().0, variant = ?x509_san_variant)
+)]
 pub fn validate<V: Verifier>(
     x509_san_variant: X509SanVariant,
     wallet_metadata: &WalletMetadata,
@@ -44,11 +47,19 @@ pub fn validate<V: Verifier>(
         bail!("'alg' header was not a string")
     };
 
-    let supported_algs: RequestObjectSigningAlgValuesSupported =
-        wallet_metadata.get().parsing_error()?;
+    let supported_algs = wallet_metadata
+        .request_object_signing_alg_values_supported()
+        .with_context(|| {
+            format!(
+                "'{}' is missing",
+                RequestObjectSigningAlgValuesSupported::KEY
+            )
+        })?;
 
     if !supported_algs.0.contains(&alg) {
-        bail!("request was signed with unsupported algorithm: {alg}")
+        bail!(InvalidRequestObject(format!(
+            "request was signed with unsupported algorithm: {alg}"
+        )))
     }
 
     let Json::Array(x5chain) = headers
@@ -119,7 +130,9 @@ pub fn validate<V: Verifier>(
 
     verifier
         .verify(&payload, &signature)
-        .context("request signature could not be verified")?;
+        .context(InvalidRequestObject(
+            "request signature could not be verified".into(),
+        ))?;
 
     Ok(())
 }
@@ -0,0 +1,75 @@
+//! Identifiers defined by OpenID4VP and the specifications it builds on, as used throughout this
+//! library.
+
+/// The issuer of static wallet metadata, see
+/// [SIOPv2 §7.1](https://openid.net/specs/openid-connect-self-issued-v2-1_0.html#section-7.1).
+pub const SELF_ISSUED_V2: &str = "https://self-issued.me/v2";
+
+/// The default authorization endpoint of wallets, a custom URL scheme.
+pub const OPENID4VP_SCHEME: &str = "openid4vp:";
+
+/// Client ID schemes, and the Client Identifier Prefixes of later drafts.
+pub mod client_id_scheme {
+    pub const DID: &str = "did";
+    pub const ENTITY_ID: &str = "entity_id";
+    pub const PRE_REGISTERED: &str = "pre-registered";
+    pub const REDIRECT_URI: &str = "redirect_uri";
+    pub const VERIFIER_ATTESTATION: &str = "verifier_attestation";
+    pub const X509_SAN_DNS: &str = "x509_san_dns";
+    pub const X509_SAN_URI: &str = "x509_san_uri";
+
+    /// The Client Identifier Prefix of the [DID] scheme.
+    pub const DECENTRALIZED_IDENTIFIER: &str = "decentralized_identifier";
+    /// The Client Identifier Prefix of the [ENTITY_ID] scheme.
+    pub const OPENID_FEDERATION: &str = "openid_federation";
+}
+
+/// Values of the `response_mode` parameter.
+pub mod response_mode {
+    pub const DIRECT_POST: &str = "direct_post";
+    pub const DIRECT_POST_JWT: &str = "direct_post.jwt";
+    /// The default response mode of the `vp_token` response type, which this library does not
+    /// support.
+    pub const FRAGMENT: &str = "fragment";
+}
+
+/// Values of the `response_type` parameter.
+pub mod response_type {
+    pub const VP_TOKEN: &str = "vp_token";
+    pub const VP_TOKEN_ID_TOKEN: &str = "vp_token id_token";
+}
+
+/// Error codes of Authorization Error Responses.
+pub mod error_code {
+    pub const INVALID_REQUEST: &str = "invalid_request";
+    /// See [RFC 9101 §6.2](https://www.rfc-editor.org/rfc/rfc9101.html#section-6.2).
+    pub const INVALID_REQUEST_OBJECT: &str = "invalid_request_object";
+    pub const ACCESS_DENIED: &str = "access_denied";
+    pub const UNSUPPORTED_RESPONSE_TYPE: &str = "unsupported_response_type";
+    pub const VP_FORMATS_NOT_SUPPORTED: &str = "vp_formats_not_supported";
+}
+
+/// Values of the `typ` header of JWTs.
+pub mod typ {
+    /// A Request Object, see [RFC 9101 §4](https://www.rfc-editor.org/rfc/rfc9101.html#section-4).
+    pub const REQUEST_OBJECT: &str = "oauth-authz-req+jwt";
+    /// A Verifier Attestation JWT.
+    pub const VERIFIER_ATTESTATION: &str = "verifier-attestation+jwt";
+}
+
+/// Media types of the requests and responses exchanged between wallets and verifiers.
+pub mod media_type {
+    pub const JSON: &str = "application/json";
+    /// A Request Object retrieved by reference.
+    pub const REQUEST_OBJECT: &str = "application/oauth-authz-req+jwt";
+    /// Authorization Responses and Error Responses, and the metadata that wallets post when
+    /// retrieving a Request Object.
+    pub const FORM_URLENCODED: &str = "application/x-www-form-urlencoded";
+}
+
+/// Suffixes of well-known URIs, see [RFC 8615](https://www.rfc-editor.org/rfc/rfc8615.html).
+pub mod well_known {
+    /// OAuth 2.0 Authorization Server Metadata, the metadata of wallets, see
+    /// [RFC 8414](https://www.rfc-editor.org/rfc/rfc8414.html#section-3).
+    pub const OAUTH_AUTHORIZATION_SERVER: &str = "oauth-authorization-server";
+}
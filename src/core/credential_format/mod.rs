@@ -148,6 +148,35 @@ impl ClaimFormatPayload {
             proof_types.push(proof_type);
         }
     }
+
+    /// Whether `alg` (or proof type) is listed.
+    ///
+    /// Other payloads are searched for lists of algorithms, such as the `alg`, `kb_jwt_alg` or
+    /// `sd-jwt_alg_values` properties. Payloads without any list of algorithms support any
+    /// algorithm.
+    pub fn supports_alg(&self, alg: &str) -> bool {
+        match self {
+            Self::Alg(algs) | Self::AlgValuesSupported(algs) | Self::ProofType(algs) => {
+                algs.iter().any(|a| a == alg)
+            }
+            Self::Json(serde_json::Value::Object(properties)) => {
+                let mut lists = properties
+                    .iter()
+                    .filter(|(key, _)| {
+                        key.as_str() == "alg"
+                            || key.as_str() == "proof_type"
+                            || key.ends_with("_alg")
+                            || key.ends_with("alg_values")
+                            || key.ends_with("alg_values_supported")
+                    })
+                    .filter_map(|(_, value)| value.as_array())
+                    .peekable();
+                lists.peek().is_none()
+                    || lists.any(|algs| algs.iter().any(|a| a.as_str() == Some(alg)))
+            }
+            Self::Json(_) => true,
+        }
+    }
 }
 
 /// The claim format designation type is used in the input description object to specify the format of the claim.
@@ -301,5 +330,14 @@ mod tests {
                 "com.example.custom_vc".to_string()
             ))
         );
+
+        let jwt_vc = &claim_format_map[&ClaimFormatDesignation::JwtVc];
+        assert!(jwt_vc.supports_alg("EdDSA"));
+        assert!(jwt_vc.supports_alg("JsonWebSignature2020"));
+        assert!(!jwt_vc.supports_alg("ES384"));
+        let sd_jwt_vc = &claim_format_map[&ClaimFormatDesignation::Other("sd_jwt_vc".into())];
+        assert!(sd_jwt_vc.supports_alg("ES384"));
+        assert!(!sd_jwt_vc.supports_alg("EdDSA"));
+        assert!(ClaimFormatPayload::Json(json!({})).supports_alg("ES256"));
     }
 }
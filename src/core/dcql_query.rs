@@ -0,0 +1,464 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{bail, Context, Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+
+use crate::utils::{OneOrMany, OneOrManyRef};
+
+use super::{object::TypedParameter, response::parameters::VpTokenItem};
+
+/// A Digital Credentials Query Language (DCQL) query, the `dcql_query` parameter of an
+/// Authorization Request.
+///
+/// > A DCQL query is a JSON-encoded query that allows the Verifier to request presentations that
+/// > match the query.
+///
+/// Unlike a presentation definition, the wallet responds with a `vp_token` that maps the `id` of
+/// each [DcqlCredentialQuery] to its presentations (see [DcqlVpToken]), without a presentation
+/// submission.
+///
+/// See: [OpenID.VP#section-6](https://openid.net/specs/openid-4-verifiable-presentations-1_0.html#name-digital-credentials-query-l)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DcqlQuery {
+    credentials: Vec<DcqlCredentialQuery>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    credential_sets: Option<Vec<DcqlCredentialSetQuery>>,
+}
+
+/// A request for presentations of a single credential, in a [DcqlQuery].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DcqlCredentialQuery {
+    pub id: String,
+    pub format: String,
+    /// Whether several presentations may be returned for this query.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub multiple: bool,
+    /// Format specific constraints, e.g. `vct_values` or `doctype_value`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Map<String, Json>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claims: Option<Vec<DcqlClaimsQuery>>,
+    /// Combinations of claim `id`s, in order of preference, that satisfy the query.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claim_sets: Option<Vec<Vec<String>>>,
+}
+
+/// A claim requested in a [DcqlCredentialQuery].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DcqlClaimsQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The path to the claim: strings select object keys, integers select array elements and
+    /// `null` selects every array element.
+    pub path: Vec<Json>,
+    /// The values that the claim is expected to have.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<Json>>,
+}
+
+/// Combinations of [DcqlCredentialQuery]s that satisfy a [DcqlQuery].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DcqlCredentialSetQuery {
+    /// Each option is a list of credential query `id`s that together satisfy the set.
+    pub options: Vec<Vec<String>>,
+    #[serde(default = "default_required")]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<Json>,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+impl DcqlQuery {
+    pub fn new(credentials: Vec<DcqlCredentialQuery>) -> Self {
+        Self {
+            credentials,
+            credential_sets: None,
+        }
+    }
+
+    pub fn with_credential_sets(mut self, credential_sets: Vec<DcqlCredentialSetQuery>) -> Self {
+        self.credential_sets = Some(credential_sets);
+        self
+    }
+
+    pub fn credentials(&self) -> &[DcqlCredentialQuery] {
+        &self.credentials
+    }
+
+    pub fn credential_sets(&self) -> Option<&[DcqlCredentialSetQuery]> {
+        self.credential_sets.as_deref()
+    }
+
+    /// The credential query with the given `id`.
+    pub fn credential(&self, id: &str) -> Option<&DcqlCredentialQuery> {
+        self.credentials
+            .iter()
+            .find(|credential| credential.id == id)
+    }
+
+    /// Check that the query is well-formed: identifiers are unique and only use alphanumeric
+    /// characters, `_` and `-`, claim paths are not empty, and claim sets and credential sets only
+    /// reference identifiers defined in the query.
+    pub fn validate(&self) -> Result<()> {
+        if self.credentials.is_empty() {
+            bail!("a DCQL query must request at least one credential")
+        }
+        let mut ids = BTreeSet::new();
+        for credential in &self.credentials {
+            check_identifier(&credential.id)?;
+            if !ids.insert(credential.id.as_str()) {
+                bail!("duplicate credential query id '{}'", credential.id)
+            }
+            credential
+                .validate()
+                .with_context(|| format!("invalid credential query '{}'", credential.id))?;
+        }
+        for set in self.credential_sets.iter().flatten() {
+            if set.options.is_empty() {
+                bail!("a credential set query must have at least one option")
+            }
+            for id in set.options.iter().flatten() {
+                if !ids.contains(id.as_str()) {
+                    bail!("credential set query references unknown credential query '{id}'")
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a DCQL-shaped `vp_token` answers this query: every entry belongs to a credential
+    /// query, only queries with `multiple` have several presentations, and the required credential
+    /// sets (or, without credential sets, every credential query) are satisfied.
+    pub fn match_vp_token(&self, vp_token: &DcqlVpToken) -> Result<()> {
+        for (id, presentations) in vp_token.iter() {
+            let Some(credential) = self.credential(id) else {
+                bail!("the vp_token contains presentations for an unknown credential query '{id}'")
+            };
+            if presentations.is_empty() {
+                bail!("the vp_token contains no presentations for credential query '{id}'")
+            }
+            if presentations.len() > 1 && !credential.multiple {
+                bail!("the vp_token contains several presentations for credential query '{id}'")
+            }
+        }
+        let presented = |option: &Vec<String>| option.iter().all(|id| vp_token.contains(id));
+        match &self.credential_sets {
+            None => {
+                if let Some(missing) = self
+                    .credentials
+                    .iter()
+                    .find(|credential| !vp_token.contains(&credential.id))
+                {
+                    bail!(
+                        "the vp_token does not contain a presentation for credential query '{}'",
+                        missing.id
+                    )
+                }
+            }
+            Some(sets) => {
+                for (index, set) in sets.iter().enumerate() {
+                    if set.required && !set.options.iter().any(presented) {
+                        bail!("the vp_token does not satisfy the required credential set {index}")
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DcqlCredentialQuery {
+    pub fn new(id: String, format: String) -> Self {
+        Self {
+            id,
+            format,
+            multiple: false,
+            meta: None,
+            claims: None,
+            claim_sets: None,
+        }
+    }
+
+    pub fn with_claims(mut self, claims: Vec<DcqlClaimsQuery>) -> Self {
+        self.claims = Some(claims);
+        self
+    }
+
+    pub fn with_meta(mut self, meta: Map<String, Json>) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        let mut claim_ids = BTreeSet::new();
+        for claim in self.claims.iter().flatten() {
+            if claim.path.is_empty() {
+                bail!("claim paths cannot be empty")
+            }
+            if let Some(element) = claim
+                .path
+                .iter()
+                .find(|element| !(element.is_string() || element.is_u64() || element.is_null()))
+            {
+                bail!("invalid claim path element: {element}")
+            }
+            if let Some(id) = &claim.id {
+                check_identifier(id)?;
+                if !claim_ids.insert(id.as_str()) {
+                    bail!("duplicate claim id '{id}'")
+                }
+            }
+        }
+        if let Some(claim_sets) = &self.claim_sets {
+            if self.claims.is_none() {
+                bail!("'claim_sets' cannot be used without 'claims'")
+            }
+            for id in claim_sets.iter().flatten() {
+                if !claim_ids.contains(id.as_str()) {
+                    bail!("claim set references unknown claim '{id}'")
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DcqlClaimsQuery {
+    pub fn new(path: Vec<Json>) -> Self {
+        Self {
+            id: None,
+            path,
+            values: None,
+        }
+    }
+}
+
+fn check_identifier(id: &str) -> Result<()> {
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        bail!("invalid identifier '{id}': must be non-empty and only contain alphanumeric characters, '_' and '-'")
+    }
+    Ok(())
+}
+
+impl TypedParameter for DcqlQuery {
+    const KEY: &'static str = "dcql_query";
+
+    fn try_from_ref(value: &Json) -> Result<Self> {
+        // The query may be passed as a JSON string in a URL encoded request.
+        match value {
+            Json::String(s) => Ok(serde_json::from_str(s)?),
+            value => Ok(Self::deserialize(value)?),
+        }
+    }
+}
+
+impl TryFrom<Json> for DcqlQuery {
+    type Error = Error;
+
+    fn try_from(value: Json) -> Result<Self, Self::Error> {
+        // The query may be passed as a JSON string in a URL encoded request.
+        let query: Self = match value {
+            Json::String(s) => serde_json::from_str(&s)?,
+            value => serde_json::from_value(value)?,
+        };
+        Ok(query)
+    }
+}
+
+impl From<DcqlQuery> for Json {
+    fn from(value: DcqlQuery) -> Self {
+        serde_json::to_value(value)
+            // SAFETY: a DCQL query has a valid JSON representation by definition.
+            .unwrap()
+    }
+}
+
+/// The `vp_token` of a response to a request with a [DcqlQuery], mapping the `id` of each
+/// credential query to its presentations.
+///
+/// The [Debug] output only lists the credential query ids.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct DcqlVpToken(pub BTreeMap<String, Vec<VpTokenItem>>);
+
+impl DcqlVpToken {
+    /// The presentations for a credential query.
+    pub fn get(&self, id: &str) -> Option<&[VpTokenItem]> {
+        self.0.get(id).map(Vec::as_slice)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.0.contains_key(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[VpTokenItem])> {
+        self.0
+            .iter()
+            .map(|(id, presentations)| (id.as_str(), presentations.as_slice()))
+    }
+
+    /// Every presentation, in credential query order.
+    pub fn presentations(&self) -> impl Iterator<Item = &VpTokenItem> {
+        self.0.values().flatten()
+    }
+}
+
+impl std::fmt::Debug for DcqlVpToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("DcqlVpToken")
+            .field(&self.0.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl TypedParameter for DcqlVpToken {
+    const KEY: &'static str = "vp_token";
+}
+
+impl TryFrom<Json> for DcqlVpToken {
+    type Error = Error;
+
+    fn try_from(value: Json) -> Result<Self, Self::Error> {
+        let Json::Object(object) = value else {
+            bail!("a DCQL vp_token must be a JSON object")
+        };
+        object
+            .into_iter()
+            .map(|(id, presentations)| {
+                let presentations: OneOrMany<VpTokenItem> =
+                    serde_json::from_value(presentations)
+                        .with_context(|| format!("invalid presentations for '{id}'"))?;
+                Ok((id, presentations.into_vec()))
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+}
+
+impl From<DcqlVpToken> for Json {
+    fn from(value: DcqlVpToken) -> Self {
+        Json::Object(
+            value
+                .0
+                .into_iter()
+                .map(|(id, presentations)| {
+                    (
+                        id,
+                        serde_json::to_value(OneOrManyRef::from_slice(&presentations))
+                            // SAFETY: presentations have a valid JSON representation by definition.
+                            .unwrap(),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn query() -> DcqlQuery {
+        serde_json::from_value(json!({
+            "credentials": [
+                {
+                    "id": "pid",
+                    "format": "dc+sd-jwt",
+                    "meta": { "vct_values": ["https://example.com/pid"] },
+                    "claims": [
+                        { "id": "given_name", "path": ["given_name"] },
+                        { "id": "address", "path": ["address", "country"] }
+                    ],
+                    "claim_sets": [["given_name", "address"], ["given_name"]]
+                },
+                { "id": "mdl", "format": "mso_mdoc" },
+                { "id": "diploma", "format": "jwt_vc_json", "multiple": true }
+            ],
+            "credential_sets": [
+                { "options": [["pid"], ["mdl"]] },
+                { "options": [["diploma"]], "required": false }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn validate() {
+        query().validate().unwrap();
+
+        let mut invalid = query();
+        invalid.credentials[1].id = "pid".into();
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = query();
+        invalid.credentials[0].claim_sets = Some(vec![vec!["unknown".into()]]);
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = query();
+        invalid.credentials[0].claims.as_mut().unwrap()[0].path = vec![];
+        assert!(invalid.validate().is_err());
+
+        let invalid = query().with_credential_sets(vec![DcqlCredentialSetQuery {
+            options: vec![vec!["unknown".into()]],
+            required: true,
+            purpose: None,
+        }]);
+        assert!(invalid.validate().is_err());
+
+        assert!(DcqlQuery::new(vec![DcqlCredentialQuery::new(
+            "invalid id".into(),
+            "mso_mdoc".into()
+        )])
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn match_vp_token() {
+        let query = query();
+        let vp_token = |value: Json| DcqlVpToken::try_from(value).unwrap();
+
+        query
+            .match_vp_token(&vp_token(json!({ "mdl": "mdoc" })))
+            .unwrap();
+        query
+            .match_vp_token(&vp_token(json!({ "pid": "sd-jwt", "diploma": ["a", "b"] })))
+            .unwrap();
+
+        // No required credential set option.
+        assert!(query
+            .match_vp_token(&vp_token(json!({ "diploma": "a" })))
+            .is_err());
+        // Unknown credential query.
+        assert!(query
+            .match_vp_token(&vp_token(json!({ "mdl": "mdoc", "other": "a" })))
+            .is_err());
+        // Several presentations without `multiple`.
+        assert!(query
+            .match_vp_token(&vp_token(json!({ "mdl": ["a", "b"] })))
+            .is_err());
+
+        // Without credential sets, every credential is required.
+        let query = DcqlQuery::new(query.credentials);
+        assert!(query
+            .match_vp_token(&vp_token(json!({ "mdl": "mdoc" })))
+            .is_err());
+
+        let token = vp_token(json!({ "mdl": "mdoc", "diploma": ["a", { "b": 1 }] }));
+        assert_eq!(
+            Json::from(token.clone()),
+            json!({ "mdl": "mdoc", "diploma": ["a", { "b": 1 }] })
+        );
+        assert_eq!(token.presentations().count(), 3);
+        assert!(!format!("{token:?}").contains("mdoc\""));
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+
+use anyhow::{bail, Error, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    authorization_request::parameters::{RequestUriMethod, TransactionData},
+    dcql_query::DcqlQuery,
+    object::{ParsingErrorContext, UntypedObject},
+    response::AuthorizationResponse,
+};
+
+/// A draft of the OpenID4VP specification that a verifier pins for the wallets of a client or a
+/// session, e.g. because they have not been updated yet.
+///
+/// Requests of a pinned session are built for the draft: parameters that the draft does not
+/// define are either dropped, when ignoring them is harmless, or rejected. Responses are
+/// interpreted as the draft defines them. Unpinned sessions use everything this library
+/// supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+#[non_exhaustive]
+pub enum Draft {
+    /// Draft 20: the `client_id_scheme` parameter and presentation definitions, without
+    /// `request_uri_method`, `transaction_data` or DCQL queries.
+    Draft20,
+    /// Draft 21: draft 20 with `request_uri_method` and `transaction_data`, without DCQL queries.
+    Draft21,
+}
+
+impl Draft {
+    /// The identifier of the draft, e.g. `draft-20`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Draft20 => "draft-20",
+            Self::Draft21 => "draft-21",
+        }
+    }
+
+    /// Whether requests may carry a `dcql_query`, and responses a DCQL-shaped `vp_token`.
+    pub fn supports_dcql(&self) -> bool {
+        false
+    }
+
+    /// Whether requests may carry `request_uri_method`, for wallets to POST their metadata.
+    pub fn supports_request_uri_method(&self) -> bool {
+        *self >= Self::Draft21
+    }
+
+    /// Whether requests may carry `transaction_data`, to be bound in holder proofs.
+    pub fn supports_transaction_data(&self) -> bool {
+        *self >= Self::Draft21
+    }
+
+    /// Adapt the parameters of a request to the draft.
+    ///
+    /// A `request_uri_method` of `get` is dropped from draft 20 requests, as it is what wallets do
+    /// anyway. Parameters that would change the meaning of the request are rejected.
+    pub fn adapt_request(&self, request_parameters: &mut UntypedObject) -> Result<()> {
+        if !self.supports_dcql() && request_parameters.contains::<DcqlQuery>() {
+            bail!("{self} does not support DCQL queries, use a presentation definition")
+        }
+        if !self.supports_transaction_data() && request_parameters.contains::<TransactionData>() {
+            bail!("{self} does not support 'transaction_data'")
+        }
+        if !self.supports_request_uri_method() {
+            if let Some(method) = request_parameters.get::<RequestUriMethod>() {
+                if method.parsing_error()?.0 != "get" {
+                    bail!("{self} does not support 'request_uri_method', wallets retrieve requests with GET")
+                }
+                let _ = request_parameters.remove::<RequestUriMethod>();
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a response is shaped as the draft defines it.
+    ///
+    /// JWT responses are skipped, as their parameters are only available once they have been
+    /// verified or decrypted.
+    pub fn check_response(&self, authorization_response: &AuthorizationResponse) -> Result<()> {
+        match authorization_response {
+            AuthorizationResponse::Dcql(_) if !self.supports_dcql() => {
+                bail!(
+                    "{self} responses carry a presentation submission, not a DCQL-shaped vp_token"
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Draft {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<String> for Draft {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        match value.as_str() {
+            "draft-20" => Ok(Self::Draft20),
+            "draft-21" => Ok(Self::Draft21),
+            _ => bail!("unsupported OpenID4VP draft '{value}'"),
+        }
+    }
+}
+
+impl From<Draft> for String {
+    fn from(value: Draft) -> Self {
+        value.as_str().to_owned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::core::object::TypedParameter;
+
+    use super::*;
+
+    fn request(parameters: serde_json::Value) -> UntypedObject {
+        serde_json::from_value(parameters).unwrap()
+    }
+
+    #[test]
+    fn adapt_request() {
+        let mut parameters = request(json!({ "request_uri_method": "get" }));
+        Draft::Draft20.adapt_request(&mut parameters).unwrap();
+        assert!(!parameters.0.contains_key(RequestUriMethod::KEY));
+
+        let mut parameters = request(json!({ "request_uri_method": "post" }));
+        assert!(Draft::Draft20.adapt_request(&mut parameters).is_err());
+        Draft::Draft21.adapt_request(&mut parameters).unwrap();
+        assert!(parameters.0.contains_key(RequestUriMethod::KEY));
+
+        let mut parameters = request(json!({ "transaction_data": ["e30"] }));
+        assert!(Draft::Draft20.adapt_request(&mut parameters).is_err());
+        Draft::Draft21.adapt_request(&mut parameters).unwrap();
+
+        let mut parameters = request(json!({
+            "dcql_query": { "credentials": [{ "id": "pid", "format": "dc+sd-jwt" }] }
+        }));
+        assert!(Draft::Draft21.adapt_request(&mut parameters).is_err());
+    }
+
+    #[test]
+    fn serialization() {
+        assert_eq!(json!(Draft::Draft20), json!("draft-20"));
+        assert_eq!(
+            serde_json::from_value::<Draft>(json!("draft-21")).unwrap(),
+            Draft::Draft21
+        );
+        assert!(serde_json::from_value::<Draft>(json!("draft-99")).is_err());
+    }
+}
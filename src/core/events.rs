@@ -0,0 +1,86 @@
+use std::fmt::Debug;
+
+use uuid::Uuid;
+
+use super::authorization_request::AuthorizationRequestObject;
+
+/// The party of a presentation flow that emitted a [LifecycleEvent].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Party {
+    Verifier,
+    Wallet,
+}
+
+/// What happened in a presentation flow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LifecycleEventKind {
+    /// The verifier created a session and built its request.
+    RequestCreated,
+    /// The wallet retrieved the request of a session by reference (emitted by the verifier), or
+    /// parsed and validated a request (emitted by the wallet).
+    RequestRetrieved,
+    /// The wallet submitted a response to the verifier.
+    ResponseSubmitted,
+    /// The verifier received a response for a session.
+    ResponseReceived,
+    /// The verifier accepted the response of a session.
+    VerificationSucceeded,
+    /// The verifier rejected the response of a session, or the wallet rejected a request.
+    VerificationFailed {
+        /// Why, from the outermost to the innermost cause.
+        reasons: Vec<String>,
+    },
+}
+
+/// An event of a presentation flow, emitted by the [Verifier](crate::verifier::Verifier) or a
+/// [Wallet](crate::wallet::Wallet) to an [EventSubscriber].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifecycleEvent {
+    pub party: Party,
+    pub kind: LifecycleEventKind,
+    /// The verifier's session, for events emitted by the verifier.
+    pub session: Option<Uuid>,
+    /// The nonce of the request, if known, which correlates the events of both parties.
+    pub nonce: Option<String>,
+}
+
+impl LifecycleEvent {
+    /// An event of a verifier session.
+    pub fn verifier(
+        kind: LifecycleEventKind,
+        session: Uuid,
+        request: &AuthorizationRequestObject,
+    ) -> Self {
+        Self {
+            party: Party::Verifier,
+            kind,
+            session: Some(session),
+            nonce: Some(request.nonce().as_str().to_owned()),
+        }
+    }
+
+    /// An event of a wallet handling `request`, if it has been parsed.
+    pub fn wallet(kind: LifecycleEventKind, request: Option<&AuthorizationRequestObject>) -> Self {
+        Self {
+            party: Party::Wallet,
+            kind,
+            session: None,
+            nonce: request.map(|request| request.nonce().as_str().to_owned()),
+        }
+    }
+
+    /// A [LifecycleEventKind::VerificationFailed] kind, with the causes of `error`.
+    pub fn failure(error: &anyhow::Error) -> LifecycleEventKind {
+        LifecycleEventKind::VerificationFailed {
+            reasons: error.chain().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+/// Receives the [LifecycleEvent]s of the verifier and wallet flows, as a single point for logging,
+/// tracing or auditing.
+///
+/// Events are delivered inline, so implementations should not block.
+pub trait EventSubscriber: Debug + Send + Sync {
+    fn on_event(&self, event: &LifecycleEvent);
+}
@@ -1,14 +1,22 @@
-use super::{credential_format::*, presentation_submission::*};
+#[cfg(feature = "core")]
+use super::presentation_submission::*;
+use super::{
+    credential_format::*,
+    object::fields::{check_field, check_items, check_keys, KnownFields},
+};
 use crate::utils::NonEmptyVec;
 
 use std::collections::HashSet;
 use std::sync::Arc;
 
-use anyhow::{bail, Context, Result};
+use anyhow::Result;
+#[cfg(feature = "core")]
+use anyhow::{bail, Context};
 use jsonschema::{JSONSchema, ValidationError};
 use serde::{Deserialize, Serialize};
-use ssi::claims::jwt::VerifiablePresentation;
-use ssi::dids::ssi_json_ld::syntax::from_value;
+use serde_json::Value as Json;
+#[cfg(feature = "core")]
+use ssi::{claims::jwt::VerifiablePresentation, dids::ssi_json_ld::syntax::from_value};
 
 /// A GroupId represents a unique identifier for a group of Input Descriptors.
 ///
@@ -174,6 +182,7 @@ impl InputDescriptor {
     }
 
     /// Validate the input descriptor against the verifiable presentation and the descriptor map.
+    #[cfg(feature = "core")]
     pub fn validate_verifiable_presentation(
         &self,
         verifiable_presentation: &VerifiablePresentation,
@@ -274,7 +283,36 @@ impl InputDescriptor {
         Ok(())
     }
 
-    /// Return the humanly readable requested fields of the input descriptor.
+    /// Check whether a credential, in the given format and represented by its JSON claims, can
+    /// satisfy this input descriptor.
+    ///
+    /// The credential format must be one of the formats of the input descriptor (if any are
+    /// specified), and every required constraint field must resolve to a value that passes the
+    /// field's filter.
+    pub fn is_credential_match(
+        &self,
+        format: &ClaimFormatDesignation,
+        credential: &serde_json::Value,
+    ) -> bool {
+        if !self.format.is_empty() && !self.format.contains_key(format) {
+            return false;
+        }
+
+        self.constraints.fields().iter().all(|field| {
+            let mut selector = jsonpath_lib::selector(credential);
+            let matched = field.path().iter().any(|path| {
+                selector(path).is_ok_and(|elements| {
+                    elements.iter().any(|element| {
+                        field
+                            .validator()
+                            .is_none_or(|validator| validator.is_valid(element))
+                    })
+                })
+            });
+            matched || !field.is_required()
+        })
+    }
+
     pub fn requested_fields(&self) -> Vec<String> {
         self.constraints()
             .fields()
@@ -318,6 +356,16 @@ pub struct CredentialTypesRequestedFields {
     requested_fields: Vec<String>,
 }
 
+impl KnownFields for InputDescriptor {
+    /// The fields of formats are not checked, as they are extensible.
+    fn collect_unknown_fields(value: &Json, path: &str, unknown: &mut Vec<String>) {
+        let known = ["id", "constraints", "name", "purpose", "format", "group"];
+        if let Some(object) = check_keys(value, path, &known, unknown) {
+            check_field::<Constraints>(object, path, "constraints", unknown);
+        }
+    }
+}
+
 impl CredentialTypesRequestedFields {
     /// Return the input descriptor ID.
     pub fn input_descriptor_id(&self) -> &str {
@@ -410,6 +458,14 @@ pub struct ConstraintsFieldValidator {
     compiled: Arc<JSONSchema>,
 }
 
+impl KnownFields for Constraints {
+    fn collect_unknown_fields(value: &Json, path: &str, unknown: &mut Vec<String>) {
+        if let Some(object) = check_keys(value, path, &["fields", "limit_disclosure"], unknown) {
+            check_items::<ConstraintsField>(object, path, "fields", unknown);
+        }
+    }
+}
+
 impl ConstraintsFieldValidator {
     pub fn validator(&self) -> &Arc<JSONSchema> {
         &self.compiled
@@ -494,6 +550,23 @@ pub struct ConstraintsField {
 
 pub type ConstraintsFields = Vec<ConstraintsField>;
 
+impl KnownFields for ConstraintsField {
+    /// The `filter` is a JSON schema, whose fields are not checked.
+    fn collect_unknown_fields(value: &Json, path: &str, unknown: &mut Vec<String>) {
+        let known = [
+            "path",
+            "id",
+            "purpose",
+            "name",
+            "predicate",
+            "filter",
+            "optional",
+            "intent_to_retain",
+        ];
+        check_keys(value, path, &known, unknown);
+    }
+}
+
 impl From<NonEmptyVec<JsonPath>> for ConstraintsField {
     fn from(path: NonEmptyVec<JsonPath>) -> Self {
         Self {
@@ -586,7 +659,8 @@ impl ConstraintsField {
     ///
     /// If present its value MUST be a JSON Schema descriptor used to filter against
     /// the values returned from evaluation of the JSONPath string expressions in the path array.
-    pub fn set_filter(mut self, filter: &serde_json::Value) -> Result<Self, ValidationError> {
+    #[allow(clippy::result_large_err)]
+    pub fn set_filter(mut self, filter: &serde_json::Value) -> Result<Self, ValidationError<'_>> {
         self.filter = Some(ConstraintsFieldValidator::try_from(filter)?);
         Ok(self)
     }
@@ -680,7 +754,7 @@ impl ConstraintsField {
             // TODO: Cannot use the field path as a unique property, it may be associated to different
             // credential types.
             // NOTE: Include the namespace for uniqueness of the requested field type.
-            .filter_map(|path| path.split(&['-', '.', ':', '@'][..]).last())
+            .filter_map(|path| path.split(&['-', '.', ':', '@'][..]).next_back())
             .map(|path| {
                 path.chars()
                     .fold(String::new(), |mut acc, c| {
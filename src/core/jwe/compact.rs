@@ -0,0 +1,334 @@
+use aes::{Aes128, Aes192, Aes256};
+use aes_gcm::{
+    aead::{consts::U12, Aead, Payload},
+    AesGcm, KeyInit,
+};
+use anyhow::{bail, Context, Result};
+use base64::prelude::*;
+use cbc::cipher::{block_padding::Pkcs7, BlockCipher, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use hmac::{Hmac, Mac};
+use rand::{CryptoRng, RngCore};
+use serde_json::{Map, Value as Json};
+use sha2::{Sha256, Sha384, Sha512};
+
+pub use super::ECDH_ES;
+use super::{ecdh_es, ContentEncryptionAlgorithm, ResponseEncryption};
+
+type Jwk = Map<String, Json>;
+
+/// Encrypt a payload to the verifier as a JWE in compact serialization, using ECDH-ES in Direct
+/// Key Agreement mode.
+///
+/// `apu` and `apv` are the (decoded) Agreement PartyUInfo and PartyVInfo, and are included in the
+/// protected header when not empty. The `kid` of the verifier's key is included if present.
+pub fn encrypt<R: RngCore + CryptoRng>(
+    encryption: &ResponseEncryption,
+    plaintext: &[u8],
+    apu: &[u8],
+    apv: &[u8],
+    rng: &mut R,
+) -> Result<String> {
+    if encryption.alg != ECDH_ES {
+        bail!("unsupported key management algorithm '{}'", encryption.alg)
+    }
+
+    let key = ecdh_es::agree(&encryption.jwk, encryption.enc, apu, apv, rng)?;
+
+    let mut header = Map::new();
+    header.insert("alg".into(), ECDH_ES.into());
+    header.insert("enc".into(), encryption.enc.as_str().into());
+    header.insert("epk".into(), Json::Object(key.epk));
+    if let Some(kid) = encryption.jwk.get("kid") {
+        header.insert("kid".into(), kid.clone());
+    }
+    if !apu.is_empty() {
+        header.insert("apu".into(), BASE64_URL_SAFE_NO_PAD.encode(apu).into());
+    }
+    if !apv.is_empty() {
+        header.insert("apv".into(), BASE64_URL_SAFE_NO_PAD.encode(apv).into());
+    }
+    let header = BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?);
+
+    let mut iv = vec![0; iv_len(encryption.enc)];
+    rng.fill_bytes(&mut iv);
+    let (ciphertext, tag) = seal(encryption.enc, &key.cek, &iv, header.as_bytes(), plaintext)?;
+
+    Ok([
+        header.as_str(),
+        "",
+        &BASE64_URL_SAFE_NO_PAD.encode(iv),
+        &BASE64_URL_SAFE_NO_PAD.encode(ciphertext),
+        &BASE64_URL_SAFE_NO_PAD.encode(tag),
+    ]
+    .join("."))
+}
+
+/// Decrypt a JWE in compact serialization with the verifier's private key, returning the protected
+/// header and the payload.
+pub fn decrypt(jwe: &str, recipient: &Jwk) -> Result<(Jwk, Vec<u8>)> {
+    let [header_b64, encrypted_key, iv, ciphertext, tag]: [&str; 5] = jwe
+        .split('.')
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("JWE was not in compact serialization"))?;
+
+    let header: Jwk = serde_json::from_slice(&decode(header_b64, "protected header")?)
+        .context("JWE protected header was not a JSON object")?;
+
+    match header.get("alg").and_then(Json::as_str) {
+        Some(ECDH_ES) => {}
+        alg => bail!("unsupported key management algorithm {alg:?}"),
+    }
+    if !encrypted_key.is_empty() {
+        bail!("JWE encrypted key must be empty for '{ECDH_ES}'")
+    }
+    let enc: ContentEncryptionAlgorithm = header
+        .get("enc")
+        .and_then(Json::as_str)
+        .context("JWE protected header is missing 'enc'")?
+        .parse()?;
+    let Some(Json::Object(epk)) = header.get("epk") else {
+        bail!("JWE protected header is missing 'epk'")
+    };
+    let apu = optional_header(&header, "apu")?;
+    let apv = optional_header(&header, "apv")?;
+
+    let cek = ecdh_es::derive(recipient, epk, enc, &apu, &apv)?;
+    let plaintext = open(
+        enc,
+        &cek,
+        &decode(iv, "initialization vector")?,
+        header_b64.as_bytes(),
+        &decode(ciphertext, "ciphertext")?,
+        &decode(tag, "authentication tag")?,
+    )?;
+
+    Ok((header, plaintext))
+}
+
+fn decode(value: &str, what: &str) -> Result<Vec<u8>> {
+    BASE64_URL_SAFE_NO_PAD
+        .decode(value)
+        .with_context(|| format!("JWE {what} was not valid base64url"))
+}
+
+fn optional_header(header: &Jwk, name: &str) -> Result<Vec<u8>> {
+    match header.get(name) {
+        None => Ok(vec![]),
+        Some(Json::String(value)) => decode(value, name),
+        Some(_) => bail!("JWE '{name}' header was not a string"),
+    }
+}
+
+fn iv_len(enc: ContentEncryptionAlgorithm) -> usize {
+    match enc {
+        ContentEncryptionAlgorithm::A128Gcm
+        | ContentEncryptionAlgorithm::A192Gcm
+        | ContentEncryptionAlgorithm::A256Gcm => 12,
+        _ => 16,
+    }
+}
+
+/// Encrypt, returning the ciphertext and the authentication tag.
+fn seal(
+    enc: ContentEncryptionAlgorithm,
+    cek: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    match enc {
+        ContentEncryptionAlgorithm::A128CbcHs256 => {
+            cbc_hs_seal::<Aes128, Hmac<Sha256>>(cek, iv, aad, plaintext)
+        }
+        ContentEncryptionAlgorithm::A192CbcHs384 => {
+            cbc_hs_seal::<Aes192, Hmac<Sha384>>(cek, iv, aad, plaintext)
+        }
+        ContentEncryptionAlgorithm::A256CbcHs512 => {
+            cbc_hs_seal::<Aes256, Hmac<Sha512>>(cek, iv, aad, plaintext)
+        }
+        ContentEncryptionAlgorithm::A128Gcm => gcm_seal::<Aes128>(cek, iv, aad, plaintext),
+        ContentEncryptionAlgorithm::A192Gcm => gcm_seal::<Aes192>(cek, iv, aad, plaintext),
+        ContentEncryptionAlgorithm::A256Gcm => gcm_seal::<Aes256>(cek, iv, aad, plaintext),
+    }
+}
+
+/// Verify the authentication tag and decrypt.
+fn open(
+    enc: ContentEncryptionAlgorithm,
+    cek: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+    tag: &[u8],
+) -> Result<Vec<u8>> {
+    match enc {
+        ContentEncryptionAlgorithm::A128CbcHs256 => {
+            cbc_hs_open::<Aes128, Hmac<Sha256>>(cek, iv, aad, ciphertext, tag)
+        }
+        ContentEncryptionAlgorithm::A192CbcHs384 => {
+            cbc_hs_open::<Aes192, Hmac<Sha384>>(cek, iv, aad, ciphertext, tag)
+        }
+        ContentEncryptionAlgorithm::A256CbcHs512 => {
+            cbc_hs_open::<Aes256, Hmac<Sha512>>(cek, iv, aad, ciphertext, tag)
+        }
+        ContentEncryptionAlgorithm::A128Gcm => gcm_open::<Aes128>(cek, iv, aad, ciphertext, tag),
+        ContentEncryptionAlgorithm::A192Gcm => gcm_open::<Aes192>(cek, iv, aad, ciphertext, tag),
+        ContentEncryptionAlgorithm::A256Gcm => gcm_open::<Aes256>(cek, iv, aad, ciphertext, tag),
+    }
+}
+
+/// AES_CBC_HMAC_SHA2, see: [RFC7518#section-5.2](https://www.rfc-editor.org/rfc/rfc7518#section-5.2)
+fn cbc_hs_seal<C, M>(
+    cek: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>)>
+where
+    C: BlockCipher + BlockEncryptMut,
+    cbc::Encryptor<C>: KeyIvInit,
+    M: Mac + KeyInit,
+{
+    let (mac_key, enc_key) = cek.split_at(cek.len() / 2);
+    let ciphertext = cbc::Encryptor::<C>::new_from_slices(enc_key, iv)
+        .map_err(|_| anyhow::anyhow!("invalid content encryption key or IV length"))?
+        .encrypt_padded_vec_mut::<Pkcs7>(plaintext);
+    let tag = cbc_hs_mac::<M>(mac_key, iv, aad, &ciphertext)?
+        .finalize()
+        .into_bytes()[..mac_key.len()]
+        .to_vec();
+    Ok((ciphertext, tag))
+}
+
+fn cbc_hs_open<C, M>(
+    cek: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+    tag: &[u8],
+) -> Result<Vec<u8>>
+where
+    C: BlockCipher + BlockDecryptMut,
+    cbc::Decryptor<C>: KeyIvInit,
+    M: Mac + KeyInit,
+{
+    let (mac_key, enc_key) = cek.split_at(cek.len() / 2);
+    if tag.len() != mac_key.len() {
+        bail!("JWE authentication tag has an invalid length")
+    }
+    cbc_hs_mac::<M>(mac_key, iv, aad, ciphertext)?
+        .verify_truncated_left(tag)
+        .map_err(|_| anyhow::anyhow!("JWE authentication tag is invalid"))?;
+    cbc::Decryptor::<C>::new_from_slices(enc_key, iv)
+        .map_err(|_| anyhow::anyhow!("invalid content encryption key or IV length"))?
+        .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+        .map_err(|_| anyhow::anyhow!("JWE ciphertext has invalid padding"))
+}
+
+fn cbc_hs_mac<M: Mac + KeyInit>(
+    mac_key: &[u8],
+    iv: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Result<M> {
+    let mut mac = <M as Mac>::new_from_slice(mac_key)
+        .map_err(|_| anyhow::anyhow!("invalid MAC key length"))?;
+    mac.update(aad);
+    mac.update(iv);
+    mac.update(ciphertext);
+    mac.update(&((aad.len() as u64) * 8).to_be_bytes());
+    Ok(mac)
+}
+
+/// AES GCM, see: [RFC7518#section-5.3](https://www.rfc-editor.org/rfc/rfc7518#section-5.3)
+fn gcm_seal<C>(cek: &[u8], iv: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>)>
+where
+    AesGcm<C, U12>: KeyInit + Aead,
+{
+    let mut ciphertext = AesGcm::<C, U12>::new_from_slice(cek)
+        .map_err(|_| anyhow::anyhow!("invalid content encryption key length"))?
+        .encrypt(
+            iv.into(),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("failed to encrypt payload"))?;
+    let tag = ciphertext.split_off(ciphertext.len() - 16);
+    Ok((ciphertext, tag))
+}
+
+fn gcm_open<C>(cek: &[u8], iv: &[u8], aad: &[u8], ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>>
+where
+    AesGcm<C, U12>: KeyInit + Aead,
+{
+    if iv.len() != 12 {
+        bail!("JWE initialization vector has an invalid length")
+    }
+    let msg = [ciphertext, tag].concat();
+    AesGcm::<C, U12>::new_from_slice(cek)
+        .map_err(|_| anyhow::anyhow!("invalid content encryption key length"))?
+        .decrypt(iv.into(), Payload { msg: &msg, aad })
+        .map_err(|_| anyhow::anyhow!("failed to decrypt JWE"))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    const ENCS: [ContentEncryptionAlgorithm; 6] = [
+        ContentEncryptionAlgorithm::A128CbcHs256,
+        ContentEncryptionAlgorithm::A192CbcHs384,
+        ContentEncryptionAlgorithm::A256CbcHs512,
+        ContentEncryptionAlgorithm::A128Gcm,
+        ContentEncryptionAlgorithm::A192Gcm,
+        ContentEncryptionAlgorithm::A256Gcm,
+    ];
+
+    fn keys() -> (Jwk, Jwk) {
+        let secret = p256::SecretKey::random(&mut OsRng);
+        let private = serde_json::from_str(&secret.to_jwk_string()).unwrap();
+        let mut public: Jwk = serde_json::from_str(&secret.public_key().to_jwk_string()).unwrap();
+        public.insert("kid".into(), "enc-key".into());
+        (private, public)
+    }
+
+    #[test]
+    fn round_trip() {
+        let (private, public) = keys();
+        for enc in ENCS {
+            let encryption = ResponseEncryption {
+                alg: ECDH_ES.into(),
+                enc,
+                jwk: public.clone(),
+            };
+            let jwe = encrypt(&encryption, b"payload", b"wallet", b"nonce", &mut OsRng).unwrap();
+            let (header, plaintext) = decrypt(&jwe, &private).unwrap();
+            assert_eq!(plaintext, b"payload", "{enc}");
+            assert_eq!(header["enc"], enc.as_str());
+            assert_eq!(header["kid"], "enc-key");
+        }
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let (private, public) = keys();
+        for enc in ENCS {
+            let encryption = ResponseEncryption {
+                alg: ECDH_ES.into(),
+                enc,
+                jwk: public.clone(),
+            };
+            let jwe = encrypt(&encryption, b"payload", b"", b"", &mut OsRng).unwrap();
+            let mut parts: Vec<String> = jwe.split('.').map(ToOwned::to_owned).collect();
+            let mut ciphertext = BASE64_URL_SAFE_NO_PAD.decode(&parts[3]).unwrap();
+            ciphertext[0] ^= 1;
+            parts[3] = BASE64_URL_SAFE_NO_PAD.encode(ciphertext);
+            assert!(decrypt(&parts.join("."), &private).is_err(), "{enc}");
+        }
+    }
+}
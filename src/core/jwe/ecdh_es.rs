@@ -0,0 +1,336 @@
+use anyhow::{bail, Context, Result};
+use base64::prelude::*;
+use p256::elliptic_curve::JwkEcKey;
+use rand::{CryptoRng, RngCore};
+use serde_json::{Map, Value as Json};
+use sha2::{Digest, Sha256};
+
+use crate::core::{metadata::parameters::verifier::JWKs, object::UntypedObject};
+
+use super::ContentEncryptionAlgorithm;
+
+type Jwk = Map<String, Json>;
+
+/// Curves supported for ECDH-ES key agreement on encrypted responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAgreementCurve {
+    P256,
+    P384,
+    X25519,
+}
+
+impl KeyAgreementCurve {
+    /// Determine the curve of a JWK from its `kty` and `crv` members.
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self> {
+        let kty = jwk.get("kty").and_then(Json::as_str);
+        let crv = jwk.get("crv").and_then(Json::as_str);
+        Ok(match (kty, crv) {
+            (Some("EC"), Some("P-256")) => Self::P256,
+            (Some("EC"), Some("P-384")) => Self::P384,
+            (Some("OKP"), Some("X25519")) => Self::X25519,
+            _ => bail!(
+                "unsupported key agreement key (kty: {kty:?}, crv: {crv:?}), expected P-256, P-384 or X25519"
+            ),
+        })
+    }
+}
+
+/// The result of the wallet-side ECDH-ES key agreement.
+#[derive(Clone)]
+pub struct EcdhEsKey {
+    /// The ephemeral public key, to be included as `epk` in the JWE protected header.
+    pub epk: Jwk,
+    /// The derived content encryption key.
+    pub cek: Vec<u8>,
+}
+
+impl std::fmt::Debug for EcdhEsKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EcdhEsKey")
+            .field("epk", &self.epk)
+            .field("cek", &format_args!("[{} bytes]", self.cek.len()))
+            .finish()
+    }
+}
+
+/// Select the verifier's key for response encryption from the `jwks` in the client metadata.
+///
+/// The first key with a supported curve, and a `use` of `enc` (or no `use`), is selected.
+pub fn select_encryption_jwk(client_metadata: &UntypedObject) -> Result<Jwk> {
+    let jwks = client_metadata
+        .get::<JWKs>()
+        .context("client_metadata does not contain 'jwks'")??;
+    select_jwk(jwks)
+        .context("client_metadata 'jwks' does not contain a supported key agreement key")
+}
+
+/// Select the first key of `jwks` with a supported curve, and a `use` of `enc` (or no `use`).
+pub fn select_jwk(jwks: JWKs) -> Option<Jwk> {
+    jwks.keys
+        .into_iter()
+        .filter(|jwk| {
+            jwk.get("use")
+                .and_then(Json::as_str)
+                .is_none_or(|u| u == "enc")
+        })
+        .find(|jwk| KeyAgreementCurve::from_jwk(jwk).is_ok())
+}
+
+/// Generate a key pair on `curve`, for the verifier to receive encrypted responses, returning the
+/// private JWK.
+pub fn generate<R: RngCore + CryptoRng>(curve: KeyAgreementCurve, rng: &mut R) -> Result<Jwk> {
+    match curve {
+        KeyAgreementCurve::P256 => to_map(&p256::SecretKey::random(rng).to_jwk()),
+        KeyAgreementCurve::P384 => to_map(&p384::SecretKey::random(rng).to_jwk()),
+        KeyAgreementCurve::X25519 => {
+            let secret = x25519_dalek::StaticSecret::random_from_rng(rng);
+            let public = x25519_dalek::PublicKey::from(&secret);
+            let mut jwk = Jwk::new();
+            jwk.insert("kty".into(), "OKP".into());
+            jwk.insert("crv".into(), "X25519".into());
+            jwk.insert(
+                "x".into(),
+                BASE64_URL_SAFE_NO_PAD.encode(public.as_bytes()).into(),
+            );
+            jwk.insert(
+                "d".into(),
+                BASE64_URL_SAFE_NO_PAD.encode(secret.as_bytes()).into(),
+            );
+            Ok(jwk)
+        }
+    }
+}
+
+/// Perform ECDH-ES key agreement with the verifier's public key, generating an ephemeral key pair
+/// on the curve of the verifier's key.
+///
+/// `apu` and `apv` are the decoded `apu` and `apv` JWE header parameters.
+pub fn agree<R: RngCore + CryptoRng>(
+    recipient: &Jwk,
+    enc: ContentEncryptionAlgorithm,
+    apu: &[u8],
+    apv: &[u8],
+    rng: &mut R,
+) -> Result<EcdhEsKey> {
+    let (epk, z) = match KeyAgreementCurve::from_jwk(recipient)? {
+        KeyAgreementCurve::P256 => {
+            let public = p256::PublicKey::from_jwk(&ec_jwk(recipient)?)
+                .context("invalid P-256 public key")?;
+            let secret = p256::ecdh::EphemeralSecret::random(rng);
+            let z = secret.diffie_hellman(&public).raw_secret_bytes().to_vec();
+            (to_map(&secret.public_key().to_jwk())?, z)
+        }
+        KeyAgreementCurve::P384 => {
+            let public = p384::PublicKey::from_jwk(&ec_jwk(recipient)?)
+                .context("invalid P-384 public key")?;
+            let secret = p384::ecdh::EphemeralSecret::random(rng);
+            let z = secret.diffie_hellman(&public).raw_secret_bytes().to_vec();
+            (to_map(&secret.public_key().to_jwk())?, z)
+        }
+        KeyAgreementCurve::X25519 => {
+            let public = x25519_dalek::PublicKey::from(okp_bytes(recipient, "x")?);
+            let secret = x25519_dalek::EphemeralSecret::random_from_rng(rng);
+            let epk = x25519_dalek::PublicKey::from(&secret);
+            let shared = secret.diffie_hellman(&public);
+            // A low-order public key yields a shared secret that does not depend on our key.
+            if !shared.was_contributory() {
+                bail!("the X25519 key agreement key is a low-order point")
+            }
+            let z = shared.as_bytes().to_vec();
+            let mut jwk = Jwk::new();
+            jwk.insert("kty".into(), "OKP".into());
+            jwk.insert("crv".into(), "X25519".into());
+            jwk.insert(
+                "x".into(),
+                BASE64_URL_SAFE_NO_PAD.encode(epk.as_bytes()).into(),
+            );
+            (jwk, z)
+        }
+    };
+
+    Ok(EcdhEsKey {
+        epk,
+        cek: concat_kdf(&z, enc, apu, apv),
+    })
+}
+
+/// Derive the content encryption key from the verifier's private key and the `epk` of a received
+/// JWE.
+pub fn derive(
+    recipient: &Jwk,
+    epk: &Jwk,
+    enc: ContentEncryptionAlgorithm,
+    apu: &[u8],
+    apv: &[u8],
+) -> Result<Vec<u8>> {
+    let curve = KeyAgreementCurve::from_jwk(recipient)?;
+    if KeyAgreementCurve::from_jwk(epk)? != curve {
+        bail!("'epk' is not on the same curve as the recipient key")
+    }
+
+    let z = match curve {
+        KeyAgreementCurve::P256 => {
+            let secret = p256::SecretKey::from_jwk(&ec_jwk(recipient)?)
+                .context("invalid P-256 private key")?;
+            let public = p256::PublicKey::from_jwk(&ec_jwk(epk)?).context("invalid P-256 'epk'")?;
+            p256::ecdh::diffie_hellman(secret.to_nonzero_scalar(), public.as_affine())
+                .raw_secret_bytes()
+                .to_vec()
+        }
+        KeyAgreementCurve::P384 => {
+            let secret = p384::SecretKey::from_jwk(&ec_jwk(recipient)?)
+                .context("invalid P-384 private key")?;
+            let public = p384::PublicKey::from_jwk(&ec_jwk(epk)?).context("invalid P-384 'epk'")?;
+            p384::ecdh::diffie_hellman(secret.to_nonzero_scalar(), public.as_affine())
+                .raw_secret_bytes()
+                .to_vec()
+        }
+        KeyAgreementCurve::X25519 => {
+            let secret = x25519_dalek::StaticSecret::from(okp_bytes(recipient, "d")?);
+            let public = x25519_dalek::PublicKey::from(okp_bytes(epk, "x")?);
+            let shared = secret.diffie_hellman(&public);
+            if !shared.was_contributory() {
+                bail!("the X25519 'epk' is a low-order point")
+            }
+            shared.as_bytes().to_vec()
+        }
+    };
+
+    Ok(concat_kdf(&z, enc, apu, apv))
+}
+
+/// Concat KDF as used by ECDH-ES in Direct Key Agreement mode, where the `AlgorithmID` is the
+/// `enc` value.
+///
+/// See: [RFC7518#section-4.6.2](https://www.rfc-editor.org/rfc/rfc7518#section-4.6.2)
+fn concat_kdf(z: &[u8], enc: ContentEncryptionAlgorithm, apu: &[u8], apv: &[u8]) -> Vec<u8> {
+    let key_len = enc.key_len();
+
+    let mut other_info = Vec::new();
+    for field in [enc.as_str().as_bytes(), apu, apv] {
+        other_info.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        other_info.extend_from_slice(field);
+    }
+    other_info.extend_from_slice(&((key_len * 8) as u32).to_be_bytes());
+
+    let mut key = Vec::with_capacity(key_len);
+    let mut counter: u32 = 1;
+    while key.len() < key_len {
+        let mut hasher = Sha256::new();
+        hasher.update(counter.to_be_bytes());
+        hasher.update(z);
+        hasher.update(&other_info);
+        key.extend_from_slice(&hasher.finalize());
+        counter += 1;
+    }
+    key.truncate(key_len);
+    key
+}
+
+fn ec_jwk(jwk: &Jwk) -> Result<JwkEcKey> {
+    // Other members such as `kid`, `use` or `alg` are not accepted by [JwkEcKey].
+    let key_members = jwk
+        .iter()
+        .filter(|(member, _)| ["kty", "crv", "x", "y", "d"].contains(&member.as_str()))
+        .map(|(member, value)| (member.clone(), value.clone()))
+        .collect();
+    serde_json::from_value(Json::Object(key_members)).context("invalid EC JWK")
+}
+
+fn to_map(jwk: &JwkEcKey) -> Result<Jwk> {
+    match serde_json::to_value(jwk)? {
+        Json::Object(map) => Ok(map),
+        _ => bail!("EC JWK did not serialize to a JSON object"),
+    }
+}
+
+fn okp_bytes(jwk: &Jwk, member: &str) -> Result<[u8; 32]> {
+    let value = jwk
+        .get(member)
+        .and_then(Json::as_str)
+        .with_context(|| format!("OKP JWK is missing '{member}'"))?;
+    BASE64_URL_SAFE_NO_PAD
+        .decode(value)
+        .with_context(|| format!("OKP JWK '{member}' was not valid base64url"))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("OKP JWK '{member}' was not 32 bytes"))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::OsRng;
+    use serde_json::json;
+
+    use super::*;
+
+    fn private_jwk(curve: KeyAgreementCurve) -> Jwk {
+        generate(curve, &mut OsRng).unwrap()
+    }
+
+    fn public_jwk(private: &Jwk) -> Jwk {
+        let mut public = private.clone();
+        public.remove("d");
+        public
+    }
+
+    #[test]
+    fn concat_kdf_rfc7518_appendix_c() {
+        let z = [
+            158, 86, 217, 29, 129, 113, 53, 211, 114, 131, 66, 131, 191, 132, 38, 156, 251, 49,
+            110, 163, 218, 128, 106, 72, 246, 218, 167, 121, 140, 254, 144, 196,
+        ];
+        let cek = concat_kdf(&z, ContentEncryptionAlgorithm::A128Gcm, b"Alice", b"Bob");
+        assert_eq!(BASE64_URL_SAFE_NO_PAD.encode(cek), "VqqN6vgjbSBcIijNcacQGg");
+    }
+
+    #[test]
+    fn round_trip() {
+        for curve in [
+            KeyAgreementCurve::P256,
+            KeyAgreementCurve::P384,
+            KeyAgreementCurve::X25519,
+        ] {
+            let private = private_jwk(curve);
+            let enc = ContentEncryptionAlgorithm::A256Gcm;
+
+            let agreed = agree(&public_jwk(&private), enc, b"apu", b"apv", &mut OsRng).unwrap();
+            assert_eq!(KeyAgreementCurve::from_jwk(&agreed.epk).unwrap(), curve);
+            assert_eq!(agreed.cek.len(), 32);
+
+            let derived = derive(&private, &agreed.epk, enc, b"apu", b"apv").unwrap();
+            assert_eq!(agreed.cek, derived, "{curve:?}");
+        }
+    }
+
+    #[test]
+    fn x25519_low_order_points() {
+        let private = private_jwk(KeyAgreementCurve::X25519);
+        let enc = ContentEncryptionAlgorithm::A256Gcm;
+        let mut low_order = public_jwk(&private);
+        low_order.insert("x".into(), BASE64_URL_SAFE_NO_PAD.encode([0; 32]).into());
+
+        assert!(agree(&low_order, enc, b"apu", b"apv", &mut OsRng).is_err());
+        assert!(derive(&private, &low_order, enc, b"apu", b"apv").is_err());
+    }
+
+    #[test]
+    fn select_key_from_client_metadata() {
+        let p384 = public_jwk(&private_jwk(KeyAgreementCurve::P384));
+        let client_metadata: UntypedObject = serde_json::from_value(json!({
+            "jwks": {
+                "keys": [
+                    { "kty": "EC", "crv": "secp256k1", "use": "enc", "x": "", "y": "" },
+                    { "kty": "OKP", "crv": "X25519", "use": "sig", "x": "" },
+                    p384,
+                ]
+            }
+        }))
+        .unwrap();
+
+        let selected = select_encryption_jwk(&client_metadata).unwrap();
+        assert_eq!(
+            KeyAgreementCurve::from_jwk(&selected).unwrap(),
+            KeyAgreementCurve::P384
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use std::fmt;
+
+use anyhow::{bail, Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+
+use super::{
+    consts::error_code,
+    metadata::parameters::{
+        verifier::AuthorizationEncryptedResponseEnc,
+        wallet::{
+            AuthorizationEncryptionEncValuesSupported, RequestObjectEncryptionEncValuesSupported,
+        },
+    },
+    object::UntypedObject,
+};
+#[cfg(any(feature = "wallet", feature = "verifier"))]
+use super::{
+    metadata::{parameters::verifier::AuthorizationEncryptedResponseAlg, WalletMetadata},
+    object::TypedParameter,
+};
+
+#[cfg(any(feature = "wallet", feature = "verifier"))]
+pub mod compact;
+#[cfg(any(feature = "wallet", feature = "verifier"))]
+pub mod ecdh_es;
+
+/// The only key management algorithm supported for encrypted responses.
+pub const ECDH_ES: &str = "ECDH-ES";
+
+/// JWE content encryption algorithm (`enc`) used for encrypted authorization responses.
+///
+/// See: [RFC7518#section-5.1](https://www.rfc-editor.org/rfc/rfc7518#section-5.1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ContentEncryptionAlgorithm {
+    A128CbcHs256,
+    A192CbcHs384,
+    A256CbcHs512,
+    A128Gcm,
+    A192Gcm,
+    A256Gcm,
+}
+
+impl ContentEncryptionAlgorithm {
+    /// The `enc` used when the verifier specifies `authorization_encrypted_response_alg` but omits
+    /// `authorization_encrypted_response_enc`.
+    ///
+    /// See: [JARM#section-3](https://openid.net/specs/oauth-v2-jarm.html#section-3)
+    pub const DEFAULT: Self = Self::A128CbcHs256;
+
+    /// Every content encryption algorithm, all of which are implemented.
+    pub const ALL: &'static [Self] = &[
+        Self::A128CbcHs256,
+        Self::A192CbcHs384,
+        Self::A256CbcHs512,
+        Self::A128Gcm,
+        Self::A192Gcm,
+        Self::A256Gcm,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::A128CbcHs256 => "A128CBC-HS256",
+            Self::A192CbcHs384 => "A192CBC-HS384",
+            Self::A256CbcHs512 => "A256CBC-HS512",
+            Self::A128Gcm => "A128GCM",
+            Self::A192Gcm => "A192GCM",
+            Self::A256Gcm => "A256GCM",
+        }
+    }
+
+    /// Length in bytes of the content encryption key.
+    pub fn key_len(&self) -> usize {
+        match self {
+            Self::A128Gcm => 16,
+            Self::A192Gcm => 24,
+            Self::A128CbcHs256 | Self::A256Gcm => 32,
+            Self::A192CbcHs384 => 48,
+            Self::A256CbcHs512 => 64,
+        }
+    }
+}
+
+impl fmt::Display for ContentEncryptionAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl TryFrom<String> for ContentEncryptionAlgorithm {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl std::str::FromStr for ContentEncryptionAlgorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "A128CBC-HS256" => Self::A128CbcHs256,
+            "A192CBC-HS384" => Self::A192CbcHs384,
+            "A256CBC-HS512" => Self::A256CbcHs512,
+            "A128GCM" => Self::A128Gcm,
+            "A192GCM" => Self::A192Gcm,
+            "A256GCM" => Self::A256Gcm,
+            _ => bail!("unsupported content encryption algorithm '{s}'"),
+        })
+    }
+}
+
+impl From<ContentEncryptionAlgorithm> for String {
+    fn from(value: ContentEncryptionAlgorithm) -> Self {
+        value.as_str().to_owned()
+    }
+}
+
+impl From<ContentEncryptionAlgorithm> for AuthorizationEncryptedResponseEnc {
+    fn from(value: ContentEncryptionAlgorithm) -> Self {
+        Self(value.into())
+    }
+}
+
+impl FromIterator<ContentEncryptionAlgorithm> for AuthorizationEncryptionEncValuesSupported {
+    fn from_iter<T: IntoIterator<Item = ContentEncryptionAlgorithm>>(iter: T) -> Self {
+        Self(iter.into_iter().map(String::from).collect())
+    }
+}
+
+impl FromIterator<ContentEncryptionAlgorithm> for RequestObjectEncryptionEncValuesSupported {
+    fn from_iter<T: IntoIterator<Item = ContentEncryptionAlgorithm>>(iter: T) -> Self {
+        Self(iter.into_iter().map(String::from).collect())
+    }
+}
+
+/// Select the `enc` to use for an encrypted response.
+///
+/// The verifier's `authorization_encrypted_response_enc` is taken from the client metadata, falling
+/// back to [ContentEncryptionAlgorithm::DEFAULT], and must be one of the `supported` values.
+pub fn negotiate_enc(
+    client_metadata: &UntypedObject,
+    supported: &[ContentEncryptionAlgorithm],
+) -> Result<ContentEncryptionAlgorithm> {
+    let enc = match client_metadata.get::<AuthorizationEncryptedResponseEnc>() {
+        Some(enc) => enc?.0.parse()?,
+        None => ContentEncryptionAlgorithm::DEFAULT,
+    };
+
+    if !supported.contains(&enc) {
+        bail!("unsupported authorization_encrypted_response_enc '{enc}'")
+    }
+
+    Ok(enc)
+}
+
+/// The response encryption parameters agreed between the verifier's client metadata and the
+/// wallet's metadata.
+///
+/// Also describes the encryption of Request Objects to the wallet, see
+/// [negotiate_request_object].
+#[derive(Debug, Clone)]
+pub struct ResponseEncryption {
+    /// The key management algorithm, e.g. `ECDH-ES`.
+    pub alg: String,
+    pub enc: ContentEncryptionAlgorithm,
+    /// The verifier's public key to encrypt the response to.
+    pub jwk: Map<String, Json>,
+}
+
+/// The wallet cannot produce an encrypted response the verifier would accept.
+///
+/// This is detected while validating the request, so that the wallet can respond with an error
+/// (using [EncryptionNotSupported::ERROR_CODE]) rather than failing at submission time.
+#[derive(Debug, Clone)]
+pub struct EncryptionNotSupported(pub String);
+
+impl EncryptionNotSupported {
+    /// The error code to return to the verifier.
+    pub const ERROR_CODE: &'static str = error_code::VP_FORMATS_NOT_SUPPORTED;
+}
+
+impl fmt::Display for EncryptionNotSupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", Self::ERROR_CODE, self.0)
+    }
+}
+
+impl std::error::Error for EncryptionNotSupported {}
+
+/// Negotiate the parameters of an encrypted response.
+///
+/// Returns `None` if the client metadata does not request encrypted responses. Otherwise the
+/// verifier's `alg` and `enc` must be supported by the wallet, and the client metadata must publish
+/// a key on a supported curve. The `alg` must be `ECDH-ES`, the only one implemented by this
+/// library, and when the wallet metadata does not list its supported `enc` values any of
+/// [ContentEncryptionAlgorithm::ALL] is accepted. Failures are [EncryptionNotSupported] errors.
+#[cfg(any(feature = "wallet", feature = "verifier"))]
+pub fn negotiate(
+    client_metadata: &UntypedObject,
+    wallet_metadata: &WalletMetadata,
+) -> Result<Option<ResponseEncryption>> {
+    let Some(alg) = client_metadata.get::<AuthorizationEncryptedResponseAlg>() else {
+        return Ok(None);
+    };
+    let AuthorizationEncryptedResponseAlg(alg) = alg?;
+
+    // This library only implements `ECDH-ES`, whatever else the wallet lists.
+    let supported = alg == ECDH_ES
+        && wallet_metadata
+            .authorization_encryption_alg_values_supported()
+            .is_none_or(|supported_algs| supported_algs.0.contains(&alg));
+    if !supported {
+        bail!(EncryptionNotSupported(format!(
+            "unsupported {} '{alg}'",
+            AuthorizationEncryptedResponseAlg::KEY,
+        )))
+    }
+
+    let supported_encs = match wallet_metadata.authorization_encryption_enc_values_supported() {
+        Some(supported_encs) => supported_encs
+            .0
+            .iter()
+            .filter_map(|enc| enc.parse().ok())
+            .collect::<Vec<ContentEncryptionAlgorithm>>(),
+        None => ContentEncryptionAlgorithm::ALL.to_vec(),
+    };
+    let enc = negotiate_enc(client_metadata, &supported_encs)
+        .map_err(|e| EncryptionNotSupported(e.to_string()))?;
+
+    let jwk = ecdh_es::select_encryption_jwk(client_metadata)
+        .map_err(|e| EncryptionNotSupported(e.to_string()))?;
+
+    Ok(Some(ResponseEncryption { alg, enc, jwk }))
+}
+
+/// Negotiate the encryption of a Request Object to the wallet.
+///
+/// Returns `None` if the wallet does not accept encrypted Request Objects, or if it supports none
+/// of the algorithms or curves of this library. Otherwise the Request Object is encrypted with
+/// `ECDH-ES`, with the first `enc` the wallet lists that is supported (or
+/// [ContentEncryptionAlgorithm::DEFAULT] if it lists none), to the first supported key of its
+/// `jwks`.
+#[cfg(any(feature = "wallet", feature = "verifier"))]
+pub fn negotiate_request_object(wallet_metadata: &WalletMetadata) -> Option<ResponseEncryption> {
+    let algs = wallet_metadata.request_object_encryption_alg_values_supported()?;
+    let alg = algs.0.iter().find(|alg| *alg == ECDH_ES)?.clone();
+
+    let enc = match wallet_metadata.request_object_encryption_enc_values_supported() {
+        Some(encs) => encs.0.iter().find_map(|enc| enc.parse().ok())?,
+        None => ContentEncryptionAlgorithm::DEFAULT,
+    };
+
+    let jwk = ecdh_es::select_jwk(wallet_metadata.jwks()?.clone())?;
+
+    Some(ResponseEncryption { alg, enc, jwk })
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    const SUPPORTED: &[ContentEncryptionAlgorithm] = &[
+        ContentEncryptionAlgorithm::A128CbcHs256,
+        ContentEncryptionAlgorithm::A256Gcm,
+    ];
+
+    fn client_metadata(value: serde_json::Value) -> UntypedObject {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn negotiate_requested_enc() {
+        let metadata = client_metadata(json!({
+            "authorization_encrypted_response_alg": "ECDH-ES",
+            "authorization_encrypted_response_enc": "A256GCM"
+        }));
+        assert_eq!(
+            negotiate_enc(&metadata, SUPPORTED).unwrap(),
+            ContentEncryptionAlgorithm::A256Gcm
+        );
+        assert!(negotiate_enc(&metadata, &[ContentEncryptionAlgorithm::A128Gcm]).is_err());
+    }
+
+    #[test]
+    fn negotiate_default() {
+        let metadata = client_metadata(json!({
+            "authorization_encrypted_response_alg": "ECDH-ES"
+        }));
+        assert_eq!(
+            negotiate_enc(&metadata, SUPPORTED).unwrap(),
+            ContentEncryptionAlgorithm::A128CbcHs256
+        );
+    }
+
+    fn wallet_metadata(value: serde_json::Value) -> WalletMetadata {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn negotiate_response_encryption() {
+        let jwk = json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "use": "enc",
+            "x": "MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4",
+            "y": "4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyM"
+        });
+        let client_metadata = client_metadata(json!({
+            "jwks": { "keys": [jwk] },
+            "authorization_encrypted_response_alg": "ECDH-ES",
+            "authorization_encrypted_response_enc": "A256GCM"
+        }));
+        let wallet = |encs: serde_json::Value| {
+            wallet_metadata(json!({
+                "authorization_endpoint": "openid4vp:",
+                "vp_formats_supported": {},
+                "authorization_encryption_alg_values_supported": ["ECDH-ES"],
+                "authorization_encryption_enc_values_supported": encs
+            }))
+        };
+
+        let negotiated = negotiate(&client_metadata, &wallet(json!(["A128GCM", "A256GCM"])))
+            .unwrap()
+            .unwrap();
+        assert_eq!(negotiated.alg, "ECDH-ES");
+        assert_eq!(negotiated.enc, ContentEncryptionAlgorithm::A256Gcm);
+        assert_eq!(negotiated.jwk["crv"], "P-256");
+
+        let error = negotiate(&client_metadata, &wallet(json!(["A128GCM"]))).unwrap_err();
+        assert!(error.downcast_ref::<EncryptionNotSupported>().is_some());
+
+        assert!(negotiate(&UntypedObject::default(), &wallet(json!([])))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn negotiate_default_alg() {
+        let client_metadata = |alg: &str| {
+            client_metadata(json!({
+                "jwks": { "keys": [{
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "use": "enc",
+                    "x": "MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4",
+                    "y": "4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyM"
+                }] },
+                "authorization_encrypted_response_alg": alg
+            }))
+        };
+        let wallet = wallet_metadata(json!({
+            "authorization_endpoint": "openid4vp:",
+            "vp_formats_supported": {}
+        }));
+        assert!(wallet
+            .authorization_encryption_alg_values_supported()
+            .is_none());
+
+        let negotiated = negotiate(&client_metadata("ECDH-ES"), &wallet)
+            .unwrap()
+            .unwrap();
+        assert_eq!(negotiated.alg, "ECDH-ES");
+
+        let error = negotiate(&client_metadata("RSA-OAEP-256"), &wallet).unwrap_err();
+        assert!(error.downcast_ref::<EncryptionNotSupported>().is_some());
+
+        // The wallet supporting an alg is not enough, this library must implement it too.
+        let rsa_wallet = wallet_metadata(json!({
+            "authorization_endpoint": "openid4vp:",
+            "vp_formats_supported": {},
+            "authorization_encryption_alg_values_supported": ["RSA-OAEP"]
+        }));
+        let error = negotiate(&client_metadata("RSA-OAEP"), &rsa_wallet).unwrap_err();
+        assert!(error.downcast_ref::<EncryptionNotSupported>().is_some());
+        assert!(negotiate(&client_metadata("ECDH-ES"), &rsa_wallet).is_err());
+    }
+
+    #[test]
+    fn negotiate_request_object_encryption() {
+        let wallet = |parameters: serde_json::Value| {
+            let mut metadata = json!({
+                "authorization_endpoint": "openid4vp:",
+                "vp_formats_supported": {},
+                "jwks": { "keys": [{
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "x": "MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4",
+                    "y": "4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyM"
+                }] }
+            });
+            metadata
+                .as_object_mut()
+                .unwrap()
+                .extend(parameters.as_object().unwrap().clone());
+            wallet_metadata(metadata)
+        };
+
+        let negotiated = negotiate_request_object(&wallet(json!({
+            "request_object_encryption_alg_values_supported": ["RSA-OAEP", "ECDH-ES"],
+            "request_object_encryption_enc_values_supported": ["A999GCM", "A256GCM"]
+        })))
+        .unwrap();
+        assert_eq!(negotiated.alg, "ECDH-ES");
+        assert_eq!(negotiated.enc, ContentEncryptionAlgorithm::A256Gcm);
+        assert_eq!(negotiated.jwk["crv"], "P-256");
+
+        let negotiated = negotiate_request_object(&wallet(json!({
+            "request_object_encryption_alg_values_supported": ["ECDH-ES"]
+        })))
+        .unwrap();
+        assert_eq!(negotiated.enc, ContentEncryptionAlgorithm::DEFAULT);
+
+        assert!(negotiate_request_object(&wallet(json!({}))).is_none());
+        assert!(negotiate_request_object(&wallet(json!({
+            "request_object_encryption_alg_values_supported": ["RSA-OAEP"]
+        })))
+        .is_none());
+    }
+
+    #[test]
+    fn advertised_values() {
+        let supported: AuthorizationEncryptionEncValuesSupported =
+            SUPPORTED.iter().copied().collect();
+        assert_eq!(
+            serde_json::Value::from(supported),
+            json!(["A128CBC-HS256", "A256GCM"])
+        );
+    }
+}
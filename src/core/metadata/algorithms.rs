@@ -0,0 +1,29 @@
+//! The algorithms that this build of the library implements, to list in wallet metadata.
+//!
+//! Metadata built from these lists only advertises algorithms that the wallet can actually
+//! process, whichever features are enabled, see
+//! [WalletMetadataBuilder::with_implemented_algorithms](super::builder::WalletMetadataBuilder::with_implemented_algorithms).
+
+use ssi::jwk::Algorithm;
+
+use crate::core::jwe::{ContentEncryptionAlgorithm, ECDH_ES};
+
+/// The algorithms that Request Objects can be verified with, by the
+/// [P256Verifier](crate::core::authorization_request::verification::verifier::P256Verifier) of
+/// X.509 clients and the DID resolution of DID clients.
+///
+/// Other algorithms require a custom
+/// [Verifier](crate::core::authorization_request::verification::verifier::Verifier).
+pub fn request_object_signing() -> Vec<Algorithm> {
+    vec![Algorithm::ES256]
+}
+
+/// The key management algorithms of encrypted responses and Request Objects.
+pub fn key_management() -> Vec<String> {
+    vec![ECDH_ES.to_owned()]
+}
+
+/// The content encryption algorithms of encrypted responses and Request Objects.
+pub fn content_encryption() -> Vec<ContentEncryptionAlgorithm> {
+    ContentEncryptionAlgorithm::ALL.to_vec()
+}
@@ -0,0 +1,414 @@
+use anyhow::{bail, Result};
+use ssi::jwk::Algorithm;
+use tracing::warn;
+use url::Url;
+
+use crate::core::{
+    authorization_request::parameters::{ClientIdScheme, ResponseMode, ResponseType},
+    consts::OPENID4VP_SCHEME,
+    credential_format::{ClaimFormatDesignation, ClaimFormatMap, ClaimFormatPayload},
+    jwe::ContentEncryptionAlgorithm,
+};
+
+use super::{
+    algorithms,
+    parameters::{
+        verifier::JWKs,
+        wallet::{
+            AuthorizationEndpoint, AuthorizationEndpoints, ClientIdPrefixesSupported,
+            ClientIdSchemesSupported, Issuer, RequestObjectSigningAlgValuesSupported,
+            ResponseModesSupported, ResponseTypesSupported, VpFormatsSupported,
+        },
+    },
+    WalletMetadata,
+};
+
+/// Builder for [WalletMetadata].
+///
+/// Starts from the static discovery metadata bound to `openid4vp:` (see
+/// [WalletMetadata::openid4vp_scheme_static]), every setter replaces the corresponding default.
+#[derive(Debug, Clone)]
+pub struct WalletMetadataBuilder {
+    issuer: Option<String>,
+    authorization_endpoint: Url,
+    authorization_endpoints: Option<Vec<Url>>,
+    vp_formats_supported: ClaimFormatMap,
+    request_object_signing_alg_values_supported: Vec<Algorithm>,
+    response_types_supported: Vec<ResponseType>,
+    response_modes_supported: Option<Vec<ResponseMode>>,
+    client_id_schemes_supported: Option<Vec<ClientIdScheme>>,
+    client_id_prefixes_supported: Option<Vec<ClientIdScheme>>,
+    authorization_encryption_alg_values_supported: Option<Vec<String>>,
+    authorization_encryption_enc_values_supported: Option<Vec<ContentEncryptionAlgorithm>>,
+    request_object_encryption_alg_values_supported: Option<Vec<String>>,
+    request_object_encryption_enc_values_supported: Option<Vec<ContentEncryptionAlgorithm>>,
+    jwks: Option<JWKs>,
+    presentation_definition_uri_supported: Option<bool>,
+}
+
+impl Default for WalletMetadataBuilder {
+    fn default() -> Self {
+        let es256 = vec![Algorithm::ES256.to_string()];
+        Self {
+            issuer: None,
+            // Unwrap safety: unit tested.
+            authorization_endpoint: OPENID4VP_SCHEME.parse().unwrap(),
+            authorization_endpoints: None,
+            vp_formats_supported: ClaimFormatMap::from([
+                (
+                    ClaimFormatDesignation::JwtVpJson,
+                    ClaimFormatPayload::AlgValuesSupported(es256.clone()),
+                ),
+                (
+                    ClaimFormatDesignation::JwtVcJson,
+                    ClaimFormatPayload::AlgValuesSupported(es256),
+                ),
+            ]),
+            request_object_signing_alg_values_supported: vec![Algorithm::ES256],
+            response_types_supported: vec![ResponseType::VpToken],
+            response_modes_supported: None,
+            client_id_schemes_supported: None,
+            client_id_prefixes_supported: None,
+            authorization_encryption_alg_values_supported: None,
+            authorization_encryption_enc_values_supported: None,
+            request_object_encryption_alg_values_supported: None,
+            request_object_encryption_enc_values_supported: None,
+            jwks: None,
+            presentation_definition_uri_supported: None,
+        }
+    }
+}
+
+impl WalletMetadataBuilder {
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    pub fn with_authorization_endpoint(mut self, authorization_endpoint: Url) -> Self {
+        self.authorization_endpoint = authorization_endpoint;
+        self
+    }
+
+    /// Further endpoints the wallet can be invoked at, in order of preference after the
+    /// authorization endpoint, see [AuthorizationEndpoints].
+    pub fn with_authorization_endpoints(
+        mut self,
+        authorization_endpoints: impl IntoIterator<Item = Url>,
+    ) -> Self {
+        self.authorization_endpoints = Some(authorization_endpoints.into_iter().collect());
+        self
+    }
+
+    /// Replace the supported vp formats.
+    pub fn with_vp_formats_supported(mut self, vp_formats_supported: ClaimFormatMap) -> Self {
+        self.vp_formats_supported = vp_formats_supported;
+        self
+    }
+
+    /// Add (or replace) a single supported vp format.
+    pub fn with_vp_format(
+        mut self,
+        designation: ClaimFormatDesignation,
+        payload: ClaimFormatPayload,
+    ) -> Self {
+        self.vp_formats_supported.insert(designation, payload);
+        self
+    }
+
+    pub fn with_request_object_signing_alg_values_supported(
+        mut self,
+        algs: impl IntoIterator<Item = Algorithm>,
+    ) -> Self {
+        self.request_object_signing_alg_values_supported = algs.into_iter().collect();
+        self
+    }
+
+    pub fn with_response_types_supported(
+        mut self,
+        response_types: impl IntoIterator<Item = ResponseType>,
+    ) -> Self {
+        self.response_types_supported = response_types.into_iter().collect();
+        self
+    }
+
+    pub fn with_response_modes_supported(
+        mut self,
+        response_modes: impl IntoIterator<Item = ResponseMode>,
+    ) -> Self {
+        self.response_modes_supported = Some(response_modes.into_iter().collect());
+        self
+    }
+
+    pub fn with_client_id_schemes_supported(
+        mut self,
+        client_id_schemes: impl IntoIterator<Item = ClientIdScheme>,
+    ) -> Self {
+        self.client_id_schemes_supported = Some(client_id_schemes.into_iter().collect());
+        self
+    }
+
+    /// List the supported client ID schemes as Client Identifier Prefixes, for wallets
+    /// implementing later drafts. Both lists may be set.
+    pub fn with_client_id_prefixes_supported(
+        mut self,
+        client_id_prefixes: impl IntoIterator<Item = ClientIdScheme>,
+    ) -> Self {
+        self.client_id_prefixes_supported = Some(client_id_prefixes.into_iter().collect());
+        self
+    }
+
+    pub fn with_authorization_encryption_alg_values_supported(
+        mut self,
+        algs: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.authorization_encryption_alg_values_supported =
+            Some(algs.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_authorization_encryption_enc_values_supported(
+        mut self,
+        encs: impl IntoIterator<Item = ContentEncryptionAlgorithm>,
+    ) -> Self {
+        self.authorization_encryption_enc_values_supported = Some(encs.into_iter().collect());
+        self
+    }
+
+    /// Accept Request Objects encrypted with these key management algorithms, to the public keys
+    /// in [with_jwks](Self::with_jwks).
+    pub fn with_request_object_encryption_alg_values_supported(
+        mut self,
+        algs: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.request_object_encryption_alg_values_supported =
+            Some(algs.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_request_object_encryption_enc_values_supported(
+        mut self,
+        encs: impl IntoIterator<Item = ContentEncryptionAlgorithm>,
+    ) -> Self {
+        self.request_object_encryption_enc_values_supported = Some(encs.into_iter().collect());
+        self
+    }
+
+    /// Advertise the algorithms that this build of the library implements, see [algorithms]: the
+    /// Request Object signing algorithms it verifies, and the key management and content
+    /// encryption algorithms of encrypted responses.
+    ///
+    /// Encrypted Request Objects are not enabled, as they also need [with_jwks](Self::with_jwks).
+    pub fn with_implemented_algorithms(self) -> Self {
+        self.with_request_object_signing_alg_values_supported(algorithms::request_object_signing())
+            .with_authorization_encryption_alg_values_supported(algorithms::key_management())
+            .with_authorization_encryption_enc_values_supported(algorithms::content_encryption())
+    }
+
+    /// Publish the public keys of the wallet, e.g. to receive encrypted Request Objects.
+    pub fn with_jwks(mut self, jwks: JWKs) -> Self {
+        self.jwks = Some(jwks);
+        self
+    }
+
+    /// Whether the wallet retrieves presentation definitions passed by reference. Omitted by
+    /// default, which means that it does.
+    pub fn with_presentation_definition_uri_supported(mut self, supported: bool) -> Self {
+        self.presentation_definition_uri_supported = Some(supported);
+        self
+    }
+
+    /// Build the [WalletMetadata].
+    ///
+    /// # Errors
+    /// Returns an error if no vp formats, request object signing algorithms or response types
+    /// are supported, if an unsupported response type, response mode or key management
+    /// algorithm was set, or if the metadata is otherwise [invalid](WalletMetadata::validate).
+    ///
+    /// Request Object signing algorithms that this library cannot verify are only logged, as
+    /// they may be verified by a custom
+    /// [Verifier](crate::core::authorization_request::verification::verifier::Verifier).
+    pub fn build(self) -> Result<WalletMetadata> {
+        if self.vp_formats_supported.is_empty() {
+            bail!("at least one vp format must be supported")
+        }
+        if self.request_object_signing_alg_values_supported.is_empty() {
+            bail!("at least one request object signing algorithm must be supported")
+        }
+        if self.response_types_supported.is_empty() {
+            bail!("at least one response type must be supported")
+        }
+        if let Some(ResponseType::Unsupported(rt)) = self
+            .response_types_supported
+            .iter()
+            .find(|rt| matches!(rt, ResponseType::Unsupported(_)))
+        {
+            bail!("response type '{rt}' is not supported by this library")
+        }
+        if let Some(ResponseMode::Unsupported(rm)) = self
+            .response_modes_supported
+            .iter()
+            .flatten()
+            .find(|rm| matches!(rm, ResponseMode::Unsupported(_)))
+        {
+            bail!("response mode '{rm}' is not supported by this library")
+        }
+        let key_management = algorithms::key_management();
+        if let Some(alg) = self
+            .authorization_encryption_alg_values_supported
+            .iter()
+            .chain(&self.request_object_encryption_alg_values_supported)
+            .flatten()
+            .find(|alg| !key_management.contains(alg))
+        {
+            bail!("key management algorithm '{alg}' is not supported by this library")
+        }
+        let request_object_signing = algorithms::request_object_signing();
+        for alg in &self.request_object_signing_alg_values_supported {
+            if !request_object_signing.contains(alg) {
+                warn!(
+                    "Request Objects signed with '{alg}' can only be verified by a custom verifier"
+                )
+            }
+        }
+
+        let mut metadata = WalletMetadata::new(
+            AuthorizationEndpoint(self.authorization_endpoint),
+            VpFormatsSupported(self.vp_formats_supported),
+            None,
+        )?;
+
+        metadata.set_issuer(self.issuer.map(Issuer));
+        metadata
+            .set_authorization_endpoints(self.authorization_endpoints.map(AuthorizationEndpoints));
+        metadata.set_response_types_supported(Some(ResponseTypesSupported(
+            self.response_types_supported,
+        )));
+        metadata.set_request_object_signing_alg_values_supported(Some(
+            RequestObjectSigningAlgValuesSupported(
+                self.request_object_signing_alg_values_supported
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            ),
+        ));
+        metadata.set_response_modes_supported(
+            self.response_modes_supported.map(ResponseModesSupported),
+        );
+        metadata.set_client_id_schemes_supported(
+            self.client_id_schemes_supported
+                .map(ClientIdSchemesSupported),
+        );
+        metadata.set_client_id_prefixes_supported(
+            self.client_id_prefixes_supported
+                .map(ClientIdPrefixesSupported),
+        );
+        if let Some(algs) = self.authorization_encryption_alg_values_supported {
+            metadata.set_authorization_encryption_alg_values_supported(algs);
+        }
+        if let Some(encs) = self.authorization_encryption_enc_values_supported {
+            metadata.set_authorization_encryption_enc_values_supported(encs);
+        }
+
+        if let Some(algs) = self.request_object_encryption_alg_values_supported {
+            metadata.set_request_object_encryption_alg_values_supported(algs);
+        }
+        if let Some(encs) = self.request_object_encryption_enc_values_supported {
+            metadata.set_request_object_encryption_enc_values_supported(encs);
+        }
+        metadata.set_jwks(self.jwks);
+        metadata
+            .set_presentation_definition_uri_supported(self.presentation_definition_uri_supported);
+
+        metadata.validate()?;
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn defaults_match_static_metadata() {
+        assert_eq!(
+            serde_json::to_value(WalletMetadataBuilder::default().build().unwrap()).unwrap(),
+            serde_json::to_value(WalletMetadata::openid4vp_scheme_static()).unwrap()
+        );
+    }
+
+    #[test]
+    fn implemented_algorithms() {
+        let metadata = WalletMetadata::builder()
+            .with_implemented_algorithms()
+            .build()
+            .unwrap();
+        let metadata = serde_json::to_value(metadata).unwrap();
+        assert_eq!(
+            metadata["request_object_signing_alg_values_supported"],
+            json!(["ES256"])
+        );
+        assert_eq!(
+            metadata["authorization_encryption_alg_values_supported"],
+            json!(["ECDH-ES"])
+        );
+        assert_eq!(
+            metadata["authorization_encryption_enc_values_supported"]
+                .as_array()
+                .unwrap()
+                .len(),
+            ContentEncryptionAlgorithm::ALL.len()
+        );
+
+        assert!(WalletMetadata::builder()
+            .with_request_object_encryption_alg_values_supported(["RSA-OAEP-256"])
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn build() {
+        let metadata = WalletMetadata::builder()
+            .with_authorization_endpoint("https://wallet.example.com/authorize".parse().unwrap())
+            .with_vp_formats_supported(ClaimFormatMap::from([(
+                ClaimFormatDesignation::MsoMDoc,
+                ClaimFormatPayload::Json(json!({})),
+            )]))
+            .with_response_modes_supported([ResponseMode::DirectPost, ResponseMode::DirectPostJwt])
+            .with_client_id_schemes_supported([ClientIdScheme::X509SanDns])
+            .with_authorization_encryption_alg_values_supported(["ECDH-ES"])
+            .with_authorization_encryption_enc_values_supported([
+                ContentEncryptionAlgorithm::A256Gcm,
+            ])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(metadata).unwrap(),
+            json!({
+                "authorization_endpoint": "https://wallet.example.com/authorize",
+                "vp_formats_supported": { "mso_mdoc": {} },
+                "response_types_supported": ["vp_token"],
+                "response_modes_supported": ["direct_post", "direct_post.jwt"],
+                "request_object_signing_alg_values_supported": ["ES256"],
+                "client_id_schemes_supported": ["x509_san_dns"],
+                "authorization_encryption_alg_values_supported": ["ECDH-ES"],
+                "authorization_encryption_enc_values_supported": ["A256GCM"]
+            })
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(WalletMetadata::builder()
+            .with_vp_formats_supported(ClaimFormatMap::new())
+            .build()
+            .is_err());
+        assert!(WalletMetadata::builder()
+            .with_response_modes_supported([ResponseMode::Unsupported("fragment".into())])
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::{bail, Context, Result};
+use url::Url;
+
+use crate::core::{consts::well_known, util::AsyncHttpClient};
+
+use super::WalletMetadata;
+
+/// The well-known URI suffix of OAuth 2.0 Authorization Server Metadata, see
+/// [RFC 8414](https://www.rfc-editor.org/rfc/rfc8414.html#section-3).
+pub const WELL_KNOWN_SUFFIX: &str = well_known::OAUTH_AUTHORIZATION_SERVER;
+
+/// The URL that the metadata of `issuer` is published at.
+///
+/// The well-known path is inserted between the host and the path of the issuer, e.g.
+/// `https://wallet.example.com/tenant` publishes its metadata at
+/// `https://wallet.example.com/.well-known/oauth-authorization-server/tenant`.
+pub fn well_known_url(issuer: &Url) -> Result<Url> {
+    if issuer.scheme() != "https" {
+        bail!("the issuer '{issuer}' does not use the https scheme")
+    }
+    if issuer.query().is_some() || issuer.fragment().is_some() {
+        bail!("the issuer '{issuer}' has a query or a fragment")
+    }
+    let mut url = issuer.clone();
+    let path = issuer.path().trim_end_matches('/');
+    url.set_path(&format!("/.well-known/{WELL_KNOWN_SUFFIX}{path}"));
+    Ok(url)
+}
+
+/// Whether `identifier` is the `issuer`, compared as strings. Only the `/` path that [Url] adds to
+/// an issuer without one may be omitted.
+fn is_issuer(identifier: &str, issuer: &Url) -> bool {
+    identifier == issuer.as_str()
+        || (issuer.path() == "/" && identifier == issuer.as_str().trim_end_matches('/'))
+}
+
+impl WalletMetadata {
+    /// Discover the metadata of the wallet identified by `issuer`, at its
+    /// [well-known URL](well_known_url).
+    ///
+    /// The metadata must carry an `issuer` identical to the one it was discovered with, so that a
+    /// wallet cannot impersonate another, see
+    /// [RFC 8414#section-3.3](https://www.rfc-editor.org/rfc/rfc8414.html#section-3.3).
+    pub async fn discover<H: AsyncHttpClient + Sync>(
+        issuer: &Url,
+        http_client: &H,
+    ) -> Result<Self> {
+        let metadata = Self::fetch(&well_known_url(issuer)?, http_client).await?;
+        let Some(discovered) = metadata.issuer() else {
+            bail!("the wallet metadata discovered for the issuer '{issuer}' has no issuer")
+        };
+        if !is_issuer(&discovered.0, issuer) {
+            bail!(
+                "the wallet metadata was discovered for the issuer '{issuer}', but is for '{}'",
+                discovered.0
+            )
+        }
+        Ok(metadata)
+    }
+
+    /// Fetch the metadata of a wallet published at `url`, and [validate](Self::validate) it.
+    pub async fn fetch<H: AsyncHttpClient + Sync>(url: &Url, http_client: &H) -> Result<Self> {
+        let response = http_client
+            .get(url)
+            .await
+            .context(format!("failed to make wallet metadata request at {url}"))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            bail!("wallet metadata request was unsuccessful (status: {status})")
+        }
+
+        let metadata: Self = serde_json::from_slice(response.body()).context(format!(
+            "failed to parse wallet metadata response from {url} (status: {status})"
+        ))?;
+        metadata
+            .validate()
+            .context(format!("the wallet metadata at {url} is invalid"))?;
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+    use http::{Request, Response};
+    use serde_json::json;
+
+    use super::*;
+
+    struct StaticClient(serde_json::Value);
+
+    #[async_trait]
+    impl AsyncHttpClient for StaticClient {
+        async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+            let status = if request.uri().path().starts_with("/.well-known/") {
+                200
+            } else {
+                404
+            };
+            Ok(Response::builder()
+                .status(status)
+                .body(serde_json::to_vec(&self.0)?)?)
+        }
+    }
+
+    fn metadata(issuer: &str) -> serde_json::Value {
+        json!({
+            "issuer": issuer,
+            "authorization_endpoint": "https://wallet.example.com/authorize",
+            "response_types_supported": ["vp_token"],
+            "vp_formats_supported": { "jwt_vp_json": { "alg": ["ES256"] } }
+        })
+    }
+
+    #[test]
+    fn well_known_urls() {
+        let url = |issuer: &str| well_known_url(&issuer.parse().unwrap()).map(String::from);
+        assert_eq!(
+            url("https://wallet.example.com").unwrap(),
+            "https://wallet.example.com/.well-known/oauth-authorization-server"
+        );
+        assert_eq!(
+            url("https://wallet.example.com/tenant/").unwrap(),
+            "https://wallet.example.com/.well-known/oauth-authorization-server/tenant"
+        );
+        assert!(url("http://wallet.example.com").is_err());
+        assert!(url("https://wallet.example.com?tenant=1").is_err());
+    }
+
+    #[tokio::test]
+    async fn discover() {
+        let issuer: Url = "https://wallet.example.com".parse().unwrap();
+
+        let client = StaticClient(metadata("https://wallet.example.com"));
+        let discovered = WalletMetadata::discover(&issuer, &client).await.unwrap();
+        assert_eq!(
+            discovered.authorization_endpoint().0.as_str(),
+            "https://wallet.example.com/authorize"
+        );
+
+        let client = StaticClient(metadata("https://wallet.example.com/"));
+        WalletMetadata::discover(&issuer, &client).await.unwrap();
+
+        let url = "https://wallet.example.com/metadata".parse().unwrap();
+        assert!(WalletMetadata::fetch(&url, &client).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn discover_missing_issuer() {
+        let issuer: Url = "https://wallet.example.com".parse().unwrap();
+        let mut metadata = metadata("https://wallet.example.com");
+        metadata.as_object_mut().unwrap().remove("issuer");
+
+        let error = WalletMetadata::discover(&issuer, &StaticClient(metadata))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("has no issuer"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn discover_mismatched_issuer() {
+        let issuer: Url = "https://wallet.example.com/tenant".parse().unwrap();
+        for other in [
+            "https://other.example.com/tenant",
+            "https://wallet.example.com/tenant/",
+            "https://WALLET.example.com/tenant",
+            "https://wallet.example.com",
+        ] {
+            let client = StaticClient(metadata(other));
+            assert!(
+                WalletMetadata::discover(&issuer, &client).await.is_err(),
+                "{other}"
+            );
+        }
+        let client = StaticClient(metadata("https://wallet.example.com/tenant"));
+        WalletMetadata::discover(&issuer, &client).await.unwrap();
+    }
+}
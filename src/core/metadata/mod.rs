@@ -1,52 +1,276 @@
 use super::{authorization_request::parameters::ClientIdScheme, credential_format::*};
 
-use std::ops::{Deref, DerefMut};
-
-use anyhow::{Error, Result};
-use parameters::wallet::{
-    ClientIdSchemesSupported, RequestObjectSigningAlgValuesSupported, ResponseTypesSupported,
+use anyhow::{bail, Context, Error, Result};
+use parameters::{
+    verifier::JWKs,
+    wallet::{
+        AuthorizationEncryptionAlgValuesSupported, AuthorizationEncryptionEncValuesSupported,
+        ClientIdPrefixesSupported, ClientIdSchemesSupported, Issuer,
+        PresentationDefinitionUriSupported, RequestObjectEncryptionAlgValuesSupported,
+        RequestObjectEncryptionEncValuesSupported, RequestObjectSigningAlgValuesSupported,
+        ResponseModesSupported, ResponseTypesSupported, SignedMetadata,
+    },
 };
 use serde::{Deserialize, Serialize};
-use ssi::jwk::Algorithm;
+use url::Url;
 
-use self::parameters::wallet::{AuthorizationEndpoint, VpFormatsSupported};
+use self::parameters::wallet::{AuthorizationEndpoint, AuthorizationEndpoints, VpFormatsSupported};
 
 use super::{
-    authorization_request::parameters::ResponseType,
-    object::{ParsingErrorContext, UntypedObject},
+    authorization_request::parameters::{ResponseMode, ResponseType},
+    consts::OPENID4VP_SCHEME,
+    draft::Draft,
+    jwe::ContentEncryptionAlgorithm,
+    object::{ParsingErrorContext, TypedParameter, UntypedObject},
 };
 
+#[cfg(feature = "core")]
+pub mod algorithms;
+#[cfg(feature = "core")]
+pub mod builder;
+pub mod discovery;
 pub mod parameters;
+#[cfg(feature = "core")]
+pub mod signed;
+
+#[cfg(feature = "core")]
+pub use builder::WalletMetadataBuilder;
 
+/// The metadata of a wallet, as an OAuth 2.0 Authorization Server.
+///
+/// The parameters defined by OpenID4VP are parsed when the metadata is deserialized, other
+/// parameters are kept as [extensions](Self::extensions) and serialized back as they were.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(try_from = "UntypedObject", into = "UntypedObject")]
-pub struct WalletMetadata(UntypedObject, AuthorizationEndpoint, VpFormatsSupported);
+pub struct WalletMetadata {
+    issuer: Option<Issuer>,
+    authorization_endpoint: AuthorizationEndpoint,
+    authorization_endpoints: Option<AuthorizationEndpoints>,
+    response_types_supported: Option<ResponseTypesSupported>,
+    response_modes_supported: Option<ResponseModesSupported>,
+    vp_formats_supported: VpFormatsSupported,
+    request_object_signing_alg_values_supported: Option<RequestObjectSigningAlgValuesSupported>,
+    client_id_schemes_supported: Option<ClientIdSchemesSupported>,
+    client_id_prefixes_supported: Option<ClientIdPrefixesSupported>,
+    authorization_encryption_alg_values_supported:
+        Option<AuthorizationEncryptionAlgValuesSupported>,
+    authorization_encryption_enc_values_supported:
+        Option<AuthorizationEncryptionEncValuesSupported>,
+    request_object_encryption_alg_values_supported:
+        Option<RequestObjectEncryptionAlgValuesSupported>,
+    request_object_encryption_enc_values_supported:
+        Option<RequestObjectEncryptionEncValuesSupported>,
+    jwks: Option<JWKs>,
+    presentation_definition_uri_supported: Option<PresentationDefinitionUriSupported>,
+    signed_metadata: Option<SignedMetadata>,
+    extensions: UntypedObject,
+}
 
 impl WalletMetadata {
+    /// Metadata with the required parameters, and `other` parameters.
+    ///
+    /// # Errors
+    /// Returns an error if a parameter defined by OpenID4VP in `other` cannot be parsed.
     pub fn new(
         authorization_endpoint: AuthorizationEndpoint,
         vp_formats_supported: VpFormatsSupported,
         other: Option<UntypedObject>,
-    ) -> Self {
-        Self(
-            other.unwrap_or_default(),
-            authorization_endpoint,
-            vp_formats_supported,
-        )
+    ) -> Result<Self> {
+        let mut other = other.unwrap_or_default();
+        other.insert(authorization_endpoint);
+        other.insert(vp_formats_supported);
+        other.try_into()
+    }
+
+    /// Build wallet metadata starting from the static discovery defaults.
+    #[cfg(feature = "core")]
+    pub fn builder() -> WalletMetadataBuilder {
+        WalletMetadataBuilder::default()
+    }
+
+    pub fn issuer(&self) -> Option<&Issuer> {
+        self.issuer.as_ref()
+    }
+
+    pub fn set_issuer(&mut self, issuer: Option<Issuer>) {
+        self.issuer = issuer;
     }
 
     pub fn authorization_endpoint(&self) -> &AuthorizationEndpoint {
-        &self.1
+        &self.authorization_endpoint
+    }
+
+    pub fn set_authorization_endpoint(&mut self, authorization_endpoint: AuthorizationEndpoint) {
+        self.authorization_endpoint = authorization_endpoint;
+    }
+
+    /// Further endpoints the wallet can be invoked at, see [AuthorizationEndpoints].
+    pub fn authorization_endpoints(&self) -> Option<&AuthorizationEndpoints> {
+        self.authorization_endpoints.as_ref()
+    }
+
+    pub fn set_authorization_endpoints(
+        &mut self,
+        authorization_endpoints: Option<AuthorizationEndpoints>,
+    ) {
+        self.authorization_endpoints = authorization_endpoints;
+    }
+
+    /// The endpoints the wallet can be invoked at, in order of preference: the
+    /// `authorization_endpoint`, then the other
+    /// [authorization_endpoints](Self::authorization_endpoints).
+    pub fn invocation_endpoints(&self) -> Vec<&Url> {
+        let mut endpoints = vec![&self.authorization_endpoint.0];
+        for endpoint in self.authorization_endpoints.iter().flat_map(|e| &e.0) {
+            if !endpoints.contains(&endpoint) {
+                endpoints.push(endpoint);
+            }
+        }
+        endpoints
+    }
+
+    /// The endpoint to invoke the wallet at: the first of the verifier's `preferences` that is an
+    /// [invocation endpoint](Self::invocation_endpoints) of the wallet, or the
+    /// `authorization_endpoint` if there is none.
+    ///
+    /// Endpoints are compared by scheme, authority and path, so that a preference for `haip://`
+    /// selects `haip:` and vice versa.
+    pub fn preferred_authorization_endpoint(&self, preferences: &[Url]) -> &Url {
+        let endpoints = self.invocation_endpoints();
+        preferences
+            .iter()
+            .find_map(|preference| {
+                endpoints.iter().copied().find(|endpoint| {
+                    endpoint.scheme() == preference.scheme()
+                        && endpoint.authority() == preference.authority()
+                        && endpoint.path() == preference.path()
+                })
+            })
+            .unwrap_or(&self.authorization_endpoint.0)
+    }
+
+    pub fn response_types_supported(&self) -> Option<&ResponseTypesSupported> {
+        self.response_types_supported.as_ref()
+    }
+
+    pub fn set_response_types_supported(
+        &mut self,
+        response_types_supported: Option<ResponseTypesSupported>,
+    ) {
+        self.response_types_supported = response_types_supported;
+    }
+
+    /// Whether the wallet supports `response_type`, which must be listed in
+    /// `response_types_supported`.
+    pub fn supports_response_type(&self, response_type: &ResponseType) -> bool {
+        self.response_types_supported
+            .as_ref()
+            .is_some_and(|supported| supported.0.contains(response_type))
+    }
+
+    /// The response modes supported by the wallet. Any response mode may be requested if they
+    /// are not listed.
+    pub fn response_modes_supported(&self) -> Option<&ResponseModesSupported> {
+        self.response_modes_supported.as_ref()
+    }
+
+    /// Whether the wallet supports `response_mode`, see
+    /// [response_modes_supported](Self::response_modes_supported).
+    pub fn supports_response_mode(&self, response_mode: &ResponseMode) -> bool {
+        self.response_modes_supported
+            .as_ref()
+            .is_none_or(|supported| supported.0.contains(response_mode))
+    }
+
+    pub fn set_response_modes_supported(
+        &mut self,
+        response_modes_supported: Option<ResponseModesSupported>,
+    ) {
+        self.response_modes_supported = response_modes_supported;
     }
 
     /// Return a reference to the vp formats supported.
     pub fn vp_formats_supported(&self) -> &VpFormatsSupported {
-        &self.2
+        &self.vp_formats_supported
     }
 
     /// Return a mutable reference to the vp formats supported.
     pub fn vp_formats_supported_mut(&mut self) -> &mut VpFormatsSupported {
-        &mut self.2
+        &mut self.vp_formats_supported
+    }
+
+    /// The vp formats supported, by designation.
+    pub fn vp_formats(&self) -> &ClaimFormatMap {
+        &self.vp_formats_supported.0
+    }
+
+    /// Add a supported vp format, returning the payload it replaces, if any.
+    pub fn add_format(
+        &mut self,
+        designation: ClaimFormatDesignation,
+        payload: ClaimFormatPayload,
+    ) -> Option<ClaimFormatPayload> {
+        self.vp_formats_supported.0.insert(designation, payload)
+    }
+
+    /// Remove a supported vp format, returning its payload, if it was supported.
+    ///
+    /// The metadata is [invalid](Self::validate) once no formats are supported.
+    pub fn remove_format(
+        &mut self,
+        designation: &ClaimFormatDesignation,
+    ) -> Option<ClaimFormatPayload> {
+        self.vp_formats_supported.0.remove(designation)
+    }
+
+    /// Whether the wallet supports the vp format `designation` with the algorithm (or proof type)
+    /// `alg`, see [ClaimFormatPayload::supports_alg].
+    pub fn supports(&self, designation: &ClaimFormatDesignation, alg: &str) -> bool {
+        self.vp_formats_supported
+            .0
+            .get(designation)
+            .is_some_and(|payload| payload.supports_alg(alg))
+    }
+
+    pub fn request_object_signing_alg_values_supported(
+        &self,
+    ) -> Option<&RequestObjectSigningAlgValuesSupported> {
+        self.request_object_signing_alg_values_supported.as_ref()
+    }
+
+    pub fn set_request_object_signing_alg_values_supported(
+        &mut self,
+        request_object_signing_alg_values_supported: Option<RequestObjectSigningAlgValuesSupported>,
+    ) {
+        self.request_object_signing_alg_values_supported =
+            request_object_signing_alg_values_supported;
+    }
+
+    /// The client ID schemes listed by the wallet, see
+    /// [supports_client_id_scheme](Self::supports_client_id_scheme).
+    pub fn client_id_schemes_supported(&self) -> Option<&ClientIdSchemesSupported> {
+        self.client_id_schemes_supported.as_ref()
+    }
+
+    /// Whether the wallet supports `client_id_scheme`, as a client ID scheme or as a Client
+    /// Identifier Prefix. Wallets that list neither only support `pre-registered`.
+    pub fn supports_client_id_scheme(&self, client_id_scheme: &ClientIdScheme) -> bool {
+        match (
+            &self.client_id_schemes_supported,
+            &self.client_id_prefixes_supported,
+        ) {
+            (None, None) => ClientIdSchemesSupported::default()
+                .0
+                .contains(client_id_scheme),
+            (schemes, prefixes) => {
+                schemes
+                    .iter()
+                    .any(|supported| supported.0.contains(client_id_scheme))
+                    || prefixes
+                        .iter()
+                        .any(|supported| supported.0.contains(client_id_scheme))
+            }
+        }
     }
 
     /// Add a client ID scheme to the list of the client ID schemes supported.
@@ -54,17 +278,287 @@ impl WalletMetadata {
     /// This method will construct a `client_id_schemes_supported` proprety in the
     /// wallet metadata if none exists previously, otherwise, this method will add
     /// the client ID scheme to the existing list of the client ID schemes supported.
-    pub fn add_client_id_schemes_supported(
+    pub fn add_client_id_schemes_supported(&mut self, client_id_scheme: ClientIdScheme) {
+        self.client_id_schemes_supported
+            .get_or_insert_with(Default::default)
+            .0
+            .push(client_id_scheme);
+    }
+
+    pub fn set_client_id_schemes_supported(
         &mut self,
-        client_id_scheme: ClientIdScheme,
-    ) -> Result<()> {
-        let mut supported = self.0.get_or_default::<ClientIdSchemesSupported>()?;
+        client_id_schemes_supported: Option<ClientIdSchemesSupported>,
+    ) {
+        self.client_id_schemes_supported = client_id_schemes_supported;
+    }
+
+    /// The Client Identifier Prefixes listed by the wallet, see
+    /// [supports_client_id_scheme](Self::supports_client_id_scheme).
+    pub fn client_id_prefixes_supported(&self) -> Option<&ClientIdPrefixesSupported> {
+        self.client_id_prefixes_supported.as_ref()
+    }
 
-        // Insert the scheme.
-        supported.0.push(client_id_scheme);
+    pub fn set_client_id_prefixes_supported(
+        &mut self,
+        client_id_prefixes_supported: Option<ClientIdPrefixesSupported>,
+    ) {
+        self.client_id_prefixes_supported = client_id_prefixes_supported;
+    }
 
-        // Insert the updated client IDs schemes supported.
-        self.0.insert(supported);
+    /// The JWE key management algorithms supported for encrypted responses. Any algorithm may
+    /// be requested if they are not listed.
+    pub fn authorization_encryption_alg_values_supported(
+        &self,
+    ) -> Option<&AuthorizationEncryptionAlgValuesSupported> {
+        self.authorization_encryption_alg_values_supported.as_ref()
+    }
+
+    pub fn set_authorization_encryption_alg_values_supported(
+        &mut self,
+        algs: impl IntoIterator<Item = impl Into<String>>,
+    ) {
+        self.authorization_encryption_alg_values_supported = Some(
+            AuthorizationEncryptionAlgValuesSupported(algs.into_iter().map(Into::into).collect()),
+        );
+    }
+
+    /// The JWE content encryption algorithms supported for encrypted responses. Any algorithm
+    /// may be requested if they are not listed.
+    pub fn authorization_encryption_enc_values_supported(
+        &self,
+    ) -> Option<&AuthorizationEncryptionEncValuesSupported> {
+        self.authorization_encryption_enc_values_supported.as_ref()
+    }
+
+    /// Set the JWE content encryption algorithms supported for encrypted responses.
+    ///
+    /// This replaces any existing `authorization_encryption_enc_values_supported` property.
+    pub fn set_authorization_encryption_enc_values_supported(
+        &mut self,
+        encs: impl IntoIterator<Item = ContentEncryptionAlgorithm>,
+    ) {
+        self.authorization_encryption_enc_values_supported = Some(
+            encs.into_iter()
+                .collect::<AuthorizationEncryptionEncValuesSupported>(),
+        );
+    }
+
+    /// The JWE key management algorithms the wallet can decrypt Request Objects with. Request
+    /// Objects are only encrypted for wallets that list them, see
+    /// [negotiate_request_object](super::jwe::negotiate_request_object).
+    pub fn request_object_encryption_alg_values_supported(
+        &self,
+    ) -> Option<&RequestObjectEncryptionAlgValuesSupported> {
+        self.request_object_encryption_alg_values_supported.as_ref()
+    }
+
+    pub fn set_request_object_encryption_alg_values_supported(
+        &mut self,
+        algs: impl IntoIterator<Item = impl Into<String>>,
+    ) {
+        self.request_object_encryption_alg_values_supported = Some(
+            RequestObjectEncryptionAlgValuesSupported(algs.into_iter().map(Into::into).collect()),
+        );
+    }
+
+    /// The JWE content encryption algorithms the wallet can decrypt Request Objects with. Any
+    /// algorithm may be used if they are not listed.
+    pub fn request_object_encryption_enc_values_supported(
+        &self,
+    ) -> Option<&RequestObjectEncryptionEncValuesSupported> {
+        self.request_object_encryption_enc_values_supported.as_ref()
+    }
+
+    pub fn set_request_object_encryption_enc_values_supported(
+        &mut self,
+        encs: impl IntoIterator<Item = ContentEncryptionAlgorithm>,
+    ) {
+        self.request_object_encryption_enc_values_supported = Some(
+            encs.into_iter()
+                .collect::<RequestObjectEncryptionEncValuesSupported>(),
+        );
+    }
+
+    /// The public keys of the wallet, that Request Objects are encrypted to.
+    pub fn jwks(&self) -> Option<&JWKs> {
+        self.jwks.as_ref()
+    }
+
+    pub fn set_jwks(&mut self, jwks: Option<JWKs>) {
+        self.jwks = jwks;
+    }
+
+    pub fn presentation_definition_uri_supported(
+        &self,
+    ) -> Option<&PresentationDefinitionUriSupported> {
+        self.presentation_definition_uri_supported.as_ref()
+    }
+
+    /// Whether the wallet retrieves presentation definitions passed by reference, which it does
+    /// unless its metadata says otherwise.
+    pub fn supports_presentation_definition_uri(&self) -> bool {
+        self.presentation_definition_uri_supported
+            .as_ref()
+            .is_none_or(|supported| supported.0)
+    }
+
+    pub fn set_presentation_definition_uri_supported(&mut self, supported: Option<bool>) {
+        self.presentation_definition_uri_supported =
+            supported.map(PresentationDefinitionUriSupported);
+    }
+
+    /// The metadata signed by a trust authority, which is only trusted once
+    /// [verified](Self::verify_signed).
+    pub fn signed_metadata(&self) -> Option<&SignedMetadata> {
+        self.signed_metadata.as_ref()
+    }
+
+    /// Set the signed metadata, see [sign](Self::sign) to produce it.
+    pub fn set_signed_metadata(&mut self, signed_metadata: Option<SignedMetadata>) {
+        self.signed_metadata = signed_metadata;
+    }
+
+    /// The parameters that are not defined by OpenID4VP, e.g. extensions or parameters of
+    /// other OAuth 2.0 specifications.
+    pub fn extensions(&self) -> &UntypedObject {
+        &self.extensions
+    }
+
+    /// Return a mutable reference to the parameters that are not defined by OpenID4VP.
+    ///
+    /// Parameters defined by OpenID4VP that are inserted here are parsed, and replace the typed
+    /// values, when the metadata is deserialized again.
+    pub fn extensions_mut(&mut self) -> &mut UntypedObject {
+        &mut self.extensions
+    }
+
+    /// Check that the metadata is consistent, and carries the parameters OpenID4VP requires.
+    ///
+    /// The authorization endpoints must be `https` URLs without a fragment, or use a custom
+    /// scheme, such as `openid4vp:`. `response_types_supported` must include a response type with
+    /// `vp_token`, the vp formats must be valid (see [VpFormatsSupported::validate]), and the
+    /// listed algorithms and client ID schemes must not be empty. Encryption algorithms must be
+    /// listed together.
+    ///
+    /// Metadata is validated when a verifier or a wallet is configured with it, and before
+    /// requests are built for it.
+    pub fn validate(&self) -> Result<()> {
+        validate_endpoint(&self.authorization_endpoint.0, AuthorizationEndpoint::KEY)?;
+        if let Some(endpoints) = &self.authorization_endpoints {
+            if endpoints.0.is_empty() {
+                bail!("'{}' is empty", AuthorizationEndpoints::KEY)
+            }
+            for endpoint in &endpoints.0 {
+                validate_endpoint(endpoint, AuthorizationEndpoints::KEY)?;
+            }
+        }
+
+        let Some(response_types) = &self.response_types_supported else {
+            bail!("'{}' is missing", ResponseTypesSupported::KEY)
+        };
+        if !response_types
+            .0
+            .iter()
+            .any(|rt| matches!(rt, ResponseType::VpToken | ResponseType::VpTokenIdToken))
+        {
+            bail!(
+                "'{}' does not include vp_token",
+                ResponseTypesSupported::KEY
+            )
+        }
+
+        self.vp_formats_supported
+            .validate()
+            .with_context(|| format!("'{}' is invalid", VpFormatsSupported::KEY))?;
+
+        let lists = [
+            (
+                ResponseModesSupported::KEY,
+                self.response_modes_supported.as_ref().map(|p| p.0.len()),
+            ),
+            (
+                RequestObjectSigningAlgValuesSupported::KEY,
+                self.request_object_signing_alg_values_supported
+                    .as_ref()
+                    .map(|p| p.0.len()),
+            ),
+            (
+                ClientIdSchemesSupported::KEY,
+                self.client_id_schemes_supported.as_ref().map(|p| p.0.len()),
+            ),
+            (
+                ClientIdPrefixesSupported::KEY,
+                self.client_id_prefixes_supported
+                    .as_ref()
+                    .map(|p| p.0.len()),
+            ),
+            (
+                AuthorizationEncryptionAlgValuesSupported::KEY,
+                self.authorization_encryption_alg_values_supported
+                    .as_ref()
+                    .map(|p| p.0.len()),
+            ),
+            (
+                AuthorizationEncryptionEncValuesSupported::KEY,
+                self.authorization_encryption_enc_values_supported
+                    .as_ref()
+                    .map(|p| p.0.len()),
+            ),
+            (
+                RequestObjectEncryptionAlgValuesSupported::KEY,
+                self.request_object_encryption_alg_values_supported
+                    .as_ref()
+                    .map(|p| p.0.len()),
+            ),
+            (
+                RequestObjectEncryptionEncValuesSupported::KEY,
+                self.request_object_encryption_enc_values_supported
+                    .as_ref()
+                    .map(|p| p.0.len()),
+            ),
+        ];
+        if let Some((key, _)) = lists.iter().find(|(_, len)| *len == Some(0)) {
+            bail!("'{key}' is empty")
+        }
+
+        if self.authorization_encryption_enc_values_supported.is_some()
+            && self.authorization_encryption_alg_values_supported.is_none()
+        {
+            bail!(
+                "'{}' is listed without '{}'",
+                AuthorizationEncryptionEncValuesSupported::KEY,
+                AuthorizationEncryptionAlgValuesSupported::KEY
+            )
+        }
+        if self
+            .request_object_encryption_enc_values_supported
+            .is_some()
+            && self
+                .request_object_encryption_alg_values_supported
+                .is_none()
+        {
+            bail!(
+                "'{}' is listed without '{}'",
+                RequestObjectEncryptionEncValuesSupported::KEY,
+                RequestObjectEncryptionAlgValuesSupported::KEY
+            )
+        }
+        match &self.jwks {
+            None if self
+                .request_object_encryption_alg_values_supported
+                .is_some() =>
+            {
+                bail!(
+                    "'{}' is listed without '{}' to encrypt Request Objects to",
+                    RequestObjectEncryptionAlgValuesSupported::KEY,
+                    JWKs::KEY
+                )
+            }
+            Some(jwks) if jwks.keys.iter().any(|jwk| jwk.contains_key("d")) => {
+                bail!("'{}' must only contain public keys", JWKs::KEY)
+            }
+            _ => {}
+        }
 
         Ok(())
     }
@@ -91,11 +585,11 @@ impl WalletMetadata {
     /// ```
     pub fn openid4vp_scheme_static() -> Self {
         // Unwrap safety: unit tested.
-        let authorization_endpoint = AuthorizationEndpoint("openid4vp:".parse().unwrap());
+        let authorization_endpoint = AuthorizationEndpoint(OPENID4VP_SCHEME.parse().unwrap());
 
         let response_types_supported = ResponseTypesSupported(vec![ResponseType::VpToken]);
 
-        let alg_values_supported = vec![Algorithm::ES256.to_string()];
+        let alg_values_supported = vec!["ES256".to_owned()];
 
         let mut vp_formats_supported = ClaimFormatMap::new();
         vp_formats_supported.insert(
@@ -111,23 +605,76 @@ impl WalletMetadata {
         let request_object_signing_alg_values_supported =
             RequestObjectSigningAlgValuesSupported(alg_values_supported);
 
-        let mut object = UntypedObject::default();
-
-        object.insert(authorization_endpoint);
-        object.insert(response_types_supported);
-        object.insert(vp_formats_supported);
-        object.insert(request_object_signing_alg_values_supported);
+        Self {
+            issuer: None,
+            authorization_endpoint,
+            authorization_endpoints: None,
+            response_types_supported: Some(response_types_supported),
+            response_modes_supported: None,
+            vp_formats_supported,
+            request_object_signing_alg_values_supported: Some(
+                request_object_signing_alg_values_supported,
+            ),
+            client_id_schemes_supported: None,
+            client_id_prefixes_supported: None,
+            authorization_encryption_alg_values_supported: None,
+            authorization_encryption_enc_values_supported: None,
+            request_object_encryption_alg_values_supported: None,
+            request_object_encryption_enc_values_supported: None,
+            jwks: None,
+            presentation_definition_uri_supported: None,
+            signed_metadata: None,
+            extensions: UntypedObject::default(),
+        }
+    }
 
-        // Unwrap safety: unit tested.
-        object.try_into().unwrap()
+    /// The static metadata that wallets have under `draft`, which verifiers assume for wallets
+    /// that do not post their metadata, see
+    /// [build_with_default_metadata](crate::verifier::request_builder::RequestBuilder::build_with_default_metadata).
+    ///
+    /// Drafts 20 and 21 both define the [openid4vp_scheme_static](Self::openid4vp_scheme_static)
+    /// metadata.
+    pub fn default_for(draft: Draft) -> Self {
+        match draft {
+            Draft::Draft20 | Draft::Draft21 => Self::openid4vp_scheme_static(),
+        }
     }
 }
 
 impl From<WalletMetadata> for UntypedObject {
     fn from(value: WalletMetadata) -> Self {
-        let mut inner = value.0;
-        inner.insert(value.1);
-        inner.insert(value.2);
+        let mut inner = value.extensions;
+        inner.insert(value.authorization_endpoint);
+        inner.insert(value.vp_formats_supported);
+        insert_some(&mut inner, value.issuer);
+        insert_some(&mut inner, value.authorization_endpoints);
+        insert_some(&mut inner, value.response_types_supported);
+        insert_some(&mut inner, value.response_modes_supported);
+        insert_some(
+            &mut inner,
+            value.request_object_signing_alg_values_supported,
+        );
+        insert_some(&mut inner, value.client_id_schemes_supported);
+        insert_some(&mut inner, value.client_id_prefixes_supported);
+        insert_some(
+            &mut inner,
+            value.authorization_encryption_alg_values_supported,
+        );
+        insert_some(
+            &mut inner,
+            value.authorization_encryption_enc_values_supported,
+        );
+        insert_some(
+            &mut inner,
+            value.request_object_encryption_alg_values_supported,
+        );
+        insert_some(
+            &mut inner,
+            value.request_object_encryption_enc_values_supported,
+        );
+        insert_some(&mut inner, value.jwks);
+        insert_some(&mut inner, value.presentation_definition_uri_supported);
+        insert_some(&mut inner, value.signed_metadata);
         inner
     }
 }
@@ -135,30 +682,62 @@ impl From<WalletMetadata> for UntypedObject {
 impl TryFrom<UntypedObject> for WalletMetadata {
     type Error = Error;
 
-    fn try_from(value: UntypedObject) -> Result<Self, Self::Error> {
-        let authorization_endpoint = value.get().parsing_error()?;
-        let vp_formats_supported = value.get().parsing_error()?;
-        Ok(Self(value, authorization_endpoint, vp_formats_supported))
+    fn try_from(mut value: UntypedObject) -> Result<Self, Self::Error> {
+        Ok(Self {
+            issuer: value.take()?,
+            authorization_endpoint: value.remove().parsing_error()?,
+            authorization_endpoints: value.take()?,
+            response_types_supported: value.take()?,
+            response_modes_supported: value.take()?,
+            vp_formats_supported: value.remove().parsing_error()?,
+            request_object_signing_alg_values_supported: value.take()?,
+            client_id_schemes_supported: value.take()?,
+            client_id_prefixes_supported: value.take()?,
+            authorization_encryption_alg_values_supported: value.take()?,
+            authorization_encryption_enc_values_supported: value.take()?,
+            request_object_encryption_alg_values_supported: value.take()?,
+            request_object_encryption_enc_values_supported: value.take()?,
+            jwks: value.take()?,
+            presentation_definition_uri_supported: value.take()?,
+            signed_metadata: value.take()?,
+            extensions: value,
+        })
     }
 }
 
-impl Deref for WalletMetadata {
-    type Target = UntypedObject;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
+/// Check that an endpoint the wallet is invoked at is an `https` URL without a fragment, or uses a
+/// custom scheme.
+fn validate_endpoint(endpoint: &Url, key: &str) -> Result<()> {
+    match endpoint.scheme() {
+        "https" if endpoint.fragment().is_some() => {
+            bail!("the {key} must not have a fragment")
+        }
+        "https" => {}
+        scheme @ ("http" | "ws" | "wss" | "ftp" | "file" | "data" | "javascript" | "blob"
+        | "about") => {
+            bail!("the {key} scheme '{scheme}' is not allowed, use https or a custom scheme")
+        }
+        _ => {}
     }
+    Ok(())
 }
 
-impl DerefMut for WalletMetadata {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+fn insert_some<T: TypedParameter>(object: &mut UntypedObject, parameter: Option<T>) {
+    if let Some(parameter) = parameter {
+        object.insert(parameter);
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::WalletMetadata;
+    use serde_json::Value as Json;
+
+    use crate::core::{
+        authorization_request::parameters::{ClientIdScheme, ResponseMode},
+        credential_format::{ClaimFormatDesignation, ClaimFormatPayload},
+    };
+
+    use super::{Draft, Url, WalletMetadata};
 
     #[test]
     fn openid4vp_scheme_static() {
@@ -186,4 +765,184 @@ mod test {
 
         assert_eq!(expected, serde_json::to_value(wallet_metadata).unwrap())
     }
+
+    #[test]
+    fn default_for() {
+        for draft in [Draft::Draft20, Draft::Draft21] {
+            let metadata = WalletMetadata::default_for(draft);
+            metadata.validate().unwrap();
+            assert_eq!(
+                serde_json::to_value(metadata).unwrap(),
+                serde_json::to_value(WalletMetadata::openid4vp_scheme_static()).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn vp_formats() {
+        let mut wallet_metadata = WalletMetadata::openid4vp_scheme_static();
+        assert!(wallet_metadata.supports(&ClaimFormatDesignation::JwtVpJson, "ES256"));
+        assert!(!wallet_metadata.supports(&ClaimFormatDesignation::JwtVpJson, "EdDSA"));
+        assert!(!wallet_metadata.supports(&ClaimFormatDesignation::MsoMDoc, "ES256"));
+
+        assert!(wallet_metadata
+            .add_format(
+                ClaimFormatDesignation::MsoMDoc,
+                ClaimFormatPayload::Json(serde_json::json!({}))
+            )
+            .is_none());
+        assert!(wallet_metadata.supports(&ClaimFormatDesignation::MsoMDoc, "ES256"));
+        assert!(wallet_metadata
+            .remove_format(&ClaimFormatDesignation::JwtVcJson)
+            .is_some());
+        assert_eq!(
+            serde_json::to_value(&wallet_metadata).unwrap()["vp_formats_supported"],
+            serde_json::json!({
+                "jwt_vp_json": { "alg_values_supported": ["ES256"] },
+                "mso_mdoc": {}
+            })
+        );
+        assert_eq!(wallet_metadata.vp_formats().len(), 2);
+    }
+
+    #[test]
+    fn validate() {
+        WalletMetadata::openid4vp_scheme_static()
+            .validate()
+            .unwrap();
+
+        let invalid = |patch: Json| {
+            let mut json = serde_json::to_value(WalletMetadata::openid4vp_scheme_static()).unwrap();
+            for (key, value) in patch.as_object().unwrap() {
+                json[key] = value.clone();
+            }
+            serde_json::from_value::<WalletMetadata>(json)
+                .unwrap()
+                .validate()
+                .is_err()
+        };
+        assert!(invalid(
+            serde_json::json!({ "authorization_endpoint": "http://wallet.example.com" })
+        ));
+        assert!(invalid(
+            serde_json::json!({ "authorization_endpoint": "https://wallet.example.com#a" })
+        ));
+        assert!(!invalid(
+            serde_json::json!({ "authorization_endpoint": "https://wallet.example.com" })
+        ));
+        assert!(!invalid(
+            serde_json::json!({ "authorization_endpoint": "haip://" })
+        ));
+        assert!(invalid(
+            serde_json::json!({ "response_types_supported": ["id_token"] })
+        ));
+        assert!(invalid(
+            serde_json::json!({ "request_object_signing_alg_values_supported": [] })
+        ));
+        assert!(invalid(serde_json::json!({ "vp_formats_supported": {} })));
+        assert!(invalid(serde_json::json!({
+            "vp_formats_supported": { "jwt_vp_json": { "alg_values_supported": [] } }
+        })));
+        assert!(invalid(serde_json::json!({
+            "vp_formats_supported": { "ldp_vp": { "alg_values_supported": ["ES256"] } }
+        })));
+        assert!(!invalid(serde_json::json!({
+            "vp_formats_supported": { "ldp_vp": { "proof_type": ["Ed25519Signature2018"] } }
+        })));
+        assert!(invalid(serde_json::json!({
+            "authorization_encryption_enc_values_supported": ["A256GCM"]
+        })));
+        assert!(invalid(
+            serde_json::json!({ "authorization_endpoints": [] })
+        ));
+        assert!(invalid(
+            serde_json::json!({ "authorization_endpoints": ["haip://", "http://wallet.example.com"] })
+        ));
+    }
+
+    #[test]
+    fn authorization_endpoints() {
+        let wallet_metadata: WalletMetadata = serde_json::from_value(serde_json::json!({
+            "authorization_endpoint": "openid4vp://",
+            "authorization_endpoints": ["haip://", "openid4vp://", "https://wallet.example.com/authorize"],
+            "response_types_supported": ["vp_token"],
+            "vp_formats_supported": { "mso_mdoc": {} }
+        }))
+        .unwrap();
+        wallet_metadata.validate().unwrap();
+        let endpoints = wallet_metadata
+            .invocation_endpoints()
+            .into_iter()
+            .map(Url::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            endpoints,
+            [
+                "openid4vp://",
+                "haip://",
+                "https://wallet.example.com/authorize"
+            ]
+        );
+
+        let preferred = |preferences: &[&str]| {
+            let preferences = preferences
+                .iter()
+                .map(|p| p.parse().unwrap())
+                .collect::<Vec<Url>>();
+            wallet_metadata
+                .preferred_authorization_endpoint(&preferences)
+                .to_string()
+        };
+        assert_eq!(preferred(&[]), "openid4vp://");
+        assert_eq!(preferred(&["eudi-openid4vp://", "haip://"]), "haip://");
+        assert_eq!(preferred(&["eudi-openid4vp://"]), "openid4vp://");
+        assert_eq!(
+            preferred(&["https://wallet.example.com/authorize"]),
+            "https://wallet.example.com/authorize"
+        );
+    }
+
+    #[test]
+    fn typed_parameters_and_extensions() {
+        let json = serde_json::json!({
+            "issuer": "https://self-issued.me/v2",
+            "authorization_endpoint": "openid4vp:",
+            "response_modes_supported": ["direct_post"],
+            "vp_formats_supported": { "mso_mdoc": {} },
+            "client_id_schemes_supported": ["x509_san_dns"],
+            "presentation_definition_uri_supported": false,
+            "subject_syntax_types_supported": ["urn:ietf:params:oauth:jwk-thumbprint"]
+        });
+        let wallet_metadata: WalletMetadata = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(
+            wallet_metadata.issuer().unwrap().0,
+            "https://self-issued.me/v2"
+        );
+        assert!(wallet_metadata.response_types_supported().is_none());
+        assert_eq!(
+            wallet_metadata.response_modes_supported().unwrap().0,
+            [ResponseMode::DirectPost]
+        );
+        assert!(wallet_metadata.supports_client_id_scheme(&ClientIdScheme::X509SanDns));
+        assert!(!wallet_metadata.supports_client_id_scheme(&ClientIdScheme::PreRegistered));
+        assert!(WalletMetadata::openid4vp_scheme_static()
+            .supports_client_id_scheme(&ClientIdScheme::PreRegistered));
+        assert!(!wallet_metadata.supports_presentation_definition_uri());
+        assert!(WalletMetadata::openid4vp_scheme_static().supports_presentation_definition_uri());
+        assert_eq!(
+            Json::from(wallet_metadata.extensions().clone()),
+            serde_json::json!({
+                "subject_syntax_types_supported": ["urn:ietf:params:oauth:jwk-thumbprint"]
+            })
+        );
+        assert_eq!(serde_json::to_value(wallet_metadata).unwrap(), json);
+
+        assert!(serde_json::from_value::<WalletMetadata>(serde_json::json!({
+            "authorization_endpoint": "openid4vp:",
+            "vp_formats_supported": {},
+            "response_modes_supported": "direct_post"
+        }))
+        .is_err());
+    }
 }
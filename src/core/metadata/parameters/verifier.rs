@@ -1,5 +1,5 @@
 use crate::core::credential_format::ClaimFormatMap;
-use crate::core::object::TypedParameter;
+use crate::core::object::{typed_parameter, TypedParameter};
 
 use anyhow::{Context, Error};
 use serde::{Deserialize, Serialize};
@@ -54,67 +54,17 @@ impl From<JWKs> for Json {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct RequireSignedRequestObject(pub bool);
+typed_parameter! {
+    #[derive(Debug, Clone)]
+    pub struct RequireSignedRequestObject(pub bool) = "require_signed_request_object";
 
-impl TypedParameter for RequireSignedRequestObject {
-    const KEY: &'static str = "require_signed_request_object";
-}
-
-impl TryFrom<Json> for RequireSignedRequestObject {
-    type Error = Error;
-
-    fn try_from(value: Json) -> Result<Self, Self::Error> {
-        Ok(Self(serde_json::from_value(value)?))
-    }
-}
-
-impl From<RequireSignedRequestObject> for Json {
-    fn from(value: RequireSignedRequestObject) -> Json {
-        Json::Bool(value.0)
-    }
-}
-
-#[derive(Debug, Clone)]
-pub struct AuthorizationEncryptedResponseAlg(pub String);
-
-impl TypedParameter for AuthorizationEncryptedResponseAlg {
-    const KEY: &'static str = "authorization_encrypted_response_alg";
-}
-
-impl TryFrom<Json> for AuthorizationEncryptedResponseAlg {
-    type Error = Error;
-
-    fn try_from(value: Json) -> Result<Self, Self::Error> {
-        Ok(Self(serde_json::from_value(value)?))
-    }
-}
-
-impl From<AuthorizationEncryptedResponseAlg> for Json {
-    fn from(value: AuthorizationEncryptedResponseAlg) -> Json {
-        Json::String(value.0)
-    }
-}
-
-#[derive(Debug, Clone)]
-pub struct AuthorizationEncryptedResponseEnc(pub String);
-
-impl TypedParameter for AuthorizationEncryptedResponseEnc {
-    const KEY: &'static str = "authorization_encrypted_response_enc";
-}
-
-impl TryFrom<Json> for AuthorizationEncryptedResponseEnc {
-    type Error = Error;
-
-    fn try_from(value: Json) -> Result<Self, Self::Error> {
-        Ok(Self(serde_json::from_value(value)?))
-    }
-}
+    #[derive(Debug, Clone)]
+    pub struct AuthorizationEncryptedResponseAlg(pub String) =
+        "authorization_encrypted_response_alg";
 
-impl From<AuthorizationEncryptedResponseEnc> for Json {
-    fn from(value: AuthorizationEncryptedResponseEnc) -> Json {
-        Json::String(value.0)
-    }
+    #[derive(Debug, Clone)]
+    pub struct AuthorizationEncryptedResponseEnc(pub String) =
+        "authorization_encrypted_response_enc";
 }
 
 #[cfg(test)]
@@ -1,21 +1,35 @@
 use crate::core::{
-    authorization_request::parameters::{ClientIdScheme, ResponseType},
-    credential_format::{ClaimFormatDesignation, ClaimFormatMap},
-    object::TypedParameter,
+    authorization_request::parameters::{ClientIdScheme, ResponseMode, ResponseType},
+    credential_format::{ClaimFormatDesignation, ClaimFormatMap, ClaimFormatPayload},
+    object::{typed_parameter, TypedParameter},
 };
 
 use anyhow::{bail, Error, Result};
 use serde_json::Value as Json;
 use url::Url;
 
+typed_parameter! {
+    #[derive(Debug, Clone)]
+    pub struct Issuer(pub String) = "issuer";
+}
+
+typed_parameter! {
+    /// A JWT signed by a trust authority, whose claims are metadata values that take precedence
+    /// over the plain ones, see
+    /// [RFC 8414 §2.1](https://www.rfc-editor.org/rfc/rfc8414.html#section-2.1) and
+    /// [WalletMetadata::verify_signed](crate::core::metadata::WalletMetadata::verify_signed).
+    #[derive(Debug, Clone)]
+    pub struct SignedMetadata(pub String) = "signed_metadata";
+}
+
 #[derive(Debug, Clone)]
-pub struct Issuer(pub String);
+pub struct AuthorizationEndpoint(pub Url);
 
-impl TypedParameter for Issuer {
-    const KEY: &'static str = "issuer";
+impl TypedParameter for AuthorizationEndpoint {
+    const KEY: &'static str = "authorization_endpoint";
 }
 
-impl TryFrom<Json> for Issuer {
+impl TryFrom<Json> for AuthorizationEndpoint {
     type Error = Error;
 
     fn try_from(value: Json) -> Result<Self, Self::Error> {
@@ -23,20 +37,25 @@ impl TryFrom<Json> for Issuer {
     }
 }
 
-impl From<Issuer> for Json {
-    fn from(value: Issuer) -> Json {
-        Json::String(value.0)
+impl From<AuthorizationEndpoint> for Json {
+    fn from(value: AuthorizationEndpoint) -> Json {
+        Json::String(value.0.to_string())
     }
 }
 
+/// Further endpoints the wallet can be invoked at, e.g. under other URI schemes such as `haip://`,
+/// in order of preference after the `authorization_endpoint`.
+///
+/// This parameter is not defined by OpenID4VP, verifiers that do not know it invoke the wallet at
+/// its `authorization_endpoint`.
 #[derive(Debug, Clone)]
-pub struct AuthorizationEndpoint(pub Url);
+pub struct AuthorizationEndpoints(pub Vec<Url>);
 
-impl TypedParameter for AuthorizationEndpoint {
-    const KEY: &'static str = "authorization_endpoint";
+impl TypedParameter for AuthorizationEndpoints {
+    const KEY: &'static str = "authorization_endpoints";
 }
 
-impl TryFrom<Json> for AuthorizationEndpoint {
+impl TryFrom<Json> for AuthorizationEndpoints {
     type Error = Error;
 
     fn try_from(value: Json) -> Result<Self, Self::Error> {
@@ -44,9 +63,15 @@ impl TryFrom<Json> for AuthorizationEndpoint {
     }
 }
 
-impl From<AuthorizationEndpoint> for Json {
-    fn from(value: AuthorizationEndpoint) -> Json {
-        Json::String(value.0.to_string())
+impl From<AuthorizationEndpoints> for Json {
+    fn from(value: AuthorizationEndpoints) -> Json {
+        Json::Array(
+            value
+                .0
+                .into_iter()
+                .map(|endpoint| Json::String(endpoint.to_string()))
+                .collect(),
+        )
     }
 }
 
@@ -79,6 +104,27 @@ impl From<ResponseTypesSupported> for Json {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct ResponseModesSupported(pub Vec<ResponseMode>);
+
+impl TypedParameter for ResponseModesSupported {
+    const KEY: &'static str = "response_modes_supported";
+}
+
+impl TryFrom<Json> for ResponseModesSupported {
+    type Error = Error;
+
+    fn try_from(value: Json) -> Result<Self, Self::Error> {
+        Ok(Self(serde_json::from_value(value)?))
+    }
+}
+
+impl From<ResponseModesSupported> for Json {
+    fn from(value: ResponseModesSupported) -> Json {
+        Json::Array(value.0.into_iter().map(Json::from).collect())
+    }
+}
+
 // TODO: Client ID scheme types?
 #[derive(Debug, Clone)]
 pub struct ClientIdSchemesSupported(pub Vec<ClientIdScheme>);
@@ -113,27 +159,62 @@ impl Default for ClientIdSchemesSupported {
     }
 }
 
+typed_parameter! {
+    /// Whether the wallet retrieves presentation definitions passed by reference, with
+    /// `presentation_definition_uri`. Wallets that omit it support them.
+    #[derive(Debug, Clone)]
+    pub struct PresentationDefinitionUriSupported(pub bool) =
+        "presentation_definition_uri_supported";
+}
+
+/// The Client Identifier Prefixes supported by the wallet, the name later drafts give to
+/// [ClientIdSchemesSupported]. Prefixes are parsed with [ClientIdScheme::from_prefix].
 #[derive(Debug, Clone)]
-pub struct RequestObjectSigningAlgValuesSupported(pub Vec<String>);
+pub struct ClientIdPrefixesSupported(pub Vec<ClientIdScheme>);
 
-impl TypedParameter for RequestObjectSigningAlgValuesSupported {
-    const KEY: &'static str = "request_object_signing_alg_values_supported";
+impl TypedParameter for ClientIdPrefixesSupported {
+    const KEY: &'static str = "client_id_prefixes_supported";
 }
 
-impl TryFrom<Json> for RequestObjectSigningAlgValuesSupported {
+impl TryFrom<Json> for ClientIdPrefixesSupported {
     type Error = Error;
 
     fn try_from(value: Json) -> Result<Self, Self::Error> {
-        Ok(Self(serde_json::from_value(value)?))
+        let prefixes: Vec<String> = serde_json::from_value(value)?;
+        Ok(Self(
+            prefixes
+                .into_iter()
+                .map(ClientIdScheme::from_prefix)
+                .collect(),
+        ))
     }
 }
 
-impl From<RequestObjectSigningAlgValuesSupported> for Json {
-    fn from(value: RequestObjectSigningAlgValuesSupported) -> Json {
-        Json::Array(value.0.into_iter().map(Json::from).collect())
+impl From<ClientIdPrefixesSupported> for Json {
+    fn from(value: ClientIdPrefixesSupported) -> Json {
+        Json::Array(
+            value
+                .0
+                .iter()
+                .map(ClientIdScheme::prefix)
+                .map(Json::from)
+                .collect(),
+        )
     }
 }
 
+impl Default for ClientIdPrefixesSupported {
+    fn default() -> Self {
+        Self(vec![ClientIdScheme::PreRegistered])
+    }
+}
+
+typed_parameter! {
+    #[derive(Debug, Clone)]
+    pub struct RequestObjectSigningAlgValuesSupported(pub Vec<String>) =
+        "request_object_signing_alg_values_supported";
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct VpFormatsSupported(pub ClaimFormatMap);
 
@@ -161,48 +242,84 @@ impl VpFormatsSupported {
     pub fn is_claim_format_supported(&self, designation: &ClaimFormatDesignation) -> bool {
         self.0.contains_key(designation)
     }
-}
-
-#[derive(Debug, Clone)]
-pub struct AuthorizationEncryptionAlgValuesSupported(pub Vec<String>);
-
-impl TypedParameter for AuthorizationEncryptionAlgValuesSupported {
-    const KEY: &'static str = "authorization_encryption_alg_values_supported";
-}
 
-impl TryFrom<Json> for AuthorizationEncryptionAlgValuesSupported {
-    type Error = Error;
-
-    fn try_from(value: Json) -> Result<Self, Self::Error> {
-        Ok(Self(serde_json::from_value(value)?))
+    /// Check that the formats are expressed as the [claim format
+    /// registry](https://identity.foundation/claim-format-registry/#registry) defines them.
+    ///
+    /// JWT formats list their algorithms with `alg` or `alg_values_supported`, Linked Data Proof
+    /// formats with `proof_type`, and the lists must not be empty. Other formats must be
+    /// described by an object.
+    pub fn validate(&self) -> Result<()> {
+        if self.0.is_empty() {
+            bail!("no vp formats are supported")
+        }
+        for (designation, payload) in &self.0 {
+            let name = String::from(designation.clone());
+            if name.is_empty() {
+                bail!("the vp format name is empty")
+            }
+            match payload {
+                ClaimFormatPayload::Alg(values)
+                | ClaimFormatPayload::AlgValuesSupported(values)
+                | ClaimFormatPayload::ProofType(values)
+                    if values.is_empty() =>
+                {
+                    bail!("the algorithms of the vp format '{name}' are empty")
+                }
+                ClaimFormatPayload::Alg(_) | ClaimFormatPayload::AlgValuesSupported(_)
+                    if !is_jwt(designation) =>
+                {
+                    bail!("the vp format '{name}' is not a JWT format, but lists JWT algorithms")
+                }
+                ClaimFormatPayload::ProofType(_) if !is_ldp(designation) => {
+                    bail!("the vp format '{name}' is not a Linked Data Proof format, but lists proof types")
+                }
+                ClaimFormatPayload::Json(value) if !value.is_object() => {
+                    bail!("the vp format '{name}' is not described by an object")
+                }
+                _ => {}
+            }
+        }
+        Ok(())
     }
 }
 
-impl From<AuthorizationEncryptionAlgValuesSupported> for Json {
-    fn from(value: AuthorizationEncryptionAlgValuesSupported) -> Json {
-        Json::Array(value.0.into_iter().map(Json::from).collect())
-    }
+fn is_jwt(designation: &ClaimFormatDesignation) -> bool {
+    matches!(
+        designation,
+        ClaimFormatDesignation::Jwt
+            | ClaimFormatDesignation::JwtVc
+            | ClaimFormatDesignation::JwtVp
+            | ClaimFormatDesignation::JwtVcJson
+            | ClaimFormatDesignation::JwtVpJson
+    )
 }
 
-#[derive(Debug, Clone)]
-pub struct AuthorizationEncryptionEncValuesSupported(pub Vec<String>);
-
-impl TypedParameter for AuthorizationEncryptionEncValuesSupported {
-    const KEY: &'static str = "authorization_encryption_enc_values_supported";
+fn is_ldp(designation: &ClaimFormatDesignation) -> bool {
+    matches!(
+        designation,
+        ClaimFormatDesignation::Ldp | ClaimFormatDesignation::LdpVc | ClaimFormatDesignation::LdpVp
+    )
 }
 
-impl TryFrom<Json> for AuthorizationEncryptionEncValuesSupported {
-    type Error = Error;
+typed_parameter! {
+    #[derive(Debug, Clone)]
+    pub struct AuthorizationEncryptionAlgValuesSupported(pub Vec<String>) =
+        "authorization_encryption_alg_values_supported";
 
-    fn try_from(value: Json) -> Result<Self, Self::Error> {
-        Ok(Self(serde_json::from_value(value)?))
-    }
-}
+    #[derive(Debug, Clone)]
+    pub struct AuthorizationEncryptionEncValuesSupported(pub Vec<String>) =
+        "authorization_encryption_enc_values_supported";
 
-impl From<AuthorizationEncryptionEncValuesSupported> for Json {
-    fn from(value: AuthorizationEncryptionEncValuesSupported) -> Json {
-        Json::Array(value.0.into_iter().map(Json::from).collect())
-    }
+    /// The JWE key management algorithms the wallet can decrypt Request Objects with.
+    #[derive(Debug, Clone)]
+    pub struct RequestObjectEncryptionAlgValuesSupported(pub Vec<String>) =
+        "request_object_encryption_alg_values_supported";
+
+    /// The JWE content encryption algorithms the wallet can decrypt Request Objects with.
+    #[derive(Debug, Clone)]
+    pub struct RequestObjectEncryptionEncValuesSupported(pub Vec<String>) =
+        "request_object_encryption_enc_values_supported";
 }
 
 #[cfg(test)]
@@ -240,6 +357,12 @@ mod test {
             ],
             "authorization_encryption_enc_values_supported": [
               "A256GCM"
+            ],
+            "request_object_encryption_alg_values_supported": [
+              "ECDH-ES"
+            ],
+            "request_object_encryption_enc_values_supported": [
+              "A128GCM"
             ]
         }
         ))
@@ -276,6 +399,32 @@ mod test {
         assert!(v.iter().all(|x| exp.contains(x)));
     }
 
+    #[test]
+    fn client_id_prefixes_supported() {
+        let ClientIdPrefixesSupported(v) = ClientIdPrefixesSupported::try_from(json!([
+            "decentralized_identifier",
+            "openid_federation",
+            "x509_san_dns"
+        ]))
+        .unwrap();
+        assert_eq!(
+            v,
+            [
+                ClientIdScheme::Did,
+                ClientIdScheme::EntityId,
+                ClientIdScheme::X509SanDns
+            ]
+        );
+        assert_eq!(
+            Json::from(ClientIdPrefixesSupported(v)),
+            json!([
+                "decentralized_identifier",
+                "openid_federation",
+                "x509_san_dns"
+            ])
+        );
+    }
+
     #[test]
     fn request_object_signing_alg_values_supported() {
         let exp = ["ES256".to_string()];
@@ -302,6 +451,14 @@ mod test {
         assert!(v.iter().all(|x| exp.contains(x)));
     }
 
+    #[test]
+    fn request_object_encryption_values_supported() {
+        let RequestObjectEncryptionAlgValuesSupported(v) = metadata().get().unwrap().unwrap();
+        assert_eq!(v, ["ECDH-ES"]);
+        let RequestObjectEncryptionEncValuesSupported(v) = metadata().get().unwrap().unwrap();
+        assert_eq!(v, ["A128GCM"]);
+    }
+
     #[test]
     fn authorization_encryption_enc_values_supported() {
         let exp = ["A256GCM".to_string()];
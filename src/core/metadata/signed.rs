@@ -0,0 +1,179 @@
+use std::{collections::BTreeMap, time::SystemTime};
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value as Json};
+use ssi::jwk::{Algorithm, JWK};
+
+use crate::core::{
+    object::{TypedParameter, UntypedObject},
+    util::unix_time,
+};
+
+use super::{parameters::wallet::SignedMetadata, WalletMetadata};
+
+/// The claims of signed metadata that are not metadata values.
+const JWT_CLAIMS: &[&str] = &["iss", "sub", "aud", "exp", "nbf", "iat", "jti"];
+
+/// The trust authorities whose [signed metadata](SignedMetadata) is trusted, with their public
+/// keys, by issuer (the `iss` of the signed metadata).
+#[derive(Debug, Clone, Default)]
+pub struct MetadataTrustAnchors {
+    keys: BTreeMap<String, Vec<JWK>>,
+}
+
+impl MetadataTrustAnchors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust the metadata signed by `issuer` with `jwk`. An issuer may have several keys, e.g.
+    /// while rotating them.
+    pub fn add(&mut self, issuer: impl Into<String>, jwk: JWK) {
+        self.keys
+            .entry(issuer.into())
+            .or_default()
+            .push(jwk.to_public());
+    }
+
+    /// Trust the metadata signed by `issuer` with `jwk`, see [add](Self::add).
+    pub fn with_anchor(mut self, issuer: impl Into<String>, jwk: JWK) -> Self {
+        self.add(issuer, jwk);
+        self
+    }
+
+    /// The keys of `issuer`, if it is trusted.
+    pub fn keys(&self, issuer: &str) -> Option<&[JWK]> {
+        self.keys.get(issuer).map(Vec::as_slice)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl WalletMetadata {
+    /// Sign the metadata as the trust authority `issuer`, with its private `jwk`, and set the
+    /// result as the [signed metadata](Self::signed_metadata).
+    ///
+    /// Every parameter of the metadata is signed. The algorithm is the one of the key, `ES256`
+    /// if it has none.
+    pub fn sign(&mut self, issuer: impl Into<String>, jwk: &JWK) -> Result<()> {
+        self.signed_metadata = None;
+        let mut claims = UntypedObject::from(self.clone()).0;
+        claims.insert("iss".into(), issuer.into().into());
+        claims.insert("iat".into(), unix_time(SystemTime::now())?.into());
+
+        let algorithm = jwk.get_algorithm().unwrap_or(Algorithm::ES256);
+        let jwt = ssi::claims::jwt::encode_sign(algorithm, &claims, jwk)
+            .context("failed to sign the wallet metadata")?;
+        self.signed_metadata = Some(SignedMetadata(jwt));
+        Ok(())
+    }
+
+    /// Verify the [signed metadata](Self::signed_metadata) against the trust `anchors`, and return
+    /// the metadata it signs.
+    ///
+    /// Only the signed values are kept: plain values take no precedence over them, as
+    /// [RFC 8414](https://www.rfc-editor.org/rfc/rfc8414.html#section-2.1) requires, and plain
+    /// values that are not signed are dropped, so that they cannot add e.g. an endpoint.
+    ///
+    /// Fails if the metadata is not signed, if it is signed by an untrusted issuer or with an
+    /// unknown key, if it has expired, or if the resulting metadata is
+    /// [invalid](Self::validate).
+    pub fn verify_signed(&self, anchors: &MetadataTrustAnchors) -> Result<Self> {
+        let Some(SignedMetadata(jwt)) = &self.signed_metadata else {
+            bail!("the wallet metadata is not signed")
+        };
+
+        let unverified: Map<String, Json> =
+            ssi::claims::jwt::decode_unverified(jwt).context("the signed metadata is not a JWT")?;
+        let Some(Json::String(issuer)) = unverified.get("iss") else {
+            bail!("the signed metadata has no 'iss'")
+        };
+        let Some(keys) = anchors.keys(issuer) else {
+            bail!("the signed metadata was issued by '{issuer}', which is not trusted")
+        };
+        let mut claims: Map<String, Json> = keys
+            .iter()
+            .find_map(|jwk| ssi::claims::jwt::decode_verify(jwt, jwk).ok())
+            .with_context(|| {
+                format!("the signed metadata could not be verified with the keys of '{issuer}'")
+            })?;
+
+        if let Some(exp) = claims.get("exp") {
+            let exp = exp
+                .as_u64()
+                .context("the 'exp' of the signed metadata is not a timestamp")?;
+            if exp < unix_time(SystemTime::now())? {
+                bail!("the signed metadata expired at {exp}")
+            }
+        }
+
+        claims.retain(|claim, _| {
+            !JWT_CLAIMS.contains(&claim.as_str()) && claim != SignedMetadata::KEY
+        });
+        let mut metadata: Self = UntypedObject(claims)
+            .try_into()
+            .context("the signed metadata has an invalid parameter")?;
+        metadata
+            .validate()
+            .context("the signed metadata is invalid")?;
+        metadata.signed_metadata = self.signed_metadata.clone();
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn metadata() -> WalletMetadata {
+        serde_json::from_value(json!({
+            "authorization_endpoint": "https://wallet.example.com/authorize",
+            "response_types_supported": ["vp_token"],
+            "vp_formats_supported": { "jwt_vp_json": { "alg_values_supported": ["ES256"] } }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let authority = JWK::generate_p256();
+        let anchors = MetadataTrustAnchors::new()
+            .with_anchor("https://federation.example.com", authority.clone());
+
+        let mut signed = metadata();
+        signed
+            .sign("https://federation.example.com", &authority)
+            .unwrap();
+
+        // Plain values are not trusted, only signed ones are kept.
+        let mut tampered: UntypedObject = signed.clone().into();
+        tampered.0.insert(
+            "authorization_endpoint".into(),
+            "https://attacker.example.com/authorize".into(),
+        );
+        tampered.0.insert(
+            "authorization_endpoints".into(),
+            json!(["https://attacker.example.com/other"]),
+        );
+        let tampered: WalletMetadata = tampered.try_into().unwrap();
+        let verified = tampered.verify_signed(&anchors).unwrap();
+        assert_eq!(
+            verified.authorization_endpoint().0.as_str(),
+            "https://wallet.example.com/authorize"
+        );
+        assert!(verified.authorization_endpoints().is_none());
+        assert!(verified.signed_metadata().is_some());
+
+        assert!(metadata().verify_signed(&anchors).is_err());
+        let untrusted = MetadataTrustAnchors::new()
+            .with_anchor("https://federation.example.com", JWK::generate_p256());
+        assert!(signed.verify_signed(&untrusted).is_err());
+        let other_issuer =
+            MetadataTrustAnchors::new().with_anchor("https://other.example.com", authority.clone());
+        assert!(signed.verify_signed(&other_issuer).is_err());
+    }
+}
@@ -1,9 +1,17 @@
 pub mod authorization_request;
+pub mod consts;
 pub mod credential_format;
+pub mod dcql_query;
+pub mod draft;
+pub mod events;
 pub mod input_descriptor;
+pub mod jwe;
 pub mod metadata;
 pub mod object;
 pub mod presentation_definition;
 pub mod presentation_submission;
 pub mod response;
+#[cfg(any(feature = "wallet", feature = "verifier"))]
+pub(crate) mod spans;
+pub mod transaction_data;
 pub mod util;
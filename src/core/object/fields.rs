@@ -0,0 +1,102 @@
+use anyhow::{bail, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value as Json};
+use tracing::warn;
+
+use super::UnknownParameters;
+
+/// Structures whose unknown fields can be detected before they are deserialized, so that they are
+/// parsed leniently (as serde does by default) or strictly, as chosen by the caller.
+///
+/// Conformance tooling can reject peers that send misspelled or non-standard fields, which are
+/// otherwise silently ignored:
+///
+/// ```
+/// # use openid4vp::core::object::{fields::KnownFields, UnknownParameters};
+/// # use openid4vp::core::presentation_definition::PresentationDefinition;
+/// let value = serde_json::json!({ "id": "pd", "input_descriptors": [], "purpse": "typo" });
+///
+/// assert!(PresentationDefinition::from_value_with(value.clone(), UnknownParameters::Ignore).is_ok());
+/// assert!(PresentationDefinition::from_value_with(value, UnknownParameters::Reject).is_err());
+/// ```
+pub trait KnownFields: DeserializeOwned {
+    /// Record the paths of the fields of `value` (and of its nested structures) that are not
+    /// known, `value` being at `path`, e.g. `input_descriptors[0].constraints`.
+    fn collect_unknown_fields(value: &Json, path: &str, unknown: &mut Vec<String>);
+
+    /// The paths of the unknown fields of `value`.
+    fn unknown_fields(value: &Json) -> Vec<String> {
+        let mut unknown = Vec::new();
+        Self::collect_unknown_fields(value, "", &mut unknown);
+        unknown
+    }
+
+    /// Deserialize `value`, handling its unknown fields as `unknown_fields` says.
+    fn from_value_with(value: Json, unknown_fields: UnknownParameters) -> Result<Self> {
+        if unknown_fields != UnknownParameters::Ignore {
+            let unknown = Self::unknown_fields(&value);
+            if !unknown.is_empty() {
+                match unknown_fields {
+                    UnknownParameters::Reject => bail!("unknown fields: {unknown:?}"),
+                    _ => warn!("unknown fields: {unknown:?}"),
+                }
+            }
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// Record the keys of `value` that are not `known`, if it is an object, and return the object.
+///
+/// Values that are not objects are left for deserialization to reject.
+pub(crate) fn check_keys<'a>(
+    value: &'a Json,
+    path: &str,
+    known: &[&str],
+    unknown: &mut Vec<String>,
+) -> Option<&'a Map<String, Json>> {
+    let object = value.as_object()?;
+    unknown.extend(
+        object
+            .keys()
+            .filter(|key| !known.contains(&key.as_str()))
+            .map(|key| field_path(path, key)),
+    );
+    Some(object)
+}
+
+/// Record the unknown fields of the structure at `key` of `object`, if present.
+pub(crate) fn check_field<T: KnownFields>(
+    object: &Map<String, Json>,
+    path: &str,
+    key: &str,
+    unknown: &mut Vec<String>,
+) {
+    if let Some(value) = object.get(key) {
+        T::collect_unknown_fields(value, &field_path(path, key), unknown);
+    }
+}
+
+/// Record the unknown fields of the structures in the array at `key` of `object`, if present.
+pub(crate) fn check_items<T: KnownFields>(
+    object: &Map<String, Json>,
+    path: &str,
+    key: &str,
+    unknown: &mut Vec<String>,
+) {
+    let Some(Json::Array(items)) = object.get(key) else {
+        return;
+    };
+    let path = field_path(path, key);
+    for (i, item) in items.iter().enumerate() {
+        T::collect_unknown_fields(item, &format!("{path}[{i}]"), unknown);
+    }
+}
+
+fn field_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{path}.{key}")
+    }
+}
@@ -1,8 +1,12 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, fmt};
 
-use anyhow::{Context, Error, Result};
+use anyhow::{bail, Context, Error, Result};
 use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value as Json};
+use serde_json::{map::Entry, Map, Value as Json};
+use tracing::warn;
+
+pub mod fields;
+pub mod registry;
 
 /// An untyped (JSON) Object from which [TypedParameters](TypedParameter) can be parsed.
 ///
@@ -16,32 +20,168 @@ pub trait TypedParameter:
     TryFrom<Json, Error = anyhow::Error> + TryInto<Json> + Clone + std::fmt::Debug
 {
     const KEY: &'static str;
+
+    /// Parse the parameter from a borrowed value.
+    ///
+    /// The value is cloned by default, parameters that can be deserialized from a reference
+    /// override this to avoid copying large values.
+    fn try_from_ref(value: &Json) -> Result<Self> {
+        value.clone().try_into()
+    }
 }
 
+/// Define newtype [TypedParameters](TypedParameter), that are parsed from JSON with serde and
+/// converted back with [From], e.g. for strings, booleans and lists of strings.
+///
+/// ```ignore
+/// typed_parameter! {
+///     /// `state` field in the Authorization Request.
+///     #[derive(Debug, Clone)]
+///     pub struct State(pub String) = "state";
+/// }
+/// ```
+macro_rules! typed_parameter {
+    ($(
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident($field_vis:vis $ty:ty) = $key:literal;
+    )*) => {$(
+        $(#[$attr])*
+        $vis struct $name($field_vis $ty);
+
+        impl $crate::core::object::TypedParameter for $name {
+            const KEY: &'static str = $key;
+
+            fn try_from_ref(value: &::serde_json::Value) -> ::anyhow::Result<Self> {
+                ::core::result::Result::Ok(Self(<$ty as ::serde::Deserialize>::deserialize(
+                    value,
+                )?))
+            }
+        }
+
+        impl TryFrom<::serde_json::Value> for $name {
+            type Error = ::anyhow::Error;
+
+            fn try_from(value: ::serde_json::Value) -> ::anyhow::Result<Self> {
+                ::core::result::Result::Ok(Self(::serde_json::from_value(value)?))
+            }
+        }
+
+        impl From<$name> for ::serde_json::Value {
+            fn from(value: $name) -> Self {
+                Self::from(value.0)
+            }
+        }
+    )*};
+}
+
+pub(crate) use typed_parameter;
+
 impl UntypedObject {
     /// Get a [TypedParameter] from the Object or return the default value.
     ///
-    /// Note that this method clones the underlying data.
+    /// Note that this method may clone the underlying data, see [TypedParameter::try_from_ref].
     pub fn get_or_default<T: TypedParameter + Default>(&self) -> Result<T> {
         Ok(self
             .0
             .get(T::KEY)
-            .cloned()
-            .map(TryInto::try_into)
+            .map(T::try_from_ref)
             .transpose()?
             .unwrap_or_default())
     }
 
     /// Get a [TypedParameter] from the Object.
     ///
-    /// Note that this method clones the underlying data.
+    /// Note that this method may clone the underlying data, see [TypedParameter::try_from_ref].
     pub fn get<T: TypedParameter>(&self) -> Option<Result<T>> {
-        Some(self.0.get(T::KEY)?.clone().try_into().map_err(Into::into))
+        Some(T::try_from_ref(self.0.get(T::KEY)?))
+    }
+
+    /// Whether the Object has a [TypedParameter], without parsing it.
+    pub fn contains<T: TypedParameter>(&self) -> bool {
+        self.0.contains_key(T::KEY)
+    }
+
+    /// Borrow the unparsed value of a [TypedParameter], e.g. to read a large
+    /// `presentation_definition` or `client_metadata` without copying it.
+    pub fn get_ref<T: TypedParameter>(&self) -> Option<&Json> {
+        self.0.get(T::KEY)
+    }
+
+    /// Mutably borrow the unparsed value of a [TypedParameter].
+    pub fn get_mut<T: TypedParameter>(&mut self) -> Option<&mut Json> {
+        self.0.get_mut(T::KEY)
+    }
+
+    /// The entry of a [TypedParameter], to modify its unparsed value in place or insert it if
+    /// it is missing.
+    ///
+    /// ```
+    /// # use openid4vp::core::{authorization_request::parameters::ClientMetadata, object::UntypedObject};
+    /// let mut request = UntypedObject::default();
+    /// request
+    ///     .entry::<ClientMetadata>()
+    ///     .or_insert_with(|| serde_json::json!({}))
+    ///     .as_object_mut()
+    ///     .unwrap()
+    ///     .insert("client_name".into(), "Verifier".into());
+    /// assert!(request.contains::<ClientMetadata>());
+    /// ```
+    pub fn entry<T: TypedParameter>(&mut self) -> Entry<'_> {
+        self.0.entry(T::KEY)
     }
 
     /// Remove a [TypedParameter] from the Object.
     pub fn remove<T: TypedParameter>(&mut self) -> Option<Result<T>> {
-        Some(self.0.remove(T::KEY)?.try_into().map_err(Into::into))
+        Some(self.0.remove(T::KEY)?.try_into())
+    }
+
+    /// Remove an optional [TypedParameter] from the Object, failing if it cannot be parsed.
+    pub fn take<T: TypedParameter>(&mut self) -> Result<Option<T>> {
+        self.remove()
+            .map(ParsingErrorContext::parsing_error)
+            .transpose()
+    }
+
+    /// The keys of the parameters in the Object, in lexicographic order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    /// The parameters in the Object, unparsed, in lexicographic order of their keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Json)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// The number of parameters in the Object.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Merge the parameters of `other` into the Object, including the ones this library does
+    /// not know.
+    ///
+    /// Parameters that both objects have with different values are left as they are in the
+    /// Object, and returned, so that the caller decides which value wins.
+    pub fn merge(&mut self, other: UntypedObject) -> Vec<MergeConflict> {
+        let mut conflicts = Vec::new();
+        for (key, incoming) in other.0 {
+            match self.0.get(&key) {
+                None => {
+                    self.0.insert(key, incoming);
+                }
+                Some(existing) if *existing == incoming => {}
+                Some(existing) => conflicts.push(MergeConflict {
+                    existing: existing.clone(),
+                    key,
+                    incoming,
+                }),
+            }
+        }
+        conflicts
     }
 
     /// Insert a [TypedParameter].
@@ -53,19 +193,28 @@ impl UntypedObject {
     pub fn insert<T: TypedParameter>(&mut self, t: T) -> Option<Result<T>> {
         match t.try_into() {
             Err(_) => Some(Err(Error::msg("failed to parse typed parameter"))),
-            Ok(value) => Some(
-                self.0
-                    .insert(T::KEY.to_owned(), value)?
-                    .try_into()
-                    .map_err(Into::into),
-            ),
+            Ok(value) => Some(self.0.insert(T::KEY.to_owned(), value)?.try_into()),
         }
     }
 
+    /// The keys of the Object that are not in `known`, e.g. typos of parameter names.
+    pub fn unknown_keys<'a>(&'a self, known: &[&str]) -> Vec<&'a str> {
+        self.0
+            .keys()
+            .map(String::as_str)
+            .filter(|key| !known.contains(key))
+            .collect()
+    }
+
     /// Flatten the structure for posting as a form.
+    ///
+    /// Strings are posted as they are, while objects and arrays (e.g. a `vp_token` with several
+    /// presentations, or a `presentation_submission`) are posted as JSON strings. Other scalars
+    /// are posted as their JSON serialization, and `null` parameters are omitted.
     pub(crate) fn flatten_for_form(self) -> Result<BTreeMap<String, String>> {
         self.0
             .into_iter()
+            .filter(|(_, v)| !v.is_null())
             .map(|(k, v)| {
                 if let Json::String(s) = v {
                     return Ok((k, s));
@@ -76,6 +225,25 @@ impl UntypedObject {
             })
             .collect()
     }
+
+    /// Parse the parameters of a form, the inverse of [flatten_for_form](Self::flatten_for_form).
+    ///
+    /// Values are only parsed as JSON if they are JSON objects or arrays, so that strings which
+    /// happen to be valid JSON (e.g. a numeric `state`) are kept as they are. Values that look
+    /// like JSON but do not parse are also kept as strings, for the typed parameters to reject.
+    pub(crate) fn from_form(form: BTreeMap<String, String>) -> Self {
+        Self(
+            form.into_iter()
+                .map(|(k, v)| {
+                    let nested = v.starts_with('{') || v.starts_with('[');
+                    match serde_json::from_str(&v) {
+                        Ok(v @ (Json::Object(_) | Json::Array(_))) if nested => (k, v),
+                        _ => (k, Json::String(v)),
+                    }
+                })
+                .collect(),
+        )
+    }
 }
 
 impl From<UntypedObject> for Json {
@@ -84,6 +252,64 @@ impl From<UntypedObject> for Json {
     }
 }
 
+/// A parameter that two [merged](UntypedObject::merge) objects have with different values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    pub key: String,
+    /// The value that was kept.
+    pub existing: Json,
+    /// The value that was not merged.
+    pub incoming: Json,
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' is both {} and {}",
+            self.key, self.existing, self.incoming
+        )
+    }
+}
+
+/// How parameters that this library does not know are handled when an object is parsed.
+///
+/// OAuth 2.0 requires unknown parameters to be ignored, which also hides typos in parameter
+/// names. Stricter modes help during development and conformance testing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownParameters {
+    /// Ignore unknown parameters.
+    #[default]
+    Ignore,
+    /// Log unknown parameters as warnings.
+    Warn,
+    /// Reject objects with unknown parameters.
+    Reject,
+}
+
+impl UnknownParameters {
+    /// Check the keys of `object` against the `known` ones, returning the unknown keys.
+    ///
+    /// `name` describes the object in warnings and errors, e.g. `the request`.
+    pub fn check<'a>(
+        self,
+        object: &'a UntypedObject,
+        known: &[&str],
+        name: &str,
+    ) -> Result<Vec<&'a str>> {
+        let unknown = object.unknown_keys(known);
+        if unknown.is_empty() {
+            return Ok(unknown);
+        }
+        match self {
+            Self::Ignore => {}
+            Self::Warn => warn!("{name} has unknown parameters: {unknown:?}"),
+            Self::Reject => bail!("{name} has unknown parameters: {unknown:?}"),
+        }
+        Ok(unknown)
+    }
+}
+
 pub trait ParsingErrorContext {
     type T: TypedParameter;
 
@@ -106,3 +332,112 @@ impl<T: TypedParameter> ParsingErrorContext for Result<T> {
         self.context(format!("'{}' could not be parsed", T::KEY))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    typed_parameter! {
+        #[derive(Debug, Clone)]
+        struct Origins(Vec<String>) = "expected_origins";
+    }
+
+    #[test]
+    fn borrowed_access() {
+        let mut object: UntypedObject =
+            serde_json::from_value(json!({ "expected_origins": ["https://example.com"] })).unwrap();
+
+        assert!(object.contains::<Origins>());
+        assert_eq!(
+            object.get_ref::<Origins>(),
+            Some(&json!(["https://example.com"]))
+        );
+        assert_eq!(
+            object.get::<Origins>().unwrap().unwrap().0,
+            ["https://example.com"]
+        );
+
+        object
+            .get_mut::<Origins>()
+            .and_then(Json::as_array_mut)
+            .unwrap()
+            .push("https://example.org".into());
+        assert_eq!(object.get::<Origins>().unwrap().unwrap().0.len(), 2);
+
+        object.0.remove("expected_origins");
+        object.entry::<Origins>().or_insert_with(|| json!([]));
+        assert!(object.get::<Origins>().unwrap().unwrap().0.is_empty());
+
+        object
+            .0
+            .insert("expected_origins".into(), json!("not a list"));
+        assert!(object.contains::<Origins>());
+        assert!(object.get::<Origins>().unwrap().is_err());
+    }
+
+    #[test]
+    fn take_iterate_and_merge() {
+        let mut object: UntypedObject = serde_json::from_value(json!({
+            "expected_origins": ["https://example.com"],
+            "nonce": "n",
+            "x_custom": 1
+        }))
+        .unwrap();
+        assert_eq!(
+            object.keys().collect::<Vec<_>>(),
+            ["expected_origins", "nonce", "x_custom"]
+        );
+        assert_eq!(object.iter().nth(2), Some(("x_custom", &json!(1))));
+
+        let origins = object.take::<Origins>().unwrap().unwrap();
+        assert_eq!(origins.0, ["https://example.com"]);
+        assert!(object.take::<Origins>().unwrap().is_none());
+        assert_eq!(object.len(), 2);
+
+        object.insert(Origins(vec![]));
+        object.0.insert("expected_origins".into(), json!(42));
+        assert!(object.take::<Origins>().is_err());
+
+        let other: UntypedObject =
+            serde_json::from_value(json!({ "nonce": "m", "state": "s", "x_custom": 1 })).unwrap();
+        let conflicts = object.merge(other);
+        assert_eq!(
+            conflicts,
+            [MergeConflict {
+                key: "nonce".into(),
+                existing: json!("n"),
+                incoming: json!("m"),
+            }]
+        );
+        assert_eq!(conflicts[0].to_string(), r#"'nonce' is both "n" and "m""#);
+        assert_eq!(
+            Json::from(object),
+            json!({ "nonce": "n", "state": "s", "x_custom": 1 })
+        );
+    }
+
+    #[test]
+    fn unknown_parameters() {
+        let object: UntypedObject =
+            serde_json::from_value(json!({ "nonce": "n", "presentation_defintion": {} })).unwrap();
+        let known = ["nonce", "presentation_definition"];
+
+        assert_eq!(object.unknown_keys(&known), ["presentation_defintion"]);
+        assert_eq!(
+            UnknownParameters::Warn
+                .check(&object, &known, "the request")
+                .unwrap(),
+            ["presentation_defintion"]
+        );
+        let error = UnknownParameters::Reject
+            .check(&object, &known, "the request")
+            .unwrap_err();
+        assert!(error.to_string().contains("presentation_defintion"));
+        assert!(UnknownParameters::Reject
+            .check(&object, &["nonce", "presentation_defintion"], "the request")
+            .unwrap()
+            .is_empty());
+    }
+}
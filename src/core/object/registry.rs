@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value as Json;
+
+use crate::core::{
+    authorization_request::{parameters::ClientMetadata, AuthorizationRequestObject},
+    response::AuthorizationResponse,
+};
+
+use super::{TypedParameter, UnknownParameters, UntypedObject};
+
+/// The object that a [registered](ParameterRegistry::register) parameter is carried in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ParameterLocation {
+    /// The parameters of an authorization request, or the claims of its Request Object.
+    Request,
+    /// The `client_metadata` of an authorization request.
+    ClientMetadata,
+    /// The parameters of an unencoded (or decrypted) authorization response.
+    Response,
+}
+
+impl ParameterLocation {
+    /// The parameters of the object that this library knows.
+    pub fn known_parameters(&self) -> &'static [&'static str] {
+        match self {
+            Self::Request => AuthorizationRequestObject::KNOWN_PARAMETERS,
+            Self::ClientMetadata => ClientMetadata::KNOWN_PARAMETERS,
+            Self::Response => AuthorizationResponse::KNOWN_PARAMETERS,
+        }
+    }
+}
+
+/// Parses a registered parameter, discarding the result.
+type Parser = fn(&Json) -> Result<()>;
+
+/// [TypedParameters](TypedParameter) defined outside of this library, e.g. by a national eID
+/// profile, that are validated along with the parameters this library knows.
+///
+/// Registered parameters are parsed whenever the object that carries them is validated, so that
+/// malformed values are rejected early, and are [known](UnknownParameters) to strict modes.
+/// They are read and written with [UntypedObject::get] and [UntypedObject::insert] like any other
+/// parameter.
+#[derive(Debug, Clone, Default)]
+pub struct ParameterRegistry {
+    parameters: BTreeMap<(ParameterLocation, &'static str), Parser>,
+}
+
+impl ParameterRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `T` as a parameter of the objects at `location`.
+    ///
+    /// Fails if this library already knows the parameter, or if it is already registered.
+    pub fn register<T: TypedParameter>(&mut self, location: ParameterLocation) -> Result<()> {
+        if location.known_parameters().contains(&T::KEY) {
+            bail!(
+                "'{}' is already a parameter of {location:?} objects",
+                T::KEY
+            )
+        }
+        if self
+            .parameters
+            .insert((location, T::KEY), |value| T::try_from_ref(value).map(drop))
+            .is_some()
+        {
+            bail!(
+                "'{}' is already registered for {location:?} objects",
+                T::KEY
+            )
+        }
+        Ok(())
+    }
+
+    /// Register `T` as a parameter of the objects at `location`, see [register](Self::register).
+    pub fn with_parameter<T: TypedParameter>(
+        mut self,
+        location: ParameterLocation,
+    ) -> Result<Self> {
+        self.register::<T>(location)?;
+        Ok(self)
+    }
+
+    /// The keys of the parameters registered for `location`.
+    pub fn keys(&self, location: ParameterLocation) -> impl Iterator<Item = &'static str> + '_ {
+        self.parameters
+            .keys()
+            .filter(move |(l, _)| *l == location)
+            .map(|(_, key)| *key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parameters.is_empty()
+    }
+
+    /// The parameters that this library knows at `location`, and the registered ones.
+    pub fn known_parameters(&self, location: ParameterLocation) -> Vec<&'static str> {
+        let mut known = location.known_parameters().to_vec();
+        known.extend(self.keys(location));
+        known
+    }
+
+    /// Parse the registered parameters of `object`, which is carried at `location`.
+    ///
+    /// Registered parameters are optional, only those present are parsed.
+    pub fn validate(&self, location: ParameterLocation, object: &UntypedObject) -> Result<()> {
+        for ((_, key), parse) in self.parameters.iter().filter(|((l, _), _)| *l == location) {
+            if let Some(value) = object.0.get(*key) {
+                parse(value).with_context(|| format!("'{key}' could not be parsed"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// [Validate](Self::validate) the registered parameters of `object`, then check its keys
+    /// against the [known parameters](Self::known_parameters), see [UnknownParameters::check].
+    pub fn check<'a>(
+        &self,
+        location: ParameterLocation,
+        object: &'a UntypedObject,
+        unknown_parameters: UnknownParameters,
+        name: &str,
+    ) -> Result<Vec<&'a str>> {
+        self.validate(location, object)
+            .with_context(|| format!("{name} has an invalid parameter"))?;
+        unknown_parameters.check(object, &self.known_parameters(location), name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::core::object::typed_parameter;
+
+    use super::*;
+
+    typed_parameter! {
+        #[derive(Debug, Clone)]
+        struct LevelOfAssurance(String) = "level_of_assurance";
+    }
+
+    typed_parameter! {
+        #[derive(Debug, Clone)]
+        struct ResponseState(String) = "state";
+    }
+
+    #[test]
+    fn register_and_check() {
+        let registry = ParameterRegistry::new()
+            .with_parameter::<LevelOfAssurance>(ParameterLocation::Request)
+            .unwrap();
+        assert!(registry
+            .clone()
+            .with_parameter::<LevelOfAssurance>(ParameterLocation::Request)
+            .is_err());
+        assert!(registry
+            .clone()
+            .with_parameter::<ResponseState>(ParameterLocation::Response)
+            .is_err());
+        assert_eq!(
+            registry
+                .keys(ParameterLocation::Request)
+                .collect::<Vec<_>>(),
+            ["level_of_assurance"]
+        );
+        assert_eq!(registry.keys(ParameterLocation::Response).count(), 0);
+
+        let object: UntypedObject =
+            serde_json::from_value(json!({ "nonce": "n", "level_of_assurance": "high" })).unwrap();
+        assert!(registry
+            .check(
+                ParameterLocation::Request,
+                &object,
+                UnknownParameters::Reject,
+                "the request"
+            )
+            .unwrap()
+            .is_empty());
+        assert!(ParameterRegistry::new()
+            .check(
+                ParameterLocation::Request,
+                &object,
+                UnknownParameters::Reject,
+                "the request"
+            )
+            .is_err());
+
+        let object: UntypedObject =
+            serde_json::from_value(json!({ "level_of_assurance": ["high"] })).unwrap();
+        let error = registry
+            .validate(ParameterLocation::Request, &object)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "'level_of_assurance' could not be parsed"
+        );
+        registry
+            .validate(ParameterLocation::ClientMetadata, &object)
+            .unwrap();
+    }
+}
@@ -1,12 +1,15 @@
 use super::credential_format::*;
 use super::input_descriptor::*;
+use super::object::fields::{check_items, check_keys, KnownFields};
 use super::presentation_submission::*;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
-use serde_json::Map;
+use serde_json::{Map, Value as Json};
+#[cfg(feature = "core")]
 use ssi::claims::jwt::VerifiablePresentation;
 
 /// A non-normative mappings of credential type(s) to requested fields.
@@ -246,38 +249,82 @@ impl PresentationDefinition {
             .collect()
     }
 
+    /// Check the structure of a descriptor map against the presentation definition.
+    ///
+    /// A wallet may submit more than one credential for an input descriptor, as several entries
+    /// with the same id and distinct paths. This reports every entry duplicating the id and path
+    /// of another entry, every entry whose id does not match an input descriptor, and, if there are no submission requirements, every input
+    /// descriptor without a corresponding entry. When submission requirements are present, they
+    /// determine which input descriptors are required, see
+    /// [PresentationDefinition::validate_submission_requirements].
+    pub fn check_descriptor_map(
+        &self,
+        descriptor_map: &[DescriptorMap],
+    ) -> Result<(), DescriptorMapErrors> {
+        let input_descriptors_map = self.input_descriptors_map();
+        let mut issues = Vec::new();
+
+        let mut seen = HashSet::new();
+        let mut seen_paths = HashSet::new();
+        for descriptor in descriptor_map {
+            seen.insert(descriptor.id());
+            if !seen_paths.insert((descriptor.id(), descriptor.path(), descriptor.path_nested())) {
+                let issue = DescriptorMapIssue::Duplicate(descriptor.id().clone());
+                if !issues.contains(&issue) {
+                    issues.push(issue);
+                }
+            }
+
+            if !input_descriptors_map.contains_key(descriptor.id()) {
+                issues.push(DescriptorMapIssue::Orphan(descriptor.id().clone()));
+            }
+        }
+
+        if self.submission_requirements.is_none() {
+            issues.extend(
+                self.input_descriptors
+                    .iter()
+                    .filter(|input_descriptor| !seen.contains(&input_descriptor.id().to_string()))
+                    .map(|input_descriptor| {
+                        DescriptorMapIssue::Missing(input_descriptor.id().to_string())
+                    }),
+            );
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(DescriptorMapErrors(issues))
+        }
+    }
+
     /// Validate a presentation submission against the presentation definition.
     ///
     /// This descriptor map is a map of descriptor objects, keyed by their id.
     ///
     /// For convenience, use [PresentationSubmission::descriptor_map_by_id] to generate this map.
     ///
-    /// Internally, this method will call [PresentationDefinition::validate_submission_requirements].
+    /// Internally, this method will call [PresentationDefinition::check_descriptor_map] and
+    /// [PresentationDefinition::validate_submission_requirements]. Errors from the former can be
+    /// downcast to [DescriptorMapErrors].
+    #[cfg(feature = "core")]
     pub fn validate_presentation(
         &self,
         verifiable_presentation: VerifiablePresentation,
         descriptor_map: &[DescriptorMap],
     ) -> Result<()> {
+        self.check_descriptor_map(descriptor_map)?;
+
         // Validate the submission requirements. This will
         // no-op if there are no submission requirements.
         self.validate_submission_requirements(descriptor_map)?;
 
         let input_descript_map = self.input_descriptors_map();
 
-        // Validate the submission requirements
-
         for descriptor in descriptor_map.iter() {
-            match input_descript_map.get(descriptor.id()) {
-                None => {
-                    bail!(
-                        "Descriptor map ID, {}, does not match a valid input descriptor.",
-                        descriptor.id()
-                    )
-                }
-                Some(input_descriptor) => {
-                    input_descriptor
-                        .validate_verifiable_presentation(&verifiable_presentation, descriptor)?;
-                }
+            if let Some(input_descriptor) = input_descript_map.get(descriptor.id()) {
+                input_descriptor
+                    .validate_verifiable_presentation(&verifiable_presentation, descriptor)?;
             }
         }
 
@@ -326,6 +373,75 @@ impl PresentationDefinition {
     }
 }
 
+/// A structural problem with a descriptor map, see [PresentationDefinition::check_descriptor_map].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DescriptorMapIssue {
+    /// More than one descriptor map entry has this id and the same path.
+    Duplicate(DescriptorMapId),
+    /// The descriptor map entry does not match any input descriptor.
+    Orphan(DescriptorMapId),
+    /// The required input descriptor with this id has no descriptor map entry.
+    Missing(String),
+}
+
+impl KnownFields for PresentationDefinition {
+    /// The fields of submission requirements and formats are not checked, as they are extensible.
+    fn collect_unknown_fields(value: &Json, path: &str, unknown: &mut Vec<String>) {
+        let known = [
+            "id",
+            "input_descriptors",
+            "submission_requirements",
+            "name",
+            "purpose",
+            "format",
+        ];
+        if let Some(object) = check_keys(value, path, &known, unknown) {
+            check_items::<InputDescriptor>(object, path, "input_descriptors", unknown);
+        }
+    }
+}
+
+impl fmt::Display for DescriptorMapIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DescriptorMapIssue::Duplicate(id) => {
+                write!(
+                    f,
+                    "descriptor map ID, {id}, appears more than once with the same path"
+                )
+            }
+            DescriptorMapIssue::Orphan(id) => {
+                write!(
+                    f,
+                    "descriptor map ID, {id}, does not match a valid input descriptor"
+                )
+            }
+            DescriptorMapIssue::Missing(id) => {
+                write!(f, "input descriptor, {id}, has no descriptor map entry")
+            }
+        }
+    }
+}
+
+/// Every structural problem found in a descriptor map.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DescriptorMapErrors(pub Vec<DescriptorMapIssue>);
+
+impl fmt::Display for DescriptorMapErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid descriptor map: ")?;
+        for (i, issue) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            issue.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DescriptorMapErrors {}
+
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct SubmissionRequirementObject {
     pub name: Option<String>,
@@ -371,13 +487,14 @@ impl SubmissionRequirement {
             .filter(|input_descriptor| input_descriptor.groups().contains(group))
             .collect::<Vec<&InputDescriptor>>();
 
-        // Filter for the descriptor maps that match the grouped input descriptors.
-        let group_count = decriptor_map
+        // Count the grouped input descriptors with at least one descriptor map, as several
+        // credentials may be submitted for the same input descriptor.
+        let group_count = grouped_input_descriptors
             .iter()
-            .filter(|descriptor| {
-                grouped_input_descriptors
+            .filter(|input_descriptor| {
+                decriptor_map
                     .iter()
-                    .any(|input_descriptor| input_descriptor.id() == descriptor.id())
+                    .any(|descriptor| input_descriptor.id() == descriptor.id())
             })
             .count();
 
@@ -548,4 +665,82 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_check_descriptor_map_reports_all_issues() {
+        let definition = PresentationDefinition::new(
+            "definition".into(),
+            InputDescriptor::new("a".into(), Constraints::new()),
+        )
+        .add_input_descriptor(InputDescriptor::new("b".into(), Constraints::new()))
+        .add_input_descriptor(InputDescriptor::new("c".into(), Constraints::new()));
+
+        let descriptor_map = vec![
+            DescriptorMap::new("a", ClaimFormatDesignation::JwtVpJson, "$".into()),
+            DescriptorMap::new("a", ClaimFormatDesignation::JwtVpJson, "$".into()),
+            DescriptorMap::new("d", ClaimFormatDesignation::JwtVpJson, "$".into()),
+        ];
+
+        let DescriptorMapErrors(issues) = definition
+            .check_descriptor_map(&descriptor_map)
+            .unwrap_err();
+
+        assert_eq!(
+            issues,
+            vec![
+                DescriptorMapIssue::Duplicate("a".into()),
+                DescriptorMapIssue::Orphan("d".into()),
+                DescriptorMapIssue::Missing("b".into()),
+                DescriptorMapIssue::Missing("c".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_descriptor_map_valid() {
+        let definition = PresentationDefinition::new(
+            "definition".into(),
+            InputDescriptor::new("a".into(), Constraints::new()),
+        );
+
+        let descriptor_map = vec![DescriptorMap::new(
+            "a",
+            ClaimFormatDesignation::JwtVpJson,
+            "$".into(),
+        )];
+
+        assert!(definition.check_descriptor_map(&descriptor_map).is_ok());
+    }
+
+    #[test]
+    fn test_multiple_credentials_per_input_descriptor() {
+        let definition: PresentationDefinition = serde_json::from_value(serde_json::json!({
+            "id": "definition",
+            "submission_requirements": [{ "rule": "pick", "from": "A", "count": 1 }],
+            "input_descriptors": [
+                { "id": "a", "group": ["A"], "constraints": { "fields": [] } },
+                { "id": "b", "group": ["A"], "constraints": { "fields": [] } }
+            ]
+        }))
+        .unwrap();
+
+        let descriptor_map = vec![
+            DescriptorMap::new("a", ClaimFormatDesignation::JwtVpJson, "$[0]".into()),
+            DescriptorMap::new("a", ClaimFormatDesignation::JwtVpJson, "$[1]".into()),
+        ];
+
+        assert!(definition.check_descriptor_map(&descriptor_map).is_ok());
+        assert!(definition
+            .validate_submission_requirements(&descriptor_map)
+            .is_ok());
+
+        let descriptor_map = vec![
+            DescriptorMap::new("a", ClaimFormatDesignation::JwtVpJson, "$[0]".into()),
+            DescriptorMap::new("b", ClaimFormatDesignation::JwtVpJson, "$[1]".into()),
+        ];
+
+        assert!(definition
+            .validate_submission_requirements(&descriptor_map)
+            .is_err());
+    }
 }
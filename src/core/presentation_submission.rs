@@ -1,4 +1,11 @@
-use super::{credential_format::*, input_descriptor::*, object::TypedParameter};
+use super::{
+    credential_format::*,
+    input_descriptor::*,
+    object::{
+        fields::{check_field, check_items, check_keys, KnownFields},
+        TypedParameter,
+    },
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as Json;
 
@@ -46,6 +53,33 @@ impl PresentationSubmission {
         }
     }
 
+    /// Create a presentation submission for a `vp_token` with one presentation per entry, in
+    /// order, given as the id of the input descriptor it was submitted for and its format.
+    ///
+    /// The same input descriptor may appear more than once, when several credentials are
+    /// submitted for it. The path of each entry is `$` if there is a single presentation, and
+    /// its index in the `vp_token` array (e.g. `$[1]`) otherwise.
+    pub fn for_vp_token(
+        definition_id: DescriptorMapId,
+        presentations: impl IntoIterator<Item = (impl Into<DescriptorMapId>, ClaimFormatDesignation)>,
+    ) -> Self {
+        let presentations = presentations.into_iter().collect::<Vec<_>>();
+        let single = presentations.len() == 1;
+        let descriptor_map = presentations
+            .into_iter()
+            .enumerate()
+            .map(|(index, (id, format))| {
+                let path = if single {
+                    "$".to_string()
+                } else {
+                    format!("$[{index}]")
+                };
+                DescriptorMap::new(id, format, path)
+            })
+            .collect();
+        Self::new(uuid::Uuid::new_v4(), definition_id, descriptor_map)
+    }
+
     /// Return the id of the presentation submission.
     pub fn id(&self) -> &uuid::Uuid {
         &self.id
@@ -66,11 +100,27 @@ impl PresentationSubmission {
         &mut self.descriptor_map
     }
 
+    /// Return every descriptor map submitted for an input descriptor.
+    ///
+    /// A wallet may submit more than one credential for the same input descriptor, each with its
+    /// own descriptor map.
+    pub fn descriptors_for<'a>(
+        &'a self,
+        input_descriptor_id: &'a str,
+    ) -> impl Iterator<Item = &'a DescriptorMap> + 'a {
+        self.descriptor_map
+            .iter()
+            .filter(move |descriptor_map| descriptor_map.id == input_descriptor_id)
+    }
+
     /// Returns the descriptor map as a mapping of descriptor map id to descriptor map.
     ///
     /// The descriptor map id is expected to match the id of the input descriptor.
     /// This mapping is helpful for checking if an input descriptor has an associated descriptor map,
     /// using this mapping from the presentation submission.
+    ///
+    /// Only the last descriptor map of an input descriptor is kept, use
+    /// [PresentationSubmission::descriptors_for] to get all of them.
     pub fn descriptor_map_by_id(
         &self,
     ) -> std::collections::HashMap<DescriptorMapId, &DescriptorMap> {
@@ -81,6 +131,19 @@ impl PresentationSubmission {
     }
 }
 
+impl KnownFields for PresentationSubmission {
+    fn collect_unknown_fields(value: &Json, path: &str, unknown: &mut Vec<String>) {
+        if let Some(object) = check_keys(
+            value,
+            path,
+            &["id", "definition_id", "descriptor_map"],
+            unknown,
+        ) {
+            check_items::<DescriptorMap>(object, path, "descriptor_map", unknown);
+        }
+    }
+}
+
 impl TryFrom<Json> for PresentationSubmission {
     type Error = anyhow::Error;
 
@@ -101,7 +164,7 @@ impl From<PresentationSubmission> for Json {
 /// Descriptor Maps are objects used to describe the information a [Holder](https://identity.foundation/presentation-exchange/spec/v2.0.0/#term:holder) provides to a [Verifier](https://identity.foundation/presentation-exchange/spec/v2.0.0/#term:verifier).
 ///
 /// For more information, see: [https://identity.foundation/presentation-exchange/spec/v2.0.0/#presentation-submission](https://identity.foundation/presentation-exchange/spec/v2.0.0/#presentation-submission)
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct DescriptorMap {
     id: DescriptorMapId,
     format: ClaimFormatDesignation,
@@ -150,6 +213,11 @@ impl DescriptorMap {
         &self.path
     }
 
+    /// Return the nested path of the descriptor map, if any.
+    pub fn path_nested(&self) -> Option<&DescriptorMap> {
+        self.path_nested.as_deref()
+    }
+
     /// Set the nested path of the descriptor map.
     ///
     /// The format of a path_nested object mirrors that of a [DescriptorMap] property. The nesting may be any number of levels deep.
@@ -167,3 +235,77 @@ impl DescriptorMap {
         self
     }
 }
+
+impl KnownFields for DescriptorMap {
+    fn collect_unknown_fields(value: &Json, path: &str, unknown: &mut Vec<String>) {
+        if let Some(object) = check_keys(
+            value,
+            path,
+            &["id", "format", "path", "path_nested"],
+            unknown,
+        ) {
+            check_field::<DescriptorMap>(object, path, "path_nested", unknown);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::core::object::UnknownParameters;
+
+    use super::*;
+
+    #[test]
+    fn multiple_presentations_per_input_descriptor() {
+        let submission = PresentationSubmission::for_vp_token(
+            "definition".into(),
+            [
+                ("pid", ClaimFormatDesignation::Other("vc+sd-jwt".into())),
+                ("diploma", ClaimFormatDesignation::JwtVpJson),
+                ("diploma", ClaimFormatDesignation::JwtVpJson),
+            ],
+        );
+
+        let paths = submission
+            .descriptors_for("diploma")
+            .map(|descriptor_map| descriptor_map.path().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["$[1]", "$[2]"]);
+
+        let submission = PresentationSubmission::for_vp_token(
+            "definition".into(),
+            [("pid", ClaimFormatDesignation::Other("vc+sd-jwt".into()))],
+        );
+        assert_eq!(submission.descriptor_map()[0].path(), "$");
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let value = serde_json::json!({
+            "id": "submission",
+            "definition_id": "definition",
+            "descriptor_map": [{
+                "id": "pid",
+                "format": "jwt_vp_json",
+                "path": "$",
+                "path_nested": { "id": "pid", "formt": "jwt_vc_json", "path": "$.vp" }
+            }],
+            "x-vendor": true
+        });
+
+        assert_eq!(
+            PresentationSubmission::unknown_fields(&value),
+            ["x-vendor", "descriptor_map[0].path_nested.formt"]
+        );
+        assert!(
+            PresentationSubmission::from_value_with(value.clone(), UnknownParameters::Reject)
+                .is_err()
+        );
+        // The misspelled format is then missing.
+        assert!(PresentationSubmission::from_value_with(value, UnknownParameters::Ignore).is_err());
+
+        let value = serde_json::json!({ "id": "pid", "format": "jwt_vp_json", "path": "$" });
+        assert!(DescriptorMap::unknown_fields(&value).is_empty());
+        DescriptorMap::from_value_with(value, UnknownParameters::Reject).unwrap();
+    }
+}
@@ -0,0 +1,316 @@
+use std::fmt;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    authorization_request::parameters::{ClientIdScheme, ResponseMode, ResponseType},
+    consts::error_code::*,
+    credential_format::ClaimFormatDesignation,
+    jwe::EncryptionNotSupported,
+};
+
+/// An error code of an Authorization Error Response.
+///
+/// See [RFC 6749 §4.1.2.1](https://www.rfc-editor.org/rfc/rfc6749#section-4.1.2.1) and
+/// [OID4VP §6.4](https://openid.net/specs/openid-4-verifiable-presentations-1_0-20.html#section-6.4).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")]
+pub enum AuthorizationErrorCode {
+    /// The request is missing a parameter, includes an invalid value, or is otherwise malformed.
+    InvalidRequest,
+    /// The Request Object is invalid, e.g. its signature could not be verified.
+    InvalidRequestObject,
+    /// The wallet has no matching credentials, or the user refused to share them.
+    AccessDenied,
+    /// The wallet does not support any of the formats requested by the verifier.
+    VpFormatsNotSupported,
+    /// The wallet does not support the `response_type` of the request.
+    UnsupportedResponseType,
+    Other(String),
+}
+
+impl AuthorizationErrorCode {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::InvalidRequest => INVALID_REQUEST,
+            Self::InvalidRequestObject => INVALID_REQUEST_OBJECT,
+            Self::AccessDenied => ACCESS_DENIED,
+            Self::VpFormatsNotSupported => VP_FORMATS_NOT_SUPPORTED,
+            Self::UnsupportedResponseType => UNSUPPORTED_RESPONSE_TYPE,
+            Self::Other(s) => s,
+        }
+    }
+
+    /// Choose the error code to report for a wallet-side failure.
+    ///
+    /// [EncryptionNotSupported] and [VpFormatsNotSupported] errors map to
+    /// `vp_formats_not_supported`, [ResponseTypeNotSupported] errors to
+    /// `unsupported_response_type`, [InvalidRequestObject] errors to `invalid_request_object`,
+    /// anything else is reported as `invalid_request`.
+    pub fn for_error(error: &anyhow::Error) -> Self {
+        if error.downcast_ref::<InvalidRequestObject>().is_some() {
+            Self::InvalidRequestObject
+        } else if error.downcast_ref::<EncryptionNotSupported>().is_some()
+            || error.downcast_ref::<VpFormatsNotSupported>().is_some()
+        {
+            Self::VpFormatsNotSupported
+        } else if error.downcast_ref::<ResponseTypeNotSupported>().is_some() {
+            Self::UnsupportedResponseType
+        } else {
+            Self::InvalidRequest
+        }
+    }
+}
+
+impl From<String> for AuthorizationErrorCode {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            INVALID_REQUEST => Self::InvalidRequest,
+            INVALID_REQUEST_OBJECT => Self::InvalidRequestObject,
+            ACCESS_DENIED => Self::AccessDenied,
+            VP_FORMATS_NOT_SUPPORTED => Self::VpFormatsNotSupported,
+            UNSUPPORTED_RESPONSE_TYPE => Self::UnsupportedResponseType,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<AuthorizationErrorCode> for String {
+    fn from(value: AuthorizationErrorCode) -> Self {
+        match value {
+            AuthorizationErrorCode::Other(s) => s,
+            code => code.as_str().to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for AuthorizationErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The Request Object of a request could not be trusted: it is signed with an unsupported
+/// algorithm, or its signature could not be verified.
+///
+/// Reported to the verifier as `invalid_request_object`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidRequestObject(pub String);
+
+impl fmt::Display for InvalidRequestObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidRequestObject {}
+
+/// None of the formats requested by an input descriptor are both supported by the wallet, with
+/// compatible algorithms, and held in its credential store.
+///
+/// Reported to the verifier as `vp_formats_not_supported`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VpFormatsNotSupported {
+    pub input_descriptor_id: String,
+    /// The formats requested for the input descriptor.
+    pub requested: Vec<ClaimFormatDesignation>,
+}
+
+impl fmt::Display for VpFormatsNotSupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "none of the formats requested by input descriptor '{}' can be presented: {:?}",
+            self.input_descriptor_id, self.requested
+        )
+    }
+}
+
+impl std::error::Error for VpFormatsNotSupported {}
+
+/// The wallet supports neither the client ID scheme nor the Client Identifier Prefix of a
+/// request, see
+/// [supports_client_id_scheme](crate::core::metadata::WalletMetadata::supports_client_id_scheme).
+///
+/// Reported to the verifier as `invalid_request`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientIdSchemeNotSupported(pub ClientIdScheme);
+
+impl fmt::Display for ClientIdSchemeNotSupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wallet does not support client_id_scheme '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ClientIdSchemeNotSupported {}
+
+/// The request passes its presentation definition by reference, but the wallet does not retrieve
+/// them, see
+/// [supports_presentation_definition_uri](crate::core::metadata::WalletMetadata::supports_presentation_definition_uri).
+///
+/// Reported to the verifier as `invalid_request`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresentationDefinitionUriNotSupported;
+
+impl fmt::Display for PresentationDefinitionUriNotSupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("wallet does not support 'presentation_definition_uri'")
+    }
+}
+
+impl std::error::Error for PresentationDefinitionUriNotSupported {}
+
+/// The wallet does not support the `response_type` of a request, see
+/// [supports_response_type](crate::core::metadata::WalletMetadata::supports_response_type).
+///
+/// Reported to the verifier as `unsupported_response_type`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseTypeNotSupported(pub ResponseType);
+
+impl fmt::Display for ResponseTypeNotSupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wallet does not support response_type '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ResponseTypeNotSupported {}
+
+/// The wallet does not support the `response_mode` of a request, see
+/// [supports_response_mode](crate::core::metadata::WalletMetadata::supports_response_mode).
+///
+/// Reported to the verifier as `invalid_request`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseModeNotSupported(pub ResponseMode);
+
+impl fmt::Display for ResponseModeNotSupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wallet does not support response_mode '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ResponseModeNotSupported {}
+
+/// An Authorization Error Response, sent to the verifier instead of a presentation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorizationErrorResponse {
+    pub error: AuthorizationErrorCode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+}
+
+impl AuthorizationErrorResponse {
+    pub fn new(error: AuthorizationErrorCode) -> Self {
+        Self {
+            error,
+            error_description: None,
+            state: None,
+        }
+    }
+
+    /// The error response reporting `error`, with the [code](AuthorizationErrorCode::for_error)
+    /// that it maps to and its message as description.
+    pub fn for_error(error: &anyhow::Error) -> Self {
+        Self::new(AuthorizationErrorCode::for_error(error))
+            .with_error_description(error.to_string())
+    }
+
+    /// Set the description of the error.
+    ///
+    /// Characters that [RFC 6749](https://www.rfc-editor.org/rfc/rfc6749#section-4.1.2.1) does
+    /// not allow in descriptions are replaced: double quotes by single quotes, backslashes by
+    /// slashes, whitespace by spaces and anything else by `?`.
+    pub fn with_error_description(mut self, error_description: impl Into<String>) -> Self {
+        self.error_description = Some(sanitize_description(&error_description.into()));
+        self
+    }
+
+    pub fn with_state(mut self, state: impl Into<String>) -> Self {
+        self.state = Some(state.into());
+        self
+    }
+
+    /// Encode the Authorization Error Response as 'application/x-www-form-urlencoded'.
+    pub fn into_x_www_form_urlencoded(self) -> Result<String> {
+        serde_urlencoded::to_string(self)
+            .context("failed to encode error response as 'application/x-www-form-urlencoded'")
+    }
+}
+
+/// Replace the characters outside of `%x20-21 / %x23-5B / %x5D-7E`.
+fn sanitize_description(description: &str) -> String {
+    description
+        .chars()
+        .map(|c| match c {
+            '"' => '\'',
+            '\\' => '/',
+            c if c.is_whitespace() => ' ',
+            ' '..='~' => c,
+            _ => '?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn error_code_for_error() {
+        let error = anyhow!(VpFormatsNotSupported {
+            input_descriptor_id: "pid".into(),
+            requested: vec![ClaimFormatDesignation::MsoMDoc],
+        });
+        assert_eq!(
+            AuthorizationErrorCode::for_error(&error),
+            AuthorizationErrorCode::VpFormatsNotSupported
+        );
+        let error = anyhow!(EncryptionNotSupported("unsupported enc".into())).context("wrapped");
+        assert_eq!(
+            AuthorizationErrorCode::for_error(&error),
+            AuthorizationErrorCode::VpFormatsNotSupported
+        );
+        let error = anyhow!(InvalidRequestObject("bad signature".into())).context("wrapped");
+        assert_eq!(
+            AuthorizationErrorCode::for_error(&error),
+            AuthorizationErrorCode::InvalidRequestObject
+        );
+        assert_eq!(
+            AuthorizationErrorCode::for_error(&anyhow!("bad request")),
+            AuthorizationErrorCode::InvalidRequest
+        );
+    }
+
+    #[test]
+    fn error_response_for_error() {
+        let error = anyhow!(InvalidRequestObject("bad signature".into()))
+            .context("request \"abc\" was invalid:\n\tsee café");
+        let response = AuthorizationErrorResponse::for_error(&error);
+        assert_eq!(response.error, AuthorizationErrorCode::InvalidRequestObject);
+        assert_eq!(
+            response.error_description.as_deref(),
+            Some("request 'abc' was invalid:  see caf?")
+        );
+    }
+
+    #[test]
+    fn error_response_to_form_urlencoded() {
+        let response = AuthorizationErrorResponse::new(AuthorizationErrorCode::AccessDenied)
+            .with_error_description("user refused")
+            .with_state("abc");
+        assert_eq!(
+            response.into_x_www_form_urlencoded().unwrap(),
+            "error=access_denied&error_description=user+refused&state=abc"
+        );
+        assert_eq!(
+            serde_json::from_str::<AuthorizationErrorResponse>(r#"{"error":"other_code"}"#)
+                .unwrap()
+                .error,
+            AuthorizationErrorCode::Other("other_code".into())
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value as Json;
+
+use crate::core::{
+    authorization_request::{parameters::Audience, AuthorizationRequestObject},
+    object::{typed_parameter, ParsingErrorContext, TypedParameter, UntypedObject},
+    util::unix_time,
+};
+
+use super::UnencodedAuthorizationResponse;
+
+typed_parameter! {
+    /// `iss` claim of a JWT-secured Authorization Response, identifying the wallet.
+    #[derive(Debug, Clone)]
+    pub struct ResponseIssuer(pub String) = "iss";
+
+    /// `exp` claim of a JWT-secured Authorization Response, in seconds since the UNIX epoch.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Expiry(pub u64) = "exp";
+}
+
+/// Wallet-side configuration of the standard claims added to JWT-secured Authorization
+/// Responses (`direct_post.jwt`).
+///
+/// See: [JARM#section-2.1](https://openid.net/specs/oauth-v2-jarm.html#section-2.1)
+#[derive(Debug, Clone)]
+pub struct JarmClaims {
+    issuer: String,
+    lifetime: Duration,
+}
+
+impl JarmClaims {
+    /// The lifetime of a response JWT unless otherwise configured.
+    pub const DEFAULT_LIFETIME: Duration = Duration::from_secs(600);
+
+    pub fn new(issuer: impl Into<String>) -> Self {
+        Self {
+            issuer: issuer.into(),
+            lifetime: Self::DEFAULT_LIFETIME,
+        }
+    }
+
+    /// Set how long the response JWT is valid for.
+    pub fn with_lifetime(mut self, lifetime: Duration) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    /// Build the claims of the response JWT: the response parameters, along with `iss`, `aud`
+    /// (the `client_id` of the request) and `exp`.
+    pub fn apply(
+        &self,
+        response: UnencodedAuthorizationResponse,
+        request: &AuthorizationRequestObject,
+        now: SystemTime,
+    ) -> Result<UntypedObject> {
+        let UnencodedAuthorizationResponse(mut claims, vp_token, presentation_submission) =
+            response;
+        claims.insert(vp_token);
+        claims.insert(presentation_submission);
+        claims.insert(ResponseIssuer(self.issuer.clone()));
+        claims.insert(Audience(request.client_id().0.clone()));
+        claims.insert(Expiry(unix_time(now + self.lifetime)?));
+        Ok(claims)
+    }
+}
+
+/// Verifier-side validation of the standard claims of JWT-secured Authorization Responses.
+#[derive(Debug, Clone)]
+pub struct JarmValidator {
+    audiences: Vec<String>,
+    issuer: Option<String>,
+    leeway: Duration,
+}
+
+impl JarmValidator {
+    /// Accept responses addressed to `audience`, typically the verifier's `client_id`.
+    pub fn new(audience: impl Into<String>) -> Self {
+        Self {
+            audiences: vec![audience.into()],
+            issuer: None,
+            leeway: Duration::from_secs(60),
+        }
+    }
+
+    /// Also accept responses addressed to `audience`.
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audiences.push(audience.into());
+        self
+    }
+
+    /// Require the `iss` claim to be `issuer`, otherwise any issuer is accepted.
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    /// Allowed clock skew when checking `exp`.
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// Check the `iss`, `aud` and `exp` claims of a response JWT.
+    pub fn validate(&self, claims: &UntypedObject, now: SystemTime) -> Result<()> {
+        let ResponseIssuer(iss) = claims.get().parsing_error()?;
+        if let Some(issuer) = &self.issuer {
+            if &iss != issuer {
+                bail!("unexpected response issuer '{iss}'")
+            }
+        }
+
+        let aud = claims
+            .0
+            .get(Audience::KEY)
+            .context("response is missing 'aud'")?;
+        let audiences: Vec<String> = match aud {
+            Json::String(aud) => vec![aud.clone()],
+            aud => serde_json::from_value(aud.clone()).context("'aud' was malformed")?,
+        };
+        if !audiences.iter().any(|aud| self.audiences.contains(aud)) {
+            bail!("response was not addressed to this verifier (aud: {audiences:?})")
+        }
+
+        let Expiry(exp) = claims.get().parsing_error()?;
+        if exp.saturating_add(self.leeway.as_secs()) < unix_time(now)? {
+            bail!("response expired at {exp}")
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::UNIX_EPOCH;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn request() -> AuthorizationRequestObject {
+        serde_json::from_value::<UntypedObject>(json!({
+            "client_id": "verifier.example.com",
+            "client_id_scheme": "redirect_uri",
+            "response_type": "vp_token",
+            "response_mode": "direct_post.jwt",
+            "response_uri": "https://verifier.example.com/response",
+            "nonce": "n-0S6_WzA2Mj",
+            "presentation_definition_uri": "https://verifier.example.com/pd"
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap()
+    }
+
+    fn response() -> UnencodedAuthorizationResponse {
+        serde_json::from_value::<UntypedObject>(json!({
+            "vp_token": "presentation",
+            "presentation_submission": {
+                "id": "d05a7f51-ac09-43af-8864-e00f0175f2c7",
+                "definition_id": "f619e64a-8f80-4b71-8373-30cf07b1e4f2",
+                "descriptor_map": []
+            }
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap()
+    }
+
+    #[test]
+    fn apply_and_validate() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let claims = JarmClaims::new("https://wallet.example.com")
+            .with_lifetime(Duration::from_secs(120))
+            .apply(response(), &request(), now)
+            .unwrap();
+
+        let Expiry(exp) = claims.get().unwrap().unwrap();
+        assert_eq!(exp, 1_700_000_120);
+        assert_eq!(claims.0["aud"], "verifier.example.com");
+
+        let validator = JarmValidator::new("verifier.example.com")
+            .with_issuer("https://wallet.example.com")
+            .with_leeway(Duration::ZERO);
+        validator.validate(&claims, now).unwrap();
+        assert!(validator
+            .validate(&claims, now + Duration::from_secs(121))
+            .is_err());
+        assert!(JarmValidator::new("other.example.com")
+            .validate(&claims, now)
+            .is_err());
+        assert!(validator
+            .clone()
+            .with_issuer("https://other.example.com")
+            .validate(&claims, now)
+            .is_err());
+    }
+
+    #[test]
+    fn missing_claims() {
+        let mut claims: UntypedObject = serde_json::from_value(json!({
+            "aud": ["other.example.com", "verifier.example.com"],
+            "exp": 1_700_000_000u64
+        }))
+        .unwrap();
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let validator = JarmValidator::new("verifier.example.com");
+
+        assert!(validator.validate(&claims, now).is_err());
+        claims.insert(ResponseIssuer("https://wallet.example.com".into()));
+        validator.validate(&claims, now).unwrap();
+    }
+
+    #[test]
+    fn far_future_expiry() {
+        let claims: UntypedObject = serde_json::from_value(json!({
+            "iss": "https://wallet.example.com",
+            "aud": "verifier.example.com",
+            "exp": u64::MAX
+        }))
+        .unwrap();
+        JarmValidator::new("verifier.example.com")
+            .validate(&claims, SystemTime::now())
+            .unwrap();
+    }
+}
@@ -1,46 +1,143 @@
 use super::{
-    object::{ParsingErrorContext, UntypedObject},
+    authorization_request::parameters::State,
+    dcql_query::DcqlVpToken,
+    object::{ParsingErrorContext, TypedParameter, UntypedObject},
     presentation_submission::PresentationSubmission,
+    util::Redacted,
 };
 
 use std::collections::BTreeMap;
 
-use anyhow::{Context, Error, Result};
+use anyhow::{bail, Context, Error, Result};
+use base64::prelude::*;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use url::Url;
 
-use self::parameters::VpToken;
+use self::{
+    error::AuthorizationErrorResponse,
+    parameters::{IdToken, MdocGeneratedNonce, VpToken},
+};
 
+pub mod error;
+pub mod jarm;
 pub mod parameters;
+pub mod parser;
 
 #[derive(Debug, Clone)]
 pub enum AuthorizationResponse {
     Unencoded(UnencodedAuthorizationResponse),
     Jwt(JwtAuthorizationResponse),
+    /// An unencoded response to a request with a DCQL query.
+    Dcql(DcqlAuthorizationResponse),
 }
 
 impl AuthorizationResponse {
+    /// The response parameters that this library knows, see
+    /// [ParameterRegistry](super::object::registry::ParameterRegistry).
+    pub const KNOWN_PARAMETERS: &'static [&'static str] = &[
+        VpToken::KEY,
+        PresentationSubmission::KEY,
+        IdToken::KEY,
+        State::KEY,
+        "response",
+    ];
+
+    /// Parse an Authorization Response posted as 'application/x-www-form-urlencoded', the
+    /// inverse of the `into_x_www_form_urlencoded` methods of the response types.
+    ///
+    /// A `response` parameter is a JWT or JWE response. Otherwise the parameters are those of an
+    /// unencoded response, where a `vp_token` with several presentations (or a DCQL-shaped one)
+    /// and the `presentation_submission` are JSON encoded. An Authorization Error Response is
+    /// rejected with its error code and description.
     pub fn from_x_www_form_urlencoded(bytes: &[u8]) -> Result<Self> {
         if let Ok(jwt) = serde_urlencoded::from_bytes(bytes) {
             return Ok(Self::Jwt(jwt));
         }
 
-        let flattened = serde_urlencoded::from_bytes::<BTreeMap<String, String>>(bytes)
+        let form = serde_urlencoded::from_bytes::<BTreeMap<String, String>>(bytes)
             .context("failed to construct flat map")?;
-        let map = flattened
-            .into_iter()
-            .map(|(k, v)| {
-                let v = serde_json::from_str::<Value>(&v).unwrap_or(Value::String(v));
-                (k, v)
-            })
-            .collect();
+        if form.contains_key("error") && !form.contains_key(VpToken::KEY) {
+            let error: AuthorizationErrorResponse = serde_urlencoded::from_bytes(bytes)
+                .context("failed to parse the authorization error response")?;
+            match error.error_description {
+                Some(description) => {
+                    bail!("the wallet responded with '{}': {description}", error.error)
+                }
+                None => bail!("the wallet responded with '{}'", error.error),
+            }
+        }
 
-        Ok(Self::Unencoded(UntypedObject(map).try_into()?))
+        UntypedObject::from_form(form).try_into()
     }
 }
 
-#[derive(Debug, Clone)]
+impl TryFrom<UntypedObject> for AuthorizationResponse {
+    type Error = Error;
+
+    /// Parse the parameters of an unencoded response, e.g. the payload of a decrypted response.
+    fn try_from(object: UntypedObject) -> Result<Self, Self::Error> {
+        if !object.contains::<PresentationSubmission>()
+            && matches!(object.0.get(DcqlVpToken::KEY), Some(Value::Object(_)))
+        {
+            return Ok(Self::Dcql(object.try_into()?));
+        }
+
+        Ok(Self::Unencoded(object.try_into()?))
+    }
+}
+
+/// An unencoded Authorization Response to a request with a
+/// [DcqlQuery](super::dcql_query::DcqlQuery): the `vp_token` maps credential query ids to
+/// presentations, and there is no presentation submission.
+///
+/// The [Debug] output only lists the parameter names and the credential query ids.
+#[derive(Clone)]
+pub struct DcqlAuthorizationResponse(pub UntypedObject, pub DcqlVpToken);
+
+impl DcqlAuthorizationResponse {
+    /// Encode the Authorization Response as 'application/x-www-form-urlencoded'.
+    pub fn into_x_www_form_urlencoded(self) -> Result<String> {
+        serde_urlencoded::to_string(self.into_untyped().flatten_for_form()?)
+            .context("failed to encode response as 'application/x-www-form-urlencoded'")
+    }
+
+    /// Return all of the response parameters.
+    pub fn into_untyped(self) -> UntypedObject {
+        let mut inner = self.0;
+        inner.insert(self.1);
+        inner
+    }
+
+    /// Return the DCQL-shaped Verifiable Presentation Token.
+    pub fn vp_token(&self) -> &DcqlVpToken {
+        &self.1
+    }
+}
+
+impl std::fmt::Debug for DcqlAuthorizationResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DcqlAuthorizationResponse")
+            .field("parameters", &self.0.keys().collect::<Vec<_>>())
+            .field("vp_token", &self.1)
+            .finish()
+    }
+}
+
+impl TryFrom<UntypedObject> for DcqlAuthorizationResponse {
+    type Error = Error;
+
+    fn try_from(value: UntypedObject) -> Result<Self, Self::Error> {
+        let vp_token = value.get().parsing_error()?;
+        Ok(Self(value, vp_token))
+    }
+}
+
+/// An Authorization Response that has not been encoded as a JWT or JWE (or has been decrypted).
+///
+/// The [Debug] output only lists the parameter names and the presentation submission, the
+/// values of the other parameters are available through the public fields.
+#[derive(Clone)]
 pub struct UnencodedAuthorizationResponse(
     pub UntypedObject,
     pub VpToken,
@@ -50,11 +147,16 @@ pub struct UnencodedAuthorizationResponse(
 impl UnencodedAuthorizationResponse {
     /// Encode the Authorization Response as 'application/x-www-form-urlencoded'.
     pub fn into_x_www_form_urlencoded(self) -> Result<String> {
+        serde_urlencoded::to_string(self.into_untyped().flatten_for_form()?)
+            .context("failed to encode response as 'application/x-www-form-urlencoded'")
+    }
+
+    /// Return all of the response parameters, e.g. to be encrypted as a JWE payload.
+    pub fn into_untyped(self) -> UntypedObject {
         let mut inner = self.0;
         inner.insert(self.1);
         inner.insert(self.2);
-        serde_urlencoded::to_string(inner.flatten_for_form()?)
-            .context("failed to encode response as 'application/x-www-form-urlencoded'")
+        inner
     }
 
     /// Return the Verifiable Presentation Token.
@@ -68,18 +170,56 @@ impl UnencodedAuthorizationResponse {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl std::fmt::Debug for UnencodedAuthorizationResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnencodedAuthorizationResponse")
+            .field("parameters", &self.0.keys().collect::<Vec<_>>())
+            .field("vp_token", &self.1)
+            .field("presentation_submission", &self.2)
+            .finish()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct JwtAuthorizationResponse {
     /// Can be JWT or JWE.
     pub response: String,
 }
 
+impl std::fmt::Debug for JwtAuthorizationResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtAuthorizationResponse")
+            .field("response", &Redacted(self.response.as_bytes()))
+            .finish()
+    }
+}
+
 impl JwtAuthorizationResponse {
     /// Encode the Authorization Response as 'application/x-www-form-urlencoded'.
     pub fn into_x_www_form_urlencoded(self) -> Result<String> {
         serde_urlencoded::to_string(self)
             .context("failed to encode response as 'application/x-www-form-urlencoded'")
     }
+
+    /// Decode the protected header of the JWT or JWE.
+    pub fn protected_header(&self) -> Result<Map<String, Value>> {
+        let Some((header_b64, _)) = self.response.split_once('.') else {
+            bail!("response was not a compact serialized JWT or JWE")
+        };
+        let header_bytes = BASE64_URL_SAFE_NO_PAD
+            .decode(header_b64)
+            .context("response header was not valid base64url")?;
+        serde_json::from_slice(&header_bytes).context("response header was not a JSON object")
+    }
+
+    /// Return the [MdocGeneratedNonce] from the `apu` header of the encrypted response, if present.
+    pub fn mdoc_generated_nonce(&self) -> Result<Option<MdocGeneratedNonce>> {
+        match self.protected_header()?.get("apu") {
+            None => Ok(None),
+            Some(Value::String(apu)) => MdocGeneratedNonce::from_apu(apu).map(Some),
+            Some(_) => bail!("'apu' header was not a string"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -99,11 +239,15 @@ impl TryFrom<UntypedObject> for UnencodedAuthorizationResponse {
 
 #[cfg(test)]
 mod test {
+    use base64::prelude::*;
     use serde_json::json;
 
-    use crate::core::object::UntypedObject;
+    use crate::core::{authorization_request::parameters::Nonce, object::UntypedObject};
 
-    use super::{JwtAuthorizationResponse, UnencodedAuthorizationResponse};
+    use super::{
+        parameters::MdocGeneratedNonce, AuthorizationResponse, JwtAuthorizationResponse,
+        UnencodedAuthorizationResponse,
+    };
 
     #[test]
     fn jwt_authorization_response_to_form_urlencoded() {
@@ -135,4 +279,123 @@ mod test {
             "presentation_submission=%7B%22id%22%3A%22d05a7f51-ac09-43af-8864-e00f0175f2c7%22%2C%22definition_id%22%3A%22f619e64a-8f80-4b71-8373-30cf07b1e4f2%22%2C%22descriptor_map%22%3A%5B%5D%7D&vp_token=string",
         )
     }
+
+    #[test]
+    fn form_urlencoded_round_trip() {
+        let submission = json!({
+            "id": "d05a7f51-ac09-43af-8864-e00f0175f2c7",
+            "definition_id": "f619e64a-8f80-4b71-8373-30cf07b1e4f2",
+            "descriptor_map": []
+        });
+        let round_trip = |parameters: serde_json::Value| {
+            let object: UntypedObject = serde_json::from_value(parameters.clone()).unwrap();
+            let encoded = match AuthorizationResponse::try_from(object).unwrap() {
+                AuthorizationResponse::Unencoded(response) => response.into_x_www_form_urlencoded(),
+                AuthorizationResponse::Dcql(response) => response.into_x_www_form_urlencoded(),
+                AuthorizationResponse::Jwt(_) => panic!("expected an unencoded response"),
+            }
+            .unwrap();
+            let untyped = match AuthorizationResponse::from_x_www_form_urlencoded(
+                encoded.as_bytes(),
+            )
+            .unwrap()
+            {
+                AuthorizationResponse::Unencoded(response) => response.into_untyped(),
+                AuthorizationResponse::Dcql(response) => response.into_untyped(),
+                AuthorizationResponse::Jwt(_) => panic!("expected an unencoded response"),
+            };
+            assert_eq!(serde_json::Value::from(untyped), parameters);
+        };
+
+        // Strings that are valid JSON are not parsed as JSON.
+        round_trip(json!({
+            "presentation_submission": submission,
+            "vp_token": "12345",
+            "state": "42"
+        }));
+        round_trip(json!({
+            "presentation_submission": submission,
+            "vp_token": ["header.payload.signature", { "holder": "did:example:holder" }],
+            "id_token": "header.payload.signature"
+        }));
+        round_trip(json!({
+            "vp_token": { "pid": "header.payload.signature~", "mdl": ["omQ", "omR"] },
+            "state": "[not json"
+        }));
+    }
+
+    #[test]
+    fn error_response_from_form_urlencoded() {
+        let error = AuthorizationResponse::from_x_www_form_urlencoded(
+            b"error=access_denied&error_description=user+refused&state=abc",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the wallet responded with 'access_denied': user refused"
+        );
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let object: UntypedObject = serde_json::from_value(json!(
+            {
+                "presentation_submission": {
+                    "id": "d05a7f51-ac09-43af-8864-e00f0175f2c7",
+                    "definition_id": "f619e64a-8f80-4b71-8373-30cf07b1e4f2",
+                    "descriptor_map": []
+                },
+                "vp_token": ["secret_presentation", { "holder": "secret_holder" }],
+                "id_token": "secret_id_token"
+            }
+        ))
+        .unwrap();
+        let response = UnencodedAuthorizationResponse::try_from(object).unwrap();
+
+        let debug = format!("{response:?}");
+        assert!(!debug.contains("secret"), "{debug}");
+        assert!(debug.contains("id_token"));
+        assert!(debug.contains("d05a7f51-ac09-43af-8864-e00f0175f2c7"));
+        assert_eq!(
+            response.vp_token().expose()[0].expose(),
+            "secret_presentation"
+        );
+
+        let response = JwtAuthorizationResponse {
+            response: "secret.secret.secret".into(),
+        };
+        assert!(!format!("{response:?}").contains("secret"));
+    }
+
+    #[test]
+    fn mdoc_generated_nonce_from_jwe_header() {
+        let mdoc_generated_nonce = MdocGeneratedNonce("mdoc_nonce_0123456789".into());
+        let mut header = mdoc_generated_nonce.jwe_header_parameters(&Nonce::from("request_nonce"));
+        assert_eq!(
+            header["apv"],
+            BASE64_URL_SAFE_NO_PAD.encode("request_nonce")
+        );
+
+        header.insert("alg".into(), "ECDH-ES".into());
+        header.insert("enc".into(), "A256GCM".into());
+        let header_b64 = BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header).unwrap());
+        let response = JwtAuthorizationResponse {
+            response: format!("{header_b64}..iv.ciphertext.tag"),
+        };
+
+        assert_eq!(
+            response.mdoc_generated_nonce().unwrap(),
+            Some(mdoc_generated_nonce)
+        );
+    }
+
+    #[test]
+    fn mdoc_generated_nonce_absent() {
+        let header_b64 = BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256"}"#);
+        let response = JwtAuthorizationResponse {
+            response: format!("{header_b64}.body.signature"),
+        };
+
+        assert_eq!(response.mdoc_generated_nonce().unwrap(), None);
+    }
 }
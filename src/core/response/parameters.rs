@@ -1,14 +1,40 @@
 pub use crate::core::authorization_request::parameters::State;
-use crate::core::object::TypedParameter;
-
-use anyhow::Error;
+use crate::{
+    core::{
+        authorization_request::parameters::Nonce,
+        object::TypedParameter,
+        util::{Base64Policy, Redacted},
+    },
+    utils::{OneOrMany, OneOrManyRef},
+};
+
+use anyhow::{Context, Error};
+use base64::prelude::*;
 use serde::{Deserialize, Serialize};
-use serde_json::Value as Json;
-use ssi::{claims::vc, one_or_many::OneOrManyRef, prelude::AnyJsonPresentation, OneOrMany};
+use serde_json::{Map, Value as Json};
+#[cfg(feature = "core")]
+use ssi::{claims::vc, prelude::AnyJsonPresentation};
 
-#[derive(Debug, Clone)]
+/// `id_token` in the Authorization Response.
+///
+/// The [Debug] output is redacted, use [IdToken::expose] to access the token.
+#[derive(Clone)]
 pub struct IdToken(pub String);
 
+impl IdToken {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for IdToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("IdToken")
+            .field(&Redacted(self.0.as_bytes()))
+            .finish()
+    }
+}
+
 impl TypedParameter for IdToken {
     const KEY: &'static str = "id_token";
 }
@@ -27,6 +53,58 @@ impl From<IdToken> for Json {
     }
 }
 
+/// The `mdocGeneratedNonce` as defined in ISO/IEC 18013-7 Annex B.
+///
+/// Generated by the wallet when presenting an mdoc, and bound into the session transcript
+/// (`OID4VPHandover`) by both the wallet and the verifier. It is transported to the verifier in the
+/// `apu` header of the encrypted authorization response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MdocGeneratedNonce(pub String);
+
+impl MdocGeneratedNonce {
+    /// Create a new `MdocGeneratedNonce` with a random value of the given length.
+    ///
+    /// ISO/IEC 18013-7 requires at least 16 bytes of entropy.
+    pub fn random(rng: &mut impl rand::Rng, length: usize) -> Self {
+        use rand::distributions::{Alphanumeric, DistString};
+
+        Self(Alphanumeric.sample_string(rng, length))
+    }
+
+    /// Encode as the value of the JWE `apu` (Agreement PartyUInfo) header.
+    pub fn to_apu(&self) -> String {
+        BASE64_URL_SAFE_NO_PAD.encode(self.0.as_bytes())
+    }
+
+    /// Decode from the value of the JWE `apu` (Agreement PartyUInfo) header.
+    pub fn from_apu(apu: &str) -> Result<Self, Error> {
+        let bytes = BASE64_URL_SAFE_NO_PAD
+            .decode(apu)
+            .context("'apu' was not valid base64url")?;
+        String::from_utf8(bytes)
+            .map(Self)
+            .context("'apu' did not contain a UTF-8 mdocGeneratedNonce")
+    }
+
+    /// The key agreement parameters (`apu` and `apv`) to include in the protected header of the
+    /// JWE-encrypted authorization response, where `apv` is the `nonce` from the request.
+    pub fn jwe_header_parameters(&self, nonce: &Nonce) -> Map<String, Json> {
+        let mut params = Map::new();
+        params.insert("apu".into(), self.to_apu().into());
+        params.insert(
+            "apv".into(),
+            BASE64_URL_SAFE_NO_PAD.encode(nonce.as_bytes()).into(),
+        );
+        params
+    }
+}
+
+impl std::fmt::Display for MdocGeneratedNonce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// OpenID Connect for Verifiable Presentations specification defines `vp_token` parameter:
 ///
 /// > JSON String or JSON object that MUST contain a single Verifiable Presentation or
@@ -41,10 +119,16 @@ impl From<IdToken> for Json {
 /// > any additional encoding when a Credential format is already represented as a JSON object or a JSON string.
 ///
 /// See: [OpenID.VP#section-6.1-2.2](https://openid.net/specs/openid-4-verifiable-presentations-1_0.html#section-6.1-2.2)
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// The [Debug] output is redacted, use [VpToken::expose] to access the presentations.
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct VpToken(pub Vec<VpTokenItem>);
 
 impl VpToken {
+    pub fn expose(&self) -> &[VpTokenItem] {
+        &self.0
+    }
+
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
@@ -53,11 +137,17 @@ impl VpToken {
         self.0.len()
     }
 
-    pub fn iter(&self) -> std::slice::Iter<VpTokenItem> {
+    pub fn iter(&self) -> std::slice::Iter<'_, VpTokenItem> {
         self.0.iter()
     }
 }
 
+impl std::fmt::Debug for VpToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("VpToken").field(&self.0).finish()
+    }
+}
+
 impl TypedParameter for VpToken {
     const KEY: &'static str = "vp_token";
 }
@@ -94,18 +184,21 @@ impl From<String> for VpToken {
     }
 }
 
+#[cfg(feature = "core")]
 impl From<vc::v1::syntax::JsonPresentation> for VpToken {
     fn from(value: vc::v1::syntax::JsonPresentation) -> Self {
         Self(vec![value.into()])
     }
 }
 
+#[cfg(feature = "core")]
 impl From<vc::v2::syntax::JsonPresentation> for VpToken {
     fn from(value: vc::v2::syntax::JsonPresentation) -> Self {
         Self(vec![value.into()])
     }
 }
 
+#[cfg(feature = "core")]
 impl From<AnyJsonPresentation> for VpToken {
     fn from(value: AnyJsonPresentation) -> Self {
         Self(vec![value.into()])
@@ -146,19 +239,56 @@ impl IntoIterator for VpToken {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// A single presentation in the `vp_token`.
+///
+/// The [Debug] output is redacted, use [VpTokenItem::expose] to access the presentation.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum VpTokenItem {
     String(String),
     JsonObject(serde_json::Map<String, serde_json::Value>),
 }
 
+impl VpTokenItem {
+    /// Return the presentation as it would appear in the `vp_token`: the string itself, or the
+    /// serialized JSON object.
+    pub fn expose(&self) -> String {
+        match self {
+            Self::String(value) => value.clone(),
+            Self::JsonObject(object) => Json::Object(object.clone()).to_string(),
+        }
+    }
+
+    /// Decode a base64url-encoded item, such as an `mso_mdoc` DeviceResponse, according to the
+    /// given [Base64Policy].
+    pub fn decode_base64(&self, policy: Base64Policy) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::String(value) => policy.decode(value),
+            Self::JsonObject(_) => anyhow::bail!("expected a base64url encoded string"),
+        }
+    }
+}
+
+impl std::fmt::Debug for VpTokenItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let exposed = self.expose();
+        let name = match self {
+            Self::String(_) => "String",
+            Self::JsonObject(_) => "JsonObject",
+        };
+        f.debug_tuple(name)
+            .field(&Redacted(exposed.as_bytes()))
+            .finish()
+    }
+}
+
 impl From<String> for VpTokenItem {
     fn from(value: String) -> Self {
         Self::String(value)
     }
 }
 
+#[cfg(feature = "core")]
 impl From<vc::v1::syntax::JsonPresentation> for VpTokenItem {
     fn from(value: vc::v1::syntax::JsonPresentation) -> Self {
         let serde_json::Value::Object(obj) = serde_json::to_value(value)
@@ -173,6 +303,7 @@ impl From<vc::v1::syntax::JsonPresentation> for VpTokenItem {
     }
 }
 
+#[cfg(feature = "core")]
 impl From<vc::v2::syntax::JsonPresentation> for VpTokenItem {
     fn from(value: vc::v2::syntax::JsonPresentation) -> Self {
         let serde_json::Value::Object(obj) = serde_json::to_value(value)
@@ -187,6 +318,7 @@ impl From<vc::v2::syntax::JsonPresentation> for VpTokenItem {
     }
 }
 
+#[cfg(feature = "core")]
 impl From<AnyJsonPresentation> for VpTokenItem {
     fn from(value: AnyJsonPresentation) -> Self {
         let serde_json::Value::Object(obj) = serde_json::to_value(value)
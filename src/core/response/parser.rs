@@ -0,0 +1,263 @@
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value as Json;
+
+use crate::core::{
+    credential_format::ClaimFormatDesignation,
+    presentation_submission::DescriptorMap,
+    util::{jwt_payload, Base64Policy},
+};
+
+use super::parameters::{VpToken, VpTokenItem};
+
+/// Parses a presentation or credential of a particular [ClaimFormatDesignation] into a JSON value.
+///
+/// The parsed value is what descriptor map paths (and nested paths) are resolved against, and
+/// what is returned as the normalized claims of the presentation or credential.
+pub trait VpTokenParser: Debug + Send + Sync {
+    fn parse(&self, item: &VpTokenItem) -> Result<Json>;
+}
+
+/// Parser for JWT-encoded presentations and credentials.
+///
+/// Returns the JWT claims without verifying the signature. JSON objects are passed through as-is.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JwtParser {
+    base64: Base64Policy,
+}
+
+impl JwtParser {
+    pub fn new(base64: Base64Policy) -> Self {
+        Self { base64 }
+    }
+}
+
+impl VpTokenParser for JwtParser {
+    fn parse(&self, item: &VpTokenItem) -> Result<Json> {
+        match item {
+            VpTokenItem::String(jwt) => jwt_payload(jwt, self.base64),
+            VpTokenItem::JsonObject(object) => Ok(Json::Object(object.clone())),
+        }
+    }
+}
+
+/// Parser for presentations and credentials that are JSON objects, such as Linked Data Proofs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonParser;
+
+impl VpTokenParser for JsonParser {
+    fn parse(&self, item: &VpTokenItem) -> Result<Json> {
+        match item {
+            VpTokenItem::JsonObject(object) => Ok(Json::Object(object.clone())),
+            VpTokenItem::String(s) => serde_json::from_str(s).context("expected a JSON object"),
+        }
+    }
+}
+
+/// A registry of [VpTokenParser]s, keyed by [ClaimFormatDesignation].
+///
+/// The default registry can parse the JWT and Linked Data Proof formats. Parsers for other formats,
+/// such as `ac_vp` or proprietary formats, can be added with [VpTokenParserRegistry::register].
+#[derive(Debug, Clone)]
+pub struct VpTokenParserRegistry {
+    parsers: HashMap<ClaimFormatDesignation, Arc<dyn VpTokenParser>>,
+}
+
+impl Default for VpTokenParserRegistry {
+    fn default() -> Self {
+        Self::with_base64_policy(Base64Policy::default())
+    }
+}
+
+impl VpTokenParserRegistry {
+    /// The default registry, decoding JWTs according to the given [Base64Policy].
+    pub fn with_base64_policy(base64: Base64Policy) -> Self {
+        let mut registry = Self::empty();
+        for designation in [
+            ClaimFormatDesignation::Jwt,
+            ClaimFormatDesignation::JwtVc,
+            ClaimFormatDesignation::JwtVp,
+            ClaimFormatDesignation::JwtVcJson,
+            ClaimFormatDesignation::JwtVpJson,
+        ] {
+            registry.register(designation, JwtParser::new(base64));
+        }
+        for designation in [
+            ClaimFormatDesignation::Ldp,
+            ClaimFormatDesignation::LdpVc,
+            ClaimFormatDesignation::LdpVp,
+        ] {
+            registry.register(designation, JsonParser);
+        }
+        registry
+    }
+
+    /// A registry with no parsers.
+    pub fn empty() -> Self {
+        Self {
+            parsers: HashMap::new(),
+        }
+    }
+
+    /// Register a parser for a format, replacing any existing parser for that format.
+    pub fn register(
+        &mut self,
+        designation: ClaimFormatDesignation,
+        parser: impl VpTokenParser + 'static,
+    ) -> &mut Self {
+        self.parsers.insert(designation, Arc::new(parser));
+        self
+    }
+
+    /// Register the parsers of `other`, replacing the existing parsers for their formats.
+    pub fn merge(&mut self, other: Self) -> &mut Self {
+        self.parsers.extend(other.parsers);
+        self
+    }
+
+    /// Return the parser for a format.
+    pub fn get(&self, designation: &ClaimFormatDesignation) -> Option<&Arc<dyn VpTokenParser>> {
+        self.parsers.get(designation)
+    }
+
+    /// Parse an item with the parser registered for its format.
+    pub fn parse(&self, designation: &ClaimFormatDesignation, item: &VpTokenItem) -> Result<Json> {
+        let Some(parser) = self.get(designation) else {
+            bail!(
+                "no parser registered for format '{}'",
+                String::from(designation.clone())
+            )
+        };
+        parser.parse(item)
+    }
+
+    /// Resolve a descriptor map entry against a [VpToken], following any nested paths.
+    ///
+    /// The `path` of the top-level entry is evaluated against the `vp_token` (a single
+    /// presentation, or an array of presentations), and each nested path is evaluated against the
+    /// parsed result of the previous level.
+    ///
+    /// ## Returns
+    /// The parsed claims of each presentation or credential that the entry resolves to.
+    pub fn resolve(&self, vp_token: &VpToken, descriptor: &DescriptorMap) -> Result<Vec<Json>> {
+        let root = match vp_token.0.as_slice() {
+            [item] => serde_json::to_value(item)?,
+            items => serde_json::to_value(items)?,
+        };
+        self.resolve_in(&root, descriptor)
+    }
+
+    fn resolve_in(&self, root: &Json, descriptor: &DescriptorMap) -> Result<Vec<Json>> {
+        let selected = jsonpath_lib::select(root, descriptor.path())
+            .map_err(|e| anyhow::anyhow!("invalid path '{}': {e:?}", descriptor.path()))?;
+
+        if selected.is_empty() {
+            bail!(
+                "path '{}' of descriptor '{}' did not resolve to a value",
+                descriptor.path(),
+                descriptor.id()
+            )
+        }
+
+        let mut resolved = Vec::new();
+        for value in selected {
+            let item: VpTokenItem = serde_json::from_value(value.clone()).context(format!(
+                "path '{}' did not resolve to a string or an object",
+                descriptor.path()
+            ))?;
+            let parsed = self.parse(descriptor.format(), &item)?;
+            match descriptor.path_nested() {
+                None => resolved.push(parsed),
+                Some(nested) => resolved.extend(self.resolve_in(&parsed, nested)?),
+            }
+        }
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use base64::prelude::*;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct UppercaseParser;
+
+    impl VpTokenParser for UppercaseParser {
+        fn parse(&self, item: &VpTokenItem) -> Result<Json> {
+            let VpTokenItem::String(s) = item else {
+                bail!("expected a string")
+            };
+            Ok(json!({ "value": s.to_uppercase() }))
+        }
+    }
+
+    fn jwt(claims: Json) -> String {
+        let header = BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256"}"#);
+        let payload = BASE64_URL_SAFE_NO_PAD.encode(claims.to_string());
+        format!("{header}.{payload}.signature")
+    }
+
+    #[test]
+    fn resolve_nested_jwt() {
+        let vc = jwt(json!({ "vc": { "credentialSubject": { "id": "did:example:holder" } } }));
+        let vp = jwt(json!({ "vp": { "verifiableCredential": [vc] } }));
+        let vp_token = VpToken(vec![vp.into()]);
+
+        let descriptor = DescriptorMap::new("id", ClaimFormatDesignation::JwtVpJson, "$".into())
+            .set_path_nested(DescriptorMap::new(
+                "id",
+                ClaimFormatDesignation::JwtVcJson,
+                "$.vp.verifiableCredential[0]".into(),
+            ));
+
+        let resolved = VpTokenParserRegistry::default()
+            .resolve(&vp_token, &descriptor)
+            .unwrap();
+
+        assert_eq!(
+            resolved,
+            vec![json!({ "vc": { "credentialSubject": { "id": "did:example:holder" } } })]
+        );
+    }
+
+    #[test]
+    fn resolve_custom_format() {
+        let vp_token = VpToken(vec![
+            "first".to_string().into(),
+            "second".to_string().into(),
+        ]);
+        let format = ClaimFormatDesignation::Other("com.example.upper".into());
+
+        let mut registry = VpTokenParserRegistry::default();
+        assert!(registry
+            .resolve(
+                &vp_token,
+                &DescriptorMap::new("id", format.clone(), "$[1]".into())
+            )
+            .is_err());
+
+        registry.register(format.clone(), UppercaseParser);
+        let resolved = registry
+            .resolve(&vp_token, &DescriptorMap::new("id", format, "$[1]".into()))
+            .unwrap();
+
+        assert_eq!(resolved, vec![json!({ "value": "SECOND" })]);
+    }
+
+    #[test]
+    fn padded_jwt_payload() {
+        let payload = BASE64_URL_SAFE.encode(r#"{"vp":{"a":1}}"#);
+        assert!(payload.ends_with('='));
+        let item = VpTokenItem::from(format!("e30.{payload}.signature"));
+
+        assert_eq!(
+            JwtParser::default().parse(&item).unwrap(),
+            json!({ "vp": { "a": 1 } })
+        );
+        assert!(JwtParser::new(Base64Policy::Strict).parse(&item).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+//! Fields of the [tracing] spans that wallets and verifiers open around each stage of a
+//! presentation, so that the stages of a flow can be correlated in traces:
+//!
+//! | Field | Value |
+//! |-------|-------|
+//! | `session` | the UUID of the verifier's session |
+//! | `client_id` | the `client_id` of the request |
+//! | `client_id_scheme` | the client ID scheme of the request |
+//! | `response_mode` | the `response_mode` of the request |
+//! | `formats` | the formats of the presentations, e.g. `jwt_vp_json,mso_mdoc` |
+//!
+//! Fields that are only known once a stage has progressed, e.g. the `client_id` of a request
+//! being validated, are declared [Empty](tracing::field::Empty) and recorded when known.
+
+use tracing::{field::display, Span};
+
+use super::{
+    authorization_request::AuthorizationRequestObject, credential_format::ClaimFormatDesignation,
+};
+
+/// Record the `client_id`, `client_id_scheme` and `response_mode` of `request` in `span`.
+pub(crate) fn record_request(span: &Span, request: &AuthorizationRequestObject) {
+    span.record("client_id", request.client_id().0.as_str());
+    span.record("client_id_scheme", display(request.client_id_scheme()));
+    span.record("response_mode", display(request.response_mode()));
+}
+
+/// Record the `formats` of the presentations in `span`.
+pub(crate) fn record_formats<'a>(
+    span: &Span,
+    formats: impl IntoIterator<Item = &'a ClaimFormatDesignation>,
+) {
+    let mut recorded: Vec<String> = Vec::new();
+    for format in formats {
+        let format = String::from(format.clone());
+        if !recorded.contains(&format) {
+            recorded.push(format);
+        }
+    }
+    span.record("formats", recorded.join(","));
+}
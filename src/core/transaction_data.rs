@@ -0,0 +1,316 @@
+use std::fmt;
+
+use anyhow::{bail, Context, Error, Result};
+use base64::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+use sha2::{Digest, Sha256, Sha384};
+
+use super::authorization_request::{parameters::TransactionData, AuthorizationRequestObject};
+
+const SHA_256: &str = "sha-256";
+const SHA_384: &str = "sha-384";
+
+/// Claim of the holder proof (the SD-JWT Key Binding JWT) carrying the hashes.
+pub const TRANSACTION_DATA_HASHES: &str = "transaction_data_hashes";
+/// Claim of the holder proof carrying the hash algorithm, omitted when `sha-256`.
+pub const TRANSACTION_DATA_HASHES_ALG: &str = "transaction_data_hashes_alg";
+
+/// Hash algorithm used to compute `transaction_data_hashes`, identified by its name in the IANA
+/// "Named Information Hash Algorithm" registry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum TransactionDataHashAlg {
+    /// `sha-256`, the default when `transaction_data_hashes_alg` is omitted.
+    #[default]
+    Sha256,
+    /// `sha-384`.
+    Sha384,
+}
+
+impl TransactionDataHashAlg {
+    /// Hash a single `transaction_data` entry, returning the base64url-encoded digest.
+    ///
+    /// The digest is computed over the base64url string exactly as it appeared in the request,
+    /// without decoding or re-serializing it.
+    pub fn hash(&self, encoded_transaction_data: &str) -> String {
+        let digest = match self {
+            Self::Sha256 => Sha256::digest(encoded_transaction_data.as_bytes()).to_vec(),
+            Self::Sha384 => Sha384::digest(encoded_transaction_data.as_bytes()).to_vec(),
+        };
+        BASE64_URL_SAFE_NO_PAD.encode(digest)
+    }
+
+    /// Compute the `transaction_data_hashes` for every entry of the `transaction_data`, in order.
+    pub fn hashes(&self, transaction_data: &TransactionData) -> Vec<String> {
+        transaction_data
+            .0
+            .iter()
+            .map(|entry| self.hash(entry))
+            .collect()
+    }
+}
+
+impl TryFrom<String> for TransactionDataHashAlg {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            SHA_256 => Ok(Self::Sha256),
+            SHA_384 => Ok(Self::Sha384),
+            _ => bail!("unsupported transaction data hash algorithm '{value}'"),
+        }
+    }
+}
+
+impl From<TransactionDataHashAlg> for String {
+    fn from(value: TransactionDataHashAlg) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for TransactionDataHashAlg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sha256 => SHA_256,
+            Self::Sha384 => SHA_384,
+        }
+        .fmt(f)
+    }
+}
+
+/// Check that the `transaction_data_hashes` returned by the wallet match the `transaction_data`
+/// from the request.
+///
+/// Every entry of the `transaction_data` must be hashed exactly once, although the order of the
+/// hashes is not significant.
+pub fn verify_hashes(
+    alg: TransactionDataHashAlg,
+    transaction_data: &TransactionData,
+    transaction_data_hashes: &[String],
+) -> Result<()> {
+    let mut expected = alg.hashes(transaction_data);
+    let mut received = transaction_data_hashes.to_vec();
+    expected.sort();
+    received.sort();
+
+    if expected != received {
+        bail!("transaction_data_hashes did not match the transaction_data from the request ({alg})")
+    }
+
+    Ok(())
+}
+
+/// The binding of the `transaction_data` of a request into the Key Binding JWT of an SD-JWT
+/// presentation.
+///
+/// mdoc presentations are not covered, as this library does not decode their device-signed items.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionDataBinding {
+    pub alg: TransactionDataHashAlg,
+    pub hashes: Vec<String>,
+}
+
+impl TransactionDataBinding {
+    pub fn new(alg: TransactionDataHashAlg, transaction_data: &TransactionData) -> Self {
+        Self {
+            alg,
+            hashes: alg.hashes(transaction_data),
+        }
+    }
+
+    /// Compute the binding for the `transaction_data` of a request, if any.
+    ///
+    /// The hash algorithm is the first one supported by this library that every entry accepts
+    /// through its `transaction_data_hashes_alg`, defaulting to `sha-256`.
+    pub fn from_request(request: &AuthorizationRequestObject) -> Result<Option<Self>> {
+        let Some(transaction_data) = request.get::<TransactionData>() else {
+            return Ok(None);
+        };
+        let transaction_data = transaction_data?;
+
+        let mut accepted = vec![
+            TransactionDataHashAlg::Sha256,
+            TransactionDataHashAlg::Sha384,
+        ];
+        for entry in &transaction_data.0 {
+            let algs = accepted_algs(entry)?;
+            accepted.retain(|alg| algs.contains(&alg.to_string()));
+        }
+
+        let Some(alg) = accepted.first() else {
+            bail!("no supported transaction_data_hashes_alg is accepted by every transaction_data entry")
+        };
+
+        Ok(Some(Self::new(*alg, &transaction_data)))
+    }
+
+    /// Insert the binding into the claims of a holder proof.
+    pub fn insert_into(&self, claims: &mut Map<String, Json>) {
+        claims.insert(TRANSACTION_DATA_HASHES.into(), self.hashes.clone().into());
+        if self.alg != TransactionDataHashAlg::Sha256 {
+            claims.insert(
+                TRANSACTION_DATA_HASHES_ALG.into(),
+                self.alg.to_string().into(),
+            );
+        }
+    }
+
+    /// Check that the claims of a holder proof bind the `transaction_data` of the request.
+    ///
+    /// The hash algorithm of the proof must be accepted by every entry through its
+    /// `transaction_data_hashes_alg`, defaulting to `sha-256`, so that the holder cannot
+    /// downgrade it.
+    pub fn verify(transaction_data: &TransactionData, claims: &Map<String, Json>) -> Result<()> {
+        let alg: TransactionDataHashAlg = match claims.get(TRANSACTION_DATA_HASHES_ALG) {
+            Some(alg) => serde_json::from_value(alg.clone())?,
+            None => TransactionDataHashAlg::default(),
+        };
+        for (index, entry) in transaction_data.0.iter().enumerate() {
+            if !accepted_algs(entry)?.contains(&alg.to_string()) {
+                bail!("transaction_data entry {index} does not accept the hash algorithm '{alg}'")
+            }
+        }
+        let hashes: Vec<String> = serde_json::from_value(
+            claims
+                .get(TRANSACTION_DATA_HASHES)
+                .cloned()
+                .context("holder proof is missing transaction_data_hashes")?,
+        )
+        .context("transaction_data_hashes was not an array of strings")?;
+
+        verify_hashes(alg, transaction_data, &hashes)
+    }
+}
+
+/// The `transaction_data_hashes_alg` accepted by a `transaction_data` entry, `sha-256` if it
+/// lists none.
+fn accepted_algs(encoded_transaction_data: &str) -> Result<Vec<String>> {
+    match decode(encoded_transaction_data)?.remove(TRANSACTION_DATA_HASHES_ALG) {
+        Some(algs) => serde_json::from_value(algs)
+            .context("transaction_data_hashes_alg was not an array of strings"),
+        None => Ok(vec![SHA_256.to_owned()]),
+    }
+}
+
+/// Decode a single `transaction_data` entry into its JSON object.
+pub fn decode(encoded_transaction_data: &str) -> Result<Map<String, Json>> {
+    let bytes = BASE64_URL_SAFE_NO_PAD
+        .decode(encoded_transaction_data)
+        .context("transaction_data entry was not valid base64url")?;
+    serde_json::from_slice(&bytes).context("transaction_data entry was not a JSON object")
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn transaction_data() -> TransactionData {
+        TransactionData(vec![
+            BASE64_URL_SAFE_NO_PAD.encode(
+                json!({
+                    "type": "payment_data",
+                    "credential_ids": ["pid"],
+                    "payload": { "amount": "23.58", "currency": "EUR" }
+                })
+                .to_string(),
+            ),
+            BASE64_URL_SAFE_NO_PAD.encode(json!({ "type": "qes_authorization" }).to_string()),
+        ])
+    }
+
+    fn transaction_data_with_algs(algs: Json) -> TransactionData {
+        TransactionData(vec![BASE64_URL_SAFE_NO_PAD.encode(
+            json!({
+                "type": "payment_data",
+                "credential_ids": ["pid"],
+                "transaction_data_hashes_alg": algs
+            })
+            .to_string(),
+        )])
+    }
+
+    #[test]
+    fn hash_is_over_encoded_string() {
+        assert_eq!(
+            TransactionDataHashAlg::Sha256.hash("abc"),
+            "ungWv48Bz-pBQUDeXa4iI7ADYaOWF3qctBD_YfIAFa0"
+        );
+        assert_eq!(
+            TransactionDataHashAlg::Sha384.hash("abc"),
+            "ywB1P0WjXou1oD1pmsZQBycsMqsO3tFjGotgWkP_W-2AhgcroefMI1i67KE0yCWn"
+        );
+    }
+
+    #[test]
+    fn verify() {
+        let transaction_data = transaction_data();
+        let mut hashes = TransactionDataHashAlg::Sha256.hashes(&transaction_data);
+        hashes.reverse();
+
+        verify_hashes(TransactionDataHashAlg::Sha256, &transaction_data, &hashes).unwrap();
+        assert!(verify_hashes(TransactionDataHashAlg::Sha384, &transaction_data, &hashes).is_err());
+        assert!(verify_hashes(
+            TransactionDataHashAlg::Sha256,
+            &transaction_data,
+            &hashes[1..]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn binding_round_trip() {
+        let transaction_data = transaction_data_with_algs(json!(["sha-256", "sha-384"]));
+        let binding =
+            TransactionDataBinding::new(TransactionDataHashAlg::Sha384, &transaction_data);
+
+        let mut kb_jwt_claims = Map::new();
+        binding.insert_into(&mut kb_jwt_claims);
+        assert_eq!(kb_jwt_claims[TRANSACTION_DATA_HASHES_ALG], "sha-384");
+        TransactionDataBinding::verify(&transaction_data, &kb_jwt_claims).unwrap();
+
+        kb_jwt_claims.remove(TRANSACTION_DATA_HASHES_ALG);
+        assert!(TransactionDataBinding::verify(&transaction_data, &kb_jwt_claims).is_err());
+        assert!(TransactionDataBinding::verify(&transaction_data, &Map::new()).is_err());
+    }
+
+    #[test]
+    fn binding_alg_downgrade() {
+        let sha_384_only = transaction_data_with_algs(json!(["sha-384"]));
+        let mut claims = Map::new();
+        TransactionDataBinding::new(TransactionDataHashAlg::Sha384, &sha_384_only)
+            .insert_into(&mut claims);
+        TransactionDataBinding::verify(&sha_384_only, &claims).unwrap();
+
+        // Correct sha-256 hashes, but the entry only accepts sha-384.
+        let mut claims = Map::new();
+        TransactionDataBinding::new(TransactionDataHashAlg::Sha256, &sha_384_only)
+            .insert_into(&mut claims);
+        let error = TransactionDataBinding::verify(&sha_384_only, &claims).unwrap_err();
+        assert!(error.to_string().contains("does not accept"), "{error}");
+
+        // Without transaction_data_hashes_alg, entries only accept sha-256.
+        let without_algs = transaction_data();
+        let mut claims = Map::new();
+        TransactionDataBinding::new(TransactionDataHashAlg::Sha384, &without_algs)
+            .insert_into(&mut claims);
+        assert!(TransactionDataBinding::verify(&without_algs, &claims).is_err());
+    }
+
+    #[test]
+    fn decode_entry() {
+        let decoded = decode(&transaction_data().0[0]).unwrap();
+        assert_eq!(decoded["type"], "payment_data");
+    }
+
+    #[test]
+    fn alg_serialization() {
+        assert_eq!(
+            serde_json::to_value(TransactionDataHashAlg::Sha384).unwrap(),
+            json!("sha-384")
+        );
+        assert!(serde_json::from_value::<TransactionDataHashAlg>(json!("md5")).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use http::{Request, Response};
+
+use super::{retry::HttpOperation, AsyncHttpClient};
+
+/// A hook to modify outgoing HTTP requests before they are sent, e.g. to add headers required by
+/// a gateway, or a DPoP proof.
+///
+/// Values for the [AsyncHttpClient] implementation, such as the client certificate to use for
+/// mTLS, can be attached as request [extensions](http::Request::extensions).
+///
+/// The hook is invoked again for every retry, so that single-use values can be regenerated.
+#[async_trait]
+pub trait HttpMiddleware: Send + Sync {
+    async fn prepare(&self, operation: HttpOperation, request: &mut Request<Vec<u8>>)
+        -> Result<()>;
+}
+
+/// An [AsyncHttpClient] applying an optional [HttpMiddleware] to every request.
+#[cfg_attr(not(feature = "wallet"), allow(dead_code))]
+pub(crate) struct MiddlewareClient<'a, H: ?Sized> {
+    pub inner: &'a H,
+    pub middleware: Option<&'a dyn HttpMiddleware>,
+    pub operation: HttpOperation,
+}
+
+#[async_trait]
+impl<H: AsyncHttpClient + Sync + ?Sized> AsyncHttpClient for MiddlewareClient<'_, H> {
+    async fn execute(&self, mut request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+        if let Some(middleware) = self.middleware {
+            middleware.prepare(self.operation, &mut request).await?;
+        }
+        self.inner.execute(request).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    struct Dpop(AtomicU32);
+
+    #[async_trait]
+    impl HttpMiddleware for Dpop {
+        async fn prepare(
+            &self,
+            operation: HttpOperation,
+            request: &mut Request<Vec<u8>>,
+        ) -> Result<()> {
+            assert_eq!(operation, HttpOperation::Response);
+            let proof = format!("proof-{}", self.0.fetch_add(1, Ordering::SeqCst));
+            request.headers_mut().insert("DPoP", proof.parse()?);
+            Ok(())
+        }
+    }
+
+    /// Responds with the DPoP header of the request.
+    struct Echo;
+
+    #[async_trait]
+    impl AsyncHttpClient for Echo {
+        async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+            let dpop = request.headers().get("DPoP").unwrap().as_bytes().to_vec();
+            Ok(Response::new(dpop))
+        }
+    }
+
+    #[tokio::test]
+    async fn applied_to_every_request() {
+        let middleware = Dpop(AtomicU32::new(0));
+        let client = MiddlewareClient {
+            inner: &Echo,
+            middleware: Some(&middleware),
+            operation: HttpOperation::Response,
+        };
+        for expected in ["proof-0", "proof-1"] {
+            let response = client
+                .execute(Request::new(vec![]))
+                .await
+                .unwrap()
+                .into_body();
+            assert_eq!(response, expected.as_bytes());
+        }
+    }
+}
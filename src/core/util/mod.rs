@@ -1,65 +1,167 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
+use base64::prelude::*;
 use http::{Request, Response};
+use serde::de::DeserializeOwned;
+use url::Url;
+
+pub mod middleware;
+#[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+mod reqwest_client;
+pub mod retry;
+
+#[cfg(any(feature = "rustls-tls", feature = "native-tls"))]
+pub use reqwest_client::{RedirectPolicy, ReqwestClient, ReqwestClientBuilder, TlsVersion};
 
 /// Generic HTTP client.
 ///
-/// A trait is used here so to facilitate native HTTP/TLS when compiled for mobile applications.
+/// A trait is used here so to facilitate native HTTP/TLS when compiled for mobile applications,
+/// `fetch` on wasm, or test doubles. Only [execute](AsyncHttpClient::execute) needs to be
+/// implemented, [ReqwestClient] is provided as a default implementation.
 #[async_trait]
 pub trait AsyncHttpClient {
     async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>>;
+
+    /// Make a GET request.
+    async fn get(&self, uri: &Url) -> Result<Response<Vec<u8>>>
+    where
+        Self: Sync,
+    {
+        let request = base_request()
+            .method("GET")
+            .uri(uri.as_str())
+            .body(vec![])
+            .context("failed to build GET request")?;
+        self.execute(request).await
+    }
+
+    /// Make a POST request with the given `Content-Type`.
+    async fn post(&self, uri: &Url, content_type: &str, body: Vec<u8>) -> Result<Response<Vec<u8>>>
+    where
+        Self: Sync,
+    {
+        let request = base_request()
+            .method("POST")
+            .uri(uri.as_str())
+            .header(http::header::CONTENT_TYPE, content_type)
+            .body(body)
+            .context("failed to build POST request")?;
+        self.execute(request).await
+    }
 }
 
 pub(crate) fn base_request() -> http::request::Builder {
     Request::builder().header("Prefer", "OID4VP-0.0.20")
 }
 
-#[derive(Debug)]
-pub struct ReqwestClient(reqwest::Client);
+/// Policy for decoding base64url values received from wallets, such as the entries of a
+/// `vp_token` or the segments of a JWT.
+///
+/// Wallets in the field disagree on padding and occasionally use the standard alphabet, so by
+/// default decoding is [lenient](Base64Policy::Lenient). [Base64Policy::Strict] only accepts
+/// unpadded base64url as required by the specification, and is intended for conformance testing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Base64Policy {
+    /// Only accept unpadded base64url.
+    Strict,
+    /// Accept padded and unpadded values, in either the url-safe or the standard alphabet.
+    #[default]
+    Lenient,
+}
 
-impl ReqwestClient {
-    pub fn new() -> Result<Self> {
-        reqwest::Client::builder()
-            .use_rustls_tls()
-            .build()
-            .context("unable to build http_client")
-            .map(Self)
+impl Base64Policy {
+    pub fn decode(&self, value: &str) -> Result<Vec<u8>> {
+        match self {
+            Self::Strict => BASE64_URL_SAFE_NO_PAD
+                .decode(value)
+                .context("value was not valid unpadded base64url"),
+            Self::Lenient => {
+                let normalized: String = value
+                    .trim_end_matches('=')
+                    .chars()
+                    .map(|c| match c {
+                        '+' => '-',
+                        '/' => '_',
+                        c => c,
+                    })
+                    .collect();
+                BASE64_URL_SAFE_NO_PAD
+                    .decode(normalized)
+                    .context("value was not valid base64")
+            }
+        }
     }
 }
 
-#[async_trait]
-impl AsyncHttpClient for ReqwestClient {
-    async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
-        let response = self
-            .0
-            .execute(request.try_into().context("unable to convert request")?)
-            .await
-            .context("http request failed")?;
-
-        let mut builder = Response::builder()
-            .status(response.status())
-            .version(response.version());
-
-        builder
-            .extensions_mut()
-            .context("unable to set extensions")?
-            .extend(response.extensions().clone());
-
-        builder
-            .headers_mut()
-            .context("unable to set headers")?
-            .extend(response.headers().clone());
-
-        builder
-            .body(
-                response
-                    .bytes()
-                    .await
-                    .context("failed to extract response body")?
-                    .to_vec(),
-            )
-            .context("unable to construct response")
+/// Decode the header and payload of a compact JWS, without verifying it.
+#[cfg(feature = "verifier")]
+pub(crate) fn decode_jwt<H, P>(jwt: &str, base64: Base64Policy) -> Result<(H, P)>
+where
+    H: DeserializeOwned,
+    P: DeserializeOwned,
+{
+    let (header, payload) = jwt_segments(jwt)?;
+    Ok((
+        decode_jwt_segment(header, "header", base64)?,
+        decode_jwt_segment(payload, "payload", base64)?,
+    ))
+}
+
+/// Decode the payload of a compact JWS, without verifying it.
+pub(crate) fn jwt_payload<P: DeserializeOwned>(jwt: &str, base64: Base64Policy) -> Result<P> {
+    let (_, payload) = jwt_segments(jwt)?;
+    decode_jwt_segment(payload, "payload", base64)
+}
+
+fn jwt_segments(jwt: &str) -> Result<(&str, &str)> {
+    let mut parts = jwt.split('.');
+    let (Some(header), Some(payload), Some(_signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        bail!("expected a compact serialized JWT")
+    };
+    Ok((header, payload))
+}
+
+fn decode_jwt_segment<T: DeserializeOwned>(
+    segment: &str,
+    name: &str,
+    base64: Base64Policy,
+) -> Result<T> {
+    let bytes = base64
+        .decode(segment)
+        .with_context(|| format!("JWT {name} was not valid base64url"))?;
+    serde_json::from_slice(&bytes).with_context(|| format!("JWT {name} was not valid JSON"))
+}
+
+/// Seconds since the UNIX epoch, as used by the `iat` and `exp` claims of JWTs.
+pub(crate) fn unix_time(time: SystemTime) -> Result<u64> {
+    Ok(time
+        .duration_since(UNIX_EPOCH)
+        .context("time was before the UNIX epoch")?
+        .as_secs())
+}
+
+/// Formats sensitive data for [Debug](std::fmt::Debug) output without revealing it.
+///
+/// Only the length and a truncated SHA-256 digest are shown, which is enough to correlate values
+/// across log lines.
+pub struct Redacted<'a>(pub &'a [u8]);
+
+impl std::fmt::Debug for Redacted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use sha2::Digest;
+
+        let digest = sha2::Sha256::digest(self.0);
+        write!(f, "<redacted {} bytes, sha256:", self.0.len())?;
+        for byte in &digest[..4] {
+            write!(f, "{byte:02x}")?;
+        }
+        write!(f, ">")
     }
 }
 
@@ -67,6 +169,65 @@ impl AsyncHttpClient for ReqwestClient {
 mod test {
     use http::Response;
 
+    use super::{AsyncHttpClient, Base64Policy, Redacted};
+
+    struct EchoClient;
+
+    #[async_trait::async_trait]
+    impl AsyncHttpClient for EchoClient {
+        async fn execute(
+            &self,
+            request: http::Request<Vec<u8>>,
+        ) -> anyhow::Result<Response<Vec<u8>>> {
+            let summary = format!(
+                "{} {} {:?} {}",
+                request.method(),
+                request.uri(),
+                request.headers().get(http::header::CONTENT_TYPE),
+                String::from_utf8_lossy(request.body())
+            );
+            Ok(Response::new(summary.into_bytes()))
+        }
+    }
+
+    #[tokio::test]
+    async fn http_client_helpers() {
+        let uri = "https://example.com/path".parse().unwrap();
+
+        let response = EchoClient.get(&uri).await.unwrap();
+        assert_eq!(response.body(), b"GET https://example.com/path None ");
+
+        let response = EchoClient
+            .post(&uri, "application/json", b"{}".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            response.body(),
+            b"POST https://example.com/path Some(\"application/json\") {}"
+        );
+    }
+
+    #[test]
+    fn redacted() {
+        assert_eq!(
+            format!("{:?}", Redacted(b"abc")),
+            "<redacted 3 bytes, sha256:ba7816bf>"
+        );
+    }
+
+    #[test]
+    fn base64_policy() {
+        // 0xfb 0xff encodes to '-_8' in base64url and '+/8=' in standard base64.
+        for value in ["-_8", "-_8=", "+/8", "+/8="] {
+            assert_eq!(Base64Policy::Lenient.decode(value).unwrap(), [0xfb, 0xff]);
+        }
+        assert_eq!(Base64Policy::Strict.decode("-_8").unwrap(), [0xfb, 0xff]);
+        for value in ["-_8=", "+/8", "+/8="] {
+            assert!(Base64Policy::Strict.decode(value).is_err());
+        }
+        assert!(Base64Policy::Lenient.decode("-_8==x").is_err());
+    }
+
     #[test]
     fn debug() {
         Response::builder().extensions_mut().unwrap();
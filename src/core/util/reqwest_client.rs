@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use http::{Request, Response};
+use reqwest::{redirect, tls, Proxy};
+use url::Url;
+
+use super::AsyncHttpClient;
+
+/// An [AsyncHttpClient] backed by [reqwest], with the TLS backend selected by the `rustls-tls`
+/// (default) or `native-tls` feature. With both features enabled, native-tls is used.
+///
+/// The client is cheap to clone, clones share their connection pool, so that a wallet and e.g. an
+/// [HttpAttestationSource](crate::verifier::attestation::HttpAttestationSource) can own the same
+/// configured client.
+#[derive(Debug, Clone)]
+pub struct ReqwestClient(reqwest::Client);
+
+impl ReqwestClient {
+    /// A client with the default configuration, see [ReqwestClientBuilder].
+    pub fn new() -> Result<Self> {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> ReqwestClientBuilder {
+        ReqwestClientBuilder::default()
+    }
+}
+
+/// The redirects that a [ReqwestClient] follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectPolicy {
+    /// Redirects are returned as responses.
+    None,
+    /// Follow up to this many redirects, then fail.
+    Limited(usize),
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self::Limited(10)
+    }
+}
+
+/// A TLS protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    Tls1_2,
+    /// Not supported as a minimum or maximum version by the native-tls backend.
+    Tls1_3,
+}
+
+impl From<TlsVersion> for tls::Version {
+    fn from(value: TlsVersion) -> Self {
+        match value {
+            TlsVersion::Tls1_2 => tls::Version::TLS_1_2,
+            TlsVersion::Tls1_3 => tls::Version::TLS_1_3,
+        }
+    }
+}
+
+/// Builder for [ReqwestClient].
+///
+/// By default there are no timeouts, the proxies of the environment (e.g. `HTTPS_PROXY`) are
+/// used, up to 10 redirects are followed, and the TLS versions are those of the TLS backend.
+/// Timeouts of individual operations of a wallet are configured with
+/// [RetryPolicy](super::retry::RetryPolicy) instead.
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestClientBuilder {
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    proxy: Option<Url>,
+    user_agent: Option<String>,
+    redirect_policy: RedirectPolicy,
+    min_tls_version: Option<TlsVersion>,
+    max_tls_version: Option<TlsVersion>,
+}
+
+impl ReqwestClientBuilder {
+    /// Time limit for establishing a connection, including the TLS handshake.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Time limit for each read of a response, reset after every successful read.
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Send every request through the proxy at `proxy`, instead of the proxies of the environment.
+    pub fn with_proxy(mut self, proxy: Url) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn with_redirect_policy(mut self, redirect_policy: RedirectPolicy) -> Self {
+        self.redirect_policy = redirect_policy;
+        self
+    }
+
+    pub fn with_min_tls_version(mut self, version: TlsVersion) -> Self {
+        self.min_tls_version = Some(version);
+        self
+    }
+
+    pub fn with_max_tls_version(mut self, version: TlsVersion) -> Self {
+        self.max_tls_version = Some(version);
+        self
+    }
+
+    /// Build the client, failing if the configuration is not supported, e.g. if the minimum TLS
+    /// version is above the maximum.
+    pub fn build(self) -> Result<ReqwestClient> {
+        if let (Some(min), Some(max)) = (self.min_tls_version, self.max_tls_version) {
+            if min > max {
+                bail!("the minimum TLS version {min:?} is above the maximum {max:?}")
+            }
+        }
+
+        let builder = reqwest::Client::builder();
+        #[cfg(feature = "native-tls")]
+        let mut builder = builder.use_native_tls();
+        #[cfg(not(feature = "native-tls"))]
+        let mut builder = builder.use_rustls_tls();
+
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(proxy) = self.proxy {
+            let proxy =
+                Proxy::all(proxy.as_str()).with_context(|| format!("invalid proxy '{proxy}'"))?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(user_agent) = self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        builder = builder.redirect(match self.redirect_policy {
+            RedirectPolicy::None => redirect::Policy::none(),
+            RedirectPolicy::Limited(max) => redirect::Policy::limited(max),
+        });
+        if let Some(version) = self.min_tls_version {
+            builder = builder.min_tls_version(version.into());
+        }
+        if let Some(version) = self.max_tls_version {
+            builder = builder.max_tls_version(version.into());
+        }
+
+        builder
+            .build()
+            .context("unable to build http_client")
+            .map(ReqwestClient)
+    }
+}
+
+#[async_trait]
+impl AsyncHttpClient for ReqwestClient {
+    async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+        let response = self
+            .0
+            .execute(request.try_into().context("unable to convert request")?)
+            .await
+            .context("http request failed")?;
+
+        let mut builder = Response::builder()
+            .status(response.status())
+            .version(response.version());
+
+        builder
+            .extensions_mut()
+            .context("unable to set extensions")?
+            .extend(response.extensions().clone());
+
+        builder
+            .headers_mut()
+            .context("unable to set headers")?
+            .extend(response.headers().clone());
+
+        builder
+            .body(
+                response
+                    .bytes()
+                    .await
+                    .context("failed to extract response body")?
+                    .to_vec(),
+            )
+            .context("unable to construct response")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builds_configured_client() {
+        ReqwestClient::builder()
+            .with_connect_timeout(Duration::from_secs(5))
+            .with_read_timeout(Duration::from_secs(30))
+            .with_proxy("http://proxy.example.com:3128".parse().unwrap())
+            .with_user_agent("wallet/1.0")
+            .with_redirect_policy(RedirectPolicy::None)
+            .with_min_tls_version(TlsVersion::Tls1_2)
+            .with_max_tls_version(TlsVersion::Tls1_2)
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_configuration() {
+        assert!(ReqwestClient::builder()
+            .with_min_tls_version(TlsVersion::Tls1_3)
+            .with_max_tls_version(TlsVersion::Tls1_2)
+            .build()
+            .is_err());
+        assert!(ReqwestClient::builder()
+            .with_user_agent("wallet\n1.0")
+            .build()
+            .is_err());
+    }
+}
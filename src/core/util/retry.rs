@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use futures::future::{select, Either};
+use futures_timer::Delay;
+use http::{Request, Response, StatusCode};
+
+use super::AsyncHttpClient;
+
+/// The HTTP operations made by the wallet that have their own [RetryPolicy].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpOperation {
+    /// Fetching the Request Object from the `request_uri`.
+    RequestObject,
+    /// Submitting the Authorization Response (or an error response) to the `response_uri`.
+    Response,
+}
+
+/// Timeout and retry behaviour of an HTTP operation.
+///
+/// Only failures after which repeating the request is safe are retried: for non-idempotent
+/// requests (e.g. response submission) these are responses where the server indicates that the
+/// request was not processed (`408`, `429` and `503`). For idempotent requests, other server errors,
+/// timeouts and transport errors are retried too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Time limit for each attempt.
+    pub timeout: Option<Duration>,
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every subsequent retry.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Whether the request can safely be repeated after a timeout or a transport error.
+    pub idempotent: bool,
+}
+
+impl RetryPolicy {
+    /// Default policy for idempotent requests: 30 second timeout and up to 3 attempts.
+    pub fn idempotent() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(30)),
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(4),
+            idempotent: true,
+        }
+    }
+
+    /// Default policy for non-idempotent requests: 30 second timeout and up to 3 attempts.
+    pub fn non_idempotent() -> Self {
+        Self {
+            idempotent: false,
+            ..Self::idempotent()
+        }
+    }
+
+    /// A single attempt with no timeout.
+    pub fn none() -> Self {
+        Self {
+            timeout: None,
+            max_attempts: 1,
+            ..Self::idempotent()
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// The default policy for an operation.
+    pub fn for_operation(operation: HttpOperation) -> Self {
+        match operation {
+            HttpOperation::RequestObject => Self::idempotent(),
+            HttpOperation::Response => Self::non_idempotent(),
+        }
+    }
+
+    fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+
+    fn is_retryable_status(&self, status: StatusCode) -> bool {
+        match status {
+            StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::SERVICE_UNAVAILABLE => true,
+            StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::GATEWAY_TIMEOUT => self.idempotent,
+            _ => false,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::idempotent()
+    }
+}
+
+/// Execute a request according to a [RetryPolicy].
+///
+/// When all attempts fail with a retryable status, the last response is returned so that the
+/// caller can report it.
+pub async fn execute_with_policy<H: AsyncHttpClient + Sync + ?Sized>(
+    client: &H,
+    request: Request<Vec<u8>>,
+    policy: &RetryPolicy,
+) -> Result<Response<Vec<u8>>> {
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let last_attempt = attempt == max_attempts;
+
+        let result = match policy.timeout {
+            None => client.execute(clone_request(&request)?).await,
+            Some(timeout) => {
+                match select(
+                    client.execute(clone_request(&request)?),
+                    Delay::new(timeout),
+                )
+                .await
+                {
+                    Either::Left((result, _)) => result,
+                    Either::Right(_) => Err(anyhow::anyhow!(
+                        "request to {} timed out after {timeout:?}",
+                        request.uri()
+                    )),
+                }
+            }
+        };
+
+        match result {
+            Ok(response) if last_attempt || !policy.is_retryable_status(response.status()) => {
+                return Ok(response)
+            }
+            Err(e) if last_attempt || !policy.idempotent => {
+                return Err(e).context(format!("request failed after {attempt} attempt(s)"))
+            }
+            _ => Delay::new(policy.backoff(attempt - 1)).await,
+        }
+    }
+}
+
+fn clone_request(request: &Request<Vec<u8>>) -> Result<Request<Vec<u8>>> {
+    let mut builder = Request::builder()
+        .method(request.method().clone())
+        .uri(request.uri().clone())
+        .version(request.version());
+    let Some(headers) = builder.headers_mut() else {
+        bail!("failed to copy request")
+    };
+    headers.extend(request.headers().clone());
+    builder
+        .body(request.body().clone())
+        .context("failed to copy request")
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    /// Responds with the given statuses in order, then with `200`.
+    struct Flaky {
+        statuses: Vec<u16>,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl AsyncHttpClient for Flaky {
+        async fn execute(&self, _: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) as usize;
+            match self.statuses.get(call) {
+                Some(0) => bail!("connection reset"),
+                Some(status) => Ok(Response::builder().status(*status).body(vec![])?),
+                None => Ok(Response::builder().status(200).body(vec![])?),
+            }
+        }
+    }
+
+    fn flaky(statuses: &[u16]) -> Flaky {
+        Flaky {
+            statuses: statuses.to_vec(),
+            calls: AtomicU32::new(0),
+        }
+    }
+
+    fn request() -> Request<Vec<u8>> {
+        Request::builder()
+            .uri("https://example.com")
+            .header("Prefer", "test")
+            .body(vec![1, 2, 3])
+            .unwrap()
+    }
+
+    fn policy(idempotent: bool) -> RetryPolicy {
+        RetryPolicy {
+            idempotent,
+            ..RetryPolicy::idempotent().with_backoff(Duration::ZERO, Duration::ZERO)
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_idempotent_requests() {
+        let client = flaky(&[0, 502]);
+        let response = execute_with_policy(&client, request(), &policy(true))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(client.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn non_idempotent_requests_are_only_retried_when_not_processed() {
+        let client = flaky(&[503, 0]);
+        assert!(execute_with_policy(&client, request(), &policy(false))
+            .await
+            .is_err());
+        assert_eq!(client.calls.load(Ordering::SeqCst), 2);
+
+        let client = flaky(&[502]);
+        let response = execute_with_policy(&client, request(), &policy(false))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 502);
+        assert_eq!(client.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let client = flaky(&[503, 503, 503, 503]);
+        let response = execute_with_policy(&client, request(), &policy(true))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 503);
+        assert_eq!(client.calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use http::{Request, Response};
+
+use crate::core::util::AsyncHttpClient;
+
+use super::FfiError;
+
+/// An HTTP header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FfiHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FfiHttpRequest {
+    /// The method, e.g. `GET`.
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<FfiHeader>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FfiHttpResponse {
+    pub status: u16,
+    pub headers: Vec<FfiHeader>,
+    pub body: Vec<u8>,
+}
+
+/// The HTTP client of the host app, e.g. backed by `URLSession` or OkHttp, which the wallet
+/// retrieves Request Objects and submits responses with.
+///
+/// Redirects must not be followed, and responses with error statuses must be returned rather
+/// than failing.
+#[async_trait]
+pub trait FfiHttpClient: Send + Sync + 'static {
+    async fn execute(&self, request: FfiHttpRequest) -> Result<FfiHttpResponse, FfiError>;
+}
+
+/// An [AsyncHttpClient] calling an [FfiHttpClient].
+#[derive(Clone)]
+pub(crate) struct HttpBridge(pub Arc<dyn FfiHttpClient>);
+
+#[async_trait]
+impl AsyncHttpClient for HttpBridge {
+    async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+        let (parts, body) = request.into_parts();
+        let headers = parts
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                Ok(FfiHeader {
+                    name: name.to_string(),
+                    value: value
+                        .to_str()
+                        .with_context(|| format!("header '{name}' is not visible ASCII"))?
+                        .to_owned(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        let request = FfiHttpRequest {
+            method: parts.method.to_string(),
+            url: parts.uri.to_string(),
+            headers,
+            body,
+        };
+
+        let response = self.0.execute(request).await?;
+
+        let mut builder = Response::builder().status(response.status);
+        for FfiHeader { name, value } in response.headers {
+            builder = builder.header(name, value);
+        }
+        builder
+            .body(response.body)
+            .context("the response of the HTTP client is invalid")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use http::header::CONTENT_TYPE;
+
+    use super::*;
+
+    /// Returns the request as the response.
+    struct Echo;
+
+    #[async_trait]
+    impl FfiHttpClient for Echo {
+        async fn execute(&self, request: FfiHttpRequest) -> Result<FfiHttpResponse, FfiError> {
+            if request.method != "POST" {
+                return Err(FfiError::Callback {
+                    message: "unexpected method".into(),
+                });
+            }
+            Ok(FfiHttpResponse {
+                status: 201,
+                headers: request.headers,
+                body: request.url.into_bytes(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn bridge() {
+        let client = HttpBridge(Arc::new(Echo));
+        let response = client
+            .post(
+                &"https://example.com/response".parse().unwrap(),
+                "application/x-www-form-urlencoded",
+                b"vp_token=abc".to_vec(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 201);
+        assert_eq!(
+            response.headers()[CONTENT_TYPE],
+            "application/x-www-form-urlencoded"
+        );
+        assert_eq!(response.body(), b"https://example.com/response");
+
+        let error = client
+            .get(&"https://example.com/request".parse().unwrap())
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<FfiError>().is_some());
+    }
+}
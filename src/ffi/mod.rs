@@ -0,0 +1,105 @@
+//! A facade over the wallet API for foreign-language bindings, e.g. generated with uniffi or
+//! written by hand for JNI and Swift.
+//!
+//! The [Wallet](crate::wallet::Wallet) trait and its handlers are generic, borrow their inputs
+//! and exchange typed objects, which bindings cannot express. The facade flattens them:
+//!
+//! - the callbacks implemented by the host app, [FfiHttpClient] and [FfiPresentationBuilder],
+//!   are object-safe `Send + Sync + 'static` traits;
+//! - inputs and outputs are owned strings, bytes and plain records, with JSON for structured
+//!   protocol objects such as Request Objects;
+//! - stateful objects, [FfiWallet] and [FfiPresentationRequest], are shared as `Arc`s;
+//! - every failure is an [FfiError].
+
+use std::fmt;
+
+use crate::core::response::error::AuthorizationErrorCode;
+
+pub mod http;
+pub mod wallet;
+
+pub use http::{FfiHeader, FfiHttpClient, FfiHttpRequest, FfiHttpResponse};
+pub use wallet::{
+    FfiCandidates, FfiPresentation, FfiPresentationBuilder, FfiPresentationRequest,
+    FfiSelectedCredential, FfiSelection, FfiWallet, FfiWalletConfig,
+};
+
+/// An error of the facade.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FfiError {
+    /// A request or response could not be processed. `code` is the OAuth error code that the
+    /// failure is reported to the verifier with, see [AuthorizationErrorCode::for_error].
+    Flow { code: String, message: String },
+    /// An argument passed by the host app is invalid, e.g. malformed JSON.
+    InvalidArgument { message: String },
+    /// A callback implemented by the host app failed.
+    Callback { message: String },
+}
+
+impl FfiError {
+    pub(crate) fn invalid_argument(error: impl fmt::Display) -> Self {
+        Self::InvalidArgument {
+            message: format!("{error:#}"),
+        }
+    }
+}
+
+impl From<anyhow::Error> for FfiError {
+    fn from(error: anyhow::Error) -> Self {
+        let message = format!("{error:#}");
+        // Failures of callbacks are returned as they are, with the context they were wrapped in.
+        match error.downcast_ref::<FfiError>() {
+            Some(Self::Callback { .. }) => Self::Callback { message },
+            Some(Self::InvalidArgument { .. }) => Self::InvalidArgument { message },
+            _ => Self::Flow {
+                code: AuthorizationErrorCode::for_error(&error).to_string(),
+                message,
+            },
+        }
+    }
+}
+
+impl fmt::Display for FfiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Flow { code, message } => write!(f, "{code}: {message}"),
+            Self::InvalidArgument { message } => write!(f, "invalid argument: {message}"),
+            Self::Callback { message } => write!(f, "callback failed: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for FfiError {}
+
+#[cfg(test)]
+mod test {
+    use anyhow::{anyhow, Context};
+
+    use crate::core::response::error::InvalidRequestObject;
+
+    use super::*;
+
+    #[test]
+    fn errors() {
+        let error = anyhow!(InvalidRequestObject("bad signature".into()));
+        assert_eq!(
+            FfiError::from(error),
+            FfiError::Flow {
+                code: "invalid_request_object".into(),
+                message: "bad signature".into()
+            }
+        );
+
+        let error = Err::<(), _>(FfiError::Callback {
+            message: "offline".into(),
+        })
+        .context("failed to retrieve the request")
+        .unwrap_err();
+        assert_eq!(
+            FfiError::from(error),
+            FfiError::Callback {
+                message: "failed to retrieve the request: callback failed: offline".into()
+            }
+        );
+    }
+}
@@ -0,0 +1,377 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::Value as Json;
+use tracing::warn;
+use url::Url;
+
+use crate::{
+    core::{
+        authorization_request::{
+            verification::unsigned::UnsignedRequestPolicy, AuthorizationRequestObject,
+        },
+        credential_format::ClaimFormatDesignation,
+        metadata::WalletMetadata,
+        object::UntypedObject,
+        presentation_submission::PresentationSubmission,
+        response::{
+            error::{AuthorizationErrorCode, AuthorizationErrorResponse},
+            parameters::{VpToken, VpTokenItem},
+            UnencodedAuthorizationResponse,
+        },
+    },
+    wallet::{
+        consent::SelectedCredential,
+        credential_store::{CandidateSets, StoredCredential},
+        presentation::{key_binding_claims, PresentationHandler},
+        simple::SimpleWallet,
+        HandledRequest, Wallet,
+    },
+};
+
+use super::{
+    http::{FfiHttpClient, HttpBridge},
+    FfiError,
+};
+
+/// The configuration of an [FfiWallet].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FfiWalletConfig {
+    /// The wallet metadata, as JSON.
+    pub metadata_json: String,
+    /// Only accept requests from these DIDs, all DIDs are trusted if `None`.
+    pub trusted_dids: Option<Vec<String>>,
+    /// Accept unsigned requests whose response is sent to one of these origins, e.g.
+    /// `https://verifier.example.com`. Unsigned requests are rejected if empty.
+    pub unsigned_request_origins: Vec<String>,
+    /// Report failures to the verifier with an Authorization Error Response, see
+    /// [Wallet::auto_submit_errors].
+    pub auto_submit_errors: bool,
+}
+
+/// A credential selected by the user, to present with the [FfiPresentationBuilder].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FfiSelectedCredential {
+    pub input_descriptor_id: String,
+    pub credential_id: String,
+    /// The format of the credential, e.g. `jwt_vc_json`.
+    pub format: String,
+    /// The credential as it was added to the wallet.
+    pub encoded: String,
+    /// The paths of the claims the user agreed to disclose, or `None` for all the requested
+    /// claims.
+    pub approved_claims: Option<Vec<String>>,
+    /// The claims that the Key Binding JWT of an SD-JWT presentation must carry, as a JSON
+    /// object, see [key_binding_claims].
+    pub key_binding_claims_json: String,
+}
+
+/// A presentation built by the [FfiPresentationBuilder].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FfiPresentation {
+    /// The presentation as it appears in the `vp_token`: a string, e.g. a JWT VP or an SD-JWT
+    /// with a Key Binding JWT, or a JSON object, e.g. a Data Integrity VP.
+    pub presentation: String,
+    /// The format of the presentation, e.g. `jwt_vp_json`.
+    pub format: String,
+}
+
+/// Builds the presentations of the host app, which holds the keys that credentials are bound to.
+#[async_trait]
+pub trait FfiPresentationBuilder: Send + Sync + 'static {
+    /// Present `credential` in response to the request, given as the JSON object of its
+    /// parameters. The `nonce` and `client_id` of the request must be bound into the
+    /// presentation.
+    async fn present(
+        &self,
+        request_json: String,
+        credential: FfiSelectedCredential,
+    ) -> Result<FfiPresentation, FfiError>;
+}
+
+/// The credentials that can satisfy an input descriptor of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FfiCandidates {
+    pub input_descriptor_id: String,
+    pub name: Option<String>,
+    pub purpose: Option<String>,
+    /// The ids of the matching credentials.
+    pub credential_ids: Vec<String>,
+}
+
+/// The credential chosen by the user for an input descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FfiSelection {
+    pub input_descriptor_id: String,
+    pub credential_id: String,
+    /// The paths of the claims the user agreed to disclose, e.g. `$.given_name`, or `None` for
+    /// all the requested claims.
+    pub approved_claims: Option<Vec<String>>,
+}
+
+/// A wallet holding JWT VC and SD-JWT VC credentials, see [SimpleWallet], whose HTTP requests
+/// and presentations are made by the host app.
+pub struct FfiWallet {
+    inner: SimpleWallet<HttpBridge>,
+    presentation_builder: Arc<dyn FfiPresentationBuilder>,
+}
+
+impl FfiWallet {
+    pub fn new(
+        config: FfiWalletConfig,
+        http_client: Arc<dyn FfiHttpClient>,
+        presentation_builder: Arc<dyn FfiPresentationBuilder>,
+    ) -> Result<Arc<Self>, FfiError> {
+        let metadata: WalletMetadata =
+            serde_json::from_str(&config.metadata_json).map_err(FfiError::invalid_argument)?;
+        let unsigned_request_policy = if config.unsigned_request_origins.is_empty() {
+            UnsignedRequestPolicy::Reject
+        } else {
+            UnsignedRequestPolicy::AllowFrom(config.unsigned_request_origins)
+        };
+        let mut inner = SimpleWallet::new(metadata, HttpBridge(http_client))
+            .with_unsigned_request_policy(unsigned_request_policy)
+            .with_auto_submit_errors(config.auto_submit_errors);
+        if let Some(trusted_dids) = config.trusted_dids {
+            inner = inner.with_trusted_dids(trusted_dids);
+        }
+        Ok(Arc::new(Self {
+            inner,
+            presentation_builder,
+        }))
+    }
+
+    /// Add a JWT VC, see [SimpleWallet::add_jwt_vc].
+    pub async fn add_jwt_vc(&self, id: String, jwt: String) -> Result<(), FfiError> {
+        self.inner
+            .add_jwt_vc(id, jwt)
+            .await
+            .map_err(FfiError::invalid_argument)
+    }
+
+    /// Add an SD-JWT VC, see [SimpleWallet::add_sd_jwt].
+    pub async fn add_sd_jwt(&self, id: String, sd_jwt: String) -> Result<(), FfiError> {
+        self.inner
+            .add_sd_jwt(id, sd_jwt)
+            .await
+            .map_err(FfiError::invalid_argument)
+    }
+
+    /// Remove a credential, returning whether it was present.
+    pub async fn remove_credential(&self, id: String) -> bool {
+        self.inner.remove(&id).await
+    }
+
+    /// Validate the request at `url` and find the credentials that match it, for the host app to
+    /// ask the user for consent.
+    pub async fn handle_request(
+        self: Arc<Self>,
+        url: String,
+    ) -> Result<Arc<FfiPresentationRequest>, FfiError> {
+        let url: Url = url.parse().map_err(FfiError::invalid_argument)?;
+        let request = self.inner.validate_request(url).await?;
+        let candidates = match self.inner.find_candidates(&request, &self.inner).await {
+            Ok(candidates) => candidates,
+            Err(e) => {
+                if self.inner.auto_submit_errors() {
+                    self.submit_error(&request, AuthorizationErrorResponse::for_error(&e))
+                        .await;
+                }
+                return Err(e.into());
+            }
+        };
+        Ok(Arc::new(FfiPresentationRequest {
+            wallet: self,
+            request,
+            candidates,
+        }))
+    }
+
+    /// Send an error response, logging failures as the original error is returned instead.
+    async fn submit_error(
+        &self,
+        request: &AuthorizationRequestObject,
+        response: AuthorizationErrorResponse,
+    ) {
+        if let Err(e) = self.inner.submit_error(request, response).await {
+            warn!("failed to submit error response: {e:#}")
+        }
+    }
+}
+
+/// A validated request, awaiting the user's decision.
+pub struct FfiPresentationRequest {
+    wallet: Arc<FfiWallet>,
+    request: AuthorizationRequestObject,
+    candidates: CandidateSets,
+}
+
+impl FfiPresentationRequest {
+    pub fn client_id(&self) -> String {
+        self.request.client_id().0.clone()
+    }
+
+    pub fn client_id_scheme(&self) -> String {
+        self.request.client_id_scheme().to_string()
+    }
+
+    /// The parameters of the request, as a JSON object.
+    pub fn request_json(&self) -> Result<String, FfiError> {
+        Ok(request_json(&self.request)?)
+    }
+
+    /// The matching credentials, by input descriptor.
+    pub fn candidates(&self) -> Vec<FfiCandidates> {
+        self.candidates
+            .candidates
+            .iter()
+            .map(|candidates| FfiCandidates {
+                input_descriptor_id: candidates.input_descriptor.id().to_owned(),
+                name: candidates.input_descriptor.name().cloned(),
+                purpose: candidates.input_descriptor.purpose().cloned(),
+                credential_ids: candidates
+                    .credentials
+                    .iter()
+                    .map(|credential| credential.id().to_owned())
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Whether every input descriptor has a matching credential, see
+    /// [CandidateSets::is_fully_satisfiable].
+    pub fn is_satisfiable(&self) -> bool {
+        self.candidates.is_fully_satisfiable()
+    }
+
+    /// Present the credentials selected by the user, and return the redirect of the verifier, if
+    /// any.
+    pub async fn approve(&self, selections: Vec<FfiSelection>) -> Result<Option<String>, FfiError> {
+        let selected = selections
+            .into_iter()
+            .map(|selection| {
+                let credential = self
+                    .candidates
+                    .get(&selection.input_descriptor_id)
+                    .and_then(|candidates| {
+                        candidates
+                            .credentials
+                            .iter()
+                            .find(|credential| credential.id() == selection.credential_id)
+                    })
+                    .ok_or_else(|| {
+                        FfiError::invalid_argument(format!(
+                            "credential '{}' is not a candidate for input descriptor '{}'",
+                            selection.credential_id, selection.input_descriptor_id
+                        ))
+                    })?;
+                let selected =
+                    SelectedCredential::new(selection.input_descriptor_id, credential.clone());
+                Ok(match selection.approved_claims {
+                    Some(approved_claims) => selected.with_approved_claims(approved_claims),
+                    None => selected,
+                })
+            })
+            .collect::<Result<Vec<_>, FfiError>>()?;
+        self.candidates
+            .validate_selection(
+                &selected
+                    .iter()
+                    .map(|s| (s.input_descriptor_id.as_str(), &s.credential))
+                    .collect::<Vec<(&str, &StoredCredential)>>(),
+            )
+            .map_err(FfiError::invalid_argument)?;
+
+        let handled = HandledRequest {
+            request: self.request.clone(),
+            presentation_definition: self.candidates.presentation_definition.clone(),
+            selected,
+        };
+        let handler = PresentationBridge {
+            wallet: &self.wallet,
+            definition_id: self.candidates.presentation_definition.id(),
+        };
+        let redirect = self.wallet.inner.respond(handled, &handler).await?;
+        Ok(redirect.map(String::from))
+    }
+
+    /// Report the user's refusal to the verifier, and return its redirect, if any.
+    pub async fn refuse(&self) -> Result<Option<String>, FfiError> {
+        let response = AuthorizationErrorResponse::new(AuthorizationErrorCode::AccessDenied)
+            .with_error_description("the user refused to share credentials");
+        let redirect = self
+            .wallet
+            .inner
+            .submit_error(&self.request, response)
+            .await?;
+        Ok(redirect.map(String::from))
+    }
+}
+
+/// A [PresentationHandler] calling the [FfiPresentationBuilder] of a wallet.
+struct PresentationBridge<'a> {
+    wallet: &'a FfiWallet,
+    /// The id of the presentation definition of the request.
+    definition_id: &'a str,
+}
+
+#[async_trait]
+impl PresentationHandler for PresentationBridge<'_> {
+    async fn to_response(
+        &self,
+        request: &AuthorizationRequestObject,
+        selected: &[SelectedCredential],
+    ) -> Result<UnencodedAuthorizationResponse> {
+        let request_json = request_json(request)?;
+        let key_binding_claims_json = serde_json::to_string(&key_binding_claims(request)?)?;
+        let mut presentations = Vec::new();
+        let mut formats = Vec::new();
+        for selected in selected {
+            let credential_id = selected.credential.id();
+            let encoded = self
+                .wallet
+                .inner
+                .encoded(credential_id)
+                .await
+                .with_context(|| format!("credential '{credential_id}' was removed"))?;
+            let FfiPresentation {
+                presentation,
+                format,
+            } = self
+                .wallet
+                .presentation_builder
+                .present(
+                    request_json.clone(),
+                    FfiSelectedCredential {
+                        input_descriptor_id: selected.input_descriptor_id.clone(),
+                        credential_id: credential_id.to_owned(),
+                        format: selected.credential.format().clone().into(),
+                        encoded,
+                        approved_claims: selected.approved_claims.clone(),
+                        key_binding_claims_json: key_binding_claims_json.clone(),
+                    },
+                )
+                .await?;
+            presentations.push(match serde_json::from_str(&presentation) {
+                Ok(Json::Object(object)) => VpTokenItem::JsonObject(object),
+                _ => VpTokenItem::String(presentation),
+            });
+            formats.push((
+                selected.input_descriptor_id.clone(),
+                ClaimFormatDesignation::from(format.as_str()),
+            ));
+        }
+
+        Ok(UnencodedAuthorizationResponse(
+            UntypedObject::default(),
+            VpToken(presentations),
+            PresentationSubmission::for_vp_token(self.definition_id.to_owned(), formats),
+        ))
+    }
+}
+
+fn request_json(request: &AuthorizationRequestObject) -> Result<String> {
+    serde_json::to_string(&UntypedObject::from(request.clone()))
+        .context("failed to serialize the request")
+}
@@ -1,7 +1,15 @@
+#[cfg(feature = "verifier")]
+pub mod conformance;
 pub mod core;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 #[cfg(test)]
 pub(crate) mod tests;
 mod utils;
+#[cfg(feature = "verifier")]
 pub mod verifier;
+#[cfg(feature = "wallet")]
 pub mod wallet;
 pub use jsonpath_lib;
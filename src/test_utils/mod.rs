@@ -0,0 +1,22 @@
+//! Test doubles for integration testing OID4VP code without network servers, behind the
+//! `test-utils` feature.
+//!
+//! A [MockWallet] presents canned credentials to a [Verifier](crate::verifier::Verifier) through
+//! a [MockVerifier], which serves the verifier's `request_uri` and response endpoints in process:
+//!
+//! ```ignore
+//! let wallet = MockWallet::new(verifier.clone()).with_credential("id-card", jwt_vc);
+//! let (url, session) = verifier.begin_session().await?;
+//! wallet.present(url).await?;
+//! assert!(matches!(verifier.poll_status(session).await?, Status::Complete(_)));
+//! ```
+//!
+//! A [MockVerifier] can also serve as the HTTP client of a wallet under test. Complete flows,
+//! with injected faults, are run with a [Simulation](simulation::Simulation).
+
+pub mod simulation;
+mod verifier;
+mod wallet;
+
+pub use verifier::MockVerifier;
+pub use wallet::{MockBehavior, MockWallet};
@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use base64::prelude::*;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    core::{
+        authorization_request::{
+            parameters::Nonce, AuthorizationRequest, AuthorizationRequestObject, RequestIndirection,
+        },
+        object::UntypedObject,
+        response::AuthorizationResponse,
+    },
+    verifier::{
+        session::{Outcome, Status},
+        Verifier,
+    },
+    wallet::Wallet,
+};
+
+use super::MockWallet;
+
+/// Builds the response of a wallet to a validated request, for a [Simulation].
+#[async_trait]
+pub trait Responder: Send + Sync {
+    async fn build_response(
+        &self,
+        request: &AuthorizationRequestObject,
+    ) -> Result<AuthorizationResponse>;
+}
+
+#[async_trait]
+impl Responder for MockWallet {
+    async fn build_response(
+        &self,
+        request: &AuthorizationRequestObject,
+    ) -> Result<AuthorizationResponse> {
+        self.response(request).await
+    }
+}
+
+/// A fault injected into a simulated flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The signature of the Request Object is corrupted before the wallet receives it. A request
+    /// passed by reference is retrieved by the harness, and passed to the wallet by value.
+    BadSignature,
+    /// The wallet binds its presentations to another nonce than the request's.
+    WrongNonce,
+    /// The session is backdated past the request URI and session TTLs of the verifier before the
+    /// wallet receives the request.
+    ExpiredRequest,
+}
+
+/// What happened in a simulated flow.
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    pub session: Uuid,
+    /// Why the wallet rejected the request, if it did.
+    pub request_error: Option<String>,
+    /// Why the wallet failed to build or submit its response, if it did, e.g. because the
+    /// verifier rejected it.
+    pub response_error: Option<String>,
+    /// The status of the session at the end of the flow.
+    pub status: Status,
+}
+
+impl SimulationReport {
+    /// Whether the verifier verified the response successfully.
+    pub fn succeeded(&self) -> bool {
+        matches!(self.status, Status::Complete(Outcome::Success { .. }))
+    }
+
+    /// Whether the verifier received a response, and rejected it.
+    pub fn response_rejected(&self) -> bool {
+        matches!(
+            self.status,
+            Status::Complete(Outcome::Failure { .. } | Outcome::Error { .. })
+        )
+    }
+}
+
+/// Runs full presentation flows in process: the verifier creates a request, the wallet fetches and
+/// validates it, builds and submits its response, and the verifier validates the response.
+///
+/// The wallet must reach the verifier's endpoints through its HTTP client, e.g. a
+/// [MockVerifier](super::MockVerifier). [Fault]s can be injected to check how either side
+/// handles them:
+///
+/// ```ignore
+/// let simulation = Simulation::new(verifier, wallet);
+/// assert!(simulation.run(None).await?.succeeded());
+/// assert!(simulation.run(Some(Fault::WrongNonce)).await?.response_rejected());
+/// ```
+pub struct Simulation<W> {
+    verifier: Arc<Verifier>,
+    wallet: W,
+}
+
+impl<W: Wallet + Responder + Send + Sync> Simulation<W> {
+    pub fn new(verifier: Arc<Verifier>, wallet: W) -> Self {
+        Self { verifier, wallet }
+    }
+
+    pub fn verifier(&self) -> &Arc<Verifier> {
+        &self.verifier
+    }
+
+    pub fn wallet(&self) -> &W {
+        &self.wallet
+    }
+
+    /// Run a flow for a new session, see [Verifier::begin_session].
+    pub async fn run(&self, fault: Option<Fault>) -> Result<SimulationReport> {
+        let (url, session) = self
+            .verifier
+            .begin_session()
+            .await
+            .context("failed to begin the session")?;
+        self.run_session(url, session, fault).await
+    }
+
+    /// Run a flow for a session begun by the caller, e.g. with a custom request.
+    ///
+    /// Fails if the fault cannot be injected, or if the status of the session cannot be read.
+    pub async fn run_session(
+        &self,
+        url: Url,
+        session: Uuid,
+        fault: Option<Fault>,
+    ) -> Result<SimulationReport> {
+        let url = match fault {
+            Some(Fault::BadSignature) => self.corrupt_signature(url).await?,
+            Some(Fault::ExpiredRequest) => {
+                self.verifier.expire_session(session).await?;
+                url
+            }
+            _ => url,
+        };
+
+        let mut report = SimulationReport {
+            session,
+            request_error: None,
+            response_error: None,
+            status: Status::SentRequest,
+        };
+
+        match self.wallet.validate_request(url).await {
+            Err(e) => report.request_error = Some(format!("{e:#}")),
+            Ok(request) => {
+                let result = async {
+                    let response = match fault {
+                        Some(Fault::WrongNonce) => {
+                            Responder::build_response(&self.wallet, &with_wrong_nonce(&request)?)
+                                .await?
+                        }
+                        _ => Responder::build_response(&self.wallet, &request).await?,
+                    };
+                    self.wallet.submit_response(request, response).await
+                }
+                .await;
+                if let Err(e) = result {
+                    report.response_error = Some(format!("{e:#}"))
+                }
+            }
+        }
+
+        report.status = self.verifier.poll_status(session).await?;
+        Ok(report)
+    }
+
+    /// The request at `url`, passed by value with a corrupted signature.
+    async fn corrupt_signature(&self, url: Url) -> Result<Url> {
+        let request = AuthorizationRequest::from_url_with_endpoints(
+            url,
+            self.wallet.metadata().invocation_endpoints(),
+        )?;
+        let jwt = match request.request_indirection {
+            RequestIndirection::ByValue(jwt) => jwt,
+            RequestIndirection::ByReference(request_uri) => {
+                let token = request_uri
+                    .path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .context("the request_uri has no token")?;
+                self.verifier.retrieve_authorization_request(token).await?
+            }
+            RequestIndirection::Unsigned(_) => {
+                bail!("unsigned requests have no signature to corrupt")
+            }
+        };
+        let (signing_input, signature) = jwt
+            .rsplit_once('.')
+            .context("the request object is not a compact JWS")?;
+        let mut signature = BASE64_URL_SAFE_NO_PAD
+            .decode(signature)
+            .context("the signature of the request object is not base64url encoded")?;
+        let Some(byte) = signature.first_mut() else {
+            bail!("the request object is not signed")
+        };
+        *byte ^= 0xff;
+        AuthorizationRequest {
+            client_id: request.client_id,
+            request_indirection: RequestIndirection::ByValue(format!(
+                "{signing_input}.{}",
+                BASE64_URL_SAFE_NO_PAD.encode(signature)
+            )),
+        }
+        .to_url(self.wallet.metadata().authorization_endpoint().0.clone())
+    }
+}
+
+/// The request, with another nonce.
+fn with_wrong_nonce(request: &AuthorizationRequestObject) -> Result<AuthorizationRequestObject> {
+    let mut parameters: UntypedObject = request.clone().into();
+    parameters.insert(Nonce::from(format!("{}-wrong", request.nonce().as_str())));
+    parameters.try_into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wrong_nonce() {
+        let request: AuthorizationRequestObject = serde_json::from_value(serde_json::json!({
+            "client_id": "https://example.com/submission/1",
+            "client_id_scheme": "redirect_uri",
+            "response_type": "vp_token",
+            "response_mode": "direct_post",
+            "response_uri": "https://example.com/submission/1",
+            "nonce": "n",
+            "presentation_definition": { "id": "pd", "input_descriptors": [] }
+        }))
+        .unwrap();
+        let wrong = with_wrong_nonce(&request).unwrap();
+        assert_eq!(wrong.nonce().as_str(), "n-wrong");
+        assert_eq!(wrong.client_id().0, request.client_id().0);
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use http::{header::CONTENT_TYPE, Request, Response, StatusCode};
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    core::{
+        consts::media_type, response::error::AuthorizationErrorResponse, util::AsyncHttpClient,
+    },
+    verifier::{endpoint_error::EndpointError, Verifier},
+};
+
+/// Serves the `request_uri` and response endpoints of a [Verifier] in process, as an
+/// [AsyncHttpClient] for a wallet, so that a full presentation flow runs without an HTTP server.
+///
+/// Requests are routed by URL: those under the `request_uri` base URL of the verifier (see
+/// [VerifierBuilder::by_reference](crate::verifier::VerifierBuilder::by_reference)) retrieve a
+/// request, and those under its submission endpoint submit a response. The verifier's errors are
+/// returned as `400 Bad Request`, as a server would.
+///
+/// Authorization Error Responses, which the verifier does not process, are recorded, see
+/// [error_responses](Self::error_responses).
+#[derive(Debug, Clone)]
+pub struct MockVerifier {
+    verifier: Arc<Verifier>,
+    error_responses: Arc<Mutex<Vec<(String, AuthorizationErrorResponse)>>>,
+}
+
+impl MockVerifier {
+    pub fn new(verifier: Arc<Verifier>) -> Self {
+        Self {
+            verifier,
+            error_responses: Arc::default(),
+        }
+    }
+
+    pub fn verifier(&self) -> &Arc<Verifier> {
+        &self.verifier
+    }
+
+    /// The Authorization Error Responses received, with the session they were sent to: the UUID
+    /// of the session, or the token of a stateless session.
+    pub fn error_responses(&self) -> Vec<(String, AuthorizationErrorResponse)> {
+        self.error_responses.lock().unwrap().clone()
+    }
+
+    /// Handle a request to one of the verifier's endpoints, returning the response body.
+    async fn route(&self, url: &Url, body: &[u8]) -> Result<(&'static str, Vec<u8>)> {
+        if let Some(base) = self.verifier.request_uri_base() {
+            if let Some(token) = child_segment(base, url) {
+                let jwt = self.verifier.retrieve_authorization_request(token).await?;
+                return Ok((media_type::REQUEST_OBJECT, jwt.into_bytes()));
+            }
+        }
+
+        let id = child_segment(self.verifier.submission_endpoint(), url)
+            .with_context(|| format!("no endpoint of the verifier at {url}"))?;
+
+        if let Ok(error) = serde_urlencoded::from_bytes::<AuthorizationErrorResponse>(body) {
+            self.error_responses
+                .lock()
+                .unwrap()
+                .push((id.to_owned(), error));
+            return Ok((media_type::JSON, vec![]));
+        }
+
+        // Stateless sessions are identified by a sealed token instead of a UUID.
+        let Ok(id) = id.parse::<Uuid>() else {
+            self.verifier.receive_stateless_response(id, body).await?;
+            return Ok((media_type::JSON, vec![]));
+        };
+        self.verifier.receive_response(id, body).await?;
+        let body = match self.verifier.post_redirection(id).await? {
+            Some(redirection) => serde_json::to_vec(&redirection)?,
+            None => vec![],
+        };
+        Ok((media_type::JSON, body))
+    }
+}
+
+/// The last path segment of `url`, if `url` is a direct child of `base`.
+fn child_segment<'a>(base: &Url, url: &'a Url) -> Option<&'a str> {
+    if (url.scheme(), url.host_str(), url.port_or_known_default())
+        != (base.scheme(), base.host_str(), base.port_or_known_default())
+    {
+        return None;
+    }
+    let (parent, segment) = url.path().rsplit_once('/')?;
+    (parent == base.path().trim_end_matches('/') && !segment.is_empty()).then_some(segment)
+}
+
+#[async_trait]
+impl AsyncHttpClient for MockVerifier {
+    async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+        let url: Url = request
+            .uri()
+            .to_string()
+            .parse()
+            .context("invalid request URL")?;
+        let response = match self.route(&url, request.body()).await {
+            Ok((content_type, body)) => Response::builder()
+                .status(StatusCode::OK)
+                .header(CONTENT_TYPE, content_type)
+                .body(body),
+            Err(e) => return EndpointError::for_error(&e).into_response(),
+        };
+        response.context("failed to build response")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn child_segments() {
+        let base: Url = "https://example.com/submission".parse().unwrap();
+        let url = |s: &str| -> Url { s.parse().unwrap() };
+        assert_eq!(
+            child_segment(&base, &url("https://example.com/submission/abc")),
+            Some("abc")
+        );
+        assert_eq!(
+            child_segment(
+                &"https://example.com/submission/".parse().unwrap(),
+                &url("https://example.com/submission/abc")
+            ),
+            Some("abc")
+        );
+        assert_eq!(
+            child_segment(&base, &url("https://example.com/request/abc")),
+            None
+        );
+        assert_eq!(
+            child_segment(&base, &url("https://example.org/submission/abc")),
+            None
+        );
+        assert_eq!(
+            child_segment(&base, &url("https://example.com/submission/a/b")),
+            None
+        );
+    }
+}
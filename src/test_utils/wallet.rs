@@ -0,0 +1,400 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use base64::prelude::*;
+use serde_json::{json, Map, Value as Json};
+use url::Url;
+
+use crate::{
+    core::{
+        authorization_request::{
+            parameters::Scope,
+            verification::{unsigned::UnsignedRequestPolicy, RequestVerifier},
+            AuthorizationRequestObject,
+        },
+        credential_format::ClaimFormatDesignation,
+        dcql_query::DcqlVpToken,
+        metadata::WalletMetadata,
+        object::{registry::ParameterRegistry, UnknownParameters, UntypedObject},
+        presentation_definition::PresentationDefinition,
+        presentation_submission::PresentationSubmission,
+        response::{
+            error::{AuthorizationErrorCode, AuthorizationErrorResponse},
+            parameters::{VpToken, VpTokenItem},
+            AuthorizationResponse, DcqlAuthorizationResponse, UnencodedAuthorizationResponse,
+        },
+    },
+    verifier::Verifier,
+    wallet::Wallet,
+};
+
+use super::MockVerifier;
+
+/// How a [MockWallet] responds to requests.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MockBehavior {
+    /// Present the configured credentials.
+    #[default]
+    Present,
+    /// Refuse to share credentials, as a user would, with an `access_denied` error response.
+    Decline,
+    /// Present the configured credentials, bound to another nonce than the request's.
+    TamperNonce,
+}
+
+/// A [Wallet] for testing verifiers, which presents canned credentials to the verifier it is
+/// connected to through a [MockVerifier].
+///
+/// Credentials are configured by the input descriptor (or DCQL credential query) they answer,
+/// and presented in JWT VPs (`jwt_vp_json`) that are not signed (`alg` `none`), bound to the nonce
+/// of the request. The validator of the verifier under test must accept such presentations, e.g.
+/// by only checking the claims it expects.
+///
+/// Request Objects are decoded but their signatures are not verified, and unsigned requests are
+/// accepted, so the mock can be used with any `client_id_scheme`.
+pub struct MockWallet {
+    metadata: WalletMetadata,
+    http_client: MockVerifier,
+    behavior: MockBehavior,
+    holder: String,
+    credentials: BTreeMap<String, String>,
+    request_object_decryption_key: Option<Map<String, Json>>,
+    unknown_parameters: UnknownParameters,
+    parameter_registry: Option<ParameterRegistry>,
+}
+
+impl MockWallet {
+    /// A wallet with the [wallet metadata](Verifier::wallet_metadata) of `verifier`, submitting
+    /// its responses to `verifier`.
+    pub fn new(verifier: Arc<Verifier>) -> Self {
+        Self {
+            metadata: verifier.wallet_metadata().clone(),
+            http_client: MockVerifier::new(verifier),
+            behavior: MockBehavior::default(),
+            holder: "did:example:holder".to_owned(),
+            credentials: BTreeMap::new(),
+            request_object_decryption_key: None,
+            unknown_parameters: UnknownParameters::default(),
+            parameter_registry: None,
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: WalletMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn with_behavior(mut self, behavior: MockBehavior) -> Self {
+        self.behavior = behavior;
+        self
+    }
+
+    /// The `iss` of the presentations, `did:example:holder` by default.
+    pub fn with_holder(mut self, holder: impl Into<String>) -> Self {
+        self.holder = holder.into();
+        self
+    }
+
+    /// Present `credential` (e.g. a JWT VC) for the input descriptor, or DCQL credential query,
+    /// `id`.
+    pub fn with_credential(mut self, id: impl Into<String>, credential: impl Into<String>) -> Self {
+        self.credentials.insert(id.into(), credential.into());
+        self
+    }
+
+    /// The private JWK that Request Objects are encrypted to, see
+    /// [Wallet::request_object_decryption_key].
+    pub fn with_request_object_decryption_key(mut self, jwk: Map<String, Json>) -> Self {
+        self.request_object_decryption_key = Some(jwk);
+        self
+    }
+
+    /// How unknown request parameters are handled, e.g. to check that the verifier under test
+    /// does not send typos. Ignored by default.
+    pub fn with_unknown_parameters(mut self, unknown_parameters: UnknownParameters) -> Self {
+        self.unknown_parameters = unknown_parameters;
+        self
+    }
+
+    /// The parameters of the profile under test, see [Wallet::parameter_registry].
+    pub fn with_parameter_registry(mut self, registry: ParameterRegistry) -> Self {
+        self.parameter_registry = Some(registry);
+        self
+    }
+
+    /// The verifier endpoints the wallet is connected to.
+    pub fn mock_verifier(&self) -> &MockVerifier {
+        &self.http_client
+    }
+
+    /// Validate the request at `url` and respond to it according to the [MockBehavior].
+    ///
+    /// Returns the redirect returned by the verifier, if any, or an error if the request was
+    /// declined.
+    pub async fn present(&self, url: Url) -> Result<Option<Url>> {
+        let request = self.validate_request(url).await?;
+        if self.behavior == MockBehavior::Decline {
+            let response = AuthorizationErrorResponse::new(AuthorizationErrorCode::AccessDenied)
+                .with_error_description("the user declined to share credentials");
+            self.submit_error(&request, response).await?;
+            bail!("the request was declined")
+        }
+        let response = self.response(&request).await?;
+        self.submit_response(request, response).await
+    }
+
+    /// Build the response to a request with the configured credentials.
+    pub async fn response(
+        &self,
+        request: &AuthorizationRequestObject,
+    ) -> Result<AuthorizationResponse> {
+        let nonce = match self.behavior {
+            MockBehavior::TamperNonce => format!("{}-tampered", request.nonce().as_str()),
+            _ => request.nonce().as_str().to_owned(),
+        };
+        let present = |credential: &str| -> VpTokenItem {
+            self.presentation(&request.client_id().0, &nonce, credential)
+                .into()
+        };
+
+        if let Some(dcql_query) = request.dcql_query() {
+            let vp_token = dcql_query
+                .credentials()
+                .iter()
+                .filter_map(|query| {
+                    let credential = self.credentials.get(&query.id)?;
+                    Some((query.id.clone(), vec![present(credential)]))
+                })
+                .collect::<BTreeMap<_, _>>();
+            if vp_token.is_empty() {
+                bail!("no credentials for the DCQL query")
+            }
+            return Ok(AuthorizationResponse::Dcql(DcqlAuthorizationResponse(
+                UntypedObject::default(),
+                DcqlVpToken(vp_token),
+            )));
+        }
+
+        let presentation_definition = match request.presentation_definition_scope() {
+            Some(scope) => self.resolve_scope(scope).await?,
+            None => request
+                .resolve_presentation_definition(self.http_client())
+                .await?
+                .into_parsed(),
+        };
+        let presented: Vec<_> = presentation_definition
+            .input_descriptors()
+            .iter()
+            .filter_map(|input_descriptor| {
+                let credential = self.credentials.get(input_descriptor.id())?;
+                Some((input_descriptor.id().to_owned(), credential))
+            })
+            .collect();
+        if presented.is_empty() {
+            bail!("no credentials for the presentation definition")
+        }
+        Ok(AuthorizationResponse::Unencoded(
+            UnencodedAuthorizationResponse(
+                UntypedObject::default(),
+                VpToken(
+                    presented
+                        .iter()
+                        .map(|(_, credential)| present(credential))
+                        .collect(),
+                ),
+                PresentationSubmission::for_vp_token(
+                    presentation_definition.id().to_owned(),
+                    presented
+                        .iter()
+                        .map(|(id, _)| (id.clone(), ClaimFormatDesignation::JwtVpJson)),
+                ),
+            ),
+        ))
+    }
+
+    /// An unsigned JWT VP of `credential`, bound to `audience` and `nonce`.
+    fn presentation(&self, audience: &str, nonce: &str, credential: &str) -> String {
+        let claims = json!({
+            "iss": self.holder,
+            "aud": audience,
+            "nonce": nonce,
+            "vp": {
+                "@context": ["https://www.w3.org/2018/credentials/v1"],
+                "type": ["VerifiablePresentation"],
+                "holder": self.holder,
+                "verifiableCredential": [credential],
+            },
+        });
+        format!(
+            "{}.{}.",
+            BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#),
+            BASE64_URL_SAFE_NO_PAD.encode(claims.to_string())
+        )
+    }
+}
+
+#[async_trait]
+impl Wallet for MockWallet {
+    type HttpClient = MockVerifier;
+
+    fn metadata(&self) -> &WalletMetadata {
+        &self.metadata
+    }
+
+    fn http_client(&self) -> &Self::HttpClient {
+        &self.http_client
+    }
+
+    fn request_object_decryption_key(&self) -> Option<&Map<String, Json>> {
+        self.request_object_decryption_key.as_ref()
+    }
+
+    fn unknown_parameters(&self) -> UnknownParameters {
+        self.unknown_parameters
+    }
+
+    fn parameter_registry(&self) -> Option<&ParameterRegistry> {
+        self.parameter_registry.as_ref()
+    }
+
+    fn unsigned_request_policy(&self) -> UnsignedRequestPolicy {
+        UnsignedRequestPolicy::Allow
+    }
+
+    async fn resolve_scope(&self, scope: &Scope) -> Result<PresentationDefinition> {
+        self.http_client.verifier().scopes().resolve(scope).cloned()
+    }
+}
+
+#[async_trait]
+impl RequestVerifier for MockWallet {
+    async fn did(&self, _: &AuthorizationRequestObject, _: String) -> Result<()> {
+        Ok(())
+    }
+
+    async fn entity_id(&self, _: &AuthorizationRequestObject, _: String) -> Result<()> {
+        Ok(())
+    }
+
+    async fn preregistered(&self, _: &AuthorizationRequestObject, _: String) -> Result<()> {
+        Ok(())
+    }
+
+    async fn redirect_uri(&self, _: &AuthorizationRequestObject, _: String) -> Result<()> {
+        Ok(())
+    }
+
+    async fn verifier_attestation(&self, _: &AuthorizationRequestObject, _: String) -> Result<()> {
+        Ok(())
+    }
+
+    async fn x509_san_dns(&self, _: &AuthorizationRequestObject, _: String) -> Result<()> {
+        Ok(())
+    }
+
+    async fn x509_san_uri(&self, _: &AuthorizationRequestObject, _: String) -> Result<()> {
+        Ok(())
+    }
+
+    async fn other(&self, _: &str, _: &AuthorizationRequestObject, _: String) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::{
+        core::{
+            authorization_request::parameters::{ClientMetadata, ResponseMode, ResponseType},
+            input_descriptor::{Constraints, ConstraintsField, InputDescriptor},
+        },
+        verifier::{
+            client::RedirectUriClient,
+            report::VerificationReport,
+            session::{MemoryStore, Outcome, Session, Status},
+            validator::ResponseValidator,
+        },
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct AcceptAll;
+
+    #[async_trait]
+    impl ResponseValidator for AcceptAll {
+        async fn validate(&self, _: Session, _: AuthorizationResponse) -> Outcome {
+            VerificationReport::new().into_verified_outcome(Default::default())
+        }
+    }
+
+    async fn verifier() -> Arc<Verifier> {
+        let submission_endpoint: Url = "https://example.com/submission".parse().unwrap();
+        let metadata: WalletMetadata = serde_json::from_value(json!({
+            "authorization_endpoint": "openid4vp:",
+            "client_id_schemes_supported": ["redirect_uri"],
+            "response_types_supported": ["vp_token"],
+            "vp_formats_supported": { "jwt_vp_json": {} }
+        }))
+        .unwrap();
+        Arc::new(
+            Verifier::builder()
+                .with_client(Arc::new(RedirectUriClient::new(
+                    submission_endpoint.clone(),
+                )))
+                .with_submission_endpoint(submission_endpoint)
+                .with_session_store(Arc::new(MemoryStore::default()))
+                .with_wallet_metadata(metadata)
+                .with_presentation_definition(PresentationDefinition::new(
+                    "pd".into(),
+                    InputDescriptor::new(
+                        "id-card".into(),
+                        Constraints::new()
+                            .add_constraint(ConstraintsField::new("$.credentialSubject.id".into())),
+                    ),
+                ))
+                .with_default_request_parameter(ResponseMode::DirectPost)
+                .with_default_request_parameter(ResponseType::VpToken)
+                .with_default_request_parameter(ClientMetadata(UntypedObject::default()))
+                .with_response_validator(Arc::new(AcceptAll))
+                .build()
+                .await
+                .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn canned_behaviors() {
+        let verifier = verifier().await;
+        let wallet = MockWallet::new(verifier.clone()).with_credential("id-card", "a.b.c");
+
+        let (url, session) = verifier.begin_session().await.unwrap();
+        wallet.present(url).await.unwrap();
+        assert!(matches!(
+            verifier.poll_status(session).await.unwrap(),
+            Status::Complete(Outcome::Success { .. })
+        ));
+
+        let wallet = wallet.with_behavior(MockBehavior::TamperNonce);
+        let (url, session) = verifier.begin_session().await.unwrap();
+        assert!(wallet.present(url).await.is_err());
+        assert!(matches!(
+            verifier.poll_status(session).await.unwrap(),
+            Status::Complete(Outcome::Failure { .. })
+        ));
+
+        let wallet = wallet.with_behavior(MockBehavior::Decline);
+        let (url, session) = verifier.begin_session().await.unwrap();
+        assert!(wallet.present(url).await.is_err());
+        let error_responses = wallet.mock_verifier().error_responses();
+        assert_eq!(error_responses.len(), 1);
+        assert_eq!(error_responses[0].0, session.to_string());
+        assert_eq!(
+            error_responses[0].1.error,
+            AuthorizationErrorCode::AccessDenied
+        );
+    }
+}
@@ -185,11 +185,10 @@ fn test_input_descriptor_validation() -> Result<()> {
 
     let presentation_definition: PresentationDefinition = value
         .as_object_mut()
-        .map(|obj| {
+        .and_then(|obj| {
             obj.remove("presentation_definition")
                 .map(serde_json::from_value)
         })
-        .flatten()
         .expect("failed to parse presentation definition")?;
 
     let presentation_submission = include_str!(
@@ -200,11 +199,10 @@ fn test_input_descriptor_validation() -> Result<()> {
 
     let presentation_submission: PresentationSubmission = value
         .as_object()
-        .map(|obj| {
+        .and_then(|obj| {
             obj.get("presentation_submission")
                 .map(|v| serde_json::from_value(v.clone()))
         })
-        .flatten()
         .expect("failed to parse presentation submission")?;
 
     let descriptor_map = presentation_submission.descriptor_map();
@@ -213,7 +211,7 @@ fn test_input_descriptor_validation() -> Result<()> {
 
     // Expect the example to fail here because the submission does match the definition.
     assert!(presentation_definition
-        .validate_presentation(verifiable_presentation, &descriptor_map)
+        .validate_presentation(verifiable_presentation, descriptor_map)
         .is_err());
 
     Ok(())
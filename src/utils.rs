@@ -54,3 +54,37 @@ impl<T: Clone> Deref for NonEmptyVec<T> {
         &self.0
     }
 }
+
+/// A single value, or an array of values, e.g. the presentations of a `vp_token`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub(crate) enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    pub fn into_vec(self) -> Vec<T> {
+        match self {
+            Self::One(t) => vec![t],
+            Self::Many(v) => v,
+        }
+    }
+}
+
+/// Serializes a slice as its only value if it has exactly one, as an array otherwise.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(untagged)]
+pub(crate) enum OneOrManyRef<'a, T> {
+    One(&'a T),
+    Many(&'a [T]),
+}
+
+impl<'a, T> OneOrManyRef<'a, T> {
+    pub fn from_slice(s: &'a [T]) -> Self {
+        match s {
+            [t] => Self::One(t),
+            _ => Self::Many(s),
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use super::{
+    minimization::RequestedClaims,
+    outcome::VerifiedPresentationOutcome,
+    session::{Outcome, Session},
+};
+
+/// The record of a completed session, as kept by a [PresentationArchive] for auditing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresentationRecord {
+    /// The UUID of the session.
+    pub session: Uuid,
+    /// The [Tenant](super::tenant::Tenant) that the session was created for, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// The `client_id` the request was made as.
+    pub client_id: String,
+    /// The nonce of the request.
+    pub nonce: String,
+    /// When the session was created.
+    pub requested_at: SystemTime,
+    /// When the response was verified, or rejected.
+    pub completed_at: SystemTime,
+    /// When the record may be deleted, if it has a retention period.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retain_until: Option<SystemTime>,
+    /// When the claims presented for each input descriptor must be deleted, as set by a
+    /// [RetentionPolicy], see [expire_claims](Self::expire_claims).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub claims_retain_until: BTreeMap<String, SystemTime>,
+    /// The outcome of the session. Failures carry the findings (including the decisions of the
+    /// [TrustPolicy](super::policy::TrustPolicy)) that rejected the response.
+    pub outcome: Outcome,
+    /// The verified credentials, their disclosed claims and the warnings of a successful outcome,
+    /// if the validator produced a [VerifiedPresentationOutcome].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified: Option<VerifiedPresentationOutcome>,
+}
+
+impl PresentationRecord {
+    /// The record of `session` completing with `outcome` at `now`, to be kept for `retention`.
+    pub fn new(
+        session: &Session,
+        outcome: &Outcome,
+        now: SystemTime,
+        retention: Option<Duration>,
+    ) -> Self {
+        Self {
+            session: session.uuid,
+            tenant: session.tenant.clone(),
+            client_id: session.authorization_request_object.client_id().0.clone(),
+            nonce: session.authorization_request_object.nonce().to_string(),
+            requested_at: session.created_at,
+            completed_at: now,
+            retain_until: retention.map(|retention| now + retention),
+            claims_retain_until: BTreeMap::new(),
+            outcome: outcome.clone(),
+            verified: match outcome {
+                Outcome::Success { .. } => outcome.clone().try_into().ok(),
+                _ => None,
+            },
+        }
+    }
+
+    /// Whether the retention period of the record is over at `now`.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.retain_until.is_some_and(|until| until <= now)
+    }
+
+    /// Drop the claims that were not requested from the verified outcome, see
+    /// [VerifiedPresentationOutcome::minimize].
+    pub fn minimize(&mut self, requested: &RequestedClaims) {
+        if let Some(verified) = &mut self.verified {
+            verified.minimize(requested);
+            self.sync_outcome();
+        }
+    }
+
+    /// Drop the credentials and claims of the input descriptors whose retention deadline is over
+    /// at `now`, returning whether any were dropped. The rest of the record is kept.
+    pub fn expire_claims(&mut self, now: SystemTime) -> bool {
+        let expired: Vec<String> = self
+            .claims_retain_until
+            .iter()
+            .filter(|(_, until)| **until <= now)
+            .map(|(input_descriptor_id, _)| input_descriptor_id.clone())
+            .collect();
+        if expired.is_empty() {
+            return false;
+        }
+        for input_descriptor_id in &expired {
+            self.claims_retain_until.remove(input_descriptor_id);
+            if let Some(verified) = &mut self.verified {
+                verified.remove_descriptor(input_descriptor_id);
+            }
+        }
+        self.sync_outcome();
+        true
+    }
+
+    /// Keep the outcome consistent with the verified outcome, which it embeds.
+    fn sync_outcome(&mut self) {
+        if let (Outcome::Success { info }, Some(verified)) = (&mut self.outcome, &self.verified) {
+            if let Ok(verified) = serde_json::to_value(verified) {
+                *info = verified;
+            }
+        }
+    }
+}
+
+/// How long the claims of archived records are kept, by input descriptor, configured with
+/// [VerifierBuilder::with_retention_policy](super::VerifierBuilder::with_retention_policy).
+///
+/// Claims that the verifier declared it intends to retain (an input descriptor with an
+/// `intent_to_retain` constraint field) are kept for `retained`, or as long as the record if
+/// `None`, and other claims are kept for `transient`. DCQL queries do not declare an intent to
+/// retain, so all of their claims are transient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub retained: Option<Duration>,
+    pub transient: Duration,
+}
+
+impl RetentionPolicy {
+    /// Keep the claims not intended to be retained for `transient`, and the others as long as
+    /// the record.
+    pub fn new(transient: Duration) -> Self {
+        Self {
+            retained: None,
+            transient,
+        }
+    }
+
+    pub fn with_retained(mut self, retained: Duration) -> Self {
+        self.retained = Some(retained);
+        self
+    }
+
+    /// The retention deadlines of the claims presented in a session completed at `now`, by input
+    /// descriptor, for [PresentationRecord::claims_retain_until].
+    pub fn deadlines(&self, session: &Session, now: SystemTime) -> BTreeMap<String, SystemTime> {
+        let descriptors: Vec<(String, bool)> =
+            match (&session.presentation_definition, &session.dcql_query) {
+                (Some(presentation_definition), _) => presentation_definition
+                    .input_descriptors()
+                    .iter()
+                    .map(|input_descriptor| {
+                        let retained = input_descriptor
+                            .constraints()
+                            .fields()
+                            .iter()
+                            .any(|field| field.intent_to_retain());
+                        (input_descriptor.id().to_owned(), retained)
+                    })
+                    .collect(),
+                (None, Some(dcql_query)) => dcql_query
+                    .credentials()
+                    .iter()
+                    .map(|credential| (credential.id.clone(), false))
+                    .collect(),
+                (None, None) => Vec::new(),
+            };
+        descriptors
+            .into_iter()
+            .filter_map(|(input_descriptor_id, retained)| {
+                let retention = if retained {
+                    self.retained?
+                } else {
+                    self.transient
+                };
+                Some((input_descriptor_id, now + retention))
+            })
+            .collect()
+    }
+}
+
+/// Storage for the [PresentationRecord]s of completed sessions, configured with
+/// [VerifierBuilder::with_presentation_archive](super::VerifierBuilder::with_presentation_archive).
+///
+/// Unlike sessions, records are kept once the session has been removed, for as long as audit
+/// requirements dictate.
+#[async_trait]
+pub trait PresentationArchive: Debug + Send + Sync {
+    /// Store the record of a completed session.
+    async fn archive(&self, record: PresentationRecord) -> Result<()>;
+
+    /// Get the record of a session.
+    async fn get_record(&self, session: Uuid) -> Result<PresentationRecord>;
+
+    /// Remove every record whose retention period is over at `now`, returning how many were
+    /// removed, and the claims of the remaining records whose retention period is over (see
+    /// [PresentationRecord::expire_claims]).
+    async fn purge(&self, now: SystemTime) -> Result<usize> {
+        let _ = now;
+        bail!("this archive does not support purging records")
+    }
+}
+
+/// A local in-memory archive. Not for production use!
+///
+/// # Warning
+/// This in-memory archive should only be used for test purposes, records are lost when the
+/// process exits.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryArchive {
+    records: Arc<Mutex<BTreeMap<Uuid, PresentationRecord>>>,
+}
+
+#[async_trait]
+impl PresentationArchive for MemoryArchive {
+    async fn archive(&self, record: PresentationRecord) -> Result<()> {
+        self.records.try_lock()?.insert(record.session, record);
+        Ok(())
+    }
+
+    async fn get_record(&self, session: Uuid) -> Result<PresentationRecord> {
+        if let Some(record) = self.records.try_lock()?.get(&session) {
+            return Ok(record.clone());
+        }
+        bail!("record not found")
+    }
+
+    async fn purge(&self, now: SystemTime) -> Result<usize> {
+        let mut records = self.records.try_lock()?;
+        let before = records.len();
+        records.retain(|_, record| !record.is_expired(now));
+        for record in records.values_mut() {
+            record.expire_claims(now);
+        }
+        Ok(before - records.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value as Json};
+
+    use crate::{
+        core::credential_format::ClaimFormatDesignation,
+        verifier::{
+            outcome::VerifiedCredential,
+            report::VerificationReport,
+            session::{test_session, Status},
+        },
+    };
+
+    use super::*;
+
+    fn session() -> Session {
+        Session {
+            status: Status::ReceivedResponse,
+            ..test_session(json!({
+                "presentation_definition": {
+                    "id": "pd",
+                    "input_descriptors": [
+                        {
+                            "id": "id-card",
+                            "constraints": { "fields": [
+                                { "path": ["$.credentialSubject.given_name"], "intent_to_retain": true }
+                            ]}
+                        },
+                        {
+                            "id": "age",
+                            "constraints": { "fields": [{ "path": ["$.credentialSubject.age_over_18"] }] }
+                        }
+                    ]
+                }
+            }))
+        }
+    }
+
+    fn credential(input_descriptor_id: &str, claims: Json) -> VerifiedCredential {
+        VerifiedCredential::new(
+            input_descriptor_id.into(),
+            ClaimFormatDesignation::JwtVcJson,
+        )
+        .with_claims(claims.as_object().unwrap().clone())
+    }
+
+    #[test]
+    fn minimize_and_expire_claims() {
+        let session = session();
+        let outcome = VerificationReport::new().into_verified_outcome(
+            VerifiedPresentationOutcome::new(None)
+                .with_credential(credential(
+                    "id-card",
+                    json!({ "given_name": "Alice", "family_name": "Doe" }),
+                ))
+                .with_credential(credential("age", json!({ "age_over_18": true }))),
+        );
+        let now = SystemTime::now();
+        let mut record = PresentationRecord::new(&session, &outcome, now, None);
+
+        record.minimize(&RequestedClaims::for_session(&session));
+        let verified = VerifiedPresentationOutcome::try_from(record.outcome.clone()).unwrap();
+        assert_eq!(verified.claims.get("id-card", "/family_name"), None);
+        assert_eq!(
+            verified.claims.get("id-card", "/given_name"),
+            Some(&json!("Alice"))
+        );
+
+        let transient = Duration::from_secs(60);
+        record.claims_retain_until = RetentionPolicy::new(transient).deadlines(&session, now);
+        assert_eq!(
+            record.claims_retain_until.keys().collect::<Vec<_>>(),
+            ["age"]
+        );
+        assert!(!record.expire_claims(now));
+        assert!(record.expire_claims(now + transient));
+
+        let verified = record.verified.as_ref().unwrap();
+        assert_eq!(verified.credentials_for("age").count(), 0);
+        assert_eq!(verified.credentials_for("id-card").count(), 1);
+        assert_eq!(
+            VerifiedPresentationOutcome::try_from(record.outcome.clone()).unwrap(),
+            *verified
+        );
+    }
+}
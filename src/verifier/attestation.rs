@@ -0,0 +1,190 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use base64::prelude::*;
+use http::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as Json};
+use ssi::jwk::JWK;
+use url::Url;
+
+use crate::core::{
+    consts::{media_type, typ},
+    util::{base_request, unix_time, AsyncHttpClient},
+};
+
+use super::request_signer::RequestSigner;
+
+/// The `typ` of a Verifier Attestation JWT.
+pub const VERIFIER_ATTESTATION_TYP: &str = typ::VERIFIER_ATTESTATION;
+
+/// The claims of a Verifier Attestation JWT.
+///
+/// See [OID4VP §5.10.1](https://openid.net/specs/openid-4-verifiable-presentations-1_0-20.html#section-5.10.1).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifierAttestationClaims {
+    /// The attestation issuer.
+    pub iss: String,
+    /// The `client_id` of the verifier.
+    pub sub: String,
+    /// Expiry, in seconds since the UNIX epoch.
+    pub exp: u64,
+    /// Issuance time, in seconds since the UNIX epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<u64>,
+    /// The key the verifier signs requests with, as `{ "jwk": ... }`.
+    pub cnf: Json,
+    /// The redirect URIs the verifier may use, if restricted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_uris: Option<Vec<String>>,
+}
+
+impl VerifierAttestationClaims {
+    /// Create the claims of an attestation binding `jwk` to `client_id`, valid for `ttl`.
+    pub fn new(iss: String, client_id: String, jwk: &JWK, ttl: Duration) -> Result<Self> {
+        let now = unix_time(SystemTime::now())?;
+        Ok(Self {
+            iss,
+            sub: client_id,
+            exp: now + ttl.as_secs(),
+            iat: Some(now),
+            cnf: json!({ "jwk": jwk.to_public() }),
+            redirect_uris: None,
+        })
+    }
+
+    /// Sign the claims as a Verifier Attestation JWT, as the attestation issuer.
+    pub async fn issue<S: RequestSigner + ?Sized>(&self, signer: &S) -> Result<String> {
+        let alg = signer
+            .alg()
+            .map_err(|e| anyhow::anyhow!("failed to retrieve signing algorithm: {e}"))?;
+        let mut header = json!({ "alg": alg, "typ": VERIFIER_ATTESTATION_TYP });
+        if let Some(kid) = signer.kid() {
+            header["kid"] = kid.into();
+        }
+        let header = BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?);
+        let claims = BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(self)?);
+        let signature = signer
+            .sign(format!("{header}.{claims}").as_bytes())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to sign the verifier attestation: {e}"))?;
+        Ok(format!(
+            "{header}.{claims}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+}
+
+/// A Verifier Attestation JWT, attached in the `jwt` header of the requests of a verifier using
+/// the `verifier_attestation` Client Identifier Scheme.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifierAttestation {
+    jwt: String,
+    claims: VerifierAttestationClaims,
+}
+
+impl VerifierAttestation {
+    /// Parse a Verifier Attestation JWT.
+    ///
+    /// The signature is not verified, this is the wallet's responsibility.
+    pub fn parse(jwt: String) -> Result<Self> {
+        let claims = jwt
+            .split('.')
+            .nth(1)
+            .context("the verifier attestation is not a JWT")?;
+        let claims = BASE64_URL_SAFE_NO_PAD
+            .decode(claims)
+            .context("the verifier attestation claims are not base64url encoded")?;
+        let claims = serde_json::from_slice(&claims)
+            .context("the verifier attestation claims are invalid")?;
+        Ok(Self { jwt, claims })
+    }
+
+    pub fn jwt(&self) -> &str {
+        &self.jwt
+    }
+
+    pub fn claims(&self) -> &VerifierAttestationClaims {
+        &self.claims
+    }
+
+    /// The key bound to the attestation (`cnf.jwk`).
+    pub fn jwk(&self) -> Result<JWK> {
+        serde_json::from_value(
+            self.claims
+                .cnf
+                .get("jwk")
+                .cloned()
+                .context("the verifier attestation has no 'cnf.jwk'")?,
+        )
+        .context("the verifier attestation 'cnf.jwk' is invalid")
+    }
+
+    pub fn expires_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.claims.exp)
+    }
+
+    /// Whether the attestation expires within `margin` of `now`, and should be refreshed.
+    pub fn needs_refresh(&self, now: SystemTime, margin: Duration) -> bool {
+        now + margin >= self.expires_at()
+    }
+}
+
+/// A source of Verifier Attestations, typically an attestation issuer endpoint.
+#[async_trait]
+pub trait AttestationSource: std::fmt::Debug + Send + Sync {
+    /// Obtain a new attestation for the verifier `client_id` and its request signing key.
+    async fn fetch(&self, client_id: &str, jwk: &JWK) -> Result<VerifierAttestation>;
+}
+
+/// Request attestations from an issuer endpoint.
+///
+/// The endpoint receives a JSON `POST` request containing the `client_id` and the public `jwk`
+/// of the verifier, and responds with the attestation JWT.
+#[derive(Debug)]
+pub struct HttpAttestationSource<H> {
+    endpoint: Url,
+    http_client: H,
+}
+
+impl<H> HttpAttestationSource<H> {
+    pub fn new(endpoint: Url, http_client: H) -> Self {
+        Self {
+            endpoint,
+            http_client,
+        }
+    }
+}
+
+#[async_trait]
+impl<H: AsyncHttpClient + std::fmt::Debug + Send + Sync> AttestationSource
+    for HttpAttestationSource<H>
+{
+    async fn fetch(&self, client_id: &str, jwk: &JWK) -> Result<VerifierAttestation> {
+        let body = serde_json::to_vec(&json!({
+            "client_id": client_id,
+            "jwk": jwk.to_public(),
+        }))?;
+        let request = base_request()
+            .method("POST")
+            .uri(self.endpoint.as_str())
+            .header(CONTENT_TYPE, media_type::JSON)
+            .body(body)
+            .context("failed to build the verifier attestation request")?;
+        let response = self
+            .http_client
+            .execute(request)
+            .await
+            .context("failed to request a verifier attestation")?;
+        if !response.status().is_success() {
+            bail!(
+                "the attestation issuer responded with status {}",
+                response.status()
+            )
+        }
+        let jwt = String::from_utf8(response.into_body())
+            .context("the verifier attestation is not valid UTF-8")?;
+        VerifierAttestation::parse(jwt.trim().to_string())
+    }
+}
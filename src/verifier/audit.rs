@@ -0,0 +1,200 @@
+use std::{
+    fmt::Debug,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::core::response::AuthorizationResponse;
+
+use super::session::{Outcome, Session};
+
+/// The audit record of a completed session: the artifacts exchanged with the wallet, the outcome
+/// of the verification and its timing, as received by an [AuditSink].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// The UUID of the session.
+    pub session: Uuid,
+    /// The [Tenant](super::tenant::Tenant) that the session was created for, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// The `client_id` the request was made as.
+    pub client_id: String,
+    /// The signed request (the Request Object JWT), unless it was redacted, or the session was
+    /// stateless and the request was not kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<String>,
+    /// The response as received, `application/x-www-form-urlencoded`, unless it was redacted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    /// The decrypted response, `application/x-www-form-urlencoded`, for encrypted responses that
+    /// could be decrypted, unless it was redacted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decrypted_response: Option<String>,
+    /// The outcome of the session, with the findings of the verification.
+    pub outcome: Outcome,
+    /// When the session was created.
+    pub requested_at: SystemTime,
+    /// When the response was received.
+    pub received_at: SystemTime,
+    /// When the response was verified, or rejected.
+    pub completed_at: SystemTime,
+    /// How long the response took to process.
+    pub processing_time: Duration,
+}
+
+impl AuditRecord {
+    /// The SHA-256 digest of the record (base64url), e.g. for a sink to chain each record to the
+    /// previous one, so that the trail is tamper-evident.
+    pub fn digest(&self) -> Result<String> {
+        Ok(BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(serde_json::to_vec(self)?)))
+    }
+}
+
+/// Receives the [AuditRecord] of every completed session, configured with
+/// [VerifierBuilder::with_audit_sink](super::VerifierBuilder::with_audit_sink).
+///
+/// Unlike notifications, a failure to record is returned to the caller that completed the
+/// session, as audit records must not be lost silently.
+#[async_trait]
+pub trait AuditSink: Debug + Send + Sync {
+    async fn record(&self, record: AuditRecord) -> Result<()>;
+}
+
+/// How an artifact is recorded in an [AuditRecord].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArtifactRedaction {
+    /// The artifact is recorded as is.
+    #[default]
+    Keep,
+    /// Only the SHA-256 digest of the artifact is recorded (`sha-256:<base64url>`), which proves
+    /// what was exchanged to whoever holds the artifact, without disclosing it.
+    Digest,
+    /// The artifact is not recorded.
+    Omit,
+}
+
+impl ArtifactRedaction {
+    fn apply(self, artifact: String) -> Option<String> {
+        match self {
+            Self::Keep => Some(artifact),
+            Self::Digest => Some(format!(
+                "sha-256:{}",
+                BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(artifact.as_bytes()))
+            )),
+            Self::Omit => None,
+        }
+    }
+}
+
+/// The redaction of each artifact of the [AuditRecord]s. Artifacts are kept by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AuditRedaction {
+    pub request: ArtifactRedaction,
+    pub response: ArtifactRedaction,
+    pub decrypted_response: ArtifactRedaction,
+}
+
+impl AuditRedaction {
+    /// Record the digests of the artifacts only.
+    pub fn digests() -> Self {
+        Self {
+            request: ArtifactRedaction::Digest,
+            response: ArtifactRedaction::Digest,
+            decrypted_response: ArtifactRedaction::Digest,
+        }
+    }
+}
+
+/// The artifacts of a response, captured as it is processed for its [AuditRecord].
+#[derive(Debug)]
+pub(crate) struct AuditCapture {
+    received_at: SystemTime,
+    start: Instant,
+    encrypted: bool,
+    response: Option<String>,
+    decrypted_response: Option<String>,
+}
+
+impl AuditCapture {
+    pub(crate) fn new(authorization_response: &AuthorizationResponse) -> Self {
+        Self {
+            received_at: SystemTime::now(),
+            start: Instant::now(),
+            encrypted: matches!(authorization_response, AuthorizationResponse::Jwt(_)),
+            response: form_encoded(authorization_response),
+            decrypted_response: None,
+        }
+    }
+
+    /// Capture the decrypted form of the response, if it was encrypted.
+    pub(crate) fn decrypted(&mut self, decrypted: &AuthorizationResponse) {
+        if self.encrypted {
+            self.decrypted_response = form_encoded(decrypted);
+        }
+    }
+
+    pub(crate) fn into_record(
+        self,
+        session: &Session,
+        outcome: &Outcome,
+        redaction: &AuditRedaction,
+    ) -> AuditRecord {
+        AuditRecord {
+            session: session.uuid,
+            tenant: session.tenant.clone(),
+            client_id: session.authorization_request_object.client_id().0.clone(),
+            request: Some(session.authorization_request_jwt.clone())
+                .filter(|jwt| !jwt.is_empty())
+                .and_then(|jwt| redaction.request.apply(jwt)),
+            response: self
+                .response
+                .and_then(|response| redaction.response.apply(response)),
+            decrypted_response: self
+                .decrypted_response
+                .and_then(|response| redaction.decrypted_response.apply(response)),
+            outcome: outcome.clone(),
+            requested_at: session.created_at,
+            received_at: self.received_at,
+            completed_at: SystemTime::now(),
+            processing_time: self.start.elapsed(),
+        }
+    }
+}
+
+fn form_encoded(authorization_response: &AuthorizationResponse) -> Option<String> {
+    match authorization_response.clone() {
+        AuthorizationResponse::Unencoded(response) => response.into_x_www_form_urlencoded(),
+        AuthorizationResponse::Jwt(response) => response.into_x_www_form_urlencoded(),
+        AuthorizationResponse::Dcql(response) => response.into_x_www_form_urlencoded(),
+    }
+    .ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn redact_artifacts() {
+        assert_eq!(
+            ArtifactRedaction::Keep.apply("response=abc".into()),
+            Some("response=abc".into())
+        );
+        assert_eq!(ArtifactRedaction::Omit.apply("response=abc".into()), None);
+        let digest = ArtifactRedaction::Digest
+            .apply("response=abc".into())
+            .unwrap();
+        assert!(digest.starts_with("sha-256:"));
+        assert!(!digest.contains("abc"));
+        assert_eq!(
+            ArtifactRedaction::Digest.apply("response=abc".into()),
+            Some(digest)
+        );
+    }
+}
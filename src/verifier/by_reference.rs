@@ -1,4 +1,15 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rand::distributions::{Alphanumeric, DistString};
 use url::Url;
+use uuid::Uuid;
+
+/// How long a `request_uri` can be retrieved for, by default.
+pub const DEFAULT_REQUEST_URI_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// The length of the single-use secret in a `request_uri` token.
+const SECRET_LENGTH: usize = 32;
 
 #[derive(Debug, Clone, Default)]
 pub enum ByReference {
@@ -8,3 +19,36 @@ pub enum ByReference {
         at: Url,
     },
 }
+
+/// Generate the single-use secret of a `request_uri`.
+pub(crate) fn generate_secret() -> String {
+    Alphanumeric.sample_string(&mut rand::thread_rng(), SECRET_LENGTH)
+}
+
+/// The token at the end of a `request_uri`: the UUID of the session and its single-use secret.
+pub(crate) fn token(uuid: Uuid, secret: &str) -> String {
+    format!("{uuid}.{secret}")
+}
+
+/// Split a `request_uri` token into the UUID of the session and its secret.
+pub(crate) fn parse_token(token: &str) -> Result<(Uuid, &str)> {
+    let (uuid, secret) = token.split_once('.').context("invalid request_uri token")?;
+    Ok((uuid.parse().context("invalid request_uri token")?, secret))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        let uuid = Uuid::new_v4();
+        let secret = generate_secret();
+        assert_eq!(secret.len(), SECRET_LENGTH);
+
+        let token = token(uuid, &secret);
+        assert_eq!(parse_token(&token).unwrap(), (uuid, secret.as_str()));
+        assert!(parse_token(&uuid.to_string()).is_err());
+        assert!(parse_token("session.secret").is_err());
+    }
+}
@@ -1,24 +1,33 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    fmt::Debug,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
 
 use anyhow::{bail, Context as _, Result};
 use async_trait::async_trait;
 use base64::prelude::*;
 use serde_json::{json, Value as Json};
 use ssi::jwk::JWKResolver;
+use url::Url;
 
-use tracing::debug;
+use tracing::{debug, warn};
 use x509_cert::{
     der::Encode,
     ext::pkix::{name::GeneralName, SubjectAltName},
     Certificate,
 };
 
+pub use crate::core::authorization_request::parameters::X509SanVariant;
 use crate::core::authorization_request::{
     parameters::{ClientId, ClientIdScheme},
     AuthorizationRequestObject,
 };
 
-use super::request_signer::RequestSigner;
+use super::{
+    attestation::{AttestationSource, VerifierAttestation},
+    request_signer::RequestSigner,
+};
 
 #[async_trait]
 pub trait Client: Debug {
@@ -70,6 +79,8 @@ impl DIDClient {
 }
 
 /// A [Client] with the `x509_san_dns` or `x509_san_uri` Client Identifier.
+///
+/// The certificate chain (leaf first) is attached to each request in the `x5c` header.
 #[derive(Debug, Clone)]
 pub struct X509SanClient {
     id: ClientId,
@@ -79,56 +90,222 @@ pub struct X509SanClient {
 }
 
 impl X509SanClient {
+    /// How long before the leaf certificate expires that a warning is logged.
+    pub const DEFAULT_EXPIRY_WARNING: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+    /// Create a client from a certificate chain, using the first matching SAN of the leaf
+    /// certificate as the `client_id`.
     pub fn new(
         x5c: Vec<Certificate>,
         signer: Arc<dyn RequestSigner<Error = anyhow::Error> + Send + Sync>,
         variant: X509SanVariant,
     ) -> Result<Self> {
-        let leaf = &x5c[0];
-        let id = if let Some(san) = leaf
-            .tbs_certificate
-            .filter::<SubjectAltName>()
-            .filter_map(|r| match r {
-                Ok((_crit, san)) => Some(san.0.into_iter()),
-                Err(e) => {
-                    debug!("unable to parse SubjectAlternativeName from DER: {e}");
-                    None
-                }
-            })
-            .flatten()
-            .filter_map(|general_name| match (general_name, variant) {
-                (GeneralName::DnsName(uri), X509SanVariant::Dns) => Some(uri.to_string()),
-                (gn, X509SanVariant::Dns) => {
-                    debug!("found non-DNS SAN: {gn:?}");
-                    None
-                }
-                (GeneralName::UniformResourceIdentifier(uri), X509SanVariant::Uri) => {
-                    Some(uri.to_string())
-                }
-                (gn, X509SanVariant::Uri) => {
-                    debug!("found non-URI SAN: {gn:?}");
-                    None
-                }
-            })
-            .next()
-        {
-            san
-        } else {
+        let leaf = x5c.first().context("x509 certificate chain was empty")?;
+        let Some(id) = subject_alternative_names(leaf, variant).into_iter().next() else {
             bail!("x509 certificate does not contain Subject Alternative Name");
         };
-        Ok(X509SanClient {
+        let client = X509SanClient {
             id: ClientId(id),
             x5c,
             signer,
             variant,
+        };
+        client.warn_if_expiring(Self::DEFAULT_EXPIRY_WARNING);
+        Ok(client)
+    }
+
+    /// Create a client from a PEM encoded certificate chain, leaf first.
+    pub fn from_pem_chain(
+        pem: &[u8],
+        signer: Arc<dyn RequestSigner<Error = anyhow::Error> + Send + Sync>,
+        variant: X509SanVariant,
+    ) -> Result<Self> {
+        let x5c = Certificate::load_pem_chain(pem)
+            .context("unable to parse PEM encoded certificate chain")?;
+        Self::new(x5c, signer, variant)
+    }
+
+    /// Use the configured `client_id`, which must match a SAN of the leaf certificate.
+    pub fn with_client_id(mut self, client_id: &str) -> Result<Self> {
+        if !subject_alternative_names(self.leaf(), self.variant)
+            .iter()
+            .any(|san| san == client_id)
+        {
+            bail!("client_id '{client_id}' does not match any Subject Alternative Name of the leaf certificate")
+        }
+        self.id = ClientId(client_id.to_owned());
+        Ok(self)
+    }
+
+    /// The leaf certificate.
+    pub fn leaf(&self) -> &Certificate {
+        &self.x5c[0]
+    }
+
+    /// The certificate chain, leaf first.
+    pub fn x5c(&self) -> &[Certificate] {
+        &self.x5c
+    }
+
+    /// When the leaf certificate expires.
+    pub fn not_after(&self) -> SystemTime {
+        self.leaf()
+            .tbs_certificate
+            .validity
+            .not_after
+            .to_system_time()
+    }
+
+    /// Whether the leaf certificate expires within `window` of `now`.
+    pub fn expires_within(&self, window: Duration, now: SystemTime) -> bool {
+        self.not_after() <= now + window
+    }
+
+    /// Log a warning if the leaf certificate expires within `window`.
+    ///
+    /// Returns whether the warning was logged.
+    pub fn warn_if_expiring(&self, window: Duration) -> bool {
+        let expiring = self.expires_within(window, SystemTime::now());
+        if expiring {
+            warn!(
+                client_id = self.id.0,
+                not_after = %self.leaf().tbs_certificate.validity.not_after,
+                "the verifier's x509 certificate is about to expire"
+            );
+        }
+        expiring
+    }
+}
+
+fn subject_alternative_names(leaf: &Certificate, variant: X509SanVariant) -> Vec<String> {
+    leaf.tbs_certificate
+        .filter::<SubjectAltName>()
+        .filter_map(|r| match r {
+            Ok((_crit, san)) => Some(san.0.into_iter()),
+            Err(e) => {
+                debug!("unable to parse SubjectAlternativeName from DER: {e}");
+                None
+            }
+        })
+        .flatten()
+        .filter_map(|general_name| match (general_name, variant) {
+            (GeneralName::DnsName(uri), X509SanVariant::Dns) => Some(uri.to_string()),
+            (gn, X509SanVariant::Dns) => {
+                debug!("found non-DNS SAN: {gn:?}");
+                None
+            }
+            (GeneralName::UniformResourceIdentifier(uri), X509SanVariant::Uri) => {
+                Some(uri.to_string())
+            }
+            (gn, X509SanVariant::Uri) => {
+                debug!("found non-URI SAN: {gn:?}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// A [Client] with the `verifier_attestation` Client Identifier.
+///
+/// The Verifier Attestation JWT is attached to each request in the `jwt` header, and can be
+/// refreshed from an [AttestationSource] before it expires.
+#[derive(Debug)]
+pub struct VerifierAttestationClient {
+    id: ClientId,
+    attestation: RwLock<VerifierAttestation>,
+    signer: Arc<dyn RequestSigner<Error = anyhow::Error> + Send + Sync>,
+}
+
+impl VerifierAttestationClient {
+    /// Create a client from an attestation of the signer's key.
+    ///
+    /// The `client_id` is the `sub` of the attestation, and the attested key (`cnf.jwk`) must be
+    /// the signer's key.
+    pub fn new(
+        attestation: VerifierAttestation,
+        signer: Arc<dyn RequestSigner<Error = anyhow::Error> + Send + Sync>,
+    ) -> Result<Self> {
+        check_attested_key(&attestation, signer.as_ref())?;
+        Ok(Self {
+            id: ClientId(attestation.claims().sub.clone()),
+            attestation: RwLock::new(attestation),
+            signer,
         })
     }
+
+    /// Obtain an attestation from `source` and create a client from it.
+    pub async fn from_source(
+        client_id: &str,
+        source: &dyn AttestationSource,
+        signer: Arc<dyn RequestSigner<Error = anyhow::Error> + Send + Sync>,
+    ) -> Result<Self> {
+        let jwk = signer.jwk().context("signer did not have a JWK")?;
+        let attestation = source.fetch(client_id, &jwk).await?;
+        if attestation.claims().sub != client_id {
+            bail!("the verifier attestation was issued for another client_id")
+        }
+        Self::new(attestation, signer)
+    }
+
+    /// The current attestation.
+    pub fn attestation(&self) -> Result<VerifierAttestation> {
+        self.attestation
+            .read()
+            .map(|attestation| attestation.clone())
+            .map_err(|_| anyhow::anyhow!("verifier attestation lock poisoned"))
+    }
+
+    /// Replace the attestation from `source` if it expires within `margin`.
+    ///
+    /// Returns whether the attestation was refreshed.
+    pub async fn refresh(&self, source: &dyn AttestationSource, margin: Duration) -> Result<bool> {
+        if !self.attestation()?.needs_refresh(SystemTime::now(), margin) {
+            return Ok(false);
+        }
+        let jwk = self.signer.jwk().context("signer did not have a JWK")?;
+        let attestation = source.fetch(&self.id.0, &jwk).await?;
+        if attestation.claims().sub != self.id.0 {
+            bail!("the verifier attestation was issued for another client_id")
+        }
+        check_attested_key(&attestation, self.signer.as_ref())?;
+        *self
+            .attestation
+            .write()
+            .map_err(|_| anyhow::anyhow!("verifier attestation lock poisoned"))? = attestation;
+        Ok(true)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum X509SanVariant {
-    Uri,
-    Dns,
+fn check_attested_key(
+    attestation: &VerifierAttestation,
+    signer: &(dyn RequestSigner<Error = anyhow::Error> + Send + Sync),
+) -> Result<()> {
+    if attestation.jwk()?.to_public() != signer.jwk().context("signer did not have a JWK")? {
+        bail!("the key in the verifier attestation did not match the public key of the signer")
+    }
+    Ok(())
+}
+
+/// A [Client] with the `redirect_uri` Client Identifier, for low-assurance or development
+/// scenarios.
+///
+/// Requests are not signed: their parameters are passed directly in the URL, without a Request
+/// Object, and their `client_id` is the `response_uri` of each session, as required for this
+/// Client Identifier.
+#[derive(Debug, Clone)]
+pub struct RedirectUriClient {
+    id: ClientId,
+}
+
+impl RedirectUriClient {
+    /// Create a client for a verifier receiving responses at `submission_endpoint` (see
+    /// [VerifierBuilder::with_submission_endpoint](super::VerifierBuilder::with_submission_endpoint)),
+    /// below which the `response_uri` of each session is.
+    pub fn new(submission_endpoint: Url) -> Self {
+        Self {
+            id: ClientId(submission_endpoint.to_string()),
+        }
+    }
 }
 
 #[async_trait]
@@ -179,21 +356,86 @@ impl Client for X509SanClient {
             .signer
             .alg()
             .context("failed to retrieve signing algorithm")?;
+        if self.expires_within(Duration::ZERO, SystemTime::now()) {
+            bail!("the verifier's x509 certificate has expired")
+        }
         let x5c: Vec<String> = self
             .x5c
             .iter()
             .map(|x509| x509.to_der())
             .map(|der| Ok(BASE64_STANDARD.encode(der?)))
             .collect::<Result<_>>()?;
-        let header = json!({
+        let mut header = json!({
             "alg": algorithm,
             "x5c": x5c,
             "typ": "JWT"
         });
+        if let Some(kid) = self.signer.kid() {
+            header["kid"] = kid.into();
+        }
         make_jwt(header, body, self.signer.as_ref()).await
     }
 }
 
+#[async_trait]
+impl Client for VerifierAttestationClient {
+    fn id(&self) -> &ClientId {
+        &self.id
+    }
+
+    fn scheme(&self) -> &ClientIdScheme {
+        &ClientIdScheme::VerifierAttestation
+    }
+
+    async fn generate_request_object_jwt(
+        &self,
+        body: &AuthorizationRequestObject,
+    ) -> Result<String> {
+        let algorithm = self
+            .signer
+            .alg()
+            .context("failed to retrieve signing algorithm")?;
+        let attestation = self.attestation()?;
+        if attestation.needs_refresh(SystemTime::now(), Duration::ZERO) {
+            bail!("the verifier attestation has expired")
+        }
+        let mut header = json!({
+            "alg": algorithm,
+            "jwt": attestation.jwt(),
+            "typ": "JWT"
+        });
+        if let Some(kid) = self.signer.kid() {
+            header["kid"] = kid.into();
+        }
+        make_jwt(header, body, self.signer.as_ref()).await
+    }
+}
+
+#[async_trait]
+impl Client for RedirectUriClient {
+    fn id(&self) -> &ClientId {
+        &self.id
+    }
+
+    fn scheme(&self) -> &ClientIdScheme {
+        &ClientIdScheme::RedirectUri
+    }
+
+    /// The unsigned (`"alg": "none"`) Request Object, which is only kept in the session, as the
+    /// request is passed in the URL.
+    async fn generate_request_object_jwt(
+        &self,
+        body: &AuthorizationRequestObject,
+    ) -> Result<String> {
+        let header_b64 = BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&json!({
+            "alg": "none",
+            "typ": "JWT"
+        }))?);
+        let body_b64 = BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(body)?);
+        Ok(format!("{header_b64}.{body_b64}."))
+    }
+}
+
 async fn make_jwt<S: RequestSigner + ?Sized>(
     header: Json,
     body: &AuthorizationRequestObject,
@@ -203,7 +445,164 @@ async fn make_jwt<S: RequestSigner + ?Sized>(
         serde_json::to_vec(&header).map(|b| BASE64_URL_SAFE_NO_PAD.encode(b))?;
     let body_b64 = serde_json::to_vec(body).map(|b| BASE64_URL_SAFE_NO_PAD.encode(b))?;
     let payload = [header_b64.as_bytes(), b".", body_b64.as_bytes()].concat();
-    let signature = signer.sign(&payload).await;
+    let signature = signer
+        .sign(&payload)
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to sign the request object: {e}"))?;
     let signature_b64 = BASE64_URL_SAFE_NO_PAD.encode(signature);
     Ok(format!("{header_b64}.{body_b64}.{signature_b64}"))
 }
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value as Json;
+
+    use crate::{
+        core::object::UntypedObject, verifier::attestation::VerifierAttestationClaims,
+        verifier::request_signer::P256Signer,
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Issuer(P256Signer);
+
+    #[async_trait]
+    impl AttestationSource for Issuer {
+        async fn fetch(&self, client_id: &str, jwk: &ssi::jwk::JWK) -> Result<VerifierAttestation> {
+            let claims = VerifierAttestationClaims::new(
+                "https://attester.example".into(),
+                client_id.into(),
+                jwk,
+                Duration::from_secs(60),
+            )?;
+            VerifierAttestation::parse(claims.issue(&self.0).await?)
+        }
+    }
+
+    fn certificate(validity: Duration, sans: Vec<GeneralName>) -> Certificate {
+        use std::str::FromStr;
+        use x509_cert::{
+            builder::{Builder, CertificateBuilder, Profile},
+            name::Name,
+            serial_number::SerialNumber,
+            spki::SubjectPublicKeyInfoOwned,
+            time::Validity,
+        };
+
+        let key = p256::ecdsa::SigningKey::random(&mut rand::thread_rng());
+        let spki = SubjectPublicKeyInfoOwned::from_key(*key.verifying_key()).unwrap();
+        let mut builder = CertificateBuilder::new(
+            Profile::Root,
+            SerialNumber::from(1u32),
+            Validity::from_now(validity).unwrap(),
+            Name::from_str("CN=verifier").unwrap(),
+            spki,
+            &key,
+        )
+        .unwrap();
+        builder.add_extension(&SubjectAltName(sans)).unwrap();
+        builder.build::<p256::ecdsa::DerSignature>().unwrap()
+    }
+
+    fn signer() -> P256Signer {
+        P256Signer::new(p256::SecretKey::random(&mut rand::thread_rng()).into()).unwrap()
+    }
+
+    fn decode(part: &str) -> Json {
+        serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(part).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn verifier_attestation_header() {
+        let issuer = Issuer(signer());
+        let client =
+            VerifierAttestationClient::from_source("verifier.example", &issuer, Arc::new(signer()))
+                .await
+                .unwrap();
+        assert_eq!(client.id().0, "verifier.example");
+
+        let request: UntypedObject = serde_json::from_value(json!({
+            "client_id": "verifier.example",
+            "client_id_scheme": "verifier_attestation",
+            "response_type": "vp_token",
+            "response_mode": "direct_post",
+            "response_uri": "https://verifier.example/response",
+            "nonce": "nonce",
+            "presentation_definition": { "id": "pd", "input_descriptors": [] }
+        }))
+        .unwrap();
+        let jwt = client
+            .generate_request_object_jwt(&request.try_into().unwrap())
+            .await
+            .unwrap();
+
+        let header = decode(jwt.split('.').next().unwrap());
+        let attestation = header["jwt"].as_str().unwrap();
+        assert_eq!(attestation, client.attestation().unwrap().jwt());
+        assert_eq!(
+            decode(attestation.split('.').nth(1).unwrap())["sub"],
+            "verifier.example"
+        );
+
+        // Still valid for 60 seconds.
+        assert!(!client
+            .refresh(&issuer, Duration::from_secs(10))
+            .await
+            .unwrap());
+        assert!(client
+            .refresh(&issuer, Duration::from_secs(120))
+            .await
+            .unwrap());
+
+        // An attestation for another key is rejected.
+        assert!(
+            VerifierAttestationClient::new(client.attestation().unwrap(), Arc::new(signer()))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn x509_san_identity() {
+        use x509_cert::der::{asn1::Ia5String, EncodePem};
+
+        let day = Duration::from_secs(24 * 60 * 60);
+        let cert = certificate(
+            365 * day,
+            vec![
+                GeneralName::DnsName(Ia5String::new("verifier.example").unwrap()),
+                GeneralName::DnsName(Ia5String::new("other.example").unwrap()),
+            ],
+        );
+        let pem = cert
+            .to_pem(x509_cert::der::pem::LineEnding::LF)
+            .unwrap()
+            .repeat(2);
+
+        let client =
+            X509SanClient::from_pem_chain(pem.as_bytes(), Arc::new(signer()), X509SanVariant::Dns)
+                .unwrap();
+        assert_eq!(client.x5c().len(), 2);
+        assert_eq!(client.id().0, "verifier.example");
+        assert!(!client.warn_if_expiring(X509SanClient::DEFAULT_EXPIRY_WARNING));
+        assert!(client.expires_within(366 * day, SystemTime::now()));
+
+        let client = client.with_client_id("other.example").unwrap();
+        assert_eq!(client.id().0, "other.example");
+        assert!(client.clone().with_client_id("unknown.example").is_err());
+        assert!(X509SanClient::new(vec![cert], Arc::new(signer()), X509SanVariant::Uri).is_err());
+
+        let expiring = X509SanClient::new(
+            vec![certificate(
+                day,
+                vec![GeneralName::DnsName(
+                    Ia5String::new("verifier.example").unwrap(),
+                )],
+            )],
+            Arc::new(signer()),
+            X509SanVariant::Dns,
+        )
+        .unwrap();
+        assert!(expiring.warn_if_expiring(X509SanClient::DEFAULT_EXPIRY_WARNING));
+    }
+}
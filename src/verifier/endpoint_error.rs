@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use http::{
+    header::{CONTENT_TYPE, RETRY_AFTER},
+    Response, StatusCode,
+};
+
+use crate::core::{
+    consts::media_type,
+    response::error::{AuthorizationErrorCode, AuthorizationErrorResponse},
+};
+
+use super::guard::RequestRejected;
+
+/// The HTTP error response of an endpoint of a [Verifier](super::Verifier) that wallets call,
+/// such as [retrieve_authorization_request](super::Verifier::retrieve_authorization_request) and
+/// [receive_response](super::Verifier::receive_response), for the server layer to send.
+///
+/// The body is an OAuth error response, as defined by
+/// [RFC 6749 §5.2](https://www.rfc-editor.org/rfc/rfc6749#section-5.2):
+///
+/// | Error | Status | Code |
+/// |-------|--------|------|
+/// | [RequestRejected] with a `retry_after` | `429 Too Many Requests` | `access_denied` |
+/// | [RequestRejected] | `403 Forbidden` | `access_denied` |
+/// | anything else, e.g. [DuplicateResponse](super::session::DuplicateResponse) | `400 Bad Request` | `invalid_request` |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointError {
+    pub status: StatusCode,
+    pub body: AuthorizationErrorResponse,
+    /// When the wallet may try again, sent as the `Retry-After` header.
+    pub retry_after: Option<Duration>,
+}
+
+impl EndpointError {
+    /// The response to `error`, which an endpoint of the verifier failed with.
+    pub fn for_error(error: &anyhow::Error) -> Self {
+        let (status, code, retry_after) = match error.downcast_ref::<RequestRejected>() {
+            Some(RequestRejected {
+                retry_after: Some(retry_after),
+                ..
+            }) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                AuthorizationErrorCode::AccessDenied,
+                Some(*retry_after),
+            ),
+            Some(_) => (
+                StatusCode::FORBIDDEN,
+                AuthorizationErrorCode::AccessDenied,
+                None,
+            ),
+            // Duplicate responses, malformed submissions, unknown sessions, etc.
+            None => (
+                StatusCode::BAD_REQUEST,
+                AuthorizationErrorCode::InvalidRequest,
+                None,
+            ),
+        };
+
+        Self {
+            status,
+            body: AuthorizationErrorResponse::new(code)
+                .with_error_description(format!("{error:#}")),
+            retry_after,
+        }
+    }
+
+    /// Build the HTTP response, with a JSON body.
+    pub fn into_response(self) -> Result<Response<Vec<u8>>> {
+        let mut response = Response::builder()
+            .status(self.status)
+            .header(CONTENT_TYPE, media_type::JSON);
+        if let Some(retry_after) = self.retry_after {
+            // Retry-After is a number of seconds, rounded up so that wallets do not retry early.
+            let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            response = response.header(RETRY_AFTER, seconds);
+        }
+        let body = serde_json::to_vec(&self.body).context("failed to serialize error response")?;
+        response
+            .body(body)
+            .context("failed to build error response")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::anyhow;
+    use uuid::Uuid;
+
+    use crate::verifier::{guard::Endpoint, session::DuplicateResponse};
+
+    use super::*;
+
+    #[test]
+    fn rejected_calls() {
+        let rejected = |retry_after| {
+            anyhow!(RequestRejected {
+                endpoint: Endpoint::RequestUri,
+                reason: "too many calls".into(),
+                retry_after,
+            })
+        };
+
+        let error = EndpointError::for_error(&rejected(Some(Duration::from_millis(1500))));
+        assert_eq!(error.status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(error.body.error, AuthorizationErrorCode::AccessDenied);
+        let response = error.into_response().unwrap();
+        assert_eq!(response.headers()[RETRY_AFTER], "2");
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(response.body()).unwrap(),
+            serde_json::json!({
+                "error": "access_denied",
+                "error_description": "the call was rejected: too many calls"
+            })
+        );
+
+        let error = EndpointError::for_error(&rejected(None));
+        assert_eq!(error.status, StatusCode::FORBIDDEN);
+        assert!(!error
+            .into_response()
+            .unwrap()
+            .headers()
+            .contains_key(RETRY_AFTER));
+    }
+
+    #[test]
+    fn invalid_requests() {
+        let error = anyhow!(DuplicateResponse {
+            session: Uuid::nil(),
+            identical: false,
+        })
+        .context("the response could not be received");
+        let error = EndpointError::for_error(&error);
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.body.error, AuthorizationErrorCode::InvalidRequest);
+        assert!(error
+            .body
+            .error_description
+            .unwrap()
+            .starts_with("the response could not be received: a response was already received"));
+    }
+}
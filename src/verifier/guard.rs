@@ -0,0 +1,99 @@
+use std::{collections::BTreeMap, fmt, net::IpAddr, time::Duration};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+/// The verifier endpoint that a wallet (or anyone else) called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    /// The `request_uri` of a request passed by reference.
+    RequestUri,
+    /// The `response_uri` that responses are submitted to.
+    ResponseUri,
+}
+
+/// What the server layer knows about the caller of an endpoint, e.g. taken from the connection and
+/// the HTTP request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteMetadata {
+    /// The IP address of the caller.
+    pub ip: Option<IpAddr>,
+    /// The `User-Agent` of the caller.
+    pub user_agent: Option<String>,
+    /// Any other headers or attributes the guard needs, e.g. a proof-of-work solution.
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl RemoteMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_ip(mut self, ip: IpAddr) -> Self {
+        self.ip = Some(ip);
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn with_attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(name.into(), value.into());
+        self
+    }
+}
+
+/// The context of a call to a verifier endpoint, passed to a [RequestGuard].
+#[derive(Debug, Clone)]
+pub struct RequestContext<'a> {
+    pub endpoint: Endpoint,
+    /// The session that the call is for.
+    pub session: Uuid,
+    /// The `client_id` of the session.
+    pub client_id: &'a str,
+    /// The [Tenant](super::tenant::Tenant) of the session, if any.
+    pub tenant: Option<&'a str>,
+    pub remote: &'a RemoteMetadata,
+}
+
+/// The decision of a [RequestGuard].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuardDecision {
+    Allow,
+    /// The call is refused, see [RequestRejected].
+    Reject {
+        reason: String,
+        /// When the caller may try again, for rate limiting.
+        retry_after: Option<Duration>,
+    },
+}
+
+/// A hook to protect the endpoints of a [Verifier](super::Verifier) that wallets call, e.g. with
+/// rate limiting, proof-of-work or IP reputation, configured with
+/// [VerifierBuilder::with_request_guard](super::VerifierBuilder::with_request_guard).
+///
+/// The guard is consulted once the session of a call is known, before the request is served or
+/// the response processed.
+#[async_trait]
+pub trait RequestGuard: fmt::Debug + Send + Sync {
+    async fn check(&self, context: &RequestContext<'_>) -> GuardDecision;
+}
+
+/// A call was refused by the [RequestGuard], which the server layer can map to e.g. an HTTP 429
+/// response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestRejected {
+    pub endpoint: Endpoint,
+    pub reason: String,
+    pub retry_after: Option<Duration>,
+}
+
+impl fmt::Display for RequestRejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the call was rejected: {}", self.reason)
+    }
+}
+
+impl std::error::Error for RequestRejected {}
@@ -0,0 +1,338 @@
+use std::{
+    fmt::{self, Debug},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+use ssi::{
+    dids::{AnyDidMethod, VerificationMethodDIDResolver},
+    jwk::{JWKResolver, JWK},
+    verification_methods::AnyJwkMethod,
+};
+
+use crate::core::{
+    authorization_request::AuthorizationRequestObject,
+    response::parameters::IdToken,
+    util::{decode_jwt, Base64Policy},
+};
+
+use super::{
+    outcome::VerifiedPresentationOutcome,
+    report::{FindingCode, VerificationReport},
+    session::Outcome,
+};
+
+/// Allowed clock skew when checking `iat` and `exp`.
+pub const ID_TOKEN_LEEWAY: Duration = Duration::from_secs(60);
+
+/// The `sub` prefix of the `urn:ietf:params:oauth:jwk-thumbprint` subject syntax type.
+const JWK_THUMBPRINT_PREFIX: &str = "urn:ietf:params:oauth:jwk-thumbprint:sha-256:";
+
+/// The claims of a Self-Issued ID Token (SIOPv2) that passed verification, returned alongside the
+/// `vp_token` when the request had `response_type` `vp_token id_token`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedIdToken {
+    /// The subject, and issuer, of the token: a DID, or the thumbprint of the key in `sub_jwk`.
+    pub subject: String,
+    /// When the token was issued, in seconds since the UNIX epoch.
+    pub issued_at: u64,
+    /// When the token expires, in seconds since the UNIX epoch.
+    pub expires_at: u64,
+    /// The claims of the token other than the ones that were verified, e.g. user claims.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub claims: Map<String, Json>,
+}
+
+impl VerifiedIdToken {
+    /// Whether the token was issued by `holder`, the holder bound in the presentations: its DID,
+    /// or one of its verification methods.
+    pub fn binds_holder(&self, holder: &str) -> bool {
+        let holder = holder.split_once('#').map_or(holder, |(did, _)| did);
+        holder == self.subject
+    }
+}
+
+/// Resolves the keys of ID Tokens whose subject is a DID.
+#[async_trait]
+pub trait IdTokenKeyResolver: Debug + Send + Sync {
+    /// The public key of the verification method `kid`, a DID URL.
+    async fn resolve_key(&self, kid: &str) -> Result<JWK>;
+}
+
+/// Resolves keys with the DID methods supported by `ssi`, see [AnyDidMethod].
+#[derive(Default)]
+pub struct AnyDidKeyResolver(AnyDidMethod);
+
+impl fmt::Debug for AnyDidKeyResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AnyDidKeyResolver").finish()
+    }
+}
+
+#[async_trait]
+impl IdTokenKeyResolver for AnyDidKeyResolver {
+    async fn resolve_key(&self, kid: &str) -> Result<JWK> {
+        let resolver: VerificationMethodDIDResolver<_, AnyJwkMethod> =
+            VerificationMethodDIDResolver::new(self.0.clone());
+        Ok(resolver
+            .fetch_public_jwk(Some(kid))
+            .await
+            .with_context(|| format!("failed to resolve the key '{kid}'"))?
+            .into_owned())
+    }
+}
+
+/// Verify a Self-Issued ID Token returned in response to `request`.
+///
+/// The token must be self-issued (`iss` is `sub`), addressed to the `client_id` of the request,
+/// carry its `nonce`, and be current. It must be signed by its subject: the verification method
+/// in `kid` of a DID subject, resolved with `resolver`, or the key in `sub_jwk` whose JWK
+/// thumbprint is the subject.
+pub async fn verify_id_token(
+    id_token: &IdToken,
+    request: &AuthorizationRequestObject,
+    resolver: &dyn IdTokenKeyResolver,
+    now: SystemTime,
+) -> Result<VerifiedIdToken> {
+    let jwt = id_token.expose();
+    let (header, mut claims) = decode(jwt)?;
+
+    let subject = take_string(&mut claims, "sub")?;
+    if take_string(&mut claims, "iss")? != subject {
+        bail!("the id_token is not self-issued: 'iss' is not 'sub'")
+    }
+
+    let audiences = match claims.remove("aud") {
+        Some(Json::String(aud)) => vec![aud],
+        Some(aud) => serde_json::from_value(aud).context("'aud' was malformed")?,
+        None => bail!("the id_token is missing 'aud'"),
+    };
+    let client_id = &request.client_id().0;
+    if !audiences.contains(client_id) {
+        bail!("the id_token was not addressed to this verifier (aud: {audiences:?})")
+    }
+
+    if take_string(&mut claims, "nonce")? != request.nonce().as_str() {
+        bail!("the nonce of the id_token does not match the nonce of the session")
+    }
+
+    let now = now
+        .duration_since(UNIX_EPOCH)
+        .context("time was before the UNIX epoch")?
+        .as_secs();
+    let issued_at = take_time(&mut claims, "iat")?;
+    let expires_at = take_time(&mut claims, "exp")?;
+    if issued_at > now + ID_TOKEN_LEEWAY.as_secs() {
+        bail!("the id_token was issued in the future, at {issued_at}")
+    }
+    if expires_at + ID_TOKEN_LEEWAY.as_secs() < now {
+        bail!("the id_token expired at {expires_at}")
+    }
+
+    let sub_jwk = claims.remove("sub_jwk");
+    let jwk = if subject.starts_with("did:") {
+        let Some(Json::String(kid)) = header.get("kid") else {
+            bail!("'kid' is missing from the header of the id_token")
+        };
+        if kid.split_once('#').map_or(kid.as_str(), |(did, _)| did) != subject {
+            bail!("the key '{kid}' of the id_token is not a key of its subject")
+        }
+        resolver.resolve_key(kid).await?
+    } else {
+        let jwk = sub_jwk
+            .or_else(|| header.get("jwk").cloned())
+            .context("the id_token carries no 'sub_jwk' for its JWK thumbprint subject")?;
+        let jwk: JWK = serde_json::from_value(jwk).context("'sub_jwk' was malformed")?;
+        let thumbprint = jwk
+            .thumbprint()
+            .context("failed to compute the JWK thumbprint")?;
+        if subject
+            .strip_prefix(JWK_THUMBPRINT_PREFIX)
+            .unwrap_or(&subject)
+            != thumbprint
+        {
+            bail!("the subject of the id_token is not the thumbprint of its 'sub_jwk'")
+        }
+        jwk
+    };
+    let _: Json = ssi::claims::jwt::decode_verify(jwt, &jwk)
+        .context("the signature of the id_token could not be verified")?;
+
+    Ok(VerifiedIdToken {
+        subject,
+        issued_at,
+        expires_at,
+        claims,
+    })
+}
+
+/// Add a verified ID Token to a successful outcome, whose holder must be the subject of the token.
+pub(super) fn bind_id_token(id_token: VerifiedIdToken, outcome: Outcome) -> Outcome {
+    let Outcome::Success { .. } = outcome else {
+        return outcome;
+    };
+    let mut verified = match VerifiedPresentationOutcome::try_from(outcome) {
+        Ok(verified) => verified,
+        Err(e) => {
+            return Outcome::Error {
+                cause: format!("the id_token could not be bound to the presentations: {e:#}"),
+            }
+        }
+    };
+    let mut report = VerificationReport::new();
+    for warning in std::mem::take(&mut verified.warnings) {
+        report.push(warning.code, warning.severity, warning.message);
+    }
+    match &verified.holder {
+        Some(holder) if id_token.binds_holder(holder) => {}
+        Some(holder) => report.fatal(
+            FindingCode::InvalidIdToken,
+            format!(
+                "the subject of the id_token is not the holder of the presentations ({holder})"
+            ),
+        ),
+        None => report.fatal(
+            FindingCode::InvalidIdToken,
+            "the holder of the presentations is unknown, so the id_token cannot be bound to them",
+        ),
+    }
+    verified.id_token = Some(id_token);
+    report.into_verified_outcome(verified)
+}
+
+/// Decode the header and claims of a compact JWS, without verifying it.
+fn decode(jwt: &str) -> Result<(Map<String, Json>, Map<String, Json>)> {
+    decode_jwt(jwt, Base64Policy::Strict).context("the id_token is not a compact JWS")
+}
+
+fn take_string(claims: &mut Map<String, Json>, claim: &str) -> Result<String> {
+    match claims.remove(claim) {
+        Some(Json::String(value)) => Ok(value),
+        Some(_) => bail!("'{claim}' of the id_token is not a string"),
+        None => bail!("the id_token is missing '{claim}'"),
+    }
+}
+
+fn take_time(claims: &mut Map<String, Json>, claim: &str) -> Result<u64> {
+    claims
+        .remove(claim)
+        .with_context(|| format!("the id_token is missing '{claim}'"))?
+        .as_u64()
+        .with_context(|| format!("'{claim}' of the id_token is not a timestamp"))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use ssi::jwk::Algorithm;
+
+    use crate::core::object::UntypedObject;
+
+    use super::*;
+
+    fn request() -> AuthorizationRequestObject {
+        serde_json::from_value::<UntypedObject>(json!({
+            "client_id": "verifier.example.com",
+            "client_id_scheme": "redirect_uri",
+            "response_type": "vp_token id_token",
+            "response_mode": "direct_post",
+            "response_uri": "https://verifier.example.com/response",
+            "nonce": "n-0S6_WzA2Mj",
+            "presentation_definition_uri": "https://verifier.example.com/pd"
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap()
+    }
+
+    fn id_token(key: &JWK, claims: Json) -> IdToken {
+        IdToken(ssi::claims::jws::encode_sign(Algorithm::ES256, &claims.to_string(), key).unwrap())
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[tokio::test]
+    async fn jwk_thumbprint_subject() {
+        let key = JWK::generate_p256();
+        let subject = key.thumbprint().unwrap();
+        let claims = |nonce: &str| {
+            json!({
+                "iss": subject,
+                "sub": subject,
+                "aud": "verifier.example.com",
+                "nonce": nonce,
+                "iat": now(),
+                "exp": now() + 600,
+                "sub_jwk": key.to_public(),
+                "email": "holder@example.com",
+            })
+        };
+
+        let verified = verify_id_token(
+            &id_token(&key, claims("n-0S6_WzA2Mj")),
+            &request(),
+            &AnyDidKeyResolver::default(),
+            SystemTime::now(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(verified.subject, subject);
+        assert_eq!(verified.claims["email"], "holder@example.com");
+        assert!(verified.binds_holder(&subject));
+        assert!(!verified.binds_holder("did:example:other"));
+
+        assert!(verify_id_token(
+            &id_token(&key, claims("other")),
+            &request(),
+            &AnyDidKeyResolver::default(),
+            SystemTime::now(),
+        )
+        .await
+        .is_err());
+
+        let other = JWK::generate_p256();
+        let mut claims = claims("n-0S6_WzA2Mj");
+        claims["sub_jwk"] = serde_json::to_value(other.to_public()).unwrap();
+        assert!(verify_id_token(
+            &id_token(&key, claims),
+            &request(),
+            &AnyDidKeyResolver::default(),
+            SystemTime::now(),
+        )
+        .await
+        .is_err());
+    }
+
+    #[test]
+    fn bind_to_holder() {
+        let id_token = VerifiedIdToken {
+            subject: "did:example:holder".into(),
+            issued_at: 0,
+            expires_at: 0,
+            claims: Map::new(),
+        };
+        let outcome = |holder: Option<&str>| {
+            VerificationReport::new()
+                .into_verified_outcome(VerifiedPresentationOutcome::new(holder.map(Into::into)))
+        };
+
+        let bound = bind_id_token(id_token.clone(), outcome(Some("did:example:holder#key-1")));
+        let verified = VerifiedPresentationOutcome::try_from(bound).unwrap();
+        assert_eq!(verified.id_token, Some(id_token.clone()));
+
+        for holder in [Some("did:example:other"), None] {
+            assert!(matches!(
+                bind_id_token(id_token.clone(), outcome(holder)),
+                Outcome::Failure { .. }
+            ));
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::{fmt::Debug, time::Duration};
+
+use crate::core::{credential_format::ClaimFormatDesignation, response::AuthorizationResponse};
+
+use super::{
+    report::FindingCode,
+    session::{Outcome, Session},
+};
+
+/// Callbacks invoked by the [Verifier](super::Verifier) as sessions progress, to record metrics.
+///
+/// Every callback does nothing by default. Callbacks are invoked inline, so implementations should
+/// not block. With the `metrics` feature, [MetricsRecorder] records them with the `metrics` crate.
+pub trait VerifierMetrics: Debug + Send + Sync {
+    /// A session was created, and its request built.
+    fn session_created(&self, _session: &Session) {}
+
+    /// The wallet retrieved the request of a session by reference.
+    fn request_retrieved(&self, _session: &Session) {}
+
+    /// The wallet submitted a response for a session.
+    fn response_received(&self, _session: &Session) {}
+
+    /// A response was rejected by the verifier before it was validated, e.g. because of a nonce
+    /// mismatch or an expired session.
+    fn response_rejected(&self, _session: &Session, _code: &FindingCode) {}
+
+    /// A response was validated. The `formats` are those of the presentation submission or of the
+    /// DCQL query, and are empty for encrypted or signed responses.
+    fn verification_completed(
+        &self,
+        _session: &Session,
+        _formats: &[ClaimFormatDesignation],
+        _outcome: &Outcome,
+        _elapsed: Duration,
+    ) {
+    }
+}
+
+/// The formats presented in a response, if they can be read without verifying it.
+///
+/// The formats of a DCQL-shaped `vp_token` are those of the credential queries of the session.
+pub(crate) fn presented_formats(
+    session: &Session,
+    response: &AuthorizationResponse,
+) -> Vec<ClaimFormatDesignation> {
+    let presented: Vec<ClaimFormatDesignation> = match response {
+        AuthorizationResponse::Unencoded(response) => response
+            .presentation_submission()
+            .descriptor_map()
+            .iter()
+            .map(|descriptor| descriptor.format().clone())
+            .collect(),
+        AuthorizationResponse::Dcql(response) => response
+            .vp_token()
+            .iter()
+            .filter_map(|(id, _)| session.dcql_query.as_ref()?.credential(id))
+            .map(|credential| ClaimFormatDesignation::from(credential.format.as_str()))
+            .collect(),
+        AuthorizationResponse::Jwt(_) => return Vec::new(),
+    };
+    let mut formats: Vec<ClaimFormatDesignation> = Vec::new();
+    for format in presented {
+        if !formats.contains(&format) {
+            formats.push(format);
+        }
+    }
+    formats
+}
+
+/// The label of an outcome in metrics.
+pub fn outcome_label(outcome: &Outcome) -> &'static str {
+    match outcome {
+        Outcome::Success { .. } => "success",
+        Outcome::Failure { .. } => "failure",
+        Outcome::Error { .. } => "error",
+    }
+}
+
+/// The label of a finding code in metrics.
+pub fn finding_label(code: &FindingCode) -> String {
+    match serde_json::to_value(code) {
+        Ok(serde_json::Value::String(label)) => label,
+        _ => "other".into(),
+    }
+}
+
+/// Records [VerifierMetrics] with the `metrics` crate:
+///
+/// - `oid4vp_verifier_sessions_created_total`
+/// - `oid4vp_verifier_requests_retrieved_total`
+/// - `oid4vp_verifier_responses_received_total`
+/// - `oid4vp_verifier_responses_rejected_total`, by `reason`
+/// - `oid4vp_verifier_verifications_total`, by `outcome`
+/// - `oid4vp_verifier_verification_seconds`, by `format` and `outcome`
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsRecorder;
+
+#[cfg(feature = "metrics")]
+impl VerifierMetrics for MetricsRecorder {
+    fn session_created(&self, _session: &Session) {
+        metrics::counter!("oid4vp_verifier_sessions_created_total").increment(1);
+    }
+
+    fn request_retrieved(&self, _session: &Session) {
+        metrics::counter!("oid4vp_verifier_requests_retrieved_total").increment(1);
+    }
+
+    fn response_received(&self, _session: &Session) {
+        metrics::counter!("oid4vp_verifier_responses_received_total").increment(1);
+    }
+
+    fn response_rejected(&self, _session: &Session, code: &FindingCode) {
+        metrics::counter!("oid4vp_verifier_responses_rejected_total", "reason" => finding_label(code))
+            .increment(1);
+    }
+
+    fn verification_completed(
+        &self,
+        _session: &Session,
+        formats: &[ClaimFormatDesignation],
+        outcome: &Outcome,
+        elapsed: Duration,
+    ) {
+        let outcome = outcome_label(outcome);
+        metrics::counter!("oid4vp_verifier_verifications_total", "outcome" => outcome).increment(1);
+        let unknown = [ClaimFormatDesignation::Other("unknown".into())];
+        let formats = if formats.is_empty() {
+            &unknown[..]
+        } else {
+            formats
+        };
+        for format in formats {
+            metrics::histogram!(
+                "oid4vp_verifier_verification_seconds",
+                "format" => String::from(format.clone()),
+                "outcome" => outcome
+            )
+            .record(elapsed.as_secs_f64());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::verifier::session::test_session;
+
+    use super::*;
+
+    #[test]
+    fn labels() {
+        let session = test_session(json!({
+            "dcql_query": { "credentials": [
+                { "id": "pid", "format": "dc+sd-jwt" },
+                { "id": "mdl", "format": "mso_mdoc" }
+            ] }
+        }));
+        let response = AuthorizationResponse::from_x_www_form_urlencoded(
+            serde_urlencoded::to_string([
+                ("vp_token", json!(["a", "b", "c"]).to_string()),
+                (
+                    "presentation_submission",
+                    json!({
+                        "id": "67e55044-10b1-426f-9247-bb680e5fe0c8",
+                        "definition_id": "definition",
+                        "descriptor_map": [
+                            { "id": "a", "format": "jwt_vp_json", "path": "$[0]" },
+                            { "id": "b", "format": "mso_mdoc", "path": "$[1]" },
+                            { "id": "c", "format": "jwt_vp_json", "path": "$[2]" }
+                        ]
+                    })
+                    .to_string(),
+                ),
+            ])
+            .unwrap()
+            .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            presented_formats(&session, &response),
+            [
+                ClaimFormatDesignation::JwtVpJson,
+                ClaimFormatDesignation::MsoMDoc
+            ]
+        );
+
+        let response = AuthorizationResponse::from_x_www_form_urlencoded(
+            serde_urlencoded::to_string([(
+                "vp_token",
+                json!({ "mdl": "mdoc", "pid": "sd-jwt" }).to_string(),
+            )])
+            .unwrap()
+            .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            presented_formats(&session, &response),
+            [
+                ClaimFormatDesignation::MsoMDoc,
+                ClaimFormatDesignation::from("dc+sd-jwt")
+            ]
+        );
+
+        assert_eq!(finding_label(&FindingCode::NonceMismatch), "nonce_mismatch");
+        assert_eq!(finding_label(&FindingCode::Other("custom".into())), "other");
+        assert_eq!(
+            outcome_label(&Outcome::Failure {
+                reason: "invalid".into()
+            }),
+            "failure"
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value as Json};
+
+use crate::core::{dcql_query::DcqlQuery, presentation_definition::PresentationDefinition};
+
+use super::session::Session;
+
+/// The claims that a request asked for, by input descriptor (or DCQL credential query), to strip
+/// a [VerifiedPresentationOutcome](super::outcome::VerifiedPresentationOutcome) down to them with
+/// [minimize](super::outcome::VerifiedPresentationOutcome::minimize).
+///
+/// Claims are identified by their path within the claims of a
+/// [VerifiedCredential](super::outcome::VerifiedCredential), i.e. relative to the
+/// `credentialSubject` for W3C VCs. A path that selects array elements, or every member of an
+/// object, selects the whole claim it is in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestedClaims(BTreeMap<String, Vec<Vec<String>>>);
+
+impl RequestedClaims {
+    /// The claims requested by the constraint fields of the input descriptors.
+    ///
+    /// Fields are requested by JSONPath, in dot (`$.credentialSubject.given_name`) or bracket
+    /// (`$['given_name']`) notation. Paths that cannot be interpreted are ignored.
+    pub fn from_presentation_definition(presentation_definition: &PresentationDefinition) -> Self {
+        Self(
+            presentation_definition
+                .input_descriptors()
+                .iter()
+                .map(|input_descriptor| {
+                    let paths = input_descriptor
+                        .constraints()
+                        .fields()
+                        .iter()
+                        .flat_map(|field| field.path().iter())
+                        .filter_map(|path| claim_path(path))
+                        .collect();
+                    (input_descriptor.id().to_owned(), paths)
+                })
+                .collect(),
+        )
+    }
+
+    /// The claims requested by the credential queries of a DCQL query. A credential query
+    /// without `claims` requests no claims.
+    pub fn from_dcql_query(dcql_query: &DcqlQuery) -> Self {
+        Self(
+            dcql_query
+                .credentials()
+                .iter()
+                .map(|credential| {
+                    let paths = credential
+                        .claims
+                        .iter()
+                        .flatten()
+                        .map(|claim| {
+                            claim
+                                .path
+                                .iter()
+                                .map_while(|segment| segment.as_str().map(ToOwned::to_owned))
+                                .collect()
+                        })
+                        .collect();
+                    (credential.id.clone(), paths)
+                })
+                .collect(),
+        )
+    }
+
+    /// The claims requested by the presentation definition or DCQL query of a session.
+    pub fn for_session(session: &Session) -> Self {
+        match (&session.presentation_definition, &session.dcql_query) {
+            (Some(presentation_definition), _) => {
+                Self::from_presentation_definition(presentation_definition)
+            }
+            (None, Some(dcql_query)) => Self::from_dcql_query(dcql_query),
+            (None, None) => Self::default(),
+        }
+    }
+
+    /// Keep only the requested claims of a credential presented for an input descriptor. No
+    /// claims are kept for an input descriptor that was not requested.
+    pub fn retain(
+        &self,
+        input_descriptor_id: &str,
+        claims: &Map<String, Json>,
+    ) -> Map<String, Json> {
+        let Some(paths) = self.0.get(input_descriptor_id) else {
+            return Map::new();
+        };
+        let paths: Vec<&[String]> = paths.iter().map(Vec::as_slice).collect();
+        retain_paths(claims, &paths)
+    }
+}
+
+fn retain_paths(claims: &Map<String, Json>, paths: &[&[String]]) -> Map<String, Json> {
+    claims
+        .iter()
+        .filter_map(|(name, value)| {
+            let nested: Vec<&[String]> = paths
+                .iter()
+                .filter_map(|path| match path.split_first() {
+                    Some((first, rest)) if first == name => Some(rest),
+                    _ => None,
+                })
+                .collect();
+            if nested.is_empty() {
+                return None;
+            }
+            match value {
+                Json::Object(object) if nested.iter().all(|rest| !rest.is_empty()) => {
+                    let object = retain_paths(object, &nested);
+                    (!object.is_empty()).then(|| (name.clone(), Json::Object(object)))
+                }
+                _ => Some((name.clone(), value.clone())),
+            }
+        })
+        .collect()
+}
+
+/// The path of a claim within the claims of a [VerifiedCredential](super::outcome::VerifiedCredential),
+/// from the JSONPath of a constraint field: the segments after the `credentialSubject`, if any,
+/// up to the first wildcard or array index.
+fn claim_path(path: &str) -> Option<Vec<String>> {
+    let mut rest = path.trim().strip_prefix('$')?;
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix("['") {
+            let (name, r) = r.split_once("']")?;
+            segments.push(name);
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix('[') {
+            let (_, r) = r.split_once(']')?;
+            segments.push("*");
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix('.') {
+            let end = r.find(['.', '[']).unwrap_or(r.len());
+            segments.push(&r[..end]);
+            rest = &r[end..];
+        } else {
+            return None;
+        }
+    }
+    let start = segments
+        .iter()
+        .rposition(|segment| *segment == "credentialSubject")
+        .map_or(0, |index| index + 1);
+    let path: Vec<String> = segments[start..]
+        .iter()
+        .take_while(|segment| !segment.is_empty() && **segment != "*")
+        .map(|segment| segment.to_string())
+        .collect();
+    (!path.is_empty()).then_some(path)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn claim_paths() {
+        assert_eq!(
+            claim_path("$.verifiableCredential[0].credentialSubject.address.country"),
+            Some(vec!["address".into(), "country".into()])
+        );
+        assert_eq!(
+            claim_path("$['given_name']"),
+            Some(vec!["given_name".into()])
+        );
+        assert_eq!(
+            claim_path("$.credentialSubject.degrees[*].type"),
+            Some(vec!["degrees".into()])
+        );
+        assert_eq!(claim_path("$.credentialSubject"), None);
+        assert_eq!(claim_path("credentialSubject.id"), None);
+    }
+
+    #[test]
+    fn retain_requested_claims() {
+        let presentation_definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "pd",
+            "input_descriptors": [{
+                "id": "id-card",
+                "constraints": { "fields": [
+                    { "path": ["$.credentialSubject.address.country"] },
+                    { "path": ["$.vc.credentialSubject.given_name", "$.given_name"] }
+                ]}
+            }]
+        }))
+        .unwrap();
+        let requested = RequestedClaims::from_presentation_definition(&presentation_definition);
+
+        let claims = json!({
+            "given_name": "Alice",
+            "family_name": "Doe",
+            "address": { "country": "FR", "locality": "Paris" }
+        });
+        let claims = claims.as_object().unwrap();
+        assert_eq!(
+            Json::Object(requested.retain("id-card", claims)),
+            json!({ "given_name": "Alice", "address": { "country": "FR" } })
+        );
+        assert!(requested.retain("other", claims).is_empty());
+
+        let dcql_query: DcqlQuery = serde_json::from_value(json!({
+            "credentials": [{
+                "id": "id-card",
+                "format": "dc+sd-jwt",
+                "claims": [{ "path": ["address"] }]
+            }]
+        }))
+        .unwrap();
+        assert_eq!(
+            Json::Object(RequestedClaims::from_dcql_query(&dcql_query).retain("id-card", claims)),
+            json!({ "address": { "country": "FR", "locality": "Paris" } })
+        );
+    }
+}
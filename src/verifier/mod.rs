@@ -1,35 +1,166 @@
-use std::{fmt::Debug, future::Future, pin::Pin, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
 use anyhow::{bail, Context, Result};
 use client::Client;
+use futures::future::{select, Either};
+use futures_timer::Delay;
 use request_builder::RequestBuilder;
-use session::{Outcome, Session, SessionStore, Status};
+use serde_json::Value as Json;
+use session::{DuplicateResponse, Outcome, Session, SessionState, SessionStore, Status};
+use tokio::sync::broadcast;
+use tracing::{
+    field::{display, Empty},
+    instrument, warn, Span,
+};
 use url::Url;
 use uuid::Uuid;
 
 use crate::core::{
-    object::{TypedParameter, UntypedObject},
-    response::AuthorizationResponse,
+    authorization_request::{
+        self,
+        parameters::{
+            ClientId, ClientIdScheme, Nonce, ResponseMode, ResponseType, ResponseUri, State,
+        },
+    },
+    credential_format::ClaimFormatDesignation,
+    dcql_query::DcqlQuery,
+    events::{EventSubscriber, LifecycleEvent, LifecycleEventKind},
+    jwe::ecdh_es::KeyAgreementCurve,
+    metadata::{signed::MetadataTrustAnchors, WalletMetadata},
+    object::{
+        registry::{ParameterLocation, ParameterRegistry},
+        TypedParameter, UntypedObject,
+    },
+    presentation_definition::PresentationDefinition,
+    presentation_submission::DescriptorMap,
+    response::{
+        jarm::JarmValidator,
+        parameters::{IdToken, MdocGeneratedNonce},
+        parser::{VpTokenParser, VpTokenParserRegistry},
+        AuthorizationResponse, JwtAuthorizationResponse, PostRedirection,
+    },
+    spans,
+    util::Base64Policy,
 };
 
+use archive::{PresentationArchive, PresentationRecord, RetentionPolicy};
+use audit::{AuditCapture, AuditRedaction, AuditSink};
 use by_reference::ByReference;
+pub use by_reference::DEFAULT_REQUEST_URI_TTL;
+use guard::{
+    Endpoint, GuardDecision, RemoteMetadata, RequestContext, RequestGuard, RequestRejected,
+};
+use id_token::{
+    bind_id_token, verify_id_token, AnyDidKeyResolver, IdTokenKeyResolver, VerifiedIdToken,
+};
+use metrics::{presented_formats, VerifierMetrics};
+use minimization::RequestedClaims;
+use nonce::{key_binding_claims, presentation_nonce};
+use notifier::ResponseNotifier;
+use outcome::VerifiedPresentationOutcome;
+use policy::TrustPolicy;
+use report::{FindingCode, VerificationReport};
+use response_encryption::ResponseEncryptionKeys;
+use scope::ScopeRegistry;
+use stateless::{definition_hash, NoSessionStore, SeenTokenStore, StateKey, StatelessState};
+use template::{RequestTemplate, SessionOverrides};
+use tenant::Tenant;
+use validator::{PresentationVerifier, ResponseValidator};
+use vp_token::{verify_presentations, DEFAULT_CONCURRENCY};
 
+pub mod archive;
+pub mod attestation;
+pub mod audit;
 mod by_reference;
 pub mod client;
+pub mod endpoint_error;
+pub mod guard;
+pub mod id_token;
+pub mod metrics;
+pub mod minimization;
+pub mod nonce;
+pub mod notifier;
+pub mod outcome;
+pub mod policy;
+#[cfg(feature = "qrcode")]
+pub mod qr;
+pub mod report;
 pub mod request_builder;
 pub mod request_signer;
+pub mod response_code;
+pub mod response_encryption;
+pub mod scope;
 pub mod session;
+pub mod stateless;
+pub mod template;
+pub mod tenant;
+pub mod validator;
+pub mod vp_token;
 
 /// An OpenID4VP verifier, also known as the client.
+///
+/// A verifier is cheap to clone, and `Send + Sync`: clones share their configuration and stores, so
+/// a verifier can be used directly as the shared state of a web server.
 #[derive(Debug, Clone)]
 pub struct Verifier {
+    inner: Arc<VerifierInner>,
+}
+
+#[derive(Debug)]
+struct VerifierInner {
     client: Arc<dyn Client + Send + Sync>,
     default_request_params: UntypedObject,
     pass_by_reference: ByReference,
     session_store: Arc<dyn SessionStore + Send + Sync>,
     submission_endpoint: Url,
+    enforce_state: bool,
+    presentation_definition: Option<PresentationDefinition>,
+    dcql_query: Option<DcqlQuery>,
+    response_encryption_curve: KeyAgreementCurve,
+    response_encryption_keys: Option<Arc<ResponseEncryptionKeys>>,
+    response_redirect_uri: Option<Url>,
+    request_uri_ttl: Duration,
+    wallet_metadata: WalletMetadata,
+    metadata_trust_anchors: Option<MetadataTrustAnchors>,
+    preferred_authorization_endpoints: Vec<Url>,
+    response_validator: Option<Arc<dyn ResponseValidator>>,
+    presentation_verifier: Option<Arc<dyn PresentationVerifier>>,
+    jarm_validator: Option<JarmValidator>,
+    verification_concurrency: usize,
+    session_ttl: Option<Duration>,
+    scopes: ScopeRegistry,
+    parameter_registry: ParameterRegistry,
+    vp_token_parsers: VpTokenParserRegistry,
+    base64_policy: Base64Policy,
+    trust_policy: Option<Arc<TrustPolicy>>,
+    metrics: Option<Arc<dyn VerifierMetrics>>,
+    notifier: Option<Arc<dyn ResponseNotifier>>,
+    event_subscriber: Option<Arc<dyn EventSubscriber>>,
+    archive: Option<Arc<dyn PresentationArchive>>,
+    archive_retention: Option<Duration>,
+    minimize_archived_claims: bool,
+    retention_policy: Option<RetentionPolicy>,
+    audit: Option<(Arc<dyn AuditSink>, AuditRedaction)>,
+    state_key: Option<Arc<StateKey>>,
+    seen_tokens: Option<Arc<dyn SeenTokenStore>>,
+    guard: Option<Arc<dyn RequestGuard>>,
+    id_token_resolver: Arc<dyn IdTokenKeyResolver>,
+    tenants: BTreeMap<String, Tenant>,
+    templates: BTreeMap<String, Arc<RequestTemplate>>,
+    /// The UUIDs of the sessions completed by this verifier, see [Verifier::wait_for_result].
+    completions: broadcast::Sender<Uuid>,
 }
 
+/// How many completions are buffered for each [Verifier::wait_for_result] call.
+const COMPLETIONS_CAPACITY: usize = 64;
+
 impl Verifier {
     /// Build a new verifier.
     pub fn builder() -> VerifierBuilder {
@@ -41,6 +172,244 @@ impl Verifier {
         RequestBuilder::new(self)
     }
 
+    /// Begin a presentation session with the default presentation definition (see
+    /// [VerifierBuilder::with_presentation_definition]) or DCQL query (see
+    /// [VerifierBuilder::with_dcql_query]) and the default request parameters.
+    ///
+    /// The request is built for the wallet metadata set with
+    /// [VerifierBuilder::with_wallet_metadata], or the static `openid4vp:` metadata. Use
+    /// [Verifier::build_authorization_request] to customise a single request.
+    ///
+    /// ## Returns
+    /// - URL that the application frontend should use to drive the user to their wallet application.
+    /// - UUID of the session, to receive the response and poll for its status.
+    pub async fn begin_session(&self) -> Result<(Url, Uuid)> {
+        let (uuid, url) = self
+            .with_default_query(self.build_authorization_request(), None)?
+            .build(self.inner.wallet_metadata.clone())
+            .await?;
+        Ok((url, uuid))
+    }
+
+    /// Begin building a new authorization request on behalf of a [Tenant].
+    pub fn build_tenant_authorization_request(
+        &self,
+        tenant_id: &str,
+    ) -> Result<RequestBuilder<'_>> {
+        RequestBuilder::for_tenant(self, tenant_id)
+    }
+
+    /// Begin a presentation session on behalf of a [Tenant], with its presentation definition (or
+    /// the verifier's) and its default request parameters.
+    ///
+    /// See [Verifier::begin_session].
+    pub async fn begin_tenant_session(&self, tenant_id: &str) -> Result<(Url, Uuid)> {
+        let builder = self.build_tenant_authorization_request(tenant_id)?;
+        let presentation_definition = self
+            .inner
+            .tenants
+            .get(tenant_id)
+            .and_then(|tenant| tenant.presentation_definition.clone());
+        let (uuid, url) = self
+            .with_default_query(builder, presentation_definition)?
+            .build(self.inner.wallet_metadata.clone())
+            .await?;
+        Ok((url, uuid))
+    }
+
+    /// The registered [Tenant]s, by identifier.
+    pub fn tenants(&self) -> &BTreeMap<String, Tenant> {
+        &self.inner.tenants
+    }
+
+    /// Begin a presentation session from a [RequestTemplate] registered with
+    /// [VerifierBuilder::with_request_template], with only the [SessionOverrides] of this session
+    /// on top of the template.
+    ///
+    /// See [Verifier::begin_session].
+    pub async fn begin_template_session(
+        &self,
+        template_id: &str,
+        overrides: SessionOverrides,
+    ) -> Result<(Url, Uuid)> {
+        let (uuid, url) = RequestBuilder::from_template(self, template_id, overrides)?
+            .build(self.inner.wallet_metadata.clone())
+            .await?;
+        Ok((url, uuid))
+    }
+
+    /// Begin a presentation session whose request only carries a `scope`, registered with
+    /// [VerifierBuilder::with_scope], instead of the presentation definition.
+    ///
+    /// The response is validated against the presentation definition registered for the scope.
+    pub async fn begin_scope_session(&self, scope: &str) -> Result<(Url, Uuid)> {
+        let (uuid, url) = self
+            .build_authorization_request()
+            .with_scope(scope)
+            .build(self.inner.wallet_metadata.clone())
+            .await?;
+        Ok((url, uuid))
+    }
+
+    /// The scopes registered with [VerifierBuilder::with_scope].
+    pub fn scopes(&self) -> &ScopeRegistry {
+        &self.inner.scopes
+    }
+
+    /// The parameters registered with [VerifierBuilder::with_parameter_registry].
+    pub fn parameter_registry(&self) -> &ParameterRegistry {
+        &self.inner.parameter_registry
+    }
+
+    /// The parsers that descriptor map paths of responses are resolved with, see
+    /// [VerifierBuilder::with_vp_token_parser].
+    pub fn vp_token_parsers(&self) -> &VpTokenParserRegistry {
+        &self.inner.vp_token_parsers
+    }
+
+    /// How the base64url values of responses are decoded, see
+    /// [VerifierBuilder::with_base64_policy]. Validators decode e.g. `mso_mdoc` presentations
+    /// with [VpTokenItem::decode_base64](crate::core::response::parameters::VpTokenItem::decode_base64)
+    /// and this policy.
+    pub fn base64_policy(&self) -> Base64Policy {
+        self.inner.base64_policy
+    }
+
+    /// The wallet metadata requests are built for by default, see
+    /// [VerifierBuilder::with_wallet_metadata].
+    pub fn wallet_metadata(&self) -> &WalletMetadata {
+        &self.inner.wallet_metadata
+    }
+
+    /// The endpoint responses are submitted to, see [VerifierBuilder::with_submission_endpoint].
+    pub fn submission_endpoint(&self) -> &Url {
+        &self.inner.submission_endpoint
+    }
+
+    /// The base URL of the `request_uri` of requests passed by reference, if they are, see
+    /// [VerifierBuilder::by_reference].
+    pub fn request_uri_base(&self) -> Option<&Url> {
+        match &self.inner.pass_by_reference {
+            ByReference::True { at } => Some(at),
+            ByReference::False => None,
+        }
+    }
+
+    /// Receive an authorization response submitted by the wallet to the submission endpoint of a
+    /// session, validate it with the [ResponseValidator] (see
+    /// [VerifierBuilder::with_response_validator]), and return the outcome.
+    ///
+    /// The `body` is the `application/x-www-form-urlencoded` body of the wallet's request. As with
+    /// [Verifier::verify_response], the session status is updated with the outcome.
+    ///
+    /// A session only accepts one response. A byte-identical retry of a submission that has been
+    /// processed (e.g. by a wallet that lost the connection) gets the original outcome, so that the
+    /// wallet is sent the same [redirect](Verifier::post_redirection). Any other submission fails
+    /// with a [DuplicateResponse] error.
+    pub async fn receive_response(&self, session_id: Uuid, body: &[u8]) -> Result<Outcome> {
+        self.receive_response_from(session_id, body, &RemoteMetadata::default())
+            .await
+    }
+
+    /// Receive an authorization response, see [Verifier::receive_response], from a caller
+    /// described by `remote`, which is checked by the [RequestGuard] (see
+    /// [VerifierBuilder::with_request_guard]) first.
+    #[instrument(skip_all, fields(session = %session_id))]
+    pub async fn receive_response_from(
+        &self,
+        session_id: Uuid,
+        body: &[u8],
+        remote: &RemoteMetadata,
+    ) -> Result<Outcome> {
+        let Some(response_validator) = self.inner.response_validator.clone() else {
+            bail!("response validator is required, see `with_response_validator`")
+        };
+        let session = self.inner.session_store.get_session(session_id).await?;
+        self.check_guard(Endpoint::ResponseUri, &session, remote)
+            .await?;
+
+        let digest = session::response_digest(body);
+        if self
+            .inner
+            .session_store
+            .record_response_digest(session_id, digest.clone())
+            .await?
+        {
+            let session = self.inner.session_store.get_session(session_id).await?;
+            let identical = session
+                .response_digest
+                .as_ref()
+                .is_some_and(|expected| response_code::matches(expected, &digest));
+            match session.status {
+                Status::Complete(outcome) if identical => return Ok(outcome),
+                _ => bail!(DuplicateResponse {
+                    session: session_id,
+                    identical,
+                }),
+            }
+        }
+        let authorization_response = AuthorizationResponse::from_x_www_form_urlencoded(body)
+            .context("failed to parse the authorization response")?;
+
+        self.verify_response(session_id, authorization_response, |session, response| {
+            Box::pin(async move { response_validator.validate(session, response).await })
+        })
+        .await?;
+
+        match self.poll_status(session_id).await? {
+            Status::Complete(outcome) => Ok(outcome),
+            status => bail!("unexpected session status after verification: {status:?}"),
+        }
+    }
+
+    /// The redirect to return to the wallet, as the JSON body of the response to its POSTed
+    /// authorization response, if a response redirect URI is configured (see
+    /// [VerifierBuilder::with_response_redirect_uri]).
+    ///
+    /// The redirect carries the response code of the session, and is only available once the
+    /// response has been received.
+    pub async fn post_redirection(&self, session_id: Uuid) -> Result<Option<PostRedirection>> {
+        let session = self.inner.session_store.get_session(session_id).await?;
+        if session.status < Status::ReceivedResponse {
+            bail!("no response has been received for this session")
+        }
+        let (Some(redirect_uri), Some(response_code)) =
+            (&self.inner.response_redirect_uri, &session.response_code)
+        else {
+            return Ok(None);
+        };
+        Ok(Some(PostRedirection {
+            redirect_uri: response_code::redirect_uri(redirect_uri, response_code),
+        }))
+    }
+
+    /// Exchange the response code the wallet redirected the user with (see
+    /// [Verifier::post_redirection]) for the outcome of the session.
+    ///
+    /// A response code can only be exchanged once, whether or not the exchange succeeds, and only
+    /// once the response has been verified.
+    pub async fn exchange_response_code(
+        &self,
+        session_id: Uuid,
+        response_code: &str,
+    ) -> Result<Outcome> {
+        let Status::Complete(outcome) = self.poll_status(session_id).await? else {
+            bail!("the response of this session has not been verified yet")
+        };
+        let Some(expected) = self
+            .inner
+            .session_store
+            .take_response_code(session_id)
+            .await?
+        else {
+            bail!("the response code of this session was already exchanged")
+        };
+        if !response_code::matches(&expected, response_code) {
+            bail!("invalid response code")
+        }
+        Ok(outcome)
+    }
+
     /// Retrieve the current status of an authorization request.
     ///
     /// This should be triggered by a request from the application frontend.
@@ -48,17 +417,81 @@ impl Verifier {
     /// ## Returns
     /// The status of the authorization request.
     pub async fn poll_status(&self, uuid: Uuid) -> Result<Status> {
-        self.session_store
+        self.inner
+            .session_store
             .get_session(uuid)
             .await
             .map(|session| session.status)
     }
 
+    /// Wait until the response of a session has been verified, or until `timeout` elapses, e.g. to
+    /// hold a request of the frontend open instead of polling with [Verifier::poll_status].
+    ///
+    /// The waiter is woken when the session completes, rather than polling the session store.
+    /// Completions are only notified within a process: with several verifiers sharing a session
+    /// store, a session completed by another verifier is only noticed once `timeout` elapses.
+    ///
+    /// ## Returns
+    /// The outcome of the session, or `None` if it did not complete in time.
+    pub async fn wait_for_result(
+        &self,
+        session_id: Uuid,
+        timeout: Duration,
+    ) -> Result<Option<Outcome>> {
+        // Subscribe before reading the status, so that no completion is missed.
+        let mut completions = self.inner.completions.subscribe();
+        let mut deadline = Delay::new(timeout);
+        loop {
+            if let Status::Complete(outcome) = self.poll_status(session_id).await? {
+                return Ok(Some(outcome));
+            }
+            let completion = Box::pin(next_completion(&mut completions, session_id));
+            if let Either::Right(_) = select(completion, &mut deadline).await {
+                break;
+            }
+        }
+        match self.poll_status(session_id).await? {
+            Status::Complete(outcome) => Ok(Some(outcome)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Retrieve the [VerifiedPresentationOutcome] of a session, once its response has been
+    /// verified by a [ResponseValidator] that produces one (see
+    /// [VerificationReport::into_verified_outcome]).
+    ///
+    /// Fails if the session is not complete, or the response did not pass verification.
+    pub async fn verified_outcome(&self, uuid: Uuid) -> Result<VerifiedPresentationOutcome> {
+        match self.poll_status(uuid).await? {
+            Status::Complete(outcome) => outcome.try_into(),
+            status => bail!("the session is not complete: {status:?}"),
+        }
+    }
+
+    /// Retrieve the lifecycle state of a session, for a frontend to poll (or stream) while the
+    /// request is displayed.
+    ///
+    /// Once the response has been verified, the state includes the outcome. A session that has not
+    /// completed within the [session TTL](VerifierBuilder::with_session_ttl) is reported as
+    /// [SessionState::Expired].
+    pub async fn session_state(&self, uuid: Uuid) -> Result<SessionState> {
+        let session = self.inner.session_store.get_session(uuid).await?;
+        let expired = self
+            .inner
+            .session_ttl
+            .is_some_and(|ttl| session.is_expired(ttl, SystemTime::now()));
+        match session.status {
+            Status::Complete(_) => Ok(session.status.into()),
+            _ if expired => Ok(SessionState::Expired),
+            status => Ok(status.into()),
+        }
+    }
+
     /// Retrieve an authorization request that was passed by-reference.
     ///
     /// This should be triggered by a request from the wallet when the verifier is configured to
     /// pass the authorization request by reference using [VerifierBuilder::by_reference]. The
-    /// wallet will make a request to `<configured-url>/<reference>`. For example:
+    /// wallet will make a request to `<configured-url>/<token>`. For example:
     ///
     /// ```ignore
     /// let url: Url = "https://verifier.example.com/some/sub/path".parse()?;
@@ -70,24 +503,72 @@ impl Verifier {
     /// ```
     ///
     /// The wallet will request the authorization request from
-    /// `GET https://verifier.example.com/some/sub/path/<reference>`.
+    /// `GET https://verifier.example.com/some/sub/path/<token>`.
+    ///
+    /// The token holds the session UUID and an unguessable secret, and can only be used once,
+    /// within the [request URI TTL](VerifierBuilder::with_request_uri_ttl) and before a response
+    /// is received, so that a bystander who photographed the QR code cannot replay it.
     ///
     /// This will update the presentation status.
     ///
     /// ## Returns
     /// The signed authorization request as a JWT.
-    pub async fn retrieve_authorization_request(&self, reference: Uuid) -> Result<String> {
+    pub async fn retrieve_authorization_request(&self, token: &str) -> Result<String> {
+        self.retrieve_authorization_request_from(token, &RemoteMetadata::default())
+            .await
+    }
+
+    /// Retrieve an authorization request that was passed by-reference, see
+    /// [Verifier::retrieve_authorization_request], for a caller described by `remote`, which is
+    /// checked by the [RequestGuard] (see [VerifierBuilder::with_request_guard]) first.
+    #[instrument(skip_all, fields(session = Empty))]
+    pub async fn retrieve_authorization_request_from(
+        &self,
+        token: &str,
+        remote: &RemoteMetadata,
+    ) -> Result<String> {
+        let (reference, secret) = by_reference::parse_token(token)?;
+        Span::current().record("session", display(reference));
         let session = self
+            .inner
             .session_store
             .get_session(reference)
             .await
             .context("failed to retrieve session")?;
+        self.check_guard(Endpoint::RequestUri, &session, remote)
+            .await?;
+        if session.status >= Status::ReceivedResponse {
+            bail!("a response was already received for this session")
+        }
+        if session.is_expired(self.inner.request_uri_ttl, SystemTime::now()) {
+            bail!("the request_uri has expired")
+        }
+        match &session.request_uri_secret {
+            Some(expected) if response_code::matches(expected, secret) => {}
+            Some(_) => bail!("invalid request_uri"),
+            None => bail!("the request was already retrieved"),
+        }
+        // Only one of concurrent retrievals takes the secret.
+        if self
+            .inner
+            .session_store
+            .take_request_uri_secret(reference)
+            .await?
+            .is_none()
+        {
+            bail!("the request was already retrieved")
+        }
         if session.status < Status::SentRequest {
-            self.session_store
+            self.inner
+                .session_store
                 .update_status(reference, Status::SentRequest)
                 .await
                 .context("failed to update session status")?;
         }
+        if let Some(metrics) = &self.inner.metrics {
+            metrics.request_retrieved(&session);
+        }
+        self.emit_event(LifecycleEventKind::RequestRetrieved, &session);
         Ok(session.authorization_request_jwt)
     }
 
@@ -111,7 +592,32 @@ impl Verifier {
     /// If using the `direct_post` response mode, the wallet will submit the authorization response
     /// to `POST https://verifier.example.com/some/sub/path/<reference>`.
     ///
+    /// If the authorization request contained a `state` parameter, and state enforcement has not
+    /// been disabled with [VerifierBuilder::enforce_state], an unencoded response must echo the
+    /// same `state` or it is rejected without calling the `validator_function`.
+    ///
+    /// The nonce of a session is single-use: a response is rejected if one was already received
+    /// for the session (with a [DuplicateResponse] error), or if the session is older than the
+    /// [session TTL](VerifierBuilder::with_session_ttl). Presentations whose nonce can be read
+    /// without verification (see [presentation_nonce]) must carry the nonce of the session,
+    /// otherwise the outcome is a failure without calling the `validator_function`.
+    ///
+    /// If the session has an ephemeral
+    /// [response_encryption_key](Session::response_encryption_key), the response must be
+    /// encrypted to it, and is decrypted before it is checked and passed to the
+    /// `validator_function`. The key is then discarded.
+    ///
     /// This will update the presentation status.
+    #[instrument(
+        skip_all,
+        fields(
+            session = %reference,
+            client_id = Empty,
+            client_id_scheme = Empty,
+            response_mode = Empty,
+            formats = Empty,
+        )
+    )]
     pub async fn verify_response<F, Fut>(
         &self,
         reference: Uuid,
@@ -122,24 +628,991 @@ impl Verifier {
         F: FnOnce(Session, AuthorizationResponse) -> Pin<Box<Fut>>,
         Fut: Future<Output = Outcome>,
     {
-        let session = self.session_store.get_session(reference).await?;
+        let mut capture = self
+            .inner
+            .audit
+            .is_some()
+            .then(|| AuditCapture::new(&authorization_response));
+        let mut session = self.inner.session_store.get_session(reference).await?;
+        spans::record_request(&Span::current(), &session.authorization_request_object);
+        if let Some(metrics) = &self.inner.metrics {
+            metrics.response_received(&session);
+        }
+        self.emit_event(LifecycleEventKind::ResponseReceived, &session);
+        let trust_policy = self.trust_policy_for(&session)?;
+
+        if !self.inner.session_store.claim_response(reference).await? {
+            bail!(DuplicateResponse {
+                session: reference,
+                identical: false,
+            })
+        }
 
-        let outcome = validator_function(session, authorization_response).await;
+        if let Some(ttl) = self.inner.session_ttl {
+            if session.is_expired(ttl, SystemTime::now()) {
+                return self
+                    .fail_session(
+                        &session,
+                        capture,
+                        FindingCode::SessionExpired,
+                        "the session has expired",
+                    )
+                    .await;
+            }
+        }
 
-        self.session_store
-            .update_status(reference, Status::Complete(outcome))
+        if session.response_encryption_key.is_some() {
+            self.inner
+                .session_store
+                .discard_response_encryption_key(reference)
+                .await?;
+        }
+
+        match self
+            .evaluate_response(
+                &mut session,
+                authorization_response,
+                validator_function,
+                trust_policy,
+                &mut capture,
+            )
+            .await
+        {
+            Ok(outcome) => self.complete_session(&session, outcome, capture).await,
+            Err((code, message)) => self.fail_session(&session, capture, code, message).await,
+        }
+    }
+
+    /// Begin a presentation session that is not kept in the [SessionStore]: its nonce, the hash of
+    /// its presentation definition (or DCQL query), its expiry and the references of its response
+    /// encryption keys are sealed with the [StateKey] of the verifier (see
+    /// [VerifierBuilder::stateless]) into the `state` of the request, and into the token at the
+    /// end of its `response_uri`.
+    ///
+    /// Any instance of the verifier sharing the key can then receive the response, see
+    /// [Verifier::verify_stateless_response]. Stateless requests carry the default presentation
+    /// definition or DCQL query and the default request parameters, and are passed by value.
+    /// Encrypted responses require static keys, see
+    /// [VerifierBuilder::with_response_encryption_keys].
+    ///
+    /// ## Returns
+    /// - URL that the application frontend should use to drive the user to their wallet application.
+    /// - UUID of the session, to correlate it with its events.
+    pub async fn begin_stateless_session(&self) -> Result<(Url, Uuid)> {
+        let (uuid, url) = self
+            .with_default_query(self.build_authorization_request().stateless(), None)?
+            .build(self.inner.wallet_metadata.clone())
+            .await?;
+        Ok((url, uuid))
+    }
+
+    /// Receive an authorization response submitted by the wallet to the submission endpoint of a
+    /// stateless session, i.e. to `<configured-url>/<token>`, and validate it with the
+    /// [ResponseValidator] (see [VerifierBuilder::with_response_validator]).
+    ///
+    /// See [Verifier::verify_stateless_response].
+    pub async fn receive_stateless_response(&self, token: &str, body: &[u8]) -> Result<Outcome> {
+        self.receive_stateless_response_from(token, body, &RemoteMetadata::default())
             .await
     }
+
+    /// Receive the authorization response of a stateless session, see
+    /// [Verifier::receive_stateless_response], from a caller described by `remote`, which is
+    /// checked by the [RequestGuard] (see [VerifierBuilder::with_request_guard]) first.
+    pub async fn receive_stateless_response_from(
+        &self,
+        token: &str,
+        body: &[u8],
+        remote: &RemoteMetadata,
+    ) -> Result<Outcome> {
+        let Some(response_validator) = self.inner.response_validator.clone() else {
+            bail!("response validator is required, see `with_response_validator`")
+        };
+        if let (Some(guard), Some(state_key)) = (&self.inner.guard, &self.inner.state_key) {
+            let state = state_key.open(token, SystemTime::now())?;
+            let context = RequestContext {
+                endpoint: Endpoint::ResponseUri,
+                session: state.session,
+                client_id: &self.inner.client.id().0,
+                tenant: None,
+                remote,
+            };
+            guard_decision(Endpoint::ResponseUri, guard.check(&context).await)?;
+        }
+        let authorization_response = AuthorizationResponse::from_x_www_form_urlencoded(body)
+            .context("failed to parse the authorization response")?;
+
+        self.verify_stateless_response(token, authorization_response, |session, response| {
+            Box::pin(async move { response_validator.validate(session, response).await })
+        })
+        .await
+    }
+
+    /// Verify the authorization response of a stateless session (see
+    /// [Verifier::begin_stateless_session]), identified by the token at the end of its
+    /// `response_uri`.
+    ///
+    /// The session is restored from the token, which must be authentic and unexpired, and from
+    /// the configuration of the verifier, whose presentation definition or DCQL query must not have
+    /// changed. The response is then checked as with [Verifier::verify_response], and must echo
+    /// the token as its `state`.
+    ///
+    /// As nothing is stored, the outcome is returned instead. Unless a [SeenTokenStore] is
+    /// configured with [VerifierBuilder::with_seen_token_store], the nonce of a stateless session
+    /// is not single-use: a response can be replayed until the session expires. With the store,
+    /// any response after the first one is rejected with a [DuplicateResponse] error.
+    #[instrument(
+        skip_all,
+        fields(
+            session = Empty,
+            client_id = Empty,
+            client_id_scheme = Empty,
+            response_mode = Empty,
+            formats = Empty,
+        )
+    )]
+    pub async fn verify_stateless_response<F, Fut>(
+        &self,
+        token: &str,
+        authorization_response: AuthorizationResponse,
+        validator_function: F,
+    ) -> Result<Outcome>
+    where
+        F: FnOnce(Session, AuthorizationResponse) -> Pin<Box<Fut>>,
+        Fut: Future<Output = Outcome>,
+    {
+        let Some(state_key) = &self.inner.state_key else {
+            bail!("this verifier is not stateless, see `stateless`")
+        };
+        let mut capture = self
+            .inner
+            .audit
+            .is_some()
+            .then(|| AuditCapture::new(&authorization_response));
+        let state = state_key.open(token, SystemTime::now())?;
+        let mut session = self.restore_session(token, &state)?;
+        Span::current().record("session", display(session.uuid));
+        if let Some(seen_tokens) = &self.inner.seen_tokens {
+            if seen_tokens
+                .mark_seen(session.uuid, state.expires_at())
+                .await
+                .context("failed to record the stateless session as seen")?
+            {
+                bail!(DuplicateResponse {
+                    session: session.uuid,
+                    identical: false,
+                })
+            }
+        }
+        spans::record_request(&Span::current(), &session.authorization_request_object);
+        if let Some(metrics) = &self.inner.metrics {
+            metrics.response_received(&session);
+        }
+        self.emit_event(LifecycleEventKind::ResponseReceived, &session);
+        let trust_policy = self.trust_policy_for(&session)?;
+
+        if let AuthorizationResponse::Jwt(response) = &authorization_response {
+            let kid = response.protected_header()?.get("kid").cloned();
+            if !state.response_encryption_kids.is_empty()
+                && !state
+                    .response_encryption_kids
+                    .iter()
+                    .any(|expected| kid.as_ref().and_then(Json::as_str) == Some(expected))
+            {
+                let message = "the response was not encrypted to a key of the session";
+                self.report_rejection(&session, &FindingCode::InvalidEncryption);
+                self.session_completed(
+                    &session,
+                    &rejection(FindingCode::InvalidEncryption, message),
+                    capture,
+                )
+                .await?;
+                bail!(message)
+            }
+        }
+
+        match self
+            .evaluate_response(
+                &mut session,
+                authorization_response,
+                validator_function,
+                trust_policy,
+                &mut capture,
+            )
+            .await
+        {
+            Ok(outcome) => {
+                self.session_completed(&session, &outcome, capture).await?;
+                Ok(outcome)
+            }
+            Err((code, message)) => {
+                self.report_rejection(&session, &code);
+                self.session_completed(&session, &rejection(code, message.clone()), capture)
+                    .await?;
+                bail!(message)
+            }
+        }
+    }
+}
+
+impl Verifier {
+    /// Decrypt and check a response, then validate it with the `validator_function` and the
+    /// [TrustPolicy], if any.
+    ///
+    /// Returns the outcome, or why the response was rejected before it was validated.
+    async fn evaluate_response<F, Fut>(
+        &self,
+        session: &mut Session,
+        authorization_response: AuthorizationResponse,
+        validator_function: F,
+        trust_policy: Option<Arc<TrustPolicy>>,
+        capture: &mut Option<AuditCapture>,
+    ) -> Result<Outcome, (FindingCode, String)>
+    where
+        F: FnOnce(Session, AuthorizationResponse) -> Pin<Box<Fut>>,
+        Fut: Future<Output = Outcome>,
+    {
+        if let AuthorizationResponse::Jwt(response) = &authorization_response {
+            session.mdoc_generated_nonce = check_mdoc_generated_nonce(session, response)
+                .map_err(|e| (FindingCode::NonceMismatch, format!("{e:#}")))?;
+        }
+
+        let encrypted = matches!(authorization_response, AuthorizationResponse::Jwt(_));
+        let authorization_response = self
+            .decrypt_response(session, authorization_response)
+            .map_err(|e| (FindingCode::InvalidEncryption, format!("{e:#}")))?;
+        if let Some(capture) = capture {
+            capture.decrypted(&authorization_response);
+        }
+
+        if let (true, Some(validator)) = (encrypted, &self.inner.jarm_validator) {
+            check_jarm_claims(validator, &authorization_response)
+                .map_err(|e| (FindingCode::InvalidSubmission, format!("{e:#}")))?;
+        }
+
+        if let Some(draft) = session.draft {
+            draft
+                .check_response(&authorization_response)
+                .map_err(|e| (FindingCode::InvalidSubmission, e.to_string()))?;
+        }
+
+        check_nonce(session, &authorization_response, self.inner.base64_policy)
+            .map_err(|e| (FindingCode::NonceMismatch, e.to_string()))?;
+
+        check_transaction_data(session, &authorization_response, self.inner.base64_policy)
+            .map_err(|e| (FindingCode::TransactionDataMismatch, format!("{e:#}")))?;
+
+        self.check_registered_parameters(&authorization_response)
+            .map_err(|e| (FindingCode::InvalidSubmission, format!("{e:#}")))?;
+
+        self.check_descriptor_paths(&authorization_response)
+            .map_err(|e| (FindingCode::InvalidSubmission, format!("{e:#}")))?;
+
+        if self.inner.enforce_state {
+            check_state(session, &authorization_response)
+                .map_err(|e| (FindingCode::StateMismatch, e.to_string()))?;
+        }
+
+        check_dcql_query(session, &authorization_response)
+            .map_err(|e| (FindingCode::InvalidSubmission, format!("{e:#}")))?;
+
+        self.verify_presentations(session, &authorization_response)
+            .await
+            .map_err(|e| (FindingCode::InvalidSignature, format!("{e:#}")))?;
+
+        let id_token = self
+            .check_id_token(session, &authorization_response)
+            .await
+            .map_err(|e| (FindingCode::InvalidIdToken, format!("{e:#}")))?;
+
+        let formats = presented_formats(session, &authorization_response);
+        spans::record_formats(&Span::current(), &formats);
+        let start = Instant::now();
+        let mut outcome = validator_function(session.clone(), authorization_response).await;
+        if let Some(id_token) = id_token {
+            outcome = bind_id_token(id_token, outcome);
+        }
+        if let Some(policy) = trust_policy {
+            outcome = apply_trust_policy(&policy, session, outcome).await;
+        }
+        if let Some(metrics) = &self.inner.metrics {
+            metrics.verification_completed(session, &formats, &outcome, start.elapsed());
+        }
+        Ok(outcome)
+    }
+
+    /// Parse the [registered](VerifierBuilder::with_parameter_registry) parameters of a response.
+    ///
+    /// JWT responses are skipped, as their parameters are only available once they have been
+    /// verified or decrypted.
+    fn check_registered_parameters(
+        &self,
+        authorization_response: &AuthorizationResponse,
+    ) -> Result<()> {
+        let parameters = match authorization_response {
+            AuthorizationResponse::Unencoded(response) => &response.0,
+            AuthorizationResponse::Dcql(response) => &response.0,
+            AuthorizationResponse::Jwt(_) => return Ok(()),
+        };
+        self.inner
+            .parameter_registry
+            .validate(ParameterLocation::Response, parameters)
+            .context("the response has an invalid parameter")
+    }
+
+    /// Resolve the descriptor map entries of a response against its `vp_token` with the
+    /// [parsers](VerifierBuilder::with_vp_token_parser) of their formats.
+    ///
+    /// Only the top-level `path` of each entry is resolved, `path_nested` is relative to the
+    /// envelope of the presentation and is left to the validator. Entries of formats without a
+    /// parser are skipped, as are DCQL and JWT responses, which have no descriptor map, or whose
+    /// descriptor map is only available once they have been verified or decrypted.
+    fn check_descriptor_paths(&self, authorization_response: &AuthorizationResponse) -> Result<()> {
+        let AuthorizationResponse::Unencoded(response) = authorization_response else {
+            return Ok(());
+        };
+        let parsers = &self.inner.vp_token_parsers;
+        for descriptor in response.presentation_submission().descriptor_map() {
+            if parsers.get(descriptor.format()).is_some() {
+                let entry = DescriptorMap::new(
+                    descriptor.id().clone(),
+                    descriptor.format().clone(),
+                    descriptor.path().clone(),
+                );
+                parsers
+                    .resolve(response.vp_token(), &entry)
+                    .with_context(|| {
+                        format!("failed to resolve descriptor '{}'", descriptor.id())
+                    })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify the presentations of a response with the
+    /// [PresentationVerifier](VerifierBuilder::with_presentation_verifier), if any, up to
+    /// [verification concurrency](VerifierBuilder::with_verification_concurrency) at a time.
+    ///
+    /// JWT responses are skipped, as the presentations are only available once the response has
+    /// been verified or decrypted.
+    async fn verify_presentations(
+        &self,
+        session: &Session,
+        authorization_response: &AuthorizationResponse,
+    ) -> Result<()> {
+        let Some(verifier) = &self.inner.presentation_verifier else {
+            return Ok(());
+        };
+        let presentations: Vec<_> = match authorization_response {
+            AuthorizationResponse::Unencoded(response) => response.vp_token().iter().collect(),
+            AuthorizationResponse::Dcql(response) => response.vp_token().presentations().collect(),
+            AuthorizationResponse::Jwt(_) => return Ok(()),
+        };
+        let results = verify_presentations(
+            presentations,
+            self.inner.verification_concurrency,
+            |index, presentation| async move {
+                verifier
+                    .verify(session, index, presentation)
+                    .await
+                    .with_context(|| format!("presentation {index} failed verification"))
+            },
+        )
+        .await;
+        results.into_iter().collect::<Result<Vec<()>>>()?;
+        Ok(())
+    }
+
+    /// Verify the `id_token` of a response to a request with `response_type` `vp_token id_token`,
+    /// see [verify_id_token].
+    ///
+    /// JWT responses are skipped, as the `id_token` is only available once the response has been
+    /// verified or decrypted.
+    async fn check_id_token(
+        &self,
+        session: &Session,
+        authorization_response: &AuthorizationResponse,
+    ) -> Result<Option<VerifiedIdToken>> {
+        let request = &session.authorization_request_object;
+        if request.response_type() != &ResponseType::VpTokenIdToken {
+            return Ok(None);
+        }
+        let parameters = match authorization_response {
+            AuthorizationResponse::Unencoded(response) => &response.0,
+            AuthorizationResponse::Dcql(response) => &response.0,
+            AuthorizationResponse::Jwt(_) => return Ok(None),
+        };
+        let id_token = parameters
+            .get::<IdToken>()
+            .context("the response is missing the requested 'id_token'")?
+            .context("failed to parse the 'id_token' of the response")?;
+        verify_id_token(
+            &id_token,
+            request,
+            self.inner.id_token_resolver.as_ref(),
+            SystemTime::now(),
+        )
+        .await
+        .map(Some)
+    }
+
+    /// Restore the session of a [StatelessState] sealed in `token`, with the request parameters it
+    /// was built with.
+    fn restore_session(&self, token: &str, state: &StatelessState) -> Result<Session> {
+        let mut request_parameters = self.inner.default_request_params.clone();
+        request_parameters.insert(self.inner.client.id().clone());
+        request_parameters.insert(self.inner.client.scheme().clone());
+        request_parameters.insert(Nonce::from(state.nonce.as_str()));
+        request_parameters.insert(State(token.to_owned()));
+
+        let (presentation_definition, dcql_query) = match (
+            &self.inner.presentation_definition,
+            &self.inner.dcql_query,
+        ) {
+            (Some(presentation_definition), _) => {
+                if definition_hash(&serde_json::to_value(presentation_definition)?)?
+                    != state.definition_hash
+                {
+                    bail!("the presentation definition of the stateless session is no longer configured")
+                }
+                request_parameters.insert(
+                    authorization_request::parameters::PresentationDefinition::try_from(
+                        presentation_definition.clone(),
+                    )?,
+                );
+                (Some(presentation_definition.clone()), None)
+            }
+            (None, Some(dcql_query)) => {
+                if definition_hash(&serde_json::to_value(dcql_query)?)? != state.definition_hash {
+                    bail!("the DCQL query of the stateless session is no longer configured")
+                }
+                request_parameters.insert(dcql_query.clone());
+                (None, Some(dcql_query.clone()))
+            }
+            (None, None) => {
+                bail!("presentation definition is required, see `with_presentation_definition`")
+            }
+        };
+
+        let mut response_uri = self.inner.submission_endpoint.clone();
+        response_uri
+            .path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("invalid base URL for the submission endpoint"))?
+            .push(token);
+        if self.inner.client.scheme() == &ClientIdScheme::RedirectUri {
+            request_parameters.insert(ClientId(response_uri.to_string()));
+        }
+        request_parameters.insert(ResponseUri(response_uri));
+
+        Ok(Session {
+            uuid: state.session,
+            status: Status::ReceivedResponse,
+            authorization_request_jwt: String::new(),
+            authorization_request_object: request_parameters
+                .try_into()
+                .context("failed to restore the request of the stateless session")?,
+            presentation_definition,
+            dcql_query,
+            response_encryption_key: None,
+            request_uri_secret: None,
+            response_code: None,
+            response_digest: None,
+            mdoc_generated_nonce: None,
+            created_at: state.created_at(),
+            tenant: None,
+            draft: None,
+        })
+    }
+
+    /// Backdate a stored session past its request URI TTL and session TTL, as if its request had
+    /// expired, for the simulation harness.
+    #[cfg(feature = "test-utils")]
+    pub(crate) async fn expire_session(&self, uuid: Uuid) -> Result<()> {
+        let store = &self.inner.session_store;
+        let mut session = store.get_session(uuid).await?;
+        let age = self
+            .inner
+            .session_ttl
+            .unwrap_or_default()
+            .max(self.inner.request_uri_ttl)
+            + Duration::from_secs(1);
+        session.created_at = session
+            .created_at
+            .checked_sub(age)
+            .context("failed to backdate the session")?;
+        store.remove_session(uuid).await?;
+        store.initiate(session).await
+    }
+
+    /// Set the presentation definition, or else the default presentation definition or DCQL query
+    /// of the verifier, on a request.
+    pub(crate) fn with_default_query<'a>(
+        &self,
+        builder: RequestBuilder<'a>,
+        presentation_definition: Option<PresentationDefinition>,
+    ) -> Result<RequestBuilder<'a>> {
+        if let Some(presentation_definition) =
+            presentation_definition.or_else(|| self.inner.presentation_definition.clone())
+        {
+            return Ok(builder.with_presentation_definition(presentation_definition));
+        }
+        let Some(dcql_query) = self.inner.dcql_query.clone() else {
+            bail!("presentation definition is required, see `with_presentation_definition`")
+        };
+        Ok(builder.with_dcql_query(dcql_query))
+    }
+
+    /// Decrypt the response of a session that requested an encrypted response, with the key of
+    /// the session, which is dropped, or else with the static keys of the verifier.
+    ///
+    /// Responses are passed through as is when the verifier holds no key for them.
+    fn decrypt_response(
+        &self,
+        session: &mut Session,
+        authorization_response: AuthorizationResponse,
+    ) -> Result<AuthorizationResponse> {
+        let session_key = session.response_encryption_key.take();
+        let static_keys = self.inner.response_encryption_keys.as_ref().filter(|_| {
+            session.authorization_request_object.response_mode() == &ResponseMode::DirectPostJwt
+        });
+        let encrypted = |authorization_response| match authorization_response {
+            AuthorizationResponse::Jwt(response) => Ok(response),
+            _ => bail!("the response was not encrypted as requested"),
+        };
+        match (session_key, static_keys) {
+            (Some(key), _) => key.decrypt(&encrypted(authorization_response)?),
+            (None, Some(keys)) => {
+                keys.decrypt(&encrypted(authorization_response)?, SystemTime::now())
+            }
+            (None, None) => Ok(authorization_response),
+        }
+    }
+
+    fn report_rejection(&self, session: &Session, code: &FindingCode) {
+        if let Some(metrics) = &self.inner.metrics {
+            metrics.response_rejected(session, code);
+        }
+    }
+
+    /// Reject a response before it is validated: the session completes with a fatal finding, and
+    /// the error is returned.
+    async fn fail_session(
+        &self,
+        session: &Session,
+        capture: Option<AuditCapture>,
+        code: FindingCode,
+        message: impl Into<String>,
+    ) -> Result<()> {
+        let message = message.into();
+        self.report_rejection(session, &code);
+        self.complete_session(session, rejection(code, message.clone()), capture)
+            .await?;
+        bail!(message)
+    }
+
+    /// Check a call to an endpoint for a session with the [RequestGuard], if any.
+    async fn check_guard(
+        &self,
+        endpoint: Endpoint,
+        session: &Session,
+        remote: &RemoteMetadata,
+    ) -> Result<()> {
+        let Some(guard) = &self.inner.guard else {
+            return Ok(());
+        };
+        let context = RequestContext {
+            endpoint,
+            session: session.uuid,
+            client_id: &session.authorization_request_object.client_id().0,
+            tenant: session.tenant.as_deref(),
+            remote,
+        };
+        guard_decision(endpoint, guard.check(&context).await)
+    }
+
+    /// Emit a [LifecycleEvent] of a session to the [EventSubscriber], if any.
+    fn emit_event(&self, kind: LifecycleEventKind, session: &Session) {
+        if let Some(subscriber) = &self.inner.event_subscriber {
+            subscriber.on_event(&LifecycleEvent::verifier(
+                kind,
+                session.uuid,
+                &session.authorization_request_object,
+            ))
+        }
+    }
+
+    /// Store the outcome of a session, then report it, see [Verifier::session_completed].
+    async fn complete_session(
+        &self,
+        session: &Session,
+        outcome: Outcome,
+        capture: Option<AuditCapture>,
+    ) -> Result<()> {
+        self.inner
+            .session_store
+            .update_status(session.uuid, Status::Complete(outcome.clone()))
+            .await?;
+        self.session_completed(session, &outcome, capture).await
+    }
+
+    /// Emit the [LifecycleEvent] of a completed session, archive it in the [PresentationArchive],
+    /// record it in the [AuditSink] and notify the [ResponseNotifier], if any.
+    ///
+    /// A failure to archive or audit the outcome is returned, as audit records must not be lost
+    /// silently.
+    async fn session_completed(
+        &self,
+        session: &Session,
+        outcome: &Outcome,
+        capture: Option<AuditCapture>,
+    ) -> Result<()> {
+        let kind = match outcome {
+            Outcome::Success { .. } => LifecycleEventKind::VerificationSucceeded,
+            Outcome::Failure { reason } => LifecycleEventKind::VerificationFailed {
+                reasons: vec![reason.clone()],
+            },
+            Outcome::Error { cause } => LifecycleEventKind::VerificationFailed {
+                reasons: vec![cause.clone()],
+            },
+        };
+        self.emit_event(kind, session);
+        // There are no receivers unless a result is awaited.
+        let _ = self.inner.completions.send(session.uuid);
+        if let Some(notifier) = &self.inner.notifier {
+            if let Err(e) = notifier.notify(session, outcome).await {
+                warn!(session = %session.uuid, "failed to notify the session outcome: {e:#}");
+            }
+        }
+        if let Some(archive) = &self.inner.archive {
+            let now = SystemTime::now();
+            let mut record =
+                PresentationRecord::new(session, outcome, now, self.inner.archive_retention);
+            if self.inner.minimize_archived_claims {
+                record.minimize(&RequestedClaims::for_session(session));
+            }
+            if let Some(policy) = &self.inner.retention_policy {
+                record.claims_retain_until = policy.deadlines(session, now);
+            }
+            archive
+                .archive(record)
+                .await
+                .context("failed to archive the session outcome")?;
+        }
+        if let (Some((sink, redaction)), Some(capture)) = (&self.inner.audit, capture) {
+            sink.record(capture.into_record(session, outcome, redaction))
+                .await
+                .context("failed to record the audit record of the session")?;
+        }
+        Ok(())
+    }
+
+    /// The [TrustPolicy] of the tenant of a session, or of the verifier.
+    fn trust_policy_for(&self, session: &Session) -> Result<Option<Arc<TrustPolicy>>> {
+        let Some(tenant_id) = &session.tenant else {
+            return Ok(self.inner.trust_policy.clone());
+        };
+        let Some(tenant) = self.inner.tenants.get(tenant_id) else {
+            bail!("the session was created for an unknown tenant '{tenant_id}'")
+        };
+        Ok(tenant
+            .trust_policy
+            .clone()
+            .or_else(|| self.inner.trust_policy.clone()))
+    }
+}
+
+/// Wait until the session is completed, or completions were missed and the session store must be
+/// checked again.
+async fn next_completion(completions: &mut broadcast::Receiver<Uuid>, session_id: Uuid) {
+    loop {
+        match completions.recv().await {
+            Ok(uuid) if uuid == session_id => return,
+            Ok(_) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+/// Fail with [RequestRejected] if the [RequestGuard] rejected a call.
+fn guard_decision(endpoint: Endpoint, decision: GuardDecision) -> Result<()> {
+    match decision {
+        GuardDecision::Allow => Ok(()),
+        GuardDecision::Reject {
+            reason,
+            retry_after,
+        } => bail!(RequestRejected {
+            endpoint,
+            reason,
+            retry_after,
+        }),
+    }
+}
+
+/// The outcome of a response rejected before it was validated, with a fatal finding.
+fn rejection(code: FindingCode, message: impl Into<String>) -> Outcome {
+    let mut report = VerificationReport::new();
+    report.fatal(code, message);
+    report.into_outcome(Default::default())
+}
+
+/// Evaluate the [TrustPolicy] on a successful outcome, which must carry a
+/// [VerifiedPresentationOutcome].
+async fn apply_trust_policy(policy: &TrustPolicy, session: &Session, outcome: Outcome) -> Outcome {
+    let Outcome::Success { .. } = outcome else {
+        return outcome;
+    };
+    let mut verified = match VerifiedPresentationOutcome::try_from(outcome) {
+        Ok(verified) => verified,
+        Err(e) => {
+            return Outcome::Error {
+                cause: format!("the trust policy could not be evaluated: {e:#}"),
+            }
+        }
+    };
+    let mut report = VerificationReport::new();
+    policy
+        .evaluate(session, &verified, &mut report, SystemTime::now())
+        .await;
+    let warnings = std::mem::take(&mut verified.warnings);
+    let mut merged = VerificationReport::new();
+    for warning in warnings {
+        merged.push(warning.code, warning.severity, warning.message);
+    }
+    merged.merge(report);
+    merged.into_verified_outcome(verified)
+}
+
+/// Check that every presentation with a readable nonce carries the nonce of the session.
+///
+/// JWT responses are skipped, as the presentations are only available once the response has been
+/// verified or decrypted.
+fn check_nonce(
+    session: &Session,
+    authorization_response: &AuthorizationResponse,
+    base64: Base64Policy,
+) -> Result<()> {
+    let presentations: Vec<_> = match authorization_response {
+        AuthorizationResponse::Unencoded(response) => response.vp_token().iter().collect(),
+        AuthorizationResponse::Dcql(response) => response.vp_token().presentations().collect(),
+        AuthorizationResponse::Jwt(_) => return Ok(()),
+    };
+    let expected = session.authorization_request_object.nonce();
+    for (index, item) in presentations.into_iter().enumerate() {
+        if let Some(nonce) = presentation_nonce(item, base64) {
+            if nonce != expected.as_str() {
+                bail!("the nonce of presentation {index} does not match the nonce of the session")
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check the `iss`, `aud` and `exp` claims of a decrypted response with the [JarmValidator]
+/// configured with [VerifierBuilder::with_jarm_validator].
+///
+/// Responses that are still encrypted are skipped, as the verifier holds no key for them.
+fn check_jarm_claims(
+    validator: &JarmValidator,
+    authorization_response: &AuthorizationResponse,
+) -> Result<()> {
+    let claims = match authorization_response {
+        AuthorizationResponse::Unencoded(response) => &response.0,
+        AuthorizationResponse::Dcql(response) => &response.0,
+        AuthorizationResponse::Jwt(_) => return Ok(()),
+    };
+    validator
+        .validate(claims, SystemTime::now())
+        .context("the claims of the JWT-secured response are invalid")
+}
+
+/// Read the [MdocGeneratedNonce] of an encrypted response from its `apu` header, and check that
+/// the `apv` header it is bound with carries the nonce of the session.
+fn check_mdoc_generated_nonce(
+    session: &Session,
+    response: &JwtAuthorizationResponse,
+) -> Result<Option<MdocGeneratedNonce>> {
+    let Some(mdoc_generated_nonce) = response.mdoc_generated_nonce()? else {
+        return Ok(None);
+    };
+    let expected =
+        mdoc_generated_nonce.jwe_header_parameters(session.authorization_request_object.nonce());
+    if response.protected_header()?.get("apv") != expected.get("apv") {
+        bail!("the 'apv' header of the response does not carry the nonce of the session")
+    }
+    Ok(Some(mdoc_generated_nonce))
+}
+
+/// Check that the Key Binding JWT of every SD-JWT presentation binds the `transaction_data` of the
+/// request, if it contained any, see [Session::verify_transaction_data_binding].
+///
+/// JWT responses are skipped, as the presentations are only available once the response has been
+/// verified or decrypted. mdoc presentations are not checked: this library does not decode their
+/// device-signed items, so binding them is left to the validator.
+fn check_transaction_data(
+    session: &Session,
+    authorization_response: &AuthorizationResponse,
+    base64: Base64Policy,
+) -> Result<()> {
+    let presentations: Vec<_> = match authorization_response {
+        AuthorizationResponse::Unencoded(response) => response.vp_token().iter().collect(),
+        AuthorizationResponse::Dcql(response) => response.vp_token().presentations().collect(),
+        AuthorizationResponse::Jwt(_) => return Ok(()),
+    };
+    for (index, item) in presentations.into_iter().enumerate() {
+        if let Some(claims) = key_binding_claims(item, base64) {
+            session
+                .verify_transaction_data_binding(&claims)
+                .with_context(|| {
+                    format!("the Key Binding JWT of presentation {index} does not bind the transaction data of the request")
+                })?;
+        }
+    }
+    Ok(())
+}
+
+/// Check that the response to a DCQL request carries a DCQL-shaped `vp_token` that answers the
+/// query, and that a response to a presentation definition request does not.
+///
+/// JWT responses are skipped, as the `vp_token` is only available once the response has been
+/// verified or decrypted.
+fn check_dcql_query(
+    session: &Session,
+    authorization_response: &AuthorizationResponse,
+) -> Result<()> {
+    match (&session.dcql_query, authorization_response) {
+        (_, AuthorizationResponse::Jwt(_)) => Ok(()),
+        (Some(dcql_query), AuthorizationResponse::Dcql(response)) => dcql_query
+            .match_vp_token(response.vp_token())
+            .context("the vp_token does not answer the DCQL query of the request"),
+        (Some(_), AuthorizationResponse::Unencoded(_)) => {
+            bail!(
+                "the request carried a DCQL query, but the response has a presentation submission"
+            )
+        }
+        (None, AuthorizationResponse::Dcql(_)) => {
+            bail!("the response has a DCQL-shaped vp_token, but the request carried no DCQL query")
+        }
+        (None, AuthorizationResponse::Unencoded(_)) => Ok(()),
+    }
+}
+
+/// Check that the `state` in the request, if any, is echoed in the response.
+///
+/// JWT responses are skipped, as the `state` is only available once the response has been
+/// verified or decrypted.
+fn check_state(session: &Session, authorization_response: &AuthorizationResponse) -> Result<()> {
+    let Some(expected) = session.authorization_request_object.get::<State>() else {
+        return Ok(());
+    };
+    let expected = expected.context("failed to parse 'state' from the authorization request")?;
+
+    let parameters = match authorization_response {
+        AuthorizationResponse::Unencoded(response) => &response.0,
+        AuthorizationResponse::Dcql(response) => &response.0,
+        AuthorizationResponse::Jwt(_) => return Ok(()),
+    };
+
+    match parameters.get::<State>() {
+        None => bail!("authorization response did not include the 'state' from the request"),
+        Some(state) => {
+            let state = state.context("failed to parse 'state' from the authorization response")?;
+            if state.0 != expected.0 {
+                bail!("'state' in the authorization response did not match the request")
+            }
+        }
+    }
+
+    Ok(())
 }
 
 /// Builder struct for [Verifier].
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct VerifierBuilder {
     client: Option<Arc<dyn Client + Send + Sync>>,
     default_request_params: UntypedObject,
     pass_by_reference: ByReference,
     session_store: Option<Arc<dyn SessionStore + Send + Sync>>,
     submission_endpoint: Option<Url>,
+    enforce_state: bool,
+    presentation_definition: Option<PresentationDefinition>,
+    dcql_query: Option<DcqlQuery>,
+    response_encryption_curve: KeyAgreementCurve,
+    response_encryption_keys: Option<Arc<ResponseEncryptionKeys>>,
+    response_redirect_uri: Option<Url>,
+    request_uri_ttl: Duration,
+    wallet_metadata: Option<WalletMetadata>,
+    metadata_trust_anchors: Option<MetadataTrustAnchors>,
+    preferred_authorization_endpoints: Vec<Url>,
+    response_validator: Option<Arc<dyn ResponseValidator>>,
+    presentation_verifier: Option<Arc<dyn PresentationVerifier>>,
+    jarm_validator: Option<JarmValidator>,
+    verification_concurrency: usize,
+    session_ttl: Option<Duration>,
+    scopes: ScopeRegistry,
+    parameter_registry: ParameterRegistry,
+    vp_token_parsers: VpTokenParserRegistry,
+    base64_policy: Base64Policy,
+    trust_policy: Option<Arc<TrustPolicy>>,
+    metrics: Option<Arc<dyn VerifierMetrics>>,
+    notifier: Option<Arc<dyn ResponseNotifier>>,
+    event_subscriber: Option<Arc<dyn EventSubscriber>>,
+    archive: Option<Arc<dyn PresentationArchive>>,
+    archive_retention: Option<Duration>,
+    minimize_archived_claims: bool,
+    retention_policy: Option<RetentionPolicy>,
+    audit: Option<(Arc<dyn AuditSink>, AuditRedaction)>,
+    state_key: Option<Arc<StateKey>>,
+    seen_tokens: Option<Arc<dyn SeenTokenStore>>,
+    guard: Option<Arc<dyn RequestGuard>>,
+    id_token_resolver: Arc<dyn IdTokenKeyResolver>,
+    tenants: BTreeMap<String, Tenant>,
+    templates: BTreeMap<String, Arc<RequestTemplate>>,
+}
+
+impl Default for VerifierBuilder {
+    fn default() -> Self {
+        Self {
+            client: None,
+            default_request_params: UntypedObject::default(),
+            pass_by_reference: ByReference::default(),
+            session_store: None,
+            submission_endpoint: None,
+            enforce_state: true,
+            presentation_definition: None,
+            dcql_query: None,
+            response_encryption_curve: KeyAgreementCurve::P256,
+            response_encryption_keys: None,
+            response_redirect_uri: None,
+            request_uri_ttl: DEFAULT_REQUEST_URI_TTL,
+            wallet_metadata: None,
+            metadata_trust_anchors: None,
+            preferred_authorization_endpoints: Vec::new(),
+            response_validator: None,
+            presentation_verifier: None,
+            jarm_validator: None,
+            verification_concurrency: DEFAULT_CONCURRENCY,
+            session_ttl: None,
+            scopes: ScopeRegistry::default(),
+            parameter_registry: ParameterRegistry::default(),
+            vp_token_parsers: VpTokenParserRegistry::empty(),
+            base64_policy: Base64Policy::default(),
+            trust_policy: None,
+            metrics: None,
+            notifier: None,
+            event_subscriber: None,
+            archive: None,
+            archive_retention: None,
+            minimize_archived_claims: false,
+            retention_policy: None,
+            audit: None,
+            state_key: None,
+            seen_tokens: None,
+            guard: None,
+            id_token_resolver: Arc::new(AnyDidKeyResolver::default()),
+            tenants: BTreeMap::new(),
+            templates: BTreeMap::new(),
+        }
+    }
 }
 
 impl VerifierBuilder {
@@ -151,26 +1624,115 @@ impl VerifierBuilder {
             pass_by_reference,
             session_store,
             submission_endpoint,
+            enforce_state,
+            presentation_definition,
+            dcql_query,
+            response_encryption_curve,
+            response_encryption_keys,
+            response_redirect_uri,
+            request_uri_ttl,
+            wallet_metadata,
+            metadata_trust_anchors,
+            preferred_authorization_endpoints,
+            response_validator,
+            presentation_verifier,
+            jarm_validator,
+            verification_concurrency,
+            session_ttl,
+            scopes,
+            parameter_registry,
+            vp_token_parsers,
+            base64_policy,
+            trust_policy,
+            metrics,
+            notifier,
+            event_subscriber,
+            archive,
+            archive_retention,
+            minimize_archived_claims,
+            retention_policy,
+            audit,
+            state_key,
+            seen_tokens,
+            guard,
+            id_token_resolver,
+            tenants,
+            templates,
         } = self;
 
         let Some(client) = client else {
             bail!("client is required, see `with_client`")
         };
 
-        let Some(session_store) = session_store else {
-            bail!("session store is required, see `with_session_store`")
+        let session_store = match (session_store, &state_key) {
+            (Some(session_store), _) => session_store,
+            (None, Some(_)) => Arc::new(NoSessionStore),
+            (None, None) => bail!("session store is required, see `with_session_store`"),
         };
 
         let Some(submission_endpoint) = submission_endpoint else {
             bail!("submission endpoint is required, see `with_submission_endpoint`")
         };
 
+        if let Some(wallet_metadata) = &wallet_metadata {
+            wallet_metadata
+                .validate()
+                .context("invalid wallet metadata, see `with_wallet_metadata`")?;
+        }
+
+        let mut parsers = VpTokenParserRegistry::with_base64_policy(base64_policy);
+        parsers.merge(vp_token_parsers);
+
+        for (template_id, template) in &templates {
+            template
+                .validate(&default_request_params)
+                .with_context(|| format!("invalid request template '{template_id}'"))?;
+        }
+
         Ok(Verifier {
-            client,
-            default_request_params,
-            pass_by_reference,
-            session_store,
-            submission_endpoint,
+            inner: Arc::new(VerifierInner {
+                client,
+                default_request_params,
+                pass_by_reference,
+                session_store,
+                submission_endpoint,
+                enforce_state,
+                presentation_definition,
+                dcql_query,
+                response_encryption_curve,
+                response_encryption_keys,
+                response_redirect_uri,
+                request_uri_ttl,
+                wallet_metadata: wallet_metadata
+                    .unwrap_or_else(WalletMetadata::openid4vp_scheme_static),
+                metadata_trust_anchors,
+                preferred_authorization_endpoints,
+                response_validator,
+                presentation_verifier,
+                jarm_validator,
+                verification_concurrency,
+                session_ttl,
+                scopes,
+                parameter_registry,
+                vp_token_parsers: parsers,
+                base64_policy,
+                trust_policy,
+                metrics,
+                notifier,
+                event_subscriber,
+                archive,
+                archive_retention,
+                minimize_archived_claims,
+                retention_policy,
+                audit,
+                state_key,
+                seen_tokens,
+                guard,
+                id_token_resolver,
+                tenants,
+                templates,
+                completions: broadcast::channel(COMPLETIONS_CAPACITY).0,
+            }),
         })
     }
 
@@ -181,17 +1743,44 @@ impl VerifierBuilder {
     }
 
     /// Pass the Authorization Request by reference in the `request_uri` parameter.
+    ///
+    /// Each `request_uri` can be retrieved once, see [Verifier::retrieve_authorization_request].
     pub fn by_reference(mut self, at: Url) -> Self {
         self.pass_by_reference = ByReference::True { at };
         self
     }
 
+    /// Support stateless sessions, whose data is sealed with `key`, see
+    /// [Verifier::begin_stateless_session]. The session store is then optional.
+    ///
+    /// Nothing is recorded when a response is received, so a response can be replayed until the
+    /// session expires unless a store is set with [VerifierBuilder::with_seen_token_store].
+    pub fn stateless(mut self, key: StateKey) -> Self {
+        self.state_key = Some(Arc::new(key));
+        self
+    }
+
+    /// Reject replayed responses of stateless sessions, by recording in `store` the sessions that
+    /// already received one, see [stateless::MemorySeenTokenStore].
+    pub fn with_seen_token_store(mut self, store: Arc<dyn SeenTokenStore>) -> Self {
+        self.seen_tokens = Some(store);
+        self
+    }
+
+    /// Reject retrievals of a `request_uri` more than `ttl` after the session was created.
+    /// Defaults to [DEFAULT_REQUEST_URI_TTL].
+    pub fn with_request_uri_ttl(mut self, ttl: Duration) -> Self {
+        self.request_uri_ttl = ttl;
+        self
+    }
+
     /// Set default parameters that every
     /// [AuthorizationRequest](crate::core::authorization_request::AuthorizationRequest) will
     /// contain.
     ///
     /// 'client_id' and 'client_id_scheme' are always overridden by the
-    /// [Client](crate::verifier::client::Client).
+    /// [Client](crate::verifier::client::Client), and a default 'nonce' is ignored, as a fresh
+    /// nonce is generated for each request.
     pub fn with_default_request_parameter<T: TypedParameter>(mut self, t: T) -> Self {
         self.default_request_params.insert(t);
         self
@@ -220,4 +1809,261 @@ impl VerifierBuilder {
         self.submission_endpoint = Some(endpoint);
         self
     }
+
+    /// Require that authorization responses echo the `state` from the authorization request, if
+    /// one was set. Enabled by default.
+    pub fn enforce_state(mut self, enforce: bool) -> Self {
+        self.enforce_state = enforce;
+        self
+    }
+
+    /// Set the presentation definition of the sessions started with [Verifier::begin_session].
+    pub fn with_presentation_definition(
+        mut self,
+        presentation_definition: PresentationDefinition,
+    ) -> Self {
+        self.presentation_definition = Some(presentation_definition);
+        self
+    }
+
+    /// Set the DCQL query of the sessions started with [Verifier::begin_session], when no
+    /// presentation definition is set.
+    pub fn with_dcql_query(mut self, dcql_query: DcqlQuery) -> Self {
+        self.dcql_query = Some(dcql_query);
+        self
+    }
+
+    /// Set the curve of the key generated for each session whose response is encrypted
+    /// (`direct_post.jwt`), when the client metadata does not publish keys. Defaults to P-256.
+    pub fn with_response_encryption_curve(mut self, curve: KeyAgreementCurve) -> Self {
+        self.response_encryption_curve = curve;
+        self
+    }
+
+    /// Encrypt the responses of sessions with `direct_post.jwt` to static keys, instead of keys
+    /// generated for each session, when the client metadata does not publish keys.
+    pub fn with_response_encryption_keys(mut self, keys: ResponseEncryptionKeys) -> Self {
+        self.response_encryption_keys = Some(Arc::new(keys));
+        self
+    }
+
+    /// Redirect the wallet to `redirect_uri` once it has submitted a response, with a
+    /// `response_code` that the frontend exchanges for the outcome of the session, see
+    /// [Verifier::post_redirection] and [Verifier::exchange_response_code].
+    ///
+    /// A response code is generated for every session. This is the "redirect with response code"
+    /// pattern of the `direct_post` response mode, which prevents session fixation: only the
+    /// frontend the wallet was redirected to learns the outcome.
+    pub fn with_response_redirect_uri(mut self, redirect_uri: Url) -> Self {
+        self.response_redirect_uri = Some(redirect_uri);
+        self
+    }
+
+    /// Set the metadata of the wallet that sessions started with [Verifier::begin_session] are
+    /// built for. Defaults to the static `openid4vp:` metadata, see
+    /// [WalletMetadata::openid4vp_scheme_static]. The metadata is
+    /// [validated](WalletMetadata::validate) when the verifier is built.
+    ///
+    /// The metadata of web wallets can be [discovered](WalletMetadata::discover) instead.
+    pub fn with_wallet_metadata(mut self, wallet_metadata: WalletMetadata) -> Self {
+        self.wallet_metadata = Some(wallet_metadata);
+        self
+    }
+
+    /// The endpoints to invoke wallets at, in order of preference, when wallets can be invoked
+    /// at several (e.g. `haip://` before `openid4vp://`), see
+    /// [WalletMetadata::preferred_authorization_endpoint]. Wallets are invoked at their
+    /// `authorization_endpoint` by default.
+    pub fn with_preferred_authorization_endpoints(
+        mut self,
+        endpoints: impl IntoIterator<Item = Url>,
+    ) -> Self {
+        self.preferred_authorization_endpoints = endpoints.into_iter().collect();
+        self
+    }
+
+    /// Reject responses to sessions created more than `ttl` ago. Sessions do not expire by
+    /// default, see also [SessionStore::expire].
+    pub fn with_session_ttl(mut self, ttl: Duration) -> Self {
+        self.session_ttl = Some(ttl);
+        self
+    }
+
+    /// Register the presentation definition of a scope, for scope-only requests (see
+    /// [Verifier::begin_scope_session] and [RequestBuilder::with_scope]).
+    pub fn with_scope(
+        mut self,
+        scope: impl Into<String>,
+        presentation_definition: PresentationDefinition,
+    ) -> Result<Self> {
+        self.scopes.register(scope, presentation_definition)?;
+        Ok(self)
+    }
+
+    /// Evaluate a [TrustPolicy] on the responses accepted by the validator. The validator must
+    /// produce a [VerifiedPresentationOutcome], see [VerificationReport::into_verified_outcome].
+    pub fn with_trust_policy(mut self, trust_policy: TrustPolicy) -> Self {
+        self.trust_policy = Some(Arc::new(trust_policy));
+        self
+    }
+
+    /// Register a [Tenant], to create sessions on its behalf with
+    /// [Verifier::begin_tenant_session] or [Verifier::build_tenant_authorization_request].
+    pub fn with_tenant(mut self, tenant_id: impl Into<String>, tenant: Tenant) -> Self {
+        self.tenants.insert(tenant_id.into(), tenant);
+        self
+    }
+
+    /// Register a [RequestTemplate], to create sessions from it with
+    /// [Verifier::begin_template_session]. Templates are checked when the verifier is built.
+    pub fn with_request_template(
+        mut self,
+        template_id: impl Into<String>,
+        template: RequestTemplate,
+    ) -> Self {
+        self.templates
+            .insert(template_id.into(), Arc::new(template));
+        self
+    }
+
+    /// Record metrics as sessions progress, see [VerifierMetrics].
+    pub fn with_metrics(mut self, metrics: Arc<dyn VerifierMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Emit the [LifecycleEvent]s of sessions to an [EventSubscriber].
+    pub fn with_event_subscriber(mut self, subscriber: Arc<dyn EventSubscriber>) -> Self {
+        self.event_subscriber = Some(subscriber);
+        self
+    }
+
+    /// Archive a [PresentationRecord] of every completed session in a [PresentationArchive].
+    pub fn with_presentation_archive(mut self, archive: Arc<dyn PresentationArchive>) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Set the retention period of archived records, see
+    /// [with_presentation_archive](Self::with_presentation_archive). Records are kept indefinitely
+    /// by default.
+    pub fn with_archive_retention(mut self, retention: Duration) -> Self {
+        self.archive_retention = Some(retention);
+        self
+    }
+
+    /// Only archive the claims that were requested, dropping any other claim of the verified
+    /// outcome (see [VerifiedPresentationOutcome::minimize]) before records are stored.
+    pub fn minimize_archived_claims(mut self) -> Self {
+        self.minimize_archived_claims = true;
+        self
+    }
+
+    /// Set when the claims of archived records must be deleted, by input descriptor, see
+    /// [RetentionPolicy] and [PresentationRecord::expire_claims].
+    pub fn with_retention_policy(mut self, policy: RetentionPolicy) -> Self {
+        self.retention_policy = Some(policy);
+        self
+    }
+
+    /// Record an [AuditRecord](audit::AuditRecord) of every completed session in an [AuditSink],
+    /// with its artifacts redacted as set by `redaction`.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>, redaction: AuditRedaction) -> Self {
+        self.audit = Some((sink, redaction));
+        self
+    }
+
+    /// Only build requests for wallets whose metadata is signed by one of these trust authorities,
+    /// using the values of the [signed metadata](WalletMetadata::verify_signed), as required by
+    /// federation-style ecosystems.
+    ///
+    /// Requests for wallets with unsigned metadata, e.g. the static `openid4vp:` metadata, then
+    /// fail to build.
+    pub fn with_metadata_trust_anchors(mut self, anchors: MetadataTrustAnchors) -> Self {
+        self.metadata_trust_anchors = Some(anchors);
+        self
+    }
+
+    /// Parameters defined outside of this library, e.g. by the profile the verifier implements.
+    /// Registered request and client metadata parameters are parsed when requests are built, and
+    /// registered response parameters when responses are received, rejecting the response with
+    /// [FindingCode::InvalidSubmission] if they are malformed.
+    pub fn with_parameter_registry(mut self, registry: ParameterRegistry) -> Self {
+        self.parameter_registry = registry;
+        self
+    }
+
+    /// Parse the presentations and credentials of a format with `parser`, replacing the default
+    /// parser of that format, if any (see [VpTokenParserRegistry]). The descriptor map entries of
+    /// a response are resolved with the parsers of their formats, rejecting the response with
+    /// [FindingCode::InvalidSubmission] if they cannot be, and entries of formats without a parser
+    /// are left to the validator.
+    pub fn with_vp_token_parser(
+        mut self,
+        designation: ClaimFormatDesignation,
+        parser: impl VpTokenParser + 'static,
+    ) -> Self {
+        self.vp_token_parsers.register(designation, parser);
+        self
+    }
+
+    /// How the base64url values of responses, such as the JWT presentations of the `vp_token`,
+    /// are decoded when the verifier reads their nonces and resolves their descriptor map paths.
+    /// [Lenient](Base64Policy::Lenient) by default, as wallets in the field disagree on padding,
+    /// [Base64Policy::Strict] is intended for conformance testing.
+    pub fn with_base64_policy(mut self, policy: Base64Policy) -> Self {
+        self.base64_policy = policy;
+        self
+    }
+
+    /// Consult a [RequestGuard] before serving requests by reference and receiving responses,
+    /// see [Verifier::retrieve_authorization_request_from] and [Verifier::receive_response_from].
+    pub fn with_request_guard(mut self, guard: Arc<dyn RequestGuard>) -> Self {
+        self.guard = Some(guard);
+        self
+    }
+
+    /// Resolve the keys of Self-Issued ID Tokens whose subject is a DID with `resolver`, instead of
+    /// [AnyDidKeyResolver], see [verify_id_token].
+    pub fn with_id_token_resolver(mut self, resolver: Arc<dyn IdTokenKeyResolver>) -> Self {
+        self.id_token_resolver = resolver;
+        self
+    }
+
+    /// Notify a [ResponseNotifier] when sessions complete.
+    pub fn with_response_notifier(mut self, notifier: Arc<dyn ResponseNotifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Verify each presentation of a response with `verifier` before it is validated, see
+    /// [PresentationVerifier].
+    pub fn with_presentation_verifier(mut self, verifier: Arc<dyn PresentationVerifier>) -> Self {
+        self.presentation_verifier = Some(verifier);
+        self
+    }
+
+    /// Check the `iss`, `aud` and `exp` claims of decrypted `direct_post.jwt` responses with
+    /// `validator`, see [JarmValidator]. By default these claims are not checked, as wallets may
+    /// encrypt the bare response parameters.
+    pub fn with_jarm_validator(mut self, validator: JarmValidator) -> Self {
+        self.jarm_validator = Some(validator);
+        self
+    }
+
+    /// The number of presentations of a response verified at the same time by the
+    /// [PresentationVerifier], defaults to [DEFAULT_CONCURRENCY].
+    pub fn with_verification_concurrency(mut self, concurrency: usize) -> Self {
+        self.verification_concurrency = concurrency;
+        self
+    }
+
+    /// Set the [ResponseValidator] used by [Verifier::receive_response].
+    pub fn with_response_validator(
+        mut self,
+        response_validator: Arc<dyn ResponseValidator>,
+    ) -> Self {
+        self.response_validator = Some(response_validator);
+        self
+    }
 }
@@ -0,0 +1,121 @@
+use serde_json::{Map, Value as Json};
+
+use crate::core::{
+    response::parameters::VpTokenItem,
+    util::{jwt_payload, Base64Policy},
+};
+
+/// The length of the nonces generated for each session.
+pub const NONCE_LENGTH: usize = 32;
+
+/// Extract the nonce a presentation was bound to, if it can be read without verifying the
+/// presentation.
+///
+/// - SD-JWT presentations: the `nonce` of the Key Binding JWT.
+/// - JWT presentations: the `nonce` claim.
+/// - Linked Data Proof presentations: the `challenge` of the proof.
+///
+/// mdoc presentations bind the nonce in the session transcript, and return `None`, as do
+/// presentations without a holder binding. JWTs are decoded according to the [Base64Policy].
+pub fn presentation_nonce(item: &VpTokenItem, base64: Base64Policy) -> Option<String> {
+    match item {
+        VpTokenItem::String(s) if s.contains('~') => key_binding_claims(item, base64)?
+            .get("nonce")?
+            .as_str()
+            .map(ToOwned::to_owned),
+        VpTokenItem::String(s) => jwt_claim(s, "nonce", base64),
+        VpTokenItem::JsonObject(object) => {
+            let proofs = match object.get("proof")? {
+                Json::Array(proofs) => proofs.iter().collect(),
+                proof => vec![proof],
+            };
+            proofs
+                .into_iter()
+                .find_map(|proof| proof.get("challenge")?.as_str().map(ToOwned::to_owned))
+        }
+    }
+}
+
+/// Extract the claims of the Key Binding JWT of an SD-JWT presentation, if it has one, without
+/// verifying it.
+pub fn key_binding_claims(item: &VpTokenItem, base64: Base64Policy) -> Option<Map<String, Json>> {
+    let VpTokenItem::String(s) = item else {
+        return None;
+    };
+    let (_, key_binding_jwt) = s.rsplit_once('~')?;
+    if key_binding_jwt.is_empty() {
+        return None;
+    }
+    jwt_payload(key_binding_jwt, base64).ok()
+}
+
+fn jwt_claim(jwt: &str, claim: &str, base64: Base64Policy) -> Option<String> {
+    jwt_payload::<Map<String, Json>>(jwt, base64)
+        .ok()?
+        .get(claim)?
+        .as_str()
+        .map(ToOwned::to_owned)
+}
+
+#[cfg(test)]
+mod test {
+    use base64::prelude::*;
+    use serde_json::json;
+
+    use super::*;
+
+    fn jwt(claims: Json) -> String {
+        let header = BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256"}"#);
+        let payload = BASE64_URL_SAFE_NO_PAD.encode(claims.to_string());
+        format!("{header}.{payload}.signature")
+    }
+
+    #[test]
+    fn nonces() {
+        let vp = jwt(json!({ "nonce": "n-0S6_WzA2Mj", "vp": {} }));
+        assert_eq!(
+            presentation_nonce(&vp.into(), Base64Policy::default()).as_deref(),
+            Some("n-0S6_WzA2Mj")
+        );
+
+        let sd_jwt = format!(
+            "{}~WyJzYWx0IiwgImdpdmVuX25hbWUiLCAiSm9obiJd~{}",
+            jwt(json!({ "_sd": [] })),
+            jwt(json!({ "nonce": "kb-nonce" }))
+        );
+        assert_eq!(
+            presentation_nonce(&sd_jwt.into(), Base64Policy::default()).as_deref(),
+            Some("kb-nonce")
+        );
+
+        let without_key_binding = format!("{}~", jwt(json!({ "_sd": [] })));
+        assert_eq!(
+            presentation_nonce(&without_key_binding.into(), Base64Policy::default()),
+            None
+        );
+
+        let ldp_vp = VpTokenItem::JsonObject(
+            json!({ "proof": { "challenge": "ldp-nonce" } })
+                .as_object()
+                .unwrap()
+                .clone(),
+        );
+        assert_eq!(
+            presentation_nonce(&ldp_vp, Base64Policy::default()).as_deref(),
+            Some("ldp-nonce")
+        );
+    }
+
+    #[test]
+    fn padded_nonce() {
+        let payload = BASE64_URL_SAFE.encode(json!({ "nonce": "n-0S6_WzA2M" }).to_string());
+        assert!(payload.ends_with('='));
+        let vp = VpTokenItem::from(format!("e30.{payload}.signature"));
+
+        assert_eq!(
+            presentation_nonce(&vp, Base64Policy::Lenient).as_deref(),
+            Some("n-0S6_WzA2M")
+        );
+        assert_eq!(presentation_nonce(&vp, Base64Policy::Strict), None);
+    }
+}
@@ -0,0 +1,20 @@
+use std::fmt::Debug;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use super::session::{Outcome, Session};
+
+/// Notified by the [Verifier](super::Verifier) when a session completes, i.e. its response was
+/// verified, failed verification or was rejected, for push-style integrations (webhooks, message
+/// queues) instead of polling.
+///
+/// Notifications are sent once the outcome has been stored, so the receiver can read it back from
+/// the session store. A failed notification is logged and does not affect the outcome; retrying is
+/// left to the implementation.
+#[async_trait]
+pub trait ResponseNotifier: Debug + Send + Sync {
+    /// The `session` completed with `outcome`. The status of `session` is the one before
+    /// completion.
+    async fn notify(&self, session: &Session, outcome: &Outcome) -> Result<()>;
+}
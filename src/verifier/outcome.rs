@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+
+use crate::core::credential_format::ClaimFormatDesignation;
+
+use super::{
+    id_token::VerifiedIdToken, minimization::RequestedClaims, report::Finding, session::Outcome,
+};
+
+/// The result of a verified authorization response, in a form that does not depend on the
+/// credential formats that were presented.
+///
+/// A [ResponseValidator](super::validator::ResponseValidator) builds this from the presentations it
+/// verified, and converts it into the session [Outcome] with
+/// [VerificationReport::into_verified_outcome](super::report::VerificationReport::into_verified_outcome).
+/// Relying party logic reads it back with [Verifier::verified_outcome](super::Verifier::verified_outcome).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedPresentationOutcome {
+    /// The identifier of the holder that presented the credentials, if it was bound.
+    pub holder: Option<String>,
+    /// The verified credentials, in the order they were presented.
+    pub credentials: Vec<VerifiedCredential>,
+    /// The claims of the verified credentials, by input descriptor.
+    pub claims: VerifiedClaims,
+    /// The Self-Issued ID Token returned with the presentations, if the request had
+    /// `response_type` `vp_token id_token`. Its subject is the holder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<VerifiedIdToken>,
+    /// Findings that did not invalidate the response.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Finding>,
+}
+
+impl VerifiedPresentationOutcome {
+    pub fn new(holder: Option<String>) -> Self {
+        Self {
+            holder,
+            ..Default::default()
+        }
+    }
+
+    /// Add a verified credential, and its claims.
+    pub fn with_credential(mut self, credential: VerifiedCredential) -> Self {
+        self.claims.insert(&credential);
+        self.credentials.push(credential);
+        self
+    }
+
+    /// The verified credentials that were presented for an input descriptor.
+    pub fn credentials_for<'a>(
+        &'a self,
+        input_descriptor_id: &'a str,
+    ) -> impl Iterator<Item = &'a VerifiedCredential> {
+        self.credentials
+            .iter()
+            .filter(move |credential| credential.input_descriptor_id == input_descriptor_id)
+    }
+
+    /// Drop the claims of the verified credentials that were not requested, e.g. before the
+    /// outcome is stored.
+    pub fn minimize(&mut self, requested: &RequestedClaims) {
+        for credential in &mut self.credentials {
+            credential.claims =
+                requested.retain(&credential.input_descriptor_id, &credential.claims);
+        }
+        self.rebuild_claims();
+    }
+
+    /// Drop the credentials presented for an input descriptor, and their claims.
+    pub fn remove_descriptor(&mut self, input_descriptor_id: &str) {
+        self.credentials
+            .retain(|credential| credential.input_descriptor_id != input_descriptor_id);
+        self.rebuild_claims();
+    }
+
+    fn rebuild_claims(&mut self) {
+        self.claims = VerifiedClaims::default();
+        for credential in &self.credentials {
+            self.claims.insert(credential);
+        }
+    }
+}
+
+impl TryFrom<Outcome> for VerifiedPresentationOutcome {
+    type Error = anyhow::Error;
+
+    fn try_from(outcome: Outcome) -> Result<Self> {
+        match outcome {
+            Outcome::Success { info } => serde_json::from_value(info)
+                .context("the outcome does not contain a verified presentation"),
+            Outcome::Failure { reason } => bail!("the response failed verification: {reason}"),
+            Outcome::Error { cause } => bail!("the response could not be processed: {cause}"),
+        }
+    }
+}
+
+/// A credential from a verified presentation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedCredential {
+    /// The input descriptor that the credential was presented for.
+    pub input_descriptor_id: String,
+    pub format: ClaimFormatDesignation,
+    /// The issuer of the credential, if known.
+    pub issuer: Option<String>,
+    /// The claims of the credential that were disclosed to the verifier.
+    pub claims: Map<String, Json>,
+    /// The types of the credential (e.g. the `type` of a W3C VC, the `vct` of an SD-JWT VC or the
+    /// doctype of an mdoc).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub credential_types: Vec<String>,
+    /// When the credential was issued, in seconds since the UNIX epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issued_at: Option<u64>,
+    /// The certificate chain of the issuer (base64 DER, leaf first), for credentials signed with
+    /// an X.509 certificate.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issuer_x5c: Vec<String>,
+    /// The result of the credential status check, if one was made.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<CredentialStatus>,
+}
+
+/// The result of checking the status (e.g. revocation) of a credential.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialStatus {
+    Valid,
+    Revoked,
+    Suspended,
+    /// The status could not be checked, e.g. the status list could not be retrieved.
+    Unavailable,
+}
+
+impl VerifiedCredential {
+    pub fn new(input_descriptor_id: String, format: ClaimFormatDesignation) -> Self {
+        Self {
+            input_descriptor_id,
+            format,
+            issuer: None,
+            claims: Map::new(),
+            credential_types: Vec::new(),
+            issued_at: None,
+            issuer_x5c: Vec::new(),
+            status: None,
+        }
+    }
+
+    pub fn with_issuer(mut self, issuer: String) -> Self {
+        self.issuer = Some(issuer);
+        self
+    }
+
+    pub fn with_claims(mut self, claims: Map<String, Json>) -> Self {
+        self.claims = claims;
+        self
+    }
+
+    pub fn with_credential_types(mut self, credential_types: Vec<String>) -> Self {
+        self.credential_types = credential_types;
+        self
+    }
+
+    pub fn with_issued_at(mut self, issued_at: u64) -> Self {
+        self.issued_at = Some(issued_at);
+        self
+    }
+
+    pub fn with_issuer_x5c(mut self, issuer_x5c: Vec<String>) -> Self {
+        self.issuer_x5c = issuer_x5c;
+        self
+    }
+
+    pub fn with_status(mut self, status: CredentialStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Read the issuer and the claims of the credential subject from the verified payload of a
+    /// JWT VC (`jwt_vc_json`).
+    pub fn from_jwt_vc(input_descriptor_id: String, payload: &Map<String, Json>) -> Result<Self> {
+        let vc = payload
+            .get("vc")
+            .and_then(Json::as_object)
+            .context("the JWT VC did not contain a 'vc' claim")?;
+        let issuer =
+            payload
+                .get("iss")
+                .or_else(|| vc.get("issuer"))
+                .and_then(|issuer| match issuer {
+                    Json::String(issuer) => Some(issuer.clone()),
+                    Json::Object(issuer) => issuer.get("id")?.as_str().map(ToOwned::to_owned),
+                    _ => None,
+                });
+        let claims = match vc.get("credentialSubject") {
+            Some(Json::Object(subject)) => subject.clone(),
+            Some(_) => bail!("the credentialSubject of the JWT VC was not an object"),
+            None => Map::new(),
+        };
+        let credential_types = match vc.get("type") {
+            Some(Json::String(t)) => vec![t.clone()],
+            Some(Json::Array(types)) => types
+                .iter()
+                .filter_map(|t| t.as_str().map(ToOwned::to_owned))
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            issuer,
+            claims,
+            credential_types,
+            issued_at: payload
+                .get("iat")
+                .or_else(|| payload.get("nbf"))
+                .and_then(Json::as_u64),
+            ..Self::new(input_descriptor_id, ClaimFormatDesignation::JwtVcJson)
+        })
+    }
+}
+
+/// The claims of the verified credentials, by the input descriptor they were presented for.
+///
+/// When several credentials are presented for the same input descriptor, their claims are merged,
+/// with earlier credentials taking precedence.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VerifiedClaims(BTreeMap<String, Map<String, Json>>);
+
+impl VerifiedClaims {
+    fn insert(&mut self, credential: &VerifiedCredential) {
+        let claims = self
+            .0
+            .entry(credential.input_descriptor_id.clone())
+            .or_default();
+        for (name, value) in &credential.claims {
+            claims.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    /// The claims presented for an input descriptor.
+    pub fn for_descriptor(&self, input_descriptor_id: &str) -> Option<&Map<String, Json>> {
+        self.0.get(input_descriptor_id)
+    }
+
+    /// A claim presented for an input descriptor, by its JSON pointer (e.g. `/address/country`).
+    pub fn get(&self, input_descriptor_id: &str, pointer: &str) -> Option<&Json> {
+        let claims = self.for_descriptor(input_descriptor_id)?;
+        let pointer = pointer.strip_prefix('/')?;
+        let (name, rest) = pointer.split_once('/').unwrap_or((pointer, ""));
+        let claim = claims.get(name)?;
+        if rest.is_empty() {
+            Some(claim)
+        } else {
+            claim.pointer(&format!("/{rest}"))
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Map<String, Json>)> {
+        self.0.iter().map(|(id, claims)| (id.as_str(), claims))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::verifier::report::{FindingCode, VerificationReport};
+
+    use super::*;
+
+    #[test]
+    fn outcome_roundtrip() {
+        let payload = json!({
+            "iss": "did:example:issuer",
+            "vc": {
+                "credentialSubject": {
+                    "id": "did:example:holder",
+                    "address": { "country": "FR" }
+                }
+            }
+        });
+        let credential =
+            VerifiedCredential::from_jwt_vc("id-card".into(), payload.as_object().unwrap())
+                .unwrap();
+        assert_eq!(credential.issuer.as_deref(), Some("did:example:issuer"));
+
+        let mut report = VerificationReport::new();
+        report.warn(FindingCode::ClockSkew, "iat is in the future");
+        let outcome = report.into_verified_outcome(
+            VerifiedPresentationOutcome::new(Some("did:example:holder".into()))
+                .with_credential(credential),
+        );
+
+        let verified = VerifiedPresentationOutcome::try_from(outcome).unwrap();
+        assert_eq!(verified.holder.as_deref(), Some("did:example:holder"));
+        assert_eq!(verified.credentials_for("id-card").count(), 1);
+        assert_eq!(
+            verified.claims.get("id-card", "/address/country"),
+            Some(&json!("FR"))
+        );
+        assert_eq!(verified.claims.get("id-card", "/missing"), None);
+        assert_eq!(verified.warnings[0].code, FindingCode::ClockSkew);
+
+        let mut report = VerificationReport::new();
+        report.fatal(FindingCode::NonceMismatch, "nonce did not match");
+        let outcome = report.into_verified_outcome(VerifiedPresentationOutcome::default());
+        assert!(VerifiedPresentationOutcome::try_from(outcome).is_err());
+    }
+}
@@ -0,0 +1,351 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use base64::prelude::*;
+use x509_cert::{der::Encode, Certificate};
+
+use super::{
+    outcome::{CredentialStatus, VerifiedCredential, VerifiedPresentationOutcome},
+    report::{FindingCode, VerificationReport},
+    session::Session,
+};
+
+/// Issuer rules registered for this credential type apply to every credential.
+pub const ANY_CREDENTIAL_TYPE: &str = "*";
+
+/// A custom, possibly asynchronous, rule of a [TrustPolicy].
+///
+/// Hooks record their decisions in the report, e.g. with [FindingCode::PolicyViolation].
+#[async_trait]
+pub trait PolicyHook: Debug + Send + Sync {
+    async fn evaluate(
+        &self,
+        session: &Session,
+        outcome: &VerifiedPresentationOutcome,
+        report: &mut VerificationReport,
+    );
+}
+
+#[derive(Debug, Clone, Default)]
+struct IssuerRule {
+    allow: Option<BTreeSet<String>>,
+    deny: BTreeSet<String>,
+}
+
+/// Relying party policy on the credentials of a verified response, evaluated by the
+/// [Verifier](super::Verifier) after the [ResponseValidator](super::validator::ResponseValidator)
+/// (see [VerifierBuilder::with_trust_policy](super::VerifierBuilder::with_trust_policy)).
+///
+/// The policy is evaluated against the [VerifiedPresentationOutcome] of the response, so the
+/// validator is responsible for verifying signatures, certificate chains and status, and for
+/// reporting the results in each [VerifiedCredential]. The decisions are recorded in the
+/// [VerificationReport] of the response, violations as fatal findings.
+#[derive(Debug, Clone, Default)]
+pub struct TrustPolicy {
+    issuers: BTreeMap<String, IssuerRule>,
+    x5c_roots: Option<Vec<String>>,
+    require_status: bool,
+    max_credential_age: Option<Duration>,
+    hooks: Vec<Arc<dyn PolicyHook>>,
+}
+
+impl TrustPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only accept credentials of `credential_type` from these issuers. Can be called several
+    /// times to extend the allowlist, use [ANY_CREDENTIAL_TYPE] for all credentials.
+    pub fn allow_issuers<I, S>(mut self, credential_type: impl Into<String>, issuers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.issuers
+            .entry(credential_type.into())
+            .or_default()
+            .allow
+            .get_or_insert_with(Default::default)
+            .extend(issuers.into_iter().map(Into::into));
+        self
+    }
+
+    /// Reject credentials of `credential_type` from these issuers, use [ANY_CREDENTIAL_TYPE] for
+    /// all credentials.
+    pub fn deny_issuers<I, S>(mut self, credential_type: impl Into<String>, issuers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.issuers
+            .entry(credential_type.into())
+            .or_default()
+            .deny
+            .extend(issuers.into_iter().map(Into::into));
+        self
+    }
+
+    /// Require the issuer certificate chain of every credential to include one of these roots.
+    pub fn require_x5c_roots(mut self, roots: &[Certificate]) -> anyhow::Result<Self> {
+        let roots = roots
+            .iter()
+            .map(|root| Ok(BASE64_STANDARD.encode(root.to_der()?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        self.x5c_roots.get_or_insert_with(Vec::new).extend(roots);
+        Ok(self)
+    }
+
+    /// Require the status of every credential to have been checked and found valid.
+    ///
+    /// Revoked and suspended credentials are always rejected. Without this requirement, an
+    /// unavailable status check is only a warning.
+    pub fn require_status(mut self) -> Self {
+        self.require_status = true;
+        self
+    }
+
+    /// Reject credentials issued more than `max_age` ago, or whose issuance date is unknown.
+    pub fn with_max_credential_age(mut self, max_age: Duration) -> Self {
+        self.max_credential_age = Some(max_age);
+        self
+    }
+
+    /// Add a custom rule, evaluated after the built-in rules.
+    pub fn with_hook(mut self, hook: Arc<dyn PolicyHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Evaluate the policy, recording the violations in `report`.
+    pub async fn evaluate(
+        &self,
+        session: &Session,
+        outcome: &VerifiedPresentationOutcome,
+        report: &mut VerificationReport,
+        now: SystemTime,
+    ) {
+        for credential in &outcome.credentials {
+            self.check_issuer(credential, report);
+            self.check_chain(credential, report);
+            self.check_status(credential, report);
+            self.check_age(credential, report, now);
+        }
+        for hook in &self.hooks {
+            hook.evaluate(session, outcome, report).await;
+        }
+    }
+
+    fn check_issuer(&self, credential: &VerifiedCredential, report: &mut VerificationReport) {
+        let issuer = credential.issuer.as_deref();
+        let rules = credential
+            .credential_types
+            .iter()
+            .map(String::as_str)
+            .chain([ANY_CREDENTIAL_TYPE])
+            .filter_map(|credential_type| {
+                Some((credential_type, self.issuers.get(credential_type)?))
+            });
+        for (credential_type, rule) in rules {
+            let denied = issuer.is_some_and(|issuer| rule.deny.contains(issuer));
+            let allowed = rule
+                .allow
+                .as_ref()
+                .is_none_or(|allow| issuer.is_some_and(|issuer| allow.contains(issuer)));
+            if denied || !allowed {
+                report.fatal(
+                    FindingCode::UntrustedIssuer,
+                    format!(
+                        "the issuer '{}' of the credential presented for '{}' is not trusted for '{credential_type}'",
+                        issuer.unwrap_or("unknown"),
+                        credential.input_descriptor_id
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_chain(&self, credential: &VerifiedCredential, report: &mut VerificationReport) {
+        let Some(roots) = &self.x5c_roots else {
+            return;
+        };
+        if !credential
+            .issuer_x5c
+            .iter()
+            .any(|certificate| roots.contains(certificate))
+        {
+            report.fatal(
+                FindingCode::UntrustedChain,
+                format!(
+                    "the issuer of the credential presented for '{}' does not chain to a trusted root",
+                    credential.input_descriptor_id
+                ),
+            );
+        }
+    }
+
+    fn check_status(&self, credential: &VerifiedCredential, report: &mut VerificationReport) {
+        let id = &credential.input_descriptor_id;
+        match credential.status {
+            Some(CredentialStatus::Valid) => {}
+            Some(status @ (CredentialStatus::Revoked | CredentialStatus::Suspended)) => report
+                .fatal(
+                    FindingCode::InvalidStatus,
+                    format!("the credential presented for '{id}' is {status:?}"),
+                ),
+            Some(CredentialStatus::Unavailable) | None if self.require_status => report.fatal(
+                FindingCode::StatusCheckUnavailable,
+                format!("the status of the credential presented for '{id}' was not checked"),
+            ),
+            Some(CredentialStatus::Unavailable) => report.warn(
+                FindingCode::StatusCheckUnavailable,
+                format!("the status of the credential presented for '{id}' could not be checked"),
+            ),
+            None => {}
+        }
+    }
+
+    fn check_age(
+        &self,
+        credential: &VerifiedCredential,
+        report: &mut VerificationReport,
+        now: SystemTime,
+    ) {
+        let Some(max_age) = self.max_credential_age else {
+            return;
+        };
+        let id = &credential.input_descriptor_id;
+        let Some(issued_at) = credential.issued_at else {
+            report.fatal(
+                FindingCode::CredentialTooOld,
+                format!("the issuance date of the credential presented for '{id}' is unknown"),
+            );
+            return;
+        };
+        let issued_at = UNIX_EPOCH + Duration::from_secs(issued_at);
+        if now.duration_since(issued_at).is_ok_and(|age| age > max_age) {
+            report.fatal(
+                FindingCode::CredentialTooOld,
+                format!("the credential presented for '{id}' was issued too long ago"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::{core::credential_format::ClaimFormatDesignation, verifier::session::test_session};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct RequireHolder;
+
+    #[async_trait]
+    impl PolicyHook for RequireHolder {
+        async fn evaluate(
+            &self,
+            _: &Session,
+            outcome: &VerifiedPresentationOutcome,
+            report: &mut VerificationReport,
+        ) {
+            if outcome.holder.is_none() {
+                report.fatal(FindingCode::PolicyViolation, "the holder is unknown");
+            }
+        }
+    }
+
+    fn credential(issuer: &str) -> VerifiedCredential {
+        VerifiedCredential::new("id".into(), ClaimFormatDesignation::JwtVcJson)
+            .with_issuer(issuer.into())
+            .with_credential_types(vec!["VerifiableCredential".into(), "IdCard".into()])
+            .with_issued_at(1_000)
+            .with_status(CredentialStatus::Valid)
+    }
+
+    async fn evaluate(
+        policy: &TrustPolicy,
+        outcome: VerifiedPresentationOutcome,
+    ) -> VerificationReport {
+        let mut report = VerificationReport::new();
+        policy
+            .evaluate(
+                &test_session(json!({
+                    "presentation_definition": { "id": "pd", "input_descriptors": [] }
+                })),
+                &outcome,
+                &mut report,
+                UNIX_EPOCH + Duration::from_secs(2_000),
+            )
+            .await;
+        report
+    }
+
+    #[tokio::test]
+    async fn issuer_rules() {
+        let policy = TrustPolicy::new()
+            .allow_issuers("IdCard", ["did:example:government"])
+            .deny_issuers(ANY_CREDENTIAL_TYPE, ["did:example:compromised"]);
+
+        let outcome = VerifiedPresentationOutcome::new(None)
+            .with_credential(credential("did:example:government"));
+        assert!(evaluate(&policy, outcome).await.is_success());
+
+        let outcome =
+            VerifiedPresentationOutcome::new(None).with_credential(credential("did:example:other"));
+        assert!(evaluate(&policy, outcome)
+            .await
+            .contains(&FindingCode::UntrustedIssuer));
+
+        let chained = TrustPolicy::new().require_x5c_roots(&[]).unwrap();
+        let outcome = VerifiedPresentationOutcome::new(None)
+            .with_credential(credential("did:example:government"));
+        assert!(evaluate(&chained, outcome)
+            .await
+            .contains(&FindingCode::UntrustedChain));
+
+        let policy = policy.allow_issuers("IdCard", ["did:example:compromised"]);
+        let outcome = VerifiedPresentationOutcome::new(None)
+            .with_credential(credential("did:example:compromised"));
+        assert!(evaluate(&policy, outcome)
+            .await
+            .contains(&FindingCode::UntrustedIssuer));
+    }
+
+    #[tokio::test]
+    async fn status_age_and_hooks() {
+        let policy = TrustPolicy::new()
+            .require_status()
+            .with_max_credential_age(Duration::from_secs(500))
+            .with_hook(Arc::new(RequireHolder));
+
+        let outcome = VerifiedPresentationOutcome::new(None).with_credential(
+            credential("did:example:issuer").with_status(CredentialStatus::Unavailable),
+        );
+        let report = evaluate(&policy, outcome).await;
+        assert!(report.contains(&FindingCode::StatusCheckUnavailable));
+        assert!(report.contains(&FindingCode::CredentialTooOld));
+        assert!(report.contains(&FindingCode::PolicyViolation));
+        assert_eq!(report.warnings().count(), 0);
+
+        let outcome = VerifiedPresentationOutcome::new(None).with_credential(
+            credential("did:example:issuer").with_status(CredentialStatus::Unavailable),
+        );
+        let report = evaluate(&TrustPolicy::new(), outcome).await;
+        assert!(report.is_success());
+        assert_eq!(report.warnings().count(), 1);
+
+        let outcome = VerifiedPresentationOutcome::new(None).with_credential(
+            credential("did:example:issuer").with_status(CredentialStatus::Revoked),
+        );
+        assert!(evaluate(&TrustPolicy::new(), outcome)
+            .await
+            .contains(&FindingCode::InvalidStatus));
+    }
+}
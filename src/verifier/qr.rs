@@ -0,0 +1,261 @@
+//! QR codes of wallet invocation URLs, for cross-device flows where the wallet runs on another
+//! device than the one displaying the request.
+//!
+//! ```ignore
+//! let (url, session) = verifier.begin_session().await?;
+//! let qr = RequestQrCode::new(&url)?;
+//! if let Some(warning) = qr.warning() {
+//!     eprintln!("{warning}");
+//! }
+//! let svg = qr.to_svg();
+//! ```
+
+use std::{fmt, io::Cursor};
+
+use anyhow::{bail, Context, Result};
+use image::{DynamicImage, ImageFormat, Luma};
+use qrcode::{render::svg, types::QrError, QrCode, Version};
+use tracing::warn;
+use url::Url;
+
+pub use qrcode::EcLevel;
+
+/// The largest QR code version (117×117 modules) that phone cameras scan reliably from a screen.
+/// The capacity of larger codes is rarely usable in practice.
+pub const MAX_PRACTICAL_VERSION: i16 = 25;
+
+/// How a [RequestQrCode] is encoded and rendered.
+#[derive(Debug, Clone)]
+pub struct QrCodeOptions {
+    ec_level: EcLevel,
+    module_size: u32,
+    quiet_zone: bool,
+    max_version: i16,
+}
+
+impl Default for QrCodeOptions {
+    /// Medium error correction, 8 pixels per module, with a quiet zone, and a warning above
+    /// [MAX_PRACTICAL_VERSION].
+    fn default() -> Self {
+        Self {
+            ec_level: EcLevel::M,
+            module_size: 8,
+            quiet_zone: true,
+            max_version: MAX_PRACTICAL_VERSION,
+        }
+    }
+}
+
+impl QrCodeOptions {
+    /// The error correction level. Higher levels tolerate more damage, e.g. glare on a screen,
+    /// at the cost of capacity.
+    pub fn with_ec_level(mut self, ec_level: EcLevel) -> Self {
+        self.ec_level = ec_level;
+        self
+    }
+
+    /// The size of a module, in pixels for PNGs and in user units for SVGs.
+    pub fn with_module_size(mut self, module_size: u32) -> Self {
+        self.module_size = module_size.max(1);
+        self
+    }
+
+    /// Whether to surround the code with the blank margin scanners expect. Only disable it if
+    /// the code is displayed with its own margin.
+    pub fn with_quiet_zone(mut self, quiet_zone: bool) -> Self {
+        self.quiet_zone = quiet_zone;
+        self
+    }
+
+    /// The largest version that is rendered without a [CapacityWarning].
+    pub fn with_max_version(mut self, max_version: i16) -> Self {
+        self.max_version = max_version;
+        self
+    }
+}
+
+/// A wallet invocation URL, encoded as a QR code.
+pub struct RequestQrCode {
+    code: QrCode,
+    options: QrCodeOptions,
+    warning: Option<CapacityWarning>,
+}
+
+impl fmt::Debug for RequestQrCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestQrCode")
+            .field("version", &self.version())
+            .field("width", &self.width())
+            .field("options", &self.options)
+            .field("warning", &self.warning)
+            .finish()
+    }
+}
+
+impl RequestQrCode {
+    /// Encode `url` with the [default options](QrCodeOptions::default).
+    pub fn new(url: &Url) -> Result<Self> {
+        Self::with_options(url, QrCodeOptions::default())
+    }
+
+    /// Encode `url`.
+    ///
+    /// Fails if the URL exceeds the capacity of the largest QR code at the error correction
+    /// level, which happens with requests passed by value. A [CapacityWarning] is logged, and
+    /// returned by [warning](Self::warning), if the code is larger than the maximum version of
+    /// the options.
+    pub fn with_options(url: &Url, options: QrCodeOptions) -> Result<Self> {
+        let code = match QrCode::with_error_correction_level(url.as_str(), options.ec_level) {
+            Ok(code) => code,
+            Err(QrError::DataTooLong) => bail!(
+                "the URL ({} characters) does not fit in a QR code at error correction level \
+                 {:?}{}",
+                url.as_str().len(),
+                options.ec_level,
+                if carries_request(url) {
+                    ", pass the request by reference instead"
+                } else {
+                    ""
+                }
+            ),
+            Err(e) => return Err(e).context("failed to encode the URL as a QR code"),
+        };
+
+        let version = version_number(&code);
+        let warning = (version > options.max_version).then(|| CapacityWarning {
+            version,
+            url_length: url.as_str().len(),
+            by_value: carries_request(url),
+        });
+        if let Some(warning) = &warning {
+            warn!("{warning}");
+        }
+
+        Ok(Self {
+            code,
+            options,
+            warning,
+        })
+    }
+
+    /// The version of the code, from 1 (21×21 modules) to 40 (177×177 modules).
+    pub fn version(&self) -> i16 {
+        version_number(&self.code)
+    }
+
+    /// The number of modules per side, without the quiet zone.
+    pub fn width(&self) -> usize {
+        self.code.width()
+    }
+
+    /// Why the code may not scan reliably, if it is larger than practical.
+    pub fn warning(&self) -> Option<&CapacityWarning> {
+        self.warning.as_ref()
+    }
+
+    /// Render the code as an SVG document.
+    pub fn to_svg(&self) -> String {
+        self.code
+            .render::<svg::Color>()
+            .module_dimensions(self.options.module_size, self.options.module_size)
+            .quiet_zone(self.options.quiet_zone)
+            .build()
+    }
+
+    /// Render the code as a grayscale PNG image.
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let image = self
+            .code
+            .render::<Luma<u8>>()
+            .module_dimensions(self.options.module_size, self.options.module_size)
+            .quiet_zone(self.options.quiet_zone)
+            .build();
+        let mut png = Vec::new();
+        DynamicImage::ImageLuma8(image)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .context("failed to encode the QR code as a PNG")?;
+        Ok(png)
+    }
+}
+
+/// A QR code that is larger than phone cameras reliably scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapacityWarning {
+    pub version: i16,
+    pub url_length: usize,
+    /// Whether the URL carries the request by value, rather than a `request_uri`.
+    pub by_value: bool,
+}
+
+impl fmt::Display for CapacityWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the QR code of the {} character URL is version {}, which may not scan reliably",
+            self.url_length, self.version
+        )?;
+        if self.by_value {
+            write!(f, ", pass the request by reference instead")?;
+        }
+        Ok(())
+    }
+}
+
+fn version_number(code: &QrCode) -> i16 {
+    match code.version() {
+        Version::Normal(version) | Version::Micro(version) => version,
+    }
+}
+
+/// Whether `url` carries the request, signed or not, instead of referencing it.
+fn carries_request(url: &Url) -> bool {
+    !url.query_pairs().any(|(key, _)| key == "request_uri")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let url: Url = "openid4vp://?client_id=verifier.example.com&request_uri=https%3A%2F%2Fverifier.example.com%2Frequest%2F0"
+            .parse()
+            .unwrap();
+        let qr = RequestQrCode::new(&url).unwrap();
+        assert!(qr.warning().is_none());
+        assert_eq!(qr.width(), 17 + 4 * qr.version() as usize);
+
+        let svg = qr.to_svg();
+        assert!(svg.starts_with("<?xml"));
+        assert!(svg.contains("<svg"));
+
+        let png = qr.to_png().unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn capacity() {
+        let url = |length: usize| -> Url {
+            format!(
+                "openid4vp://?client_id=verifier&request={}",
+                "a".repeat(length)
+            )
+            .parse()
+            .unwrap()
+        };
+
+        let qr = RequestQrCode::new(&url(1500)).unwrap();
+        let warning = qr.warning().unwrap();
+        assert!(warning.by_value);
+        assert!(warning.version > MAX_PRACTICAL_VERSION);
+        assert!(warning.to_string().contains("by reference"));
+
+        let qr =
+            RequestQrCode::with_options(&url(1500), QrCodeOptions::default().with_max_version(40))
+                .unwrap();
+        assert!(qr.warning().is_none());
+
+        let error = RequestQrCode::new(&url(3000)).unwrap_err();
+        assert!(error.to_string().contains("by reference"));
+    }
+}
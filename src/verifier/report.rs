@@ -0,0 +1,237 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+
+use crate::core::presentation_definition::DescriptorMapErrors;
+
+use super::{outcome::VerifiedPresentationOutcome, session::Outcome};
+
+/// Machine-readable code identifying a [Finding].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingCode {
+    /// A signature on the response, a presentation or a credential could not be verified.
+    InvalidSignature,
+    /// The nonce in a presentation did not match the nonce in the request.
+    NonceMismatch,
+    /// The state in the response did not match the state in the request.
+    StateMismatch,
+    /// A holder proof did not bind the `transaction_data` from the request.
+    TransactionDataMismatch,
+    /// The presentation submission did not match the presentation definition.
+    InvalidSubmission,
+    /// A non-critical parameter was not understood.
+    UnknownParameter,
+    /// A credential status check could not be completed.
+    StatusCheckUnavailable,
+    /// A timestamp was outside of the expected range, but within the tolerated clock skew.
+    ClockSkew,
+    /// The response was received after the session expired.
+    SessionExpired,
+    /// The response was not encrypted as requested, or could not be decrypted.
+    InvalidEncryption,
+    /// The issuer of a credential is not trusted for its type.
+    UntrustedIssuer,
+    /// The issuer certificate chain of a credential does not lead to a trusted root.
+    UntrustedChain,
+    /// A credential has been revoked or suspended.
+    InvalidStatus,
+    /// A credential was issued longer ago than allowed.
+    CredentialTooOld,
+    /// A custom policy rejected the response.
+    PolicyViolation,
+    /// The `id_token` of the response was missing or invalid, or was not issued by the holder of
+    /// the presentations.
+    InvalidIdToken,
+    /// Any other finding.
+    Other(String),
+}
+
+/// Severity of a [Finding].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The finding does not invalidate the response, the relying party decides how to treat it.
+    Warning,
+    /// The finding invalidates the response.
+    Fatal,
+}
+
+/// A single result of response verification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    pub code: FindingCode,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+/// The collected results of verifying an authorization response.
+///
+/// Fatal findings (e.g. an invalid signature or a nonce mismatch) mean the response must be
+/// rejected, while warnings (e.g. an unknown non-critical parameter or a soft-failed status check)
+/// are left to the relying party's own risk policy.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationReport {
+    findings: Vec<Finding>,
+}
+
+impl VerificationReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a finding.
+    pub fn push(&mut self, code: FindingCode, severity: Severity, message: impl Into<String>) {
+        self.findings.push(Finding {
+            code,
+            severity,
+            message: message.into(),
+        })
+    }
+
+    /// Record a warning.
+    pub fn warn(&mut self, code: FindingCode, message: impl Into<String>) {
+        self.push(code, Severity::Warning, message)
+    }
+
+    /// Record a fatal error.
+    pub fn fatal(&mut self, code: FindingCode, message: impl Into<String>) {
+        self.push(code, Severity::Fatal, message)
+    }
+
+    /// Append the findings from another report.
+    pub fn merge(&mut self, other: VerificationReport) {
+        self.findings.extend(other.findings)
+    }
+
+    /// All findings, in the order they were recorded.
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// Findings with [Severity::Warning].
+    pub fn warnings(&self) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == Severity::Warning)
+    }
+
+    /// Findings with [Severity::Fatal].
+    pub fn errors(&self) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == Severity::Fatal)
+    }
+
+    /// Whether the report contains a finding with the given code.
+    pub fn contains(&self, code: &FindingCode) -> bool {
+        self.findings.iter().any(|finding| &finding.code == code)
+    }
+
+    /// Whether the report contains no fatal findings.
+    pub fn is_success(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// Convert the report into a session [Outcome].
+    ///
+    /// If there are fatal findings the outcome is a failure, otherwise it is a success with the
+    /// provided `info`. Warnings are attached to `info` under the `warnings` key when `info` is a
+    /// JSON object.
+    pub fn into_outcome(self, mut info: Json) -> Outcome {
+        if !self.is_success() {
+            return Outcome::Failure {
+                reason: self
+                    .errors()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            };
+        }
+
+        if let Json::Object(object) = &mut info {
+            let warnings: Vec<Finding> = self.warnings().cloned().collect();
+            if !warnings.is_empty() {
+                // Unwrap safety: a Finding always has a valid JSON representation.
+                object.insert("warnings".into(), serde_json::to_value(warnings).unwrap());
+            }
+        }
+
+        Outcome::Success { info }
+    }
+}
+
+impl VerificationReport {
+    /// Convert the report into a session [Outcome] carrying a [VerifiedPresentationOutcome].
+    ///
+    /// If there are fatal findings the outcome is a failure, otherwise the warnings are added to
+    /// the verified outcome.
+    pub fn into_verified_outcome(self, mut verified: VerifiedPresentationOutcome) -> Outcome {
+        if !self.is_success() {
+            return self.into_outcome(Json::Null);
+        }
+        verified.warnings.extend(self.warnings().cloned());
+        match serde_json::to_value(verified) {
+            Ok(info) => Outcome::Success { info },
+            Err(e) => Outcome::Error {
+                cause: format!("failed to serialize the verified presentation: {e}"),
+            },
+        }
+    }
+}
+
+impl From<DescriptorMapErrors> for VerificationReport {
+    fn from(DescriptorMapErrors(issues): DescriptorMapErrors) -> Self {
+        let mut report = Self::new();
+        for issue in issues {
+            report.fatal(FindingCode::InvalidSubmission, issue.to_string());
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn warnings_do_not_fail() {
+        let mut report = VerificationReport::new();
+        report.warn(FindingCode::ClockSkew, "iat is 30 seconds in the future");
+
+        assert!(report.is_success());
+        assert!(report.contains(&FindingCode::ClockSkew));
+
+        let Outcome::Success { info } = report.into_outcome(json!({ "holder": "did:example" }))
+        else {
+            panic!("expected success")
+        };
+        assert_eq!(info["warnings"][0]["code"], "clock_skew");
+        assert_eq!(info["warnings"][0]["severity"], "warning");
+    }
+
+    #[test]
+    fn fatal_fails() {
+        let mut report = VerificationReport::new();
+        report.warn(FindingCode::UnknownParameter, "unknown parameter 'foo'");
+        report.fatal(FindingCode::NonceMismatch, "nonce did not match");
+        report.fatal(FindingCode::InvalidSignature, "signature is invalid");
+
+        assert!(!report.is_success());
+        assert_eq!(report.warnings().count(), 1);
+
+        let Outcome::Failure { reason } = report.into_outcome(Json::Null) else {
+            panic!("expected failure")
+        };
+        assert_eq!(reason, "nonce did not match; signature is invalid");
+    }
+}
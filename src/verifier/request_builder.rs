@@ -1,3 +1,5 @@
+use std::{sync::Arc, time::SystemTime};
+
 use anyhow::{bail, Context, Result};
 use url::Url;
 use uuid::Uuid;
@@ -6,38 +8,125 @@ use crate::{
     core::{
         authorization_request::{
             self,
-            parameters::{ResponseMode, ResponseType, ResponseUri},
+            parameters::{
+                ClientId, ClientIdScheme, ClientMetadata, Nonce, PresentationDefinitionUri,
+                RequestUriMethod, ResponseMode, ResponseType, ResponseUri, Scope, State,
+            },
             AuthorizationRequest, AuthorizationRequestObject, RequestIndirection,
         },
-        metadata::{
-            parameters::wallet::{AuthorizationEndpoint, ClientIdSchemesSupported},
-            WalletMetadata,
-        },
-        object::{ParsingErrorContext, TypedParameter, UntypedObject},
+        dcql_query::DcqlQuery,
+        draft::Draft,
+        events::LifecycleEventKind,
+        jwe::{self, compact},
+        metadata::{parameters::verifier::JWKs, WalletMetadata},
+        object::{registry::ParameterLocation, ParsingErrorContext, TypedParameter, UntypedObject},
         presentation_definition::PresentationDefinition,
+        response::error::{ResponseModeNotSupported, ResponseTypeNotSupported},
+        util::unix_time,
+    },
+    verifier::{
+        by_reference::{self, ByReference},
+        nonce::NONCE_LENGTH,
+        response_code,
+        response_encryption::ResponseEncryptionKey,
+        session::Status,
+        stateless::{definition_hash, StatelessState, DEFAULT_STATELESS_SESSION_TTL},
+        template::SessionOverrides,
     },
-    verifier::{by_reference::ByReference, session::Status},
 };
 
-use super::{session::Session, Verifier};
+use super::{client::Client, session::Session, Verifier};
 
 #[derive(Debug, Clone)]
 #[must_use]
 pub struct RequestBuilder<'a> {
     presentation_definition: Option<PresentationDefinition>,
+    presentation_definition_uri: Option<Url>,
+    scope: Option<String>,
+    dcql_query: Option<DcqlQuery>,
     request_parameters: UntypedObject,
+    client: Arc<dyn Client + Send + Sync>,
+    tenant: Option<String>,
+    draft: Option<Draft>,
+    stateless: bool,
     verifier: &'a Verifier,
 }
 
 impl<'a> RequestBuilder<'a> {
     pub(crate) fn new(verifier: &'a Verifier) -> Self {
+        let mut request_parameters = verifier.inner.default_request_params.clone();
+        // Nonces are never shared between sessions.
+        let _ = request_parameters.remove::<Nonce>();
         Self {
             presentation_definition: None,
-            request_parameters: verifier.default_request_params.clone(),
+            presentation_definition_uri: None,
+            scope: None,
+            dcql_query: None,
+            request_parameters,
+            client: verifier.inner.client.clone(),
+            tenant: None,
+            draft: None,
+            stateless: false,
             verifier,
         }
     }
 
+    pub(crate) fn for_tenant(verifier: &'a Verifier, tenant_id: &str) -> Result<Self> {
+        let Some(tenant) = verifier.inner.tenants.get(tenant_id) else {
+            bail!("unknown tenant '{tenant_id}'")
+        };
+        let mut builder = Self::new(verifier);
+        builder
+            .request_parameters
+            .0
+            .extend(tenant.default_request_params.0.clone());
+        let _ = builder.request_parameters.remove::<Nonce>();
+        builder.client = tenant.client.clone();
+        builder.tenant = Some(tenant_id.to_owned());
+        builder.draft = tenant.draft;
+        Ok(builder)
+    }
+
+    pub(crate) fn from_template(
+        verifier: &'a Verifier,
+        template_id: &str,
+        overrides: SessionOverrides,
+    ) -> Result<Self> {
+        let Some(template) = verifier.inner.templates.get(template_id) else {
+            bail!("unknown request template '{template_id}'")
+        };
+        let mut builder = Self::new(verifier);
+        builder
+            .request_parameters
+            .0
+            .extend(template.request_parameters.0.clone());
+        builder.presentation_definition = template.presentation_definition.clone();
+        builder.dcql_query = template.dcql_query.clone();
+
+        let SessionOverrides {
+            nonce,
+            state,
+            transaction_data,
+        } = overrides;
+        if let Some(nonce) = nonce {
+            builder.request_parameters.insert(nonce);
+        }
+        if let Some(state) = state {
+            builder.request_parameters.insert(state);
+        }
+        if let Some(transaction_data) = transaction_data {
+            builder.request_parameters.insert(transaction_data);
+        }
+        Ok(builder)
+    }
+
+    /// Seal the session into its `state` and `response_uri`, instead of storing it, see
+    /// [Verifier::begin_stateless_session](super::Verifier::begin_stateless_session).
+    pub(crate) fn stateless(mut self) -> Self {
+        self.stateless = true;
+        self
+    }
+
     /// Set the presentation definition.
     pub fn with_presentation_definition(
         mut self,
@@ -47,67 +136,251 @@ impl<'a> RequestBuilder<'a> {
         self
     }
 
+    /// Pass the presentation definition by reference, with a `presentation_definition_uri`, to
+    /// wallets that [support it](WalletMetadata::supports_presentation_definition_uri). Other
+    /// wallets receive it by value.
+    ///
+    /// The application serves the presentation definition set with
+    /// [with_presentation_definition](Self::with_presentation_definition) at `uri`.
+    pub fn with_presentation_definition_uri(mut self, uri: Url) -> Self {
+        self.presentation_definition_uri = Some(uri);
+        self
+    }
+
+    /// Request the presentation definition registered for `scope` (see
+    /// [VerifierBuilder::with_scope](super::VerifierBuilder::with_scope)) with the `scope`
+    /// parameter, instead of passing it in the request.
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// Request credentials with a DCQL query, in the `dcql_query` parameter, instead of a
+    /// presentation definition.
+    ///
+    /// The response must then carry a DCQL-shaped `vp_token`, which is matched against the query.
+    pub fn with_dcql_query(mut self, dcql_query: DcqlQuery) -> Self {
+        self.dcql_query = Some(dcql_query);
+        self
+    }
+
+    /// Build the request for, and interpret the response according to, a [Draft], instead of the
+    /// draft of the [Tenant](super::tenant::Tenant), if any, or the latest this library supports.
+    ///
+    /// Building fails if the request needs parameters the draft does not define, e.g. a DCQL
+    /// query.
+    pub fn with_draft(mut self, draft: Draft) -> Self {
+        self.draft = Some(draft);
+        self
+    }
+
     /// Set or override the default authorization request parameters.
+    ///
+    /// A random [Nonce] is generated for each request, unless one is set here.
     pub fn with_request_parameter<T: TypedParameter>(mut self, t: T) -> Self {
         self.request_parameters.insert(t);
         self
     }
 
-    /// Build the request.
+    /// Build the request for a wallet whose metadata is not known, e.g. one that does not support
+    /// `request_uri_method`, assuming the [default metadata](WalletMetadata::default_for) of the
+    /// draft the request is built for, or the
+    /// [static metadata](WalletMetadata::openid4vp_scheme_static) of the latest draft.
+    pub async fn build_with_default_metadata(self) -> Result<(Uuid, Url)> {
+        let wallet_metadata = self.draft.map_or_else(
+            WalletMetadata::openid4vp_scheme_static,
+            WalletMetadata::default_for,
+        );
+        self.build(wallet_metadata).await
+    }
+
+    /// Build the request for a wallet with `wallet_metadata`, which must be
+    /// [valid](WalletMetadata::validate), [signed](WalletMetadata::verify_signed) if trust anchors
+    /// are configured (see
+    /// [VerifierBuilder::with_metadata_trust_anchors](super::VerifierBuilder::with_metadata_trust_anchors)),
+    /// and support the response type and mode of the request.
     ///
     /// ## Returns
     /// - UUID that can be used by the application frontend to poll for the status of this request.
     /// - URL that the application frontend should use to drive the user to their wallet application.
-    pub async fn build(mut self, wallet_metadata: WalletMetadata) -> Result<(Uuid, Url)> {
+    pub async fn build(mut self, mut wallet_metadata: WalletMetadata) -> Result<(Uuid, Url)> {
+        wallet_metadata
+            .validate()
+            .context("the wallet metadata is invalid")?;
+        if let Some(anchors) = &self.verifier.inner.metadata_trust_anchors {
+            wallet_metadata = wallet_metadata
+                .verify_signed(anchors)
+                .context("the wallet metadata is not trusted")?;
+        }
+
         let uuid = Uuid::new_v4();
 
-        let client_id = self.verifier.client.id();
-        let client_id_scheme = self.verifier.client.scheme();
+        let mut client_id = self.client.id().clone();
+        let client_id_scheme = self.client.scheme();
+        // Requests of the `redirect_uri` Client Identifier are not signed, see RedirectUriClient.
+        let unsigned = client_id_scheme == &ClientIdScheme::RedirectUri;
+
+        if !self.request_parameters.contains::<Nonce>() {
+            let _ = self
+                .request_parameters
+                .insert(Nonce::random(&mut rand::thread_rng(), NONCE_LENGTH));
+        }
 
         let _ = self.request_parameters.insert(client_id.clone());
         let _ = self.request_parameters.insert(client_id_scheme.clone());
 
-        let Some(presentation_definition) = self.presentation_definition else {
-            bail!("presentation definition is required, see `with_presentation_definition`")
-        };
+        let (presentation_definition, dcql_query) =
+            match (self.presentation_definition, self.scope, self.dcql_query) {
+                (None, None, None) => {
+                    bail!("presentation definition is required, see `with_presentation_definition`")
+                }
+                (Some(presentation_definition), None, None) => {
+                    match self.presentation_definition_uri {
+                        Some(uri) if wallet_metadata.supports_presentation_definition_uri() => {
+                            let _ = self
+                                .request_parameters
+                                .insert(PresentationDefinitionUri(uri));
+                        }
+                        _ => {
+                            let _ = self.request_parameters.insert(
+                                authorization_request::parameters::PresentationDefinition::try_from(
+                                    presentation_definition.clone(),
+                                )
+                                .context(
+                                    "failed to construct PresentationDefinition request parameter",
+                                )?,
+                            );
+                        }
+                    }
+                    (Some(presentation_definition), None)
+                }
+                _ if self.presentation_definition_uri.is_some() => {
+                    bail!("a presentation_definition_uri requires a presentation definition")
+                }
+                (None, Some(scope), None) => {
+                    let scope = Scope::new(scope);
+                    let presentation_definition =
+                        self.verifier.inner.scopes.resolve(&scope)?.clone();
+                    let _ = self.request_parameters.insert(scope);
+                    (Some(presentation_definition), None)
+                }
+                (None, None, Some(dcql_query)) => {
+                    dcql_query.validate().context("invalid DCQL query")?;
+                    let _ = self.request_parameters.insert(dcql_query.clone());
+                    (None, Some(dcql_query))
+                }
+                _ => bail!(
+                    "a presentation definition, a scope and a DCQL query are mutually exclusive"
+                ),
+            };
 
-        let _ = self.request_parameters.insert(
-            authorization_request::parameters::PresentationDefinition::try_from(
-                presentation_definition.clone(),
-            )
-            .context("failed to construct PresentationDefinition request parameter")?,
-        );
+        if let Some(draft) = self.draft {
+            draft
+                .adapt_request(&mut self.request_parameters)
+                .with_context(|| format!("the request cannot be built for {draft}"))?;
+        }
 
-        let _ = self
+        let registry = &self.verifier.inner.parameter_registry;
+        registry
+            .validate(ParameterLocation::Request, &self.request_parameters)
+            .context("the request has an invalid parameter")?;
+        if let Some(client_metadata) = self.request_parameters.get::<ClientMetadata>() {
+            registry
+                .validate(
+                    ParameterLocation::ClientMetadata,
+                    &client_metadata.parsing_error()?.0,
+                )
+                .context("the client metadata has an invalid parameter")?;
+        }
+
+        let response_type = self
             .request_parameters
             .get::<ResponseType>()
             .context("response type is required, see `with_request_parameter`")?
             .context("error occurred when retrieving response type")?;
+        if !wallet_metadata.supports_response_type(&response_type) {
+            bail!(ResponseTypeNotSupported(response_type))
+        }
 
-        match self
+        let response_mode = self
             .request_parameters
             .get::<ResponseMode>()
             .context("response mode is required, see `with_request_parameter`")?
-            .context("error occurred when retrieving response mode")?
-        {
+            .context("error occurred when retrieving response mode")?;
+        if !wallet_metadata.supports_response_mode(&response_mode) {
+            bail!(ResponseModeNotSupported(response_mode))
+        }
+
+        if unsigned && self.request_parameters.contains::<RequestUriMethod>() {
+            bail!("unsigned requests must not have a 'request_uri_method'")
+        }
+
+        let created_at = SystemTime::now();
+        let stateless_token = if self.stateless {
+            let token = Self::seal_state(
+                self.verifier,
+                &self.request_parameters,
+                uuid,
+                created_at,
+                &presentation_definition,
+                &dcql_query,
+                &response_mode,
+            )?;
+            self.request_parameters.insert(State(token.clone()));
+            Some(token)
+        } else {
+            None
+        };
+
+        match &response_mode {
             ResponseMode::DirectPost | ResponseMode::DirectPostJwt => {
-                let mut uri = self.verifier.submission_endpoint.clone();
+                let mut uri = self.verifier.inner.submission_endpoint.clone();
                 {
                     let Ok(mut path) = uri.path_segments_mut() else {
                         bail!("invalid base URL for the submission endpoint")
                     };
-                    path.push(&uuid.to_string());
+                    match &stateless_token {
+                        Some(token) => path.push(token),
+                        None => path.push(&uuid.to_string()),
+                    };
+                }
+                if unsigned {
+                    // The client_id of unsigned requests is the URI the response is sent to.
+                    client_id = ClientId(uri.to_string());
+                    self.request_parameters.insert(client_id.clone());
                 }
                 self.request_parameters.insert(ResponseUri(uri));
             }
             ResponseMode::Unsupported(r) => bail!("unsupported response_mode: {r}"),
         }
 
-        if !wallet_metadata
-            .get_or_default::<ClientIdSchemesSupported>()?
-            .0
-            .contains(client_id_scheme)
-        {
+        // Encrypted responses are encrypted to the static keys of the verifier, if any, or to a key
+        // generated for the session, unless the client metadata already publishes keys.
+        let mut response_encryption_key = None;
+        if response_mode == ResponseMode::DirectPostJwt {
+            let mut client_metadata = match self.request_parameters.get::<ClientMetadata>() {
+                Some(client_metadata) => client_metadata.parsing_error()?,
+                None => ClientMetadata(UntypedObject::default()),
+            };
+            if !client_metadata.0.contains::<JWKs>() {
+                if let Some(keys) = &self.verifier.inner.response_encryption_keys {
+                    keys.publish(&mut client_metadata, created_at)?;
+                } else if self.stateless {
+                    bail!("stateless sessions with encrypted responses require static response encryption keys, see `with_response_encryption_keys`")
+                } else {
+                    let key = ResponseEncryptionKey::generate(
+                        self.verifier.inner.response_encryption_curve,
+                        &mut rand::thread_rng(),
+                    )
+                    .context("failed to generate the response encryption key")?;
+                    key.publish(&mut client_metadata);
+                    response_encryption_key = Some(key);
+                }
+                self.request_parameters.insert(client_metadata);
+            }
+        }
+
+        if !wallet_metadata.supports_client_id_scheme(client_id_scheme) {
             bail!("the wallet does not support the client_id_scheme '{client_id_scheme}'")
         }
 
@@ -116,32 +389,58 @@ impl<'a> RequestBuilder<'a> {
                 "unable to construct the Authorization Request from provided request parameters",
             )?;
 
-        let authorization_request_jwt = self
-            .verifier
+        let mut authorization_request_jwt = self
             .client
             .generate_request_object_jwt(&authorization_request_object)
             .await?;
 
+        // Signed Request Objects are encrypted to the wallet, if it publishes keys to do so.
+        if !unsigned {
+            if let Some(encryption) = jwe::negotiate_request_object(&wallet_metadata) {
+                authorization_request_jwt = compact::encrypt(
+                    &encryption,
+                    authorization_request_jwt.as_bytes(),
+                    &[],
+                    &[],
+                    &mut rand::thread_rng(),
+                )
+                .context("failed to encrypt the Request Object")?;
+            }
+        }
+
         let mut initial_status = Status::SentRequest;
+        let mut request_uri_secret = None;
 
-        let request_indirection = match self.verifier.pass_by_reference.clone() {
+        let request_indirection = match self.verifier.inner.pass_by_reference.clone() {
+            ByReference::False if unsigned => {
+                RequestIndirection::Unsigned(authorization_request_object.clone().into())
+            }
             ByReference::False => RequestIndirection::ByValue(authorization_request_jwt.clone()),
+            ByReference::True { .. } if unsigned => {
+                bail!("unsigned requests cannot be passed by reference, as they have no Request Object")
+            }
+            ByReference::True { .. } if self.stateless => {
+                bail!("stateless requests cannot be passed by reference, as they are not stored")
+            }
             ByReference::True { mut at } => {
+                let secret = by_reference::generate_secret();
                 {
                     let Ok(mut path) = at.path_segments_mut() else {
                         bail!("invalid base URL for Authorization Request by reference")
                     };
-                    path.push(&uuid.to_string());
+                    path.push(&by_reference::token(uuid, &secret));
                 }
+                request_uri_secret = Some(secret);
                 initial_status = Status::SentRequestByReference;
                 RequestIndirection::ByReference(at)
             }
         };
 
         let authorization_endpoint = wallet_metadata
-            .get::<AuthorizationEndpoint>()
-            .parsing_error()?
-            .0;
+            .preferred_authorization_endpoint(
+                &self.verifier.inner.preferred_authorization_endpoints,
+            )
+            .clone();
 
         let authorization_request_url = AuthorizationRequest {
             client_id: client_id.0.clone(),
@@ -156,14 +455,90 @@ impl<'a> RequestBuilder<'a> {
             authorization_request_jwt,
             authorization_request_object,
             presentation_definition,
+            dcql_query,
+            response_encryption_key,
+            request_uri_secret,
+            response_code: self
+                .verifier
+                .inner
+                .response_redirect_uri
+                .as_ref()
+                .map(|_| response_code::generate()),
+            response_digest: None,
+            mdoc_generated_nonce: None,
+            created_at,
+            tenant: self.tenant,
+            draft: self.draft,
         };
 
-        self.verifier
-            .session_store
-            .initiate(session)
-            .await
-            .context("failed to store the session in the session store")?;
+        let created = (self.verifier.inner.metrics.is_some()
+            || self.verifier.inner.event_subscriber.is_some())
+        .then(|| session.clone());
+
+        if !self.stateless {
+            self.verifier
+                .inner
+                .session_store
+                .initiate(session)
+                .await
+                .context("failed to store the session in the session store")?;
+        }
+
+        if let Some(session) = created {
+            if let Some(metrics) = &self.verifier.inner.metrics {
+                metrics.session_created(&session);
+            }
+            self.verifier
+                .emit_event(LifecycleEventKind::RequestCreated, &session);
+        }
 
         Ok((uuid, authorization_request_url))
     }
+
+    /// Seal the [StatelessState] of a session.
+    fn seal_state(
+        verifier: &Verifier,
+        request_parameters: &UntypedObject,
+        uuid: Uuid,
+        created_at: SystemTime,
+        presentation_definition: &Option<PresentationDefinition>,
+        dcql_query: &Option<DcqlQuery>,
+        response_mode: &ResponseMode,
+    ) -> Result<String> {
+        let Some(state_key) = &verifier.inner.state_key else {
+            bail!("stateless sessions require a state key, see `stateless`")
+        };
+        let definition = match (presentation_definition, dcql_query) {
+            (Some(presentation_definition), _) => serde_json::to_value(presentation_definition)?,
+            (None, Some(dcql_query)) => serde_json::to_value(dcql_query)?,
+            (None, None) => {
+                bail!("stateless sessions require a presentation definition or a DCQL query")
+            }
+        };
+        let response_encryption_kids =
+            match (response_mode, &verifier.inner.response_encryption_keys) {
+                (ResponseMode::DirectPostJwt, Some(keys)) => keys
+                    .published(created_at)
+                    .iter()
+                    .filter_map(|jwk| jwk.get("kid")?.as_str().map(ToOwned::to_owned))
+                    .collect(),
+                _ => Vec::new(),
+            };
+        let ttl = verifier
+            .inner
+            .session_ttl
+            .unwrap_or(DEFAULT_STATELESS_SESSION_TTL);
+        let state = StatelessState {
+            session: uuid,
+            nonce: request_parameters
+                .get::<Nonce>()
+                .parsing_error()?
+                .to_string(),
+            definition_hash: definition_hash(&definition)?,
+            created_at: unix_time(created_at)?,
+            expires_at: unix_time(created_at + ttl)?,
+            response_encryption_kids,
+        };
+        state_key.seal(&state, &mut rand::thread_rng())
+    }
 }
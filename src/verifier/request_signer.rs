@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use p256::ecdsa::{signature::Signer, Signature, SigningKey};
 use ssi::claims::jws::{JwsSigner, JwsSignerInfo};
@@ -6,8 +6,70 @@ use ssi::jwk::Algorithm;
 
 use ssi::jwk::JWK;
 
+use serde_json::{json, Value as Json};
+
 use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
 
+/// Signs authorization request objects.
+///
+/// Signing is asynchronous and fallible, so that keys held in a remote KMS, an HSM (e.g. via
+/// PKCS#11) or a secure enclave can be used. For example, a signer backed by a KMS that returns
+/// DER-encoded ECDSA signatures:
+///
+/// ```
+/// use anyhow::{Context, Result};
+/// use async_trait::async_trait;
+/// use openid4vp::verifier::request_signer::RequestSigner;
+/// use ssi::jwk::JWK;
+///
+/// /// A client for a remote key management service.
+/// #[derive(Debug)]
+/// struct KmsClient;
+///
+/// impl KmsClient {
+///     async fn sign(&self, key_id: &str, message: &[u8]) -> Result<Vec<u8>> {
+///         // Call the KMS API.
+///         # let _ = (key_id, message);
+///         # anyhow::bail!("not connected")
+///     }
+/// }
+///
+/// #[derive(Debug)]
+/// struct KmsSigner {
+///     client: KmsClient,
+///     key_id: String,
+///     public_jwk: JWK,
+/// }
+///
+/// #[async_trait]
+/// impl RequestSigner for KmsSigner {
+///     type Error = anyhow::Error;
+///
+///     fn alg(&self) -> Result<String> {
+///         Ok("ES256".into())
+///     }
+///
+///     fn jwk(&self) -> Result<JWK> {
+///         Ok(self.public_jwk.clone())
+///     }
+///
+///     fn kid(&self) -> Option<String> {
+///         Some(self.key_id.clone())
+///     }
+///
+///     async fn sign(&self, payload: &[u8]) -> Result<Vec<u8>> {
+///         let der = self
+///             .client
+///             .sign(&self.key_id, payload)
+///             .await
+///             .context("the KMS failed to sign the request")?;
+///         // JWS uses the fixed size `r || s` encoding of ECDSA signatures.
+///         Ok(p256::ecdsa::Signature::from_der(&der)?.to_vec())
+///     }
+/// }
+/// ```
 #[async_trait]
 pub trait RequestSigner: Debug {
     type Error: std::fmt::Display;
@@ -18,18 +80,195 @@ pub trait RequestSigner: Debug {
     /// The public JWK of the signer.
     fn jwk(&self) -> Result<JWK, Self::Error>;
 
+    /// The key id to set in the `kid` header of signed requests, if any.
+    fn kid(&self) -> Option<String> {
+        None
+    }
+
     /// Sign the payload and return the signature.
-    async fn sign(&self, payload: &[u8]) -> Vec<u8>;
+    async fn sign(&self, payload: &[u8]) -> Result<Vec<u8>, Self::Error>;
+}
 
-    /// Attempt to sign the payload and return the signature.
-    async fn try_sign(&self, payload: &[u8]) -> Result<Vec<u8>, Self::Error> {
-        // default implementation will call sign.
-        // Override for custom error handling.
-        Ok(self.sign(payload).await)
-    }
+/// The algorithms of the [RequestSigners](RequestSigner) of this build, which depend on the
+/// enabled features. [P256Signer] (`ES256`) is always available.
+pub fn implemented_algorithms() -> Vec<&'static str> {
+    [
+        ("ES256", true),
+        ("EdDSA", cfg!(feature = "eddsa")),
+        ("ES384", cfg!(feature = "es384")),
+        ("ES512", cfg!(feature = "es512")),
+        ("RS256", cfg!(feature = "rs256")),
+    ]
+    .into_iter()
+    .filter_map(|(alg, enabled)| enabled.then_some(alg))
+    .collect()
+}
+
+/// A [RequestSigner] that rotates between several keys, identified by key ids.
+///
+/// New requests are signed with the current key, which is the last key added unless set with
+/// [RotatingSigner::set_current]. Previous keys stay published in the [jwks](RotatingSigner::jwks)
+/// until their retirement time, so requests signed before a rotation can still be verified.
+///
+/// Keys can be rotated while the signer is in use by a [Verifier](super::Verifier).
+#[derive(Debug)]
+pub struct RotatingSigner {
+    keys: RwLock<RotatingKeys>,
 }
 
 #[derive(Debug)]
+struct RotatingKeys {
+    keys: Vec<RotatingKey>,
+    current: String,
+}
+
+#[derive(Debug, Clone)]
+struct RotatingKey {
+    kid: String,
+    signer: Arc<dyn RequestSigner<Error = anyhow::Error> + Send + Sync>,
+    retire_at: Option<SystemTime>,
+}
+
+impl RotatingSigner {
+    /// Create a signer with a single, current, key.
+    pub fn new(
+        kid: impl Into<String>,
+        signer: Arc<dyn RequestSigner<Error = anyhow::Error> + Send + Sync>,
+    ) -> Self {
+        let kid = kid.into();
+        Self {
+            keys: RwLock::new(RotatingKeys {
+                keys: vec![RotatingKey {
+                    kid: kid.clone(),
+                    signer,
+                    retire_at: None,
+                }],
+                current: kid,
+            }),
+        }
+    }
+
+    /// Add a key, and make it the current key.
+    pub fn add_key(
+        &self,
+        kid: impl Into<String>,
+        signer: Arc<dyn RequestSigner<Error = anyhow::Error> + Send + Sync>,
+    ) -> Result<()> {
+        let kid = kid.into();
+        let mut keys = self.write()?;
+        if keys.keys.iter().any(|key| key.kid == kid) {
+            bail!("key '{kid}' already exists")
+        }
+        keys.keys.push(RotatingKey {
+            kid: kid.clone(),
+            signer,
+            retire_at: None,
+        });
+        keys.current = kid;
+        Ok(())
+    }
+
+    /// Sign new requests with a previously added key.
+    pub fn set_current(&self, kid: &str) -> Result<()> {
+        let mut keys = self.write()?;
+        match keys.keys.iter().find(|key| key.kid == kid) {
+            Some(key) if key.retire_at.is_some() => bail!("key '{kid}' is retiring"),
+            Some(_) => {
+                keys.current = kid.to_string();
+                Ok(())
+            }
+            None => bail!("unknown key '{kid}'"),
+        }
+    }
+
+    /// The key id of the current key.
+    pub fn current_kid(&self) -> Result<String> {
+        Ok(self.read()?.current.clone())
+    }
+
+    /// Stop publishing a key at `retire_at`. The current key cannot be retired.
+    pub fn retire(&self, kid: &str, retire_at: SystemTime) -> Result<()> {
+        let mut keys = self.write()?;
+        if keys.current == kid {
+            bail!("the current key '{kid}' cannot be retired")
+        }
+        let Some(key) = keys.keys.iter_mut().find(|key| key.kid == kid) else {
+            bail!("unknown key '{kid}'")
+        };
+        key.retire_at = Some(retire_at);
+        Ok(())
+    }
+
+    /// Remove the keys that were retired before `now`.
+    pub fn prune(&self, now: SystemTime) -> Result<()> {
+        self.write()?
+            .keys
+            .retain(|key| key.retire_at.is_none_or(|retire_at| retire_at > now));
+        Ok(())
+    }
+
+    /// The public keys that are not retired at `now`, as a JWK Set, each with its key id.
+    pub fn jwks(&self, now: SystemTime) -> Result<Json> {
+        let keys = self
+            .read()?
+            .keys
+            .iter()
+            .filter(|key| key.retire_at.is_none_or(|retire_at| retire_at > now))
+            .map(|key| {
+                let mut jwk = key.signer.jwk()?;
+                jwk.key_id = Some(key.kid.clone());
+                Ok(serde_json::to_value(jwk)?)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(json!({ "keys": keys }))
+    }
+
+    fn current(&self) -> Result<RotatingKey> {
+        let keys = self.read()?;
+        keys.keys
+            .iter()
+            .find(|key| key.kid == keys.current)
+            .cloned()
+            .context("the current key is missing")
+    }
+
+    fn read(&self) -> Result<std::sync::RwLockReadGuard<'_, RotatingKeys>> {
+        self.keys
+            .read()
+            .map_err(|_| anyhow::anyhow!("rotating signer lock poisoned"))
+    }
+
+    fn write(&self) -> Result<std::sync::RwLockWriteGuard<'_, RotatingKeys>> {
+        self.keys
+            .write()
+            .map_err(|_| anyhow::anyhow!("rotating signer lock poisoned"))
+    }
+}
+
+#[async_trait]
+impl RequestSigner for RotatingSigner {
+    type Error = anyhow::Error;
+
+    fn alg(&self) -> Result<String, Self::Error> {
+        self.current()?.signer.alg()
+    }
+
+    fn jwk(&self) -> Result<JWK, Self::Error> {
+        let key = self.current()?;
+        let mut jwk = key.signer.jwk()?;
+        jwk.key_id = Some(key.kid);
+        Ok(jwk)
+    }
+
+    fn kid(&self) -> Option<String> {
+        self.current_kid().ok()
+    }
+
+    async fn sign(&self, payload: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        self.current()?.signer.sign(payload).await
+    }
+}
+
 pub struct P256Signer {
     key: SigningKey,
     jwk: JWK,
@@ -47,6 +286,14 @@ impl P256Signer {
     }
 }
 
+impl std::fmt::Debug for P256Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("P256Signer")
+            .field("jwk", &self.jwk)
+            .finish_non_exhaustive()
+    }
+}
+
 #[async_trait]
 impl RequestSigner for P256Signer {
     type Error = anyhow::Error;
@@ -59,9 +306,9 @@ impl RequestSigner for P256Signer {
         Ok(self.jwk.clone())
     }
 
-    async fn sign(&self, payload: &[u8]) -> Vec<u8> {
+    async fn sign(&self, payload: &[u8]) -> Result<Vec<u8>, Self::Error> {
         let sig: Signature = self.key.sign(payload);
-        sig.to_vec()
+        Ok(sig.to_vec())
     }
 }
 
@@ -78,8 +325,364 @@ impl JwsSigner for P256Signer {
         &self,
         signing_bytes: &[u8],
     ) -> std::result::Result<Vec<u8>, ssi::claims::SignatureError> {
-        self.try_sign(signing_bytes)
+        RequestSigner::sign(self, signing_bytes)
             .await
             .map_err(|e| ssi::claims::SignatureError::Other(format!("Failed to sign bytes: {}", e)))
     }
 }
+
+/// Parse the JWK representation of a public key.
+#[cfg(any(
+    feature = "eddsa",
+    feature = "es384",
+    feature = "es512",
+    feature = "rs256"
+))]
+fn public_jwk(jwk: Json) -> Result<JWK> {
+    serde_json::from_value(jwk).context("invalid public JWK")
+}
+
+/// A [RequestSigner] for `EdDSA` with an Ed25519 key.
+#[cfg(feature = "eddsa")]
+pub struct Ed25519Signer {
+    key: ed25519_dalek::SigningKey,
+    jwk: JWK,
+}
+
+#[cfg(feature = "eddsa")]
+impl Ed25519Signer {
+    pub fn new(key: ed25519_dalek::SigningKey) -> Result<Self> {
+        use base64::prelude::*;
+
+        let jwk = public_jwk(json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": BASE64_URL_SAFE_NO_PAD.encode(key.verifying_key().as_bytes()),
+        }))?;
+        Ok(Self { key, jwk })
+    }
+
+    pub fn jwk(&self) -> &JWK {
+        &self.jwk
+    }
+}
+
+#[cfg(feature = "eddsa")]
+impl std::fmt::Debug for Ed25519Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ed25519Signer")
+            .field("jwk", &self.jwk)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "eddsa")]
+#[async_trait]
+impl RequestSigner for Ed25519Signer {
+    type Error = anyhow::Error;
+
+    fn alg(&self) -> Result<String, Self::Error> {
+        Ok(Algorithm::EdDSA.to_string())
+    }
+
+    fn jwk(&self) -> Result<JWK, Self::Error> {
+        Ok(self.jwk.clone())
+    }
+
+    async fn sign(&self, payload: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        let sig: ed25519_dalek::Signature = self.key.sign(payload);
+        Ok(sig.to_vec())
+    }
+}
+
+/// A [RequestSigner] for `ES384` with a P-384 key.
+#[cfg(feature = "es384")]
+pub struct P384Signer {
+    key: p384::ecdsa::SigningKey,
+    jwk: JWK,
+}
+
+#[cfg(feature = "es384")]
+impl P384Signer {
+    pub fn new(key: p384::ecdsa::SigningKey) -> Result<Self> {
+        let pk: p384::PublicKey = key.verifying_key().into();
+        let jwk = public_jwk(serde_json::from_str(&pk.to_jwk_string())?)?;
+        Ok(Self { key, jwk })
+    }
+
+    pub fn jwk(&self) -> &JWK {
+        &self.jwk
+    }
+}
+
+#[cfg(feature = "es384")]
+impl std::fmt::Debug for P384Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("P384Signer")
+            .field("jwk", &self.jwk)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "es384")]
+#[async_trait]
+impl RequestSigner for P384Signer {
+    type Error = anyhow::Error;
+
+    fn alg(&self) -> Result<String, Self::Error> {
+        Ok(Algorithm::ES384.to_string())
+    }
+
+    fn jwk(&self) -> Result<JWK, Self::Error> {
+        Ok(self.jwk.clone())
+    }
+
+    async fn sign(&self, payload: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        let sig: p384::ecdsa::Signature = self.key.sign(payload);
+        Ok(sig.to_vec())
+    }
+}
+
+/// A [RequestSigner] for `ES512` with a P-521 key.
+#[cfg(feature = "es512")]
+pub struct P521Signer {
+    key: p521::ecdsa::SigningKey,
+    jwk: JWK,
+}
+
+#[cfg(feature = "es512")]
+impl P521Signer {
+    pub fn new(key: p521::ecdsa::SigningKey) -> Result<Self> {
+        let pk = p521::SecretKey::from_bytes(&key.to_bytes())
+            .context("invalid P-521 key")?
+            .public_key();
+        let jwk = public_jwk(serde_json::from_str(&pk.to_jwk_string())?)?;
+        Ok(Self { key, jwk })
+    }
+
+    pub fn jwk(&self) -> &JWK {
+        &self.jwk
+    }
+}
+
+#[cfg(feature = "es512")]
+impl std::fmt::Debug for P521Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("P521Signer")
+            .field("jwk", &self.jwk)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "es512")]
+#[async_trait]
+impl RequestSigner for P521Signer {
+    type Error = anyhow::Error;
+
+    fn alg(&self) -> Result<String, Self::Error> {
+        // Not defined by ssi's Algorithm.
+        Ok("ES512".to_string())
+    }
+
+    fn jwk(&self) -> Result<JWK, Self::Error> {
+        Ok(self.jwk.clone())
+    }
+
+    async fn sign(&self, payload: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        let sig: p521::ecdsa::Signature = self.key.sign(payload);
+        Ok(sig.to_vec())
+    }
+}
+
+/// A [RequestSigner] for `RS256` (RSASSA-PKCS1-v1_5 with SHA-256) with an RSA key.
+#[cfg(feature = "rs256")]
+pub struct RsaSigner {
+    key: rsa::pkcs1v15::SigningKey<sha2::Sha256>,
+    jwk: JWK,
+}
+
+#[cfg(feature = "rs256")]
+impl RsaSigner {
+    pub fn new(key: rsa::RsaPrivateKey) -> Result<Self> {
+        use base64::prelude::*;
+        use rsa::traits::PublicKeyParts;
+
+        let jwk = public_jwk(json!({
+            "kty": "RSA",
+            "n": BASE64_URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+            "e": BASE64_URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+        }))?;
+        Ok(Self {
+            key: rsa::pkcs1v15::SigningKey::new(key),
+            jwk,
+        })
+    }
+
+    pub fn jwk(&self) -> &JWK {
+        &self.jwk
+    }
+}
+
+#[cfg(feature = "rs256")]
+impl std::fmt::Debug for RsaSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RsaSigner")
+            .field("jwk", &self.jwk)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "rs256")]
+#[async_trait]
+impl RequestSigner for RsaSigner {
+    type Error = anyhow::Error;
+
+    fn alg(&self) -> Result<String, Self::Error> {
+        Ok(Algorithm::RS256.to_string())
+    }
+
+    fn jwk(&self) -> Result<JWK, Self::Error> {
+        Ok(self.jwk.clone())
+    }
+
+    async fn sign(&self, payload: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        use rsa::signature::SignatureEncoding;
+
+        let sig: rsa::pkcs1v15::Signature = self.key.sign(payload);
+        Ok(sig.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn signer() -> Arc<P256Signer> {
+        Arc::new(P256Signer::new(p256::SecretKey::random(&mut rand::thread_rng()).into()).unwrap())
+    }
+
+    #[test]
+    fn p256_signer_algorithm() {
+        assert!(implemented_algorithms().contains(&signer().alg().unwrap().as_str()));
+    }
+
+    #[cfg(feature = "eddsa")]
+    #[tokio::test]
+    async fn ed25519_signer() {
+        use ed25519_dalek::Verifier;
+
+        let key = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
+        let signer = Ed25519Signer::new(key.clone()).unwrap();
+        assert_eq!(signer.alg().unwrap(), "EdDSA");
+        assert!(implemented_algorithms().contains(&"EdDSA"));
+
+        let signature = signer.sign(b"payload").await.unwrap();
+        key.verifying_key()
+            .verify(
+                b"payload",
+                &ed25519_dalek::Signature::from_slice(&signature).unwrap(),
+            )
+            .unwrap();
+    }
+
+    #[cfg(feature = "es384")]
+    #[tokio::test]
+    async fn p384_signer() {
+        use p384::ecdsa::signature::Verifier;
+
+        let key = p384::ecdsa::SigningKey::random(&mut rand::rngs::OsRng);
+        let signer = P384Signer::new(key.clone()).unwrap();
+        assert_eq!(signer.alg().unwrap(), "ES384");
+
+        let signature = signer.sign(b"payload").await.unwrap();
+        key.verifying_key()
+            .verify(
+                b"payload",
+                &p384::ecdsa::Signature::from_slice(&signature).unwrap(),
+            )
+            .unwrap();
+    }
+
+    #[cfg(feature = "es512")]
+    #[tokio::test]
+    async fn p521_signer() {
+        use p521::ecdsa::signature::Verifier;
+
+        let key = p521::ecdsa::SigningKey::random(&mut rand::rngs::OsRng);
+        let signer = P521Signer::new(key.clone()).unwrap();
+        assert_eq!(signer.alg().unwrap(), "ES512");
+
+        let signature = signer.sign(b"payload").await.unwrap();
+        p521::ecdsa::VerifyingKey::from_affine(
+            *p521::SecretKey::from_bytes(&key.to_bytes())
+                .unwrap()
+                .public_key()
+                .as_affine(),
+        )
+        .unwrap()
+        .verify(
+            b"payload",
+            &p521::ecdsa::Signature::from_slice(&signature).unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[cfg(feature = "rs256")]
+    #[tokio::test]
+    async fn rsa_signer() {
+        use rsa::signature::Verifier;
+
+        let key = rsa::RsaPrivateKey::new(&mut rand::rngs::OsRng, 2048).unwrap();
+        let verifying_key = rsa::pkcs1v15::VerifyingKey::<sha2::Sha256>::new(key.to_public_key());
+        let signer = RsaSigner::new(key).unwrap();
+        assert_eq!(signer.alg().unwrap(), "RS256");
+        assert_eq!(serde_json::to_value(signer.jwk()).unwrap()["kty"], "RSA");
+
+        let signature = signer.sign(b"payload").await.unwrap();
+        verifying_key
+            .verify(
+                b"payload",
+                &rsa::pkcs1v15::Signature::try_from(signature.as_slice()).unwrap(),
+            )
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn rotation() {
+        let now = SystemTime::now();
+        let rotating = RotatingSigner::new("2024", signer());
+        let next = signer();
+        rotating.add_key("2025", next.clone()).unwrap();
+
+        assert_eq!(rotating.current_kid().unwrap(), "2025");
+        assert_eq!(rotating.kid().as_deref(), Some("2025"));
+        assert_eq!(rotating.jwk().unwrap().to_public(), {
+            let mut jwk = next.jwk().clone();
+            jwk.key_id = Some("2025".into());
+            jwk
+        });
+        assert!(rotating.retire("2025", now).is_err());
+
+        rotating
+            .retire("2024", now + Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(
+            rotating.jwks(now).unwrap()["keys"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let later = now + Duration::from_secs(120);
+        let jwks = rotating.jwks(later).unwrap();
+        assert_eq!(jwks["keys"].as_array().unwrap().len(), 1);
+        assert_eq!(jwks["keys"][0]["kid"], "2025");
+
+        rotating.prune(later).unwrap();
+        assert!(rotating.set_current("2024").is_err());
+        assert!(!rotating.sign(b"payload").await.unwrap().is_empty());
+    }
+}
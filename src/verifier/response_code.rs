@@ -0,0 +1,58 @@
+use rand::distributions::{Alphanumeric, DistString};
+use url::Url;
+
+/// The length of the response codes generated for each session.
+pub const RESPONSE_CODE_LENGTH: usize = 32;
+
+/// The query parameter carrying the response code in the redirect to the frontend.
+pub const RESPONSE_CODE_PARAMETER: &str = "response_code";
+
+/// Generate an unguessable response code.
+pub(crate) fn generate() -> String {
+    Alphanumeric.sample_string(&mut rand::thread_rng(), RESPONSE_CODE_LENGTH)
+}
+
+/// The URI the wallet redirects the user to after submitting a response: the frontend's
+/// `redirect_uri`, with the response code of the session.
+pub fn redirect_uri(redirect_uri: &Url, response_code: &str) -> Url {
+    let mut redirect_uri = redirect_uri.clone();
+    redirect_uri
+        .query_pairs_mut()
+        .append_pair(RESPONSE_CODE_PARAMETER, response_code);
+    redirect_uri
+}
+
+/// Compare response codes in constant time.
+pub(crate) fn matches(expected: &str, presented: &str) -> bool {
+    expected.len() == presented.len()
+        && expected
+            .bytes()
+            .zip(presented.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn response_codes() {
+        let code = generate();
+        assert_eq!(code.len(), RESPONSE_CODE_LENGTH);
+        assert_ne!(code, generate());
+
+        assert!(matches(&code, &code.clone()));
+        assert!(!matches(&code, &generate()));
+        assert!(!matches(&code, &code[1..]));
+
+        let redirect = redirect_uri(
+            &"https://verifier.example/done?lang=en".parse().unwrap(),
+            &code,
+        );
+        assert_eq!(
+            redirect.as_str(),
+            format!("https://verifier.example/done?lang=en&response_code={code}")
+        );
+    }
+}
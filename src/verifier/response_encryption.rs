@@ -0,0 +1,408 @@
+use std::{fmt, time::SystemTime};
+
+use anyhow::{bail, Context, Result};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+use uuid::Uuid;
+use zeroize::Zeroize;
+
+use crate::core::{
+    authorization_request::parameters::ClientMetadata,
+    jwe::{
+        compact::{self, ECDH_ES},
+        ecdh_es::{self, KeyAgreementCurve},
+    },
+    metadata::parameters::verifier::{AuthorizationEncryptedResponseAlg, JWKs},
+    object::UntypedObject,
+    response::{AuthorizationResponse, JwtAuthorizationResponse},
+};
+
+/// An ECDH-ES key pair to receive encrypted responses.
+///
+/// Keys are either generated for a single session, in which case the private key is kept in the
+/// [Session](super::session::Session) until the response has been decrypted, or configured on the
+/// verifier in [ResponseEncryptionKeys]. The public key is published in the `jwks` of the
+/// request's client metadata. The private key is zeroized when dropped.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ResponseEncryptionKey(Map<String, Json>);
+
+impl ResponseEncryptionKey {
+    /// Generate a key pair on `curve`, with a random `kid`.
+    pub fn generate<R: RngCore + CryptoRng>(curve: KeyAgreementCurve, rng: &mut R) -> Result<Self> {
+        let mut jwk = ecdh_es::generate(curve, rng)?;
+        jwk.insert("kid".into(), Uuid::new_v4().to_string().into());
+        Self::from_jwk(jwk)
+    }
+
+    /// Use an existing private JWK, which must have a `kid` and be on a supported curve. Its
+    /// `use` and `alg` default to `enc` and `ECDH-ES`.
+    pub fn from_jwk(mut jwk: Map<String, Json>) -> Result<Self> {
+        KeyAgreementCurve::from_jwk(&jwk)?;
+        if !jwk.contains_key("d") {
+            bail!("the response encryption key is missing its private key 'd'")
+        }
+        if !matches!(jwk.get("kid"), Some(Json::String(_))) {
+            bail!("the response encryption key must have a 'kid'")
+        }
+        match jwk.entry("use").or_insert_with(|| "enc".into()).as_str() {
+            Some("enc") => {}
+            _ => bail!("the response encryption key must have a 'use' of 'enc'"),
+        }
+        match jwk.entry("alg").or_insert_with(|| ECDH_ES.into()).as_str() {
+            Some(ECDH_ES) => {}
+            _ => bail!("the response encryption key must have an 'alg' of '{ECDH_ES}'"),
+        }
+        Ok(Self(jwk))
+    }
+
+    /// The `kid` of the key.
+    pub fn kid(&self) -> Option<&str> {
+        self.0.get("kid").and_then(Json::as_str)
+    }
+
+    /// The public JWK, with its `kid`, `use` and `alg`.
+    pub fn public_jwk(&self) -> Map<String, Json> {
+        let mut public = self.0.clone();
+        public.remove("d");
+        public
+    }
+
+    /// Publish the public key in client metadata, as the only key of its `jwks`, and request
+    /// encrypted responses with `ECDH-ES` unless another `authorization_encrypted_response_alg`
+    /// is set.
+    pub fn publish(&self, client_metadata: &mut ClientMetadata) {
+        client_metadata.0.insert(JWKs {
+            keys: vec![self.public_jwk()],
+        });
+        if client_metadata
+            .0
+            .get::<AuthorizationEncryptedResponseAlg>()
+            .is_none()
+        {
+            client_metadata
+                .0
+                .insert(AuthorizationEncryptedResponseAlg(ECDH_ES.into()));
+        }
+    }
+
+    /// Decrypt an encrypted response, which must have been encrypted to this key.
+    pub fn decrypt(&self, response: &JwtAuthorizationResponse) -> Result<AuthorizationResponse> {
+        let (header, mut payload) = compact::decrypt(&response.response, &self.0)
+            .context("failed to decrypt the authorization response")?;
+        if let (Some(kid), Some(expected)) = (header.get("kid"), self.kid()) {
+            if kid.as_str() != Some(expected) {
+                payload.zeroize();
+                bail!("the authorization response was encrypted to another key")
+            }
+        }
+        let object: Result<UntypedObject, _> = serde_json::from_slice(&payload);
+        payload.zeroize();
+        object
+            .context("the decrypted authorization response was not a JSON object")?
+            .try_into()
+    }
+}
+
+impl fmt::Debug for ResponseEncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseEncryptionKey")
+            .field("kid", &self.kid())
+            .field("crv", &self.0.get("crv"))
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for ResponseEncryptionKey {
+    fn drop(&mut self) {
+        if let Some(Json::String(d)) = self.0.get_mut("d") {
+            d.zeroize();
+        }
+    }
+}
+
+/// The static response encryption keys of a long-lived verifier, for key rotation.
+///
+/// Every key is published until its retirement (see [ResponseEncryptionKeys::with_retiring_key]),
+/// the most recently added first, so that wallets, which encrypt to the first supported key,
+/// switch to new keys. A retired key is no longer published, but still decrypts responses until it
+/// expires, as wallets may have cached older client metadata. The key of a response is selected
+/// by the `kid` of its JWE header.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseEncryptionKeys {
+    keys: Vec<ScheduledKey>,
+}
+
+#[derive(Debug, Clone)]
+struct ScheduledKey {
+    key: ResponseEncryptionKey,
+    retire_at: Option<SystemTime>,
+    expire_at: Option<SystemTime>,
+}
+
+impl ScheduledKey {
+    fn is_published(&self, now: SystemTime) -> bool {
+        self.retire_at.is_none_or(|retire_at| now < retire_at) && self.is_usable(now)
+    }
+
+    fn is_usable(&self, now: SystemTime) -> bool {
+        self.expire_at.is_none_or(|expire_at| now < expire_at)
+    }
+}
+
+impl ResponseEncryptionKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a key that is published and accepted indefinitely. Its `kid` must be unique.
+    pub fn with_key(self, key: ResponseEncryptionKey) -> Result<Self> {
+        self.with_scheduled_key(key, None, None)
+    }
+
+    /// Add a key that is published until `retire_at`, and accepted until `expire_at`. Its `kid`
+    /// must be unique.
+    pub fn with_retiring_key(
+        self,
+        key: ResponseEncryptionKey,
+        retire_at: SystemTime,
+        expire_at: SystemTime,
+    ) -> Result<Self> {
+        if expire_at < retire_at {
+            bail!("a response encryption key cannot expire before it is retired")
+        }
+        self.with_scheduled_key(key, Some(retire_at), Some(expire_at))
+    }
+
+    fn with_scheduled_key(
+        mut self,
+        key: ResponseEncryptionKey,
+        retire_at: Option<SystemTime>,
+        expire_at: Option<SystemTime>,
+    ) -> Result<Self> {
+        if self.get(key.kid()).is_some() {
+            bail!(
+                "duplicate response encryption key '{}'",
+                key.kid().unwrap_or_default()
+            )
+        }
+        self.keys.push(ScheduledKey {
+            key,
+            retire_at,
+            expire_at,
+        });
+        Ok(self)
+    }
+
+    fn get(&self, kid: Option<&str>) -> Option<&ScheduledKey> {
+        self.keys
+            .iter()
+            .find(|scheduled| scheduled.key.kid() == kid)
+    }
+
+    /// The public JWKs of the keys published at `now`, the most recently added first.
+    pub fn published(&self, now: SystemTime) -> Vec<Map<String, Json>> {
+        self.keys
+            .iter()
+            .rev()
+            .filter(|scheduled| scheduled.is_published(now))
+            .map(|scheduled| scheduled.key.public_jwk())
+            .collect()
+    }
+
+    /// Publish the keys in client metadata, as its `jwks`, and request encrypted responses with
+    /// `ECDH-ES` unless another `authorization_encrypted_response_alg` is set.
+    pub fn publish(&self, client_metadata: &mut ClientMetadata, now: SystemTime) -> Result<()> {
+        let keys = self.published(now);
+        if keys.is_empty() {
+            bail!("every response encryption key has been retired")
+        }
+        client_metadata.0.insert(JWKs { keys });
+        if client_metadata
+            .0
+            .get::<AuthorizationEncryptedResponseAlg>()
+            .is_none()
+        {
+            client_metadata
+                .0
+                .insert(AuthorizationEncryptedResponseAlg(ECDH_ES.into()));
+        }
+        Ok(())
+    }
+
+    /// Decrypt an encrypted response with the key identified by the `kid` of its JWE header,
+    /// which must not have expired at `now`.
+    pub fn decrypt(
+        &self,
+        response: &JwtAuthorizationResponse,
+        now: SystemTime,
+    ) -> Result<AuthorizationResponse> {
+        let header = response.protected_header()?;
+        let Some(kid) = header.get("kid") else {
+            bail!("the encrypted authorization response does not identify its key with a 'kid'")
+        };
+        let Some(scheduled) = self.get(kid.as_str()) else {
+            bail!("the authorization response was encrypted to an unknown key {kid}")
+        };
+        if !scheduled.is_usable(now) {
+            bail!("the authorization response was encrypted to an expired key {kid}")
+        }
+        scheduled.key.decrypt(response)
+    }
+
+    /// Remove the keys that have expired at `now`, returning how many were removed.
+    pub fn prune(&mut self, now: SystemTime) -> usize {
+        let before = self.keys.len();
+        self.keys.retain(|scheduled| scheduled.is_usable(now));
+        before - self.keys.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use rand::rngs::OsRng;
+    use serde_json::json;
+
+    use crate::core::{
+        jwe::{ContentEncryptionAlgorithm, ResponseEncryption},
+        response::UnencodedAuthorizationResponse,
+    };
+
+    use super::*;
+
+    fn encrypt(jwk: Map<String, Json>) -> JwtAuthorizationResponse {
+        let encryption = ResponseEncryption {
+            alg: ECDH_ES.into(),
+            enc: ContentEncryptionAlgorithm::A256Gcm,
+            jwk,
+        };
+        let payload = json!({
+            "vp_token": "presentation",
+            "presentation_submission": {
+                "id": "67e55044-10b1-426f-9247-bb680e5fe0c8",
+                "definition_id": "definition",
+                "descriptor_map": []
+            }
+        });
+        JwtAuthorizationResponse {
+            response: compact::encrypt(
+                &encryption,
+                payload.to_string().as_bytes(),
+                &[],
+                &[],
+                &mut OsRng,
+            )
+            .unwrap(),
+        }
+    }
+
+    fn key() -> ResponseEncryptionKey {
+        ResponseEncryptionKey::generate(KeyAgreementCurve::P256, &mut OsRng).unwrap()
+    }
+
+    #[test]
+    fn decrypt_response() {
+        let key = key();
+        assert!(!key.public_jwk().contains_key("d"));
+        assert!(!format!("{key:?}").contains("\"d\""));
+
+        let mut client_metadata = ClientMetadata(UntypedObject::default());
+        key.publish(&mut client_metadata);
+        let jwk = ecdh_es::select_encryption_jwk(&client_metadata.0).unwrap();
+        assert_eq!(jwk["kid"].as_str(), key.kid());
+        assert_eq!(
+            client_metadata
+                .0
+                .get::<AuthorizationEncryptedResponseAlg>()
+                .unwrap()
+                .unwrap()
+                .0,
+            ECDH_ES
+        );
+
+        let response = key.decrypt(&encrypt(jwk)).unwrap();
+        let AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(_, vp_token, _)) =
+            response
+        else {
+            panic!("expected an unencoded response")
+        };
+        assert_eq!(vp_token.0.len(), 1);
+
+        // A response encrypted to another key.
+        assert!(key.decrypt(&encrypt(self::key().public_jwk())).is_err());
+
+        // The private key survives the session store.
+        let stored: ResponseEncryptionKey =
+            serde_json::from_value(serde_json::to_value(&key).unwrap()).unwrap();
+        assert_eq!(stored.kid(), key.kid());
+    }
+
+    #[test]
+    fn key_rollover() {
+        let now = SystemTime::now();
+        let hour = Duration::from_secs(3600);
+        let (old, current) = (key(), key());
+        let keys = ResponseEncryptionKeys::new()
+            .with_retiring_key(old.clone(), now + hour, now + 2 * hour)
+            .unwrap()
+            .with_key(current.clone())
+            .unwrap();
+
+        assert!(keys.clone().with_key(current.clone()).is_err());
+        assert!(ResponseEncryptionKeys::new()
+            .with_retiring_key(key(), now + hour, now)
+            .is_err());
+
+        // The most recent key is published first, and retired keys are no longer published.
+        let kids = |at| -> Vec<_> {
+            keys.published(at)
+                .into_iter()
+                .map(|jwk| jwk["kid"].as_str().unwrap().to_owned())
+                .collect()
+        };
+        assert_eq!(kids(now), [current.kid().unwrap(), old.kid().unwrap()]);
+        assert_eq!(kids(now + hour), [current.kid().unwrap()]);
+
+        let mut client_metadata = ClientMetadata(UntypedObject::default());
+        keys.publish(&mut client_metadata, now).unwrap();
+        let jwk = ecdh_es::select_encryption_jwk(&client_metadata.0).unwrap();
+        assert_eq!(jwk["kid"].as_str(), current.kid());
+
+        // A retired key still decrypts, until it expires.
+        let response = encrypt(old.public_jwk());
+        keys.decrypt(&response, now + hour).unwrap();
+        assert!(keys.decrypt(&response, now + 2 * hour).is_err());
+        keys.decrypt(&encrypt(current.public_jwk()), now + 2 * hour)
+            .unwrap();
+        assert!(keys.decrypt(&encrypt(key().public_jwk()), now).is_err());
+
+        let mut pruned = keys.clone();
+        assert_eq!(pruned.prune(now + 2 * hour), 1);
+        assert_eq!(pruned.published(now).len(), 1);
+    }
+
+    #[test]
+    fn static_key_from_jwk() {
+        let mut jwk = key().0.clone();
+        jwk.remove("use");
+        jwk.remove("alg");
+        let static_key = ResponseEncryptionKey::from_jwk(jwk.clone()).unwrap();
+        assert_eq!(static_key.public_jwk()["use"], "enc");
+        assert_eq!(static_key.public_jwk()["alg"], ECDH_ES);
+
+        let mut signing = jwk.clone();
+        signing.insert("use".into(), "sig".into());
+        assert!(ResponseEncryptionKey::from_jwk(signing).is_err());
+
+        let mut anonymous = jwk.clone();
+        anonymous.remove("kid");
+        assert!(ResponseEncryptionKey::from_jwk(anonymous).is_err());
+
+        let mut public = jwk;
+        public.remove("d");
+        assert!(ResponseEncryptionKey::from_jwk(public).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+
+use crate::core::{
+    authorization_request::parameters::Scope, presentation_definition::PresentationDefinition,
+};
+
+/// Presentation definitions registered by name, for authorization requests that identify what is
+/// requested with a `scope` value instead of passing the presentation definition.
+///
+/// The mapping has to be known to the wallet as well, and is kept by the verifier so that the
+/// response to a scope-only request is validated against the registered presentation definition.
+#[derive(Debug, Clone, Default)]
+pub struct ScopeRegistry {
+    scopes: BTreeMap<String, PresentationDefinition>,
+}
+
+impl ScopeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the presentation definition of a scope, returning the presentation definition
+    /// that was previously registered for it, if any.
+    pub fn register(
+        &mut self,
+        scope: impl Into<String>,
+        presentation_definition: PresentationDefinition,
+    ) -> Result<Option<PresentationDefinition>> {
+        let scope = scope.into();
+        if scope.is_empty() || scope.contains(char::is_whitespace) {
+            bail!("scope values cannot be empty or contain whitespace: '{scope}'")
+        }
+        Ok(self.scopes.insert(scope, presentation_definition))
+    }
+
+    /// The presentation definition registered for a scope value.
+    pub fn get(&self, scope: &str) -> Option<&PresentationDefinition> {
+        self.scopes.get(scope)
+    }
+
+    /// The registered scope values.
+    pub fn scopes(&self) -> impl Iterator<Item = &str> {
+        self.scopes.keys().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
+    }
+
+    /// Resolve the `scope` parameter of a request to a registered presentation definition.
+    ///
+    /// Requests must carry exactly one registered scope value.
+    pub fn resolve(&self, scope: &Scope) -> Result<&PresentationDefinition> {
+        let [value] = scope.0.as_slice() else {
+            bail!(
+                "expected a single scope value, found '{}'",
+                scope.0.join(" ")
+            )
+        };
+        self.get(value).with_context(|| {
+            format!("no presentation definition is registered for scope '{value}'")
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value as Json};
+
+    use crate::core::object::TypedParameter;
+
+    use super::*;
+
+    #[test]
+    fn resolve_scope() {
+        let presentation_definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "pd",
+            "input_descriptors": [{ "id": "id", "constraints": { "fields": [] } }]
+        }))
+        .unwrap();
+
+        let mut registry = ScopeRegistry::new();
+        assert!(registry
+            .register("com.example.id", presentation_definition.clone())
+            .unwrap()
+            .is_none());
+        assert!(registry
+            .register("invalid scope", presentation_definition)
+            .is_err());
+        assert_eq!(registry.scopes().collect::<Vec<_>>(), ["com.example.id"]);
+
+        let scope = Scope::try_from(json!("com.example.id")).unwrap();
+        assert_eq!(registry.resolve(&scope).unwrap().id(), "pd");
+
+        let scope = Scope::try_from(json!("com.example.id openid")).unwrap();
+        assert_eq!(Json::from(scope.clone()), json!("com.example.id openid"));
+        assert!(registry.resolve(&scope).is_err());
+        assert!(registry.resolve(&Scope::new("unknown")).is_err());
+        assert!(Scope::try_from(json!(" ")).is_err());
+        assert_eq!(Scope::KEY, "scope");
+    }
+}
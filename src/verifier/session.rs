@@ -1,26 +1,153 @@
-use std::{collections::BTreeMap, fmt::Debug, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 use anyhow::{bail, Ok, Result};
 use async_trait::async_trait;
+use base64::prelude::*;
 pub use openid4vp_frontend::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+use sha2::{Digest, Sha256};
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
+use super::response_encryption::ResponseEncryptionKey;
 use crate::core::{
-    authorization_request::AuthorizationRequestObject,
+    authorization_request::{
+        parameters::{State, TransactionData},
+        AuthorizationRequestObject,
+    },
+    dcql_query::DcqlQuery,
+    draft::Draft,
     presentation_definition::PresentationDefinition,
+    response::parameters::MdocGeneratedNonce,
+    transaction_data::TransactionDataBinding,
 };
 
-#[derive(Debug, Clone)]
+/// The state of a presentation session, as kept by a [SessionStore].
+///
+/// Sessions serialize to JSON, so they can be kept in an external database. The nonce and other
+/// parameters of the request are part of the
+/// [authorization_request_object](Self::authorization_request_object).
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
     pub uuid: Uuid,
     pub status: Status,
     pub authorization_request_jwt: String,
     pub authorization_request_object: AuthorizationRequestObject,
-    pub presentation_definition: PresentationDefinition,
+    /// The presentation definition that the response is validated against, unless the request
+    /// carried a [DcqlQuery].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presentation_definition: Option<PresentationDefinition>,
+    /// The DCQL query of the request, that the DCQL-shaped `vp_token` of the response must answer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dcql_query: Option<DcqlQuery>,
+    /// The ephemeral key that the response of the session is encrypted to, until it has been
+    /// decrypted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_encryption_key: Option<ResponseEncryptionKey>,
+    /// The single-use secret of the `request_uri` of a request passed by reference, until the
+    /// request has been retrieved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_uri_secret: Option<String>,
+    /// The code that the frontend exchanges for the outcome of the session, once the wallet has
+    /// been redirected to it, see
+    /// [Verifier::exchange_response_code](super::Verifier::exchange_response_code).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_code: Option<String>,
+    /// The digest of the body of the response submitted for the session, to recognise retries of
+    /// the same submission, see [Verifier::receive_response](super::Verifier::receive_response).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_digest: Option<String>,
+    /// The [MdocGeneratedNonce] of the encrypted response, once it has been received, for the
+    /// session transcript of its mdoc presentations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mdoc_generated_nonce: Option<MdocGeneratedNonce>,
+    /// When the session was created.
+    pub created_at: SystemTime,
+    /// The [Tenant](super::tenant::Tenant) that the session was created for, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// The [Draft] that the request was built for, and that the response is interpreted with, if
+    /// the session is pinned to one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub draft: Option<Draft>,
+}
+
+impl Session {
+    /// The `state` parameter of the authorization request, if any.
+    pub fn state(&self) -> Option<String> {
+        self.authorization_request_object
+            .get::<State>()
+            .and_then(Result::ok)
+            .map(|state| state.0)
+    }
+
+    /// Whether the session was created more than `max_age` before `now`.
+    pub fn is_expired(&self, max_age: Duration, now: SystemTime) -> bool {
+        now.duration_since(self.created_at)
+            .is_ok_and(|age| age > max_age)
+    }
+
+    /// Check that the claims of the Key Binding JWT of an SD-JWT presentation bind the
+    /// `transaction_data` of the request, if the request contained any.
+    pub fn verify_transaction_data_binding(
+        &self,
+        holder_proof_claims: &Map<String, Json>,
+    ) -> Result<()> {
+        let Some(transaction_data) = self.authorization_request_object.get::<TransactionData>()
+        else {
+            return Ok(());
+        };
+        TransactionDataBinding::verify(&transaction_data?, holder_proof_claims)
+    }
+}
+
+/// The digest of the body of a submitted response: its base64url-encoded SHA-256 hash.
+pub(crate) fn response_digest(body: &[u8]) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(body))
 }
 
+/// A response was submitted for a session that already received one.
+///
+/// Byte-identical retries of a submission that has been processed are not duplicates, and get the
+/// original outcome instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateResponse {
+    pub session: Uuid,
+    /// Whether the response is identical to the first one, which is still being processed.
+    pub identical: bool,
+}
+
+impl fmt::Display for DuplicateResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.identical {
+            write!(
+                f,
+                "the response of session {} is still being processed",
+                self.session
+            )
+        } else {
+            write!(
+                f,
+                "a response was already received for session {}, its nonce has been consumed",
+                self.session
+            )
+        }
+    }
+}
+
+impl std::error::Error for DuplicateResponse {}
+
 /// Storage interface for session information.
+///
+/// Sessions are keyed by their UUID, which is also the token at the end of the `response_uri` of
+/// the session (and, with a single-use secret, of its `request_uri`), and can be looked up by the
+/// `state` of their authorization request.
 #[async_trait]
 pub trait SessionStore: Debug {
     /// Store a new authorization request session.
@@ -34,6 +161,67 @@ pub trait SessionStore: Debug {
 
     /// Remove a session from the store.
     async fn remove_session(&self, uuid: Uuid) -> Result<()>;
+
+    /// Remove the [response_encryption_key](Session::response_encryption_key) of a session, once
+    /// its response has been decrypted.
+    ///
+    /// Does nothing by default. Stores that persist sessions should override it, so that private
+    /// keys do not outlive the sessions' responses.
+    async fn discard_response_encryption_key(&self, uuid: Uuid) -> Result<()> {
+        let _ = uuid;
+        Ok(())
+    }
+
+    /// Remove and return the [request_uri_secret](Session::request_uri_secret) of a session, so
+    /// that its request can only be retrieved once.
+    ///
+    /// Implementations must remove the secret atomically.
+    async fn take_request_uri_secret(&self, uuid: Uuid) -> Result<Option<String>> {
+        let _ = uuid;
+        bail!("this session store does not support single-use request URIs")
+    }
+
+    /// Remove and return the [response_code](Session::response_code) of a session, so that it
+    /// can only be exchanged once.
+    ///
+    /// Implementations must remove the code atomically.
+    async fn take_response_code(&self, uuid: Uuid) -> Result<Option<String>> {
+        let _ = uuid;
+        bail!("this session store does not support response codes")
+    }
+
+    /// Move a session to [Status::ReceivedResponse], unless it already received a response, so
+    /// that its nonce is only consumed once. Returns whether the session was moved.
+    ///
+    /// Implementations must check and update the status atomically.
+    async fn claim_response(&self, uuid: Uuid) -> Result<bool> {
+        let _ = uuid;
+        bail!("this session store does not support single-use nonces")
+    }
+
+    /// Record the digest of the response submitted for a session, see
+    /// [response_digest](Session::response_digest), unless a response was already submitted for
+    /// it. Returns whether one was, in which case the recorded digest is left as it is.
+    ///
+    /// Implementations must check and record the digest atomically. Records nothing by default,
+    /// so that every duplicate submission is rejected, including retries.
+    async fn record_response_digest(&self, uuid: Uuid, digest: String) -> Result<bool> {
+        let _ = (uuid, digest);
+        Ok(false)
+    }
+
+    /// Get the session whose authorization request has the given `state`.
+    async fn get_session_by_state(&self, state: &str) -> Result<Session> {
+        let _ = state;
+        bail!("this session store does not support looking up sessions by state")
+    }
+
+    /// Remove every session created more than `max_age` before `now`, returning how many were
+    /// removed.
+    async fn expire(&self, max_age: Duration, now: SystemTime) -> Result<usize> {
+        let _ = (max_age, now);
+        bail!("this session store does not support expiring sessions")
+    }
 }
 
 /// A local in-memory store. Not for production use!
@@ -62,6 +250,50 @@ impl SessionStore for MemoryStore {
         bail!("session not found")
     }
 
+    async fn discard_response_encryption_key(&self, uuid: Uuid) -> Result<()> {
+        if let Some(session) = self.store.try_lock()?.get_mut(&uuid) {
+            session.response_encryption_key = None;
+            return Ok(());
+        }
+        bail!("session not found")
+    }
+
+    async fn take_request_uri_secret(&self, uuid: Uuid) -> Result<Option<String>> {
+        if let Some(session) = self.store.try_lock()?.get_mut(&uuid) {
+            return Ok(session.request_uri_secret.take());
+        }
+        bail!("session not found")
+    }
+
+    async fn take_response_code(&self, uuid: Uuid) -> Result<Option<String>> {
+        if let Some(session) = self.store.try_lock()?.get_mut(&uuid) {
+            return Ok(session.response_code.take());
+        }
+        bail!("session not found")
+    }
+
+    async fn claim_response(&self, uuid: Uuid) -> Result<bool> {
+        if let Some(session) = self.store.try_lock()?.get_mut(&uuid) {
+            if session.status >= Status::ReceivedResponse {
+                return Ok(false);
+            }
+            session.status = Status::ReceivedResponse;
+            return Ok(true);
+        }
+        bail!("session not found")
+    }
+
+    async fn record_response_digest(&self, uuid: Uuid, digest: String) -> Result<bool> {
+        if let Some(session) = self.store.try_lock()?.get_mut(&uuid) {
+            if session.response_digest.is_some() || session.status >= Status::ReceivedResponse {
+                return Ok(true);
+            }
+            session.response_digest = Some(digest);
+            return Ok(false);
+        }
+        bail!("session not found")
+    }
+
     async fn get_session(&self, uuid: Uuid) -> Result<Session> {
         if let Some(session) = self.store.try_lock()?.get(&uuid) {
             return Ok(session.clone());
@@ -77,4 +309,129 @@ impl SessionStore for MemoryStore {
 
         bail!("session not found")
     }
+
+    async fn get_session_by_state(&self, state: &str) -> Result<Session> {
+        if let Some(session) = self
+            .store
+            .try_lock()?
+            .values()
+            .find(|session| session.state().as_deref() == Some(state))
+        {
+            return Ok(session.clone());
+        }
+
+        bail!("session not found")
+    }
+
+    async fn expire(&self, max_age: Duration, now: SystemTime) -> Result<usize> {
+        let mut store = self.store.try_lock()?;
+        let before = store.len();
+        store.retain(|_, session| !session.is_expired(max_age, now));
+        Ok(before - store.len())
+    }
+}
+
+/// A session for a `direct_post` request of `did:example:verifier`, with the other parameters of
+/// `request`, such as its `presentation_definition` or `dcql_query`.
+#[cfg(test)]
+pub(crate) fn test_session(request: Json) -> Session {
+    use serde::de::DeserializeOwned;
+
+    use crate::core::object::UntypedObject;
+
+    fn parameter<T: DeserializeOwned>(parameters: &Json, key: &str) -> Option<T> {
+        let value = parameters.get(key)?.clone();
+        Some(serde_json::from_value(value).unwrap())
+    }
+
+    let mut parameters = serde_json::json!({
+        "client_id": "did:example:verifier",
+        "client_id_scheme": "did",
+        "response_type": "vp_token",
+        "response_mode": "direct_post",
+        "response_uri": "https://verifier.example/response",
+        "nonce": "nonce"
+    });
+    if let (Some(parameters), Json::Object(request)) = (parameters.as_object_mut(), request) {
+        parameters.extend(request);
+    }
+    Session {
+        uuid: Uuid::new_v4(),
+        status: Status::SentRequest,
+        authorization_request_jwt: String::new(),
+        presentation_definition: parameter(&parameters, "presentation_definition"),
+        dcql_query: parameter(&parameters, "dcql_query"),
+        authorization_request_object: serde_json::from_value::<UntypedObject>(parameters)
+            .unwrap()
+            .try_into()
+            .unwrap(),
+        response_encryption_key: None,
+        request_uri_secret: None,
+        response_code: None,
+        response_digest: None,
+        mdoc_generated_nonce: None,
+        created_at: SystemTime::now(),
+        tenant: None,
+        draft: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn session(state: &str, created_at: SystemTime) -> Session {
+        Session {
+            created_at,
+            ..test_session(json!({
+                "state": state,
+                "presentation_definition": { "id": "pd", "input_descriptors": [] }
+            }))
+        }
+    }
+
+    #[test]
+    fn session_serialization_roundtrip() {
+        let session = session("state", SystemTime::now());
+
+        let json = serde_json::to_string(&session).unwrap();
+        let deserialized: Session = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized.uuid, session.uuid);
+        assert_eq!(deserialized.status, session.status);
+        assert_eq!(deserialized.created_at, session.created_at);
+        assert_eq!(deserialized.state().as_deref(), Some("state"));
+        assert_eq!(
+            deserialized.authorization_request_object.nonce().as_str(),
+            "nonce"
+        );
+        assert_eq!(
+            deserialized.presentation_definition,
+            session.presentation_definition
+        );
+    }
+
+    #[tokio::test]
+    async fn memory_store_state_and_expiry() {
+        let store = MemoryStore::default();
+        let now = SystemTime::now();
+
+        let old = session("old", now - Duration::from_secs(600));
+        let new = session("new", now);
+        store.initiate(old.clone()).await.unwrap();
+        store.initiate(new.clone()).await.unwrap();
+
+        assert_eq!(
+            store.get_session_by_state("new").await.unwrap().uuid,
+            new.uuid
+        );
+        assert!(store.get_session_by_state("unknown").await.is_err());
+
+        let expired = store.expire(Duration::from_secs(300), now).await.unwrap();
+        assert_eq!(expired, 1);
+        assert!(store.get_session(old.uuid).await.is_err());
+        assert!(store.get_session(new.uuid).await.is_ok());
+    }
 }
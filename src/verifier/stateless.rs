@@ -0,0 +1,271 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Aes256Gcm, KeyInit,
+};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use base64::prelude::*;
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use zeroize::Zeroize;
+
+use crate::core::util::unix_time;
+
+use super::session::{Session, SessionStore, Status};
+
+/// How long a stateless session can be answered for, unless a
+/// [session TTL](super::VerifierBuilder::with_session_ttl) is set.
+pub const DEFAULT_STATELESS_SESSION_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// The associated data of sealed states, so that they cannot be confused with other ciphertexts
+/// produced with the same key.
+const STATE_AAD: &[u8] = b"openid4vp stateless session";
+
+const IV_LENGTH: usize = 12;
+
+/// The data of a stateless session, sealed into the `state` of its request and the token at the
+/// end of its `response_uri`, instead of being kept in a [SessionStore].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatelessState {
+    /// The UUID of the session, to correlate it in logs and events.
+    pub session: Uuid,
+    /// The nonce of the request.
+    pub nonce: String,
+    /// The hash of the presentation definition or DCQL query of the request, see
+    /// [definition_hash].
+    pub definition_hash: String,
+    /// When the session was created, in seconds since the UNIX epoch.
+    pub created_at: u64,
+    /// When the session expires, in seconds since the UNIX epoch.
+    pub expires_at: u64,
+    /// The `kid`s of the response encryption keys published in the request, if the response is
+    /// encrypted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub response_encryption_kids: Vec<String>,
+}
+
+impl StatelessState {
+    /// When the session was created.
+    pub fn created_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.created_at)
+    }
+
+    /// When the session expires.
+    pub fn expires_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.expires_at)
+    }
+
+    /// Whether the session has expired at `now`.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        unix_time(now).unwrap_or_default() >= self.expires_at
+    }
+}
+
+/// Records the stateless sessions that received a response, so that responses cannot be replayed
+/// while the token is still valid, see
+/// [VerifierBuilder::with_seen_token_store](super::VerifierBuilder::with_seen_token_store).
+///
+/// A session only needs to be remembered until it expires, after which its token is rejected
+/// anyway. Every instance of a horizontally scaled verifier must share the store.
+#[async_trait]
+pub trait SeenTokenStore: fmt::Debug + Send + Sync {
+    /// Record that `session`, which expires at `expires_at`, received a response, and return
+    /// whether it already had.
+    async fn mark_seen(&self, session: Uuid, expires_at: SystemTime) -> Result<bool>;
+}
+
+/// A [SeenTokenStore] in memory, forgetting sessions once they expire.
+///
+/// # Warning
+/// This in-memory store only protects a single verifier instance, it will not work for a
+/// distributed deployment.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySeenTokenStore {
+    seen: Arc<Mutex<BTreeMap<Uuid, SystemTime>>>,
+}
+
+#[async_trait]
+impl SeenTokenStore for MemorySeenTokenStore {
+    async fn mark_seen(&self, session: Uuid, expires_at: SystemTime) -> Result<bool> {
+        let now = SystemTime::now();
+        let mut seen = self.seen.lock().await;
+        seen.retain(|_, expires_at| *expires_at > now);
+        Ok(seen.insert(session, expires_at).is_some())
+    }
+}
+
+/// The symmetric key that seals the [StatelessState] of sessions, with AES-256-GCM.
+///
+/// Every instance of a horizontally scaled verifier must share the key, so that any of them can
+/// receive the response to a session created by another.
+#[derive(Clone)]
+pub struct StateKey([u8; 32]);
+
+impl StateKey {
+    /// Generate a random key.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut key = [0; 32];
+        rng.fill_bytes(&mut key);
+        Self(key)
+    }
+
+    pub fn from_bytes(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+
+    /// Encrypt and authenticate a state into a URL-safe token.
+    pub fn seal<R: RngCore + CryptoRng>(
+        &self,
+        state: &StatelessState,
+        rng: &mut R,
+    ) -> Result<String> {
+        let mut iv = [0; IV_LENGTH];
+        rng.fill_bytes(&mut iv);
+        let plaintext = serde_json::to_vec(state).context("failed to serialize the state")?;
+        let ciphertext = Aes256Gcm::new(&self.0.into())
+            .encrypt(
+                &iv.into(),
+                Payload {
+                    msg: &plaintext,
+                    aad: STATE_AAD,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("failed to seal the state"))?;
+        Ok(BASE64_URL_SAFE_NO_PAD.encode([iv.as_slice(), &ciphertext].concat()))
+    }
+
+    /// Decrypt and authenticate a token sealed with [StateKey::seal], which must not have expired
+    /// at `now`.
+    pub fn open(&self, token: &str, now: SystemTime) -> Result<StatelessState> {
+        let sealed = BASE64_URL_SAFE_NO_PAD
+            .decode(token)
+            .context("the stateless session token is not base64url encoded")?;
+        if sealed.len() < IV_LENGTH {
+            bail!("the stateless session token is too short")
+        }
+        let (iv, ciphertext) = sealed.split_at(IV_LENGTH);
+        let iv: [u8; IV_LENGTH] = iv.try_into()?;
+        let plaintext = Aes256Gcm::new(&self.0.into())
+            .decrypt(
+                &iv.into(),
+                Payload {
+                    msg: ciphertext,
+                    aad: STATE_AAD,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("the stateless session token is invalid"))?;
+        let state: StatelessState =
+            serde_json::from_slice(&plaintext).context("failed to parse the stateless session")?;
+        if state.is_expired(now) {
+            bail!("the stateless session has expired")
+        }
+        Ok(state)
+    }
+}
+
+impl fmt::Debug for StateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateKey").finish_non_exhaustive()
+    }
+}
+
+impl Drop for StateKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// The hash of the JSON of a presentation definition or DCQL query: the base64url-encoded SHA-256
+/// digest of its serialization.
+pub fn definition_hash(definition: &Json) -> Result<String> {
+    let json = serde_json::to_vec(definition).context("failed to serialize the definition")?;
+    Ok(BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(json)))
+}
+
+/// The session store of a stateless verifier without one, which rejects every operation.
+#[derive(Debug)]
+pub(crate) struct NoSessionStore;
+
+#[async_trait]
+impl SessionStore for NoSessionStore {
+    async fn initiate(&self, _: Session) -> Result<()> {
+        bail!("this verifier is stateless, see `begin_stateless_session`")
+    }
+
+    async fn update_status(&self, _: Uuid, _: Status) -> Result<()> {
+        bail!("this verifier is stateless, see `begin_stateless_session`")
+    }
+
+    async fn get_session(&self, _: Uuid) -> Result<Session> {
+        bail!("this verifier is stateless, see `begin_stateless_session`")
+    }
+
+    async fn remove_session(&self, _: Uuid) -> Result<()> {
+        bail!("this verifier is stateless, see `begin_stateless_session`")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    fn state(expires_at: u64) -> StatelessState {
+        StatelessState {
+            session: Uuid::new_v4(),
+            nonce: "nonce".into(),
+            definition_hash: definition_hash(&serde_json::json!({ "id": "pd" })).unwrap(),
+            created_at: 0,
+            expires_at,
+            response_encryption_kids: vec!["key-1".into()],
+        }
+    }
+
+    #[test]
+    fn seal_and_open() {
+        let key = StateKey::generate(&mut OsRng);
+        let now = SystemTime::now();
+        let state = state(unix_time(now).unwrap() + 60);
+
+        let token = key.seal(&state, &mut OsRng).unwrap();
+        assert_eq!(key.open(&token, now).unwrap(), state);
+
+        // Expired, tampered with or sealed with another key.
+        assert!(key.open(&token, now + Duration::from_secs(60)).is_err());
+        let mut tampered = token.clone().into_bytes();
+        let last = tampered.len() - 2;
+        tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+        assert!(key
+            .open(&String::from_utf8(tampered).unwrap(), now)
+            .is_err());
+        assert!(StateKey::generate(&mut OsRng).open(&token, now).is_err());
+    }
+
+    #[tokio::test]
+    async fn seen_tokens() {
+        let store = MemorySeenTokenStore::default();
+        let session = Uuid::new_v4();
+        let expires_at = SystemTime::now() + Duration::from_secs(60);
+
+        assert!(!store.mark_seen(session, expires_at).await.unwrap());
+        assert!(store.mark_seen(session, expires_at).await.unwrap());
+        assert!(!store.mark_seen(Uuid::new_v4(), expires_at).await.unwrap());
+
+        // Expired sessions are forgotten.
+        let expired = Uuid::new_v4();
+        store.mark_seen(expired, UNIX_EPOCH).await.unwrap();
+        assert!(!store.mark_seen(expired, UNIX_EPOCH).await.unwrap());
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{bail, Context, Result};
+
+use crate::core::{
+    authorization_request::parameters::{
+        Nonce, ResponseMode, ResponseType, State, TransactionData,
+    },
+    dcql_query::DcqlQuery,
+    object::{ParsingErrorContext, TypedParameter, UntypedObject},
+    presentation_definition::PresentationDefinition,
+};
+
+/// A reusable authorization request, registered with
+/// [VerifierBuilder::with_request_template](super::VerifierBuilder::with_request_template): what
+/// is requested (a presentation definition or a DCQL query) and the request parameters shared by
+/// its sessions, e.g. `response_mode` and `client_metadata`.
+///
+/// Templates are checked once, when they are registered, and sessions started from a template
+/// (see [Verifier::begin_template_session](super::Verifier::begin_template_session)) only set
+/// their [SessionOverrides].
+#[derive(Debug, Clone)]
+pub struct RequestTemplate {
+    pub(crate) presentation_definition: Option<PresentationDefinition>,
+    pub(crate) dcql_query: Option<DcqlQuery>,
+    pub(crate) request_parameters: UntypedObject,
+}
+
+impl RequestTemplate {
+    /// A template requesting credentials with a presentation definition.
+    pub fn with_presentation_definition(presentation_definition: PresentationDefinition) -> Self {
+        Self {
+            presentation_definition: Some(presentation_definition),
+            dcql_query: None,
+            request_parameters: UntypedObject::default(),
+        }
+    }
+
+    /// A template requesting credentials with a DCQL query.
+    pub fn with_dcql_query(dcql_query: DcqlQuery) -> Self {
+        Self {
+            presentation_definition: None,
+            dcql_query: Some(dcql_query),
+            request_parameters: UntypedObject::default(),
+        }
+    }
+
+    /// Set a request parameter of the template's sessions, overriding the default parameters of
+    /// the verifier.
+    pub fn with_request_parameter<T: TypedParameter>(mut self, t: T) -> Self {
+        self.request_parameters.insert(t);
+        self
+    }
+
+    /// Check that the template is complete, and that it does not set the parameters that differ
+    /// between sessions.
+    ///
+    /// `default_request_params` are the default parameters of the verifier, which the template
+    /// extends.
+    pub(crate) fn validate(&self, default_request_params: &UntypedObject) -> Result<()> {
+        if let Some(dcql_query) = &self.dcql_query {
+            dcql_query.validate().context("invalid DCQL query")?;
+        }
+        for key in [Nonce::KEY, State::KEY, TransactionData::KEY] {
+            if self.request_parameters.0.contains_key(key) {
+                bail!("'{key}' is set for each session, see `SessionOverrides`")
+            }
+        }
+        let parameter = |key| {
+            self.request_parameters
+                .0
+                .get(key)
+                .or_else(|| default_request_params.0.get(key))
+                .cloned()
+        };
+        let response_type: ResponseType = parameter(ResponseType::KEY)
+            .map(TryInto::try_into)
+            .parsing_error()?;
+        if let ResponseType::Unsupported(r) = response_type {
+            bail!("unsupported response_type: {r}")
+        }
+        let response_mode: ResponseMode = parameter(ResponseMode::KEY)
+            .map(TryInto::try_into)
+            .parsing_error()?;
+        if let ResponseMode::Unsupported(r) = response_mode {
+            bail!("unsupported response_mode: {r}")
+        }
+        Ok(())
+    }
+}
+
+/// The parameters of a session started from a [RequestTemplate].
+///
+/// A random [Nonce] is generated unless one is set.
+#[derive(Debug, Clone, Default)]
+pub struct SessionOverrides {
+    pub nonce: Option<Nonce>,
+    pub state: Option<State>,
+    pub transaction_data: Option<TransactionData>,
+}
+
+impl SessionOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_nonce(mut self, nonce: Nonce) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    pub fn with_state(mut self, state: State) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    pub fn with_transaction_data(mut self, transaction_data: TransactionData) -> Self {
+        self.transaction_data = Some(transaction_data);
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn presentation_definition() -> PresentationDefinition {
+        serde_json::from_value(json!({
+            "id": "pd",
+            "input_descriptors": [{ "id": "id", "constraints": { "fields": [] } }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn validate_template() {
+        let mut defaults = UntypedObject::default();
+        defaults.insert(ResponseType::VpToken);
+
+        let template = RequestTemplate::with_presentation_definition(presentation_definition());
+        assert!(template.validate(&defaults).is_err());
+
+        let template = template.with_request_parameter(ResponseMode::DirectPost);
+        template.validate(&defaults).unwrap();
+        assert!(template.validate(&UntypedObject::default()).is_err());
+
+        let with_nonce = template
+            .clone()
+            .with_request_parameter(Nonce::from("nonce"));
+        assert!(with_nonce.validate(&defaults).is_err());
+
+        let unsupported = template
+            .with_request_parameter(ResponseMode::Unsupported("query".into()))
+            .validate(&defaults);
+        assert!(unsupported.is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use crate::core::{
+    draft::Draft,
+    object::{TypedParameter, UntypedObject},
+    presentation_definition::PresentationDefinition,
+};
+
+use super::{client::Client, policy::TrustPolicy};
+
+/// A verifier identity that a [Verifier](super::Verifier) acts as on behalf of a relying party,
+/// registered with [VerifierBuilder::with_tenant](super::VerifierBuilder::with_tenant).
+///
+/// Each tenant has its own [Client] (`client_id`, scheme and signing key), and optionally its own
+/// default request parameters (e.g. `client_metadata`), presentation definition, [TrustPolicy]
+/// (e.g. trusted issuers and X.509 roots) and pinned [Draft]. The session store, submission
+/// endpoint and response validator are shared by all tenants, and sessions record the tenant they
+/// were created for.
+#[derive(Debug, Clone)]
+pub struct Tenant {
+    pub(crate) client: Arc<dyn Client + Send + Sync>,
+    pub(crate) default_request_params: UntypedObject,
+    pub(crate) presentation_definition: Option<PresentationDefinition>,
+    pub(crate) trust_policy: Option<Arc<TrustPolicy>>,
+    pub(crate) draft: Option<Draft>,
+}
+
+impl Tenant {
+    pub fn new(client: Arc<dyn Client + Send + Sync>) -> Self {
+        Self {
+            client,
+            default_request_params: UntypedObject::default(),
+            presentation_definition: None,
+            trust_policy: None,
+            draft: None,
+        }
+    }
+
+    /// The [Client] that identifies the tenant to the wallet.
+    pub fn client(&self) -> &Arc<dyn Client + Send + Sync> {
+        &self.client
+    }
+
+    /// Set a default request parameter for the tenant's requests, overriding the default
+    /// parameters of the verifier.
+    pub fn with_default_request_parameter<T: TypedParameter>(mut self, t: T) -> Self {
+        self.default_request_params.insert(t);
+        self
+    }
+
+    /// Set the presentation definition of the tenant's sessions started with
+    /// [Verifier::begin_tenant_session](super::Verifier::begin_tenant_session), instead of the
+    /// verifier's.
+    pub fn with_presentation_definition(
+        mut self,
+        presentation_definition: PresentationDefinition,
+    ) -> Self {
+        self.presentation_definition = Some(presentation_definition);
+        self
+    }
+
+    /// Evaluate this policy on the responses to the tenant's sessions, instead of the verifier's.
+    pub fn with_trust_policy(mut self, trust_policy: TrustPolicy) -> Self {
+        self.trust_policy = Some(Arc::new(trust_policy));
+        self
+    }
+
+    /// Build the tenant's requests for, and interpret the responses to them according to, a
+    /// [Draft], e.g. for a relying party whose wallets have not been updated yet.
+    pub fn with_draft(mut self, draft: Draft) -> Self {
+        self.draft = Some(draft);
+        self
+    }
+}
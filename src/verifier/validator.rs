@@ -0,0 +1,38 @@
+use std::fmt::Debug;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::core::response::{parameters::VpTokenItem, AuthorizationResponse};
+
+use super::session::{Outcome, Session};
+
+/// Validates the authorization responses received by a [Verifier](super::Verifier).
+///
+/// Implementations verify the presentations in the response (signatures, nonce, holder binding,
+/// credential status) against the session, and decide the [Outcome]. A
+/// [VerificationReport](super::report::VerificationReport) can be used to collect the findings, and
+/// convert them together with the verified credentials into an [Outcome] carrying a
+/// [VerifiedPresentationOutcome](super::outcome::VerifiedPresentationOutcome).
+#[async_trait]
+pub trait ResponseValidator: Debug + Send + Sync {
+    async fn validate(&self, session: Session, response: AuthorizationResponse) -> Outcome;
+}
+
+/// Verifies the presentations of an authorization response one by one, before the response is
+/// handed to the validator.
+///
+/// The presentations of a response are verified concurrently, up to the
+/// [verification concurrency](super::VerifierBuilder::with_verification_concurrency) of the
+/// verifier, so that the network latency of e.g. DID resolution or status list fetches is not paid
+/// once per presentation. The response is rejected if any presentation fails verification.
+#[async_trait]
+pub trait PresentationVerifier: Debug + Send + Sync {
+    /// Verify the presentation at `index` of the `vp_token` of a response to `session`.
+    async fn verify(
+        &self,
+        session: &Session,
+        index: usize,
+        presentation: &VpTokenItem,
+    ) -> Result<()>;
+}
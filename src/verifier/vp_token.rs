@@ -0,0 +1,94 @@
+use std::future::Future;
+
+use anyhow::Result;
+use futures::{stream, StreamExt};
+
+use crate::core::response::parameters::VpTokenItem;
+
+/// The default number of presentations verified at the same time by [verify_presentations], see
+/// [VerifierBuilder::with_verification_concurrency](super::VerifierBuilder::with_verification_concurrency).
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Verify the presentations of a `vp_token` concurrently, e.g. those of a
+/// [VpToken](crate::core::response::parameters::VpToken) or of a
+/// [DcqlVpToken](crate::core::dcql_query::DcqlVpToken).
+///
+/// Verifying a presentation is usually dominated by network latency (DID resolution, status list
+/// fetches), so large submissions are verified with up to `concurrency` presentations in flight,
+/// rather than sequentially.
+///
+/// The `verify` function receives the index of the presentation in the `vp_token` alongside the
+/// presentation itself. The results are returned in the same order as the presentations, and can
+/// be collected into a `Result<Vec<T>>` to fail on the first error.
+pub async fn verify_presentations<'a, I, F, Fut, T>(
+    presentations: I,
+    concurrency: usize,
+    mut verify: F,
+) -> Vec<Result<T>>
+where
+    I: IntoIterator<Item = &'a VpTokenItem>,
+    F: FnMut(usize, &'a VpTokenItem) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let verifications: Vec<Fut> = presentations
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| verify(index, item))
+        .collect();
+    stream::iter(verifications)
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use anyhow::bail;
+    use futures::future::{self, Either};
+    use futures_timer::Delay;
+    use tokio::sync::Barrier;
+
+    use crate::core::response::parameters::VpToken;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn verifies_concurrently() {
+        let vp_token = VpToken(vec![
+            VpTokenItem::String("first".into()),
+            VpTokenItem::String("invalid".into()),
+            VpTokenItem::String("third".into()),
+        ]);
+
+        // The first two verifications only complete once both of them are in flight.
+        let barrier = Barrier::new(2);
+        let verification = verify_presentations(&vp_token, 2, |index, item| {
+            let barrier = &barrier;
+            async move {
+                let VpTokenItem::String(s) = item else {
+                    bail!("expected a string")
+                };
+                if index < 2 {
+                    barrier.wait().await;
+                }
+                if s == "invalid" {
+                    bail!("invalid presentation")
+                }
+                Ok(index)
+            }
+        });
+
+        let timeout = Delay::new(Duration::from_secs(5));
+        let results = match future::select(Box::pin(verification), timeout).await {
+            Either::Left((results, _)) => results,
+            Either::Right(_) => panic!("the presentations were not verified concurrently"),
+        };
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), &0);
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap(), &2);
+    }
+}
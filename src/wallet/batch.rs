@@ -0,0 +1,25 @@
+use anyhow::Result;
+use url::Url;
+use uuid::Uuid;
+
+use crate::core::authorization_request::AuthorizationRequestObject;
+
+/// The outcome of validating one authorization request from a batch.
+#[derive(Debug)]
+pub struct BatchedRequest {
+    /// Identifies this request for the rest of the flow, e.g. to correlate UI state.
+    pub correlation_id: Uuid,
+    /// The authorization request URL as received.
+    pub url: Url,
+    pub request: Result<AuthorizationRequestObject>,
+}
+
+impl BatchedRequest {
+    pub(crate) fn new(url: Url, request: Result<AuthorizationRequestObject>) -> Self {
+        Self {
+            correlation_id: Uuid::new_v4(),
+            url,
+            request,
+        }
+    }
+}
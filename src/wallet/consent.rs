@@ -0,0 +1,92 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value as Json;
+
+use crate::core::{
+    authorization_request::parameters::{ClientId, ClientIdScheme, ClientMetadata},
+    input_descriptor::CredentialTypesRequestedFields,
+};
+
+use super::credential_store::{CandidateSets, StoredCredential};
+
+/// The identity of the verifier, as established while validating the request.
+#[derive(Debug, Clone)]
+pub struct VerifierIdentity {
+    pub client_id: ClientId,
+    pub client_id_scheme: ClientIdScheme,
+    /// The `client_name` from the client metadata, if any.
+    pub client_name: Option<String>,
+}
+
+impl VerifierIdentity {
+    pub(crate) fn new(
+        client_id: ClientId,
+        client_id_scheme: ClientIdScheme,
+        client_metadata: &ClientMetadata,
+    ) -> Self {
+        let client_name = client_metadata
+            .0
+             .0
+            .get("client_name")
+            .and_then(Json::as_str)
+            .map(ToOwned::to_owned);
+        Self {
+            client_id,
+            client_id_scheme,
+            client_name,
+        }
+    }
+}
+
+/// Everything needed to ask the user whether, and what, to share.
+#[derive(Debug, Clone, Copy)]
+pub struct ConsentRequest<'a> {
+    pub verifier: &'a VerifierIdentity,
+    /// The requested fields of each input descriptor, in a human readable form.
+    pub requested_fields: &'a [CredentialTypesRequestedFields],
+    pub candidates: &'a CandidateSets,
+}
+
+/// A credential chosen to satisfy an input descriptor.
+#[derive(Debug, Clone)]
+pub struct SelectedCredential {
+    pub input_descriptor_id: String,
+    pub credential: StoredCredential,
+    /// The paths of the claims the user agreed to disclose, e.g. `$.given_name`, or `None` to
+    /// disclose all the claims matched by the input descriptor.
+    pub approved_claims: Option<Vec<String>>,
+}
+
+impl SelectedCredential {
+    pub fn new(input_descriptor_id: impl Into<String>, credential: StoredCredential) -> Self {
+        Self {
+            input_descriptor_id: input_descriptor_id.into(),
+            credential,
+            approved_claims: None,
+        }
+    }
+
+    /// Restrict the claims to disclose to those the user approved.
+    pub fn with_approved_claims(mut self, approved_claims: Vec<String>) -> Self {
+        self.approved_claims = Some(approved_claims);
+        self
+    }
+}
+
+/// The outcome of asking the user for consent.
+#[derive(Debug, Clone)]
+pub enum ConsentDecision {
+    /// The user agreed to share the selected credentials.
+    Approve(Vec<SelectedCredential>),
+    /// The user declined to share any credentials.
+    Refuse,
+}
+
+/// Invoked by the wallet between matching credentials against a request and building the
+/// response, to let the user select the credentials to share or refuse the request.
+///
+/// Implementations typically suspend here until the user has interacted with the UI.
+#[async_trait]
+pub trait ConsentHandler: Send + Sync {
+    async fn request_consent(&self, request: ConsentRequest<'_>) -> Result<ConsentDecision>;
+}
@@ -0,0 +1,378 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use serde_json::Value as Json;
+
+use crate::core::{
+    credential_format::{
+        ClaimFormatDesignation, ClaimFormatMap, ClaimFormatPayload, CredentialType,
+    },
+    input_descriptor::InputDescriptor,
+    metadata::parameters::wallet::VpFormatsSupported,
+    presentation_definition::PresentationDefinition,
+    presentation_submission::PresentationSubmission,
+    response::error::VpFormatsNotSupported,
+};
+
+/// A credential held by the wallet, as seen by the selection engine.
+#[derive(Debug, Clone)]
+pub struct StoredCredential {
+    id: String,
+    format: ClaimFormatDesignation,
+    types: Vec<CredentialType>,
+    claims: Json,
+}
+
+impl StoredCredential {
+    /// Create a stored credential.
+    ///
+    /// The `id` is chosen by the wallet to refer back to the credential once selected, and the
+    /// `claims` are the JSON representation that input descriptor paths are evaluated against.
+    pub fn new(id: impl Into<String>, format: ClaimFormatDesignation, claims: Json) -> Self {
+        Self {
+            id: id.into(),
+            format,
+            types: Vec::new(),
+            claims,
+        }
+    }
+
+    /// Set the credential types.
+    pub fn with_types(mut self, types: Vec<CredentialType>) -> Self {
+        self.types = types;
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn format(&self) -> &ClaimFormatDesignation {
+        &self.format
+    }
+
+    pub fn types(&self) -> &[CredentialType] {
+        &self.types
+    }
+
+    pub fn claims(&self) -> &Json {
+        &self.claims
+    }
+}
+
+/// Storage of the credentials held by the wallet.
+#[async_trait]
+pub trait CredentialStore: Send + Sync {
+    /// List every credential that may be presented.
+    async fn list(&self) -> Vec<StoredCredential>;
+}
+
+/// Check, before matching credentials, that the formats requested by the presentation definition
+/// can be presented by the wallet.
+///
+/// For each input descriptor, at least one requested format (those of the input descriptor, or
+/// else those of the presentation definition) must be supported by the wallet metadata, with at
+/// least one algorithm or proof type in common where both sides list them, and at least one
+/// stored credential must be in that format. Input descriptors that do not request formats are
+/// not checked.
+///
+/// When the presentation definition has submission requirements, not every input descriptor
+/// needs to be satisfied, and a single input descriptor with a usable format is enough.
+pub fn check_vp_formats(
+    presentation_definition: &PresentationDefinition,
+    vp_formats_supported: &VpFormatsSupported,
+    credentials: &[StoredCredential],
+) -> Result<(), VpFormatsNotSupported> {
+    let mut errors = presentation_definition
+        .input_descriptors()
+        .iter()
+        .filter_map(|input_descriptor| {
+            let requested = if input_descriptor.format().is_empty() {
+                presentation_definition.format()
+            } else {
+                input_descriptor.format()
+            };
+            if requested.is_empty() {
+                return None;
+            }
+            let usable = requested.iter().any(|(format, payload)| {
+                vp_formats_supported
+                    .0
+                    .get(format)
+                    .is_some_and(|supported| is_compatible(payload, supported))
+                    && credentials.iter().any(|c| &c.format == format)
+            });
+            (!usable).then(|| VpFormatsNotSupported {
+                input_descriptor_id: input_descriptor.id().to_string(),
+                requested: requested.keys().cloned().collect(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let satisfied = presentation_definition.input_descriptors().len() - errors.len();
+    if errors.is_empty()
+        || (presentation_definition.submission_requirements().is_some() && satisfied > 0)
+    {
+        return Ok(());
+    }
+    Err(errors.remove(0))
+}
+
+/// Whether the algorithms (or proof types) requested for a format intersect those supported.
+///
+/// Payloads without a list of algorithms are compatible with anything.
+fn is_compatible(requested: &ClaimFormatPayload, supported: &ClaimFormatPayload) -> bool {
+    let algs = |payload: &ClaimFormatPayload| match payload {
+        ClaimFormatPayload::Alg(algs)
+        | ClaimFormatPayload::AlgValuesSupported(algs)
+        | ClaimFormatPayload::ProofType(algs) => Some(algs.clone()),
+        ClaimFormatPayload::Json(_) => None,
+    };
+    match (algs(requested), algs(supported)) {
+        (Some(requested), Some(supported)) => requested.iter().any(|alg| supported.contains(alg)),
+        _ => true,
+    }
+}
+
+/// The credentials that can satisfy a single input descriptor.
+#[derive(Debug, Clone)]
+pub struct DescriptorCandidates {
+    pub input_descriptor: InputDescriptor,
+    pub credentials: Vec<StoredCredential>,
+}
+
+/// The candidate credentials for every input descriptor of a presentation definition.
+#[derive(Debug, Clone)]
+pub struct CandidateSets {
+    pub presentation_definition: PresentationDefinition,
+    pub candidates: Vec<DescriptorCandidates>,
+}
+
+impl CandidateSets {
+    /// Match the credentials against each input descriptor of the presentation definition.
+    pub fn new(
+        presentation_definition: PresentationDefinition,
+        credentials: &[StoredCredential],
+    ) -> Self {
+        let candidates = presentation_definition
+            .input_descriptors()
+            .iter()
+            .map(|input_descriptor| DescriptorCandidates {
+                input_descriptor: input_descriptor.clone(),
+                credentials: credentials
+                    .iter()
+                    .filter(|credential| {
+                        let hint = input_descriptor.credential_types_hint();
+                        (hint.is_empty() || credential.types.iter().any(|t| hint.contains(t)))
+                            && input_descriptor
+                                .is_credential_match(&credential.format, &credential.claims)
+                    })
+                    .cloned()
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            presentation_definition,
+            candidates,
+        }
+    }
+
+    /// Match the credentials in the store against the presentation definition.
+    pub async fn from_store<S: CredentialStore + ?Sized>(
+        presentation_definition: PresentationDefinition,
+        store: &S,
+    ) -> Self {
+        Self::new(presentation_definition, &store.list().await)
+    }
+
+    /// Return the candidates for an input descriptor.
+    pub fn get(&self, input_descriptor_id: &str) -> Option<&DescriptorCandidates> {
+        self.candidates
+            .iter()
+            .find(|candidates| candidates.input_descriptor.id() == input_descriptor_id)
+    }
+
+    /// Whether the wallet supports at least one of the formats requested by the presentation
+    /// definition and by each input descriptor (where formats are specified).
+    pub fn is_format_supported(&self, vp_formats_supported: &VpFormatsSupported) -> bool {
+        let supports_any = |formats: &ClaimFormatMap| {
+            formats.is_empty()
+                || formats
+                    .keys()
+                    .any(|format| vp_formats_supported.is_claim_format_supported(format))
+        };
+        supports_any(self.presentation_definition.format())
+            && self
+                .presentation_definition
+                .input_descriptors()
+                .iter()
+                .all(|input_descriptor| supports_any(input_descriptor.format()))
+    }
+
+    /// Whether every input descriptor has at least one candidate.
+    ///
+    /// When the presentation definition has submission requirements, not every input descriptor
+    /// needs to be satisfied, and this is only a conservative check.
+    pub fn is_fully_satisfiable(&self) -> bool {
+        self.candidates
+            .iter()
+            .all(|candidates| !candidates.credentials.is_empty())
+    }
+
+    /// Check that a choice of credentials satisfies the presentation definition, taking
+    /// submission requirements into account.
+    ///
+    /// More than one credential may be selected for the same input descriptor.
+    pub fn validate_selection(&self, selected: &[(&str, &StoredCredential)]) -> Result<()> {
+        for (input_descriptor_id, credential) in selected {
+            let Some(candidates) = self.get(input_descriptor_id) else {
+                bail!("unknown input descriptor '{input_descriptor_id}'")
+            };
+            if !candidates
+                .credentials
+                .iter()
+                .any(|candidate| candidate.id == credential.id)
+            {
+                bail!(
+                    "credential '{}' does not satisfy input descriptor '{input_descriptor_id}'",
+                    credential.id
+                )
+            }
+        }
+
+        let submission = PresentationSubmission::for_vp_token(
+            self.presentation_definition.id().clone(),
+            selected.iter().map(|(input_descriptor_id, credential)| {
+                (*input_descriptor_id, credential.format.clone())
+            }),
+        );
+        let descriptor_map = submission.descriptor_map();
+
+        match self.presentation_definition.submission_requirements() {
+            Some(_) => self
+                .presentation_definition
+                .validate_submission_requirements(descriptor_map),
+            None => {
+                for candidates in &self.candidates {
+                    if !descriptor_map
+                        .iter()
+                        .any(|d| d.id() == candidates.input_descriptor.id())
+                    {
+                        bail!(
+                            "no credential selected for input descriptor '{}'",
+                            candidates.input_descriptor.id()
+                        )
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    struct Store(Vec<StoredCredential>);
+
+    #[async_trait]
+    impl CredentialStore for Store {
+        async fn list(&self) -> Vec<StoredCredential> {
+            self.0.clone()
+        }
+    }
+
+    fn presentation_definition() -> PresentationDefinition {
+        serde_json::from_value(json!({
+            "id": "36682080-c2ed-4ba6-a4cd-37c86ef2da8c",
+            "input_descriptors": [
+                {
+                    "id": "over_18",
+                    "format": { "jwt_vc_json": { "alg": ["ES256"] } },
+                    "constraints": {
+                        "fields": [
+                            {
+                                "path": ["$.vc.credentialSubject.age", "$.credentialSubject.age"],
+                                "filter": { "type": "number", "minimum": 18 }
+                            }
+                        ]
+                    }
+                }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn select_from_store() {
+        let adult = StoredCredential::new(
+            "adult",
+            ClaimFormatDesignation::JwtVcJson,
+            json!({ "vc": { "credentialSubject": { "age": 30 } } }),
+        );
+        let minor = StoredCredential::new(
+            "minor",
+            ClaimFormatDesignation::JwtVcJson,
+            json!({ "vc": { "credentialSubject": { "age": 16 } } }),
+        );
+        let ldp = StoredCredential::new(
+            "ldp",
+            ClaimFormatDesignation::LdpVc,
+            json!({ "credentialSubject": { "age": 30 } }),
+        );
+        let store = Store(vec![adult.clone(), minor.clone(), ldp]);
+
+        let candidates = CandidateSets::from_store(presentation_definition(), &store).await;
+        assert!(candidates.is_fully_satisfiable());
+
+        let over_18 = candidates.get("over_18").unwrap();
+        assert_eq!(
+            over_18
+                .credentials
+                .iter()
+                .map(StoredCredential::id)
+                .collect::<Vec<_>>(),
+            ["adult"]
+        );
+
+        candidates
+            .validate_selection(&[("over_18", &adult)])
+            .unwrap();
+        assert!(candidates
+            .validate_selection(&[("over_18", &minor)])
+            .is_err());
+        assert!(candidates.validate_selection(&[]).is_err());
+    }
+
+    #[test]
+    fn vp_formats_compatibility() {
+        let adult = StoredCredential::new(
+            "adult",
+            ClaimFormatDesignation::JwtVcJson,
+            json!({ "vc": { "credentialSubject": { "age": 30 } } }),
+        );
+        let supported = |alg: &str| {
+            VpFormatsSupported(ClaimFormatMap::from([(
+                ClaimFormatDesignation::JwtVcJson,
+                ClaimFormatPayload::AlgValuesSupported(vec![alg.into()]),
+            )]))
+        };
+
+        check_vp_formats(
+            &presentation_definition(),
+            &supported("ES256"),
+            std::slice::from_ref(&adult),
+        )
+        .unwrap();
+
+        let error = check_vp_formats(&presentation_definition(), &supported("EdDSA"), &[adult])
+            .unwrap_err();
+        assert_eq!(error.input_descriptor_id, "over_18");
+        assert_eq!(error.requested, [ClaimFormatDesignation::JwtVcJson]);
+
+        assert!(check_vp_formats(&presentation_definition(), &supported("ES256"), &[]).is_err());
+    }
+}
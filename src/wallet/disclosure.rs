@@ -0,0 +1,374 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value as Json;
+
+use crate::core::{
+    presentation_definition::PresentationDefinition,
+    response::UnencodedAuthorizationResponse,
+    util::{self, Base64Policy},
+};
+
+use super::consent::SelectedCredential;
+
+/// A claim included in a presentation that was not requested by the input descriptor it was
+/// submitted for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverDisclosure {
+    pub input_descriptor_id: String,
+    pub claim: String,
+}
+
+/// Compare the claims embedded in the presentations of a response with the claims requested by
+/// the presentation definition.
+///
+/// For SD-JWTs the disclosed claims are compared, and for other presentations the claims of the
+/// `credentialSubject` of each credential. Claims are compared by name, using the last segment of
+/// the paths of the input descriptor's constraint fields.
+///
+/// The presentations and disclosures are decoded according to the [Base64Policy], see
+/// [Wallet::base64_policy](super::Wallet::base64_policy).
+pub fn find_over_disclosure(
+    presentation_definition: &PresentationDefinition,
+    response: &UnencodedAuthorizationResponse,
+    base64: Base64Policy,
+) -> Vec<OverDisclosure> {
+    let input_descriptors = presentation_definition.input_descriptors_map();
+
+    disclosed_claims_by_descriptor(response, base64)
+        .into_iter()
+        .filter_map(|(input_descriptor_id, disclosed)| {
+            let input_descriptor = input_descriptors.get(&input_descriptor_id)?;
+            let requested = claim_names(
+                input_descriptor
+                    .constraints()
+                    .fields()
+                    .iter()
+                    .flat_map(|field| field.path().iter()),
+            );
+            Some(find_outside(input_descriptor_id, disclosed, &requested))
+        })
+        .flatten()
+        .collect()
+}
+
+/// Check that the presentations of a response only disclose the claims the user approved (see
+/// [SelectedCredential::approved_claims]), returning the claims disclosed beyond them.
+pub fn find_unapproved_disclosure(
+    selected: &[SelectedCredential],
+    response: &UnencodedAuthorizationResponse,
+    base64: Base64Policy,
+) -> Vec<OverDisclosure> {
+    disclosed_claims_by_descriptor(response, base64)
+        .into_iter()
+        .flat_map(|(input_descriptor_id, disclosed)| {
+            let approved = selected
+                .iter()
+                .filter(|s| s.input_descriptor_id == input_descriptor_id)
+                .map(|s| s.approved_claims.as_ref())
+                .collect::<Option<Vec<_>>>();
+            match approved {
+                // No restriction for this descriptor.
+                None => vec![],
+                Some(approved) => {
+                    let approved = claim_names(approved.into_iter().flatten());
+                    find_outside(input_descriptor_id, disclosed, &approved)
+                }
+            }
+        })
+        .collect()
+}
+
+/// Remove the disclosures of claims that were not approved from an SD-JWT.
+///
+/// This must be done before the Key Binding JWT is created, as it covers the disclosures. Array
+/// element disclosures, which have no claim name, are kept.
+pub fn restrict_sd_jwt(sd_jwt: &str, approved_claims: &[String]) -> String {
+    let approved = claim_names(approved_claims.iter());
+    let mut parts = sd_jwt.split('~');
+    let issuer_jwt = parts.next().unwrap_or_default();
+    let mut restricted = vec![issuer_jwt];
+    restricted.extend(parts.filter(|disclosure| {
+        disclosure.is_empty()
+            || disclosure_name(disclosure, Base64Policy::default())
+                .is_none_or(|name| approved.contains(name.as_str()))
+    }));
+    restricted.join("~")
+}
+
+fn find_outside(
+    input_descriptor_id: String,
+    disclosed: BTreeSet<String>,
+    allowed: &BTreeSet<&str>,
+) -> Vec<OverDisclosure> {
+    disclosed
+        .into_iter()
+        .filter(|claim| !allowed.contains(claim.as_str()))
+        .map(|claim| OverDisclosure {
+            input_descriptor_id: input_descriptor_id.clone(),
+            claim,
+        })
+        .collect()
+}
+
+/// The claims disclosed by the presentations submitted for each input descriptor.
+fn disclosed_claims_by_descriptor(
+    response: &UnencodedAuthorizationResponse,
+    base64: Base64Policy,
+) -> Vec<(String, BTreeSet<String>)> {
+    let vp_token = Json::from(response.vp_token().clone());
+    response
+        .presentation_submission()
+        .descriptor_map()
+        .iter()
+        .map(|descriptor_map| {
+            let disclosed = jsonpath_lib::select(&vp_token, descriptor_map.path())
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(|presentation| disclosed_claims(presentation, base64))
+                .collect();
+            (descriptor_map.id().clone(), disclosed)
+        })
+        .collect()
+}
+
+fn claim_names<'a>(paths: impl Iterator<Item = &'a String>) -> BTreeSet<&'a str> {
+    paths.filter_map(|path| claim_name(path)).collect()
+}
+
+/// The name of the claim a JSONPath expression selects, e.g. `given_name` for
+/// `$.credentialSubject.given_name` or `$['given_name']`.
+fn claim_name(path: &str) -> Option<&str> {
+    let path = path.trim_end();
+    if let Some(path) = path.strip_suffix("']") {
+        return path.rsplit_once("['").map(|(_, name)| name);
+    }
+    path.rsplit_once('.')
+        .map(|(_, name)| name)
+        .filter(|name| !name.is_empty() && *name != "*")
+}
+
+fn disclosed_claims(presentation: &Json, base64: Base64Policy) -> Vec<String> {
+    match presentation {
+        Json::String(sd_jwt) if sd_jwt.contains('~') => sd_jwt_disclosures(sd_jwt, base64),
+        Json::String(jwt) => util::jwt_payload(jwt, base64)
+            .ok()
+            .map(|payload| subject_claims(&payload, base64))
+            .unwrap_or_default(),
+        object => subject_claims(object, base64),
+    }
+}
+
+/// The names of the claims disclosed by an SD-JWT, ignoring array element disclosures.
+fn sd_jwt_disclosures(sd_jwt: &str, base64: Base64Policy) -> Vec<String> {
+    sd_jwt
+        .split('~')
+        .skip(1)
+        .filter_map(|disclosure| disclosure_name(disclosure, base64))
+        .collect()
+}
+
+fn disclosure_name(disclosure: &str, base64: Base64Policy) -> Option<String> {
+    let disclosure = base64.decode(disclosure).ok()?;
+    match serde_json::from_slice::<Vec<Json>>(&disclosure)
+        .ok()?
+        .as_slice()
+    {
+        [_, Json::String(name), _] => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// The `credentialSubject` claims of a credential or of the credentials of a presentation,
+/// other than `id`.
+fn subject_claims(value: &Json, base64: Base64Policy) -> Vec<String> {
+    if let Some(vp) = value.get("vp") {
+        return subject_claims(vp, base64);
+    }
+    if let Some(vc) = value.get("vc") {
+        return subject_claims(vc, base64);
+    }
+    if let Some(credentials) = value.get("verifiableCredential") {
+        let credentials = match credentials {
+            Json::Array(credentials) => credentials.iter().collect(),
+            credential => vec![credential],
+        };
+        return credentials
+            .into_iter()
+            .flat_map(|credential| match credential {
+                Json::String(jwt) => util::jwt_payload(jwt, base64)
+                    .ok()
+                    .map(|payload| subject_claims(&payload, base64))
+                    .unwrap_or_default(),
+                object => subject_claims(object, base64),
+            })
+            .collect();
+    }
+    let subjects = match value.get("credentialSubject") {
+        Some(Json::Array(subjects)) => subjects.iter().collect(),
+        Some(subject) => vec![subject],
+        None => vec![],
+    };
+    subjects
+        .into_iter()
+        .filter_map(Json::as_object)
+        .flat_map(|subject| subject.keys())
+        .filter(|claim| *claim != "id")
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use base64::prelude::*;
+    use serde_json::json;
+
+    use crate::core::{
+        credential_format::ClaimFormatDesignation,
+        input_descriptor::{Constraints, ConstraintsField, InputDescriptor},
+        object::UntypedObject,
+    };
+    use crate::wallet::credential_store::StoredCredential;
+
+    use super::*;
+
+    fn encode(value: Json) -> String {
+        BASE64_URL_SAFE_NO_PAD.encode(value.to_string())
+    }
+
+    #[test]
+    fn sd_jwt_over_disclosure() {
+        let presentation_definition = PresentationDefinition::new(
+            "pd".into(),
+            InputDescriptor::new(
+                "pid".into(),
+                Constraints::new()
+                    .add_constraint(ConstraintsField::new("$.given_name".into()))
+                    .add_constraint(ConstraintsField::new("$['family_name']".into())),
+            ),
+        );
+
+        let sd_jwt = format!(
+            "{}.{}.sig~{}~{}~{}~",
+            encode(json!({"alg": "ES256"})),
+            encode(json!({"_sd": []})),
+            encode(json!(["s1", "given_name", "Alice"])),
+            encode(json!(["s2", "family_name", "Smith"])),
+            encode(json!(["s3", "birthdate", "2000-01-01"])),
+        );
+        let response: UnencodedAuthorizationResponse =
+            serde_json::from_value::<UntypedObject>(json!({
+                "vp_token": sd_jwt,
+                "presentation_submission": {
+                    "id": "d05a7f51-ac09-43af-8864-e00f0175f2c7",
+                    "definition_id": "pd",
+                    "descriptor_map": [{
+                        "id": "pid",
+                        "format": ClaimFormatDesignation::Other("vc+sd-jwt".into()),
+                        "path": "$"
+                    }]
+                }
+            }))
+            .unwrap()
+            .try_into()
+            .unwrap();
+
+        assert_eq!(
+            find_over_disclosure(&presentation_definition, &response, Base64Policy::default()),
+            vec![OverDisclosure {
+                input_descriptor_id: "pid".into(),
+                claim: "birthdate".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn approved_claims() {
+        let sd_jwt = format!(
+            "{}.{}.sig~{}~{}~{}~",
+            encode(json!({"alg": "ES256"})),
+            encode(json!({"_sd": []})),
+            encode(json!(["s1", "given_name", "Alice"])),
+            encode(json!(["s2", "Bob"])),
+            encode(json!(["s3", "birthdate", "2000-01-01"])),
+        );
+        let restricted = restrict_sd_jwt(&sd_jwt, &["$.given_name".into()]);
+        assert_eq!(
+            sd_jwt_disclosures(&restricted, Base64Policy::default()),
+            vec!["given_name".to_string()]
+        );
+        assert_eq!(restricted.split('~').count(), 4);
+        assert!(restricted.ends_with('~'));
+
+        let response = |vp_token: &str| -> UnencodedAuthorizationResponse {
+            serde_json::from_value::<UntypedObject>(json!({
+                "vp_token": vp_token,
+                "presentation_submission": {
+                    "id": "d05a7f51-ac09-43af-8864-e00f0175f2c7",
+                    "definition_id": "pd",
+                    "descriptor_map": [{ "id": "pid", "format": "vc+sd-jwt", "path": "$" }]
+                }
+            }))
+            .unwrap()
+            .try_into()
+            .unwrap()
+        };
+        let selected = [SelectedCredential::new(
+            "pid",
+            StoredCredential::new(
+                "1",
+                ClaimFormatDesignation::Other("vc+sd-jwt".into()),
+                json!({}),
+            ),
+        )
+        .with_approved_claims(vec!["$.given_name".into()])];
+
+        assert!(find_unapproved_disclosure(
+            &selected,
+            &response(&restricted),
+            Base64Policy::default()
+        )
+        .is_empty());
+        assert_eq!(
+            find_unapproved_disclosure(&selected, &response(&sd_jwt), Base64Policy::default()),
+            vec![OverDisclosure {
+                input_descriptor_id: "pid".into(),
+                claim: "birthdate".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn credential_subject_claims() {
+        let vc = json!({"credentialSubject": {"id": "did:example:1", "name": "Alice"}});
+        let vp = format!(
+            "{}.{}.sig",
+            encode(json!({"alg": "ES256"})),
+            encode(json!({"vp": {"verifiableCredential": [
+                format!("{}.{}.sig", encode(json!({"alg": "ES256"})), encode(json!({"vc": vc})))
+            ]}}))
+        );
+        assert_eq!(
+            disclosed_claims(&Json::String(vp), Base64Policy::default()),
+            vec!["name"]
+        );
+        assert_eq!(claim_name("$.credentialSubject.name"), Some("name"));
+        assert_eq!(claim_name("$.credentialSubject.*"), None);
+    }
+
+    #[test]
+    fn padded_disclosures() {
+        let disclosure = BASE64_URL_SAFE.encode(json!(["s1", "given_name", "Alicia"]).to_string());
+        assert!(disclosure.ends_with('='));
+        let sd_jwt = format!(
+            "{}.{}.sig~{disclosure}~",
+            encode(json!({"alg": "ES256"})),
+            encode(json!({"_sd": []})),
+        );
+
+        assert_eq!(
+            sd_jwt_disclosures(&sd_jwt, Base64Policy::Lenient),
+            vec!["given_name".to_string()]
+        );
+        assert!(sd_jwt_disclosures(&sd_jwt, Base64Policy::Strict).is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use url::Url;
+
+use crate::core::authorization_request::AuthorizationRequestObject;
+
+use super::disclosure::OverDisclosure;
+
+/// The stage of the presentation flow at which an error occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletStage {
+    /// Parsing and verifying the request.
+    Validation,
+    /// Matching credentials against the request.
+    Matching,
+    /// Asking the user for consent.
+    Consent,
+    /// Submitting the response to the verifier.
+    Submission,
+}
+
+/// Callbacks invoked by the [Wallet](super::Wallet) as a presentation progresses, e.g. to record
+/// metrics or analytics.
+///
+/// Every callback does nothing by default. Durations are measured from the start of the
+/// corresponding stage. Callbacks are invoked inline, so implementations should not block.
+pub trait WalletEvents: Send + Sync {
+    /// A request was received, from a URL or (with `None`) through the Digital Credentials API.
+    fn request_received(&self, _url: Option<&Url>) {}
+
+    /// A request was validated successfully.
+    fn request_validated(&self, _request: &AuthorizationRequestObject, _elapsed: Duration) {}
+
+    /// The user approved or refused to share credentials.
+    fn user_decision(
+        &self,
+        _request: &AuthorizationRequestObject,
+        _approved: bool,
+        _elapsed: Duration,
+    ) {
+    }
+
+    /// The response built for a request discloses claims that were not requested.
+    fn over_disclosure(&self, _request: &AuthorizationRequestObject, _findings: &[OverDisclosure]) {
+    }
+
+    /// A response was submitted to the verifier.
+    fn response_submitted(&self, _request: &AuthorizationRequestObject, _elapsed: Duration) {}
+
+    /// A stage of the flow failed.
+    fn error(&self, _stage: WalletStage, _error: &anyhow::Error, _elapsed: Duration) {}
+}
+
+/// Report the failure of a stage that started at `start`, passing the result through.
+pub(crate) fn report_error<T>(
+    events: Option<&dyn WalletEvents>,
+    stage: WalletStage,
+    start: Instant,
+    result: Result<T>,
+) -> Result<T> {
+    if let (Some(events), Err(e)) = (events, &result) {
+        events.error(stage, e, start.elapsed())
+    }
+    result
+}
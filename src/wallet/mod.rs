@@ -0,0 +1,839 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use http::header::CONTENT_TYPE;
+use rand::rngs::OsRng;
+use serde_json::{Map, Value as Json};
+use tracing::{field::Empty, instrument, warn, Span};
+use url::Url;
+
+use self::{
+    batch::BatchedRequest,
+    consent::{
+        ConsentDecision, ConsentHandler, ConsentRequest, SelectedCredential, VerifierIdentity,
+    },
+    credential_store::{check_vp_formats, CandidateSets, CredentialStore},
+    disclosure::{find_over_disclosure, find_unapproved_disclosure},
+    events::{report_error, WalletEvents, WalletStage},
+    outbox::{Outbox, OutboxReport, QueuedResponse},
+    presentation::PresentationHandler,
+    response_mode::ResponsePath,
+    session::PendingPresentation,
+};
+use crate::core::{
+    authorization_request::{
+        dc_api::DcApiRequest,
+        parameters::{ClientMetadata, ResponseMode, Scope, State},
+        verification::{unsigned::UnsignedRequestPolicy, RequestVerifier},
+        AuthorizationRequest, AuthorizationRequestObject,
+    },
+    consts::media_type,
+    credential_format::ClaimFormatDesignation,
+    events::{EventSubscriber, LifecycleEvent, LifecycleEventKind},
+    jwe::{self, EncryptionNotSupported, ResponseEncryption},
+    metadata::WalletMetadata,
+    object::{registry::ParameterRegistry, ParsingErrorContext, UnknownParameters, UntypedObject},
+    presentation_definition::PresentationDefinition,
+    response::{
+        error::{AuthorizationErrorCode, AuthorizationErrorResponse, ResponseModeNotSupported},
+        jarm::JarmClaims,
+        parameters::MdocGeneratedNonce,
+        AuthorizationResponse, JwtAuthorizationResponse, PostRedirection,
+        UnencodedAuthorizationResponse,
+    },
+    spans,
+    util::{
+        base_request,
+        middleware::{HttpMiddleware, MiddlewareClient},
+        retry::{execute_with_policy, HttpOperation, RetryPolicy},
+        AsyncHttpClient, Base64Policy,
+    },
+};
+
+pub mod batch;
+pub mod consent;
+pub mod credential_store;
+pub mod disclosure;
+pub mod events;
+pub mod outbox;
+pub mod presentation;
+pub mod redirect;
+pub mod response_mode;
+pub mod session;
+#[cfg(feature = "simple-wallet")]
+pub mod simple;
+
+/// The length of the [MdocGeneratedNonce] of encrypted mdoc responses, comfortably above the 16
+/// bytes of entropy required by ISO/IEC 18013-7.
+const MDOC_GENERATED_NONCE_LENGTH: usize = 32;
+
+#[async_trait]
+pub trait Wallet: RequestVerifier + Sync {
+    type HttpClient: AsyncHttpClient + Send + Sync;
+
+    fn metadata(&self) -> &WalletMetadata;
+    fn http_client(&self) -> &Self::HttpClient;
+
+    /// Callbacks to invoke as presentations progress, none by default.
+    fn events(&self) -> Option<&dyn WalletEvents> {
+        None
+    }
+
+    /// The subscriber to the [LifecycleEvent]s of presentations, none by default.
+    fn event_subscriber(&self) -> Option<&dyn EventSubscriber> {
+        None
+    }
+
+    /// Emit a [LifecycleEvent] about `request` to the [event_subscriber](Self::event_subscriber).
+    fn emit_event(&self, kind: LifecycleEventKind, request: Option<&AuthorizationRequestObject>) {
+        if let Some(subscriber) = self.event_subscriber() {
+            subscriber.on_event(&LifecycleEvent::wallet(kind, request))
+        }
+    }
+
+    /// Whether [handle_request](Self::handle_request) reports failures to the verifier with an
+    /// Authorization Error Response, disabled by default.
+    fn auto_submit_errors(&self) -> bool {
+        false
+    }
+
+    /// A hook to modify outgoing HTTP requests, none by default.
+    fn http_middleware(&self) -> Option<&dyn HttpMiddleware> {
+        None
+    }
+
+    /// Make an HTTP request for an operation of the presentation flow, applying the
+    /// [http_middleware](Self::http_middleware) and [retry_policy](Self::retry_policy).
+    async fn execute_http(
+        &self,
+        operation: HttpOperation,
+        request: http::Request<Vec<u8>>,
+    ) -> Result<http::Response<Vec<u8>>> {
+        let client = MiddlewareClient {
+            inner: self.http_client(),
+            middleware: self.http_middleware(),
+            operation,
+        };
+        execute_with_policy(&client, request, &self.retry_policy(operation)).await
+    }
+
+    /// The timeout and retry behaviour of an HTTP operation, see [RetryPolicy::for_operation] for
+    /// the defaults.
+    fn retry_policy(&self, operation: HttpOperation) -> RetryPolicy {
+        RetryPolicy::for_operation(operation)
+    }
+
+    /// Where responses that could not be submitted because of a network failure are queued, see
+    /// [flush_outbox](Self::flush_outbox). None by default, so such failures are only returned.
+    fn outbox(&self) -> Option<&dyn Outbox> {
+        None
+    }
+
+    /// How long a queued response may be submitted for, one hour by default.
+    fn outbox_ttl(&self) -> Duration {
+        Duration::from_secs(60 * 60)
+    }
+
+    /// The private JWK that Request Objects are encrypted to, whose public key is in the `jwks`
+    /// of the [metadata](Self::metadata). Encrypted Request Objects are rejected by default.
+    fn request_object_decryption_key(&self) -> Option<&Map<String, Json>> {
+        None
+    }
+
+    /// How parameters of requests and client metadata that this library does not know are
+    /// handled, e.g. to detect typos during development. Ignored by default, as OAuth 2.0
+    /// requires.
+    fn unknown_parameters(&self) -> UnknownParameters {
+        UnknownParameters::Ignore
+    }
+
+    /// Parameters of requests and client metadata defined outside of this library, e.g. by the
+    /// profile the wallet implements, which are parsed when requests are validated and are known
+    /// to the [unknown_parameters](Self::unknown_parameters) checks. None by default.
+    fn parameter_registry(&self) -> Option<&ParameterRegistry> {
+        None
+    }
+
+    /// The `iss` claim and lifetime of JWT-secured responses (`direct_post.jwt`). By default, the
+    /// `issuer` of the [metadata](Self::metadata), or else its `authorization_endpoint`, with
+    /// [JarmClaims::DEFAULT_LIFETIME].
+    fn jarm_claims(&self) -> JarmClaims {
+        let metadata = self.metadata();
+        let issuer = match metadata.issuer() {
+            Some(issuer) => issuer.0.clone(),
+            None => metadata.authorization_endpoint().0.to_string(),
+        };
+        JarmClaims::new(issuer)
+    }
+
+    /// How the base64url values of presentations are decoded, e.g. when checking the claims they
+    /// disclose. Wallets in the field disagree on padding, so decoding is
+    /// [lenient](Base64Policy::Lenient) by default.
+    fn base64_policy(&self) -> Base64Policy {
+        Base64Policy::default()
+    }
+
+    /// Whether unsigned requests are accepted, rejected by default.
+    fn unsigned_request_policy(&self) -> UnsignedRequestPolicy {
+        UnsignedRequestPolicy::Reject
+    }
+
+    /// Resolve the presentation definition of a request that only carries a `scope`, using the
+    /// mapping agreed with the verifier. Scope-only requests are rejected by default.
+    async fn resolve_scope(&self, scope: &Scope) -> Result<PresentationDefinition> {
+        bail!(
+            "the presentation definition was requested by scope ({}), which this wallet does not support",
+            scope.0.join(" ")
+        )
+    }
+
+    #[instrument(
+        skip_all,
+        fields(client_id = Empty, client_id_scheme = Empty, response_mode = Empty)
+    )]
+    async fn validate_request(&self, url: Url) -> Result<AuthorizationRequestObject> {
+        let start = Instant::now();
+        if let Some(events) = self.events() {
+            events.request_received(Some(&url))
+        }
+
+        let result = async {
+            let ar = AuthorizationRequest::from_url_with_endpoints(
+                url,
+                self.metadata().invocation_endpoints(),
+            )
+            .context("unable to parse authorization request")?;
+            ar.validate(self)
+                .await
+                .context("unable to validate authorization request")
+        }
+        .await;
+
+        self.report_validation(start, result)
+    }
+
+    /// Notify the [events](Self::events) and the [event_subscriber](Self::event_subscriber) of
+    /// the outcome of validating a request.
+    fn report_validation(
+        &self,
+        start: Instant,
+        result: Result<AuthorizationRequestObject>,
+    ) -> Result<AuthorizationRequestObject> {
+        if let Ok(request) = &result {
+            spans::record_request(&Span::current(), request);
+        }
+        match &result {
+            Ok(request) => self.emit_event(LifecycleEventKind::RequestRetrieved, Some(request)),
+            Err(e) => self.emit_event(LifecycleEvent::failure(e), None),
+        }
+        if let (Some(events), Ok(request)) = (self.events(), &result) {
+            events.request_validated(request, start.elapsed())
+        }
+        report_error(self.events(), WalletStage::Validation, start, result)
+    }
+
+    /// Validate an Authorization Request delivered by the Digital Credentials API.
+    ///
+    /// The validated request can then be handled like a URL-based request, e.g. with
+    /// [complete_request](Self::complete_request).
+    #[instrument(
+        skip_all,
+        fields(client_id = Empty, client_id_scheme = Empty, response_mode = Empty)
+    )]
+    async fn validate_dc_api_request(
+        &self,
+        request: DcApiRequest,
+    ) -> Result<AuthorizationRequestObject> {
+        let start = Instant::now();
+        if let Some(events) = self.events() {
+            events.request_received(None)
+        }
+
+        let result = request
+            .validate(self)
+            .await
+            .context("unable to validate Digital Credentials API request");
+
+        self.report_validation(start, result)
+    }
+
+    /// Negotiate how the response to a request must be encrypted.
+    ///
+    /// Returns `None` if the verifier did not request an encrypted response.
+    async fn response_encryption(
+        &self,
+        request: &AuthorizationRequestObject,
+    ) -> Result<Option<ResponseEncryption>> {
+        let client_metadata = ClientMetadata::resolve(request, self.http_client()).await?;
+        jwe::negotiate(&client_metadata.0, self.metadata())
+    }
+
+    /// Determine how the response to a request must be delivered.
+    ///
+    /// The `response_mode` must be listed in the wallet's `response_modes_supported` (if present).
+    /// `direct_post.jwt` requires the verifier to request an encrypted response the wallet can
+    /// produce, as signed-only responses are not supported.
+    async fn response_path(&self, request: &AuthorizationRequestObject) -> Result<ResponsePath> {
+        let response_mode = request.response_mode();
+        if !self.metadata().supports_response_mode(response_mode) {
+            bail!(ResponseModeNotSupported(response_mode.clone()))
+        }
+
+        match response_mode {
+            ResponseMode::DirectPost => Ok(ResponsePath::DirectPost),
+            ResponseMode::DirectPostJwt => match self.response_encryption(request).await? {
+                Some(encryption) => Ok(ResponsePath::DirectPostJwt(encryption)),
+                None => bail!(EncryptionNotSupported(format!(
+                    "response_mode '{response_mode}' requires an encrypted response, but the client metadata does not request one"
+                ))),
+            },
+            ResponseMode::Unsupported(rm) => bail!("unsupported response_mode {rm}"),
+        }
+    }
+
+    /// Encode a response as required by the request's [ResponsePath], encrypting it if necessary.
+    ///
+    /// If the response presents an mdoc, a new [MdocGeneratedNonce] is bound into the encryption
+    /// key agreement (`apu`), together with the request's `nonce` (`apv`).
+    async fn encode_response(
+        &self,
+        request: &AuthorizationRequestObject,
+        mut response: UnencodedAuthorizationResponse,
+    ) -> Result<AuthorizationResponse> {
+        // The state from the request must be echoed in the response.
+        if let Some(state) = request.get::<State>() {
+            if !response.0.contains::<State>() {
+                response.0.insert(state.parsing_error()?);
+            }
+        }
+
+        match self.response_path(request).await? {
+            ResponsePath::DirectPost => Ok(AuthorizationResponse::Unencoded(response)),
+            ResponsePath::DirectPostJwt(encryption) => {
+                let presents_mdoc = response
+                    .presentation_submission()
+                    .descriptor_map()
+                    .iter()
+                    .any(|descriptor| descriptor.format() == &ClaimFormatDesignation::MsoMDoc);
+                let mdoc_generated_nonce = presents_mdoc
+                    .then(|| MdocGeneratedNonce::random(&mut OsRng, MDOC_GENERATED_NONCE_LENGTH));
+                let (apu, apv) = match &mdoc_generated_nonce {
+                    Some(mdoc_generated_nonce) => (
+                        mdoc_generated_nonce.0.as_bytes(),
+                        request.nonce().as_str().as_bytes(),
+                    ),
+                    None => (&[][..], &[][..]),
+                };
+                let claims = self
+                    .jarm_claims()
+                    .apply(response, request, SystemTime::now())?;
+                let payload = serde_json::to_vec(&claims)?;
+                let jwe = jwe::compact::encrypt(&encryption, &payload, apu, apv, &mut OsRng)
+                    .context("failed to encrypt authorization response")?;
+                Ok(AuthorizationResponse::Jwt(JwtAuthorizationResponse {
+                    response: jwe,
+                }))
+            }
+        }
+    }
+
+    /// Validate several authorization requests concurrently.
+    ///
+    /// All requests share this wallet's HTTP client (and any caches held by its
+    /// [RequestVerifier] implementation, see
+    /// [CachingJwkResolver](crate::core::authorization_request::verification::did::CachingJwkResolver)).
+    /// Failures are reported per request, and the results are in the same order as `urls`.
+    async fn validate_requests(&self, urls: Vec<Url>) -> Vec<BatchedRequest> {
+        futures::future::join_all(urls.into_iter().map(|url| async move {
+            let request = self.validate_request(url.clone()).await;
+            BatchedRequest::new(url, request)
+        }))
+        .await
+    }
+
+    /// Find the credentials in the store that can satisfy the presentation definition of a
+    /// validated request.
+    ///
+    /// Before matching, the requested formats are checked against the wallet metadata and the
+    /// formats of the stored credentials, failing with
+    /// [VpFormatsNotSupported](crate::core::response::error::VpFormatsNotSupported) if they cannot be
+    /// presented (see [check_vp_formats]).
+    async fn find_candidates(
+        &self,
+        request: &AuthorizationRequestObject,
+        store: &dyn CredentialStore,
+    ) -> Result<CandidateSets> {
+        let presentation_definition = match request.presentation_definition_scope() {
+            Some(scope) => self.resolve_scope(scope).await?,
+            None => request
+                .resolve_presentation_definition(self.http_client())
+                .await
+                .context("unable to resolve presentation definition")?
+                .into_parsed(),
+        };
+        let credentials = store.list().await;
+        check_vp_formats(
+            &presentation_definition,
+            self.metadata().vp_formats_supported(),
+            &credentials,
+        )?;
+        Ok(CandidateSets::new(presentation_definition, &credentials))
+    }
+
+    /// Ask the user for consent to share credentials with the verifier.
+    ///
+    /// An approved selection is checked against the candidates before it is returned, so the
+    /// response can be built from it directly.
+    async fn obtain_consent(
+        &self,
+        request: &AuthorizationRequestObject,
+        candidates: &CandidateSets,
+        handler: &dyn ConsentHandler,
+    ) -> Result<ConsentDecision> {
+        let client_metadata = ClientMetadata::resolve(request, self.http_client()).await?;
+        let verifier = VerifierIdentity::new(
+            request.client_id().clone(),
+            request.client_id_scheme().clone(),
+            &client_metadata,
+        );
+        let requested_fields = candidates
+            .presentation_definition
+            .input_descriptors()
+            .iter()
+            .map(|input_descriptor| input_descriptor.requested_fields_with_credential_types())
+            .collect::<Vec<_>>();
+
+        let decision = handler
+            .request_consent(ConsentRequest {
+                verifier: &verifier,
+                requested_fields: &requested_fields,
+                candidates,
+            })
+            .await?;
+
+        if let ConsentDecision::Approve(selected) = &decision {
+            let selected = selected
+                .iter()
+                .map(|s| (s.input_descriptor_id.as_str(), &s.credential))
+                .collect::<Vec<_>>();
+            candidates
+                .validate_selection(&selected)
+                .context("the selected credentials do not satisfy the request")?;
+        }
+
+        Ok(decision)
+    }
+
+    /// Validate a request, find the matching credentials and obtain the user's consent to share
+    /// them.
+    ///
+    /// When [auto_submit_errors](Self::auto_submit_errors) is enabled, failures after the request
+    /// has been validated are also reported to the verifier (`vp_formats_not_supported`,
+    /// `access_denied` or `invalid_request`), so that the verifier's session terminates. Failures
+    /// to validate the request are only returned locally, as the response URI of a request that
+    /// could not be validated is not trusted.
+    async fn handle_request(
+        &self,
+        url: Url,
+        store: &dyn CredentialStore,
+        handler: &dyn ConsentHandler,
+    ) -> Result<HandledRequest> {
+        let request = self.validate_request(url).await?;
+        self.complete_request(request, store, handler).await
+    }
+
+    /// Resume a presentation that was persisted while awaiting the user's consent, e.g. after the
+    /// wallet process was restarted, continuing as [handle_request](Self::handle_request) would.
+    ///
+    /// The request is not validated again, callers should discard expired presentations (see
+    /// [PendingPresentation::is_expired]).
+    async fn resume_request(
+        &self,
+        pending: &PendingPresentation,
+        store: &dyn CredentialStore,
+        handler: &dyn ConsentHandler,
+    ) -> Result<HandledRequest> {
+        let request = pending.request()?;
+        self.complete_request(request, store, handler).await
+    }
+
+    /// Match credentials against a validated request and obtain the user's consent.
+    #[instrument(
+        skip_all,
+        fields(client_id = %request.client_id().0, response_mode = %request.response_mode())
+    )]
+    async fn complete_request(
+        &self,
+        request: AuthorizationRequestObject,
+        store: &dyn CredentialStore,
+        handler: &dyn ConsentHandler,
+    ) -> Result<HandledRequest> {
+        let start = Instant::now();
+        let stage = WalletStage::Matching;
+
+        let candidates = match self.find_candidates(&request, store).await {
+            Ok(candidates) => candidates,
+            Err(e) => {
+                let code = AuthorizationErrorCode::for_error(&e);
+                return Err(self.fail_request(&request, stage, start, code, e).await);
+            }
+        };
+
+        if !candidates.is_fully_satisfiable() {
+            let e = anyhow::anyhow!("no credentials match the request");
+            let code = AuthorizationErrorCode::AccessDenied;
+            return Err(self.fail_request(&request, stage, start, code, e).await);
+        }
+
+        let start = Instant::now();
+        let stage = WalletStage::Consent;
+
+        let decision = self.obtain_consent(&request, &candidates, handler).await;
+        if let (Some(events), Ok(decision)) = (self.events(), &decision) {
+            let approved = matches!(decision, ConsentDecision::Approve(_));
+            events.user_decision(&request, approved, start.elapsed())
+        }
+
+        match decision {
+            Ok(ConsentDecision::Approve(selected)) => Ok(HandledRequest {
+                request,
+                presentation_definition: candidates.presentation_definition,
+                selected,
+            }),
+            Ok(ConsentDecision::Refuse) => {
+                let e = anyhow::anyhow!("the user refused to share credentials");
+                let code = AuthorizationErrorCode::AccessDenied;
+                Err(self.fail_request(&request, stage, start, code, e).await)
+            }
+            Err(e) => {
+                let code = AuthorizationErrorCode::for_error(&e);
+                Err(self.fail_request(&request, stage, start, code, e).await)
+            }
+        }
+    }
+
+    /// Build the response to a handled request with the [PresentationHandler], and submit it.
+    ///
+    /// The response is rejected if it discloses claims outside the
+    /// [approved claims](SelectedCredential::approved_claims) of a selected credential, which is
+    /// reported as `access_denied` like other failures, see [handle_request](Self::handle_request).
+    /// Claims included in the presentations but not requested are logged and reported to the
+    /// [events](Self::events) before submission, see [find_over_disclosure].
+    ///
+    /// Returns the redirect returned by the verifier, if any.
+    #[instrument(
+        skip_all,
+        fields(
+            client_id = %handled.request.client_id().0,
+            response_mode = %handled.request.response_mode(),
+            formats = Empty,
+        )
+    )]
+    async fn respond(
+        &self,
+        handled: HandledRequest,
+        handler: &dyn PresentationHandler,
+    ) -> Result<Option<Url>> {
+        let start = Instant::now();
+        let HandledRequest {
+            request,
+            presentation_definition,
+            selected,
+        } = handled;
+
+        let response = match handler.to_response(&request, &selected).await {
+            Ok(response) => response,
+            Err(e) => {
+                let e = e.context("failed to build the presentations");
+                let code = AuthorizationErrorCode::for_error(&e);
+                let stage = WalletStage::Submission;
+                return Err(self.fail_request(&request, stage, start, code, e).await);
+            }
+        };
+
+        spans::record_formats(
+            &Span::current(),
+            response
+                .presentation_submission()
+                .descriptor_map()
+                .iter()
+                .map(|descriptor| descriptor.format()),
+        );
+
+        let unapproved = find_unapproved_disclosure(&selected, &response, self.base64_policy());
+        if !unapproved.is_empty() {
+            let e = anyhow::anyhow!(
+                "the response discloses claims the user did not approve: {:?}",
+                unapproved
+            );
+            let code = AuthorizationErrorCode::AccessDenied;
+            let stage = WalletStage::Submission;
+            return Err(self.fail_request(&request, stage, start, code, e).await);
+        }
+
+        let over_disclosure =
+            find_over_disclosure(&presentation_definition, &response, self.base64_policy());
+        if !over_disclosure.is_empty() {
+            warn!(
+                "the response discloses claims that were not requested: {:?}",
+                over_disclosure
+            );
+            if let Some(events) = self.events() {
+                events.over_disclosure(&request, &over_disclosure)
+            }
+        }
+
+        let response = self.encode_response(&request, response).await?;
+        self.submit_response(request, response).await
+    }
+
+    /// Report a failure of a stage that started at `start` to the [events](Self::events), and to
+    /// the verifier if [auto_submit_errors](Self::auto_submit_errors) is enabled, then return the
+    /// local error.
+    ///
+    /// A failure to submit the error response is logged, and does not replace the original error.
+    async fn fail_request(
+        &self,
+        request: &AuthorizationRequestObject,
+        stage: WalletStage,
+        start: Instant,
+        code: AuthorizationErrorCode,
+        error: anyhow::Error,
+    ) -> anyhow::Error {
+        if let Some(events) = self.events() {
+            events.error(stage, &error, start.elapsed())
+        }
+
+        if self.auto_submit_errors() {
+            let response =
+                AuthorizationErrorResponse::new(code).with_error_description(error.to_string());
+            if let Err(e) = self.submit_error(request, response).await {
+                warn!("failed to submit error response: {e:#}")
+            }
+        }
+        error
+    }
+
+    /// Send an Authorization Error Response to the verifier.
+    ///
+    /// Error responses are sent as 'application/x-www-form-urlencoded' for both `direct_post` and
+    /// `direct_post.jwt`, as they contain no data that needs protecting.
+    #[instrument(
+        skip_all,
+        fields(client_id = %request.client_id().0, error = %response.error)
+    )]
+    async fn submit_error(
+        &self,
+        request: &AuthorizationRequestObject,
+        mut response: AuthorizationErrorResponse,
+    ) -> Result<Option<Url>> {
+        match request.response_mode() {
+            ResponseMode::DirectPost | ResponseMode::DirectPostJwt => {}
+            ResponseMode::Unsupported(rm) => bail!("unsupported response_mode {rm}"),
+        }
+
+        // The state from the request must be echoed in the response.
+        if response.state.is_none() {
+            if let Some(state) = request.get::<State>() {
+                response.state = Some(state.parsing_error()?.0);
+            }
+        }
+
+        let http_request = base_request()
+            .method("POST")
+            .uri(request.return_uri().as_str())
+            .header(CONTENT_TYPE, media_type::FORM_URLENCODED)
+            .body(response.into_x_www_form_urlencoded()?.into_bytes())
+            .context("failed to construct authorization error response request")?;
+        let http_response = self
+            .execute_http(HttpOperation::Response, http_request)
+            .await
+            .context("failed to make authorization error response request")?;
+
+        parse_post_response(http_response)
+    }
+
+    /// Submit the responses in the [outbox](Self::outbox) again.
+    ///
+    /// Delivered, rejected and expired responses are removed from the outbox, and reported so that
+    /// the app can inform the user about sessions that could not be completed.
+    async fn flush_outbox(&self) -> Result<Vec<OutboxReport>> {
+        let Some(outbox) = self.outbox() else {
+            return Ok(vec![]);
+        };
+
+        let mut reports = Vec::new();
+        for mut queued in outbox.list().await? {
+            let id = queued.id;
+            if queued.is_expired(SystemTime::now()) {
+                outbox.remove(id).await?;
+                reports.push(OutboxReport::Expired { id });
+                continue;
+            }
+
+            let http_request = base_request()
+                .method("POST")
+                .uri(queued.response_uri.as_str())
+                .header(CONTENT_TYPE, media_type::FORM_URLENCODED)
+                .body(queued.body.clone().into_bytes())
+                .context("failed to construct presentation submission request")?;
+
+            match self
+                .execute_http(HttpOperation::Response, http_request)
+                .await
+            {
+                Ok(http_response) => {
+                    outbox.remove(id).await?;
+                    reports.push(match parse_post_response(http_response) {
+                        Ok(redirect) => OutboxReport::Delivered { id, redirect },
+                        Err(error) => OutboxReport::Failed { id, error },
+                    });
+                }
+                Err(error) => {
+                    queued.attempts += 1;
+                    outbox.put(queued).await?;
+                    reports.push(OutboxReport::Retrying { id, error });
+                }
+            }
+        }
+        Ok(reports)
+    }
+
+    #[instrument(
+        skip_all,
+        fields(
+            client_id = %request.client_id().0,
+            response_mode = %request.response_mode(),
+            response_uri = %request.return_uri(),
+        )
+    )]
+    async fn submit_response(
+        &self,
+        request: AuthorizationRequestObject,
+        response: AuthorizationResponse,
+    ) -> Result<Option<Url>> {
+        let start = Instant::now();
+
+        let result = async {
+            // Unencoded responses to requests for an encrypted response are encrypted here.
+            let response = match (request.response_mode(), response) {
+                (ResponseMode::DirectPostJwt, AuthorizationResponse::Unencoded(unencoded)) => {
+                    self.encode_response(&request, unencoded).await?
+                }
+                (_, response) => response,
+            };
+
+            let mut http_request_builder = base_request().uri(request.return_uri().as_str());
+
+            let http_request_body = match request.response_mode() {
+                ResponseMode::DirectPost => {
+                    http_request_builder = http_request_builder
+                        .header(CONTENT_TYPE, media_type::FORM_URLENCODED)
+                        .method("POST");
+
+                    // The state from the request must be echoed in the response.
+                    let state = request.get::<State>();
+                    let echo_state = |parameters: &mut UntypedObject| -> Result<()> {
+                        if let Some(state) = state {
+                            if !parameters.contains::<State>() {
+                                parameters.insert(state.parsing_error()?);
+                            }
+                        }
+                        Ok(())
+                    };
+
+                    match response {
+                        AuthorizationResponse::Unencoded(mut unencoded) => {
+                            echo_state(&mut unencoded.0)?;
+                            unencoded.into_x_www_form_urlencoded()?.into_bytes()
+                        }
+                        AuthorizationResponse::Dcql(mut dcql) => {
+                            echo_state(&mut dcql.0)?;
+                            dcql.into_x_www_form_urlencoded()?.into_bytes()
+                        }
+                        AuthorizationResponse::Jwt(_) => {
+                            bail!("unexpected AuthorizationResponse format")
+                        }
+                    }
+                }
+                ResponseMode::DirectPostJwt => {
+                    http_request_builder = http_request_builder
+                        .header(CONTENT_TYPE, media_type::FORM_URLENCODED)
+                        .method("POST");
+
+                    let AuthorizationResponse::Jwt(jwt) = response else {
+                        bail!("unexpected AuthorizationResponse format")
+                    };
+
+                    jwt.into_x_www_form_urlencoded()?.into_bytes()
+                }
+                ResponseMode::Unsupported(rm) => bail!("unsupported response_mode {rm}"),
+            };
+
+            let http_request = http_request_builder
+                .body(http_request_body.clone())
+                .context("failed to construct presentation submission request")?;
+            let http_response = match self
+                .execute_http(HttpOperation::Response, http_request)
+                .await
+            {
+                Ok(http_response) => http_response,
+                Err(e) => {
+                    let e = e.context("failed to make authorization response request");
+                    let Some(outbox) = self.outbox() else {
+                        return Err(e);
+                    };
+                    let queued = QueuedResponse::new(
+                        request.return_uri().clone(),
+                        String::from_utf8(http_request_body)?,
+                        self.outbox_ttl(),
+                    );
+                    let id = queued.id;
+                    outbox
+                        .put(queued)
+                        .await
+                        .context("failed to queue authorization response")?;
+                    return Err(e.context(format!("authorization response queued as {id}")));
+                }
+            };
+
+            parse_post_response(http_response)
+        }
+        .await;
+
+        if result.is_ok() {
+            self.emit_event(LifecycleEventKind::ResponseSubmitted, Some(&request));
+        }
+        if let (Some(events), Ok(_)) = (self.events(), &result) {
+            events.response_submitted(&request, start.elapsed())
+        }
+        report_error(self.events(), WalletStage::Submission, start, result)
+    }
+}
+
+/// A validated request, and the credentials the user agreed to share in response.
+#[derive(Debug, Clone)]
+pub struct HandledRequest {
+    pub request: AuthorizationRequestObject,
+    pub presentation_definition: PresentationDefinition,
+    pub selected: Vec<SelectedCredential>,
+}
+
+/// Parse the verifier's response to a POSTed authorization response, which may contain a
+/// redirect.
+fn parse_post_response(http_response: http::Response<Vec<u8>>) -> Result<Option<Url>> {
+    let status = http_response.status();
+    let Ok(body) = String::from_utf8(http_response.into_body()) else {
+        bail!("failed to parse authorization response response as UTF-8 (status: {status})")
+    };
+
+    if !status.is_success() {
+        bail!("authorization response request was unsuccessful (status: {status}): {body}")
+    }
+
+    Ok(serde_json::from_str(&body)
+        .map_err(|e| warn!("response did not contain a redirect: {e}"))
+        .ok()
+        .map(|PostRedirection { redirect_uri }| redirect_uri))
+}
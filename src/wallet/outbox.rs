@@ -0,0 +1,129 @@
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use url::Url;
+use uuid::Uuid;
+
+use crate::core::util::Redacted;
+
+/// An Authorization Response that could not be delivered because of a network failure, kept to
+/// be submitted again later.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QueuedResponse {
+    pub id: Uuid,
+    pub response_uri: Url,
+    /// The 'application/x-www-form-urlencoded' request body.
+    pub body: String,
+    /// After this time, the verifier is assumed to no longer accept the response.
+    pub expires_at: SystemTime,
+    /// Number of failed submission attempts.
+    pub attempts: u32,
+}
+
+impl QueuedResponse {
+    pub fn new(response_uri: Url, body: String, ttl: Duration) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            response_uri,
+            body,
+            expires_at: SystemTime::now() + ttl,
+            attempts: 1,
+        }
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        now >= self.expires_at
+    }
+}
+
+impl std::fmt::Debug for QueuedResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueuedResponse")
+            .field("id", &self.id)
+            .field("response_uri", &self.response_uri)
+            .field("body", &Redacted(self.body.as_bytes()))
+            .field("expires_at", &self.expires_at)
+            .field("attempts", &self.attempts)
+            .finish()
+    }
+}
+
+/// The outcome of submitting a queued response again.
+#[derive(Debug)]
+pub enum OutboxReport {
+    /// The response was delivered, and the verifier may have returned a redirect.
+    Delivered { id: Uuid, redirect: Option<Url> },
+    /// Submission failed because of a network failure again, the response stays queued.
+    Retrying { id: Uuid, error: anyhow::Error },
+    /// The verifier rejected the response, which has been removed from the outbox.
+    Failed { id: Uuid, error: anyhow::Error },
+    /// The response expired before it could be delivered, and has been removed from the outbox.
+    Expired { id: Uuid },
+}
+
+/// Persistent storage for responses awaiting submission.
+#[async_trait]
+pub trait Outbox: Send + Sync {
+    /// Insert or replace a queued response.
+    async fn put(&self, response: QueuedResponse) -> Result<()>;
+    async fn remove(&self, id: Uuid) -> Result<()>;
+    async fn list(&self) -> Result<Vec<QueuedResponse>>;
+}
+
+/// An [Outbox] that does not persist across restarts.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryOutbox {
+    responses: Arc<Mutex<BTreeMap<Uuid, QueuedResponse>>>,
+}
+
+#[async_trait]
+impl Outbox for MemoryOutbox {
+    async fn put(&self, response: QueuedResponse) -> Result<()> {
+        self.responses.lock().await.insert(response.id, response);
+        Ok(())
+    }
+
+    async fn remove(&self, id: Uuid) -> Result<()> {
+        self.responses.lock().await.remove(&id);
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<QueuedResponse>> {
+        Ok(self.responses.lock().await.values().cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn memory_outbox() {
+        let outbox = MemoryOutbox::default();
+        let mut queued = QueuedResponse::new(
+            "https://verifier.example.com/response".parse().unwrap(),
+            "vp_token=secret".into(),
+            Duration::from_secs(60),
+        );
+        assert!(!format!("{queued:?}").contains("secret"));
+        assert!(!queued.is_expired(SystemTime::now()));
+        assert!(queued.is_expired(queued.expires_at));
+
+        outbox.put(queued.clone()).await.unwrap();
+        queued.attempts += 1;
+        outbox.put(queued.clone()).await.unwrap();
+        let listed = outbox.list().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].attempts, 2);
+
+        outbox.remove(queued.id).await.unwrap();
+        assert!(outbox.list().await.unwrap().is_empty());
+    }
+}
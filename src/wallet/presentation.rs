@@ -0,0 +1,40 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{Map, Value as Json};
+
+use crate::core::{
+    authorization_request::AuthorizationRequestObject, response::UnencodedAuthorizationResponse,
+    transaction_data::TransactionDataBinding,
+};
+
+use super::consent::SelectedCredential;
+
+/// Builds the presentations for the credentials the user agreed to share.
+///
+/// Building a response typically involves signing (e.g. a Key Binding JWT, or an mdoc device
+/// signature in secure hardware), so [to_response](Self::to_response) is asynchronous.
+#[async_trait]
+pub trait PresentationHandler: Send + Sync {
+    /// Build the unencoded response, containing the `vp_token` and `presentation_submission`.
+    ///
+    /// The Key Binding JWTs of SD-JWT presentations must carry the [key_binding_claims] of the
+    /// request. The response is encoded (and encrypted if required) by the wallet.
+    async fn to_response(
+        &self,
+        request: &AuthorizationRequestObject,
+        selected: &[SelectedCredential],
+    ) -> Result<UnencodedAuthorizationResponse>;
+}
+
+/// The claims that the Key Binding JWT of an SD-JWT presentation must carry for a request, besides
+/// `iat` and `sd_hash`: its `nonce`, its `client_id` as `aud`, and the `transaction_data_hashes`
+/// binding its `transaction_data`, if any (see [TransactionDataBinding]).
+pub fn key_binding_claims(request: &AuthorizationRequestObject) -> Result<Map<String, Json>> {
+    let mut claims = Map::new();
+    claims.insert("nonce".into(), request.nonce().as_str().into());
+    claims.insert("aud".into(), request.client_id().0.clone().into());
+    if let Some(binding) = TransactionDataBinding::from_request(request)? {
+        binding.insert_into(&mut claims);
+    }
+    Ok(claims)
+}
@@ -0,0 +1,137 @@
+use anyhow::{bail, Result};
+use url::Url;
+
+use crate::core::{
+    authorization_request::{parameters::State, AuthorizationRequestObject},
+    object::ParsingErrorContext,
+    response::PostRedirection,
+};
+
+/// The platform the wallet runs on, which determines how a redirect is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Android,
+    Ios,
+    /// A wallet running in a web browser.
+    Web,
+}
+
+/// How to hand the user back to the verifier after submitting a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenAction {
+    /// Open the URL in the system browser, e.g. with an Android `ACTION_VIEW` intent or iOS
+    /// `UIApplication.open`, so that the verifier's web session is resumed.
+    ExternalBrowser(Url),
+    /// Open the URL with the app registered for its (custom) scheme.
+    App(Url),
+    /// Navigate the current browsing context to the URL.
+    Navigate(Url),
+}
+
+/// The redirect returned by the verifier after a same-device response submission.
+#[derive(Debug, Clone)]
+pub struct SameDeviceRedirect {
+    uri: Url,
+    response_code: Option<String>,
+}
+
+impl SameDeviceRedirect {
+    /// Parse the `redirect_uri` returned by the verifier.
+    ///
+    /// The `response_code` is read from the fragment or the query, and only `https` and custom
+    /// schemes are accepted.
+    pub fn new(uri: Url) -> Result<Self> {
+        match uri.scheme() {
+            "http" | "javascript" | "data" | "file" => {
+                bail!("refusing to redirect to a '{}' URI", uri.scheme())
+            }
+            _ => {}
+        }
+
+        let fragment_params = uri
+            .fragment()
+            .map(|fragment| url::form_urlencoded::parse(fragment.as_bytes()))
+            .into_iter()
+            .flatten();
+        let response_code = fragment_params
+            .chain(uri.query_pairs())
+            .find(|(name, _)| name == "response_code")
+            .map(|(_, value)| value.into_owned());
+
+        Ok(Self { uri, response_code })
+    }
+
+    pub fn uri(&self) -> &Url {
+        &self.uri
+    }
+
+    /// The `response_code` the verifier uses to bind the redirect to the submitted response.
+    pub fn response_code(&self) -> Option<&str> {
+        self.response_code.as_deref()
+    }
+
+    /// The `state` of the request the redirect belongs to.
+    ///
+    /// The `state` is not part of the redirect itself, but is needed by the verifier's frontend
+    /// to look up the session along with the `response_code`.
+    pub fn state(request: &AuthorizationRequestObject) -> Result<Option<String>> {
+        request
+            .get::<State>()
+            .map(|state| state.parsing_error().map(|State(state)| state))
+            .transpose()
+    }
+
+    /// The action to open the redirect on the given platform.
+    pub fn open_action(&self, platform: Platform) -> OpenAction {
+        let uri = self.uri.clone();
+        match (platform, uri.scheme()) {
+            (Platform::Web, _) => OpenAction::Navigate(uri),
+            (_, "https") => OpenAction::ExternalBrowser(uri),
+            _ => OpenAction::App(uri),
+        }
+    }
+}
+
+impl TryFrom<PostRedirection> for SameDeviceRedirect {
+    type Error = anyhow::Error;
+
+    fn try_from(value: PostRedirection) -> Result<Self> {
+        Self::new(value.redirect_uri)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn response_code() {
+        let redirect = SameDeviceRedirect::new(
+            "https://verifier.example.com/cb#response_code=abc"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(redirect.response_code(), Some("abc"));
+        assert_eq!(
+            redirect.open_action(Platform::Android),
+            OpenAction::ExternalBrowser(redirect.uri().clone())
+        );
+
+        let redirect =
+            SameDeviceRedirect::new("verifierapp://cb?response_code=def".parse().unwrap()).unwrap();
+        assert_eq!(redirect.response_code(), Some("def"));
+        assert_eq!(
+            redirect.open_action(Platform::Ios),
+            OpenAction::App(redirect.uri().clone())
+        );
+        assert_eq!(
+            redirect.open_action(Platform::Web),
+            OpenAction::Navigate(redirect.uri().clone())
+        );
+
+        assert!(
+            SameDeviceRedirect::new("http://verifier.example.com/cb".parse().unwrap()).is_err()
+        );
+    }
+}
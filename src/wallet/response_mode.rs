@@ -0,0 +1,11 @@
+use crate::core::jwe::ResponseEncryption;
+
+/// How the response to a request is delivered to the verifier, as negotiated from the request's
+/// `response_mode`, the verifier's client metadata and the wallet's metadata.
+#[derive(Debug, Clone)]
+pub enum ResponsePath {
+    /// POST the response parameters to the `response_uri`.
+    DirectPost,
+    /// POST the response parameters to the `response_uri`, encrypted as a JWE.
+    DirectPostJwt(ResponseEncryption),
+}
@@ -0,0 +1,85 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::core::{authorization_request::AuthorizationRequestObject, object::UntypedObject};
+
+/// A validated request awaiting the user's consent, which can be persisted to resume the
+/// presentation after the wallet process has been restarted.
+///
+/// Only the request is kept: the candidate credentials are matched again when the presentation
+/// is resumed, as the credential store may have changed in the meantime.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingPresentation {
+    /// Identifies this presentation, e.g. to correlate UI state.
+    pub id: Uuid,
+    /// When the request was received.
+    pub received_at: SystemTime,
+    request: UntypedObject,
+}
+
+impl PendingPresentation {
+    pub fn new(request: AuthorizationRequestObject) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            received_at: SystemTime::now(),
+            request: request.into(),
+        }
+    }
+
+    /// Restore the validated request.
+    pub fn request(&self) -> Result<AuthorizationRequestObject> {
+        self.request
+            .clone()
+            .try_into()
+            .context("persisted request is not a valid authorization request")
+    }
+
+    /// Whether the request was received more than `max_age` before `now`, after which the
+    /// verifier is unlikely to still accept a response.
+    pub fn is_expired(&self, max_age: Duration, now: SystemTime) -> bool {
+        now.duration_since(self.received_at)
+            .is_ok_and(|age| age > max_age)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let request: AuthorizationRequestObject = serde_json::from_value::<UntypedObject>(json!({
+            "client_id": "https://verifier.example.com/response",
+            "client_id_scheme": "redirect_uri",
+            "response_type": "vp_token",
+            "response_mode": "direct_post",
+            "response_uri": "https://verifier.example.com/response",
+            "nonce": "n-0S6_WzA2Mj",
+            "state": "abc",
+            "presentation_definition_uri": "https://verifier.example.com/pd"
+        }))
+        .unwrap()
+        .try_into()
+        .unwrap();
+
+        let pending = PendingPresentation::new(request);
+        let persisted = serde_json::to_string(&pending).unwrap();
+        let restored: PendingPresentation = serde_json::from_str(&persisted).unwrap();
+
+        assert_eq!(restored.id, pending.id);
+        assert_eq!(restored.received_at, pending.received_at);
+        let request = restored.request().unwrap();
+        assert_eq!(request.nonce().as_str(), "n-0S6_WzA2Mj");
+
+        assert!(!restored.is_expired(Duration::from_secs(60), pending.received_at));
+        assert!(restored.is_expired(
+            Duration::from_secs(60),
+            pending.received_at + Duration::from_secs(61)
+        ));
+    }
+}
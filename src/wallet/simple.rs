@@ -0,0 +1,334 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::{Map, Value as Json};
+use ssi::{
+    dids::{AnyDidMethod, VerificationMethodDIDResolver},
+    verification_methods::AnyJwkMethod,
+};
+use tokio::sync::Mutex;
+
+use crate::core::{
+    authorization_request::{
+        verification::{
+            did, did::CachingJwkResolver, unsigned::UnsignedRequestPolicy, RequestVerifier,
+        },
+        AuthorizationRequestObject,
+    },
+    credential_format::ClaimFormatDesignation,
+    metadata::WalletMetadata,
+    object::{registry::ParameterRegistry, UnknownParameters},
+    util::{jwt_payload, AsyncHttpClient, Base64Policy},
+};
+
+use super::{
+    credential_store::{CredentialStore, StoredCredential},
+    Wallet,
+};
+
+/// The claim format designation of SD-JWT VCs.
+pub const SD_JWT_VC_FORMAT: &str = "vc+sd-jwt";
+
+/// Resolves the keys of verifiers using the `did` client_id_scheme.
+pub type DidResolver = VerificationMethodDIDResolver<AnyDidMethod, AnyJwkMethod>;
+
+/// A reference [Wallet] holding JWT VC and SD-JWT VC credentials in memory.
+///
+/// Requests are accepted with the `did` client_id_scheme (resolving any DID method supported by
+/// [AnyDidMethod]), and unsigned requests as allowed by the [UnsignedRequestPolicy]. It is intended
+/// for examples and tests, and as a starting point for wallets with their own storage and key
+/// management.
+pub struct SimpleWallet<H> {
+    metadata: WalletMetadata,
+    http_client: H,
+    resolver: CachingJwkResolver<DidResolver>,
+    trusted_dids: Option<Vec<String>>,
+    unsigned_request_policy: UnsignedRequestPolicy,
+    auto_submit_errors: bool,
+    unknown_parameters: UnknownParameters,
+    parameter_registry: Option<ParameterRegistry>,
+    request_object_decryption_key: Option<Map<String, Json>>,
+    base64_policy: Base64Policy,
+    credentials: Mutex<BTreeMap<String, (StoredCredential, String)>>,
+}
+
+impl<H> SimpleWallet<H> {
+    /// Create a wallet with no credentials, making its requests with `http_client`, e.g. a
+    /// [ReqwestClient](crate::core::util::ReqwestClient) configured with
+    /// [ReqwestClient::builder](crate::core::util::ReqwestClient::builder).
+    pub fn new(metadata: WalletMetadata, http_client: H) -> Self {
+        Self {
+            metadata,
+            http_client,
+            resolver: CachingJwkResolver::new(VerificationMethodDIDResolver::new(
+                AnyDidMethod::default(),
+            )),
+            trusted_dids: None,
+            unsigned_request_policy: UnsignedRequestPolicy::default(),
+            auto_submit_errors: false,
+            unknown_parameters: UnknownParameters::default(),
+            parameter_registry: None,
+            request_object_decryption_key: None,
+            base64_policy: Base64Policy::default(),
+            credentials: Mutex::default(),
+        }
+    }
+
+    /// Only accept requests from these DIDs, all DIDs are trusted by default.
+    pub fn with_trusted_dids(mut self, trusted_dids: Vec<String>) -> Self {
+        self.trusted_dids = Some(trusted_dids);
+        self
+    }
+
+    pub fn with_unsigned_request_policy(mut self, policy: UnsignedRequestPolicy) -> Self {
+        self.unsigned_request_policy = policy;
+        self
+    }
+
+    pub fn with_auto_submit_errors(mut self, auto_submit_errors: bool) -> Self {
+        self.auto_submit_errors = auto_submit_errors;
+        self
+    }
+
+    pub fn with_unknown_parameters(mut self, unknown_parameters: UnknownParameters) -> Self {
+        self.unknown_parameters = unknown_parameters;
+        self
+    }
+
+    /// Parameters of requests defined outside of this library, see
+    /// [Wallet::parameter_registry].
+    pub fn with_parameter_registry(mut self, registry: ParameterRegistry) -> Self {
+        self.parameter_registry = Some(registry);
+        self
+    }
+
+    /// The private JWK that Request Objects are encrypted to, whose public key must be in the
+    /// `jwks` of the metadata.
+    pub fn with_request_object_decryption_key(mut self, jwk: Map<String, Json>) -> Self {
+        self.request_object_decryption_key = Some(jwk);
+        self
+    }
+
+    /// How credentials and presentations are decoded, see [Wallet::base64_policy].
+    pub fn with_base64_policy(mut self, policy: Base64Policy) -> Self {
+        self.base64_policy = policy;
+        self
+    }
+
+    /// Add a JWT VC (`jwt_vc_json`), matched against requests using the claims of its payload.
+    pub async fn add_jwt_vc(&self, id: impl Into<String>, jwt: impl Into<String>) -> Result<()> {
+        let jwt = jwt.into();
+        let claims = decode_jwt_payload(&jwt, self.base64_policy)?;
+        let types = claims
+            .get("vc")
+            .and_then(|vc| vc.get("type"))
+            .map(credential_types)
+            .unwrap_or_default();
+        let credential =
+            StoredCredential::new(id, ClaimFormatDesignation::JwtVcJson, Json::Object(claims))
+                .with_types(types);
+        self.insert(credential, jwt).await;
+        Ok(())
+    }
+
+    /// Add an SD-JWT VC, matched against requests using the claims of its payload together with
+    /// all of its disclosed claims.
+    pub async fn add_sd_jwt(&self, id: impl Into<String>, sd_jwt: impl Into<String>) -> Result<()> {
+        let sd_jwt = sd_jwt.into();
+        let mut parts = sd_jwt.split('~');
+        let mut claims = decode_jwt_payload(parts.next().unwrap_or_default(), self.base64_policy)?;
+        for disclosure in parts.filter(|part| !part.is_empty()) {
+            let disclosure: Vec<Json> = serde_json::from_slice(
+                &self
+                    .base64_policy
+                    .decode(disclosure)
+                    .context("disclosure was not valid base64url")?,
+            )
+            .context("disclosure was not a JSON array")?;
+            // Array element disclosures ([salt, value]) carry no claim name.
+            if let [_, Json::String(name), value] = disclosure.as_slice() {
+                claims.insert(name.clone(), value.clone());
+            }
+        }
+        let types = claims.get("vct").map(credential_types).unwrap_or_default();
+        let credential = StoredCredential::new(
+            id,
+            ClaimFormatDesignation::Other(SD_JWT_VC_FORMAT.into()),
+            Json::Object(claims),
+        )
+        .with_types(types);
+        self.insert(credential, sd_jwt).await;
+        Ok(())
+    }
+
+    /// Remove a credential, returning whether it was present.
+    pub async fn remove(&self, id: &str) -> bool {
+        self.credentials.lock().await.remove(id).is_some()
+    }
+
+    /// Return the encoded form of a credential, to build a presentation from.
+    pub async fn encoded(&self, id: &str) -> Option<String> {
+        self.credentials
+            .lock()
+            .await
+            .get(id)
+            .map(|(_, encoded)| encoded.clone())
+    }
+
+    async fn insert(&self, credential: StoredCredential, encoded: String) {
+        self.credentials
+            .lock()
+            .await
+            .insert(credential.id().to_owned(), (credential, encoded));
+    }
+}
+
+fn decode_jwt_payload(jwt: &str, base64: Base64Policy) -> Result<Map<String, Json>> {
+    jwt_payload(jwt, base64).context("failed to decode the credential")
+}
+
+fn credential_types(value: &Json) -> Vec<String> {
+    match value {
+        Json::String(t) => vec![t.clone()],
+        Json::Array(ts) => ts
+            .iter()
+            .filter_map(Json::as_str)
+            .map(ToOwned::to_owned)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+#[async_trait]
+impl<H: Send + Sync> CredentialStore for SimpleWallet<H> {
+    async fn list(&self) -> Vec<StoredCredential> {
+        self.credentials
+            .lock()
+            .await
+            .values()
+            .map(|(credential, _)| credential.clone())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<H: AsyncHttpClient + Send + Sync> Wallet for SimpleWallet<H> {
+    type HttpClient = H;
+
+    fn metadata(&self) -> &WalletMetadata {
+        &self.metadata
+    }
+
+    fn http_client(&self) -> &Self::HttpClient {
+        &self.http_client
+    }
+
+    fn request_object_decryption_key(&self) -> Option<&Map<String, Json>> {
+        self.request_object_decryption_key.as_ref()
+    }
+
+    fn unknown_parameters(&self) -> UnknownParameters {
+        self.unknown_parameters
+    }
+
+    fn parameter_registry(&self) -> Option<&ParameterRegistry> {
+        self.parameter_registry.as_ref()
+    }
+
+    fn base64_policy(&self) -> Base64Policy {
+        self.base64_policy
+    }
+
+    fn unsigned_request_policy(&self) -> UnsignedRequestPolicy {
+        self.unsigned_request_policy.clone()
+    }
+
+    fn auto_submit_errors(&self) -> bool {
+        self.auto_submit_errors
+    }
+}
+
+#[async_trait]
+impl<H: Send + Sync> RequestVerifier for SimpleWallet<H> {
+    async fn did(
+        &self,
+        decoded_request: &AuthorizationRequestObject,
+        request_jwt: String,
+    ) -> Result<()> {
+        did::verify_with_resolver(
+            &self.metadata,
+            decoded_request,
+            request_jwt,
+            self.trusted_dids.as_deref(),
+            &self.resolver,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use base64::prelude::*;
+    use serde_json::json;
+
+    use crate::core::util::ReqwestClient;
+
+    use super::*;
+
+    fn encode(value: Json) -> String {
+        BASE64_URL_SAFE_NO_PAD.encode(value.to_string())
+    }
+
+    #[tokio::test]
+    async fn stores_credentials() {
+        let wallet = SimpleWallet::new(
+            WalletMetadata::openid4vp_scheme_static(),
+            ReqwestClient::new().unwrap(),
+        );
+
+        let jwt_vc = format!(
+            "{}.{}.sig",
+            encode(json!({"alg": "ES256"})),
+            encode(json!({"vc": {"type": ["VerifiableCredential", "Example"]}}))
+        );
+        wallet.add_jwt_vc("jwt", jwt_vc.clone()).await.unwrap();
+
+        let sd_jwt = format!(
+            "{}.{}.sig~{}~",
+            encode(json!({"alg": "ES256"})),
+            encode(json!({"vct": "Example", "_sd": ["..."]})),
+            encode(json!(["salt", "given_name", "Alice"]))
+        );
+        wallet.add_sd_jwt("sd-jwt", sd_jwt).await.unwrap();
+
+        let credentials = wallet.list().await;
+        assert_eq!(credentials.len(), 2);
+        let sd_jwt = credentials.iter().find(|c| c.id() == "sd-jwt").unwrap();
+        assert_eq!(sd_jwt.claims()["given_name"], "Alice");
+        assert_eq!(sd_jwt.types(), ["Example"]);
+        let jwt = credentials.iter().find(|c| c.id() == "jwt").unwrap();
+        assert_eq!(jwt.types(), ["VerifiableCredential", "Example"]);
+
+        assert_eq!(wallet.encoded("jwt").await, Some(jwt_vc));
+        assert!(wallet.remove("jwt").await);
+        assert_eq!(wallet.list().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn padded_credentials() {
+        let payload = BASE64_URL_SAFE.encode(json!({"vc": {"type": "Example"}}).to_string());
+        assert!(payload.ends_with('='));
+        let jwt_vc = format!("{}.{payload}.sig", encode(json!({"alg": "ES256"})));
+
+        let wallet = SimpleWallet::new(
+            WalletMetadata::openid4vp_scheme_static(),
+            ReqwestClient::new().unwrap(),
+        );
+        wallet.add_jwt_vc("jwt", jwt_vc.clone()).await.unwrap();
+
+        let wallet = wallet.with_base64_policy(Base64Policy::Strict);
+        assert_eq!(wallet.base64_policy(), Base64Policy::Strict);
+        assert!(wallet.add_jwt_vc("strict", jwt_vc).await.is_err());
+    }
+}
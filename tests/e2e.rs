@@ -1,18 +1,86 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use base64::prelude::*;
 use jwt_vp::create_test_verifiable_presentation;
 use openid4vp::{
+    conformance::{self, CheckStatus},
     core::{
-        authorization_request::parameters::{ClientMetadata, Nonce, ResponseMode, ResponseType},
+        authorization_request::{
+            dc_api::DcApiRequest,
+            parameters::{
+                ClientIdScheme, ClientMetadata, ExpectedOrigins, Nonce, PresentationDefinitionUri,
+                RequestUriMethod, ResponseMode, ResponseType, ResponseUri, State, TransactionData,
+            },
+            AuthorizationRequest, AuthorizationRequestObject, RequestIndirection,
+        },
         credential_format::*,
+        dcql_query::{DcqlClaimsQuery, DcqlCredentialQuery, DcqlQuery, DcqlVpToken},
+        draft::Draft,
+        events::{EventSubscriber, LifecycleEvent, LifecycleEventKind, Party},
         input_descriptor::*,
-        object::UntypedObject,
+        jwe::{
+            self,
+            ecdh_es::{self, KeyAgreementCurve},
+        },
+        metadata::{
+            parameters::{
+                verifier::JWKs,
+                wallet::{AuthorizationEndpoints, ResponseModesSupported, ResponseTypesSupported},
+            },
+            signed::MetadataTrustAnchors,
+            WalletMetadata,
+        },
+        object::{
+            registry::{ParameterLocation, ParameterRegistry},
+            TypedParameter, UnknownParameters, UntypedObject,
+        },
         presentation_definition::*,
         presentation_submission::*,
-        response::{AuthorizationResponse, UnencodedAuthorizationResponse},
+        response::{
+            error::{
+                AuthorizationErrorCode, ClientIdSchemeNotSupported,
+                PresentationDefinitionUriNotSupported, ResponseModeNotSupported,
+                ResponseTypeNotSupported,
+            },
+            jarm::JarmValidator,
+            parameters::{VpToken, VpTokenItem},
+            parser::VpTokenParser,
+            AuthorizationResponse, DcqlAuthorizationResponse, JwtAuthorizationResponse,
+            UnencodedAuthorizationResponse,
+        },
+        util::{AsyncHttpClient, Base64Policy},
+    },
+    test_utils::{
+        simulation::{Fault, Responder, Simulation},
+        MockWallet,
+    },
+    verifier::{
+        archive::{MemoryArchive, PresentationArchive},
+        audit::{ArtifactRedaction, AuditRecord, AuditRedaction, AuditSink},
+        guard::{
+            Endpoint, GuardDecision, RemoteMetadata, RequestContext, RequestGuard, RequestRejected,
+        },
+        notifier::ResponseNotifier,
+        outcome::VerifiedPresentationOutcome,
+        policy::{PolicyHook, TrustPolicy},
+        report::{FindingCode, VerificationReport},
+        session::{
+            DuplicateResponse, MemoryStore, Outcome, Session, SessionState, SessionStore, Status,
+        },
+        stateless::{MemorySeenTokenStore, StateKey},
+        template::{RequestTemplate, SessionOverrides},
+        tenant::Tenant,
+        validator::PresentationVerifier,
+        Verifier,
     },
-    verifier::session::{Outcome, Status},
-    wallet::Wallet,
+    wallet::{presentation::key_binding_claims, Wallet},
 };
-use ssi::jwk::Algorithm;
+use rand::rngs::OsRng;
+use ssi::jwk::{Algorithm, JWK};
+use uuid::Uuid;
 
 mod jwt_vc;
 mod jwt_vp;
@@ -46,14 +114,14 @@ async fn w3c_vc_did_client_direct_post() {
         )
         .set_name("DID Key Identity Verification".into())
         .set_purpose("Check whether your identity key has been verified.".into())
-        .set_format((|| {
+        .set_format({
             let mut map = ClaimFormatMap::new();
             map.insert(
                 ClaimFormatDesignation::JwtVcJson,
                 ClaimFormatPayload::Alg(vec![Algorithm::ES256.to_string()]),
             );
             map
-        })()),
+        }),
     );
 
     let client_metadata = UntypedObject::default();
@@ -135,7 +203,7 @@ async fn w3c_vc_did_client_direct_post() {
     let response = AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
         Default::default(),
         vp.into(),
-        presentation_submission.try_into().unwrap(),
+        presentation_submission,
     ));
 
     let status = verifier.poll_status(id).await.unwrap();
@@ -149,3 +217,2512 @@ async fn w3c_vc_did_client_direct_post() {
 
     assert!(matches!(status, Status::Complete(Outcome::Success { .. })))
 }
+
+#[tokio::test]
+async fn verifier_begin_session() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier().await;
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    assert_eq!(
+        verifier.session_state(id).await.unwrap(),
+        SessionState::RequestRetrieved
+    );
+
+    let request = wallet.validate_request(url).await.unwrap();
+    assert!(!request.nonce().is_empty());
+
+    let presentation_definition = request
+        .resolve_presentation_definition(wallet.http_client())
+        .await
+        .unwrap()
+        .into_parsed();
+
+    let presentation_submission = PresentationSubmission::for_vp_token(
+        presentation_definition.id().clone(),
+        [("did-key-id", ClaimFormatDesignation::JwtVpJson)],
+    );
+
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+
+    let response = AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+        Default::default(),
+        vp.into(),
+        presentation_submission,
+    ));
+
+    wallet
+        .submit_response(request.clone(), response.clone())
+        .await
+        .unwrap();
+
+    let status = verifier.poll_status(id).await.unwrap();
+    assert!(matches!(status, Status::Complete(Outcome::Success { .. })));
+
+    let state = verifier.session_state(id).await.unwrap();
+    assert!(state.is_final());
+    assert!(matches!(state, SessionState::Verified { .. }));
+
+    let verified = verifier.verified_outcome(id).await.unwrap();
+    assert!(verified.credentials.is_empty());
+
+    // A retry of the same submission is not verified again, and gets the original outcome.
+    wallet.submit_response(request, response).await.unwrap();
+    assert!(matches!(
+        verifier.poll_status(id).await.unwrap(),
+        Status::Complete(Outcome::Success { .. })
+    ));
+}
+
+#[tokio::test]
+async fn verifier_scope_session() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier().await;
+
+    assert!(verifier.begin_scope_session("unknown").await.is_err());
+
+    let (url, id) = verifier
+        .begin_scope_session("com.example.did_key_id")
+        .await
+        .unwrap();
+
+    let request = wallet.validate_request(url).await.unwrap();
+    let scope = request.presentation_definition_scope().unwrap();
+    assert_eq!(scope.0, ["com.example.did_key_id"]);
+    assert!(request
+        .resolve_presentation_definition(wallet.http_client())
+        .await
+        .is_err());
+
+    let presentation_definition = wallet.resolve_scope(scope).await.unwrap();
+    assert_eq!(presentation_definition.id(), "did-key-id-scope");
+
+    let presentation_submission = PresentationSubmission::for_vp_token(
+        presentation_definition.id().clone(),
+        [("did-key-id", ClaimFormatDesignation::JwtVpJson)],
+    );
+
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+
+    wallet
+        .submit_response(
+            request,
+            AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+                Default::default(),
+                vp.into(),
+                presentation_submission,
+            )),
+        )
+        .await
+        .unwrap();
+
+    let status = verifier.poll_status(id).await.unwrap();
+    assert!(matches!(status, Status::Complete(Outcome::Success { .. })));
+}
+
+#[tokio::test]
+async fn verifier_dcql_session() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier().await;
+
+    let dcql_query = DcqlQuery::new(vec![DcqlCredentialQuery::new(
+        "did-key-id".into(),
+        "jwt_vc_json".into(),
+    )
+    .with_claims(vec![DcqlClaimsQuery::new(vec![
+        "credentialSubject".into(),
+        "id".into(),
+    ])])]);
+
+    assert!(verifier
+        .build_authorization_request()
+        .with_dcql_query(DcqlQuery::new(vec![]))
+        .with_request_parameter(ResponseMode::DirectPost)
+        .with_request_parameter(ResponseType::VpToken)
+        .build(wallet.metadata().clone())
+        .await
+        .is_err());
+
+    for (credential_id, success) in [("did-key-id", true), ("other", false)] {
+        let (id, url) = verifier
+            .build_authorization_request()
+            .with_dcql_query(dcql_query.clone())
+            .with_request_parameter(ResponseMode::DirectPost)
+            .with_request_parameter(ResponseType::VpToken)
+            .build(wallet.metadata().clone())
+            .await
+            .unwrap();
+
+        let request = wallet.validate_request(url).await.unwrap();
+        assert_eq!(request.dcql_query(), Some(&dcql_query));
+        assert!(request
+            .resolve_presentation_definition(wallet.http_client())
+            .await
+            .is_err());
+
+        let vp = create_test_verifiable_presentation()
+            .await
+            .expect("failed to create verifiable presentation");
+
+        let _ = wallet
+            .submit_response(
+                request,
+                AuthorizationResponse::Dcql(DcqlAuthorizationResponse(
+                    Default::default(),
+                    DcqlVpToken([(credential_id.to_owned(), vec![vp.into()])].into()),
+                )),
+            )
+            .await;
+
+        let status = verifier.poll_status(id).await.unwrap();
+        if success {
+            assert!(matches!(status, Status::Complete(Outcome::Success { .. })));
+        } else {
+            assert!(matches!(status, Status::Complete(Outcome::Failure { .. })));
+        }
+    }
+}
+
+#[tokio::test]
+async fn verifier_encrypted_response() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier().await;
+
+    for encrypted in [true, false] {
+        let (id, url) = verifier
+            .build_authorization_request()
+            .with_presentation_definition(jwt_vc::presentation_definition())
+            .with_request_parameter(ResponseMode::DirectPostJwt)
+            .with_request_parameter(ResponseType::VpToken)
+            .build(wallet.metadata().clone())
+            .await
+            .unwrap();
+
+        let request = wallet.validate_request(url).await.unwrap();
+        let client_metadata = request.get::<ClientMetadata>().unwrap().unwrap();
+        let jwks = client_metadata.0.get::<JWKs>().unwrap().unwrap();
+        assert_eq!(jwks.keys.len(), 1);
+        assert_eq!(jwks.keys[0]["use"], "enc");
+        assert_eq!(jwks.keys[0]["alg"], "ECDH-ES");
+        assert!(!jwks.keys[0].contains_key("d"));
+
+        let response = UnencodedAuthorizationResponse(
+            Default::default(),
+            create_test_verifiable_presentation()
+                .await
+                .expect("failed to create verifiable presentation")
+                .into(),
+            jwt_vc::presentation_submission(),
+        );
+
+        if encrypted {
+            wallet
+                .submit_response(request, AuthorizationResponse::Unencoded(response))
+                .await
+                .unwrap();
+            let status = verifier.poll_status(id).await.unwrap();
+            assert!(matches!(status, Status::Complete(Outcome::Success { .. })));
+        } else {
+            // A response that was not encrypted to the session key is rejected.
+            let body = response.into_x_www_form_urlencoded().unwrap();
+            assert!(verifier
+                .receive_response(id, body.as_bytes())
+                .await
+                .is_err());
+            let Status::Complete(Outcome::Failure { reason }) =
+                verifier.poll_status(id).await.unwrap()
+            else {
+                panic!("the session should have failed")
+            };
+            assert!(reason.contains("not encrypted"), "{reason}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn verifier_jarm_claims() {
+    for validate in [false, true] {
+        let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, client| {
+            if validate {
+                builder.with_jarm_validator(JarmValidator::new(&client.id().0))
+            } else {
+                builder
+            }
+        })
+        .await;
+
+        for bare in [false, true] {
+            let (id, url) = verifier
+                .build_authorization_request()
+                .with_presentation_definition(jwt_vc::presentation_definition())
+                .with_request_parameter(ResponseMode::DirectPostJwt)
+                .build(wallet.metadata().clone())
+                .await
+                .unwrap();
+            let request = wallet.validate_request(url).await.unwrap();
+
+            let response = UnencodedAuthorizationResponse(
+                Default::default(),
+                create_test_verifiable_presentation()
+                    .await
+                    .expect("failed to create verifiable presentation")
+                    .into(),
+                jwt_vc::presentation_submission(),
+            );
+            let response = if bare {
+                // Encrypt the bare response parameters, without `iss`, `aud` and `exp`.
+                let encryption = wallet.response_encryption(&request).await.unwrap().unwrap();
+                let payload = serde_json::to_vec(&response.into_untyped()).unwrap();
+                AuthorizationResponse::Jwt(JwtAuthorizationResponse {
+                    response: jwe::compact::encrypt(&encryption, &payload, &[], &[], &mut OsRng)
+                        .unwrap(),
+                })
+            } else {
+                wallet.encode_response(&request, response).await.unwrap()
+            };
+
+            let result = verifier
+                .verify_response(id, response, jwt_vc::succeed)
+                .await;
+            let status = verifier.poll_status(id).await.unwrap();
+            if validate && bare {
+                assert!(result.is_err());
+                let Status::Complete(Outcome::Failure { reason }) = status else {
+                    panic!("the session should have failed")
+                };
+                assert!(reason.contains("'iss'"), "{reason}");
+            } else {
+                result.unwrap();
+                assert!(matches!(status, Status::Complete(Outcome::Success { .. })));
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn verifier_mdoc_generated_nonce() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier().await;
+
+    for tampered in [false, true] {
+        let (id, url) = verifier
+            .build_authorization_request()
+            .with_presentation_definition(PresentationDefinition::new(
+                "mdl-proof".into(),
+                InputDescriptor::new(
+                    "mdl".into(),
+                    Constraints::new().add_constraint(ConstraintsField::new(
+                        "$['org.iso.18013.5.1']['family_name']".into(),
+                    )),
+                ),
+            ))
+            .with_request_parameter(ResponseMode::DirectPostJwt)
+            .build(wallet.metadata().clone())
+            .await
+            .unwrap();
+        let request = wallet.validate_request(url).await.unwrap();
+
+        let response = UnencodedAuthorizationResponse(
+            Default::default(),
+            VpTokenItem::String("o2d2ZXJzaW9uYzEuMA".into()).into(),
+            PresentationSubmission::for_vp_token(
+                "mdl-proof".into(),
+                [("mdl", ClaimFormatDesignation::MsoMDoc)],
+            ),
+        );
+        let AuthorizationResponse::Jwt(mut response) =
+            wallet.encode_response(&request, response).await.unwrap()
+        else {
+            panic!("the response should have been encrypted")
+        };
+
+        // The wallet binds a new mdocGeneratedNonce (`apu`) and the nonce of the request (`apv`).
+        let mdoc_generated_nonce = response
+            .mdoc_generated_nonce()
+            .unwrap()
+            .expect("the response should carry an mdocGeneratedNonce");
+        let mut header = response.protected_header().unwrap();
+        let expected = mdoc_generated_nonce.jwe_header_parameters(request.nonce());
+        assert_eq!(header["apu"], expected["apu"]);
+        assert_eq!(header["apv"], expected["apv"]);
+
+        if tampered {
+            header.insert(
+                "apv".into(),
+                BASE64_URL_SAFE_NO_PAD.encode("another-nonce").into(),
+            );
+            let (_, rest) = response.response.split_once('.').unwrap();
+            response.response = format!(
+                "{}.{rest}",
+                BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header).unwrap())
+            );
+        }
+
+        let received = Arc::new(Mutex::new(None));
+        let result = verifier
+            .verify_response(id, AuthorizationResponse::Jwt(response), {
+                let received = received.clone();
+                move |session, _| {
+                    *received.lock().unwrap() = session.mdoc_generated_nonce;
+                    Box::pin(async {
+                        Outcome::Success {
+                            info: serde_json::Value::Null,
+                        }
+                    })
+                }
+            })
+            .await;
+
+        if tampered {
+            assert!(result.is_err());
+            let Status::Complete(Outcome::Failure { reason }) =
+                verifier.poll_status(id).await.unwrap()
+            else {
+                panic!("the session should have failed")
+            };
+            assert!(reason.contains("'apv'"), "{reason}");
+        } else {
+            result.unwrap();
+            // The verifier passes the mdocGeneratedNonce on for the mdoc session transcript.
+            assert_eq!(*received.lock().unwrap(), Some(mdoc_generated_nonce));
+        }
+    }
+}
+
+#[tokio::test]
+async fn verifier_transaction_data() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier().await;
+    let jwt = |claims: serde_json::Value| {
+        format!(
+            "{}.{}.signature",
+            BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256"}"#),
+            BASE64_URL_SAFE_NO_PAD.encode(claims.to_string())
+        )
+    };
+
+    for tampered in [false, true] {
+        let transaction_data = serde_json::json!({
+            "type": "payment_data",
+            "credential_ids": ["did-key-id"],
+            "payload": { "amount": "23.58", "currency": "EUR" }
+        });
+        let (id, url) = verifier
+            .build_authorization_request()
+            .with_presentation_definition(jwt_vc::presentation_definition())
+            .with_request_parameter(TransactionData(vec![
+                BASE64_URL_SAFE_NO_PAD.encode(transaction_data.to_string())
+            ]))
+            .build(wallet.metadata().clone())
+            .await
+            .unwrap();
+        let request = wallet.validate_request(url).await.unwrap();
+
+        // The wallet binds the transaction data into the Key Binding JWT.
+        let mut claims = key_binding_claims(&request).unwrap();
+        if tampered {
+            claims.insert(
+                "transaction_data_hashes".into(),
+                serde_json::json!([BASE64_URL_SAFE_NO_PAD.encode("another transaction")]),
+            );
+        }
+        let sd_jwt = format!(
+            "{}~{}",
+            jwt(serde_json::json!({ "_sd": [] })),
+            jwt(claims.into())
+        );
+        let response = UnencodedAuthorizationResponse(
+            Default::default(),
+            VpTokenItem::String(sd_jwt).into(),
+            PresentationSubmission::for_vp_token(
+                "did-key-id-proof".into(),
+                [("did-key-id", ClaimFormatDesignation::from("vc+sd-jwt"))],
+            ),
+        );
+
+        let result = verifier
+            .verify_response(
+                id,
+                AuthorizationResponse::Unencoded(response),
+                jwt_vc::succeed,
+            )
+            .await;
+        let status = verifier.poll_status(id).await.unwrap();
+        if tampered {
+            assert!(result.is_err());
+            let Status::Complete(Outcome::Failure { reason }) = status else {
+                panic!("the session should have failed")
+            };
+            assert!(reason.contains("transaction_data_hashes"), "{reason}");
+        } else {
+            result.unwrap();
+            assert!(matches!(status, Status::Complete(Outcome::Success { .. })));
+        }
+    }
+}
+
+#[tokio::test]
+async fn verifier_presentation_verifier() {
+    /// Only lets a verification complete once two of them are in flight.
+    #[derive(Debug)]
+    struct BarrierVerifier(tokio::sync::Barrier);
+
+    #[async_trait::async_trait]
+    impl PresentationVerifier for BarrierVerifier {
+        async fn verify(
+            &self,
+            _: &Session,
+            _: usize,
+            presentation: &VpTokenItem,
+        ) -> anyhow::Result<()> {
+            self.0.wait().await;
+            if presentation == &VpTokenItem::String("invalid".into()) {
+                anyhow::bail!("the presentation is invalid")
+            }
+            Ok(())
+        }
+    }
+
+    let format = ClaimFormatDesignation::from("com.example.opaque");
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder
+            .with_presentation_verifier(Arc::new(BarrierVerifier(tokio::sync::Barrier::new(2))))
+            .with_verification_concurrency(2)
+    })
+    .await;
+    let dcql_query = DcqlQuery::new(vec![
+        DcqlCredentialQuery::new("first".into(), "com.example.opaque".into()),
+        DcqlCredentialQuery::new("second".into(), "com.example.opaque".into()),
+    ]);
+
+    for (second, valid) in [("valid", true), ("invalid", false)] {
+        let pex = {
+            let (url, id) = verifier.begin_session().await.unwrap();
+            wallet.validate_request(url).await.unwrap();
+            let response = UnencodedAuthorizationResponse(
+                Default::default(),
+                VpToken(vec![
+                    VpTokenItem::String("valid".into()),
+                    VpTokenItem::String(second.into()),
+                ]),
+                PresentationSubmission::for_vp_token(
+                    "did-key-id-proof".into(),
+                    [
+                        ("did-key-id", format.clone()),
+                        ("did-key-id", format.clone()),
+                    ],
+                ),
+            );
+            (id, AuthorizationResponse::Unencoded(response))
+        };
+        let dcql = {
+            let (id, url) = verifier
+                .build_authorization_request()
+                .with_dcql_query(dcql_query.clone())
+                .with_request_parameter(ResponseMode::DirectPost)
+                .with_request_parameter(ResponseType::VpToken)
+                .build(wallet.metadata().clone())
+                .await
+                .unwrap();
+            wallet.validate_request(url).await.unwrap();
+            let response = DcqlAuthorizationResponse(
+                Default::default(),
+                DcqlVpToken(
+                    [
+                        ("first".to_owned(), vec!["valid".to_owned().into()]),
+                        ("second".to_owned(), vec![second.to_owned().into()]),
+                    ]
+                    .into(),
+                ),
+            );
+            (id, AuthorizationResponse::Dcql(response))
+        };
+
+        for (id, response) in [pex, dcql] {
+            let verification = verifier.verify_response(id, response, jwt_vc::succeed);
+            // Sequential verification would wait at the barrier forever.
+            let timeout = futures_timer::Delay::new(Duration::from_secs(5));
+            let result = match futures::future::select(Box::pin(verification), timeout).await {
+                futures::future::Either::Left((result, _)) => result,
+                futures::future::Either::Right(_) => {
+                    panic!("the presentations were not verified concurrently")
+                }
+            };
+
+            let status = verifier.poll_status(id).await.unwrap();
+            if valid {
+                result.unwrap();
+                assert!(matches!(status, Status::Complete(Outcome::Success { .. })));
+            } else {
+                assert!(result.is_err());
+                let Status::Complete(Outcome::Failure { reason }) = status else {
+                    panic!("the session should have failed")
+                };
+                assert!(
+                    reason.contains("presentation 1 failed verification"),
+                    "{reason}"
+                );
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn verifier_base64_policy() {
+    let payload = BASE64_URL_SAFE.encode(r#"{"vp":{"holder":"did:example:holder"}}"#);
+    assert!(payload.ends_with('='));
+    let padded = format!("e30.{payload}.signature");
+
+    for policy in [Base64Policy::Lenient, Base64Policy::Strict] {
+        let (wallet, verifier) =
+            jwt_vc::wallet_verifier_with(|builder, _| builder.with_base64_policy(policy)).await;
+        assert_eq!(verifier.base64_policy(), policy);
+
+        let (url, id) = verifier.begin_session().await.unwrap();
+        wallet.validate_request(url).await.unwrap();
+        let response = UnencodedAuthorizationResponse(
+            Default::default(),
+            VpTokenItem::String(padded.clone()).into(),
+            jwt_vc::presentation_submission(),
+        );
+
+        let result = verifier
+            .verify_response(
+                id,
+                AuthorizationResponse::Unencoded(response),
+                jwt_vc::succeed,
+            )
+            .await;
+        let status = verifier.poll_status(id).await.unwrap();
+        match policy {
+            Base64Policy::Lenient => {
+                result.unwrap();
+                assert!(matches!(status, Status::Complete(Outcome::Success { .. })));
+            }
+            Base64Policy::Strict => {
+                assert!(result.is_err());
+                let Status::Complete(Outcome::Failure { reason }) = status else {
+                    panic!("the session should have failed")
+                };
+                assert!(reason.contains("base64url"), "{reason}");
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn verifier_vp_token_parser() {
+    /// Parses presentations of the form `prefixed:<value>`.
+    #[derive(Debug)]
+    struct PrefixedParser;
+
+    impl VpTokenParser for PrefixedParser {
+        fn parse(&self, item: &VpTokenItem) -> anyhow::Result<serde_json::Value> {
+            match item {
+                VpTokenItem::String(s) => match s.strip_prefix("prefixed:") {
+                    Some(value) => Ok(serde_json::json!({ "value": value })),
+                    None => anyhow::bail!("the presentation is not prefixed"),
+                },
+                VpTokenItem::JsonObject(_) => anyhow::bail!("expected a string"),
+            }
+        }
+    }
+
+    let format = ClaimFormatDesignation::from("com.example.prefixed");
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder.with_vp_token_parser(format.clone(), PrefixedParser)
+    })
+    .await;
+    assert!(verifier.vp_token_parsers().get(&format).is_some());
+
+    for (presentation, resolves) in [("prefixed:value", true), ("value", false)] {
+        let (url, id) = verifier.begin_session().await.unwrap();
+        wallet.validate_request(url).await.unwrap();
+        let response = UnencodedAuthorizationResponse(
+            Default::default(),
+            VpTokenItem::String(presentation.into()).into(),
+            PresentationSubmission::for_vp_token(
+                "did-key-id-proof".into(),
+                [("did-key-id", format.clone())],
+            ),
+        );
+
+        let result = verifier
+            .verify_response(
+                id,
+                AuthorizationResponse::Unencoded(response),
+                jwt_vc::succeed,
+            )
+            .await;
+        let status = verifier.poll_status(id).await.unwrap();
+        if resolves {
+            result.unwrap();
+            assert!(matches!(status, Status::Complete(Outcome::Success { .. })));
+        } else {
+            assert!(result.is_err());
+            let Status::Complete(Outcome::Failure { reason }) = status else {
+                panic!("the session should have failed")
+            };
+            assert!(reason.contains("not prefixed"), "{reason}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn verifier_response_code() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder.with_response_redirect_uri("https://example.com/done".parse().unwrap())
+    })
+    .await;
+
+    // Run a session, returning its id and the response code the wallet was redirected with.
+    let session = || async {
+        let (url, id) = verifier.begin_session().await.unwrap();
+        assert!(verifier.post_redirection(id).await.is_err());
+
+        let request = wallet.validate_request(url).await.unwrap();
+        let vp = create_test_verifiable_presentation()
+            .await
+            .expect("failed to create verifiable presentation");
+        let redirect = wallet
+            .submit_response(
+                request,
+                AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+                    Default::default(),
+                    vp.into(),
+                    jwt_vc::presentation_submission(),
+                )),
+            )
+            .await
+            .unwrap()
+            .expect("the verifier should redirect the wallet");
+
+        assert_eq!(redirect.path(), "/done");
+        let (_, response_code) = redirect
+            .query_pairs()
+            .find(|(name, _)| name == "response_code")
+            .unwrap();
+        (id, response_code.into_owned())
+    };
+
+    let (id, response_code) = session().await;
+    assert!(verifier
+        .exchange_response_code(id, "invalid")
+        .await
+        .is_err());
+    // The code was consumed by the failed exchange.
+    assert!(verifier
+        .exchange_response_code(id, &response_code)
+        .await
+        .is_err());
+
+    let (id, response_code) = session().await;
+    let outcome = verifier
+        .exchange_response_code(id, &response_code)
+        .await
+        .unwrap();
+    assert!(matches!(outcome, Outcome::Success { .. }));
+    assert!(verifier
+        .exchange_response_code(id, &response_code)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn verifier_single_use_request_uri() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder.by_reference("http://example.com/request".parse().unwrap())
+    })
+    .await;
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    assert_eq!(
+        verifier.session_state(id).await.unwrap(),
+        SessionState::Created
+    );
+
+    let request = wallet.validate_request(url.clone()).await.unwrap();
+    assert_eq!(
+        verifier.session_state(id).await.unwrap(),
+        SessionState::RequestRetrieved
+    );
+
+    // The request_uri cannot be retrieved again, e.g. from a photographed QR code.
+    assert!(wallet.validate_request(url).await.is_err());
+
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    wallet
+        .submit_response(
+            request,
+            AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+                Default::default(),
+                vp.into(),
+                jwt_vc::presentation_submission(),
+            )),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        verifier.session_state(id).await.unwrap(),
+        SessionState::Verified { .. }
+    ));
+
+    // Expired request URIs are rejected.
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder
+            .by_reference("http://example.com/request".parse().unwrap())
+            .with_request_uri_ttl(Duration::ZERO)
+    })
+    .await;
+    let (url, _) = verifier.begin_session().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(wallet.validate_request(url).await.is_err());
+}
+
+#[derive(Debug, Default)]
+struct RecordingNotifier(Mutex<Vec<(Uuid, Outcome)>>);
+
+#[async_trait::async_trait]
+impl ResponseNotifier for RecordingNotifier {
+    async fn notify(&self, session: &Session, outcome: &Outcome) -> anyhow::Result<()> {
+        self.0.lock().unwrap().push((session.uuid, outcome.clone()));
+        anyhow::bail!("the webhook is unavailable")
+    }
+}
+
+#[tokio::test]
+async fn verifier_response_notifier() {
+    let notifier = Arc::new(RecordingNotifier::default());
+    let (wallet, verifier) =
+        jwt_vc::wallet_verifier_with(|builder, _| builder.with_response_notifier(notifier.clone()))
+            .await;
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    let request = wallet.validate_request(url).await.unwrap();
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    wallet
+        .submit_response(
+            request,
+            AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+                Default::default(),
+                vp.into(),
+                jwt_vc::presentation_submission(),
+            )),
+        )
+        .await
+        .unwrap();
+
+    // A rejected response is notified too.
+    let (url, rejected) = verifier.begin_session().await.unwrap();
+    let request = wallet.validate_request(url).await.unwrap();
+    assert!(wallet
+        .submit_response(
+            request,
+            AuthorizationResponse::Dcql(DcqlAuthorizationResponse(
+                Default::default(),
+                DcqlVpToken([("did-key-id".to_owned(), vec!["vp".to_owned().into()])].into()),
+            )),
+        )
+        .await
+        .is_err());
+
+    // A failed notification does not affect the outcome.
+    assert!(matches!(
+        verifier.poll_status(id).await.unwrap(),
+        Status::Complete(Outcome::Success { .. })
+    ));
+
+    let notified = notifier.0.lock().unwrap();
+    assert_eq!(notified.len(), 2);
+    assert_eq!(notified[0].0, id);
+    assert!(matches!(notified[0].1, Outcome::Success { .. }));
+    assert_eq!(notified[1].0, rejected);
+    assert!(matches!(notified[1].1, Outcome::Failure { .. }));
+}
+
+#[tokio::test]
+async fn verifier_presentation_archive() {
+    let archive = Arc::new(MemoryArchive::default());
+    let retention = Duration::from_secs(3600);
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder
+            .with_presentation_archive(archive.clone())
+            .with_archive_retention(retention)
+    })
+    .await;
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    let request = wallet.validate_request(url).await.unwrap();
+    let nonce = request.nonce().to_string();
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    wallet
+        .submit_response(
+            request,
+            AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+                Default::default(),
+                vp.into(),
+                jwt_vc::presentation_submission(),
+            )),
+        )
+        .await
+        .unwrap();
+
+    let record = archive.get_record(id).await.unwrap();
+    assert_eq!(record.nonce, nonce);
+    assert!(matches!(record.outcome, Outcome::Success { .. }));
+    assert!(record.verified.is_some());
+    assert_eq!(record.retain_until, Some(record.completed_at + retention));
+
+    // Records outlive their sessions, until their retention period is over.
+    assert_eq!(archive.purge(record.completed_at).await.unwrap(), 0);
+    assert_eq!(
+        archive
+            .purge(record.completed_at + retention)
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(archive.get_record(id).await.is_err());
+}
+
+#[derive(Debug, Default)]
+struct RecordingAuditSink(Mutex<Vec<AuditRecord>>);
+
+#[async_trait::async_trait]
+impl AuditSink for RecordingAuditSink {
+    async fn record(&self, record: AuditRecord) -> anyhow::Result<()> {
+        self.0.lock().unwrap().push(record);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn verifier_audit_sink() {
+    let sink = Arc::new(RecordingAuditSink::default());
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder.with_audit_sink(
+            sink.clone(),
+            AuditRedaction {
+                request: ArtifactRedaction::Digest,
+                ..Default::default()
+            },
+        )
+    })
+    .await;
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    let request = wallet.validate_request(url).await.unwrap();
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    wallet
+        .submit_response(
+            request,
+            AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+                Default::default(),
+                vp.into(),
+                jwt_vc::presentation_submission(),
+            )),
+        )
+        .await
+        .unwrap();
+
+    let records = sink.0.lock().unwrap().clone();
+    let [record] = records.as_slice() else {
+        panic!("expected a single audit record")
+    };
+    assert_eq!(record.session, id);
+    assert!(record.request.as_ref().unwrap().starts_with("sha-256:"));
+    assert!(record
+        .response
+        .as_ref()
+        .unwrap()
+        .contains("presentation_submission="));
+    assert!(record.decrypted_response.is_none());
+    assert!(matches!(record.outcome, Outcome::Success { .. }));
+    assert!(record.requested_at <= record.received_at);
+    assert!(record.received_at <= record.completed_at);
+    assert_ne!(record.digest().unwrap(), "");
+}
+
+#[tokio::test]
+async fn verifier_stateless_session() {
+    // Two instances of the verifier, sharing the state key but not their session stores.
+    let key = StateKey::generate(&mut rand::rngs::OsRng);
+    let archive = Arc::new(MemoryArchive::default());
+    let (_, verifier) =
+        jwt_vc::wallet_verifier_with(|builder, _| builder.stateless(key.clone())).await;
+    let (wallet, other_verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder
+            .stateless(key.clone())
+            .with_presentation_archive(archive.clone())
+    })
+    .await;
+
+    let (url, id) = verifier.begin_stateless_session().await.unwrap();
+    let request = wallet.validate_request(url).await.unwrap();
+    let token = request.get::<State>().unwrap().unwrap().0;
+    assert!(request.return_uri().as_str().ends_with(&token));
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    // The wallet echoes the token as the `state`.
+    let response = UnencodedAuthorizationResponse(
+        Default::default(),
+        vp.into(),
+        jwt_vc::presentation_submission(),
+    );
+    wallet
+        .submit_response(request, AuthorizationResponse::Unencoded(response))
+        .await
+        .unwrap();
+
+    // The response was verified by the other instance, and nothing was stored.
+    let record = archive.get_record(id).await.unwrap();
+    assert!(matches!(record.outcome, Outcome::Success { .. }));
+    assert!(verifier.poll_status(id).await.is_err());
+    assert!(other_verifier.poll_status(id).await.is_err());
+
+    // Tokens sealed with another key are rejected.
+    let (_, unrelated) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder.stateless(StateKey::generate(&mut rand::rngs::OsRng))
+    })
+    .await;
+    assert!(unrelated
+        .verify_stateless_response(
+            &token,
+            AuthorizationResponse::Dcql(DcqlAuthorizationResponse(
+                Default::default(),
+                DcqlVpToken([("did-key-id".to_owned(), vec!["vp".to_owned().into()])].into()),
+            )),
+            |_, _| Box::pin(async { unreachable!() }),
+        )
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn verifier_stateless_replay() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder
+            .stateless(StateKey::generate(&mut rand::rngs::OsRng))
+            .with_seen_token_store(Arc::new(MemorySeenTokenStore::default()))
+    })
+    .await;
+
+    let (url, _) = verifier.begin_stateless_session().await.unwrap();
+    let request = wallet.validate_request(url).await.unwrap();
+    let token = request.get::<State>().unwrap().unwrap().0;
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    let mut parameters = UntypedObject::default();
+    parameters.insert(State(token.clone()));
+    let response =
+        UnencodedAuthorizationResponse(parameters, vp.into(), jwt_vc::presentation_submission());
+
+    let outcome = verifier
+        .verify_stateless_response(
+            &token,
+            AuthorizationResponse::Unencoded(response.clone()),
+            jwt_vc::succeed,
+        )
+        .await
+        .unwrap();
+    assert!(matches!(outcome, Outcome::Success { .. }));
+
+    // The same response is rejected when replayed.
+    let error = verifier
+        .verify_stateless_response(
+            &token,
+            AuthorizationResponse::Unencoded(response),
+            |_, _| Box::pin(async { unreachable!() }),
+        )
+        .await
+        .unwrap_err();
+    assert!(error.downcast_ref::<DuplicateResponse>().is_some());
+}
+
+/// Rejects callers without an IP address.
+#[derive(Debug)]
+struct RequireIp;
+
+#[async_trait::async_trait]
+impl RequestGuard for RequireIp {
+    async fn check(&self, context: &RequestContext<'_>) -> GuardDecision {
+        match context.remote.ip {
+            Some(_) => GuardDecision::Allow,
+            None => GuardDecision::Reject {
+                reason: "unknown caller".into(),
+                retry_after: Some(Duration::from_secs(30)),
+            },
+        }
+    }
+}
+
+#[tokio::test]
+async fn verifier_request_guard() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder
+            .by_reference("http://example.com/request".parse().unwrap())
+            .with_request_guard(Arc::new(RequireIp))
+    })
+    .await;
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    let request_uri: url::Url = url
+        .query_pairs()
+        .find(|(name, _)| name == "request_uri")
+        .unwrap()
+        .1
+        .parse()
+        .unwrap();
+    let token = request_uri.path_segments().unwrap().next_back().unwrap();
+
+    // The mock wallet does not pass any remote metadata.
+    let error = wallet.validate_request(url).await.unwrap_err();
+    let rejected = error
+        .chain()
+        .find_map(|e| e.downcast_ref::<RequestRejected>());
+    assert!(rejected.is_some(), "{error:#}");
+
+    let rejected = verifier
+        .retrieve_authorization_request(token)
+        .await
+        .unwrap_err();
+    let rejected = rejected.downcast_ref::<RequestRejected>().unwrap();
+    assert_eq!(rejected.endpoint, Endpoint::RequestUri);
+    assert_eq!(rejected.retry_after, Some(Duration::from_secs(30)));
+
+    // The rejected calls did not consume the single-use request_uri.
+    let remote = RemoteMetadata::new().with_ip([192, 0, 2, 1].into());
+    verifier
+        .retrieve_authorization_request_from(token, &remote)
+        .await
+        .unwrap();
+    assert_eq!(
+        verifier.session_state(id).await.unwrap(),
+        SessionState::RequestRetrieved
+    );
+}
+
+#[tokio::test]
+async fn verifier_duplicate_responses() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| builder).await;
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    wallet.validate_request(url).await.unwrap();
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    let body = |definition_id: &str| {
+        UnencodedAuthorizationResponse(
+            Default::default(),
+            vp.clone().into(),
+            PresentationSubmission::for_vp_token(
+                definition_id.into(),
+                [("did-key-id", ClaimFormatDesignation::JwtVpJson)],
+            ),
+        )
+        .into_x_www_form_urlencoded()
+        .unwrap()
+    };
+
+    let submitted = body("did-key-id-proof");
+    let outcome = verifier
+        .receive_response(id, submitted.as_bytes())
+        .await
+        .unwrap();
+    assert!(matches!(outcome, Outcome::Success { .. }));
+
+    // A retry of the same submission gets the original outcome.
+    let retried = verifier
+        .receive_response(id, submitted.as_bytes())
+        .await
+        .unwrap();
+    assert!(matches!(retried, Outcome::Success { .. }));
+
+    // A different submission is rejected.
+    let error = verifier
+        .receive_response(id, body("another-definition").as_bytes())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<DuplicateResponse>(),
+        Some(&DuplicateResponse {
+            session: id,
+            identical: false
+        })
+    );
+    assert!(matches!(
+        verifier.poll_status(id).await.unwrap(),
+        Status::Complete(Outcome::Success { .. })
+    ));
+}
+
+/// A [MemoryStore] that yields after reading a session, so that concurrent calls interleave
+/// between reading a session and updating it.
+#[derive(Debug, Default)]
+struct YieldingStore(MemoryStore);
+
+#[async_trait::async_trait]
+impl SessionStore for YieldingStore {
+    async fn initiate(&self, session: Session) -> anyhow::Result<()> {
+        self.0.initiate(session).await
+    }
+
+    async fn update_status(&self, uuid: Uuid, status: Status) -> anyhow::Result<()> {
+        self.0.update_status(uuid, status).await
+    }
+
+    async fn get_session(&self, uuid: Uuid) -> anyhow::Result<Session> {
+        let session = self.0.get_session(uuid).await;
+        tokio::task::yield_now().await;
+        session
+    }
+
+    async fn remove_session(&self, uuid: Uuid) -> anyhow::Result<()> {
+        self.0.remove_session(uuid).await
+    }
+
+    async fn take_request_uri_secret(&self, uuid: Uuid) -> anyhow::Result<Option<String>> {
+        self.0.take_request_uri_secret(uuid).await
+    }
+
+    async fn claim_response(&self, uuid: Uuid) -> anyhow::Result<bool> {
+        self.0.claim_response(uuid).await
+    }
+
+    async fn record_response_digest(&self, uuid: Uuid, digest: String) -> anyhow::Result<bool> {
+        self.0.record_response_digest(uuid, digest).await
+    }
+}
+
+#[tokio::test]
+async fn verifier_concurrent_responses() {
+    let (wallet, verifier) =
+        jwt_vc::wallet_verifier_with_store(Arc::new(YieldingStore::default()), |builder, _| {
+            builder
+        })
+        .await;
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    wallet.validate_request(url).await.unwrap();
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    let response = AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+        Default::default(),
+        vp.into(),
+        jwt_vc::presentation_submission(),
+    ));
+    let verify = |response| verifier.verify_response(id, response, jwt_vc::succeed);
+
+    // Both submissions read the session before either of them consumes its nonce.
+    let (first, second) = tokio::join!(verify(response.clone()), verify(response));
+    let errors: Vec<_> = [first, second]
+        .into_iter()
+        .filter_map(Result::err)
+        .collect();
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(
+        errors[0].downcast_ref::<DuplicateResponse>(),
+        Some(&DuplicateResponse {
+            session: id,
+            identical: false
+        })
+    );
+    assert!(matches!(
+        verifier.poll_status(id).await.unwrap(),
+        Status::Complete(Outcome::Success { .. })
+    ));
+}
+
+#[tokio::test]
+async fn verifier_concurrent_identical_responses() {
+    let (wallet, verifier) =
+        jwt_vc::wallet_verifier_with_store(Arc::new(YieldingStore::default()), |builder, _| {
+            builder
+        })
+        .await;
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    wallet.validate_request(url).await.unwrap();
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    let body = UnencodedAuthorizationResponse(
+        Default::default(),
+        vp.into(),
+        jwt_vc::presentation_submission(),
+    )
+    .into_x_www_form_urlencoded()
+    .unwrap();
+
+    // A retry racing the original submission is recognized as identical, rather than verified as
+    // a second response.
+    let (first, second) = tokio::join!(
+        verifier.receive_response(id, body.as_bytes()),
+        verifier.receive_response(id, body.as_bytes())
+    );
+    let mut succeeded = 0;
+    for result in [first, second] {
+        match result {
+            Ok(outcome) => {
+                assert!(matches!(outcome, Outcome::Success { .. }));
+                succeeded += 1;
+            }
+            Err(error) => assert_eq!(
+                error.downcast_ref::<DuplicateResponse>(),
+                Some(&DuplicateResponse {
+                    session: id,
+                    identical: true
+                })
+            ),
+        }
+    }
+    assert!(succeeded >= 1);
+    assert!(matches!(
+        verifier.poll_status(id).await.unwrap(),
+        Status::Complete(Outcome::Success { .. })
+    ));
+}
+
+#[tokio::test]
+async fn verifier_shared_state() {
+    fn assert_shareable<T: Clone + Send + Sync + 'static>(_: &T) {}
+
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| builder).await;
+    let verifier = Verifier::clone(&verifier);
+    assert_shareable(&verifier);
+
+    // Clones share their session store, e.g. across the handlers of a web server.
+    let (url, id) = tokio::spawn({
+        let verifier = verifier.clone();
+        async move { verifier.begin_session().await.unwrap() }
+    })
+    .await
+    .unwrap();
+    wallet.validate_request(url).await.unwrap();
+    assert_eq!(
+        verifier.session_state(id).await.unwrap(),
+        SessionState::RequestRetrieved
+    );
+}
+
+#[tokio::test]
+async fn verifier_wait_for_result() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| builder).await;
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    assert!(verifier
+        .wait_for_result(id, Duration::from_millis(10))
+        .await
+        .unwrap()
+        .is_none());
+
+    let waiter = tokio::spawn({
+        let verifier = verifier.clone();
+        async move { verifier.wait_for_result(id, Duration::from_secs(60)).await }
+    });
+
+    let request = wallet.validate_request(url).await.unwrap();
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    wallet
+        .submit_response(
+            request,
+            AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+                Default::default(),
+                vp.into(),
+                jwt_vc::presentation_submission(),
+            )),
+        )
+        .await
+        .unwrap();
+
+    let outcome = waiter.await.unwrap().unwrap();
+    assert!(matches!(outcome, Some(Outcome::Success { .. })));
+
+    // Completed sessions are returned immediately.
+    assert!(verifier
+        .wait_for_result(id, Duration::ZERO)
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn verifier_unsigned_request() {
+    let (wallet, verifier) = jwt_vc::unsigned_wallet_verifier().await;
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    let authorization_request =
+        AuthorizationRequest::from_url(url.clone(), &wallet.metadata().authorization_endpoint().0)
+            .unwrap();
+    let RequestIndirection::Unsigned(parameters) = &authorization_request.request_indirection
+    else {
+        panic!("expected the parameters of the request in the URL")
+    };
+    assert!(parameters.get::<ResponseUri>().is_some());
+
+    let request = wallet.validate_request(url).await.unwrap();
+    assert_eq!(request.client_id_scheme(), &ClientIdScheme::RedirectUri);
+    assert_eq!(request.client_id().0, request.return_uri().as_str());
+    assert!(request
+        .return_uri()
+        .as_str()
+        .ends_with(&format!("/submission/{id}")));
+
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    wallet
+        .submit_response(
+            request,
+            AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+                Default::default(),
+                vp.into(),
+                jwt_vc::presentation_submission(),
+            )),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        verifier.poll_status(id).await.unwrap(),
+        Status::Complete(Outcome::Success { .. })
+    ));
+
+    // A request_uri_method is not allowed without a request_uri.
+    assert!(verifier
+        .build_authorization_request()
+        .with_presentation_definition(PresentationDefinition::new(
+            "did-key-id-proof".into(),
+            InputDescriptor::new("did-key-id".into(), Constraints::new()),
+        ))
+        .with_request_parameter(RequestUriMethod("post".into()))
+        .build(wallet.metadata().clone())
+        .await
+        .is_err());
+}
+
+#[derive(Debug, Default)]
+struct RecordingSubscriber(Mutex<Vec<LifecycleEvent>>);
+
+impl EventSubscriber for RecordingSubscriber {
+    fn on_event(&self, event: &LifecycleEvent) {
+        self.0.lock().unwrap().push(event.clone())
+    }
+}
+
+#[tokio::test]
+async fn verifier_lifecycle_events() {
+    let subscriber = Arc::new(RecordingSubscriber::default());
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder
+            .by_reference("http://example.com/request".parse().unwrap())
+            .with_event_subscriber(subscriber.clone())
+    })
+    .await;
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    let request = wallet.validate_request(url).await.unwrap();
+    let nonce = request.nonce().to_string();
+    assert!(wallet
+        .submit_response(
+            request,
+            AuthorizationResponse::Dcql(DcqlAuthorizationResponse(
+                Default::default(),
+                DcqlVpToken([("did-key-id".to_owned(), vec!["vp".to_owned().into()])].into()),
+            )),
+        )
+        .await
+        .is_err());
+
+    let events = subscriber.0.lock().unwrap();
+    let kinds: Vec<_> = events.iter().map(|event| event.kind.clone()).collect();
+    assert_eq!(kinds.len(), 4);
+    assert_eq!(kinds[0], LifecycleEventKind::RequestCreated);
+    assert_eq!(kinds[1], LifecycleEventKind::RequestRetrieved);
+    assert_eq!(kinds[2], LifecycleEventKind::ResponseReceived);
+    let LifecycleEventKind::VerificationFailed { reasons } = &kinds[3] else {
+        panic!("expected a verification failure, got {:?}", kinds[3])
+    };
+    assert!(!reasons.is_empty());
+    for event in events.iter() {
+        assert_eq!(event.party, Party::Verifier);
+        assert_eq!(event.session, Some(id));
+        assert_eq!(event.nonce.as_deref(), Some(nonce.as_str()));
+    }
+}
+
+#[derive(Debug)]
+struct RejectAll;
+
+#[async_trait::async_trait]
+impl PolicyHook for RejectAll {
+    async fn evaluate(
+        &self,
+        _: &Session,
+        _: &VerifiedPresentationOutcome,
+        report: &mut VerificationReport,
+    ) {
+        report.fatal(FindingCode::PolicyViolation, "rejected by the tenant");
+    }
+}
+
+#[tokio::test]
+async fn verifier_tenants() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, client| {
+        builder
+            .with_tenant("open", Tenant::new(client.clone()))
+            .with_tenant(
+                "closed",
+                Tenant::new(client)
+                    .with_presentation_definition(PresentationDefinition::new(
+                        "closed-tenant".into(),
+                        InputDescriptor::new(
+                            "did-key-id".into(),
+                            Constraints::new().add_constraint(ConstraintsField::new(
+                                "$.credentialSubject.id".into(),
+                            )),
+                        ),
+                    ))
+                    .with_trust_policy(TrustPolicy::new().with_hook(Arc::new(RejectAll))),
+            )
+    })
+    .await;
+
+    assert!(verifier.begin_tenant_session("unknown").await.is_err());
+    assert_eq!(verifier.tenants().len(), 2);
+
+    for (tenant, definition_id, success) in [
+        ("open", "did-key-id-proof", true),
+        ("closed", "closed-tenant", false),
+    ] {
+        let (url, id) = verifier.begin_tenant_session(tenant).await.unwrap();
+        let request = wallet.validate_request(url).await.unwrap();
+        let presentation_definition = request
+            .resolve_presentation_definition(wallet.http_client())
+            .await
+            .unwrap()
+            .into_parsed();
+        assert_eq!(presentation_definition.id(), definition_id);
+
+        let vp = create_test_verifiable_presentation()
+            .await
+            .expect("failed to create verifiable presentation");
+        let _ = wallet
+            .submit_response(
+                request,
+                AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+                    Default::default(),
+                    vp.into(),
+                    PresentationSubmission::for_vp_token(
+                        presentation_definition.id().clone(),
+                        [("did-key-id", ClaimFormatDesignation::JwtVpJson)],
+                    ),
+                )),
+            )
+            .await;
+
+        assert_eq!(verifier.verified_outcome(id).await.is_ok(), success);
+    }
+}
+
+#[tokio::test]
+async fn signed_wallet_metadata() {
+    let authority = JWK::generate_p256();
+    let anchors =
+        MetadataTrustAnchors::new().with_anchor("https://trust.example.com", authority.clone());
+    let (wallet, verifier) =
+        jwt_vc::wallet_verifier_with(|builder, _| builder.with_metadata_trust_anchors(anchors))
+            .await;
+
+    let build = |wallet_metadata: WalletMetadata| {
+        verifier
+            .build_authorization_request()
+            .with_presentation_definition(jwt_vc::presentation_definition())
+            .build(wallet_metadata)
+    };
+
+    let error = build(wallet.metadata().clone()).await.unwrap_err();
+    assert!(format!("{error:#}").contains("not signed"));
+
+    let mut signed = wallet.metadata().clone();
+    signed
+        .sign("https://trust.example.com", &authority)
+        .unwrap();
+    let (_, url) = build(signed.clone()).await.unwrap();
+    wallet.validate_request(url).await.unwrap();
+
+    let mut self_signed = wallet.metadata().clone();
+    self_signed
+        .sign("https://trust.example.com", &JWK::generate_p256())
+        .unwrap();
+    assert!(build(self_signed).await.is_err());
+}
+
+#[tokio::test]
+async fn verifier_pinned_drafts() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, client| {
+        builder.with_tenant("legacy", Tenant::new(client).with_draft(Draft::Draft20))
+    })
+    .await;
+
+    let dcql_query = DcqlQuery::new(vec![DcqlCredentialQuery::new(
+        "did-key-id".into(),
+        "jwt_vc_json".into(),
+    )]);
+    assert!(verifier
+        .build_tenant_authorization_request("legacy")
+        .unwrap()
+        .with_dcql_query(dcql_query)
+        .with_request_parameter(ResponseMode::DirectPost)
+        .with_request_parameter(ResponseType::VpToken)
+        .build(wallet.metadata().clone())
+        .await
+        .is_err());
+
+    // Draft 20 wallets cannot POST to the request_uri, but the session can pin a later draft.
+    let request_uri_method = |draft: Option<Draft>| {
+        let builder = verifier
+            .build_tenant_authorization_request("legacy")
+            .unwrap()
+            .with_presentation_definition(jwt_vc::presentation_definition())
+            .with_request_parameter(RequestUriMethod("post".into()));
+        match draft {
+            Some(draft) => builder.with_draft(draft),
+            None => builder,
+        }
+        .build(wallet.metadata().clone())
+    };
+    assert!(request_uri_method(None).await.is_err());
+    assert!(request_uri_method(Some(Draft::Draft21)).await.is_ok());
+
+    // The static metadata of draft 20 wallets only supports pre-registered clients.
+    let error = verifier
+        .build_tenant_authorization_request("legacy")
+        .unwrap()
+        .with_presentation_definition(jwt_vc::presentation_definition())
+        .build_with_default_metadata()
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("client_id_scheme 'did'"));
+
+    for (draft, dcql_shaped, success) in [
+        (Draft::Draft20, false, true),
+        (Draft::Draft21, false, true),
+        (Draft::Draft21, true, false),
+    ] {
+        let (id, url) = verifier
+            .build_tenant_authorization_request("legacy")
+            .unwrap()
+            .with_draft(draft)
+            .with_presentation_definition(jwt_vc::presentation_definition())
+            .build(wallet.metadata().clone())
+            .await
+            .unwrap();
+        let request = wallet.validate_request(url).await.unwrap();
+
+        let vp = create_test_verifiable_presentation()
+            .await
+            .expect("failed to create verifiable presentation");
+        let response = if dcql_shaped {
+            AuthorizationResponse::Dcql(DcqlAuthorizationResponse(
+                Default::default(),
+                DcqlVpToken([("did-key-id".to_owned(), vec![vp.into()])].into()),
+            ))
+        } else {
+            AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+                Default::default(),
+                vp.into(),
+                jwt_vc::presentation_submission(),
+            ))
+        };
+        let _ = wallet.submit_response(request, response).await;
+
+        assert_eq!(verifier.verified_outcome(id).await.is_ok(), success);
+    }
+}
+
+#[tokio::test]
+async fn verifier_id_token_required() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier().await;
+
+    let (id, url) = verifier
+        .build_authorization_request()
+        .with_presentation_definition(jwt_vc::presentation_definition())
+        .with_request_parameter(ResponseType::VpTokenIdToken)
+        .build(wallet.metadata().clone())
+        .await
+        .unwrap();
+    let request = wallet.validate_request(url).await.unwrap();
+
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    let response = AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+        Default::default(),
+        vp.into(),
+        jwt_vc::presentation_submission(),
+    ));
+    let _ = wallet.submit_response(request, response).await;
+
+    let Status::Complete(Outcome::Failure { reason }) = verifier.poll_status(id).await.unwrap()
+    else {
+        panic!("a response without the requested id_token was accepted")
+    };
+    assert!(reason.contains("id_token"));
+}
+
+#[tokio::test]
+async fn verifier_template_session() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder.with_request_template(
+            "did-key-id",
+            RequestTemplate::with_presentation_definition(PresentationDefinition::new(
+                "did-key-id-template".into(),
+                InputDescriptor::new(
+                    "did-key-id".into(),
+                    Constraints::new()
+                        .add_constraint(ConstraintsField::new("$.credentialSubject.id".into())),
+                ),
+            ))
+            .with_request_parameter(ResponseMode::DirectPost),
+        )
+    })
+    .await;
+
+    assert!(verifier
+        .begin_template_session("unknown", SessionOverrides::new())
+        .await
+        .is_err());
+
+    let (url, id) = verifier
+        .begin_template_session(
+            "did-key-id",
+            SessionOverrides::new()
+                .with_nonce(Nonce::from("template-nonce"))
+                .with_state(State("template-state".into())),
+        )
+        .await
+        .unwrap();
+
+    let request = wallet.validate_request(url).await.unwrap();
+    assert_eq!(request.nonce().as_str(), "template-nonce");
+    assert_eq!(request.get::<State>().unwrap().unwrap().0, "template-state");
+    let presentation_definition = request
+        .resolve_presentation_definition(wallet.http_client())
+        .await
+        .unwrap()
+        .into_parsed();
+    assert_eq!(presentation_definition.id(), "did-key-id-template");
+
+    let vp = create_test_verifiable_presentation()
+        .await
+        .expect("failed to create verifiable presentation");
+    let mut parameters = UntypedObject::default();
+    parameters.insert(State("template-state".into()));
+    wallet
+        .submit_response(
+            request,
+            AuthorizationResponse::Unencoded(UnencodedAuthorizationResponse(
+                parameters,
+                vp.into(),
+                PresentationSubmission::for_vp_token(
+                    presentation_definition.id().clone(),
+                    [("did-key-id", ClaimFormatDesignation::JwtVpJson)],
+                ),
+            )),
+        )
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        verifier.poll_status(id).await.unwrap(),
+        Status::Complete(Outcome::Success { .. })
+    ));
+}
+
+#[tokio::test]
+async fn batch_validate_requests() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier().await;
+
+    let presentation_definition = jwt_vc::presentation_definition();
+
+    let mut urls = Vec::new();
+    for nonce in ["first_nonce", "second_nonce"] {
+        let (_, request) = verifier
+            .build_authorization_request()
+            .with_presentation_definition(presentation_definition.clone())
+            .with_request_parameter(ResponseMode::DirectPost)
+            .with_request_parameter(ResponseType::VpToken)
+            .with_request_parameter(Nonce::from(nonce))
+            .with_request_parameter(ClientMetadata(UntypedObject::default()))
+            .build(wallet.metadata().clone())
+            .await
+            .unwrap();
+        urls.push(request);
+    }
+    urls.insert(1, "openid4vp://?client_id=unknown".parse().unwrap());
+
+    let batch = wallet.validate_requests(urls.clone()).await;
+
+    assert_eq!(batch.len(), 3);
+    assert_eq!(
+        batch.iter().map(|b| b.url.clone()).collect::<Vec<_>>(),
+        urls
+    );
+    assert_ne!(batch[0].correlation_id, batch[2].correlation_id);
+    assert_eq!(
+        batch[0].request.as_ref().unwrap().nonce().as_str(),
+        "first_nonce"
+    );
+    assert!(batch[1].request.is_err());
+    assert_eq!(
+        batch[2].request.as_ref().unwrap().nonce().as_str(),
+        "second_nonce"
+    );
+}
+
+#[tokio::test]
+async fn dc_api_request_origin() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier().await;
+
+    let presentation_definition = jwt_vc::presentation_definition();
+
+    let (_, url) = verifier
+        .build_authorization_request()
+        .with_presentation_definition(presentation_definition)
+        .with_request_parameter(ResponseMode::DirectPost)
+        .with_request_parameter(ResponseType::VpToken)
+        .with_request_parameter(Nonce::from("random_nonce"))
+        .with_request_parameter(ClientMetadata(UntypedObject::default()))
+        .with_request_parameter(ExpectedOrigins(vec!["https://verifier.example".into()]))
+        .build(wallet.metadata().clone())
+        .await
+        .unwrap();
+
+    let jwt =
+        match AuthorizationRequest::from_url(url, &wallet.metadata().authorization_endpoint().0)
+            .unwrap()
+            .request_indirection
+        {
+            RequestIndirection::ByValue(jwt) => jwt,
+            RequestIndirection::ByReference(uri) => {
+                String::from_utf8(wallet.http_client().get(&uri).await.unwrap().into_body())
+                    .unwrap()
+            }
+            RequestIndirection::Unsigned(_) => panic!("expected a signed request"),
+        };
+
+    let dc_api_request = |origin: &str, data| DcApiRequest {
+        origin: origin.into(),
+        data,
+    };
+
+    let request = wallet
+        .validate_dc_api_request(dc_api_request(
+            "https://verifier.example",
+            serde_json::json!({ "request": jwt }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(request.nonce().as_str(), "random_nonce");
+
+    assert!(wallet
+        .validate_dc_api_request(dc_api_request(
+            "https://attacker.example",
+            serde_json::json!({ "request": jwt }),
+        ))
+        .await
+        .is_err());
+
+    // Unsigned requests are rejected by the default policy.
+    let unsigned = UntypedObject::from(request);
+    assert!(wallet
+        .validate_dc_api_request(dc_api_request(
+            "https://verifier.example",
+            serde_json::to_value(unsigned).unwrap(),
+        ))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn dc_api_encrypted_request() {
+    let (_, verifier) = jwt_vc::wallet_verifier().await;
+    let key = ecdh_es::generate(KeyAgreementCurve::P256, &mut rand::thread_rng()).unwrap();
+    let mut public_key = key.clone();
+    public_key.remove("d");
+
+    let mut metadata = verifier.wallet_metadata().clone();
+    metadata.set_request_object_encryption_alg_values_supported(["ECDH-ES"]);
+    metadata.set_jwks(Some(JWKs {
+        keys: vec![public_key],
+    }));
+
+    let (_, url) = verifier
+        .build_authorization_request()
+        .with_presentation_definition(jwt_vc::presentation_definition())
+        .with_request_parameter(ResponseMode::DirectPost)
+        .with_request_parameter(ResponseType::VpToken)
+        .with_request_parameter(ExpectedOrigins(vec!["https://verifier.example".into()]))
+        .build(metadata.clone())
+        .await
+        .unwrap();
+
+    let wallet = MockWallet::new(verifier.clone()).with_metadata(metadata);
+    let jwt =
+        match AuthorizationRequest::from_url(url, &wallet.metadata().authorization_endpoint().0)
+            .unwrap()
+            .request_indirection
+        {
+            RequestIndirection::ByValue(jwt) => jwt,
+            RequestIndirection::ByReference(uri) => {
+                String::from_utf8(wallet.http_client().get(&uri).await.unwrap().into_body())
+                    .unwrap()
+            }
+            RequestIndirection::Unsigned(_) => panic!("expected a signed request"),
+        };
+    let dc_api_request = || DcApiRequest {
+        origin: "https://verifier.example".into(),
+        data: serde_json::json!({ "request": jwt }),
+    };
+
+    let error = wallet
+        .validate_dc_api_request(dc_api_request())
+        .await
+        .unwrap_err();
+    assert!(
+        format!("{error:#}").contains("no decryption key"),
+        "{error:#}"
+    );
+
+    let wallet = wallet.with_request_object_decryption_key(key);
+    wallet
+        .validate_dc_api_request(dc_api_request())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn dc_api_unsigned_request_object_origin() {
+    // The wallet accepts unsigned requests from `http://example.com`, which is also the origin of
+    // the response URI.
+    let (wallet, verifier) = jwt_vc::unsigned_wallet_verifier().await;
+
+    let (_, url) = verifier
+        .build_authorization_request()
+        .with_presentation_definition(jwt_vc::presentation_definition())
+        .with_request_parameter(ExpectedOrigins(vec![
+            "http://example.com".into(),
+            "https://verifier.example".into(),
+        ]))
+        .build(wallet.metadata().clone())
+        .await
+        .unwrap();
+    let RequestIndirection::Unsigned(parameters) =
+        AuthorizationRequest::from_url(url, &wallet.metadata().authorization_endpoint().0)
+            .unwrap()
+            .request_indirection
+    else {
+        panic!("expected an unsigned request")
+    };
+    let jwt = format!(
+        "{}.{}.",
+        BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#),
+        BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&parameters).unwrap())
+    );
+    let dc_api_request = |origin: &str| DcApiRequest {
+        origin: origin.into(),
+        data: serde_json::json!({ "request": jwt }),
+    };
+
+    wallet
+        .validate_dc_api_request(dc_api_request("http://example.com"))
+        .await
+        .unwrap();
+
+    // The policy applies to the invoking origin, not to the origin of the response URI.
+    let error = wallet
+        .validate_dc_api_request(dc_api_request("https://verifier.example"))
+        .await
+        .unwrap_err();
+    assert!(
+        format!("{error:#}").contains("not accepted from 'https://verifier.example'"),
+        "{error:#}"
+    );
+}
+
+#[tokio::test]
+async fn wallet_client_id_prefixes() {
+    let (_, verifier) = jwt_vc::wallet_verifier().await;
+    let metadata = |prefixes: Vec<ClientIdScheme>| {
+        WalletMetadata::builder()
+            .with_client_id_prefixes_supported(prefixes)
+            .build()
+            .unwrap()
+    };
+
+    let wallet =
+        MockWallet::new(verifier.clone()).with_metadata(metadata(vec![ClientIdScheme::X509SanDns]));
+    let (url, _) = verifier.begin_session().await.unwrap();
+    let error = wallet.validate_request(url).await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<ClientIdSchemeNotSupported>(),
+        Some(&ClientIdSchemeNotSupported(ClientIdScheme::Did))
+    );
+    assert_eq!(
+        AuthorizationErrorCode::for_error(&error),
+        AuthorizationErrorCode::InvalidRequest
+    );
+
+    // `decentralized_identifier` is the prefix of the `did` client ID scheme.
+    let wallet = wallet.with_metadata(metadata(vec![ClientIdScheme::from_prefix(
+        "decentralized_identifier".into(),
+    )]));
+    let (url, _) = verifier.begin_session().await.unwrap();
+    wallet.validate_request(url).await.unwrap();
+}
+
+/// A misspelled `presentation_definition_uri`.
+#[derive(Debug, Clone)]
+struct PresentationDefinitionUrl(String);
+
+impl TypedParameter for PresentationDefinitionUrl {
+    const KEY: &'static str = "presentation_definition_url";
+}
+
+impl TryFrom<serde_json::Value> for PresentationDefinitionUrl {
+    type Error = anyhow::Error;
+
+    fn try_from(value: serde_json::Value) -> anyhow::Result<Self> {
+        Ok(Self(serde_json::from_value(value)?))
+    }
+}
+
+impl From<PresentationDefinitionUrl> for serde_json::Value {
+    fn from(value: PresentationDefinitionUrl) -> Self {
+        value.0.into()
+    }
+}
+
+#[tokio::test]
+async fn wallet_unknown_parameters() {
+    let (_, verifier) = jwt_vc::wallet_verifier().await;
+    let wallet =
+        MockWallet::new(verifier.clone()).with_unknown_parameters(UnknownParameters::Reject);
+
+    // The requests of this library only carry parameters it knows.
+    let (url, _) = verifier.begin_session().await.unwrap();
+    wallet.validate_request(url).await.unwrap();
+
+    let (_, url) = verifier
+        .build_authorization_request()
+        .with_presentation_definition(jwt_vc::presentation_definition())
+        .with_request_parameter(ResponseMode::DirectPost)
+        .with_request_parameter(ResponseType::VpToken)
+        .with_request_parameter(PresentationDefinitionUrl("https://example.com/pd".into()))
+        .build(verifier.wallet_metadata().clone())
+        .await
+        .unwrap();
+    let error = wallet.validate_request(url.clone()).await.unwrap_err();
+    assert!(
+        format!("{error:#}").contains("presentation_definition_url"),
+        "{error:#}"
+    );
+
+    let wallet = wallet.with_unknown_parameters(UnknownParameters::Warn);
+    wallet.validate_request(url).await.unwrap();
+}
+
+/// The eIDAS level of assurance requested by a profile, which this library does not know.
+#[derive(Debug, Clone)]
+struct LevelOfAssurance(String);
+
+impl TypedParameter for LevelOfAssurance {
+    const KEY: &'static str = "level_of_assurance";
+}
+
+impl TryFrom<serde_json::Value> for LevelOfAssurance {
+    type Error = anyhow::Error;
+
+    fn try_from(value: serde_json::Value) -> anyhow::Result<Self> {
+        let level: String = serde_json::from_value(value)?;
+        if !["low", "substantial", "high"].contains(&level.as_str()) {
+            anyhow::bail!("unknown level of assurance '{level}'")
+        }
+        Ok(Self(level))
+    }
+}
+
+impl From<LevelOfAssurance> for serde_json::Value {
+    fn from(value: LevelOfAssurance) -> Self {
+        value.0.into()
+    }
+}
+
+/// A request of `verifier` for the `level` of assurance.
+async fn level_of_assurance_request(verifier: &Verifier, level: &str) -> anyhow::Result<url::Url> {
+    let (_, url) = verifier
+        .build_authorization_request()
+        .with_presentation_definition(jwt_vc::presentation_definition())
+        .with_request_parameter(ResponseMode::DirectPost)
+        .with_request_parameter(ResponseType::VpToken)
+        .with_request_parameter(LevelOfAssurance(level.into()))
+        .build(verifier.wallet_metadata().clone())
+        .await?;
+    Ok(url)
+}
+
+#[tokio::test]
+async fn extension_parameter_registry() {
+    let registry = ParameterRegistry::new()
+        .with_parameter::<LevelOfAssurance>(ParameterLocation::Request)
+        .unwrap();
+
+    let (_, verifier) = jwt_vc::wallet_verifier().await;
+    let wallet =
+        MockWallet::new(verifier.clone()).with_unknown_parameters(UnknownParameters::Reject);
+    let url = level_of_assurance_request(&verifier, "high").await.unwrap();
+    assert!(wallet.validate_request(url.clone()).await.is_err());
+
+    // Registered parameters are known to strict modes, and parsed with the request.
+    let wallet = wallet.with_parameter_registry(registry.clone());
+    let request_object = wallet.validate_request(url).await.unwrap();
+    assert_eq!(
+        request_object.get::<LevelOfAssurance>().unwrap().unwrap().0,
+        "high"
+    );
+
+    let url = level_of_assurance_request(&verifier, "very high")
+        .await
+        .unwrap();
+    let error = wallet.validate_request(url).await.unwrap_err();
+    assert!(
+        format!("{error:#}").contains("level_of_assurance"),
+        "{error:#}"
+    );
+
+    // Verifiers that register the parameter do not build such requests.
+    let (_, verifier) =
+        jwt_vc::wallet_verifier_with(|builder, _| builder.with_parameter_registry(registry)).await;
+    assert!(level_of_assurance_request(&verifier, "very high")
+        .await
+        .is_err());
+    level_of_assurance_request(&verifier, "substantial")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn preferred_authorization_endpoint() {
+    let (_, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder.with_preferred_authorization_endpoints(["haip://".parse().unwrap()])
+    })
+    .await;
+
+    // Wallets that are not invoked under `haip://` are invoked at their authorization endpoint.
+    let (url, _) = verifier.begin_session().await.unwrap();
+    assert_eq!(url.scheme(), "openid4vp");
+
+    let mut metadata = verifier.wallet_metadata().clone();
+    metadata.set_authorization_endpoints(Some(AuthorizationEndpoints(vec!["haip://"
+        .parse()
+        .unwrap()])));
+    let (_, url) = verifier
+        .build_authorization_request()
+        .with_presentation_definition(jwt_vc::presentation_definition())
+        .with_request_parameter(ResponseMode::DirectPost)
+        .with_request_parameter(ResponseType::VpToken)
+        .build(metadata.clone())
+        .await
+        .unwrap();
+    assert_eq!(url.scheme(), "haip");
+
+    let wallet = MockWallet::new(verifier.clone());
+    assert!(wallet.validate_request(url.clone()).await.is_err());
+    let wallet = wallet.with_metadata(metadata);
+    wallet.validate_request(url).await.unwrap();
+}
+
+#[tokio::test]
+async fn response_types_and_modes_supported() {
+    let (_, verifier) = jwt_vc::wallet_verifier().await;
+    let request = |metadata: WalletMetadata, response_type: ResponseType| {
+        verifier
+            .build_authorization_request()
+            .with_presentation_definition(jwt_vc::presentation_definition())
+            .with_request_parameter(ResponseMode::DirectPost)
+            .with_request_parameter(response_type)
+            .build(metadata)
+    };
+    let metadata = verifier.wallet_metadata().clone();
+    let (_, url) = request(metadata.clone(), ResponseType::VpTokenIdToken)
+        .await
+        .unwrap();
+
+    // Wallets reject requests for response types they do not list.
+    let mut vp_token_only = metadata.clone();
+    vp_token_only
+        .set_response_types_supported(Some(ResponseTypesSupported(vec![ResponseType::VpToken])));
+    let wallet = MockWallet::new(verifier.clone()).with_metadata(vp_token_only.clone());
+    let error = wallet.validate_request(url).await.unwrap_err();
+    assert!(error.downcast_ref::<ResponseTypeNotSupported>().is_some());
+    assert_eq!(
+        AuthorizationErrorCode::for_error(&error),
+        AuthorizationErrorCode::UnsupportedResponseType
+    );
+
+    // Verifiers do not build them for wallets whose metadata they know.
+    let error = request(vp_token_only, ResponseType::VpTokenIdToken)
+        .await
+        .unwrap_err();
+    assert!(error.downcast_ref::<ResponseTypeNotSupported>().is_some());
+
+    let mut encrypted_only = metadata;
+    encrypted_only.set_response_modes_supported(Some(ResponseModesSupported(vec![
+        ResponseMode::DirectPostJwt,
+    ])));
+    let error = request(encrypted_only.clone(), ResponseType::VpToken)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<ResponseModeNotSupported>(),
+        Some(&ResponseModeNotSupported(ResponseMode::DirectPost))
+    );
+
+    let (_, url) = request(verifier.wallet_metadata().clone(), ResponseType::VpToken)
+        .await
+        .unwrap();
+    let wallet = MockWallet::new(verifier.clone()).with_metadata(encrypted_only);
+    let error = wallet.validate_request(url).await.unwrap_err();
+    assert!(error.downcast_ref::<ResponseModeNotSupported>().is_some());
+    assert_eq!(
+        AuthorizationErrorCode::for_error(&error),
+        AuthorizationErrorCode::InvalidRequest
+    );
+}
+
+#[tokio::test]
+async fn presentation_definition_uri_supported() {
+    let (_, verifier) = jwt_vc::wallet_verifier().await;
+    let request = |metadata: WalletMetadata| {
+        verifier
+            .build_authorization_request()
+            .with_presentation_definition(jwt_vc::presentation_definition())
+            .with_presentation_definition_uri("https://example.com/pd".parse().unwrap())
+            .with_request_parameter(ResponseMode::DirectPost)
+            .with_request_parameter(ResponseType::VpToken)
+            .build(metadata)
+    };
+    let wallet = MockWallet::new(verifier.clone());
+
+    // Wallets retrieve presentation definitions by reference unless they say otherwise.
+    let metadata = verifier.wallet_metadata().clone();
+    let (_, url) = request(metadata.clone()).await.unwrap();
+    let request_object = wallet.validate_request(url.clone()).await.unwrap();
+    assert!(request_object.get::<PresentationDefinitionUri>().is_some());
+
+    let mut unsupported = metadata;
+    unsupported.set_presentation_definition_uri_supported(Some(false));
+    let wallet = wallet.with_metadata(unsupported.clone());
+    let error = wallet.validate_request(url).await.unwrap_err();
+    assert!(error
+        .downcast_ref::<PresentationDefinitionUriNotSupported>()
+        .is_some());
+    assert_eq!(
+        AuthorizationErrorCode::for_error(&error),
+        AuthorizationErrorCode::InvalidRequest
+    );
+
+    let (_, url) = request(unsupported).await.unwrap();
+    let request_object = wallet.validate_request(url).await.unwrap();
+    assert!(request_object.get::<PresentationDefinitionUri>().is_none());
+    assert!(request_object
+        .get::<openid4vp::core::authorization_request::parameters::PresentationDefinition>()
+        .is_some());
+}
+
+#[tokio::test]
+async fn wallet_encrypted_request_object() {
+    let (_, verifier) = jwt_vc::wallet_verifier().await;
+    let key = ecdh_es::generate(KeyAgreementCurve::P256, &mut rand::thread_rng()).unwrap();
+    let mut public_key = key.clone();
+    public_key.remove("d");
+
+    let mut metadata = verifier.wallet_metadata().clone();
+    metadata.set_request_object_encryption_alg_values_supported(["ECDH-ES"]);
+    metadata.set_jwks(Some(JWKs {
+        keys: vec![public_key],
+    }));
+
+    let (_, url) = verifier
+        .build_authorization_request()
+        .with_presentation_definition(jwt_vc::presentation_definition())
+        .with_request_parameter(ResponseMode::DirectPost)
+        .with_request_parameter(ResponseType::VpToken)
+        .build(metadata.clone())
+        .await
+        .unwrap();
+
+    let wallet = MockWallet::new(verifier.clone()).with_metadata(metadata);
+    let error = wallet.validate_request(url.clone()).await.unwrap_err();
+    assert!(
+        format!("{error:#}").contains("no decryption key"),
+        "{error:#}"
+    );
+
+    let wallet = wallet.with_request_object_decryption_key(key);
+    wallet.validate_request(url).await.unwrap();
+}
+
+#[tokio::test]
+async fn conformance_self_check() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier().await;
+
+    let report = conformance::check_verifier(&verifier).await;
+    assert!(report.passed(), "{report}");
+    assert!(report
+        .checks
+        .iter()
+        .all(|check| check.status == CheckStatus::Passed));
+
+    let report = conformance::check_wallet(&wallet, &verifier).await;
+    assert!(report.passed(), "{report}");
+    assert!(report
+        .checks
+        .iter()
+        .all(|check| check.status == CheckStatus::Passed));
+
+    // Checks that do not apply to unsigned requests are skipped.
+    let (wallet, verifier) = jwt_vc::unsigned_wallet_verifier().await;
+    let report = conformance::check_wallet(&wallet, &verifier).await;
+    assert!(report.passed(), "{report}");
+    assert!(matches!(
+        report
+            .get("wallet.rejects-tampered-request")
+            .unwrap()
+            .status,
+        CheckStatus::Skipped(_)
+    ));
+}
+
+/// Presents the test presentation for the first input descriptor, or DCQL credential query.
+#[async_trait::async_trait]
+impl Responder for jwt_vc::JwtVcWallet {
+    async fn build_response(
+        &self,
+        request: &AuthorizationRequestObject,
+    ) -> anyhow::Result<AuthorizationResponse> {
+        let vp = create_test_verifiable_presentation().await?;
+        if let Some(dcql_query) = request.dcql_query() {
+            let id = dcql_query.credentials()[0].id.clone();
+            return Ok(AuthorizationResponse::Dcql(DcqlAuthorizationResponse(
+                UntypedObject::default(),
+                DcqlVpToken([(id, vec![vp.into()])].into()),
+            )));
+        }
+        let presentation_definition = request
+            .resolve_presentation_definition(self.http_client())
+            .await?
+            .into_parsed();
+        Ok(AuthorizationResponse::Unencoded(
+            UnencodedAuthorizationResponse(
+                UntypedObject::default(),
+                vp.into(),
+                PresentationSubmission::for_vp_token(
+                    presentation_definition.id().clone(),
+                    [(
+                        presentation_definition.input_descriptors()[0]
+                            .id()
+                            .to_owned(),
+                        ClaimFormatDesignation::JwtVpJson,
+                    )],
+                ),
+            ),
+        ))
+    }
+}
+
+#[tokio::test]
+async fn simulated_flows() {
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder.with_session_ttl(Duration::from_secs(60))
+    })
+    .await;
+    let simulation = Simulation::new(verifier.clone(), wallet);
+
+    let report = simulation.run(None).await.unwrap();
+    assert!(report.succeeded(), "{report:?}");
+
+    // The wallet rejects a request whose signature does not verify.
+    let report = simulation.run(Some(Fault::BadSignature)).await.unwrap();
+    assert!(report.request_error.is_some());
+    assert!(matches!(report.status, Status::SentRequest));
+
+    // The verifier rejects the response to an expired request passed by value.
+    let report = simulation.run(Some(Fault::ExpiredRequest)).await.unwrap();
+    assert!(report.request_error.is_none());
+    assert!(report.response_rejected(), "{report:?}");
+
+    // The presentations of the mock wallet carry the nonce, which the verifier checks.
+    let wallet = MockWallet::new(verifier.clone()).with_credential("did-key-id", "a.b.c");
+    let simulation = Simulation::new(verifier, wallet);
+    assert!(simulation.run(None).await.unwrap().succeeded());
+    let report = simulation.run(Some(Fault::WrongNonce)).await.unwrap();
+    assert!(report.response_error.is_some());
+    assert!(report.response_rejected(), "{report:?}");
+
+    // An expired request_uri cannot be retrieved.
+    let (wallet, verifier) = jwt_vc::wallet_verifier_with(|builder, _| {
+        builder.by_reference("http://example.com/request".parse().unwrap())
+    })
+    .await;
+    let simulation = Simulation::new(verifier, wallet);
+    assert!(simulation.run(None).await.unwrap().succeeded());
+    let report = simulation.run(Some(Fault::ExpiredRequest)).await.unwrap();
+    assert!(report.request_error.is_some());
+    let report = simulation.run(Some(Fault::BadSignature)).await.unwrap();
+    assert!(report.request_error.is_some());
+}
+
+/// The facade for foreign-language bindings makes HTTP requests and presentations with callbacks
+/// of the host app.
+#[cfg(feature = "ffi")]
+#[tokio::test]
+async fn ffi_wallet() {
+    use base64::prelude::*;
+    use openid4vp::{ffi::*, test_utils::MockVerifier};
+    use serde_json::{json, Value as Json};
+
+    struct HttpClient(MockVerifier);
+
+    #[async_trait::async_trait]
+    impl FfiHttpClient for HttpClient {
+        async fn execute(&self, request: FfiHttpRequest) -> Result<FfiHttpResponse, FfiError> {
+            let mut builder = ::http::Request::builder()
+                .method(request.method.as_str())
+                .uri(request.url);
+            for header in request.headers {
+                builder = builder.header(header.name, header.value);
+            }
+            let response = self
+                .0
+                .execute(builder.body(request.body).unwrap())
+                .await
+                .map_err(|e| FfiError::Callback {
+                    message: e.to_string(),
+                })?;
+            Ok(FfiHttpResponse {
+                status: response.status().as_u16(),
+                headers: vec![],
+                body: response.into_body(),
+            })
+        }
+    }
+
+    /// Presents JWT VCs in unsigned JWT VPs.
+    struct Presenter;
+
+    #[async_trait::async_trait]
+    impl FfiPresentationBuilder for Presenter {
+        async fn present(
+            &self,
+            request_json: String,
+            credential: FfiSelectedCredential,
+        ) -> Result<FfiPresentation, FfiError> {
+            let request: Json = serde_json::from_str(&request_json).unwrap();
+            let payload = json!({
+                "nonce": request["nonce"],
+                "vp": { "verifiableCredential": [credential.encoded] }
+            });
+            Ok(FfiPresentation {
+                presentation: format!(
+                    "{}.{}.",
+                    BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#),
+                    BASE64_URL_SAFE_NO_PAD.encode(payload.to_string())
+                ),
+                format: "jwt_vp_json".into(),
+            })
+        }
+    }
+
+    let (_, verifier) = jwt_vc::unsigned_wallet_verifier().await;
+    let mock_verifier = MockVerifier::new(verifier.clone());
+    let wallet = FfiWallet::new(
+        FfiWalletConfig {
+            metadata_json: serde_json::to_string(verifier.wallet_metadata()).unwrap(),
+            trusted_dids: None,
+            unsigned_request_origins: vec!["http://example.com".into()],
+            auto_submit_errors: true,
+        },
+        Arc::new(HttpClient(mock_verifier.clone())),
+        Arc::new(Presenter),
+    )
+    .unwrap();
+    let credential = json!({
+        "credentialSubject": { "id": "did:example:holder", "given_name": "Alice" }
+    });
+    wallet
+        .add_jwt_vc(
+            "vc".into(),
+            format!(
+                "{}.{}.sig",
+                BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256"}"#),
+                BASE64_URL_SAFE_NO_PAD.encode(credential.to_string())
+            ),
+        )
+        .await
+        .unwrap();
+
+    let (url, id) = verifier.begin_session().await.unwrap();
+    let request = wallet
+        .clone()
+        .handle_request(url.to_string())
+        .await
+        .unwrap();
+    assert!(request
+        .client_id()
+        .starts_with("http://example.com/submission"));
+    assert!(request.is_satisfiable());
+    assert_eq!(request.candidates()[0].credential_ids, ["vc"]);
+
+    let selection = |credential_id: &str| FfiSelection {
+        input_descriptor_id: "did-key-id".into(),
+        credential_id: credential_id.into(),
+        approved_claims: None,
+    };
+    assert!(matches!(
+        request.approve(vec![selection("other")]).await,
+        Err(FfiError::InvalidArgument { .. })
+    ));
+    request.approve(vec![selection("vc")]).await.unwrap();
+    assert!(matches!(
+        verifier.poll_status(id).await.unwrap(),
+        Status::Complete(Outcome::Success { .. })
+    ));
+
+    // The presentation discloses `given_name`, which the user did not approve: the verifier is
+    // sent an error response instead.
+    let (url, id) = verifier.begin_session().await.unwrap();
+    let request = wallet.handle_request(url.to_string()).await.unwrap();
+    let error = request
+        .approve(vec![FfiSelection {
+            approved_claims: Some(vec![]),
+            ..selection("vc")
+        }])
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("did not approve"), "{error}");
+    let [(session, response)] = mock_verifier.error_responses().try_into().unwrap();
+    assert_eq!(session, id.to_string());
+    assert_eq!(response.error, AuthorizationErrorCode::AccessDenied);
+}
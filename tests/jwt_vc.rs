@@ -1,4 +1,8 @@
-use std::sync::Arc;
+use std::{
+    future::{ready, Ready},
+    pin::Pin,
+    sync::Arc,
+};
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
@@ -6,17 +10,31 @@ use http::{Request, Response};
 use openid4vp::{
     core::{
         authorization_request::{
-            verification::{did, RequestVerifier},
+            parameters::{ClientMetadata, ResponseMode, ResponseType, Scope},
+            verification::{
+                did::{self, CachingJwkResolver},
+                unsigned::UnsignedRequestPolicy,
+                RequestVerifier,
+            },
             AuthorizationRequestObject,
         },
+        credential_format::ClaimFormatDesignation,
+        input_descriptor::{Constraints, ConstraintsField, InputDescriptor},
         metadata::WalletMetadata,
+        object::UntypedObject,
+        presentation_definition::PresentationDefinition,
+        presentation_submission::PresentationSubmission,
         response::AuthorizationResponse,
         util::AsyncHttpClient,
     },
     verifier::{
+        client::{Client, RedirectUriClient},
+        outcome::VerifiedPresentationOutcome,
+        report::VerificationReport,
         request_signer::P256Signer,
-        session::{MemoryStore, Outcome},
-        Verifier,
+        session::{MemoryStore, Outcome, Session, SessionStore},
+        validator::ResponseValidator,
+        Verifier, VerifierBuilder,
     },
     wallet::Wallet,
 };
@@ -24,7 +42,52 @@ use serde_json::json;
 use ssi::dids::{DIDKey, VerificationMethodDIDResolver};
 use ssi::verification_methods::AnyJwkMethod;
 
+/// The presentation definition requested by the test verifier, for the `id` of the credential
+/// subject.
+pub fn presentation_definition() -> PresentationDefinition {
+    PresentationDefinition::new(
+        "did-key-id-proof".into(),
+        InputDescriptor::new(
+            "did-key-id".into(),
+            Constraints::new()
+                .add_constraint(ConstraintsField::new("$.credentialSubject.id".into())),
+        ),
+    )
+}
+
+/// The presentation submission of a JWT VP answering [presentation_definition].
+pub fn presentation_submission() -> PresentationSubmission {
+    PresentationSubmission::for_vp_token(
+        "did-key-id-proof".into(),
+        [("did-key-id", ClaimFormatDesignation::JwtVpJson)],
+    )
+}
+
+/// A `validator_function` accepting every response.
+pub fn succeed(_: Session, _: AuthorizationResponse) -> Pin<Box<Ready<Outcome>>> {
+    Box::pin(ready(Outcome::Success {
+        info: serde_json::Value::Null,
+    }))
+}
+
 pub async fn wallet_verifier() -> (JwtVcWallet, Arc<Verifier>) {
+    wallet_verifier_with(|builder, _| builder).await
+}
+
+/// Build the test wallet and verifier, customising the verifier with `configure`, which receives
+/// the verifier's [Client] (trusted by the wallet).
+pub async fn wallet_verifier_with(
+    configure: impl FnOnce(VerifierBuilder, Arc<dyn Client + Send + Sync>) -> VerifierBuilder,
+) -> (JwtVcWallet, Arc<Verifier>) {
+    wallet_verifier_with_store(Arc::new(MemoryStore::default()), configure).await
+}
+
+/// Build the test wallet and verifier, see [wallet_verifier_with], with the verifier keeping its
+/// sessions in `session_store`.
+pub async fn wallet_verifier_with_store(
+    session_store: Arc<dyn SessionStore + Send + Sync>,
+    configure: impl FnOnce(VerifierBuilder, Arc<dyn Client + Send + Sync>) -> VerifierBuilder,
+) -> (JwtVcWallet, Arc<Verifier>) {
     let verifier_did = "did:key:zDnaeaDj3YpPR4JXos2kCCNPS86hdELeN5PZh97KGkoFzUtGn".to_owned();
     let verifier_did_vm =
         "did:key:zDnaeaDj3YpPR4JXos2kCCNPS86hdELeN5PZh97KGkoFzUtGn#zDnaeaDj3YpPR4JXos2kCCNPS86hdELeN5PZh97KGkoFzUtGn".to_owned();
@@ -49,11 +112,51 @@ pub async fn wallet_verifier() -> (JwtVcWallet, Arc<Verifier>) {
         .await
         .unwrap(),
     );
+    let metadata: WalletMetadata = serde_json::from_value(json!(
+      {
+        "authorization_endpoint": "openid4vp:",
+        "client_id_schemes_supported": [
+          "did"
+        ],
+        "request_object_signing_alg_values_supported": [
+          "ES256"
+        ],
+        "response_types_supported": [
+          "vp_token",
+          "vp_token id_token"
+        ],
+        "vp_formats_supported": {
+          "jwt_vc_json": {
+            "alg_values_supported": ["ES256"]
+          }
+        }
+      }
+    ))
+    .unwrap();
+
     let verifier = Arc::new(
-        Verifier::builder()
+        configure(Verifier::builder(), client.clone())
             .with_client(client)
             .with_submission_endpoint("http://example.com/submission".parse().unwrap())
-            .with_session_store(Arc::new(MemoryStore::default()))
+            .with_session_store(session_store)
+            .with_wallet_metadata(metadata.clone())
+            .with_presentation_definition(presentation_definition())
+            .with_default_request_parameter(ResponseMode::DirectPost)
+            .with_default_request_parameter(ResponseType::VpToken)
+            .with_default_request_parameter(ClientMetadata(UntypedObject::default()))
+            .with_response_validator(Arc::new(AcceptAll))
+            .with_scope(
+                "com.example.did_key_id",
+                PresentationDefinition::new(
+                    "did-key-id-scope".into(),
+                    InputDescriptor::new(
+                        "did-key-id".into(),
+                        Constraints::new()
+                            .add_constraint(ConstraintsField::new("$.credentialSubject.id".into())),
+                    ),
+                ),
+            )
+            .unwrap()
             .build()
             .await
             .unwrap(),
@@ -63,14 +166,27 @@ pub async fn wallet_verifier() -> (JwtVcWallet, Arc<Verifier>) {
         verifier: verifier.clone(),
     };
 
-    let metadata = serde_json::from_value(json!(
+    (
+        JwtVcWallet {
+            http_client,
+            metadata,
+            trusted_dids: vec![verifier_did],
+            resolver: CachingJwkResolver::new(resolver),
+            unsigned_request_policy: UnsignedRequestPolicy::default(),
+        },
+        verifier,
+    )
+}
+
+/// Build a test wallet accepting unsigned requests from the verifier, and a verifier with the
+/// `redirect_uri` Client Identifier.
+pub async fn unsigned_wallet_verifier() -> (JwtVcWallet, Arc<Verifier>) {
+    let submission_endpoint: url::Url = "http://example.com/submission".parse().unwrap();
+    let metadata: WalletMetadata = serde_json::from_value(json!(
       {
         "authorization_endpoint": "openid4vp:",
         "client_id_schemes_supported": [
-          "did"
-        ],
-        "request_object_signing_alg_values_supported": [
-          "ES256"
+          "redirect_uri"
         ],
         "response_types_supported": [
           "vp_token"
@@ -84,11 +200,37 @@ pub async fn wallet_verifier() -> (JwtVcWallet, Arc<Verifier>) {
     ))
     .unwrap();
 
+    let verifier = Arc::new(
+        Verifier::builder()
+            .with_client(Arc::new(RedirectUriClient::new(
+                submission_endpoint.clone(),
+            )))
+            .with_submission_endpoint(submission_endpoint)
+            .with_session_store(Arc::new(MemoryStore::default()))
+            .with_wallet_metadata(metadata.clone())
+            .with_presentation_definition(presentation_definition())
+            .with_default_request_parameter(ResponseMode::DirectPost)
+            .with_default_request_parameter(ResponseType::VpToken)
+            .with_default_request_parameter(ClientMetadata(UntypedObject::default()))
+            .with_response_validator(Arc::new(AcceptAll))
+            .build()
+            .await
+            .unwrap(),
+    );
+
+    let http_client = MockHttpClient {
+        verifier: verifier.clone(),
+    };
+
     (
         JwtVcWallet {
             http_client,
             metadata,
-            trusted_dids: vec![verifier_did],
+            trusted_dids: vec![],
+            resolver: CachingJwkResolver::new(VerificationMethodDIDResolver::new(DIDKey)),
+            unsigned_request_policy: UnsignedRequestPolicy::AllowFrom(vec![
+                "http://example.com".into()
+            ]),
         },
         verifier,
     )
@@ -98,6 +240,8 @@ pub struct JwtVcWallet {
     http_client: MockHttpClient,
     metadata: WalletMetadata,
     trusted_dids: Vec<String>,
+    resolver: CachingJwkResolver<VerificationMethodDIDResolver<DIDKey, AnyJwkMethod>>,
+    unsigned_request_policy: UnsignedRequestPolicy,
 }
 
 pub struct MockHttpClient {
@@ -120,6 +264,15 @@ impl Wallet for JwtVcWallet {
     fn metadata(&self) -> &WalletMetadata {
         &self.metadata
     }
+
+    fn unsigned_request_policy(&self) -> UnsignedRequestPolicy {
+        self.unsigned_request_policy.clone()
+    }
+
+    async fn resolve_scope(&self, scope: &Scope) -> Result<PresentationDefinition> {
+        // The scopes are shared with the verifier out of band.
+        self.http_client.verifier.scopes().resolve(scope).cloned()
+    }
 }
 
 #[async_trait]
@@ -129,49 +282,66 @@ impl RequestVerifier for JwtVcWallet {
         decoded_request: &AuthorizationRequestObject,
         request_jwt: String,
     ) -> Result<()> {
-        let resolver: VerificationMethodDIDResolver<DIDKey, AnyJwkMethod> =
-            VerificationMethodDIDResolver::new(DIDKey);
-
         did::verify_with_resolver(
             self.metadata(),
             decoded_request,
             request_jwt,
             Some(self.trusted_dids()),
-            &resolver,
+            &self.resolver,
         )
         .await
     }
 }
 
+/// Accepts every response, the presentations are not verified in these tests.
+#[derive(Debug)]
+pub struct AcceptAll;
+
+#[async_trait]
+impl ResponseValidator for AcceptAll {
+    async fn validate(&self, _: Session, _: AuthorizationResponse) -> Outcome {
+        VerificationReport::new().into_verified_outcome(VerifiedPresentationOutcome::default())
+    }
+}
+
 #[async_trait]
 impl AsyncHttpClient for MockHttpClient {
     async fn execute(&self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
-        // Only expect submission.
         let body = request.body();
         let uri = request.uri();
+
+        // Requests passed by reference.
+        if let Some(token) = uri.path().strip_prefix("/request/") {
+            let jwt = self.verifier.retrieve_authorization_request(token).await?;
+            return Response::builder()
+                .status(200)
+                .header("Content-Type", "application/oauth-authz-req+jwt")
+                .body(jwt.into_bytes())
+                .context("failed to build response");
+        }
+
         let id = uri
             .path()
             .strip_prefix("/submission/")
             .context("failed to extract id from path")?;
 
-        self.verifier
-            .verify_response(
-                id.parse().context("failed to parse id")?,
-                AuthorizationResponse::from_x_www_form_urlencoded(body)
-                    .context("failed to parse authorization response request")?,
-                |_, _| {
-                    Box::pin(async move {
-                        Outcome::Success {
-                            info: serde_json::Value::Null,
-                        }
-                    })
-                },
-            )
-            .await?;
+        // Stateless sessions are identified by a sealed token instead of a UUID.
+        let Ok(id) = id.parse() else {
+            self.verifier.receive_stateless_response(id, body).await?;
+            return Response::builder()
+                .status(200)
+                .body(vec![])
+                .context("failed to build response");
+        };
+        self.verifier.receive_response(id, body).await?;
 
+        let body = match self.verifier.post_redirection(id).await? {
+            Some(redirection) => serde_json::to_vec(&redirection)?,
+            None => vec![],
+        };
         Response::builder()
             .status(200)
-            .body(vec![])
+            .body(body)
             .context("failed to build response")
     }
 }
@@ -35,7 +35,7 @@ pub async fn create_test_verifiable_presentation() -> Result<AnyJsonPresentation
 
     // NOTE: the `id` in the VC is a UUID string, but it should be a URI
     // according to the `SpecializedJsonCredential` type.
-    json_credential.as_object_mut().map(|obj| {
+    if let Some(obj) = json_credential.as_object_mut() {
         // Update the ID to be a UriBuf.
         let id = obj
             .get("id")
@@ -46,19 +46,16 @@ pub async fn create_test_verifiable_presentation() -> Result<AnyJsonPresentation
         let id_urn = format!("urn:uuid:{id}").as_bytes().to_vec();
         let id_url = UriBuf::new(id_urn).expect("failed to parse id into UriBuf");
         obj.insert("id".to_string(), serde_json::json!(id_url));
-    });
+    }
 
-    let mut vp = JsonPresentation::default();
-    vp.context = Context::default();
+    let mut vp = JsonPresentation {
+        context: Context::default(),
+        ..Default::default()
+    };
     vp.verifiable_credentials
         .push(serde_json::from_value(json_credential)?);
     vp.holder = Some(holder_did.into());
-    vp.id = UriBuf::new(
-        format!("urn:uuid:{}", Uuid::new_v4().to_string())
-            .as_bytes()
-            .to_vec(),
-    )
-    .ok();
+    vp.id = UriBuf::new(format!("urn:uuid:{}", Uuid::new_v4()).as_bytes().to_vec()).ok();
 
     Ok(AnyJsonPresentation::V1(vp))
 }